@@ -0,0 +1,94 @@
+//! Geometry and textures shared by the examples. The engine has no primitive shapes of its
+//! own (models come from files), so the examples build theirs here.
+
+// Each example uses a different subset
+#![allow(dead_code)]
+
+use std::f32::consts::PI;
+use rustge::engine::camera::Camera;
+use rustge::engine::object3d::{Geometry, Index, Indices, Topology, Vertex};
+
+/// Window size the examples open with.
+pub const WIDTH: u32 = 1280;
+pub const HEIGHT: u32 = 720;
+
+/// A perspective camera for the example window looking from `eye` at `target`.
+pub fn camera(eye: [f32; 3], target: [f32; 3]) -> Camera {
+    let mut camera = Camera::new(WIDTH as f32 / HEIGHT as f32);
+    camera.set_fov(60.0);
+    camera.set_near_far(0.1, 500.0);
+    camera.look_at(eye, target, [0.0, 1.0, 0.0]);
+    camera
+}
+
+/// An axis-aligned cube of edge `size` centered on the origin, each face with its own
+/// normals and full 0..1 UVs.
+pub fn cube(size: f32) -> Geometry {
+    let h = size / 2.0;
+    // Normal, then the face's right and up axes as seen from outside
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices: Vec<Index> = Vec::with_capacity(36);
+    for (normal, right, up) in faces {
+        let base = vertices.len() as Index;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let (x, y) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+            let position = std::array::from_fn(|i| (normal[i] + right[i] * x + up[i] * y) * h);
+            vertices.push(Vertex { position, normal, uv: [u, v] });
+        }
+        indices.extend([base, base + 1, base + 2, base + 2, base + 3, base]);
+    }
+    Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+}
+
+/// A UV sphere of `radius` centered on the origin.
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> Geometry {
+    let mut vertices = Vec::new();
+    for ring in 0..=rings {
+        let v = ring as f32 / rings as f32;
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        for segment in 0..=segments {
+            let u = segment as f32 / segments as f32;
+            let (sin_phi, cos_phi) = (u * 2.0 * PI).sin_cos();
+            let normal = [sin_theta * cos_phi, cos_theta, -sin_theta * sin_phi];
+            vertices.push(Vertex { position: normal.map(|n| n * radius), normal, uv: [u, 1.0 - v] });
+        }
+    }
+    let mut indices: Vec<Index> = Vec::new();
+    let row = segments + 1;
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let a = ring * row + segment;
+            let b = a + row;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+    Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+}
+
+/// A flat square of edge `size` in the XZ plane, facing up.
+pub fn plane(size: f32) -> Geometry {
+    let h = size / 2.0;
+    let vertices = [(-h, h, 0.0, 0.0), (h, h, 1.0, 0.0), (h, -h, 1.0, 1.0), (-h, -h, 0.0, 1.0)]
+        .map(|(x, z, u, v)| Vertex { position: [x, 0.0, z], normal: [0.0, 1.0, 0.0], uv: [u, v] })
+        .to_vec();
+    Geometry { vertices, indices: Indices::from_u32(vec![0, 1, 2, 2, 3, 0]), topology: Topology::Triangles, skin: None }
+}
+
+/// RGBA8 pixels of a `size`×`size` checkerboard with `cells` squares per side.
+pub fn checkerboard(size: u32, cells: u32, a: [u8; 4], b: [u8; 4]) -> Vec<u8> {
+    let cell = (size / cells.max(1)).max(1);
+    (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size / cell, i / size / cell);
+            if (x + y) % 2 == 0 { a } else { b }
+        })
+        .collect()
+}
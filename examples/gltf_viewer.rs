@@ -0,0 +1,58 @@
+//! Loads a glTF (or OBJ) model given on the command line, frames it and lets the mouse
+//! orbit around it. Further models can be dropped onto the window.
+//!
+//! Run with `cargo run --example gltf_viewer -- path/to/model.gltf`.
+
+mod common;
+
+use rustge::engine::background::Background;
+use rustge::engine::camera::OrbitController;
+use rustge::engine::import::load_model;
+use rustge::engine::light::Light;
+use rustge::engine::math::aabb::Aabb;
+use rustge::engine::math::color::Color;
+use rustge::engine::math::quat::rotation_between;
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: gltf_viewer <model.gltf | model.glb | model.obj>");
+        std::process::exit(2);
+    };
+
+    let mut renderer = Renderer::new("glTF viewer", common::WIDTH, common::HEIGHT);
+
+    let model = match load_model(&path) {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("Failed to load {path}: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    // Frame the whole model, then orbit around its center
+    let bounds = Object3D::subtree_bounds(&model).unwrap_or(Aabb::new([-1.0; 3], [1.0; 3]));
+    let mut camera = common::camera([1.0, 0.6, 1.5], [0.0, 0.0, 0.0]);
+    camera.frame_bounds(&bounds, 0.1);
+    renderer.set_camera_controller(Some(OrbitController::from_camera(&camera, bounds.center())));
+    renderer.set_camera(camera);
+
+    let sun = Object3D::new();
+    {
+        let mut sun = sun.borrow_mut();
+        sun.set_light(Some(Light::directional(Color::WHITE, 2.5)));
+        sun.set_rotation(rotation_between([0.0, 0.0, -1.0], [-0.4, -1.0, -0.6]));
+    }
+    let ambient = Object3D::new();
+    ambient.borrow_mut().set_light(Some(Light::ambient(Color::WHITE, 0.25)));
+
+    if let Some(scene) = renderer.get_scene_mut() {
+        scene.set_background(Some(Background::Gradient { top: Color::hex(0x8aa8c8), bottom: Color::hex(0x303030) }));
+        scene.add(sun);
+        scene.add(ambient);
+        scene.add(model);
+    }
+
+    renderer.run();
+}
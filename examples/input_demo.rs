@@ -0,0 +1,108 @@
+//! Moves a cube with named input actions and axes instead of raw keys.
+//!
+//! - Arrow keys or `WASD` move, `Space` jumps.
+//! - `F1` rebinds jump to the next key or mouse button pressed.
+//! - `F5` saves the bindings to `input_demo.json` in the temp directory, `F9` loads them.
+//!
+//! Run with `cargo run --example input_demo`.
+
+mod common;
+
+use rustge::engine::event::Key;
+use rustge::engine::input::{AxisBinding, GamepadAxis, Input, InputMap};
+use rustge::engine::light::Light;
+use rustge::engine::material::Material;
+use rustge::engine::math::color::Color;
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+
+fn main() {
+    let mut renderer = Renderer::new("Input demo", common::WIDTH, common::HEIGHT);
+    renderer.set_clear_color(Color::hex(0x1c2028));
+    renderer.set_camera(common::camera([0.0, 6.0, 9.0], [0.0, 0.0, 0.0]));
+
+    let player = Object3D::new();
+    {
+        let mut player = player.borrow_mut();
+        player.set_geometry(common::cube(1.0));
+        player.set_material(Material::phong(Color::hex(0x50a0ff)));
+        player.set_position([0.0, 0.5, 0.0]);
+    }
+    let floor = Object3D::new();
+    {
+        let mut floor = floor.borrow_mut();
+        floor.set_geometry(common::plane(12.0));
+        floor.set_material(Material::phong(Color::hex(0x707070)));
+    }
+    let light = Object3D::new();
+    light.borrow_mut().set_light(Some(Light::point(Color::WHITE, 3.0, 25.0)));
+    light.borrow_mut().set_position([3.0, 8.0, 4.0]);
+    let ambient = Object3D::new();
+    ambient.borrow_mut().set_light(Some(Light::ambient(Color::WHITE, 0.25)));
+    if let Some(scene) = renderer.get_scene() {
+        scene.add(player.clone());
+        scene.add(floor);
+        scene.add(light);
+        scene.add(ambient);
+    }
+
+    let input = renderer.input_mut();
+    let mut move_x = AxisBinding::keys(Key::D, Key::A).with_gamepad(GamepadAxis::LeftStickX);
+    move_x.positive.push(Input::Key(Key::Right));
+    move_x.negative.push(Input::Key(Key::Left));
+    let mut move_z = AxisBinding::keys(Key::S, Key::W);
+    move_z.positive.push(Input::Key(Key::Down));
+    move_z.negative.push(Input::Key(Key::Up));
+    input.bind_axis("move x", move_x);
+    input.bind_axis("move z", move_z);
+    input.bind_action("jump", Input::Key(Key::Space));
+    input.bind_action("rebind jump", Input::Key(Key::F1));
+    input.bind_action("save bindings", Input::Key(Key::F5));
+    input.bind_action("load bindings", Input::Key(Key::F9));
+
+    let bindings_path = std::env::temp_dir().join("input_demo.json");
+    let mut vertical_speed = 0.0f32;
+    let mut awaiting_rebind = false;
+    renderer.on_update(move |renderer, clock| {
+        let delta = clock.delta();
+        let input = renderer.input();
+        let (dx, dz) = (input.axis("move x"), input.axis("move z"));
+        if awaiting_rebind && input.rebinding().is_none() {
+            println!("Jump is now bound to {:?}", input.action_bindings("jump"));
+            awaiting_rebind = false;
+        }
+
+        let mut player = player.borrow_mut();
+        let [mut x, mut y, mut z] = player.position();
+        x = (x + dx * 5.0 * delta).clamp(-5.5, 5.5);
+        z = (z + dz * 5.0 * delta).clamp(-5.5, 5.5);
+        if input.is_action_pressed("jump") && y <= 0.5 {
+            vertical_speed = 6.0;
+        }
+        vertical_speed -= 15.0 * delta;
+        y += vertical_speed * delta;
+        if y <= 0.5 {
+            (y, vertical_speed) = (0.5, 0.0);
+        }
+        player.set_position([x, y, z]);
+        drop(player);
+
+        if input.is_action_pressed("rebind jump") {
+            println!("Press a key or mouse button to jump with");
+            renderer.input_mut().rebind_next("jump");
+            awaiting_rebind = true;
+        } else if input.is_action_pressed("save bindings") {
+            match renderer.input().save(&bindings_path) {
+                Ok(()) => println!("Saved bindings to {}", bindings_path.display()),
+                Err(err) => eprintln!("Failed to save bindings: {err}"),
+            }
+        } else if input.is_action_pressed("load bindings") {
+            match InputMap::load(&bindings_path) {
+                Ok(map) => renderer.input_mut().set_bindings(&map),
+                Err(err) => eprintln!("Failed to load bindings: {err}"),
+            }
+        }
+    });
+
+    renderer.run();
+}
@@ -0,0 +1,124 @@
+//! A row of spheres of increasing shininess on a floor, lit by colored point lights circling
+//! above them, a spot light and the procedural sky's ambient light.
+//!
+//! - `1`–`3` switch the point lights on and off, `4` the spot light.
+//! - `+` / `-` change the point lights' intensity.
+//! - The mouse orbits the camera.
+//!
+//! Run with `cargo run --example lighting_playground`.
+
+mod common;
+
+use rustge::engine::background::{Background, ProceduralSky};
+use rustge::engine::camera::OrbitController;
+use rustge::engine::event::{ButtonState, EngineEvent, Key};
+use rustge::engine::light::Light;
+use rustge::engine::material::Material;
+use rustge::engine::math::color::Color;
+use rustge::engine::math::quat::rotation_between;
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+use rustge::engine::shader::UniformValue;
+
+fn main() {
+    let mut renderer = Renderer::new("Lighting playground", common::WIDTH, common::HEIGHT);
+    let camera = common::camera([0.0, 4.0, 9.0], [0.0, 0.5, 0.0]);
+    renderer.set_camera_controller(Some(OrbitController::from_camera(&camera, [0.0, 0.5, 0.0])));
+    renderer.set_camera(camera);
+
+    let Some(scene) = renderer.get_scene_mut() else {
+        return;
+    };
+    scene.set_background(Some(Background::Sky(ProceduralSky::default())));
+    scene.set_environment_lighting(Some(0.3));
+
+    let floor = Object3D::new();
+    {
+        let mut floor = floor.borrow_mut();
+        floor.set_geometry(common::plane(20.0));
+        floor.set_material(Material::phong(Color::srgb(0.45, 0.45, 0.45)));
+    }
+    scene.add(floor);
+
+    // Shininess doubles from left to right
+    let sphere = std::rc::Rc::new(common::sphere(0.6, 48, 24));
+    for i in 0..6 {
+        let mut material = Material::phong(Color::from_hsv(i as f32 * 60.0, 0.5, 0.9));
+        material.set_uniform("u_specular", UniformValue::Vec3([0.6, 0.6, 0.6]));
+        material.set_uniform("u_shininess", UniformValue::Float(4.0 * 2f32.powi(i)));
+        let node = Object3D::new();
+        {
+            let mut node = node.borrow_mut();
+            node.set_shared_geometry(sphere.clone());
+            node.set_material(material);
+            node.set_position([(i as f32 - 2.5) * 1.5, 0.6, 0.0]);
+        }
+        scene.add(node);
+    }
+
+    let mut lights = Vec::new();
+    for (i, color) in [Color::hex(0xff4040), Color::hex(0x40ff60), Color::hex(0x4080ff)].into_iter().enumerate() {
+        let light = Object3D::new();
+        {
+            let mut light = light.borrow_mut();
+            light.set_light(Some(Light::point(color, 3.0, 8.0)));
+            let phase = i as f32 * std::f32::consts::TAU / 3.0;
+            light.on_update(move |node, clock| {
+                let angle = phase + clock.elapsed() as f32 * 0.7;
+                node.set_position([angle.cos() * 4.0, 2.0, angle.sin() * 2.5]);
+            });
+        }
+        scene.add(light.clone());
+        lights.push(light);
+    }
+
+    let spot = Object3D::new();
+    {
+        let mut spot = spot.borrow_mut();
+        spot.set_light(Some(Light::spot(Color::hex(0xfff0d0), 6.0, 15.0, 12.0, 20.0)));
+        spot.set_position([0.0, 6.0, 4.0]);
+        spot.set_rotation(rotation_between([0.0, 0.0, -1.0], [0.0, -6.0, -4.0]));
+    }
+    scene.add(spot.clone());
+    lights.push(spot);
+
+    // Lights switched off are kept here until switched back on
+    let mut switched_off: Vec<Option<Light>> = vec![None; lights.len()];
+    renderer.on_event(move |_, event| {
+        let EngineEvent::Key { key, state: ButtonState::Pressed, .. } = event else {
+            return;
+        };
+        let toggled = match key {
+            Key::Digit1 => Some(0),
+            Key::Digit2 => Some(1),
+            Key::Digit3 => Some(2),
+            Key::Digit4 => Some(3),
+            _ => None,
+        };
+        if let Some(index) = toggled {
+            let mut node = lights[index].borrow_mut();
+            let light = node.light().cloned();
+            node.set_light(switched_off[index].take());
+            switched_off[index] = light;
+            return;
+        }
+
+        let factor = match key {
+            Key::Equals | Key::NumpadAdd => 1.25,
+            Key::Minus | Key::NumpadSubtract => 0.8,
+            _ => return,
+        };
+        let point_lights = lights[..3].iter().map(|node| node.borrow_mut());
+        for mut node in point_lights {
+            if let Some(light) = node.light_mut() {
+                light.intensity *= factor;
+            }
+        }
+        for light in switched_off[..3].iter_mut().flatten() {
+            light.intensity *= factor;
+        }
+    });
+
+    renderer.run();
+}
+
@@ -0,0 +1,122 @@
+//! A fountain of a few thousand particles: each is an ECS entity moved by systems, and all
+//! are drawn by one instanced node in a single draw call.
+//!
+//! Run with `cargo run --example particles`.
+
+mod common;
+
+use rustge::engine::camera::OrbitController;
+use rustge::engine::ecs::{Entity, Transform};
+use rustge::engine::light::Light;
+use rustge::engine::material::Material;
+use rustge::engine::math::color::Color;
+use rustge::engine::math::random::Rng;
+use rustge::engine::mesh::instanced::InstancedMesh;
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+
+/// Particles spawned per second.
+const RATE: f32 = 1500.0;
+const GRAVITY: f32 = -9.81;
+
+/// Motion and remaining life of a particle entity.
+struct Particle {
+    velocity: [f32; 3],
+    life: f32,
+}
+
+fn main() {
+    let mut renderer = Renderer::new("Particles", common::WIDTH, common::HEIGHT);
+    let camera = common::camera([0.0, 4.0, 12.0], [0.0, 3.0, 0.0]);
+    renderer.set_camera_controller(Some(OrbitController::from_camera(&camera, [0.0, 3.0, 0.0])));
+    renderer.set_camera(camera);
+
+    let sparks = Object3D::new();
+    {
+        let mut sparks = sparks.borrow_mut();
+        sparks.set_geometry(common::cube(0.06));
+        sparks.set_material(Material::phong(Color::hex(0xffc060)));
+        sparks.set_instances(Some(InstancedMesh::new()));
+        // Instances move every frame; the node's bounds would always lag behind
+        sparks.set_frustum_culled(false);
+    }
+    let floor = Object3D::new();
+    {
+        let mut floor = floor.borrow_mut();
+        floor.set_geometry(common::plane(30.0));
+        floor.set_material(Material::phong(Color::hex(0x303540)));
+    }
+    let light = Object3D::new();
+    light.borrow_mut().set_light(Some(Light::point(Color::hex(0xffd8a0), 4.0, 20.0)));
+    light.borrow_mut().set_position([0.0, 6.0, 2.0]);
+    let ambient = Object3D::new();
+    ambient.borrow_mut().set_light(Some(Light::ambient(Color::WHITE, 0.3)));
+    if let Some(scene) = renderer.get_scene() {
+        scene.add(sparks.clone());
+        scene.add(floor);
+        scene.add(light);
+        scene.add(ambient);
+    }
+
+    renderer.world_mut().register::<Particle>();
+    renderer.world_mut().register::<Transform>();
+
+    // Systems run in the order added: emit, simulate, expire, then draw
+    let mut rng = Rng::new(7);
+    let mut owed = 0.0;
+    renderer.add_system("emit", move |world, clock| {
+        owed += RATE * clock.delta();
+        while owed >= 1.0 {
+            owed -= 1.0;
+            let entity = world.spawn();
+            let angle = rng.range(0.0, std::f32::consts::TAU);
+            let spread = rng.range(0.0, 1.5);
+            let velocity = [angle.cos() * spread, rng.range(7.0, 10.0), angle.sin() * spread];
+            world.insert(entity, Particle { velocity, life: rng.range(1.5, 2.5) });
+            world.insert(entity, Transform::default());
+        }
+    });
+
+    renderer.add_system("simulate", |world, clock| {
+        let delta = clock.delta();
+        let mut particles = world.write::<Particle>();
+        let mut transforms = world.write::<Transform>();
+        for (entity, particle) in particles.iter_mut() {
+            particle.velocity[1] += GRAVITY * delta;
+            particle.life -= delta;
+            if let Some(transform) = transforms.get_mut(entity) {
+                for axis in 0..3 {
+                    transform.position[axis] += particle.velocity[axis] * delta;
+                }
+                // Bounce off the floor, losing most of the energy
+                if transform.position[1] < 0.0 {
+                    transform.position[1] = 0.0;
+                    particle.velocity[1] *= -0.3;
+                }
+            }
+        }
+    });
+
+    renderer.add_system("expire", |world, _| {
+        let expired: Vec<Entity> = world.read::<Particle>().iter().filter(|(_, p)| p.life <= 0.0).map(|(e, _)| e).collect();
+        for entity in expired {
+            world.despawn(entity);
+        }
+    });
+
+    renderer.add_system("draw", move |world, _| {
+        let mut sparks = sparks.borrow_mut();
+        let Some(instances) = sparks.instances_mut() else {
+            return;
+        };
+        instances.clear();
+        let particles = world.read::<Particle>();
+        for (entity, transform) in world.read::<Transform>().iter() {
+            // Shrink as the particle dies
+            let size = particles.get(entity).map_or(0.0, |particle| particle.life.clamp(0.0, 1.0));
+            instances.add_instance_trs(transform.position, transform.rotation, [size; 3]);
+        }
+    });
+
+    renderer.run();
+}
@@ -0,0 +1,82 @@
+//! A textured cube spinning in front of the camera: window, custom shader, texture upload,
+//! materials and per-node update callbacks.
+//!
+//! Run with `cargo run --example spinning_cube`.
+
+mod common;
+
+use std::rc::Rc;
+use rustge::engine::material::Material;
+use rustge::engine::math::color::Color;
+use rustge::engine::math::quat::{from_axis_angle, mul};
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+use rustge::engine::shader::GLShaderProgram;
+use rustge::engine::texture::Texture;
+
+const VERTEX_SHADER: &str = r#"
+#version 330 core
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in vec3 a_normal;
+layout(location = 2) in vec2 a_uv;
+
+uniform mat4 u_model;
+uniform mat3 u_normal_matrix;
+uniform mat4 u_proj_view;
+
+out vec3 v_normal;
+out vec2 v_uv;
+
+void main() {
+    v_normal = u_normal_matrix * a_normal;
+    v_uv = a_uv;
+    gl_Position = u_proj_view * u_model * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+#version 330 core
+in vec3 v_normal;
+in vec2 v_uv;
+
+uniform sampler2D u_texture;
+uniform vec4 u_color;
+
+out vec4 frag_color;
+
+void main() {
+    // Fixed light from the upper left, enough to tell the faces apart
+    float light = 0.35 + 0.65 * max(dot(normalize(v_normal), normalize(vec3(-0.4, 0.8, 0.6))), 0.0);
+    vec4 texel = texture(u_texture, v_uv);
+    frag_color = vec4(texel.rgb * u_color.rgb * light, texel.a * u_color.a);
+}
+"#;
+
+fn main() {
+    let mut renderer = Renderer::new("Spinning cube", common::WIDTH, common::HEIGHT);
+    renderer.set_clear_color(Color::hex(0x20242c));
+    renderer.set_camera(common::camera([0.0, 1.5, 4.0], [0.0, 0.0, 0.0]));
+
+    // GL objects need the context the renderer just created
+    let pixels = common::checkerboard(256, 8, [235, 130, 40, 255], [250, 245, 235, 255]);
+    let texture = Rc::new(Texture::from_rgba8(256, 256, &pixels, "checkerboard"));
+    let mut material = Material::new(Rc::new(GLShaderProgram::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)));
+    material.set_texture("u_texture", texture);
+
+    let cube = Object3D::new();
+    {
+        let mut cube = cube.borrow_mut();
+        cube.set_geometry(common::cube(1.5));
+        cube.set_material(material);
+        cube.on_update(|node, clock| {
+            let time = clock.elapsed() as f32;
+            let spin = mul(from_axis_angle([0.0, 1.0, 0.0], time), from_axis_angle([1.0, 0.0, 0.0], time * 0.4));
+            node.set_rotation(spin);
+        });
+    }
+    if let Some(scene) = renderer.get_scene() {
+        scene.add(cube);
+    }
+
+    renderer.run();
+}
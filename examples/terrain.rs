@@ -0,0 +1,80 @@
+//! Flies over endless procedural hills drawn as a clipmap, streamed around the camera as it
+//! moves. `W`/`S` speed up and slow down, `A`/`D` turn.
+//!
+//! Run with `cargo run --example terrain`.
+
+mod common;
+
+use rustge::engine::background::{Background, ProceduralSky};
+use rustge::engine::event::Key;
+use rustge::engine::input::AxisBinding;
+use rustge::engine::light::Light;
+use rustge::engine::material::Material;
+use rustge::engine::math::color::Color;
+use rustge::engine::math::quat::rotation_between;
+use rustge::engine::object3d::Object3D;
+use rustge::engine::renderer::Renderer;
+use rustge::engine::terrain::clipmap::{ClipmapGround, ClipmapOptions};
+
+/// Rolling hills from a few octaves of sines; any `Fn(x, z) -> height` works.
+fn hills(x: f32, z: f32) -> f32 {
+    let mut height = 0.0;
+    let mut amplitude = 12.0;
+    let mut frequency = 0.01;
+    for octave in 0..4 {
+        let shift = octave as f32 * 1.7;
+        height += amplitude * (x * frequency + shift).sin() * (z * frequency * 1.3 - shift).cos();
+        amplitude *= 0.45;
+        frequency *= 2.1;
+    }
+    height
+}
+
+fn main() {
+    let mut renderer = Renderer::new("Terrain", common::WIDTH, common::HEIGHT);
+    let mut camera = common::camera([0.0, 30.0, 0.0], [0.0, 25.0, -10.0]);
+    camera.set_near_far(0.5, 2000.0);
+    renderer.set_camera(camera);
+
+    let mut ground = ClipmapGround::new(ClipmapOptions::default(), hills, Material::phong(Color::srgb(0.35, 0.55, 0.25)));
+    let sun = Object3D::new();
+    {
+        let mut sun = sun.borrow_mut();
+        sun.set_light(Some(Light::directional(Color::WHITE, 2.0)));
+        sun.set_rotation(rotation_between([0.0, 0.0, -1.0], [0.3, -0.6, -0.7]));
+    }
+    if let Some(scene) = renderer.get_scene_mut() {
+        scene.set_background(Some(Background::Sky(ProceduralSky::default())));
+        scene.set_environment_lighting(Some(0.4));
+        scene.add(ground.node());
+        scene.add(sun);
+    }
+
+    let input = renderer.input_mut();
+    input.bind_axis("throttle", AxisBinding::keys(Key::W, Key::S));
+    input.bind_axis("turn", AxisBinding::keys(Key::D, Key::A));
+
+    let (mut speed, mut heading) = (20.0f32, 0.0f32);
+    let mut position = [0.0f32, 0.0, 0.0];
+    renderer.on_update(move |renderer, clock| {
+        let delta = clock.delta();
+        speed = (speed + renderer.input().axis("throttle") * 40.0 * delta).clamp(0.0, 150.0);
+        heading += renderer.input().axis("turn") * 1.2 * delta;
+        let forward = [heading.sin(), 0.0, -heading.cos()];
+        position[0] += forward[0] * speed * delta;
+        position[2] += forward[2] * speed * delta;
+
+        // Keep a steady height above the hills ahead
+        let ahead = ground.height_at(position[0] + forward[0] * 20.0, position[2] + forward[2] * 20.0);
+        let below = ground.height_at(position[0], position[2]);
+        position[1] = below.max(ahead) + 15.0;
+
+        if let Some(camera) = renderer.get_camera_mut() {
+            let target = [position[0] + forward[0] * 30.0, position[1] - 6.0, position[2] + forward[2] * 30.0];
+            camera.look_at(position, target, [0.0, 1.0, 0.0]);
+        }
+        ground.update(position);
+    });
+
+    renderer.run();
+}
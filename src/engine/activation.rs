@@ -0,0 +1,126 @@
+//! Distance-bucketed activation: distant update callbacks tick less often or sleep.
+//!
+//! Frustum culling keeps far-away objects from costing draw time, but their update
+//! callbacks (AI, scripts, ambient emitters) still run every frame. With
+//! [`ActivationSettings`] set on the [`Scene`](crate::engine::scene::Scene), nodes are
+//! sorted by distance from the camera (or a focus point such as the player) into bands:
+//! nodes within the first band's radius update every frame, those in later bands only
+//! every few frames, and nodes beyond the last band sleep. A node ticking at a reduced
+//! rate receives the time since its last tick as its clock's delta, so movement stays
+//! framerate independent; a node waking up resumes where it left off rather than catching
+//! up on the time it slept.
+//!
+//! Switching bands takes a margin of hysteresis, so nodes sitting right on a band's edge
+//! don't flip between rates every frame. Nodes whose
+//! [`UpdatePolicy::always_simulate`](crate::engine::object3d::UpdatePolicy::always_simulate)
+//! is set are exempt and always update every frame.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{activation::{ActivationBand, ActivationSettings}, scene::Scene};
+//! # let mut scene = Scene::new();
+//! scene.set_activation(Some(ActivationSettings {
+//!     bands: vec![ActivationBand { radius: 30.0, interval: 1 }, ActivationBand { radius: 120.0, interval: 8 }],
+//!     hysteresis: 5.0,
+//!     focus: None,
+//! }));
+//! ```
+
+/// A distance band of [`ActivationSettings`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActivationBand {
+    /// Outer radius of the band, measured from the focus.
+    pub radius: f32,
+
+    /// Nodes in the band update every `interval` frames (1 for every frame).
+    pub interval: u32,
+}
+
+/// How often nodes update by their distance from the camera or a focus point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActivationSettings {
+    /// Bands in order of increasing radius. Nodes beyond the last one sleep.
+    pub bands: Vec<ActivationBand>,
+
+    /// Distance a node must move past a band's edge before it changes band.
+    pub hysteresis: f32,
+
+    /// Point distances are measured from, such as the player's position; `None` for the
+    /// camera.
+    pub focus: Option<[f32; 3]>,
+}
+
+/// A node's activation state, from [`Object3D::activation`](crate::engine::object3d::Object3D::activation).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Activation {
+    /// Updating at the rate of the band with this index (always 0 without activation
+    /// settings).
+    Active(usize),
+    /// Beyond the last band: not updating.
+    Asleep,
+}
+
+impl Default for Activation {
+    fn default() -> Self {
+        Activation::Active(0)
+    }
+}
+
+impl ActivationSettings {
+    /// The activation of a node at `distance` from the focus that currently has
+    /// `current`, applying hysteresis at every band edge.
+    ///
+    /// ```
+    /// # use rustge::engine::activation::{Activation, ActivationBand, ActivationSettings};
+    /// let settings = ActivationSettings {
+    ///     bands: vec![ActivationBand { radius: 10.0, interval: 1 }, ActivationBand { radius: 50.0, interval: 4 }],
+    ///     hysteresis: 2.0,
+    ///     focus: None,
+    /// };
+    /// assert_eq!(settings.activation(11.0, Activation::Active(0)), Activation::Active(0));
+    /// assert_eq!(settings.activation(13.0, Activation::Active(0)), Activation::Active(1));
+    /// assert_eq!(settings.activation(9.0, Activation::Active(1)), Activation::Active(1));
+    /// assert_eq!(settings.activation(60.0, Activation::Active(1)), Activation::Asleep);
+    /// ```
+    pub fn activation(&self, distance: f32, current: Activation) -> Activation {
+        let current = match current {
+            Activation::Active(band) => band,
+            Activation::Asleep => self.bands.len(),
+        };
+        // Crossing edge i outwards takes `radius + hysteresis`, back inwards `radius - hysteresis`
+        let band = self
+            .bands
+            .iter()
+            .enumerate()
+            .filter(|&(edge, band)| {
+                let margin = if current <= edge { self.hysteresis } else { -self.hysteresis };
+                distance > band.radius + margin
+            })
+            .count();
+        if band < self.bands.len() { Activation::Active(band) } else { Activation::Asleep }
+    }
+
+    /// Update interval in frames for `activation`, or `None` when asleep.
+    pub fn interval(&self, activation: Activation) -> Option<u32> {
+        match activation {
+            Activation::Active(band) => Some(self.bands.get(band).map_or(1, |band| band.interval.max(1))),
+            Activation::Asleep => None,
+        }
+    }
+}
+
+impl Default for ActivationSettings {
+    /// Every frame within 50 units, every 4th frame within 100, every 16th within 200,
+    /// asleep beyond, with 5 units of hysteresis, measured from the camera.
+    fn default() -> Self {
+        Self {
+            bands: vec![
+                ActivationBand { radius: 50.0, interval: 1 },
+                ActivationBand { radius: 100.0, interval: 4 },
+                ActivationBand { radius: 200.0, interval: 16 },
+            ],
+            hysteresis: 5.0,
+            focus: None,
+        }
+    }
+}
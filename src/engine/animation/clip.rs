@@ -0,0 +1,277 @@
+//! Keyframe animation clips and their playback.
+//!
+//! An [`AnimationClip`] is a set of [`Channel`]s, each animating the translation, rotation or
+//! scale of one node with keyframes, as imported from glTF animations. Clips are shared
+//! (`Rc`) and hold their target nodes weakly; applying a clip at a time poses the nodes
+//! still alive.
+//!
+//! Playback is a component: an [`AnimationPlayer`] on any node of a scene is advanced by
+//! [`Scene::update`](crate::engine::scene::Scene::update) every frame, before the update
+//! callbacks run (so they see the animated pose and can adjust it) and before the joint
+//! matrices of [skins](crate::engine::animation::skeleton) are computed. Imported models
+//! carry their clips in an [`AnimationLibrary`] component on the root node.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::animation::clip::{AnimationLibrary, AnimationPlayer};
+//! # use rustge::engine::import::gltf::load_gltf;
+//! let model = load_gltf("character.glb").expect("failed to load model");
+//! let walk = model.borrow().component::<AnimationLibrary>().and_then(|library| library.get("walk"));
+//! if let Some(walk) = walk {
+//!     let mut player = AnimationPlayer::new(walk);
+//!     player.speed = 1.5;
+//!     model.borrow_mut().insert_component(player);
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use serde::{Deserialize, Serialize};
+use crate::engine::math::quat;
+use crate::engine::object3d::Object3D;
+
+/// The part of a node's transform a [`Channel`] animates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AnimatedProperty {
+    /// The position; keyframe values are `[x, y, z, _]`.
+    Translation,
+
+    /// The rotation; keyframe values are `[x, y, z, w]` quaternions.
+    Rotation,
+
+    /// The scale; keyframe values are `[x, y, z, _]`.
+    Scale,
+}
+
+/// How values between keyframes are computed. Serialized in snake case, e.g. in
+/// [timelines](crate::engine::timeline).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// The previous keyframe's value, until the next keyframe.
+    Step,
+
+    /// Straight-line blending (spherical for rotations).
+    #[default]
+    Linear,
+
+    /// Hermite spline through the keyframes. Each keyframe has three values: the incoming
+    /// tangent, the value and the outgoing tangent.
+    CubicSpline,
+}
+
+/// Keyframes animating one property of one node.
+#[derive(Clone, Debug)]
+pub struct Channel {
+    /// The animated node.
+    pub target: Weak<RefCell<Object3D>>,
+
+    /// What the channel animates.
+    pub property: AnimatedProperty,
+
+    /// How values between keyframes are computed.
+    pub interpolation: Interpolation,
+
+    /// Keyframe times in seconds, ascending.
+    pub times: Vec<f32>,
+
+    /// Keyframe values, one per time (three for [`Interpolation::CubicSpline`]).
+    pub values: Vec<[f32; 4]>,
+}
+
+impl Channel {
+    /// The property's value at `time`, holding the first and last keyframes outside the
+    /// keyframed range. `None` if the channel has no keyframes.
+    pub fn sample(&self, time: f32) -> Option<[f32; 4]> {
+        let value = |key: usize| match self.interpolation {
+            Interpolation::CubicSpline => self.values.get(key * 3 + 1).copied(),
+            _ => self.values.get(key).copied(),
+        };
+
+        // Index of the first keyframe after `time`
+        let next = self.times.partition_point(|&t| t <= time);
+        if next == 0 {
+            return value(0);
+        }
+        if next == self.times.len() {
+            return value(next - 1);
+        }
+        let key = next - 1;
+        let span = self.times[next] - self.times[key];
+        let t = if span > 0.0 { (time - self.times[key]) / span } else { 0.0 };
+
+        let (a, b) = (value(key)?, value(next)?);
+        let sampled = match self.interpolation {
+            Interpolation::Step => a,
+            Interpolation::Linear if self.property == AnimatedProperty::Rotation => quat::slerp(a, b, t),
+            Interpolation::Linear => std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t),
+            Interpolation::CubicSpline => {
+                // Tangents are per second, scaled to the keyframe span
+                let out_tangent = *self.values.get(key * 3 + 2)?;
+                let in_tangent = *self.values.get(next * 3)?;
+                let (t2, t3) = (t * t, t * t * t);
+                let value: [f32; 4] = std::array::from_fn(|i| {
+                    (2.0 * t3 - 3.0 * t2 + 1.0) * a[i]
+                        + (t3 - 2.0 * t2 + t) * span * out_tangent[i]
+                        + (-2.0 * t3 + 3.0 * t2) * b[i]
+                        + (t3 - t2) * span * in_tangent[i]
+                });
+                if self.property == AnimatedProperty::Rotation { quat::normalize(value) } else { value }
+            }
+        };
+        Some(sampled)
+    }
+
+    /// Sets the target's property to its value at `time`, if the target is still alive.
+    ///
+    /// # Panics
+    /// Panics if the target is already borrowed.
+    pub fn apply(&self, time: f32) {
+        let (Some(target), Some(value)) = (self.target.upgrade(), self.sample(time)) else {
+            return;
+        };
+        let mut target = target.borrow_mut();
+        match self.property {
+            AnimatedProperty::Translation => target.set_position([value[0], value[1], value[2]]),
+            AnimatedProperty::Rotation => target.set_rotation(value),
+            AnimatedProperty::Scale => target.set_scale([value[0], value[1], value[2]]),
+        }
+    }
+}
+
+/// A named animation: channels played together, e.g. a character's walk cycle.
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    name: String,
+    duration: f32,
+    channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    /// A clip of `channels`, lasting until the last keyframe of any of them.
+    pub fn new(name: &str, channels: Vec<Channel>) -> Self {
+        let duration = channels.iter().filter_map(|channel| channel.times.last().copied()).fold(0.0, f32::max);
+        Self { name: name.to_string(), duration, channels }
+    }
+
+    /// The clip's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Length of the clip in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// The clip's channels.
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Poses every target node as it is at `time` seconds into the clip.
+    ///
+    /// # Panics
+    /// Panics if a target node is already borrowed.
+    pub fn apply(&self, time: f32) {
+        for channel in &self.channels {
+            channel.apply(time);
+        }
+    }
+}
+
+/// Component playing an [`AnimationClip`], advanced by
+/// [`Scene::update`](crate::engine::scene::Scene::update).
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    clip: Rc<AnimationClip>,
+    time: f32,
+
+    /// Playback rate; 1 is normal speed, negative plays backwards.
+    pub speed: f32,
+
+    /// Whether playback wraps around at the end of the clip, or stops there.
+    pub looping: bool,
+
+    /// Whether the player advances; the pose stays applied while paused.
+    pub playing: bool,
+}
+
+impl AnimationPlayer {
+    /// A player starting `clip` from the beginning, looping at normal speed.
+    pub fn new(clip: Rc<AnimationClip>) -> Self {
+        Self { clip, time: 0.0, speed: 1.0, looping: true, playing: true }
+    }
+
+    /// The clip being played.
+    pub fn clip(&self) -> &Rc<AnimationClip> {
+        &self.clip
+    }
+
+    /// Seconds into the clip.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Jumps to `time` seconds into the clip.
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.clip.duration);
+    }
+
+    /// Whether a non-looping player has reached the end of the clip (or the start, when
+    /// playing backwards).
+    pub fn is_finished(&self) -> bool {
+        !self.looping
+            && ((self.speed > 0.0 && self.time >= self.clip.duration) || (self.speed < 0.0 && self.time <= 0.0))
+    }
+
+    /// Moves playback `delta` seconds forward, scaled by the speed.
+    pub fn advance(&mut self, delta: f32) {
+        if !self.playing {
+            return;
+        }
+        let time = self.time + delta * self.speed;
+        let duration = self.clip.duration;
+        self.time = if self.looping && duration > 0.0 { time.rem_euclid(duration) } else { time.clamp(0.0, duration) };
+    }
+}
+
+/// Component holding the clips that came with a model, e.g. from a glTF file.
+#[derive(Clone, Debug, Default)]
+pub struct AnimationLibrary {
+    clips: Vec<Rc<AnimationClip>>,
+}
+
+impl AnimationLibrary {
+    /// A library of `clips`.
+    pub fn new(clips: Vec<Rc<AnimationClip>>) -> Self {
+        Self { clips }
+    }
+
+    /// The clip called `name`, if any.
+    pub fn get(&self, name: &str) -> Option<Rc<AnimationClip>> {
+        self.clips.iter().find(|clip| clip.name == name).cloned()
+    }
+
+    /// Every clip, in file order.
+    pub fn clips(&self) -> &[Rc<AnimationClip>] {
+        &self.clips
+    }
+}
+
+/// Advances the [`AnimationPlayer`] on each of `nodes` by `delta` seconds and poses the
+/// clip's targets. Players are advanced before any target is borrowed, so a player may
+/// animate its own node.
+pub(crate) fn advance_players(nodes: impl Iterator<Item = Rc<RefCell<Object3D>>>, delta: f32) {
+    let poses: Vec<(Rc<AnimationClip>, f32)> = nodes
+        .filter_map(|node| {
+            let mut node = node.borrow_mut();
+            let player = node.component_mut::<AnimationPlayer>()?;
+            player.advance(delta);
+            Some((player.clip.clone(), player.time))
+        })
+        .collect();
+    for (clip, time) in poses {
+        clip.apply(time);
+    }
+}
@@ -0,0 +1,155 @@
+//! Skinning in a compute pass, shared by every pass drawing the mesh.
+//!
+//! Vertex shader skinning (see [`skeleton`](crate::engine::animation::skeleton)) blends
+//! the joints into every vertex again in each pass that draws the mesh: the main pass, a
+//! depth prepass, every shadow map the character is in. With
+//! [`Renderer::set_compute_skinning`](crate::engine::renderer::Renderer::set_compute_skinning)
+//! on, a compute shader instead skins each node with a [`Skin`] once per frame, after the
+//! scene update, writing the deformed vertices into a vertex buffer of the node's own. Every
+//! pass then draws that buffer as rigid geometry, with `u_skinned` set to 0, so custom
+//! shaders work unchanged. Picking, outlines and motion vectors see the posed mesh too,
+//! rather than its bind pose.
+//!
+//! Compute shaders need OpenGL 4.3 (see [`compute_skinning_supported`]). The deformed
+//! buffer is as large as the geometry's vertex buffer and belongs to one node, so meshes
+//! sharing geometry no longer share that memory.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::renderer::Renderer;
+//! let mut renderer = Renderer::builder().gl_version(4, 3).build();
+//! renderer.set_compute_skinning(true);
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use gl::types::{GLint, GLsizei, GLsizeiptr, GLuint};
+use crate::engine::animation::skeleton::Skin;
+use crate::engine::mesh::gpu::GpuMesh;
+use crate::engine::object3d::{Object3D, Vertex};
+use crate::engine::shader::builtin_compute_program;
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Vertices skinned per compute work group, the shader's `local_size_x`.
+const WORK_GROUP_SIZE: usize = 64;
+
+/// Whether the current GL context can run compute shaders (OpenGL 4.3 or later).
+pub fn compute_skinning_supported() -> bool {
+    let (mut major, mut minor): (GLint, GLint) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor) >= (4, 3) && gl::DispatchCompute::is_loaded()
+}
+
+/// A node's skinned copy of its geometry's vertices, with a vertex array drawing them with
+/// the geometry's indices.
+#[derive(Debug)]
+pub(crate) struct DeformedVertices {
+    /// The uploaded geometry the vertices are skinned from, whose index buffer they share.
+    source: Rc<GpuMesh>,
+    vao: GLuint,
+    vbo: GLuint,
+    vertex_count: usize,
+}
+
+impl DeformedVertices {
+    fn new(source: Rc<GpuMesh>, vertex_count: usize) -> Self {
+        let bytes = vertex_count * std::mem::size_of::<Vertex>();
+        let stride = std::mem::size_of::<Vertex>() as GLsizei;
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, std::ptr::null(), gl::DYNAMIC_COPY);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, source.ibo);
+
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, position) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, normal) as *const _);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, uv) as *const _);
+
+            // The element buffer binding is part of VAO state, so unbind the VAO first
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::VertexBuffer, vbo, bytes, "skinned vertices");
+        Self { source, vao, vbo, vertex_count }
+    }
+
+    /// The vertex array to draw instead of `mesh`'s, if these vertices were skinned from it.
+    pub(crate) fn vao_for(&self, mesh: &Rc<GpuMesh>) -> Option<GLuint> {
+        Rc::ptr_eq(&self.source, mesh).then_some(self.vao)
+    }
+}
+
+impl Drop for DeformedVertices {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// Skins every node among `nodes` that has a [`Skin`] and skinned geometry into its
+/// deformed vertex buffer, creating the buffer on first use or when the geometry changed.
+/// Uses the joint matrices of the last scene update.
+pub(crate) fn skin_on_gpu(nodes: impl Iterator<Item = Rc<RefCell<Object3D>>>) {
+    let program = builtin_compute_program("skinning", include_str!("../shaders/skinning.comp"));
+    program.use_program();
+    for node in nodes {
+        let mut object = node.borrow_mut();
+        let skinned = object.geometry().filter(|geometry| geometry.skin.is_some());
+        let Some(vertex_count) = skinned.map(|geometry| geometry.vertices.len()) else {
+            continue;
+        };
+        let Some(mesh) = object.gpu_mesh().cloned() else {
+            continue;
+        };
+        let Some(skin) = object.component_mut::<Skin>() else {
+            continue;
+        };
+        let current = skin.deformed.as_ref().is_some_and(|deformed| {
+            Rc::ptr_eq(&deformed.source, &mesh) && deformed.vertex_count == vertex_count
+        });
+        if !current {
+            skin.deformed = Some(DeformedVertices::new(mesh.clone(), vertex_count));
+        }
+        let Some(deformed) = &skin.deformed else {
+            continue;
+        };
+
+        program.set_uniform_uint("u_vertex_count", vertex_count as u32);
+        program.set_uniform_matrix4_array("u_joint_matrices", skin.joint_matrices());
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, mesh.vbo);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, mesh.skin_vbo);
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 2, deformed.vbo);
+            gl::DispatchCompute(vertex_count.div_ceil(WORK_GROUP_SIZE) as GLuint, 1, 1);
+        }
+    }
+    unsafe {
+        for binding in 0..3 {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, 0);
+        }
+        // The passes drawing the frame read the results as vertex attributes
+        gl::MemoryBarrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    }
+}
+
+/// Frees the deformed vertex buffers of `nodes`, which go back to vertex shader skinning.
+pub(crate) fn release_deformed(nodes: impl Iterator<Item = Rc<RefCell<Object3D>>>) {
+    for node in nodes {
+        if let Some(skin) = node.borrow_mut().component_mut::<Skin>() {
+            skin.deformed = None;
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod skeleton;
+pub mod compute_skinning;
+pub mod clip;
@@ -0,0 +1,209 @@
+//! Skeletons and GPU skinning.
+//!
+//! A skinned mesh bends with a hierarchy of joints: each vertex names up to four joints of a
+//! [`Skeleton`] and how strongly each pulls it (see
+//! [`SkinVertex`](crate::engine::object3d::SkinVertex)), and is moved by the weighted sum of
+//! their transforms. The joints are ordinary [`Object3D`] nodes, usually
+//! imported from glTF together with the mesh, so they are posed like any other node: by
+//! hand, by an [`AnimationPlayer`](crate::engine::animation::clip::AnimationPlayer) or by a
+//! [`Ragdoll`](crate::engine::ragdoll::Ragdoll).
+//!
+//! A [`Skin`] component on the mesh's node links it to its skeleton. Each frame,
+//! [`Scene::update`](crate::engine::scene::Scene::update) computes the joint matrices (the
+//! joint's movement away from its bind pose, in the mesh's space) and the renderer uploads
+//! them as the `u_joint_matrices` array, with `u_skinned` set, for the vertex shader to
+//! blend. The built-in Phong shader skins; custom shaders can do the same:
+//!
+//! ```glsl
+//! layout(location = 7) in uvec4 a_joints;
+//! layout(location = 8) in vec4 a_weights;
+//! uniform int u_skinned;
+//! uniform mat4 u_joint_matrices[64];
+//!
+//! mat4 skin = u_skinned != 0
+//!     ? a_weights.x * u_joint_matrices[a_joints.x] + a_weights.y * u_joint_matrices[a_joints.y]
+//!       + a_weights.z * u_joint_matrices[a_joints.z] + a_weights.w * u_joint_matrices[a_joints.w]
+//!     : mat4(1.0);
+//! ```
+//!
+//! A skeleton drives at most [`MAX_JOINTS`] joints. Culling uses the mesh in its bind pose,
+//! so a limb swung far outside it may be culled early; so do picking and outlines, unless
+//! the mesh is skinned in a [compute pass](crate::engine::animation::compute_skinning)
+//! instead of in the vertex shader.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::animation::skeleton::{Skeleton, Skin};
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::scene::Scene;
+//! # use rustge::engine::time::Clock;
+//! let (mesh, shoulder, elbow) = (Object3D::new(), Object3D::new(), Object3D::new());
+//! elbow.borrow_mut().set_position([1.0, 0.0, 0.0]);
+//! Object3D::add_child(&shoulder, elbow.clone());
+//!
+//! // The current pose is the bind pose: the mesh is drawn as modeled
+//! let skeleton = Skeleton::from_rest_pose(&[shoulder.clone(), elbow.clone()], &mesh);
+//! mesh.borrow_mut().insert_component(Skin::new(skeleton.into()));
+//!
+//! let scene = Scene::new();
+//! scene.add(mesh.clone());
+//! scene.add(shoulder);
+//! elbow.borrow_mut().set_position([1.0, 1.0, 0.0]);
+//! scene.update(None, &Clock::new());
+//!
+//! // Vertices bound to the elbow follow it up
+//! let mesh = mesh.borrow();
+//! let matrices = mesh.component::<Skin>().unwrap().joint_matrices();
+//! assert_eq!(matrices[1][13], 1.0);
+//! ```
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use crate::engine::animation::compute_skinning::DeformedVertices;
+use crate::engine::math::matrixfuncs::{matrix_inverse_or_identity, matrix_mul_4x4};
+use crate::engine::object3d::Object3D;
+use crate::engine::shader::GLShaderProgram;
+
+/// Most joints a skeleton can drive, the size of the `u_joint_matrices` array.
+pub const MAX_JOINTS: usize = 64;
+
+const IDENTITY: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+/// The joints a skinned mesh is bound to, with their bind pose.
+///
+/// Joints are held weakly, so a skeleton doesn't keep removed nodes alive; a dropped joint
+/// stays in its bind pose.
+#[derive(Debug)]
+pub struct Skeleton {
+    joints: Vec<Weak<RefCell<Object3D>>>,
+
+    /// Per joint, the transform from the mesh's space into the joint's space at bind time.
+    inverse_bind_matrices: Vec<[f32; 16]>,
+}
+
+impl Skeleton {
+    /// A skeleton of `joints`, in the order vertices index them, with their inverse bind
+    /// matrices (missing ones are the identity). Joints past [`MAX_JOINTS`] are ignored.
+    pub fn new(joints: &[Rc<RefCell<Object3D>>], inverse_bind_matrices: Vec<[f32; 16]>) -> Self {
+        if joints.len() > MAX_JOINTS {
+            eprintln!("Warning: skeleton has {} joints, only the first {MAX_JOINTS} are used", joints.len());
+        }
+        let joints: Vec<_> = joints.iter().take(MAX_JOINTS).map(Rc::downgrade).collect();
+        let mut inverse_bind_matrices = inverse_bind_matrices;
+        inverse_bind_matrices.resize(joints.len(), IDENTITY);
+        Self { joints, inverse_bind_matrices }
+    }
+
+    /// A skeleton of `joints` bound to `mesh` in their current pose, for skinning geometry
+    /// built around the joints where they stand now.
+    pub fn from_rest_pose(joints: &[Rc<RefCell<Object3D>>], mesh: &Rc<RefCell<Object3D>>) -> Self {
+        let mesh_world = mesh.borrow_mut().world_matrix();
+        let inverse_bind_matrices = joints
+            .iter()
+            .map(|joint| matrix_mul_4x4(&matrix_inverse_or_identity(&joint.borrow_mut().world_matrix()), &mesh_world))
+            .collect();
+        Self::new(joints, inverse_bind_matrices)
+    }
+
+    /// Number of joints.
+    pub fn joint_count(&self) -> usize {
+        self.joints.len()
+    }
+
+    /// The joint nodes, `None` for dropped ones.
+    pub fn joints(&self) -> impl Iterator<Item = Option<Rc<RefCell<Object3D>>>> + '_ {
+        self.joints.iter().map(Weak::upgrade)
+    }
+
+    /// The inverse bind matrix of each joint.
+    pub fn inverse_bind_matrices(&self) -> &[[f32; 16]] {
+        &self.inverse_bind_matrices
+    }
+
+    /// The current joint matrices for a mesh whose world matrix is `mesh_world`: each maps
+    /// a vertex in the mesh's space from the bind pose to the current pose.
+    ///
+    /// # Panics
+    /// Panics if a joint is already borrowed.
+    pub fn joint_matrices(&self, mesh_world: &[f32; 16]) -> Vec<[f32; 16]> {
+        let world_to_mesh = matrix_inverse_or_identity(mesh_world);
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(joint, inverse_bind)| match joint.upgrade() {
+                Some(joint) => {
+                    let joint_world = joint.borrow_mut().world_matrix();
+                    matrix_mul_4x4(&matrix_mul_4x4(&world_to_mesh, &joint_world), inverse_bind)
+                }
+                None => IDENTITY,
+            })
+            .collect()
+    }
+}
+
+/// Component binding a node's skinned geometry to a [`Skeleton`], holding the joint
+/// matrices of the last [`Scene::update`](crate::engine::scene::Scene::update).
+#[derive(Debug)]
+pub struct Skin {
+    skeleton: Rc<Skeleton>,
+    matrices: Vec<[f32; 16]>,
+
+    /// The mesh skinned by the last compute pass, while compute skinning is on.
+    pub(crate) deformed: Option<DeformedVertices>,
+}
+
+impl Skin {
+    /// A skin driven by `skeleton`, which several meshes can share. Starts in the bind pose.
+    pub fn new(skeleton: Rc<Skeleton>) -> Self {
+        let matrices = vec![IDENTITY; skeleton.joint_count()];
+        Self { skeleton, matrices, deformed: None }
+    }
+
+    /// The skeleton driving the skin.
+    pub fn skeleton(&self) -> &Rc<Skeleton> {
+        &self.skeleton
+    }
+
+    /// The joint matrices uploaded when drawing, see [`Skeleton::joint_matrices`].
+    pub fn joint_matrices(&self) -> &[[f32; 16]] {
+        &self.matrices
+    }
+}
+
+/// Recomputes the joint matrices of every [`Skin`] on `nodes`. The node is not borrowed
+/// while its joints are, so a mesh may sit inside its own skeleton.
+pub(crate) fn update_skins(nodes: impl Iterator<Item = Rc<RefCell<Object3D>>>) {
+    for node in nodes {
+        let (skeleton, mesh_world) = {
+            let mut object = node.borrow_mut();
+            let Some(skeleton) = object.component::<Skin>().map(|skin| skin.skeleton.clone()) else {
+                continue;
+            };
+            (skeleton, object.world_matrix())
+        };
+        let matrices = skeleton.joint_matrices(&mesh_world);
+        if let Some(skin) = node.borrow_mut().component_mut::<Skin>() {
+            skin.matrices = matrices;
+        }
+    }
+}
+
+/// Sets `u_skinned` and `u_joint_matrices` on the bound `shader` for drawing `node`:
+/// skinned only if it has both a [`Skin`] and geometry with
+/// [`skin`](crate::engine::object3d::Geometry::skin) data, and wasn't already skinned by
+/// the compute pass.
+pub(crate) fn set_skin_uniforms(shader: &GLShaderProgram, node: &Object3D) {
+    let skin = node
+        .component::<Skin>()
+        .filter(|_| node.geometry().is_some_and(|geometry| geometry.skin.is_some()))
+        .filter(|_| node.deformed_vao().is_none());
+    shader.set_uniform_int("u_skinned", skin.is_some() as i32);
+    if let Some(skin) = skin {
+        shader.set_uniform_matrix4_array("u_joint_matrices", skin.joint_matrices());
+    }
+}
@@ -0,0 +1,157 @@
+//! Scene backgrounds.
+//!
+//! A [`Background`] describes what is visible where no geometry covers the screen: a solid
+//! color, a vertical gradient, a skybox cube map or a procedural sky. It is set on the
+//! [`Scene`](crate::engine::scene::Scene), which can also derive an ambient light term from it.
+//!
+//! Everything except solid colors is drawn as a single fullscreen triangle at the far plane,
+//! depth tested with `GL_LEQUAL` and without writing depth. The renderer draws it after the
+//! opaque geometry, so only pixels no geometry covers run the (possibly expensive) sky
+//! shader; drawn first, the scene simply draws over it.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use gl::types::GLuint;
+use crate::engine::camera::Camera;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::rotation_matrix_from_quat;
+use crate::engine::math::vec::normalize;
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::record_draw_call;
+use crate::engine::texture::Cubemap;
+
+/// What the scene shows behind its geometry.
+#[derive(Clone, Debug)]
+pub enum Background {
+    /// A solid color, applied by clearing the framebuffer.
+    Color(Color),
+    /// A vertical gradient from `bottom` (looking straight down) to `top` (straight up).
+    Gradient { top: Color, bottom: Color },
+    /// A cube map surrounding the camera.
+    Skybox(Rc<Cubemap>),
+    /// An analytic sky with a sun disk.
+    Sky(ProceduralSky),
+}
+
+/// Parameters of the procedural sky background.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProceduralSky {
+    /// Unit direction pointing *towards* the sun.
+    pub sun_direction: [f32; 3],
+
+    /// Color (and brightness) of the sun disk.
+    pub sun_color: Color,
+
+    /// Angular radius of the sun disk in degrees.
+    pub sun_radius: f32,
+
+    /// Sky color straight overhead.
+    pub zenith_color: Color,
+
+    /// Sky color at the horizon.
+    pub horizon_color: Color,
+
+    /// Color below the horizon.
+    pub ground_color: Color,
+}
+
+impl Default for ProceduralSky {
+    /// A clear midday sky with the sun high in the south-west.
+    fn default() -> Self {
+        Self {
+            sun_direction: [0.37, 0.86, 0.35],
+            sun_color: Color::linear_rgb(4.0, 3.8, 3.4),
+            sun_radius: 0.5,
+            zenith_color: Color::srgb(0.25, 0.45, 0.8),
+            horizon_color: Color::srgb(0.7, 0.8, 0.9),
+            ground_color: Color::srgb(0.3, 0.28, 0.25),
+        }
+    }
+}
+
+impl Background {
+    /// A rough average color of the background, used as ambient light when the scene's
+    /// environment lighting is enabled.
+    pub fn ambient_color(&self) -> Color {
+        match self {
+            Background::Color(color) => *color,
+            Background::Gradient { top, bottom } => top.lerp(*bottom, 0.5),
+            Background::Skybox(cubemap) => cubemap.average_color(),
+            Background::Sky(sky) => sky.zenith_color.lerp(sky.horizon_color, 0.5),
+        }
+    }
+
+    /// Draws the background for `camera`. Solid colors are handled by the framebuffer clear
+    /// and draw nothing here.
+    pub(crate) fn draw(&self, camera: &Camera) {
+        let mode = match self {
+            Background::Color(_) => return,
+            Background::Gradient { .. } => 0,
+            Background::Skybox(_) => 1,
+            Background::Sky(_) => 2,
+        };
+
+        let shader = builtin_program(
+            "background",
+            include_str!("shaders/background.vert"),
+            include_str!("shaders/background.frag"),
+        );
+        shader.use_program();
+        shader.set_uniform_int("u_mode", mode);
+        shader.set_uniform_matrix4("u_projection", &camera.projection_matrix());
+        shader.set_uniform_matrix4("u_view_rotation", &rotation_matrix_from_quat(camera.rotation));
+
+        let rgb = |color: &Color| [color.r, color.g, color.b];
+        match self {
+            Background::Gradient { top, bottom } => {
+                shader.set_uniform_vec3("u_top_color", rgb(top));
+                shader.set_uniform_vec3("u_bottom_color", rgb(bottom));
+            }
+            Background::Skybox(cubemap) => {
+                cubemap.bind(0);
+                shader.set_sampler("u_skybox", 0);
+            }
+            Background::Sky(sky) => {
+                shader.set_uniform_vec3("u_sun_direction", normalize(sky.sun_direction));
+                shader.set_uniform_vec3("u_sun_color", rgb(&sky.sun_color));
+                shader.set_uniform_float("u_sun_cos_radius", sky.sun_radius.to_radians().cos());
+                shader.set_uniform_vec3("u_zenith_color", rgb(&sky.zenith_color));
+                shader.set_uniform_vec3("u_horizon_color", rgb(&sky.horizon_color));
+                shader.set_uniform_vec3("u_ground_color", rgb(&sky.ground_color));
+            }
+            Background::Color(_) => unreachable!(),
+        }
+
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            // At depth 1.0, so it passes only where the depth buffer still holds the clear value
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            gl::BindVertexArray(empty_vao());
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::BindVertexArray(0);
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+        }
+        record_draw_call(gl::TRIANGLES, 3);
+    }
+}
+
+thread_local! {
+    /// Attribute-less VAO for fullscreen passes; core profiles refuse to draw without one bound.
+    static EMPTY_VAO: Cell<GLuint> = const { Cell::new(0) };
+}
+
+pub(crate) fn empty_vao() -> GLuint {
+    EMPTY_VAO.with(|vao| {
+        if vao.get() == 0 {
+            let mut id = 0;
+            unsafe {
+                gl::GenVertexArrays(1, &mut id);
+            }
+            vao.set(id);
+        }
+        vao.get()
+    })
+}
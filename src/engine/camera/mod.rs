@@ -0,0 +1,583 @@
+//! Camera and view frustum utilities for 3D rendering.
+//!
+//! This module provides foundational structures for viewing and culling in a 3D scene graph-based renderer.
+//! It includes a `Camera` for perspective projection and a `Frustum` for spatial visibility testing.
+//! A camera can optionally be driven by [`PhysicalCamera`] parameters (sensor, lens and exposure
+//! settings) so framing and brightness match content authored with physical cameras in DCC tools.
+//! [`viewport`] maps window pixels into the part of the window a camera is drawn into.
+
+pub mod orbit;
+pub mod viewport;
+
+pub use orbit::OrbitController;
+
+use viewport::Viewport;
+
+use crate::engine::math::matrixfuncs::{
+    decompose_matrix, look_at_matrix, matrix_inverse_or_identity, matrix_mul_4x4, perspective_matrix,
+    rotation_matrix_from_quat, transform_point, translation_matrix,
+};
+use crate::engine::math::sequence::halton_2d;
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::vec::{dot, normalize, scale, sub};
+use crate::engine::object3d::Object3D;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Represents a perspective projection camera in a 3D scene.
+///
+/// This camera uses a right-handed coordinate system and outputs column-major 4x4 transformation matrices
+/// suitable for use in OpenGL or other graphics APIs.
+///
+/// The camera tracks:
+/// - Position and rotation (as a quaternion)
+/// - Perspective projection parameters (field of view, aspect ratio, near/far planes)
+///
+/// Use `view_matrix()` and `projection_matrix()` to obtain camera transforms for use in rendering.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::camera::Camera;
+/// let mut camera = Camera::new(16.0 / 9.0);
+/// let view = camera.view_matrix();
+/// let proj = camera.projection_matrix();
+/// let proj_view = camera.proj_view_matrix();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Camera {
+    /// The camera's world-space position.
+    pub position: [f32; 3],
+
+    /// The camera's orientation represented as a unit quaternion `[x, y, z, w]`.
+    /// Defaults to identity (facing -Z).
+    pub rotation: [f32; 4],
+
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+
+    /// Aspect ratio of the view (width / height).
+    pub aspect: f32,
+
+    /// Whether `aspect` follows the window's size; see [`fit_aspect`](Self::fit_aspect).
+    pub aspect_mode: AspectMode,
+
+    /// Distance to the near clipping plane.
+    pub near: f32,
+
+    /// Distance to the far clipping plane.
+    pub far: f32,
+
+    /// Physical sensor, lens and exposure settings. When set, the field of view is derived
+    /// from the focal length and sensor size instead of `fov_y`.
+    pub physical: Option<PhysicalCamera>,
+
+    /// Sub-pixel offset applied to the projection, in normalized device coordinates (a
+    /// viewport spans 2 units on each axis). Zero unless a temporal technique sets it; see
+    /// [`set_jitter_pixels`](Self::set_jitter_pixels) and [`apply_halton_jitter`](Self::apply_halton_jitter).
+    pub jitter: [f32; 2],
+
+    /// Render layers the camera draws, one bit per layer: nodes are drawn if one of their
+    /// [layers](crate::engine::object3d::Object3D::set_layers) is set here. All layers by
+    /// default; a UI or minimap camera can leave out the layers of effects, say.
+    pub cull_mask: u32,
+}
+
+impl Camera {
+    /// Creates a new camera with default parameters and given aspect ratio.
+    ///
+    /// Defaults:
+    /// - Position: `[0.0, 0.0, 5.0]` (looking toward the origin)
+    /// - Rotation: identity quaternion
+    /// - FOV: 60 degrees vertical
+    /// - Near/Far: 0.1 / 100.0
+    /// - Cull mask: every layer
+    /// - Aspect mode: [`Fixed`](AspectMode::Fixed)
+    ///
+    /// # Parameters
+    /// - `aspect`: Width-to-height ratio of the viewport.
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            position: [0.0, 0.0, 5.0],
+            rotation: [0.0, 0.0, 0.0, 1.0],
+            fov_y: 60.0_f32.to_radians(),
+            aspect,
+            aspect_mode: AspectMode::Fixed,
+            near: 0.1,
+            far: 100.0,
+            physical: None,
+            jitter: [0.0, 0.0],
+            cull_mask: u32::MAX,
+        }
+    }
+
+    /// Updates the object's position and marks it dirty for recalculation.
+    ///
+    /// `pos` is the new position vector [x, y, z].
+    pub fn set_position(&mut self, pos: [f32; 3]) {
+        self.position = pos;
+    }
+
+    /// Updates the object's rotation quaternion and marks it dirty.
+    ///
+    /// `rot` is the new quaternion [x, y, z, w].
+    pub fn set_rotation(&mut self, rot: [f32; 4]) {
+        self.rotation = rot;
+    }
+
+    /// Moves the camera to `eye` and turns it to face `target`, keeping `up` pointing upwards
+    /// on screen (usually `[0.0, 1.0, 0.0]`).
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::camera::Camera;
+    /// let mut camera = Camera::new(16.0 / 9.0);
+    /// camera.look_at([10.0, 0.0, 0.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+    /// let forward = camera.forward();
+    /// assert!((forward[0] + 1.0).abs() < 1e-6 && forward[1].abs() < 1e-6 && forward[2].abs() < 1e-6);
+    /// ```
+    pub fn look_at(&mut self, eye: [f32; 3], target: [f32; 3], up: [f32; 3]) {
+        self.position = eye;
+        self.rotation = decompose_matrix(&look_at_matrix(eye, target, up)).1;
+    }
+
+    /// Moves the camera back along its view direction until `bounds` fits in view, keeping
+    /// its orientation so it looks straight at the box center. Returns the new distance to
+    /// the center.
+    ///
+    /// `margin` is the fraction of the view left free around the box on its tighter axis,
+    /// e.g. `0.1` for a 10% border. The near and far planes are widened if the box would
+    /// otherwise be clipped.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::{camera::Camera, math::aabb::Aabb};
+    /// let mut camera = Camera::new(16.0 / 9.0);
+    /// let bounds = Aabb::new([-1.0, 0.0, -3.0], [4.0, 2.0, 1.0]);
+    /// camera.frame_bounds(&bounds, 0.1);
+    /// for corner in bounds.corners() {
+    ///     let [x, y] = camera.world_to_screen(corner, [1600, 900]).unwrap();
+    ///     assert!((0.0..=1600.0).contains(&x) && (0.0..=900.0).contains(&y));
+    /// }
+    /// ```
+    pub fn frame_bounds(&mut self, bounds: &Aabb, margin: f32) -> f32 {
+        let (forward, right, up) = (self.forward(), self.right(), self.up());
+        let center = bounds.center();
+        let fill = (1.0 - margin).clamp(0.05, 1.0);
+        let tan_y = (self.vertical_fov() / 2.0).tan() * fill;
+        let tan_x = tan_y * self.aspect;
+
+        // Each corner needs the camera far enough back that its offset across the view axis
+        // fits the field of view at the corner's own depth
+        let offsets = bounds.corners().map(|corner| {
+            let offset = sub(corner, center);
+            [dot(offset, right), dot(offset, up), dot(offset, forward)]
+        });
+        let distance = offsets
+            .iter()
+            .map(|&[x, y, z]| (x.abs() / tan_x).max(y.abs() / tan_y) - z)
+            .fold(self.near * 2.0, f32::max);
+
+        self.position = sub(center, scale(forward, distance));
+
+        let nearest = offsets.iter().map(|offset| distance + offset[2]).fold(f32::INFINITY, f32::min);
+        let farthest = offsets.iter().map(|offset| distance + offset[2]).fold(0.0, f32::max);
+        self.near = self.near.min((nearest * 0.5).max(farthest * 1e-4));
+        self.far = self.far.max(farthest * 1.5);
+        distance
+    }
+
+    /// The world-space direction the camera looks in.
+    pub fn forward(&self) -> [f32; 3] {
+        // The view rotation maps world to view space; its third row is the camera's backward axis
+        let m = rotation_matrix_from_quat(self.rotation);
+        [-m[2], -m[6], -m[10]]
+    }
+
+    /// The world-space direction pointing right on screen.
+    pub fn right(&self) -> [f32; 3] {
+        let m = rotation_matrix_from_quat(self.rotation);
+        [m[0], m[4], m[8]]
+    }
+
+    /// The world-space direction pointing up on screen.
+    pub fn up(&self) -> [f32; 3] {
+        let m = rotation_matrix_from_quat(self.rotation);
+        [m[1], m[5], m[9]]
+    }
+
+    /// Sets the camera's Near & Far ranges
+    pub fn set_near_far(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+    }
+
+    /// Sets [`aspect`](Self::aspect) for a window of `window_size` pixels, as the
+    /// [`aspect_mode`](Self::aspect_mode) says; a fixed aspect stays as it is. The renderer
+    /// calls this for its camera when it is set and whenever the window is resized; call it
+    /// for other cameras, e.g. the extra ones of split-screen, on
+    /// [resize events](crate::engine::event::EngineEvent::Resized).
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::camera::{AspectMode, Camera};
+    /// let mut camera = Camera::new(1.0);
+    /// camera.aspect_mode = AspectMode::Window;
+    /// camera.fit_aspect([1920, 1080]);
+    /// assert_eq!(camera.aspect, 16.0 / 9.0);
+    ///
+    /// // The left half of the window, for the first player of split-screen
+    /// camera.aspect_mode = AspectMode::Viewport([0.0, 0.0, 0.5, 1.0]);
+    /// camera.fit_aspect([1920, 1080]);
+    /// assert_eq!(camera.aspect, 8.0 / 9.0);
+    /// ```
+    pub fn fit_aspect(&mut self, window_size: [u32; 2]) {
+        if window_size[0] == 0 || window_size[1] == 0 {
+            // Minimized
+            return;
+        }
+        match self.aspect_mode {
+            AspectMode::Fixed => {}
+            AspectMode::Window => self.aspect = Viewport::full(window_size).aspect(),
+            AspectMode::Viewport(fractions) => {
+                let viewport = Viewport::from_fractions(window_size, fractions);
+                if viewport.width > 0 && viewport.height > 0 {
+                    self.aspect = viewport.aspect();
+                }
+            }
+        }
+    }
+
+    /// Sets the camera's FOV
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov_y = fov.to_radians();
+    }
+
+    /// Drives the camera with physical sensor and lens settings, or `None` to go back to
+    /// `fov_y` and an exposure of 1.
+    pub fn set_physical(&mut self, physical: Option<PhysicalCamera>) {
+        self.physical = physical;
+    }
+
+    /// The vertical field of view in radians actually used for projection: derived from the
+    /// physical settings if present, `fov_y` otherwise.
+    pub fn vertical_fov(&self) -> f32 {
+        match &self.physical {
+            Some(physical) => physical.vertical_fov(self.aspect),
+            None => self.fov_y,
+        }
+    }
+
+    /// Multiplier applied to lit scene radiance before display.
+    ///
+    /// `1.0` without physical settings, so lights in arbitrary units look as authored. With
+    /// physical settings, light intensities are expected in physical units (e.g. lux for
+    /// [`Light::sun`](crate::engine::light::Light::sun)) and scaled by
+    /// [`PhysicalCamera::exposure`].
+    pub fn exposure(&self) -> f32 {
+        self.physical.as_ref().map_or(1.0, PhysicalCamera::exposure)
+    }
+
+    /// Computes the view matrix from the camera's position and rotation.
+    ///
+    /// This transform converts world-space coordinates into view-space,
+    /// where the camera is at the origin looking down the negative Z-axis.
+    ///
+    /// # Returns
+    /// A 4x4 column-major view matrix.
+    pub fn view_matrix(&self) -> [f32; 16] {
+        let rot_matrix = rotation_matrix_from_quat(self.rotation);
+        let trans_matrix = translation_matrix([
+            -self.position[0],
+            -self.position[1],
+            -self.position[2],
+        ]);
+
+        matrix_mul_4x4(&rot_matrix, &trans_matrix)
+    }
+
+    /// Computes the perspective projection matrix based on the camera's FOV, aspect ratio, and near/far planes,
+    /// shifted by the current [`jitter`](Self::jitter).
+    ///
+    /// # Returns
+    /// A 4x4 column-major perspective projection matrix.
+    pub fn projection_matrix(&self) -> [f32; 16] {
+        let mut projection = self.unjittered_projection_matrix();
+        // Clip w is -z, so subtracting here moves the projected image by +jitter in NDC
+        projection[8] -= self.jitter[0];
+        projection[9] -= self.jitter[1];
+        projection
+    }
+
+    /// The projection matrix without the sub-pixel jitter, e.g. for computing motion vectors
+    /// or for picking, which should not wobble from frame to frame.
+    pub fn unjittered_projection_matrix(&self) -> [f32; 16] {
+        perspective_matrix(self.vertical_fov(), self.aspect, self.near, self.far)
+    }
+
+    /// Sets the projection jitter to `offset` pixels (x right, y up) on a viewport of
+    /// `viewport_size` pixels.
+    pub fn set_jitter_pixels(&mut self, offset: [f32; 2], viewport_size: [u32; 2]) {
+        self.jitter = [
+            offset[0] * 2.0 / viewport_size[0].max(1) as f32,
+            offset[1] * 2.0 / viewport_size[1].max(1) as f32,
+        ];
+    }
+
+    /// Jitters the projection by the Halton (2, 3) sample for `frame`, cycling through
+    /// `sample_count` samples, with offsets in `[-0.5, 0.5)` pixels of a `viewport_size`
+    /// viewport. Call once per frame before rendering.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::camera::Camera;
+    /// let mut camera = Camera::new(16.0 / 9.0);
+    /// camera.apply_halton_jitter(0, 8, [1920, 1080]);
+    /// assert_eq!(camera.jitter, [0.0, (1.0 / 3.0 - 0.5) * 2.0 / 1080.0]);
+    /// camera.clear_jitter();
+    /// assert_eq!(camera.projection_matrix(), camera.unjittered_projection_matrix());
+    /// ```
+    pub fn apply_halton_jitter(&mut self, frame: u64, sample_count: u32, viewport_size: [u32; 2]) {
+        // Index 0 of the sequence is always 0; start at 1
+        let index = (frame % sample_count.max(1) as u64) as u32 + 1;
+        let [x, y] = halton_2d(index);
+        self.set_jitter_pixels([x - 0.5, y - 0.5], viewport_size);
+    }
+
+    /// Removes the projection jitter.
+    pub fn clear_jitter(&mut self) {
+        self.jitter = [0.0, 0.0];
+    }
+
+    /// Returns the combined projection * view matrix for transforming world-space coordinates
+    /// directly into clip space.
+    pub fn proj_view_matrix(&self) -> [f32; 16] {
+        matrix_mul_4x4(&self.projection_matrix(), &self.view_matrix())
+    }
+
+    /// Projects a world-space point to pixel coordinates (origin top-left) on a viewport of
+    /// `screen_size` pixels.
+    ///
+    /// Returns `None` for points behind the camera. The projection jitter is ignored.
+    pub fn world_to_screen(&self, point: [f32; 3], screen_size: [u32; 2]) -> Option<[f32; 2]> {
+        let m = matrix_mul_4x4(&self.unjittered_projection_matrix(), &self.view_matrix());
+        let w = m[3] * point[0] + m[7] * point[1] + m[11] * point[2] + m[15];
+        if w <= f32::EPSILON {
+            return None;
+        }
+        let ndc = transform_point(&m, point);
+        Some([
+            (ndc[0] + 1.0) * 0.5 * screen_size[0] as f32,
+            (1.0 - ndc[1]) * 0.5 * screen_size[1] as f32,
+        ])
+    }
+
+    /// The world-space ray through pixel `pixel` (origin top-left) of a viewport of
+    /// `screen_size` pixels, as `(origin, direction)`. The origin lies on the near plane and
+    /// the direction is normalized. The projection jitter is ignored.
+    pub fn screen_ray(&self, pixel: [f32; 2], screen_size: [u32; 2]) -> ([f32; 3], [f32; 3]) {
+        let ndc_x = pixel[0] / screen_size[0].max(1) as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - pixel[1] / screen_size[1].max(1) as f32 * 2.0;
+        let proj_view = matrix_mul_4x4(&self.unjittered_projection_matrix(), &self.view_matrix());
+        let inverse = matrix_inverse_or_identity(&proj_view);
+        let near = transform_point(&inverse, [ndc_x, ndc_y, -1.0]);
+        let far = transform_point(&inverse, [ndc_x, ndc_y, 1.0]);
+        (near, normalize(sub(far, near)))
+    }
+
+    /// The camera's view frustum in world space.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&self.proj_view_matrix())
+    }
+
+    /// Tests a world-space bounding sphere against the view frustum.
+    ///
+    /// # Parameters
+    /// - `world_pos`: Center of the object in world coordinates.
+    /// - `radius`: Radius of the object's bounding sphere.
+    ///
+    /// # Returns
+    /// `true` if the sphere may be visible; `false` if it lies entirely outside the frustum.
+    pub fn intersects_sphere(&self, world_pos: [f32; 3], radius: f32) -> bool {
+        self.frustum().intersects_sphere(world_pos, radius)
+    }
+
+    /// Whether `node` passes the same visibility test the renderer uses to cull it: it is in
+    /// one of the [layers](Self::cull_mask) the camera draws, the camera is within its
+    /// [draw distance](Object3D::set_draw_distance), if limited, and its
+    /// world-space bounding sphere (see [`Object3D::world_bounding_sphere`]) intersects the
+    /// view frustum. Nodes with frustum culling disabled skip the frustum test.
+    ///
+    /// This is a frustum test only; it doesn't account for occlusion by other objects.
+    ///
+    /// # Panics
+    /// Panics if `node` is already borrowed, e.g. from inside its own update callback; use
+    /// [`Object3D::is_visible`] there instead.
+    pub fn is_visible(&self, node: &Rc<RefCell<Object3D>>) -> bool {
+        node.borrow_mut().is_visible(self)
+    }
+}
+
+/// A view frustum as six planes `[a, b, c, d]` (`a*x + b*y + c*z + d >= 0` inside), with
+/// normalized normals pointing inwards: left, right, bottom, top, near, far.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::camera::Camera;
+/// let camera = Camera::new(1.0); // at z = 5, looking down -Z
+/// let frustum = camera.frustum();
+/// assert!(frustum.intersects_sphere([0.0, 0.0, 0.0], 0.5));
+/// assert!(!frustum.intersects_sphere([0.0, 0.0, 10.0], 0.5)); // behind the camera
+/// assert!(!frustum.intersects_sphere([50.0, 0.0, 0.0], 0.5)); // far off to the side
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a projection * view matrix (column-major).
+    pub fn from_matrix(m: &[f32; 16]) -> Self {
+        let row = |i: usize| [m[i], m[4 + i], m[8 + i], m[12 + i]];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let plane = |a: [f32; 4], b: [f32; 4], sign: f32| {
+            let p: [f32; 4] = std::array::from_fn(|i| a[i] + sign * b[i]);
+            let length = dot([p[0], p[1], p[2]], [p[0], p[1], p[2]]).sqrt().max(f32::EPSILON);
+            p.map(|v| v / length)
+        };
+        Self {
+            planes: [
+                plane(r3, r0, 1.0),
+                plane(r3, r0, -1.0),
+                plane(r3, r1, 1.0),
+                plane(r3, r1, -1.0),
+                plane(r3, r2, 1.0),
+                plane(r3, r2, -1.0),
+            ],
+        }
+    }
+
+    /// Returns `true` unless the sphere lies entirely outside one of the planes.
+    pub fn intersects_sphere(&self, center: [f32; 3], radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|p| dot([p[0], p[1], p[2]], center) + p[3] >= -radius)
+    }
+
+    /// Returns `true` if the point is inside the frustum.
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        self.intersects_sphere(point, 0.0)
+    }
+}
+
+/// How a camera's aspect ratio is kept, see [`Camera::fit_aspect`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AspectMode {
+    /// The aspect ratio only changes when set.
+    #[default]
+    Fixed,
+
+    /// The aspect ratio is the window's.
+    Window,
+
+    /// The aspect ratio is that of a part of the window, given as fractions of its size
+    /// from its top-left corner (see [`Viewport::from_fractions`]).
+    Viewport([f32; 4]),
+}
+
+/// Which sensor dimension is matched to the viewport when deriving the field of view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SensorFit {
+    /// The sensor width spans the larger viewport dimension (Blender's "Auto").
+    #[default]
+    Auto,
+    /// The sensor width spans the viewport width.
+    Horizontal,
+    /// The sensor height spans the viewport height.
+    Vertical,
+}
+
+/// Physical camera parameters: sensor size, lens and exposure settings.
+///
+/// The defaults describe a full-frame 35mm camera with a 50mm lens exposed by the
+/// "sunny 16" rule (f/16, 1/125 s, ISO 100), which suits a scene lit by [`Light::sun`]
+/// at its default illuminance.
+///
+/// [`Light::sun`]: crate::engine::light::Light::sun
+///
+/// # Example
+/// ```
+/// # use rustge::engine::camera::{Camera, PhysicalCamera};
+/// let mut camera = Camera::new(3.0 / 2.0);
+/// camera.set_physical(Some(PhysicalCamera { focal_length: 35.0, ..PhysicalCamera::default() }));
+/// // A 35mm lens on a 36mm-wide sensor sees about 54 degrees horizontally
+/// let horizontal = 2.0 * ((camera.vertical_fov() / 2.0).tan() * camera.aspect).atan();
+/// assert!((horizontal.to_degrees() - 54.4).abs() < 0.1);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhysicalCamera {
+    /// Sensor width and height in millimetres.
+    pub sensor_size: [f32; 2],
+
+    /// How the sensor is fitted to the viewport.
+    pub sensor_fit: SensorFit,
+
+    /// Lens focal length in millimetres.
+    pub focal_length: f32,
+
+    /// Aperture as an f-number (e.g. `2.8` for f/2.8).
+    pub aperture: f32,
+
+    /// Shutter speed (exposure time) in seconds.
+    pub shutter_speed: f32,
+
+    /// Sensor sensitivity (ISO).
+    pub iso: f32,
+}
+
+impl Default for PhysicalCamera {
+    fn default() -> Self {
+        Self {
+            sensor_size: [36.0, 24.0],
+            sensor_fit: SensorFit::Auto,
+            focal_length: 50.0,
+            aperture: 16.0,
+            shutter_speed: 1.0 / 125.0,
+            iso: 100.0,
+        }
+    }
+}
+
+impl PhysicalCamera {
+    /// Vertical field of view in radians for a viewport with the given aspect ratio.
+    pub fn vertical_fov(&self, aspect: f32) -> f32 {
+        let focal_length = self.focal_length.max(f32::EPSILON);
+        let fit_horizontally = match self.sensor_fit {
+            SensorFit::Auto => aspect >= 1.0,
+            SensorFit::Horizontal => true,
+            SensorFit::Vertical => false,
+        };
+
+        if fit_horizontally {
+            let half_width = self.sensor_size[0] / 2.0;
+            ((half_width / focal_length) / aspect.max(f32::EPSILON)).atan() * 2.0
+        } else {
+            // Auto on a portrait viewport puts the sensor width along the (taller) height
+            let height = if self.sensor_fit == SensorFit::Auto { self.sensor_size[0] } else { self.sensor_size[1] };
+            (height / 2.0 / focal_length).atan() * 2.0
+        }
+    }
+
+    /// Exposure value at ISO 100: `log2(N² / t * 100 / S)`.
+    pub fn ev100(&self) -> f32 {
+        let aperture = self.aperture.max(f32::EPSILON);
+        let shutter = self.shutter_speed.max(f32::EPSILON);
+        let iso = self.iso.max(f32::EPSILON);
+        (aperture * aperture / shutter * 100.0 / iso).log2()
+    }
+
+    /// Scale from scene luminance to display values, using the saturation-based sensor model
+    /// (`1 / (1.2 * 2^EV100)`).
+    pub fn exposure(&self) -> f32 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
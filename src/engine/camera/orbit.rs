@@ -0,0 +1,230 @@
+//! Orbit camera controller for inspecting models.
+//!
+//! [`OrbitController`] keeps the camera on a sphere around a target point: dragging with the
+//! left mouse button orbits, dragging with the right or middle button (or shift + left)
+//! pans the target, and the scroll wheel zooms. Movement is optionally damped so the camera
+//! eases towards where the input put it.
+//!
+//! Attach one to the renderer with
+//! [`Renderer::set_camera_controller`](crate::engine::renderer::Renderer::set_camera_controller)
+//! and it receives window input and moves the active camera every frame. It can also be
+//! driven manually through [`rotate`](OrbitController::rotate), [`pan`](OrbitController::pan),
+//! [`zoom`](OrbitController::zoom) and [`update`](OrbitController::update).
+
+use glutin::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use crate::engine::camera::Camera;
+use crate::engine::math::vec::{add, cross, length, normalize, scale, sub};
+
+/// Moves a camera around a target point in response to mouse input.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::camera::{Camera, OrbitController};
+/// let mut camera = Camera::new(16.0 / 9.0);
+/// let mut orbit = OrbitController::new([0.0, 1.0, 0.0], 5.0);
+/// orbit.damping = 0.0;
+/// orbit.rotate(100.0, 0.0); // as if dragged 100 pixels to the right
+/// orbit.zoom(2.0);          // two wheel notches towards the target
+/// orbit.update(&mut camera, 1.0 / 60.0);
+/// assert!(orbit.distance() < 5.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    /// Radians of orbit per pixel dragged.
+    pub rotate_sensitivity: f32,
+
+    /// Target movement per pixel dragged, as a fraction of the orbit distance.
+    pub pan_sensitivity: f32,
+
+    /// Fraction of the distance removed per scroll notch.
+    pub zoom_sensitivity: f32,
+
+    /// Closest the camera may get to the target.
+    pub min_distance: f32,
+
+    /// Farthest the camera may get from the target.
+    pub max_distance: f32,
+
+    /// Pitch limits in radians; kept inside +/-90 degrees so the view never flips over the poles.
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+
+    /// How quickly the camera catches up with the input, per second. `0.0` disables damping
+    /// and applies input immediately.
+    pub damping: f32,
+
+    /// Current orbit state, eased towards the goal below.
+    target: [f32; 3],
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+
+    /// Where the input wants the camera to be.
+    goal_target: [f32; 3],
+    goal_yaw: f32,
+    goal_pitch: f32,
+    goal_distance: f32,
+
+    /// Mouse state tracked from window events.
+    cursor: Option<[f64; 2]>,
+    left_down: bool,
+    pan_down: bool,
+    shift: bool,
+}
+
+impl OrbitController {
+    /// Orbits around `target` at `distance`, looking from the front (+Z) and slightly above.
+    pub fn new(target: [f32; 3], distance: f32) -> Self {
+        let (yaw, pitch) = (0.0, 0.3);
+        Self {
+            rotate_sensitivity: 0.005,
+            pan_sensitivity: 0.0015,
+            zoom_sensitivity: 0.1,
+            min_distance: 0.05,
+            max_distance: 1000.0,
+            min_pitch: -89f32.to_radians(),
+            max_pitch: 89f32.to_radians(),
+            damping: 12.0,
+            target,
+            yaw,
+            pitch,
+            distance,
+            goal_target: target,
+            goal_yaw: yaw,
+            goal_pitch: pitch,
+            goal_distance: distance,
+            cursor: None,
+            left_down: false,
+            pan_down: false,
+            shift: false,
+        }
+    }
+
+    /// Orbits around `target`, starting from wherever `camera` currently is.
+    pub fn from_camera(camera: &Camera, target: [f32; 3]) -> Self {
+        let offset = sub(camera.position, target);
+        let distance = length(offset).max(f32::EPSILON);
+        let mut controller = Self::new(target, distance);
+        controller.yaw = offset[0].atan2(offset[2]);
+        controller.pitch = (offset[1] / distance).clamp(-1.0, 1.0).asin();
+        controller.pitch = controller.pitch.clamp(controller.min_pitch, controller.max_pitch);
+        controller.goal_yaw = controller.yaw;
+        controller.goal_pitch = controller.pitch;
+        controller
+    }
+
+    /// The point being orbited.
+    pub fn target(&self) -> [f32; 3] {
+        self.target
+    }
+
+    /// Moves the orbit target (immediately, without damping).
+    pub fn set_target(&mut self, target: [f32; 3]) {
+        self.target = target;
+        self.goal_target = target;
+    }
+
+    /// Current distance from the camera to the target.
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Sets the orbit distance (immediately, clamped to the zoom limits).
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.clamp(self.min_distance, self.max_distance);
+        self.goal_distance = self.distance;
+    }
+
+    /// Current (yaw, pitch) in radians. Yaw 0 looks from +Z towards the target.
+    pub fn angles(&self) -> (f32, f32) {
+        (self.yaw, self.pitch)
+    }
+
+    /// Orbits as if the mouse were dragged by (`dx`, `dy`) pixels.
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        self.goal_yaw -= dx * self.rotate_sensitivity;
+        self.goal_pitch = (self.goal_pitch + dy * self.rotate_sensitivity).clamp(self.min_pitch, self.max_pitch);
+    }
+
+    /// Moves the target in the view plane as if dragged by (`dx`, `dy`) pixels, so the
+    /// scene follows the cursor.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let (right, up) = view_axes(self.goal_yaw, self.goal_pitch);
+        let amount = self.goal_distance * self.pan_sensitivity;
+        let offset = add(scale(right, -dx * amount), scale(up, dy * amount));
+        self.goal_target = add(self.goal_target, offset);
+    }
+
+    /// Zooms by `steps` scroll notches; positive values move towards the target.
+    pub fn zoom(&mut self, steps: f32) {
+        let factor = (1.0 - self.zoom_sensitivity).clamp(0.01, 1.0).powf(steps);
+        self.goal_distance = (self.goal_distance * factor).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Feeds a window event (mouse buttons, cursor movement, scroll, modifiers) to the controller.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.shift = modifiers.shift(),
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => self.left_down = pressed,
+                    MouseButton::Right | MouseButton::Middle => self.pan_down = pressed,
+                    _ => {}
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = [position.x, position.y];
+                if let Some(last) = self.cursor {
+                    let (dx, dy) = ((position[0] - last[0]) as f32, (position[1] - last[1]) as f32);
+                    if self.pan_down || (self.left_down && self.shift) {
+                        self.pan(dx, dy);
+                    } else if self.left_down {
+                        self.rotate(dx, dy);
+                    }
+                }
+                self.cursor = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } | WindowEvent::Focused(false) => {
+                self.cursor = None;
+                self.left_down = false;
+                self.pan_down = false;
+            }
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(_, y) => self.zoom(*y),
+                // Roughly one notch per 50 pixels of touchpad scrolling
+                MouseScrollDelta::PixelDelta(position) => self.zoom(position.y as f32 / 50.0),
+            },
+            _ => {}
+        }
+    }
+
+    /// Eases towards the latest input over `delta` seconds and places `camera` accordingly.
+    pub fn update(&mut self, camera: &mut Camera, delta: f32) {
+        let t = if self.damping > 0.0 { 1.0 - (-self.damping * delta).exp() } else { 1.0 };
+        self.yaw += (self.goal_yaw - self.yaw) * t;
+        self.pitch += (self.goal_pitch - self.pitch) * t;
+        self.distance += (self.goal_distance - self.distance) * t;
+        self.target = add(self.target, scale(sub(self.goal_target, self.target), t));
+
+        camera.look_at(self.camera_position(), self.target, [0.0, 1.0, 0.0]);
+    }
+
+    /// Where the camera sits for the current orbit state.
+    pub fn camera_position(&self) -> [f32; 3] {
+        add(self.target, scale(orbit_direction(self.yaw, self.pitch), self.distance))
+    }
+}
+
+/// Screen right and up directions for the given orbit angles.
+fn view_axes(yaw: f32, pitch: f32) -> ([f32; 3], [f32; 3]) {
+    let backward = orbit_direction(yaw, pitch);
+    let right = normalize(cross([0.0, 1.0, 0.0], backward));
+    let up = cross(backward, right);
+    (right, up)
+}
+
+/// Unit vector from the target towards the camera.
+fn orbit_direction(yaw: f32, pitch: f32) -> [f32; 3] {
+    [pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos()]
+}
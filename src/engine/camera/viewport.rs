@@ -0,0 +1,171 @@
+//! Viewport rectangles, and mapping window pixels into them.
+//!
+//! Split-screen, editor panes, minimaps and fixed-aspect games draw each camera into part of
+//! the window only. Mouse positions arrive in window pixels, so before they can pick a node,
+//! cast a ray or hit-test UI they have to be brought into the viewport under them: offset to
+//! its corner, and scaled to its size rather than the window's.
+//!
+//! A [`Viewport`] is a rectangle of the window in physical pixels, with the origin at the
+//! top-left like cursor positions. [`Viewport::letterboxed`] fits a fixed aspect ratio into
+//! the window with bars on two sides; [`Viewport::from_fractions`] cuts out a part of the
+//! window for split-screen. [`viewport_at`] finds which of several viewports the cursor is
+//! over, and [`Viewport::screen_ray`] and [`Viewport::world_to_window`] map between window
+//! pixels and the world seen by that viewport's camera.
+//!
+//! Window events report the cursor in physical pixels, as viewports are. Positions in logical
+//! pixels, e.g. from a UI laid out for any DPI, convert with [`to_physical`] and the window's
+//! [scale factor](crate::engine::renderer::Renderer::scale_factor).
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::camera::{viewport::Viewport, Camera};
+//! // A 4:3 game in a 1920x1080 window gets bars left and right
+//! let viewport = Viewport::letterboxed([1920, 1080], 4.0 / 3.0);
+//! assert_eq!(viewport, Viewport { x: 240, y: 0, width: 1440, height: 1080 });
+//!
+//! // A click on the bar is outside; one in the middle is the viewport's center
+//! assert_eq!(viewport.to_normalized([100.0, 540.0]), None);
+//! assert_eq!(viewport.to_normalized([960.0, 540.0]), Some([0.5, 0.5]));
+//!
+//! // Rays go through the clicked point of the letterboxed picture
+//! let camera = Camera::new(viewport.aspect());
+//! let (_, direction) = viewport.screen_ray(&camera, [960.0, 540.0]);
+//! assert!((direction[2] + 1.0).abs() < 1e-5);
+//! ```
+
+use crate::engine::camera::Camera;
+
+/// A rectangle of the window, in physical pixels from its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    /// The whole of a window of `window_size` pixels.
+    pub fn full(window_size: [u32; 2]) -> Self {
+        Self { x: 0, y: 0, width: window_size[0], height: window_size[1] }
+    }
+
+    /// The part of a window of `window_size` pixels given as fractions of its size, from its
+    /// top-left corner, e.g. `[0.5, 0.0, 0.5, 1.0]` for the right half.
+    pub fn from_fractions(window_size: [u32; 2], [x, y, width, height]: [f32; 4]) -> Self {
+        let [window_width, window_height] = window_size.map(|size| size as f32);
+        // Round the edges, not the sizes, so neighbouring viewports tile without gaps
+        let left = (x * window_width).round();
+        let top = (y * window_height).round();
+        let right = ((x + width) * window_width).round();
+        let bottom = ((y + height) * window_height).round();
+        Self { x: left as u32, y: top as u32, width: (right - left).max(0.0) as u32, height: (bottom - top).max(0.0) as u32 }
+    }
+
+    /// The largest viewport of aspect ratio `aspect` (width / height) centered in a window of
+    /// `window_size` pixels, leaving bars at the top and bottom or at the sides.
+    pub fn letterboxed(window_size: [u32; 2], aspect: f32) -> Self {
+        Self::full(window_size).fit(aspect)
+    }
+
+    /// The largest viewport of aspect ratio `aspect` centered in this one.
+    pub fn fit(&self, aspect: f32) -> Self {
+        if aspect <= 0.0 || self.width == 0 || self.height == 0 {
+            return *self;
+        }
+        let (width, height) = if self.aspect() > aspect {
+            (((self.height as f32 * aspect).round() as u32).min(self.width), self.height)
+        } else {
+            (self.width, ((self.width as f32 / aspect).round() as u32).min(self.height))
+        };
+        Self { x: self.x + (self.width - width) / 2, y: self.y + (self.height - height) / 2, width, height }
+    }
+
+    /// Width / height, for the [aspect ratio](Camera::aspect) of the camera drawn into it.
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    /// The size in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    /// Whether window pixel `pixel` lies inside.
+    pub fn contains(&self, pixel: [f32; 2]) -> bool {
+        let [x, y] = [pixel[0] - self.x as f32, pixel[1] - self.y as f32];
+        x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32
+    }
+
+    /// Window pixel `pixel` relative to the viewport's top-left corner, whether inside or not.
+    pub fn to_local(&self, pixel: [f32; 2]) -> [f32; 2] {
+        [pixel[0] - self.x as f32, pixel[1] - self.y as f32]
+    }
+
+    /// Viewport pixel `local` (from its top-left corner) in window pixels.
+    pub fn to_window(&self, local: [f32; 2]) -> [f32; 2] {
+        [local[0] + self.x as f32, local[1] + self.y as f32]
+    }
+
+    /// Window pixel `pixel` as fractions of the viewport, from `[0, 0]` at its top-left to
+    /// `[1, 1]` at its bottom-right, or `None` outside it.
+    pub fn to_normalized(&self, pixel: [f32; 2]) -> Option<[f32; 2]> {
+        if !self.contains(pixel) {
+            return None;
+        }
+        let [x, y] = self.to_local(pixel);
+        Some([x / self.width as f32, y / self.height as f32])
+    }
+
+    /// Window pixel `pixel` in the normalized device coordinates of the viewport: -1 to 1
+    /// from left to right and from bottom to top, beyond that outside it.
+    pub fn to_ndc(&self, pixel: [f32; 2]) -> [f32; 2] {
+        let [x, y] = self.to_local(pixel);
+        [x / self.width.max(1) as f32 * 2.0 - 1.0, 1.0 - y / self.height.max(1) as f32 * 2.0]
+    }
+
+    /// The world-space ray through window pixel `pixel` for `camera` drawn into this viewport,
+    /// as `(origin, direction)`; see [`Camera::screen_ray`].
+    pub fn screen_ray(&self, camera: &Camera, pixel: [f32; 2]) -> ([f32; 3], [f32; 3]) {
+        camera.screen_ray(self.to_local(pixel), self.size())
+    }
+
+    /// The window pixel a world-space point shows at when `camera` is drawn into this viewport,
+    /// or `None` behind the camera; see [`Camera::world_to_screen`].
+    pub fn world_to_window(&self, camera: &Camera, point: [f32; 3]) -> Option<[f32; 2]> {
+        camera.world_to_screen(point, self.size()).map(|local| self.to_window(local))
+    }
+
+    /// The arguments of `gl::Viewport` (and `gl::Scissor`) for this viewport in a window
+    /// `window_height` pixels high, whose origin is at the bottom-left.
+    pub fn gl_rect(&self, window_height: u32) -> [i32; 4] {
+        let bottom = window_height as i32 - (self.y + self.height) as i32;
+        [self.x as i32, bottom, self.width as i32, self.height as i32]
+    }
+}
+
+/// The index of the viewport window pixel `pixel` is over, the last one listed where they
+/// overlap (as drawn on top), or `None` if it's over none, e.g. on a letterbox bar.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::camera::viewport::{viewport_at, Viewport};
+/// let window = [1280, 720];
+/// let players = [Viewport::from_fractions(window, [0.0, 0.0, 0.5, 1.0]), Viewport::from_fractions(window, [0.5, 0.0, 0.5, 1.0])];
+/// assert_eq!(viewport_at(&players, [1000.0, 300.0]), Some(1));
+/// ```
+pub fn viewport_at(viewports: &[Viewport], pixel: [f32; 2]) -> Option<usize> {
+    viewports.iter().rposition(|viewport| viewport.contains(pixel))
+}
+
+/// Converts a position in logical pixels to physical pixels for a window with scale factor
+/// `scale_factor` (physical pixels per logical pixel).
+pub fn to_physical(logical: [f32; 2], scale_factor: f64) -> [f32; 2] {
+    logical.map(|value| (value as f64 * scale_factor) as f32)
+}
+
+/// Converts a position in physical pixels to logical pixels for a window with scale factor
+/// `scale_factor`.
+pub fn to_logical(physical: [f32; 2], scale_factor: f64) -> [f32; 2] {
+    physical.map(|value| (value as f64 / scale_factor) as f32)
+}
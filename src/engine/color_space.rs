@@ -0,0 +1,107 @@
+//! The color space of rendering: linear lighting with sRGB framebuffers and textures.
+//!
+//! [`Color`]s are linear, and the engine's shaders light in linear space. How colors reach
+//! the screen depends on the pipeline the renderer runs:
+//!
+//! - **sRGB pipeline** (the default): the window gets an sRGB-capable framebuffer and
+//!   `GL_FRAMEBUFFER_SRGB` is enabled, so the hardware encodes what shaders write. Color
+//!   textures and render targets are `SRGB8_ALPHA8`, decoded to linear when sampled and
+//!   blended in linear space. Shaders output linear colors.
+//! - **Legacy pipeline**, with [`RendererBuilder::srgb(false)`](crate::engine::renderer::RendererBuilder::srgb)
+//!   or when the driver offers no sRGB framebuffer: everything is `RGBA8`, shaders decode
+//!   the texels they sample and encode their output themselves, and blending mixes encoded
+//!   values, as the engine always did.
+//!
+//! The engine builds every program with `#define SRGB_PIPELINE` while the sRGB pipeline
+//! runs, so shaders can do both:
+//!
+//! ```glsl
+//! vec3 albedo = texture(u_albedo, v_uv).rgb;
+//! #ifndef SRGB_PIPELINE
+//! albedo = srgb_to_linear(albedo);
+//! #endif
+//! // ... lighting ...
+//! #ifdef SRGB_PIPELINE
+//! frag_color = vec4(lit, 1.0);
+//! #else
+//! frag_color = vec4(linear_to_srgb(lit), 1.0);
+//! #endif
+//! ```
+//!
+//! Colors handed straight to the framebuffer, such as vertex colors of debug lines, go
+//! through [`output_color`]. Pixels read back from framebuffers and textures are sRGB-encoded
+//! bytes either way.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{color_space, renderer::Renderer};
+//! let renderer = Renderer::builder().srgb(false).build();
+//! assert!(!color_space::srgb_pipeline());
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use gl::types::GLenum;
+use crate::engine::math::color::Color;
+
+/// Name of the preprocessor symbol defined in every program while the sRGB pipeline runs.
+pub const SRGB_PIPELINE_DEFINE: &str = "SRGB_PIPELINE";
+
+/// Whether the sRGB pipeline runs. Process-wide rather than per thread, since the
+/// [upload thread](crate::engine::upload) creates textures too.
+static SRGB_PIPELINE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the renderer runs the sRGB pipeline; see the [module documentation](self). Off
+/// until a renderer was built.
+pub fn srgb_pipeline() -> bool {
+    SRGB_PIPELINE.load(Ordering::Relaxed)
+}
+
+/// Switches the pipeline, enabling or disabling `GL_FRAMEBUFFER_SRGB` in the current
+/// context. Textures, targets and programs created earlier keep their format.
+pub(crate) fn set_srgb_pipeline(enabled: bool) {
+    SRGB_PIPELINE.store(enabled, Ordering::Relaxed);
+    unsafe {
+        if enabled {
+            gl::Enable(gl::FRAMEBUFFER_SRGB);
+        } else {
+            gl::Disable(gl::FRAMEBUFFER_SRGB);
+        }
+    }
+}
+
+/// `color` as written to the framebuffer by shaders that don't encode it themselves: linear
+/// in the sRGB pipeline, where the hardware encodes it, else sRGB-encoded.
+///
+/// ```
+/// # use rustge::engine::{color_space::output_color, math::color::Color};
+/// // No renderer was built, so the legacy pipeline runs
+/// assert_eq!(output_color(Color::srgb(0.5, 0.5, 0.5)), Color::srgb(0.5, 0.5, 0.5).to_srgb());
+/// ```
+pub fn output_color(color: Color) -> [f32; 4] {
+    if srgb_pipeline() {
+        [color.r, color.g, color.b, color.a]
+    } else {
+        color.to_srgb()
+    }
+}
+
+/// Internal format of 8-bit color textures and render targets in the current pipeline.
+pub(crate) fn color_texture_format() -> GLenum {
+    if srgb_pipeline() { gl::SRGB8_ALPHA8 } else { gl::RGBA8 }
+}
+
+/// `source` with [`SRGB_PIPELINE_DEFINE`] defined after its `#version` line if the sRGB
+/// pipeline runs. A `#line` directive keeps the driver's error line numbers matching the
+/// source.
+pub(crate) fn with_pipeline_define(source: &str) -> String {
+    if !srgb_pipeline() {
+        return source.to_string();
+    }
+    // `#version` must stay first; sources without one get the define at the top
+    let (offset, line) = match source.lines().position(|text| text.trim_start().starts_with("#version")) {
+        Some(index) => (source.split_inclusive('\n').take(index + 1).map(str::len).sum(), index + 2),
+        None => (0, 1),
+    };
+    let newline = if offset > 0 && !source[..offset].ends_with('\n') { "\n" } else { "" };
+    format!("{}{newline}#define {SRGB_PIPELINE_DEFINE}\n#line {line}\n{}", &source[..offset], &source[offset..])
+}
@@ -0,0 +1,311 @@
+//! Immediate-mode debug drawing.
+//!
+//! [`DebugDraw`] collects world-space lines and labels during a frame. The renderer owns one
+//! (see [`Renderer::debug_draw`](crate::engine::renderer::Renderer::debug_draw)), draws it
+//! after the scene and clears it, so shapes only have to be re-submitted every frame they
+//! should stay visible.
+//!
+//! ```no_run
+//! # use rustge::engine::{math::color::Color, renderer::Renderer};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! renderer.on_update(|renderer, _| {
+//!     let debug = renderer.debug_draw();
+//!     debug.line([0.0, 0.0, 0.0], [0.0, 2.0, 0.0], Color::GREEN);
+//!     debug.label([0.0, 2.0, 0.0], Color::WHITE, "spawn");
+//! });
+//! ```
+//!
+//! Code without access to the renderer (systems deep in the update, culling code, node
+//! callbacks) can use the free functions [`draw_line`], [`draw_aabb`], [`draw_sphere`] and
+//! [`draw_axes`] instead. They collect into a per-thread batch that is drawn together with
+//! the renderer's layer, in the same single line buffer upload:
+//!
+//! ```no_run
+//! # use rustge::engine::{debug, math::{aabb::Aabb, color::Color, matrixfuncs::IDENTITY_MATRIX}};
+//! debug::draw_aabb(&Aabb::new([-1.0; 3], [1.0; 3]), Color::GREEN);
+//! debug::draw_sphere([0.0, 3.0, 0.0], 0.5, Color::RED);
+//! debug::draw_axes(&IDENTITY_MATRIX, 2.0);
+//! ```
+
+use std::cell::{OnceCell, RefCell};
+use gl::types::{GLsizei, GLsizeiptr, GLuint};
+use crate::engine::camera::Camera;
+use crate::engine::color_space::output_color;
+use crate::engine::debug::normals::LineVertex;
+use crate::engine::debug::text::{text_width, TextBatch, GLYPH_HEIGHT};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::{transform_point, IDENTITY_MATRIX};
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{record_draw_call, release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Segments per circle of [`DebugDraw::sphere`].
+const SPHERE_SEGMENTS: usize = 32;
+
+thread_local! {
+    /// Shapes submitted through the free functions, picked up by the next [`DebugDraw::draw`].
+    static IMMEDIATE: RefCell<Vec<LineVertex>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A label anchored to a world-space point.
+#[derive(Clone, Debug)]
+struct Label {
+    position: [f32; 3],
+    color: Color,
+    text: String,
+}
+
+/// World-space lines and labels collected for one frame.
+#[derive(Debug)]
+pub struct DebugDraw {
+    /// Whether lines are hidden behind scene geometry. Off by default, so debug shapes are
+    /// always visible.
+    pub depth_test: bool,
+
+    /// Font scale of labels.
+    pub label_scale: f32,
+
+    lines: Vec<LineVertex>,
+    labels: Vec<Label>,
+    text: TextBatch,
+    buffers: OnceCell<LineBuffers>,
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugDraw {
+    /// An empty debug layer. GL resources are created on the first draw.
+    pub fn new() -> Self {
+        Self {
+            depth_test: false,
+            label_scale: 2.0,
+            lines: Vec::new(),
+            labels: Vec::new(),
+            text: TextBatch::new(),
+            buffers: OnceCell::new(),
+        }
+    }
+
+    /// Returns `true` if nothing has been submitted since the last draw, either to this
+    /// layer or through the free functions.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.labels.is_empty() && IMMEDIATE.with_borrow(Vec::is_empty)
+    }
+
+    /// Adds a line segment from `a` to `b`.
+    pub fn line(&mut self, a: [f32; 3], b: [f32; 3], color: Color) {
+        let color = output_color(color);
+        self.lines.push(LineVertex { position: a, color });
+        self.lines.push(LineVertex { position: b, color });
+    }
+
+    /// Adds a connected line through `points`.
+    pub fn polyline(&mut self, points: &[[f32; 3]], color: Color) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+    }
+
+    /// Adds an axis-aligned cross of half-size `size` marking `point`.
+    pub fn point(&mut self, point: [f32; 3], size: f32, color: Color) {
+        let [x, y, z] = point;
+        self.line([x - size, y, z], [x + size, y, z], color);
+        self.line([x, y - size, z], [x, y + size, z], color);
+        self.line([x, y, z - size], [x, y, z + size], color);
+    }
+
+    /// Adds the twelve edges of `aabb`.
+    pub fn aabb(&mut self, aabb: &Aabb, color: Color) {
+        for [a, b] in aabb_edges(aabb) {
+            self.line(a, b, color);
+        }
+    }
+
+    /// Adds a wire sphere: one circle around each axis.
+    pub fn sphere(&mut self, center: [f32; 3], radius: f32, color: Color) {
+        for [a, b] in sphere_segments(center, radius) {
+            self.line(a, b, color);
+        }
+    }
+
+    /// Adds the local X, Y and Z axes of the column-major `matrix` in red, green and blue,
+    /// `size` units long (before the matrix's own scale). Useful to check a node's world
+    /// transform.
+    pub fn axes(&mut self, matrix: &[f32; 16], size: f32) {
+        for (a, b, color) in axes_lines(matrix, size) {
+            self.line(a, b, color);
+        }
+    }
+
+    /// Adds text centered on the screen projection of `position`. Labels behind the camera
+    /// are skipped.
+    pub fn label(&mut self, position: [f32; 3], color: Color, text: &str) {
+        self.labels.push(Label { position, color, text: text.to_string() });
+    }
+
+    /// Discards everything submitted this frame, including through the free functions.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.labels.clear();
+        IMMEDIATE.with_borrow_mut(Vec::clear);
+    }
+
+    /// Draws everything submitted for `camera` on a `screen_size` viewport, including the
+    /// shapes submitted through the free functions, then clears.
+    pub fn draw(&mut self, camera: &Camera, screen_size: [u32; 2]) {
+        IMMEDIATE.with_borrow_mut(|immediate| self.lines.append(immediate));
+        if !self.lines.is_empty() {
+            self.draw_lines(camera);
+        }
+
+        for label in &self.labels {
+            if let Some([x, y]) = camera.world_to_screen(label.position, screen_size) {
+                let width = text_width(&label.text, self.label_scale);
+                let height = GLYPH_HEIGHT as f32 * self.label_scale;
+                self.text.rect(x - width / 2.0 - 2.0, y - height / 2.0 - 2.0, width + 4.0, height + 4.0, Color::BLACK.with_alpha(0.6));
+                self.text.text(x - width / 2.0, y - height / 2.0, self.label_scale, label.color, &label.text);
+            }
+        }
+        self.text.draw(screen_size);
+
+        self.clear();
+    }
+
+    fn draw_lines(&mut self, camera: &Camera) {
+        let buffers = self.buffers.get_or_init(LineBuffers::new);
+        let shader = builtin_program(
+            "debug_lines",
+            include_str!("../shaders/debug_lines.vert"),
+            include_str!("../shaders/debug_lines.frag"),
+        );
+        shader.use_program();
+        shader.set_uniform_matrix4("u_model", &IDENTITY_MATRIX);
+        shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+
+        let bytes = std::mem::size_of_val(self.lines.as_slice());
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::DepthFunc(if self.depth_test { gl::LEQUAL } else { gl::ALWAYS });
+            gl::DepthMask(gl::FALSE);
+
+            gl::BindVertexArray(buffers.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffers.vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, self.lines.as_ptr() as *const _, gl::STREAM_DRAW);
+            gl::DrawArrays(gl::LINES, 0, self.lines.len() as GLsizei);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+        }
+        track_gpu_allocation(GpuResourceKind::VertexBuffer, buffers.vbo, bytes, "debug draw lines");
+        record_draw_call(gl::LINES, self.lines.len());
+    }
+}
+
+/// Queues a line from `a` to `b` for the current frame's debug pass.
+pub fn draw_line(a: [f32; 3], b: [f32; 3], color: Color) {
+    push_immediate([[a, b]], color);
+}
+
+/// Queues the edges of `aabb` for the current frame's debug pass.
+pub fn draw_aabb(aabb: &Aabb, color: Color) {
+    push_immediate(aabb_edges(aabb), color);
+}
+
+/// Queues a wire sphere for the current frame's debug pass.
+pub fn draw_sphere(center: [f32; 3], radius: f32, color: Color) {
+    push_immediate(sphere_segments(center, radius), color);
+}
+
+/// Queues the red, green and blue X, Y and Z axes of `matrix`, `size` units long, for the
+/// current frame's debug pass.
+pub fn draw_axes(matrix: &[f32; 16], size: f32) {
+    for (a, b, color) in axes_lines(matrix, size) {
+        push_immediate([[a, b]], color);
+    }
+}
+
+fn push_immediate(segments: impl IntoIterator<Item = [[f32; 3]; 2]>, color: Color) {
+    let color = output_color(color);
+    IMMEDIATE.with_borrow_mut(|lines| {
+        for [a, b] in segments {
+            lines.push(LineVertex { position: a, color });
+            lines.push(LineVertex { position: b, color });
+        }
+    });
+}
+
+/// The twelve edges of a box, as pairs of corners.
+fn aabb_edges(aabb: &Aabb) -> [[[f32; 3]; 2]; 12] {
+    // Corner `i` has max x, y, z where bits 0, 1, 2 of `i` are set; edges join corners one bit apart
+    const EDGES: [[usize; 2]; 12] =
+        [[0, 1], [2, 3], [4, 5], [6, 7], [0, 2], [1, 3], [4, 6], [5, 7], [0, 4], [1, 5], [2, 6], [3, 7]];
+    let corners = aabb.corners();
+    EDGES.map(|[a, b]| [corners[a], corners[b]])
+}
+
+/// Segments of three circles around `center`, one in each axis plane.
+fn sphere_segments(center: [f32; 3], radius: f32) -> impl Iterator<Item = [[f32; 3]; 2]> {
+    let point = move |plane: usize, step: usize| {
+        let angle = step as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+        let (u, v) = ((plane + 1) % 3, (plane + 2) % 3);
+        let mut point = center;
+        point[u] += angle.cos() * radius;
+        point[v] += angle.sin() * radius;
+        point
+    };
+    (0..3).flat_map(move |plane| (0..SPHERE_SEGMENTS).map(move |step| [point(plane, step), point(plane, step + 1)]))
+}
+
+/// Origin-to-tip lines of the X, Y and Z axes of `matrix` with their colors.
+fn axes_lines(matrix: &[f32; 16], size: f32) -> [([f32; 3], [f32; 3], Color); 3] {
+    let origin = transform_point(matrix, [0.0; 3]);
+    let colors = [Color::RED, Color::GREEN, Color::BLUE];
+    std::array::from_fn(|axis| {
+        let mut tip = [0.0; 3];
+        tip[axis] = size;
+        (origin, transform_point(matrix, tip), colors[axis])
+    })
+}
+
+/// Streaming vertex buffer for the debug lines.
+#[derive(Debug)]
+struct LineBuffers {
+    vao: GLuint,
+    vbo: GLuint,
+}
+
+impl LineBuffers {
+    fn new() -> Self {
+        let stride = std::mem::size_of::<LineVertex>() as GLsizei;
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(LineVertex, position) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(LineVertex, color) as *const _);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        Self { vao, vbo }
+    }
+}
+
+impl Drop for LineBuffers {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
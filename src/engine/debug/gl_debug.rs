@@ -0,0 +1,233 @@
+//! OpenGL debug output and error checks.
+//!
+//! OpenGL reports misuse through `glGetError` only, and only when asked, so a bad call
+//! usually shows up much later as a black screen. With a debug context (see
+//! [`RendererBuilder::gl_debug`](crate::engine::renderer::RendererBuilder::gl_debug), on by
+//! default in debug builds), the driver calls back into the engine for each problem as it
+//! happens, and the message is logged with its source and type. Objects the engine creates
+//! carry [labels](label_object) (mesh, texture and shader names), which drivers quote in
+//! their messages. Messages are delivered synchronously, so with [`GlDebug::Abort`] the
+//! printed backtrace points at the offending call.
+//!
+//! Drivers without `KHR_debug` (OpenGL 4.3) can still be checked call by call: wrap calls in
+//! [`gl_check!`](crate::gl_check), which reads `glGetError` after the call while
+//! [call checks](set_call_checks) are on in debug builds, and compiles to the bare call in
+//! release builds.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::debug::gl_debug::{self, GlDebug};
+//! # use rustge::engine::renderer::Renderer;
+//! let renderer = Renderer::builder().gl_debug(GlDebug::Abort).build();
+//!
+//! gl_debug::set_call_checks(true);
+//! let mut texture = 0;
+//! rustge::gl_check!(unsafe { gl::GenTextures(1, &mut texture) });
+//! ```
+
+use std::backtrace::Backtrace;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::ffi::{c_void, CStr};
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+/// What the renderer does with the driver's debug output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlDebug {
+    /// No debug context: the driver reports nothing, and runs fastest.
+    Off,
+    /// Log errors and warnings, each distinct message once.
+    Log,
+    /// Log warnings, and abort the process with a backtrace on the first error.
+    Abort,
+}
+
+impl Default for GlDebug {
+    /// [`Log`](GlDebug::Log) in debug builds, [`Off`](GlDebug::Off) in release builds.
+    fn default() -> Self {
+        if cfg!(debug_assertions) { GlDebug::Log } else { GlDebug::Off }
+    }
+}
+
+thread_local! {
+    /// Messages logged so far, so a mistake made every frame is reported once.
+    static LOGGED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    static CALL_CHECKS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Boxed into the callback's user parameter, which lives as long as the context.
+struct CallbackState {
+    mode: GlDebug,
+}
+
+/// Registers the debug callback on the current context. Returns `false`, doing nothing, if
+/// `mode` is [`Off`](GlDebug::Off) or the driver lacks `KHR_debug`.
+pub(crate) fn install(mode: GlDebug) -> bool {
+    if mode == GlDebug::Off || !gl::DebugMessageCallback::is_loaded() || !gl::DebugMessageControl::is_loaded() {
+        return false;
+    }
+    // Leaked deliberately: the driver may call back until the context is gone
+    let state = Box::into_raw(Box::new(CallbackState { mode }));
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_callback), state as *const c_void);
+        // Notifications (buffer placement and the like) are chatter
+        gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, gl::DEBUG_SEVERITY_NOTIFICATION, 0, std::ptr::null(), gl::FALSE);
+    }
+    true
+}
+
+extern "system" fn debug_callback(
+    source: GLenum,
+    kind: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    user: *mut c_void,
+) {
+    // Nothing here may unwind into the driver
+    let message = unsafe {
+        if length >= 0 {
+            String::from_utf8_lossy(std::slice::from_raw_parts(message.cast::<u8>(), length as usize)).into_owned()
+        } else {
+            CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    };
+    let mode = unsafe { (*(user as *const CallbackState)).mode };
+    let text = format!("{} {} {id} from {}: {}", severity_name(severity), type_name(kind), source_name(source), message.trim_end());
+
+    if kind == gl::DEBUG_TYPE_ERROR && mode == GlDebug::Abort {
+        eprintln!("GL error: {text}\n{}", Backtrace::force_capture());
+        std::process::abort();
+    }
+    let first = LOGGED.try_with(|logged| logged.borrow_mut().insert(text.clone())).unwrap_or(false);
+    if !first {
+        return;
+    }
+    if kind == gl::DEBUG_TYPE_ERROR {
+        eprintln!("GL error: {text}");
+    } else {
+        eprintln!("Warning: GL {text}");
+    }
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "API",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other source",
+    }
+}
+
+fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability issue",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance issue",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "message",
+    }
+}
+
+fn severity_name(severity: GLenum) -> &'static str {
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => "high severity",
+        gl::DEBUG_SEVERITY_MEDIUM => "medium severity",
+        gl::DEBUG_SEVERITY_LOW => "low severity",
+        _ => "informational",
+    }
+}
+
+/// Attaches `label` to the GL object `id` in namespace `identifier` (`gl::TEXTURE`,
+/// `gl::BUFFER`, `gl::VERTEX_ARRAY`, `gl::PROGRAM`, ...), so debug messages and tools like
+/// RenderDoc show it by name. Does nothing for empty labels, or when the driver doesn't
+/// expose `glObjectLabel` (OpenGL before 4.3 without `KHR_debug`).
+pub fn label_object(identifier: GLenum, id: GLuint, label: &str) {
+    if label.is_empty() || id == 0 || !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(identifier, id, label.len() as GLsizei, label.as_ptr().cast());
+    }
+}
+
+/// Turns the `glGetError` check after each [`gl_check!`](crate::gl_check) call on or off
+/// (off by default). Has no effect in release builds, where the checks aren't compiled in.
+pub fn set_call_checks(enabled: bool) {
+    CALL_CHECKS.with(|checks| checks.set(enabled));
+}
+
+/// Whether [`gl_check!`](crate::gl_check) calls check for errors.
+pub fn call_checks() -> bool {
+    cfg!(debug_assertions) && CALL_CHECKS.with(Cell::get)
+}
+
+/// Drains the GL error flags, returning the errors raised since the last check.
+pub fn take_errors() -> Vec<GLenum> {
+    let mut errors = Vec::new();
+    loop {
+        let error = unsafe { gl::GetError() };
+        // Without a context, glGetError keeps failing; don't spin
+        if error == gl::NO_ERROR || errors.len() >= 16 {
+            return errors;
+        }
+        errors.push(error);
+    }
+}
+
+/// The name of GL error code `error`, like `"GL_INVALID_ENUM"`.
+pub fn error_name(error: GLenum) -> &'static str {
+    match error {
+        gl::INVALID_ENUM => "GL_INVALID_ENUM",
+        gl::INVALID_VALUE => "GL_INVALID_VALUE",
+        gl::INVALID_OPERATION => "GL_INVALID_OPERATION",
+        gl::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+        gl::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+        gl::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+        gl::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+        _ => "unknown GL error",
+    }
+}
+
+/// Panics naming `call` and its location if GL errors were raised and call checks are on;
+/// used by [`gl_check!`](crate::gl_check).
+///
+/// # Panics
+/// Panics if call checks are on and the GL error flags are set.
+#[doc(hidden)]
+pub fn check_call(call: &str, file: &str, line: u32) {
+    if !call_checks() {
+        return;
+    }
+    let errors = take_errors();
+    if !errors.is_empty() {
+        let names: Vec<&str> = errors.into_iter().map(error_name).collect();
+        panic!("{} after `{call}` at {file}:{line}", names.join(", "));
+    }
+}
+
+/// Evaluates a GL call (or any expression) and, in debug builds while
+/// [call checks](crate::engine::debug::gl_debug::set_call_checks) are on, panics if it raised
+/// a GL error, naming the call and where it was made. In release builds it is just the call.
+///
+/// # Example
+/// ```no_run
+/// # let texture = 0;
+/// rustge::gl_check!(unsafe { gl::BindTexture(gl::TEXTURE_2D, texture) });
+/// ```
+#[macro_export]
+macro_rules! gl_check {
+    ($call:expr) => {{
+        let result = $call;
+        #[cfg(debug_assertions)]
+        $crate::engine::debug::gl_debug::check_call(stringify!($call), file!(), line!());
+        result
+    }};
+}
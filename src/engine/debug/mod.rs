@@ -0,0 +1,8 @@
+pub mod normals;
+pub mod text;
+pub mod pass_overlay;
+pub mod profiler_overlay;
+pub mod draw;
+pub mod gl_debug;
+
+pub use draw::{draw_aabb, draw_axes, draw_line, draw_sphere};
@@ -0,0 +1,253 @@
+//! Vertex normal / tangent visualization.
+//!
+//! Attach a [`NormalsDebug`] to an object with
+//! [`Object3D::set_debug_normals`](crate::engine::object3d::Object3D::set_debug_normals)
+//! to draw a short line from every vertex along its normal (blue), tangent (red) and
+//! bitangent (green). This makes inverted normals, broken smoothing and bad tangent
+//! frames obvious at a glance after importing or procedurally generating a mesh.
+//!
+//! The lines are generated on the CPU from the object's [`Geometry`] and uploaded once;
+//! they are rebuilt automatically when the geometry or the settings change.
+
+use std::cell::RefCell;
+use gl::types::{GLboolean, GLint, GLsizei, GLsizeiptr, GLuint};
+use crate::engine::camera::Camera;
+use crate::engine::color_space::output_color;
+use crate::engine::math::color::Color;
+use crate::engine::math::vec::{cross, dot, normalize, scale, sub};
+use crate::engine::object3d::Geometry;
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{record_draw_call, release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Color of normal lines.
+pub const NORMAL_COLOR: Color = Color::linear_rgb(0.033, 0.133, 1.0);
+/// Color of tangent lines.
+pub const TANGENT_COLOR: Color = Color::linear_rgb(1.0, 0.051, 0.033);
+/// Color of bitangent lines.
+pub const BITANGENT_COLOR: Color = Color::linear_rgb(0.073, 1.0, 0.073);
+
+/// A colored line endpoint.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    /// Vertex color as uploaded to the GPU, see [`output_color`].
+    pub color: [f32; 4],
+}
+
+/// Per-object settings for the normals debug view.
+#[derive(Debug)]
+pub struct NormalsDebug {
+    /// Length of each line in the object's local units.
+    pub length: f32,
+
+    /// Draw vertex normals.
+    pub show_normals: bool,
+
+    /// Draw tangents and bitangents (derived from the UVs).
+    pub show_tangents: bool,
+
+    /// Uploaded line mesh, built lazily on first draw.
+    lines: RefCell<Option<LineMesh>>,
+}
+
+impl NormalsDebug {
+    /// Shows normals only, with the given line length.
+    pub fn new(length: f32) -> Self {
+        Self { length, show_normals: true, show_tangents: false, lines: RefCell::new(None) }
+    }
+
+    /// Shows normals, tangents and bitangents, with the given line length.
+    pub fn with_tangents(length: f32) -> Self {
+        Self { show_tangents: true, ..Self::new(length) }
+    }
+
+    /// Drops the uploaded lines so they are regenerated from the geometry on the next draw.
+    pub fn invalidate(&mut self) {
+        *self.lines.get_mut() = None;
+    }
+
+    /// Draws the debug lines for `geometry`, transformed by `model`. The GL state the pass
+    /// changes is restored afterwards.
+    pub fn draw(&self, geometry: &Geometry, model: &[f32; 16], camera: &Camera) {
+        let settings = (self.length, self.show_normals, self.show_tangents);
+        let mut lines = self.lines.borrow_mut();
+        // Toggling a view changes which lines exist, so lines built for other settings go
+        if lines.as_ref().is_some_and(|lines| lines.settings != settings) {
+            *lines = None;
+        }
+        let lines = lines.get_or_insert_with(|| {
+            LineMesh::new(&normal_lines(geometry, self.length, self.show_normals, self.show_tangents), settings)
+        });
+        if lines.vertex_count == 0 {
+            return;
+        }
+
+        let shader = builtin_program(
+            "debug_lines",
+            include_str!("../shaders/debug_lines.vert"),
+            include_str!("../shaders/debug_lines.frag"),
+        );
+        shader.use_program();
+        shader.set_uniform_matrix4("u_model", model);
+        shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+
+        let (mut previous_depth_func, mut previous_depth_mask): (GLint, GLboolean) = (0, 0);
+        unsafe {
+            let blend_enabled = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut previous_depth_func);
+            gl::GetBooleanv(gl::DEPTH_WRITEMASK, &mut previous_depth_mask);
+
+            gl::Disable(gl::BLEND);
+            gl::DepthFunc(gl::LESS);
+            gl::DepthMask(gl::TRUE);
+            gl::BindVertexArray(lines.vao);
+            gl::DrawArrays(gl::LINES, 0, lines.vertex_count as GLsizei);
+            gl::BindVertexArray(0);
+
+            if blend_enabled {
+                gl::Enable(gl::BLEND);
+            }
+            gl::DepthFunc(previous_depth_func as GLuint);
+            gl::DepthMask(previous_depth_mask);
+        }
+        record_draw_call(gl::LINES, lines.vertex_count);
+    }
+}
+
+/// Builds a line list visualizing the vertex frames of `geometry`.
+///
+/// Each enabled direction contributes two [`LineVertex`]es per mesh vertex. Tangents follow the
+/// direction of increasing `u`, bitangents increasing `v`, orthogonalized against the normal.
+pub fn normal_lines(geometry: &Geometry, length: f32, normals: bool, tangents: bool) -> Vec<LineVertex> {
+    let mut lines = Vec::new();
+    let frames = if tangents { vertex_tangents(geometry) } else { Vec::new() };
+
+    for (i, vertex) in geometry.vertices.iter().enumerate() {
+        let origin = vertex.position;
+        let mut push = |direction: [f32; 3], color: Color| {
+            let color = output_color(color);
+            lines.push(LineVertex { position: origin, color });
+            lines.push(LineVertex {
+                position: [
+                    origin[0] + direction[0] * length,
+                    origin[1] + direction[1] * length,
+                    origin[2] + direction[2] * length,
+                ],
+                color,
+            });
+        };
+
+        if normals {
+            push(vertex.normal, NORMAL_COLOR);
+        }
+        if tangents {
+            let (tangent, bitangent) = frames[i];
+            push(tangent, TANGENT_COLOR);
+            push(bitangent, BITANGENT_COLOR);
+        }
+    }
+
+    lines
+}
+
+/// Computes a per-vertex (tangent, bitangent) pair from triangle UV gradients,
+/// accumulated over adjacent triangles and orthogonalized against the vertex normal.
+pub fn vertex_tangents(geometry: &Geometry) -> Vec<([f32; 3], [f32; 3])> {
+    let mut tangents = vec![[0.0f32; 3]; geometry.vertices.len()];
+    let mut bitangents = vec![[0.0f32; 3]; geometry.vertices.len()];
+
+    for t in 0..geometry.indices.len() / 3 {
+        let [a, b, c] = [0, 1, 2].map(|k| geometry.indices.get(t * 3 + k) as usize);
+        let (va, vb, vc) = (&geometry.vertices[a], &geometry.vertices[b], &geometry.vertices[c]);
+
+        let e1 = sub(vb.position, va.position);
+        let e2 = sub(vc.position, va.position);
+        let (du1, dv1) = (vb.uv[0] - va.uv[0], vb.uv[1] - va.uv[1]);
+        let (du2, dv2) = (vc.uv[0] - va.uv[0], vc.uv[1] - va.uv[1]);
+
+        let det = du1 * dv2 - du2 * dv1;
+        if det.abs() < 1e-12 {
+            continue; // degenerate UVs, no meaningful tangent
+        }
+        let r = 1.0 / det;
+        let tangent = [
+            (e1[0] * dv2 - e2[0] * dv1) * r,
+            (e1[1] * dv2 - e2[1] * dv1) * r,
+            (e1[2] * dv2 - e2[2] * dv1) * r,
+        ];
+        let bitangent = [
+            (e2[0] * du1 - e1[0] * du2) * r,
+            (e2[1] * du1 - e1[1] * du2) * r,
+            (e2[2] * du1 - e1[2] * du2) * r,
+        ];
+
+        for &i in &[a, b, c] {
+            for k in 0..3 {
+                tangents[i][k] += tangent[k];
+                bitangents[i][k] += bitangent[k];
+            }
+        }
+    }
+
+    geometry
+        .vertices
+        .iter()
+        .enumerate()
+        .map(|(i, vertex)| {
+            let n = vertex.normal;
+            // Gram-Schmidt: remove the normal component from the tangent
+            let t = tangents[i];
+            let t = normalize(sub(t, scale(n, dot(n, t))));
+            let mut b = cross(n, t);
+            // Keep the handedness implied by the UV layout
+            if dot(b, bitangents[i]) < 0.0 {
+                b = scale(b, -1.0);
+            }
+            (t, b)
+        })
+        .collect()
+}
+
+/// GPU line buffer for the debug view.
+#[derive(Debug)]
+struct LineMesh {
+    vao: GLuint,
+    vbo: GLuint,
+    vertex_count: usize,
+    /// The (length, normals, tangents) settings the lines were built with.
+    settings: (f32, bool, bool),
+}
+
+impl LineMesh {
+    fn new(vertices: &[LineVertex], settings: (f32, bool, bool)) -> Self {
+        let bytes = std::mem::size_of_val(vertices);
+        let stride = std::mem::size_of::<LineVertex>() as GLsizei;
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, vertices.as_ptr() as *const _, gl::STATIC_DRAW);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(LineVertex, position) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(LineVertex, color) as *const _);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::VertexBuffer, vbo, bytes, "debug normal lines");
+        Self { vao, vbo, vertex_count: vertices.len(), settings }
+    }
+}
+
+impl Drop for LineMesh {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! On-screen table of the frame's render passes.
+//!
+//! Lists every pass recorded by the [`FrameGraph`] in execution order with its render
+//! target, attachment size and CPU/GPU time, so the cost of individual passes can be read
+//! without an external GPU profiler.
+
+use crate::engine::debug::text::{text_width, TextBatch, LINE_HEIGHT};
+use crate::engine::frame_graph::{FrameGraph, PassTiming};
+use crate::engine::math::color::Color;
+use std::time::Duration;
+
+/// Font scale of the overlay text.
+const SCALE: f32 = 2.0;
+
+/// Distance of the panel from the top-left corner and padding inside it, in pixels.
+const MARGIN: f32 = 8.0;
+
+const PANEL_COLOR: Color = Color::linear_rgba(0.0, 0.0, 0.0, 0.7);
+const HEADER_COLOR: Color = Color::linear_rgb(1.0, 0.8, 0.3);
+const ROW_COLOR: Color = Color::WHITE;
+
+/// Queues the pass table for `graph` into `batch`. Draw the batch afterwards.
+///
+/// Each row shows the pass index, name, render target, attachment size in pixels, CPU time
+/// and GPU time in milliseconds (`-` while GPU timing is off or unavailable), followed by
+/// the frame's totals.
+pub fn queue_pass_overlay(batch: &mut TextBatch, graph: &FrameGraph) {
+    let mut table = format!(
+        "{:>2} {:<12} {:<12} {:>9} {:>7} {:>7}\n",
+        "#", "pass", "target", "size", "cpu ms", "gpu ms"
+    );
+    for (index, pass) in graph.passes().iter().enumerate() {
+        table.push_str(&format_row(index, pass));
+        table.push('\n');
+    }
+    let (cpu, gpu) = graph.totals();
+    table.push_str(&format!(
+        "{:>2} {:<12} {:<12} {:>9} {:>7} {:>7}",
+        "", "total", "", "", format_ms(Some(cpu)), format_ms(gpu)
+    ));
+
+    let lines = table.lines().count() as f32;
+    let width = text_width(&table, SCALE) + MARGIN * 2.0;
+    let height = lines * LINE_HEIGHT as f32 * SCALE + MARGIN * 2.0;
+    batch.rect(MARGIN, MARGIN, width, height, PANEL_COLOR);
+
+    let (header, rows) = table.split_once('\n').unwrap_or((&table, ""));
+    batch.text(MARGIN * 2.0, MARGIN * 2.0, SCALE, HEADER_COLOR, header);
+    batch.text(MARGIN * 2.0, MARGIN * 2.0 + LINE_HEIGHT as f32 * SCALE, SCALE, ROW_COLOR, rows);
+}
+
+fn format_row(index: usize, pass: &PassTiming) -> String {
+    let size = format!("{}x{}", pass.target_size[0], pass.target_size[1]);
+    format!(
+        "{:>2} {:<12} {:<12} {:>9} {:>7} {:>7}",
+        index,
+        truncate(&pass.name, 12),
+        truncate(&pass.target, 12),
+        size,
+        format_ms(Some(pass.cpu_time)),
+        format_ms(pass.gpu_time)
+    )
+}
+
+fn format_ms(time: Option<Duration>) -> String {
+    match time {
+        Some(time) => format!("{:.3}", time.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+/// Shortens `text` to at most `max` characters so columns stay aligned.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max - 1).collect();
+        short.push('~');
+        short
+    }
+}
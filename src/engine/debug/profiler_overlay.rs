@@ -0,0 +1,89 @@
+//! On-screen summary of the [`Profiler`]'s latest frame.
+//!
+//! Shows frame time and rate, CPU and GPU time of the render passes, the frame's counters
+//! and the CPU time of every profiled scope, indented by nesting, in the top-right corner so
+//! it can be shown along with the pass table.
+
+use std::time::Duration;
+use crate::engine::debug::text::{text_width, TextBatch, LINE_HEIGHT};
+use crate::engine::math::color::Color;
+use crate::engine::profiler::Profiler;
+
+/// Font scale of the overlay text.
+const SCALE: f32 = 2.0;
+
+/// Distance of the panel from the top-right corner and padding inside it, in pixels.
+const MARGIN: f32 = 8.0;
+
+/// Scope names longer than this are shortened so columns stay aligned.
+const NAME_WIDTH: usize = 20;
+
+const PANEL_COLOR: Color = Color::linear_rgba(0.0, 0.0, 0.0, 0.7);
+const HEADER_COLOR: Color = Color::linear_rgb(1.0, 0.8, 0.3);
+const ROW_COLOR: Color = Color::WHITE;
+
+/// Queues the profiler summary for a window of `window_size` pixels into `batch`. Draw the
+/// batch afterwards. Queues nothing before the first frame was profiled.
+pub fn queue_profiler_overlay(batch: &mut TextBatch, profiler: &Profiler, window_size: [u32; 2]) {
+    let Some(profile) = profiler.latest() else {
+        return;
+    };
+    let counters = &profile.counters;
+    let header = format!(
+        "{:.2} ms ({:.0} fps, max {:.2} ms)",
+        millis(profiler.average_frame_time()),
+        profiler.frames_per_second(),
+        millis(profiler.max_frame_time())
+    );
+    let mut rows = format!(
+        "passes  cpu {} ms  gpu {} ms\n\
+         draws {}  triangles {}\n\
+         programs {}  materials {}  states {}\n\
+         uploads {} ({} KiB)\n",
+        format_ms(Some(profile.pass_cpu_time())),
+        format_ms(profile.pass_gpu_time()),
+        counters.draw_calls,
+        counters.triangles,
+        counters.program_binds,
+        counters.material_binds,
+        counters.state_changes,
+        counters.gpu_uploads,
+        counters.gpu_upload_bytes / 1024
+    );
+    rows.push_str(&format!("{:<width$} {:>7} {:>5}", "scope", "ms", "calls", width = NAME_WIDTH));
+    for scope in &profile.scopes {
+        let name = truncate(&format!("{}{}", "  ".repeat(scope.depth), scope.name), NAME_WIDTH);
+        rows.push_str(&format!("\n{:<width$} {:>7} {:>5}", name, format_ms(Some(scope.time)), scope.calls, width = NAME_WIDTH));
+    }
+
+    let lines = 1.0 + rows.lines().count() as f32;
+    let width = text_width(&header, SCALE).max(text_width(&rows, SCALE)) + MARGIN * 2.0;
+    let height = lines * LINE_HEIGHT as f32 * SCALE + MARGIN * 2.0;
+    let x = (window_size[0] as f32 - width - MARGIN).max(0.0);
+    batch.rect(x, MARGIN, width, height, PANEL_COLOR);
+    batch.text(x + MARGIN, MARGIN * 2.0, SCALE, HEADER_COLOR, &header);
+    batch.text(x + MARGIN, MARGIN * 2.0 + LINE_HEIGHT as f32 * SCALE, SCALE, ROW_COLOR, &rows);
+}
+
+/// `duration` in (fractional) milliseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn format_ms(time: Option<Duration>) -> String {
+    match time {
+        Some(time) => format!("{:.3}", millis(time)),
+        None => "-".to_string(),
+    }
+}
+
+/// Shortens `text` to at most `max` characters.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max - 1).collect();
+        short.push('~');
+        short
+    }
+}
@@ -0,0 +1,699 @@
+//! Screen-space text and rectangles for debug overlays.
+//!
+//! Uses a built-in 5x7 pixel bitmap font covering printable ASCII, so overlays work without
+//! loading any assets. Accented Latin letters draw as their base letter (see
+//! [`fallback_char`]); other scripts need a loaded font. [`TextBatch::font_text`] draws text
+//! shaped through a [`FontChain`], which picks a font per character and shapes each run
+//! with rustybuzz, so any script the fonts cover comes out right. Text is batched into a
+//! [`TextBatch`] during the frame and drawn on top of everything else.
+//!
+//! ```no_run
+//! # use rustge::engine::{debug::text::TextBatch, math::color::Color};
+//! let mut batch = TextBatch::new();
+//! batch.rect(8.0, 8.0, 200.0, 24.0, Color::BLACK.with_alpha(0.6));
+//! batch.text(12.0, 12.0, 2.0, Color::WHITE, "Hello, overlay");
+//! batch.draw([1280, 720]);
+//! ```
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use gl::types::{GLsizei, GLsizeiptr, GLuint};
+use crate::engine::color_space::output_color;
+use crate::engine::font::FontChain;
+use crate::engine::math::color::Color;
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{record_draw_call, release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+use crate::engine::texture::{Texture, TextureFilter};
+
+/// Width of a glyph in font pixels.
+pub const GLYPH_WIDTH: u32 = 5;
+/// Height of a glyph in font pixels.
+pub const GLYPH_HEIGHT: u32 = 7;
+/// Horizontal distance between consecutive characters, in font pixels.
+pub const GLYPH_ADVANCE: u32 = 6;
+/// Vertical distance between lines, in font pixels.
+pub const LINE_HEIGHT: u32 = 9;
+
+/// First character in the font; characters outside `FIRST_CHAR..=LAST_CHAR` draw as `?`.
+const FIRST_CHAR: u8 = b' ';
+const LAST_CHAR: u8 = b'~';
+
+/// Atlas layout: 16 x 6 cells of `GLYPH_ADVANCE x (GLYPH_HEIGHT + 1)` pixels. The last cell
+/// (unused DEL) is filled solid and used for rectangles.
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = 6;
+const CELL_WIDTH: u32 = GLYPH_ADVANCE;
+const CELL_HEIGHT: u32 = GLYPH_HEIGHT + 1;
+const SOLID_CELL: u32 = ATLAS_COLUMNS * ATLAS_ROWS - 1;
+
+/// 5x7 glyphs for ASCII 32..=126. Each row is a 5-bit mask, most significant bit on the left.
+const FONT_5X7: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // '!'
+    [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A], // '#'
+    [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04], // '$'
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // '%'
+    [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D], // '&'
+    [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // '('
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // ')'
+    [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00], // '*'
+    [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08], // ','
+    [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C], // '.'
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // '/'
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E], // '0'
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E], // '1'
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F], // '2'
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E], // '3'
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02], // '4'
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E], // '5'
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E], // '6'
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // '7'
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E], // '8'
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C], // '9'
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00], // ':'
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08], // ';'
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // '<'
+    [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00], // '='
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // '>'
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E], // '@'
+    [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11], // 'A'
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // 'B'
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // 'C'
+    [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C], // 'D'
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // 'E'
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // 'F'
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F], // 'G'
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // 'H'
+    [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // 'I'
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // 'J'
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // 'K'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // 'L'
+    [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11], // 'M'
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // 'N'
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // 'O'
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // 'P'
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // 'Q'
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // 'R'
+    [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E], // 'S'
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // 'T'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // 'U'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // 'V'
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A], // 'W'
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // 'X'
+    [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04], // 'Y'
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // 'Z'
+    [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E], // '['
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // '\\'
+    [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E], // ']'
+    [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F], // '_'
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F], // 'a'
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E], // 'b'
+    [0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E], // 'c'
+    [0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F], // 'd'
+    [0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E], // 'e'
+    [0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08], // 'f'
+    [0x00, 0x0F, 0x11, 0x11, 0x0F, 0x01, 0x0E], // 'g'
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11], // 'h'
+    [0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E], // 'i'
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0C], // 'j'
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12], // 'k'
+    [0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // 'l'
+    [0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11], // 'm'
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11], // 'n'
+    [0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E], // 'o'
+    [0x00, 0x00, 0x1E, 0x11, 0x1E, 0x10, 0x10], // 'p'
+    [0x00, 0x00, 0x0D, 0x13, 0x0F, 0x01, 0x01], // 'q'
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10], // 'r'
+    [0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E], // 's'
+    [0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06], // 't'
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D], // 'u'
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04], // 'v'
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A], // 'w'
+    [0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11], // 'x'
+    [0x00, 0x00, 0x11, 0x11, 0x0F, 0x01, 0x0E], // 'y'
+    [0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F], // 'z'
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // '{'
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // '|'
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // '}'
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00], // '~'
+];
+
+/// A vertex of a text or rectangle quad.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+/// The character whose glyph draws `ch` in the built-in font: `ch` itself if the font has
+/// it, otherwise its base letter for accented Latin letters (`é` draws as `e`), an ASCII
+/// look-alike for typographic punctuation and spaces, and `?` for everything else. Text in
+/// other scripts should be drawn with [`TextBatch::font_text`].
+///
+/// ```
+/// # use rustge::engine::debug::text::fallback_char;
+/// assert_eq!(fallback_char('a'), 'a');
+/// assert_eq!(fallback_char('Ñ'), 'N');
+/// assert_eq!(fallback_char('’'), '\'');
+/// assert_eq!(fallback_char('日'), '?');
+/// ```
+pub fn fallback_char(ch: char) -> char {
+    if (FIRST_CHAR as char..=LAST_CHAR as char).contains(&ch) {
+        return ch;
+    }
+    match ch {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'Ç' | 'Ć' | 'Č' => 'C',
+        'ç' | 'ć' | 'č' => 'c',
+        'Ď' | 'Đ' | 'Ð' => 'D',
+        'ď' | 'đ' | 'ð' => 'd',
+        'È'..='Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'è'..='ë' | 'ē' | 'ė' | 'ę' | 'ě' => 'e',
+        'Ğ' => 'G',
+        'ğ' => 'g',
+        'Ì'..='Ï' | 'Ī' | 'Į' | 'İ' => 'I',
+        'ì'..='ï' | 'ī' | 'į' | 'ı' => 'i',
+        'Ł' | 'Ľ' => 'L',
+        'ł' | 'ľ' => 'l',
+        'Ñ' | 'Ń' | 'Ň' => 'N',
+        'ñ' | 'ń' | 'ň' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ő' => 'O',
+        'ò'..='ö' | 'ø' | 'ō' | 'ő' => 'o',
+        'Ř' => 'R',
+        'ř' => 'r',
+        'Ś' | 'Ş' | 'Š' => 'S',
+        'ś' | 'ş' | 'š' | 'ß' => 's',
+        'Ť' | 'Ţ' => 'T',
+        'ť' | 'ţ' => 't',
+        'Ù'..='Ü' | 'Ū' | 'Ů' | 'Ű' => 'U',
+        'ù'..='ü' | 'ū' | 'ů' | 'ű' => 'u',
+        'Ý' | 'Ÿ' => 'Y',
+        'ý' | 'ÿ' => 'y',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        'ź' | 'ż' | 'ž' => 'z',
+        '‘' | '’' | '‚' | '´' => '\'',
+        '“' | '”' | '„' | '«' | '»' => '"',
+        '‐'..='—' | '−' => '-',
+        '…' | '·' => '.',
+        '¡' => '!',
+        '¿' => '?',
+        '\u{A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' => ' ',
+        _ => '?',
+    }
+}
+
+/// Width in screen pixels of `text` drawn at `scale` (the widest line, for multi-line text).
+pub fn text_width(text: &str, scale: f32) -> f32 {
+    let longest = text.lines().map(|line| line.chars().count()).max().unwrap_or(0);
+    longest as f32 * GLYPH_ADVANCE as f32 * scale
+}
+
+/// Texture sampled by a range of a batch's vertices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Atlas {
+    /// The built-in bitmap font.
+    Bitmap,
+
+    /// A page of the glyph atlas of loaded fonts.
+    Glyphs(usize),
+}
+
+/// Accumulates text and filled rectangles in screen pixels (origin top-left) and draws them
+/// in queue order, with one call per switch between the built-in font and glyph atlas pages.
+#[derive(Debug, Default)]
+pub struct TextBatch {
+    vertices: Vec<TextVertex>,
+
+    /// First vertex of each range sampling one atlas, in order.
+    segments: Vec<(usize, Atlas)>,
+
+    buffers: OnceCell<TextBuffers>,
+}
+
+impl TextBatch {
+    /// An empty batch. GL resources are created on the first draw.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `true` if nothing has been queued since the last draw.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Queues `text` with its top-left corner at (`x`, `y`), each font pixel covering
+    /// `scale` screen pixels. `\n` starts a new line.
+    pub fn text(&mut self, x: f32, y: f32, scale: f32, color: Color, text: &str) {
+        self.use_atlas(Atlas::Bitmap);
+        let color = output_color(color);
+        let (w, h) = (GLYPH_WIDTH as f32 * scale, GLYPH_HEIGHT as f32 * scale);
+        let mut pen_y = y;
+        for line in text.lines() {
+            let mut pen_x = x;
+            for ch in line.chars() {
+                let ch = fallback_char(ch);
+                if ch != ' ' {
+                    let cell = ch as u32 - FIRST_CHAR as u32;
+                    let (u0, v0) = cell_uv(cell);
+                    let u1 = u0 + GLYPH_WIDTH as f32 / (ATLAS_COLUMNS * CELL_WIDTH) as f32;
+                    let v1 = v0 + GLYPH_HEIGHT as f32 / (ATLAS_ROWS * CELL_HEIGHT) as f32;
+                    self.quad([pen_x, pen_y, pen_x + w, pen_y + h], [u0, v0, u1, v1], color);
+                }
+                pen_x += GLYPH_ADVANCE as f32 * scale;
+            }
+            pen_y += LINE_HEIGHT as f32 * scale;
+        }
+    }
+
+    /// Queues `text` shaped through `fonts` at `size` pixels per em, with its top-left corner
+    /// at (`x`, `y`). `\n` starts a new line. Glyphs are rasterized on first use.
+    ///
+    /// ```no_run
+    /// # use std::rc::Rc;
+    /// # use rustge::engine::{debug::text::TextBatch, font::{Font, FontChain}, math::color::Color};
+    /// let fonts = FontChain::new(vec![
+    ///     Rc::new(Font::load("fonts/NotoSans-Regular.ttf").unwrap()),
+    ///     Rc::new(Font::load("fonts/NotoSansSC-Regular.otf").unwrap()),
+    /// ]);
+    /// let mut batch = TextBatch::new();
+    /// batch.font_text(&fonts, 12.0, 12.0, 20.0, Color::WHITE, "Привет, 世界");
+    /// batch.draw([1280, 720]);
+    /// ```
+    pub fn font_text(&mut self, fonts: &FontChain, x: f32, y: f32, size: f32, color: Color, text: &str) {
+        let color = output_color(color);
+        let shaped = fonts.shape(text, size);
+        GLYPH_ATLAS.with_borrow_mut(|atlas| {
+            for glyph in &shaped.glyphs {
+                let Some(placed) = atlas.glyph(fonts, glyph.font, glyph.glyph, size) else {
+                    continue;
+                };
+                // Snap the pen to whole pixels so glyphs sample the atlas texel for texel
+                let left = (x + glyph.position[0]).round() + placed.left as f32;
+                let top = (y + glyph.position[1]).round() - placed.top as f32;
+                let [u, v, w, h] = placed.rect.map(|value| value as f32);
+                let uv = [u, v, u + w, v + h].map(|value| value / GLYPH_PAGE_SIZE as f32);
+                self.use_atlas(Atlas::Glyphs(placed.page));
+                self.quad([left, top, left + w, top + h], uv, color);
+                atlas.pages[placed.page].queued += 1;
+            }
+        });
+    }
+
+    /// Queues a filled rectangle.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.use_atlas(Atlas::Bitmap);
+        let (u0, v0) = cell_uv(SOLID_CELL);
+        // Sample the middle of the solid cell so filtering never reaches a neighbour
+        let texel = [0.5 / (ATLAS_COLUMNS * CELL_WIDTH) as f32, 0.5 / (ATLAS_ROWS * CELL_HEIGHT) as f32];
+        let uv = [u0 + texel[0] * 2.0, v0 + texel[1] * 2.0, u0 + texel[0] * 4.0, v0 + texel[1] * 4.0];
+        self.quad([x, y, x + width, y + height], uv, output_color(color));
+    }
+
+    /// Starts a vertex range sampling `atlas`, unless the last one already does.
+    fn use_atlas(&mut self, atlas: Atlas) {
+        if self.segments.last().is_none_or(|&(_, last)| last != atlas) {
+            self.segments.push((self.vertices.len(), atlas));
+        }
+    }
+
+    /// Vertex ranges with the atlas each samples, in queue order.
+    fn ranges(&self) -> impl Iterator<Item = (std::ops::Range<usize>, Atlas)> + '_ {
+        self.segments.iter().enumerate().map(|(index, &(start, atlas))| {
+            let end = self.segments.get(index + 1).map_or(self.vertices.len(), |&(next, _)| next);
+            (start..end, atlas)
+        })
+    }
+
+    /// Forgets everything queued, letting the glyph atlas reuse the pages it sampled.
+    fn clear(&mut self) {
+        if self.segments.iter().any(|&(_, atlas)| atlas != Atlas::Bitmap) {
+            // The atlas is gone if the thread is shutting down, and with it any pages
+            let _ = GLYPH_ATLAS.try_with(|atlas| {
+                let mut atlas = atlas.borrow_mut();
+                for (range, atlas_page) in self.ranges() {
+                    if let Atlas::Glyphs(page) = atlas_page {
+                        atlas.pages[page].queued -= range.len() / 6;
+                    }
+                }
+            });
+        }
+        self.vertices.clear();
+        self.segments.clear();
+    }
+
+    fn quad(&mut self, rect: [f32; 4], uv: [f32; 4], color: [f32; 4]) {
+        let [x0, y0, x1, y1] = rect;
+        let [u0, v0, u1, v1] = uv;
+        let corner = |x: f32, y: f32, u: f32, v: f32| TextVertex { position: [x, y], uv: [u, v], color };
+        self.vertices.extend_from_slice(&[
+            corner(x0, y0, u0, v0),
+            corner(x0, y1, u0, v1),
+            corner(x1, y1, u1, v1),
+            corner(x0, y0, u0, v0),
+            corner(x1, y1, u1, v1),
+            corner(x1, y0, u1, v0),
+        ]);
+    }
+
+    /// Draws everything queued on top of the current framebuffer, then clears the batch.
+    ///
+    /// `screen_size` is the framebuffer size in pixels. Depth is neither tested nor written.
+    pub fn draw(&mut self, screen_size: [u32; 2]) {
+        if self.vertices.is_empty() {
+            self.segments.clear();
+            return;
+        }
+
+        let buffers = self.buffers.get_or_init(TextBuffers::new);
+        let shader = builtin_program(
+            "text",
+            include_str!("../shaders/text.vert"),
+            include_str!("../shaders/text.frag"),
+        );
+        shader.use_program();
+        shader.set_uniform_vec2("u_screen_size", [screen_size[0].max(1) as f32, screen_size[1].max(1) as f32]);
+        shader.set_sampler("u_font", 0);
+
+        let ranges: Vec<_> = GLYPH_ATLAS.with_borrow_mut(|glyphs| {
+            self.ranges()
+                .map(|(range, atlas)| match atlas {
+                    Atlas::Bitmap => (range, buffers.font.clone()),
+                    Atlas::Glyphs(page) => (range, glyphs.pages[page].texture()),
+                })
+                .collect()
+        });
+
+        let bytes = std::mem::size_of_val(self.vertices.as_slice());
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthFunc(gl::ALWAYS);
+            gl::DepthMask(gl::FALSE);
+
+            gl::BindVertexArray(buffers.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffers.vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, self.vertices.as_ptr() as *const _, gl::STREAM_DRAW);
+            for (range, texture) in &ranges {
+                texture.bind(0);
+                gl::DrawArrays(gl::TRIANGLES, range.start as GLsizei, range.len() as GLsizei);
+                record_draw_call(gl::TRIANGLES, range.len());
+            }
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+        }
+        track_gpu_allocation(GpuResourceKind::VertexBuffer, buffers.vbo, bytes, "debug text");
+
+        self.clear();
+    }
+}
+
+impl Drop for TextBatch {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Top-left UV of an atlas cell.
+fn cell_uv(cell: u32) -> (f32, f32) {
+    let (column, row) = (cell % ATLAS_COLUMNS, cell / ATLAS_COLUMNS);
+    (
+        (column * CELL_WIDTH) as f32 / (ATLAS_COLUMNS * CELL_WIDTH) as f32,
+        (row * CELL_HEIGHT) as f32 / (ATLAS_ROWS * CELL_HEIGHT) as f32,
+    )
+}
+
+/// Rasterizes the font into a white RGBA atlas whose alpha is the glyph coverage.
+/// Rows are stored top to bottom, matching the UVs above (v grows downwards).
+fn font_atlas_pixels() -> (u32, u32, Vec<u8>) {
+    let (width, height) = (ATLAS_COLUMNS * CELL_WIDTH, ATLAS_ROWS * CELL_HEIGHT);
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    let mut set = |x: u32, y: u32| {
+        let i = ((y * width + x) * 4) as usize;
+        pixels[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+    };
+
+    for (cell, glyph) in FONT_5X7.iter().enumerate() {
+        let (column, row) = (cell as u32 % ATLAS_COLUMNS, cell as u32 / ATLAS_COLUMNS);
+        for (y, bits) in glyph.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - x)) != 0 {
+                    set(column * CELL_WIDTH + x, row * CELL_HEIGHT + y as u32);
+                }
+            }
+        }
+    }
+
+    let (column, row) = (SOLID_CELL % ATLAS_COLUMNS, SOLID_CELL / ATLAS_COLUMNS);
+    for y in 0..CELL_HEIGHT {
+        for x in 0..CELL_WIDTH {
+            set(column * CELL_WIDTH + x, row * CELL_HEIGHT + y);
+        }
+    }
+
+    (width, height, pixels)
+}
+
+/// GL objects behind a [`TextBatch`].
+#[derive(Debug)]
+struct TextBuffers {
+    vao: GLuint,
+    vbo: GLuint,
+    font: Rc<Texture>,
+}
+
+impl TextBuffers {
+    fn new() -> Self {
+        let stride = std::mem::size_of::<TextVertex>() as GLsizei;
+        let (mut vao, mut vbo) = (0, 0);
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(TextVertex, position) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(TextVertex, uv) as *const _);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(TextVertex, color) as *const _);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        Self { vao, vbo, font: font_texture() }
+    }
+}
+
+impl Drop for TextBuffers {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+thread_local! {
+    /// The font atlas, shared by every batch on the GL thread.
+    static FONT_TEXTURE: OnceCell<Rc<Texture>> = const { OnceCell::new() };
+}
+
+fn font_texture() -> Rc<Texture> {
+    FONT_TEXTURE.with(|cell| {
+        cell.get_or_init(|| {
+            let (width, height, pixels) = font_atlas_pixels();
+            let texture = Texture::from_rgba8(width, height, &pixels, "debug font atlas");
+            texture.set_filter(TextureFilter::Nearest);
+            Rc::new(texture)
+        })
+        .clone()
+    })
+}
+
+/// Edge length in pixels of a glyph atlas page.
+const GLYPH_PAGE_SIZE: u32 = 1024;
+
+/// Pages the glyph atlas keeps before reusing the least recently used one that no queued
+/// text samples; it only grows past this while every page is waiting to be drawn.
+const GLYPH_PAGE_LIMIT: usize = 4;
+
+/// Glyph sizes are rounded to a quarter pixel, so animated sizes don't rasterize every frame.
+const GLYPH_SIZE_STEPS: f32 = 4.0;
+
+/// Where a rasterized glyph sits in the glyph atlas.
+#[derive(Clone, Copy, Debug)]
+struct AtlasGlyph {
+    page: usize,
+
+    /// Left, top, width and height in page pixels.
+    rect: [u32; 4],
+
+    /// Offset of the bitmap from the pen position: right, and up from the baseline.
+    left: i32,
+    top: i32,
+}
+
+/// A white RGBA image whose alpha is glyph coverage, packed in shelves.
+#[derive(Debug)]
+struct GlyphPage {
+    pixels: Vec<u8>,
+
+    /// Top-left corner of the free space on the current shelf, and the shelf's height.
+    cursor: [u32; 2],
+    shelf_height: u32,
+
+    texture: Option<Rc<Texture>>,
+    dirty: bool,
+
+    /// Glyph quads queued in batches and not drawn yet; the page is only reused at 0.
+    queued: usize,
+
+    /// The atlas' lookup count when a glyph on the page was last used.
+    last_used: u64,
+}
+
+impl GlyphPage {
+    fn new() -> Self {
+        Self {
+            pixels: vec![0; (GLYPH_PAGE_SIZE * GLYPH_PAGE_SIZE * 4) as usize],
+            cursor: [0, 0],
+            shelf_height: 0,
+            texture: None,
+            dirty: false,
+            queued: 0,
+            last_used: 0,
+        }
+    }
+
+    /// Finds room for a `width` x `height` bitmap, or `None` if the page is full.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        // A pixel of space between glyphs keeps filtering from bleeding across
+        let (width, height) = (width + 1, height + 1);
+        if self.cursor[0] + width > GLYPH_PAGE_SIZE {
+            self.cursor = [0, self.cursor[1] + self.shelf_height];
+            self.shelf_height = 0;
+        }
+        if self.cursor[1] + height > GLYPH_PAGE_SIZE {
+            return None;
+        }
+        let position = self.cursor;
+        self.cursor[0] += width;
+        self.shelf_height = self.shelf_height.max(height);
+        Some(position)
+    }
+
+    /// The page's texture, uploading glyphs added since the last call.
+    fn texture(&mut self) -> Rc<Texture> {
+        match &self.texture {
+            Some(texture) if !self.dirty => texture.clone(),
+            _ => {
+                let texture = Texture::from_rgba8(GLYPH_PAGE_SIZE, GLYPH_PAGE_SIZE, &self.pixels, "glyph atlas");
+                texture.set_filter(TextureFilter::Linear);
+                self.dirty = false;
+                self.texture.insert(Rc::new(texture)).clone()
+            }
+        }
+    }
+}
+
+/// Glyphs of loaded fonts rasterized on demand, on pages of [`GLYPH_PAGE_SIZE`] pixels.
+#[derive(Debug, Default)]
+struct GlyphAtlas {
+    pages: Vec<GlyphPage>,
+
+    /// The page new glyphs are added to.
+    current: usize,
+
+    /// Glyphs by font id, glyph index and size in steps; `None` for glyphs with no outline.
+    glyphs: HashMap<(u64, u16, u32), Option<AtlasGlyph>>,
+
+    /// Glyph lookups so far, for finding the least recently used page.
+    lookups: u64,
+}
+
+impl GlyphAtlas {
+    /// The atlas entry for glyph `glyph` of font `font` in `fonts` at `size`, rasterizing
+    /// it on first use. `None` for glyphs with nothing to draw.
+    fn glyph(&mut self, fonts: &FontChain, font: usize, glyph: u16, size: f32) -> Option<AtlasGlyph> {
+        let font = &fonts.fonts()[font];
+        let steps = (size * GLYPH_SIZE_STEPS).round().max(1.0) as u32;
+        let key = (font.id(), glyph, steps);
+        self.lookups += 1;
+        if let Some(&placed) = self.glyphs.get(&key) {
+            if let Some(placed) = placed {
+                self.pages[placed.page].last_used = self.lookups;
+            }
+            return placed;
+        }
+
+        let placed = font.rasterize(glyph, steps as f32 / GLYPH_SIZE_STEPS).and_then(|bitmap| {
+            let (page, [x, y]) = self.allocate(bitmap.width, bitmap.height)?;
+            let pixels = &mut self.pages[page].pixels;
+            for row in 0..bitmap.height {
+                for column in 0..bitmap.width {
+                    let i = (((y + row) * GLYPH_PAGE_SIZE + x + column) * 4) as usize;
+                    let coverage = bitmap.coverage[(row * bitmap.width + column) as usize];
+                    pixels[i..i + 4].copy_from_slice(&[255, 255, 255, coverage]);
+                }
+            }
+            self.pages[page].dirty = true;
+            self.pages[page].last_used = self.lookups;
+            Some(AtlasGlyph { page, rect: [x, y, bitmap.width, bitmap.height], left: bitmap.left, top: bitmap.top })
+        });
+        self.glyphs.insert(key, placed);
+        placed
+    }
+
+    /// Finds a page with room for a `width` x `height` bitmap: the current page, a new one,
+    /// or the least recently used page no queued text samples, cleared. `None` if the bitmap
+    /// is larger than a page.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(usize, [u32; 2])> {
+        if width >= GLYPH_PAGE_SIZE || height >= GLYPH_PAGE_SIZE {
+            return None;
+        }
+        if let Some(page) = self.pages.get_mut(self.current)
+            && let Some(position) = page.allocate(width, height)
+        {
+            return Some((self.current, position));
+        }
+
+        let reusable = (0..self.pages.len())
+            .filter(|&page| self.pages[page].queued == 0)
+            .min_by_key(|&page| self.pages[page].last_used);
+        self.current = match reusable {
+            Some(page) if self.pages.len() >= GLYPH_PAGE_LIMIT => {
+                self.glyphs.retain(|_, placed| placed.is_none_or(|placed| placed.page != page));
+                self.pages[page] = GlyphPage::new();
+                page
+            }
+            _ => {
+                if self.pages.len() >= GLYPH_PAGE_LIMIT {
+                    eprintln!("Warning: every glyph atlas page holds text waiting to be drawn; adding page {}", self.pages.len() + 1);
+                }
+                self.pages.push(GlyphPage::new());
+                self.pages.len() - 1
+            }
+        };
+        self.pages[self.current].allocate(width, height).map(|position| (self.current, position))
+    }
+}
+
+thread_local! {
+    /// Glyphs of loaded fonts, shared by every batch on the GL thread.
+    static GLYPH_ATLAS: RefCell<GlyphAtlas> = RefCell::new(GlyphAtlas::default());
+}
@@ -0,0 +1,270 @@
+//! Monitors, video modes and fullscreen modes.
+//!
+//! [`Renderer::monitors`](crate::engine::renderer::Renderer::monitors) lists the connected
+//! [`Monitor`]s with the [`VideoMode`]s each supports, and
+//! [`Renderer::set_fullscreen`](crate::engine::renderer::Renderer::set_fullscreen) switches
+//! the window between the [`FullscreenMode`]s at runtime:
+//!
+//! - **Windowed**: a normal, decorated window.
+//! - **Borderless**: an undecorated window covering a monitor at its desktop resolution.
+//!   Switching is instant and other windows can appear on top, but the display keeps its
+//!   resolution.
+//! - **Exclusive**: the window takes over a monitor and switches it to a chosen video mode,
+//!   e.g. a lower resolution for performance. Switching may take a moment and blank the
+//!   screen.
+//!
+//! Either way the window is resized, and the renderer adapts its framebuffer and viewport
+//! when the resulting resize event arrives.
+//!
+//! Independently of the mode, [`Renderer::set_vsync`](crate::engine::renderer::Renderer::set_vsync)
+//! controls whether buffer swaps wait for the display's vertical blank, and
+//! [`Renderer::set_target_fps`](crate::engine::renderer::Renderer::set_target_fps) caps the
+//! frame rate on the CPU, for benchmarking or to save power with vsync off.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{display::FullscreenMode, renderer::Renderer};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! for (index, monitor) in renderer.monitors().iter().enumerate() {
+//!     println!("{index}: {} {:?}", monitor.name, monitor.size);
+//! }
+//! // 1080p at the highest refresh rate the primary monitor supports there
+//! let monitor = renderer.monitors().into_iter().next();
+//! let video_mode = monitor.and_then(|monitor| monitor.best_mode([1920, 1080]));
+//! renderer.set_fullscreen(FullscreenMode::Exclusive { monitor: Some(0), video_mode });
+//! ```
+
+use std::ffi::c_void;
+use glutin::monitor::{MonitorHandle, VideoMode as WinitVideoMode};
+use glutin::platform::ContextTraitExt;
+use glutin::window::{Fullscreen, Window};
+use glutin::{ContextWrapper, PossiblyCurrent};
+
+/// A resolution, color depth and refresh rate a monitor can be switched to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    /// Width and height in physical pixels.
+    pub size: [u32; 2],
+
+    /// Bits per pixel.
+    pub bit_depth: u16,
+
+    /// Refresh rate in millihertz (60 Hz is 60 000).
+    pub refresh_rate_millihertz: u32,
+}
+
+impl VideoMode {
+    /// Refresh rate in hertz.
+    pub fn refresh_rate(&self) -> f32 {
+        self.refresh_rate_millihertz as f32 / 1000.0
+    }
+
+    fn from_winit(mode: &WinitVideoMode) -> Self {
+        let size = mode.size();
+        Self {
+            size: [size.width, size.height],
+            bit_depth: mode.bit_depth(),
+            refresh_rate_millihertz: mode.refresh_rate_millihertz(),
+        }
+    }
+}
+
+/// A connected display.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+    /// Human-readable name, or an empty string if the platform doesn't report one.
+    pub name: String,
+
+    /// Current resolution in physical pixels.
+    pub size: [u32; 2],
+
+    /// Top-left corner on the virtual desktop, in physical pixels.
+    pub position: [i32; 2],
+
+    /// Ratio of physical to logical pixels (e.g. 2.0 on high-DPI displays).
+    pub scale_factor: f64,
+
+    /// Video modes available for exclusive fullscreen, largest and fastest first.
+    pub video_modes: Vec<VideoMode>,
+}
+
+impl Monitor {
+    /// The supported mode with resolution `size` and the highest refresh rate and color
+    /// depth, if any.
+    pub fn best_mode(&self, size: [u32; 2]) -> Option<VideoMode> {
+        self.video_modes
+            .iter()
+            .copied()
+            .filter(|mode| mode.size == size)
+            .max_by_key(|mode| (mode.refresh_rate_millihertz, mode.bit_depth))
+    }
+
+    pub(crate) fn from_winit(handle: &MonitorHandle) -> Self {
+        let (size, position) = (handle.size(), handle.position());
+        let mut video_modes: Vec<VideoMode> = handle.video_modes().map(|mode| VideoMode::from_winit(&mode)).collect();
+        video_modes.sort_by_key(|mode| std::cmp::Reverse((mode.size[0] * mode.size[1], mode.refresh_rate_millihertz, mode.bit_depth)));
+        video_modes.dedup();
+        Self {
+            name: handle.name().unwrap_or_default(),
+            size: [size.width, size.height],
+            position: [position.x, position.y],
+            scale_factor: handle.scale_factor(),
+            video_modes,
+        }
+    }
+}
+
+/// How the window is presented.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FullscreenMode {
+    /// A normal, decorated window.
+    #[default]
+    Windowed,
+
+    /// An undecorated window covering a monitor at its current resolution.
+    Borderless {
+        /// Index into [`Renderer::monitors`](crate::engine::renderer::Renderer::monitors);
+        /// `None` for the monitor the window is on.
+        monitor: Option<usize>,
+    },
+
+    /// Exclusive fullscreen, switching the monitor to a video mode.
+    Exclusive {
+        /// Index into [`Renderer::monitors`](crate::engine::renderer::Renderer::monitors);
+        /// `None` for the monitor the window is on.
+        monitor: Option<usize>,
+
+        /// One of the monitor's [`video_modes`](Monitor::video_modes); `None`, or a mode
+        /// the monitor doesn't support, picks its largest and fastest mode.
+        video_mode: Option<VideoMode>,
+    },
+}
+
+impl FullscreenMode {
+    /// Resolves the mode to a winit fullscreen setting, given the available monitors and
+    /// the one the window is on. Returns `None` for windowed mode, or when no monitor is
+    /// available for exclusive mode.
+    pub(crate) fn to_winit(self, monitors: Vec<MonitorHandle>, current: Option<MonitorHandle>) -> Option<Fullscreen> {
+        let pick = |index: Option<usize>| match index {
+            Some(index) => monitors.get(index).cloned().or_else(|| {
+                eprintln!("No monitor {index}; using the current one");
+                current.clone()
+            }),
+            None => current.clone(),
+        };
+        match self {
+            FullscreenMode::Windowed => None,
+            FullscreenMode::Borderless { monitor } => Some(Fullscreen::Borderless(pick(monitor))),
+            FullscreenMode::Exclusive { monitor, video_mode } => {
+                let handle = pick(monitor).or_else(|| monitors.first().cloned())?;
+                let modes: Vec<WinitVideoMode> = handle.video_modes().collect();
+                let requested = video_mode.and_then(|wanted| modes.iter().find(|mode| VideoMode::from_winit(mode) == wanted));
+                let best = || {
+                    modes.iter().max_by_key(|mode| {
+                        let size = mode.size();
+                        (size.width * size.height, mode.refresh_rate_millihertz(), mode.bit_depth())
+                    })
+                };
+                match requested.or_else(best) {
+                    Some(mode) => Some(Fullscreen::Exclusive(mode.clone())),
+                    None => Some(Fullscreen::Borderless(Some(handle))),
+                }
+            }
+        }
+    }
+}
+
+/// `monitors` with `primary` moved to the front, the order monitor indices refer to.
+pub(crate) fn primary_first(mut monitors: Vec<MonitorHandle>, primary: Option<MonitorHandle>) -> Vec<MonitorHandle> {
+    if let Some(primary) = primary
+        && let Some(index) = monitors.iter().position(|handle| *handle == primary)
+    {
+        monitors[..=index].rotate_right(1);
+    }
+    monitors
+}
+
+/// Sets the swap interval of `context`, which must be current: 1 makes buffer swaps wait for
+/// the vertical blank, 0 presents immediately. glutin only sets it when creating a context,
+/// so this calls the platform's swap control extension directly. Returns `false` if the
+/// platform doesn't support changing it.
+pub(crate) fn set_swap_interval(context: &ContextWrapper<PossiblyCurrent, Window>, interval: i32) -> bool {
+    let function = |name: &str| {
+        let address = context.get_proc_address(name);
+        // wglGetProcAddress returns small sentinel values instead of null on some drivers
+        (address as usize > 3 && address as isize != -1).then_some(address)
+    };
+    unsafe {
+        if let Some(display) = context.get_egl_display() {
+            let Some(swap_interval) = function("eglSwapInterval") else {
+                return false;
+            };
+            let swap_interval: extern "system" fn(*const c_void, i32) -> u32 = std::mem::transmute(swap_interval);
+            return swap_interval(display, interval) != 0;
+        }
+        platform_swap_interval(function, interval)
+    }
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn platform_swap_interval(function: impl Fn(&str) -> Option<*const c_void>, interval: i32) -> bool {
+    let Some(swap_interval) = function("wglSwapIntervalEXT") else {
+        return false;
+    };
+    let swap_interval: extern "system" fn(i32) -> i32 = unsafe { std::mem::transmute(swap_interval) };
+    swap_interval(interval) != 0
+}
+
+#[cfg(all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))]
+unsafe fn platform_swap_interval(function: impl Fn(&str) -> Option<*const c_void>, interval: i32) -> bool {
+    use std::ffi::{c_char, c_int, c_ulong, CStr};
+
+    // glXGetProcAddress hands out stubs for any name, so check the extension string first
+    let (Some(current_display), Some(current_drawable), Some(query_extensions)) =
+        (function("glXGetCurrentDisplay"), function("glXGetCurrentDrawable"), function("glXQueryExtensionsString"))
+    else {
+        return false;
+    };
+    unsafe {
+        let current_display: extern "C" fn() -> *mut c_void = std::mem::transmute(current_display);
+        let current_drawable: extern "C" fn() -> c_ulong = std::mem::transmute(current_drawable);
+        let query_extensions: extern "C" fn(*mut c_void, c_int) -> *const c_char = std::mem::transmute(query_extensions);
+        let display = current_display();
+        if display.is_null() {
+            return false;
+        }
+        let extensions = query_extensions(display, 0);
+        if extensions.is_null() {
+            return false;
+        }
+        let extensions = CStr::from_ptr(extensions).to_string_lossy();
+        let supports = |name: &str| extensions.split_whitespace().any(|extension| extension == name);
+
+        if supports("GLX_EXT_swap_control")
+            && let Some(swap_interval) = function("glXSwapIntervalEXT")
+        {
+            let swap_interval: extern "C" fn(*mut c_void, c_ulong, c_int) = std::mem::transmute(swap_interval);
+            swap_interval(display, current_drawable(), interval);
+            true
+        } else if supports("GLX_MESA_swap_control")
+            && let Some(swap_interval) = function("glXSwapIntervalMESA")
+        {
+            let swap_interval: extern "C" fn(u32) -> c_int = std::mem::transmute(swap_interval);
+            swap_interval(interval.max(0) as u32) == 0
+        } else if supports("GLX_SGI_swap_control")
+            && interval > 0
+            && let Some(swap_interval) = function("glXSwapIntervalSGI")
+        {
+            // SGI swap control can't turn vsync off
+            let swap_interval: extern "C" fn(c_int) -> c_int = std::mem::transmute(swap_interval);
+            swap_interval(interval) == 0
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+unsafe fn platform_swap_interval(_function: impl Fn(&str) -> Option<*const c_void>, _interval: i32) -> bool {
+    false
+}
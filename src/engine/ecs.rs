@@ -0,0 +1,437 @@
+//! Entities, components and systems for data-oriented game logic.
+//!
+//! The scene graph is built for rendering: every node is its own `Rc<RefCell<Object3D>>`,
+//! which is flexible but slow to iterate by the thousand. Game logic over many similar
+//! objects (projectiles, crowds, pickups) fits an entity-component-system better:
+//!
+//! - An [`Entity`] is just an id.
+//! - Components are plain values stored per type in a densely packed [`Storage`], so a
+//!   system touching one component type walks contiguous memory.
+//! - Systems are functions over the [`World`], run in order by a [`Schedule`] once per
+//!   frame from the renderer's main loop (see [`Renderer::add_system`]).
+//!
+//! The two connect through [`Transform`] and [`SceneNode`]: after the systems ran, each
+//! entity with both has its transform copied to its scene node (or, for nodes driven by the
+//! scene such as animated ones, the other way round), see [`sync_scene`].
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{ecs::{sync_scene, Schedule, SceneNode, Transform, World}, object3d::Object3D, time::Clock};
+//! struct Velocity([f32; 3]);
+//!
+//! let mut world = World::new();
+//! let node = Object3D::new();
+//! let ball = world.spawn();
+//! world.insert(ball, Transform::default());
+//! world.insert(ball, Velocity([0.0, 2.0, 0.0]));
+//! world.insert(ball, SceneNode::new(node.clone()));
+//!
+//! let mut schedule = Schedule::new();
+//! schedule.add_system("movement", |world, _clock| {
+//!     let step = 0.5; // clock.delta() in a real frame
+//!     let mut transforms = world.write::<Transform>();
+//!     let velocities = world.read::<Velocity>();
+//!     for (entity, transform) in transforms.iter_mut() {
+//!         if let Some(velocity) = velocities.get(entity) {
+//!             for axis in 0..3 {
+//!                 transform.position[axis] += velocity.0[axis] * step;
+//!             }
+//!         }
+//!     }
+//! });
+//!
+//! schedule.run(&mut world, &Clock::new());
+//! sync_scene(&world);
+//! assert_eq!(node.borrow().position(), [0.0, 1.0, 0.0]);
+//! ```
+//!
+//! [`Renderer::add_system`]: crate::engine::renderer::Renderer::add_system
+
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::engine::object3d::Object3D;
+use crate::engine::time::Clock;
+
+/// An entity: an index plus a generation, so ids of despawned entities don't alias
+/// entities spawned later in the same slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    /// Slot of the entity, for use as an array index.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Components of one type, packed densely with a sparse index by entity.
+#[derive(Debug)]
+pub struct Storage<T> {
+    dense: Vec<T>,
+    entities: Vec<Entity>,
+    /// Position in `dense` by entity index; `u32::MAX` for none.
+    sparse: Vec<u32>,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Self { dense: Vec::new(), entities: Vec::new(), sparse: Vec::new() }
+    }
+}
+
+impl<T> Storage<T> {
+    fn slot(&self, entity: Entity) -> Option<usize> {
+        let slot = *self.sparse.get(entity.index as usize)? as usize;
+        (self.entities.get(slot) == Some(&entity)).then_some(slot)
+    }
+
+    /// The component of `entity`, if it has one.
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.slot(entity).map(|slot| &self.dense[slot])
+    }
+
+    /// The component of `entity` for modification, if it has one.
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.slot(entity).map(|slot| &mut self.dense[slot])
+    }
+
+    /// Whether `entity` has a component in this storage.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.slot(entity).is_some()
+    }
+
+    /// Stores the component of `entity`, returning the one it replaces.
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(slot) = self.slot(entity) {
+            return Some(std::mem::replace(&mut self.dense[slot], component));
+        }
+        let index = entity.index as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, u32::MAX);
+        }
+        self.sparse[index] = self.dense.len() as u32;
+        self.dense.push(component);
+        self.entities.push(entity);
+        None
+    }
+
+    /// Removes and returns the component of `entity`.
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slot(entity)?;
+        self.sparse[entity.index as usize] = u32::MAX;
+        self.entities.swap_remove(slot);
+        // The last component moved into the freed slot
+        if let Some(moved) = self.entities.get(slot) {
+            self.sparse[moved.index as usize] = slot as u32;
+        }
+        Some(self.dense.swap_remove(slot))
+    }
+
+    /// Every entity with its component, in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.dense)
+    }
+
+    /// Every entity with its component for modification, in storage order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(&mut self.dense)
+    }
+
+    /// Number of components stored.
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Whether no components are stored.
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+}
+
+/// A storage of any component type, so despawning can clear an entity from all of them.
+trait AnyStorage: Any {
+    fn remove_entity(&self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static> AnyStorage for RefCell<Storage<T>> {
+    fn remove_entity(&self, entity: Entity) {
+        self.borrow_mut().remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Entities and their components.
+///
+/// Each component type's storage is borrowed separately through [`read`](Self::read) and
+/// [`write`](Self::write), so a system can read velocities while writing transforms.
+#[derive(Default)]
+pub struct World {
+    /// Current generation of each entity slot.
+    generations: Vec<u32>,
+
+    /// Whether each slot holds a live entity.
+    alive: Vec<bool>,
+
+    /// Slots of despawned entities, reused first.
+    free: Vec<u32>,
+
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl std::fmt::Debug for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "World({} entities, {} component types)", self.len(), self.storages.len())
+    }
+}
+
+impl World {
+    /// An empty world.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an entity without components.
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                (self.generations.len() - 1) as u32
+            }
+        };
+        self.alive[index as usize] = true;
+        Entity { index, generation: self.generations[index as usize] }
+    }
+
+    /// Removes an entity and all its components. Returns `false` if it was already gone.
+    /// A [`SceneNode`] component is dropped but its node stays in the scene.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values() {
+            storage.remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    /// Whether `entity` has been spawned and not despawned.
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        self.alive.get(index) == Some(&true) && self.generations[index] == entity.generation
+    }
+
+    /// Number of live entities.
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    /// Whether there are no live entities.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Creates the storage for component type `T` if it doesn't exist yet, so
+    /// [`read`](Self::read) and [`write`](Self::write) can be used before any is inserted.
+    pub fn register<T: 'static>(&mut self) {
+        self.storages.entry(TypeId::of::<T>()).or_insert_with(|| Box::new(RefCell::new(Storage::<T>::default())));
+    }
+
+    /// Attaches a component to a live entity, replacing and returning one of the same
+    /// type. Components of dead entities are dropped.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.register::<T>();
+        self.write::<T>().insert(entity, component)
+    }
+
+    /// Detaches and returns a component.
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.try_write::<T>()?.remove(entity)
+    }
+
+    /// Whether `entity` has a component of type `T`.
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.try_read::<T>().is_some_and(|storage| storage.contains(entity))
+    }
+
+    /// Shared access to the storage of `T`, or `None` if none was registered.
+    ///
+    /// # Panics
+    /// Panics if the storage is borrowed mutably at the same time.
+    pub fn try_read<T: 'static>(&self) -> Option<Ref<'_, Storage<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        storage.as_any().downcast_ref::<RefCell<Storage<T>>>().map(RefCell::borrow)
+    }
+
+    /// Mutable access to the storage of `T`, or `None` if none was registered.
+    ///
+    /// # Panics
+    /// Panics if the storage is borrowed at the same time.
+    pub fn try_write<T: 'static>(&self) -> Option<RefMut<'_, Storage<T>>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        storage.as_any().downcast_ref::<RefCell<Storage<T>>>().map(RefCell::borrow_mut)
+    }
+
+    /// Shared access to the storage of `T`.
+    ///
+    /// # Panics
+    /// Panics if no component of type `T` was ever inserted or [registered](Self::register),
+    /// or if the storage is borrowed mutably at the same time.
+    pub fn read<T: 'static>(&self) -> Ref<'_, Storage<T>> {
+        self.try_read().unwrap_or_else(|| panic!("Component type {} is not registered", std::any::type_name::<T>()))
+    }
+
+    /// Mutable access to the storage of `T`.
+    ///
+    /// # Panics
+    /// Panics if no component of type `T` was ever inserted or [registered](Self::register),
+    /// or if the storage is borrowed at the same time.
+    pub fn write<T: 'static>(&self) -> RefMut<'_, Storage<T>> {
+        self.try_write().unwrap_or_else(|| panic!("Component type {} is not registered", std::any::type_name::<T>()))
+    }
+}
+
+/// A system: game logic run once per frame over the world.
+pub type System = Box<dyn FnMut(&mut World, &Clock)>;
+
+/// Named systems, run in the order they were added.
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<(String, System)>,
+}
+
+impl std::fmt::Debug for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.systems.iter().map(|(name, _)| name)).finish()
+    }
+}
+
+impl Schedule {
+    /// An empty schedule.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a system, replacing one of the same name in place.
+    pub fn add_system<F>(&mut self, name: &str, system: F)
+    where
+        F: FnMut(&mut World, &Clock) + 'static,
+    {
+        match self.systems.iter_mut().find(|(other, _)| other == name) {
+            Some(slot) => slot.1 = Box::new(system),
+            None => self.systems.push((name.to_string(), Box::new(system))),
+        }
+    }
+
+    /// Removes the system named `name`; returns whether there was one.
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        let count = self.systems.len();
+        self.systems.retain(|(other, _)| other != name);
+        self.systems.len() != count
+    }
+
+    /// The system names in run order.
+    pub fn system_names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Runs every system once, in order.
+    pub fn run(&mut self, world: &mut World, clock: &Clock) {
+        self.run_timed(world, clock, |_, _| {});
+    }
+
+    /// Runs every system once, reporting how long each took.
+    pub(crate) fn run_timed(&mut self, world: &mut World, clock: &Clock, mut record: impl FnMut(&str, Duration)) {
+        for (name, system) in &mut self.systems {
+            let start = Instant::now();
+            system(world, clock);
+            record(name, start.elapsed());
+        }
+    }
+}
+
+/// Position, rotation (unit quaternion `[x, y, z, w]`) and scale of an entity, relative to
+/// its scene node's parent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { position: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], scale: [1.0; 3] }
+    }
+}
+
+/// Which side of a [`SceneNode`] link owns the transform.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransformSync {
+    /// Systems move the entity; its [`Transform`] is copied to the node.
+    #[default]
+    ToScene,
+    /// The scene moves the node (animation, ragdolls, update callbacks); its transform is
+    /// copied to the entity's [`Transform`].
+    FromScene,
+}
+
+/// Links an entity to the scene graph node that renders it.
+#[derive(Clone, Debug)]
+pub struct SceneNode {
+    pub node: Rc<RefCell<Object3D>>,
+    pub sync: TransformSync,
+}
+
+impl SceneNode {
+    /// A link whose node follows the entity's [`Transform`].
+    pub fn new(node: Rc<RefCell<Object3D>>) -> Self {
+        Self { node, sync: TransformSync::ToScene }
+    }
+}
+
+/// Copies transforms between every entity with both a [`Transform`] and a [`SceneNode`] and
+/// its node, in the direction of the link's [`TransformSync`]. Nodes are only touched when
+/// the transform differs, so unchanged entities don't dirty their subtree.
+///
+/// The renderer calls this after running its systems each frame.
+pub fn sync_scene(world: &World) {
+    let (Some(mut transforms), Some(links)) = (world.try_write::<Transform>(), world.try_read::<SceneNode>()) else {
+        return;
+    };
+    for (entity, link) in links.iter() {
+        let Some(transform) = transforms.get_mut(entity) else {
+            continue;
+        };
+        match link.sync {
+            TransformSync::ToScene => {
+                let mut node = link.node.borrow_mut();
+                if node.position() != transform.position || node.rotation() != transform.rotation || node.scale() != transform.scale {
+                    node.set_transform(transform.position, transform.rotation, transform.scale);
+                }
+            }
+            TransformSync::FromScene => {
+                let node = link.node.borrow();
+                *transform = Transform { position: node.position(), rotation: node.rotation(), scale: node.scale() };
+            }
+        }
+    }
+}
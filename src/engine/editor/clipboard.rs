@@ -0,0 +1,156 @@
+//! Copy, cut, paste and duplicate of scene subtrees.
+//!
+//! A [`SceneClipboard`] holds copied subtrees serialized as binary glTF (see
+//! [`glb_bytes_for_nodes`]), not as references to the live nodes: editing or deleting the
+//! originals after copying doesn't change what gets pasted, and the contents can be handed to
+//! another running instance through [`write_to`](SceneClipboard::write_to) and
+//! [`read_from`](SceneClipboard::read_from) (or any other channel, via
+//! [`contents`](SceneClipboard::contents)).
+//!
+//! Copied nodes keep their world transforms, so pasting puts them back where they were
+//! regardless of the parent they are pasted under. Everything glTF carries survives the
+//! round trip (hierarchy, names, metadata, transforms, geometry, material colors and lights);
+//! custom shaders, textures, user data and update callbacks do not, see [`export::gltf`](crate::engine::export::gltf).
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{editor::clipboard::SceneClipboard, object3d::Object3D};
+//! let root = Object3D::new();
+//! let marker = Object3D::new();
+//! marker.borrow_mut().set_position([1.0, 2.0, 3.0]);
+//! Object3D::add_child(&root, marker.clone());
+//!
+//! let mut clipboard = SceneClipboard::new();
+//! clipboard.copy(&[marker.clone()]);
+//! let pasted = clipboard.paste(&root).unwrap();
+//! assert_eq!(pasted[0].borrow().position(), [1.0, 2.0, 3.0]);
+//!
+//! let copies = SceneClipboard::duplicate(&[marker], [0.5, 0.0, 0.0]).unwrap();
+//! assert_eq!(copies[0].borrow().position(), [1.5, 2.0, 3.0]);
+//! assert_eq!(root.borrow().children().len(), 3);
+//! ```
+
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use crate::engine::export::gltf::glb_bytes_for_nodes;
+use crate::engine::import::gltf::load_gltf_slice;
+use crate::engine::math::vec::add;
+use crate::engine::object3d::Object3D;
+
+/// Serialized subtrees waiting to be pasted.
+#[derive(Clone, Debug, Default)]
+pub struct SceneClipboard {
+    /// Binary glTF of the copied subtrees, `None` when nothing was copied.
+    contents: Option<Vec<u8>>,
+}
+
+impl SceneClipboard {
+    /// An empty clipboard.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copies the subtrees rooted at `nodes`. Nodes inside another copied subtree are part of
+    /// that copy and not copied again. Copying nothing empties the clipboard.
+    pub fn copy(&mut self, nodes: &[Rc<RefCell<Object3D>>]) {
+        let roots = topmost(nodes);
+        self.contents = (!roots.is_empty()).then(|| glb_bytes_for_nodes(&roots));
+    }
+
+    /// Copies the subtrees rooted at `nodes` and removes them from their parents.
+    pub fn cut(&mut self, nodes: &[Rc<RefCell<Object3D>>]) {
+        self.copy(nodes);
+        for node in topmost(nodes) {
+            Object3D::detach(&node);
+        }
+    }
+
+    /// Adds a new copy of the clipboard contents under `parent`, each subtree at the world
+    /// transform it was copied with, and returns the new subtree roots (empty if the clipboard
+    /// is empty). Requires a current GL context when the contents have materials.
+    pub fn paste(&self, parent: &Rc<RefCell<Object3D>>) -> io::Result<Vec<Rc<RefCell<Object3D>>>> {
+        let Some(contents) = &self.contents else {
+            return Ok(Vec::new());
+        };
+        let pasted = load_gltf_slice(contents)?;
+        let roots = pasted.borrow().children().to_vec();
+        for root in &roots {
+            Object3D::reparent_keep_world(root, Some(parent));
+        }
+        Ok(roots)
+    }
+
+    /// Copies each subtree rooted at `nodes` next to its original (under the same parent),
+    /// moved by `offset` in world space, and returns the copies. Goes through the same
+    /// serialization as copy and paste, so the copies match what pasting would produce.
+    /// The clipboard contents are not touched.
+    pub fn duplicate(
+        nodes: &[Rc<RefCell<Object3D>>],
+        offset: [f32; 3],
+    ) -> io::Result<Vec<Rc<RefCell<Object3D>>>> {
+        let mut copies = Vec::new();
+        for node in topmost(nodes) {
+            let parent = node.borrow().parent();
+            let pasted = load_gltf_slice(&glb_bytes_for_nodes(std::slice::from_ref(&node)))?;
+            let Some(copy) = pasted.borrow().children().first().cloned() else {
+                continue;
+            };
+            // Still under the untransformed import root, so the position is in world space
+            let position = copy.borrow().position();
+            copy.borrow_mut().set_position(add(position, offset));
+            Object3D::reparent_keep_world(&copy, parent.as_ref());
+            copies.push(copy);
+        }
+        Ok(copies)
+    }
+
+    /// Whether nothing has been copied.
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_none()
+    }
+
+    /// Empties the clipboard.
+    pub fn clear(&mut self) {
+        self.contents = None;
+    }
+
+    /// The serialized contents (a binary glTF file), e.g. to send to another instance.
+    pub fn contents(&self) -> Option<&[u8]> {
+        self.contents.as_deref()
+    }
+
+    /// Replaces the contents with serialized subtrees received from elsewhere. They are
+    /// validated when pasted.
+    pub fn set_contents(&mut self, contents: Vec<u8>) {
+        self.contents = Some(contents);
+    }
+
+    /// Writes the contents to `path` (a `.glb` file) so another instance can
+    /// [`read_from`](Self::read_from) it. An empty clipboard writes an empty file.
+    pub fn write_to(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.contents().unwrap_or_default())
+    }
+
+    /// Replaces the contents with a file written by [`write_to`](Self::write_to) (or any
+    /// `.glb` file). An empty file empties the clipboard.
+    pub fn read_from(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = std::fs::read(path)?;
+        self.contents = (!contents.is_empty()).then_some(contents);
+        Ok(())
+    }
+}
+
+/// `nodes` without those inside the subtree of another node in the list, and without
+/// repeats, in their original order.
+fn topmost(nodes: &[Rc<RefCell<Object3D>>]) -> Vec<Rc<RefCell<Object3D>>> {
+    let mut roots: Vec<Rc<RefCell<Object3D>>> = Vec::new();
+    for node in nodes {
+        let covered = nodes.iter().any(|other| !Rc::ptr_eq(other, node) && Object3D::contains(other, node));
+        if !covered && !roots.iter().any(|root| Rc::ptr_eq(root, node)) {
+            roots.push(node.clone());
+        }
+    }
+    roots
+}
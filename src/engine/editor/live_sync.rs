@@ -0,0 +1,640 @@
+//! Live editing of a running game from an external editor, over a local socket.
+//!
+//! With [live sync](crate::engine::renderer::Renderer::enable_live_sync) on, the renderer
+//! listens on a TCP port of the local machine. An editor process or a DCC plugin connects
+//! and sends [`ScenePatch`]es, one JSON object (or array of them) per line, and the engine
+//! applies them between frames: moving nodes, renaming and tagging them, changing material
+//! colors and uniforms or light settings, adding and removing nodes. Each line is answered
+//! with `{"ok":true}`, or `{"ok":false,"error":"..."}` when a patch can't be applied, so
+//! tools can report mistakes; a line's patches are applied all together or, if one fails,
+//! not at all. A [`snapshot`](ScenePatch::Snapshot) request is answered with the
+//! [`SceneState`] of the whole scene, to show the game's graph in the editor.
+//!
+//! Nodes are addressed by [path](node_path): the names of the nodes from the scene root
+//! down, separated by `/`, with unnamed nodes given by their index among their siblings,
+//! e.g. `level/props/3`. Naming the nodes an editor works with (see
+//! [`Object3D::set_name`]) keeps paths stable as siblings come and go.
+//!
+//! The other way round, [`SceneState::diff`] turns two captures of the scene into the
+//! patches leading from one to the other, which the engine can
+//! [`broadcast`](LiveSync::broadcast) to keep an editor up to date with changes made in game.
+//!
+//! Only connections from the local machine are accepted.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::editor::live_sync::{ScenePatch, SceneState};
+//! # use rustge::engine::{object3d::Object3D, scene::Scene};
+//! let scene = Scene::new();
+//! let level = Object3D::new();
+//! level.borrow_mut().set_name("level");
+//! Object3D::add_child(&level, Object3D::new());
+//! scene.add(level);
+//! let before = SceneState::capture(&scene);
+//!
+//! // What an editor sends after dragging the first node of the level upwards
+//! let patch: ScenePatch = serde_json::from_str(r#"{"op":"transform","node":"level/0","position":[0,2,0]}"#).unwrap();
+//! patch.apply(&scene).unwrap();
+//! let crate_node = scene.find_by_name("level").unwrap().borrow().children()[0].clone();
+//! assert_eq!(crate_node.borrow().position(), [0.0, 2.0, 0.0]);
+//!
+//! // And back: the patch that reproduces the change elsewhere
+//! let patches = before.diff(&SceneState::capture(&scene));
+//! assert_eq!(patches, [ScenePatch::Transform { node: "level/0".into(), position: Some([0.0, 2.0, 0.0]), rotation: None, scale: None }]);
+//! ```
+//!
+//! [`Object3D::set_name`]: crate::engine::object3d::Object3D::set_name
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::rc::Rc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::engine::light::Light;
+use crate::engine::material::Material;
+use crate::engine::math::color::Color;
+use crate::engine::object3d::Object3D;
+use crate::engine::scene::Scene;
+use crate::engine::shader::UniformValue;
+
+/// Longest line a client may send, to bound the memory a broken client can take.
+const MAX_LINE_BYTES: usize = 16 << 20;
+
+/// Most bytes read from one client per poll; the rest waits for the next frame, so a flood
+/// of patches neither stalls a frame nor piles up in memory.
+const MAX_POLL_BYTES: usize = 1 << 20;
+
+/// Most answer bytes queued for a client that isn't reading them before it is disconnected.
+const MAX_QUEUED_BYTES: usize = 64 << 20;
+
+/// A change to the scene, sent by an editor as JSON with an `op` field naming the variant
+/// in snake case, e.g. `{"op":"remove","node":"level/lamp"}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ScenePatch {
+    /// Changes a node's local position, rotation (a quaternion) and scale, each if given.
+    Transform {
+        node: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        position: Option<[f32; 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rotation: Option<[f32; 4]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        scale: Option<[f32; 3]>,
+    },
+
+    /// Sets a property of a node:
+    ///
+    /// - `name`: a string, empty to remove the name;
+    /// - `tags`: an array of strings, replacing the node's tags;
+    /// - `color`: the material color, as sRGB `[r, g, b]` or `[r, g, b, a]` from 0 to 1;
+    /// - `uniform.<name>`: a material uniform, as a number or an array of 2, 3, 4 or 16;
+    /// - `light_color` and `light_intensity`: the node's light, like `color`, and a number;
+    /// - `visible`, `enabled` and `frustum_culled`: booleans.
+    /// - `layers`: the render layer bits, as a number.
+    Set { node: String, property: String, value: Value },
+
+    /// Adds an empty node named `name` (unnamed if empty) as the last child of `parent`.
+    Add { parent: String, name: String },
+
+    /// Removes a node and everything under it.
+    Remove { node: String },
+
+    /// Asks for the [`SceneState`] of the scene, sent back in the answer's `scene` field.
+    Snapshot,
+}
+
+impl ScenePatch {
+    /// Applies the patch to `scene`. Returns an error describing why if a node isn't found
+    /// or a value doesn't fit its property, leaving the scene as it was.
+    /// [`Snapshot`](Self::Snapshot) requests change nothing.
+    pub fn apply(&self, scene: &Scene) -> Result<(), String> {
+        self.apply_undoable(scene).map(|_| ())
+    }
+
+    /// Applies the patch like [`apply`](Self::apply), returning how to take it back.
+    fn apply_undoable(&self, scene: &Scene) -> Result<Undo, String> {
+        let undo = match self {
+            ScenePatch::Transform { node, position, rotation, scale } => {
+                let node = find(scene, node)?;
+                let previous = {
+                    let node = node.borrow();
+                    (node.position(), node.rotation(), node.scale())
+                };
+                {
+                    let mut node = node.borrow_mut();
+                    if let Some(position) = position {
+                        node.set_position(*position);
+                    }
+                    if let Some(rotation) = rotation {
+                        node.set_rotation(*rotation);
+                    }
+                    if let Some(scale) = scale {
+                        node.set_scale(*scale);
+                    }
+                }
+                Undo::Transform(node, previous)
+            }
+            ScenePatch::Set { node, property, value } => {
+                let node = find(scene, node)?;
+                let properties = NodeProperties::capture(&node.borrow());
+                set_property(&mut node.borrow_mut(), property, value)?;
+                Undo::Set(node, Box::new(properties))
+            }
+            ScenePatch::Add { parent, name } => {
+                let parent = find(scene, parent)?;
+                let node = Object3D::new();
+                node.borrow_mut().set_name(name);
+                Object3D::add_child(&parent, node.clone());
+                scene.invalidate_neighbors();
+                Undo::Add(node)
+            }
+            ScenePatch::Remove { node } => {
+                let node = find(scene, node)?;
+                if Rc::ptr_eq(&node, scene.root()) {
+                    return Err("The scene root can't be removed".to_string());
+                }
+                let parent = node.borrow().parent();
+                let index = parent.as_ref().and_then(|parent| {
+                    parent.borrow().children().iter().position(|child| Rc::ptr_eq(child, &node))
+                });
+                Object3D::detach(&node);
+                scene.invalidate_neighbors();
+                match parent.zip(index) {
+                    Some((parent, index)) => Undo::Remove { node, parent, index },
+                    None => Undo::Nothing,
+                }
+            }
+            ScenePatch::Snapshot => Undo::Nothing,
+        };
+        Ok(undo)
+    }
+}
+
+/// How to take back an applied [`ScenePatch`], for lines whose later patches fail.
+enum Undo {
+    Nothing,
+    /// The node and its previous position, rotation and scale.
+    Transform(Rc<RefCell<Object3D>>, ([f32; 3], [f32; 4], [f32; 3])),
+    Set(Rc<RefCell<Object3D>>, Box<NodeProperties>),
+    Add(Rc<RefCell<Object3D>>),
+    Remove { node: Rc<RefCell<Object3D>>, parent: Rc<RefCell<Object3D>>, index: usize },
+}
+
+impl Undo {
+    /// Takes the patch back. Patches must be reverted in the reverse order they were applied.
+    fn revert(self, scene: &Scene) {
+        match self {
+            Undo::Nothing => {}
+            Undo::Transform(node, (position, rotation, scale)) => node.borrow_mut().set_transform(position, rotation, scale),
+            Undo::Set(node, properties) => properties.restore(&mut node.borrow_mut()),
+            Undo::Add(node) => {
+                Object3D::detach(&node);
+                scene.invalidate_neighbors();
+            }
+            Undo::Remove { node, parent, index } => {
+                Object3D::insert_child(&parent, index, node);
+                scene.invalidate_neighbors();
+            }
+        }
+    }
+}
+
+/// What a [`ScenePatch::Set`] can change on a node.
+struct NodeProperties {
+    name: Option<String>,
+    tags: Vec<String>,
+    material: Option<Material>,
+    light: Option<Light>,
+    visible: bool,
+    enabled: bool,
+    layers: u32,
+    frustum_culled: bool,
+}
+
+impl NodeProperties {
+    fn capture(node: &Object3D) -> Self {
+        Self {
+            name: node.name().map(str::to_string),
+            tags: node.tags().to_vec(),
+            material: node.material().cloned(),
+            light: node.light().cloned(),
+            visible: node.visible(),
+            enabled: node.enabled(),
+            layers: node.layers(),
+            frustum_culled: node.frustum_culled(),
+        }
+    }
+
+    fn restore(self, node: &mut Object3D) {
+        node.set_name(self.name.as_deref().unwrap_or_default());
+        for tag in node.tags().to_vec() {
+            node.remove_tag(&tag);
+        }
+        for tag in &self.tags {
+            node.add_tag(tag);
+        }
+        if let Some(material) = self.material {
+            node.set_material(material);
+        }
+        node.set_light(self.light);
+        node.set_visible(self.visible);
+        node.set_enabled(self.enabled);
+        node.set_layers(self.layers);
+        node.set_frustum_culled(self.frustum_culled);
+    }
+}
+
+/// The node at `path` in `scene`, or an error naming the path.
+fn find(scene: &Scene, path: &str) -> Result<Rc<RefCell<Object3D>>, String> {
+    resolve_path(scene.root(), path).ok_or_else(|| format!("No node at {path:?}"))
+}
+
+fn set_property(node: &mut Object3D, property: &str, value: &Value) -> Result<(), String> {
+    let invalid = || format!("Invalid value {value} for {property}");
+    match property {
+        "name" => node.set_name(value.as_str().ok_or_else(invalid)?),
+        "tags" => {
+            let tags: Vec<String> = serde_json::from_value(value.clone()).map_err(|_| invalid())?;
+            for tag in node.tags().to_vec() {
+                node.remove_tag(&tag);
+            }
+            for tag in &tags {
+                node.add_tag(tag);
+            }
+        }
+        "color" => {
+            let color = color(value).ok_or_else(invalid)?;
+            node.material_mut().ok_or("The node has no material")?.set_color(color);
+        }
+        "light_color" => {
+            let color = color(value).ok_or_else(invalid)?;
+            node.light_mut().ok_or("The node has no light")?.color = color;
+        }
+        "light_intensity" => {
+            let intensity = value.as_f64().ok_or_else(invalid)? as f32;
+            node.light_mut().ok_or("The node has no light")?.intensity = intensity;
+        }
+        "visible" => node.set_visible(value.as_bool().ok_or_else(invalid)?),
+        "enabled" => node.set_enabled(value.as_bool().ok_or_else(invalid)?),
+        "layers" => {
+            let layers = value.as_u64().and_then(|layers| u32::try_from(layers).ok()).ok_or_else(invalid)?;
+            node.set_layers(layers);
+        }
+        "frustum_culled" => node.set_frustum_culled(value.as_bool().ok_or_else(invalid)?),
+        _ => {
+            let Some(uniform) = property.strip_prefix("uniform.") else {
+                return Err(format!("Unknown property {property:?}"));
+            };
+            let value = uniform_value(value).ok_or_else(invalid)?;
+            node.material_mut().ok_or("The node has no material")?.set_uniform(uniform, value);
+        }
+    }
+    Ok(())
+}
+
+/// An sRGB color from `[r, g, b]` or `[r, g, b, a]`.
+fn color(value: &Value) -> Option<Color> {
+    match *serde_json::from_value::<Vec<f32>>(value.clone()).ok()?.as_slice() {
+        [r, g, b] => Some(Color::srgb(r, g, b)),
+        [r, g, b, a] => Some(Color::srgba(r, g, b, a)),
+        _ => None,
+    }
+}
+
+/// A uniform value from a number or an array of 2, 3, 4 or 16 numbers.
+fn uniform_value(value: &Value) -> Option<UniformValue> {
+    if let Some(number) = value.as_f64() {
+        return Some(UniformValue::Float(number as f32));
+    }
+    let values: Vec<f32> = serde_json::from_value(value.clone()).ok()?;
+    match values.len() {
+        2 => Some(UniformValue::Vec2([values[0], values[1]])),
+        3 => Some(UniformValue::Vec3([values[0], values[1], values[2]])),
+        4 => Some(UniformValue::Vec4([values[0], values[1], values[2], values[3]])),
+        16 => values.try_into().ok().map(UniformValue::Mat4),
+        _ => None,
+    }
+}
+
+/// The path of `node` below `root`: the names (or, for unnamed nodes, sibling indices) of
+/// the nodes from `root` down, separated by `/`; empty for `root` itself, and `None` if
+/// `node` isn't under `root`.
+pub fn node_path(root: &Rc<RefCell<Object3D>>, node: &Rc<RefCell<Object3D>>) -> Option<String> {
+    let mut segments = Vec::new();
+    let mut current = node.clone();
+    while !Rc::ptr_eq(&current, root) {
+        let parent = current.borrow().parent()?;
+        segments.push(path_segment(&parent.borrow(), &current));
+        current = parent;
+    }
+    segments.reverse();
+    Some(segments.join("/"))
+}
+
+/// The segment naming `child` among the children of `parent`.
+fn path_segment(parent: &Object3D, child: &Rc<RefCell<Object3D>>) -> String {
+    let index = parent.children().iter().position(|other| Rc::ptr_eq(other, child)).unwrap_or(0);
+    match child.borrow().name() {
+        // Names that look like indices, or contain the separator, can't be told apart
+        Some(name) if !name.contains('/') && name.parse::<usize>().is_err() => name.to_string(),
+        _ => index.to_string(),
+    }
+}
+
+/// The node at `path` below `root` (see [`node_path`]): each segment is the name of a child,
+/// the first with that name, or else the index of one.
+pub fn resolve_path(root: &Rc<RefCell<Object3D>>, path: &str) -> Option<Rc<RefCell<Object3D>>> {
+    let mut node = root.clone();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let next = {
+            let current = node.borrow();
+            let children = current.children();
+            children
+                .iter()
+                .find(|child| child.borrow().name() == Some(segment))
+                .or_else(|| segment.parse::<usize>().ok().and_then(|index| children.get(index)))
+                .cloned()?
+        };
+        node = next;
+    }
+    Some(node)
+}
+
+/// The live-editable state of one node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// The nodes of a scene by [path](node_path), as an editor shows them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneState {
+    pub nodes: BTreeMap<String, NodeSummary>,
+}
+
+impl SceneState {
+    /// Captures every node of `scene` but the root.
+    pub fn capture(scene: &Scene) -> Self {
+        let mut nodes = BTreeMap::new();
+        scene.traverse(|node| {
+            if Rc::ptr_eq(node, scene.root()) {
+                return;
+            }
+            let Some(path) = node_path(scene.root(), node) else {
+                return;
+            };
+            let node = node.borrow();
+            let summary = NodeSummary {
+                name: node.name().map(str::to_string),
+                position: node.position(),
+                rotation: node.rotation(),
+                scale: node.scale(),
+                tags: node.tags().to_vec(),
+            };
+            nodes.insert(path, summary);
+        });
+        Self { nodes }
+    }
+
+    /// The patches turning a scene in this state into one in state `newer`: nodes added
+    /// (parents first), transforms and tags changed, and nodes removed (children first).
+    /// Nodes are matched by path, so an unnamed node whose siblings before it changed
+    /// looks like a different node.
+    pub fn diff(&self, newer: &SceneState) -> Vec<ScenePatch> {
+        let mut patches = Vec::new();
+        // Paths sort parents before their children
+        for (path, node) in &newer.nodes {
+            let Some(old) = self.nodes.get(path) else {
+                let (parent, _) = path.rsplit_once('/').unwrap_or(("", path));
+                let name = node.name.clone().unwrap_or_default();
+                patches.push(ScenePatch::Add { parent: parent.to_string(), name });
+                patches.push(ScenePatch::Transform {
+                    node: path.clone(),
+                    position: Some(node.position),
+                    rotation: Some(node.rotation),
+                    scale: Some(node.scale),
+                });
+                if !node.tags.is_empty() {
+                    patches.push(ScenePatch::Set { node: path.clone(), property: "tags".into(), value: json!(node.tags) });
+                }
+                continue;
+            };
+            let changed = |old: &[f32], new: &[f32]| old != new;
+            if changed(&old.position, &node.position) || changed(&old.rotation, &node.rotation) || changed(&old.scale, &node.scale) {
+                patches.push(ScenePatch::Transform {
+                    node: path.clone(),
+                    position: changed(&old.position, &node.position).then_some(node.position),
+                    rotation: changed(&old.rotation, &node.rotation).then_some(node.rotation),
+                    scale: changed(&old.scale, &node.scale).then_some(node.scale),
+                });
+            }
+            if old.tags != node.tags {
+                patches.push(ScenePatch::Set { node: path.clone(), property: "tags".into(), value: json!(node.tags) });
+            }
+        }
+        for path in self.nodes.keys().rev() {
+            if !newer.nodes.contains_key(path) {
+                patches.push(ScenePatch::Remove { node: path.clone() });
+            }
+        }
+        patches
+    }
+}
+
+/// A connected editor.
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    address: SocketAddr,
+    /// Received bytes of a line not complete yet.
+    incoming: Vec<u8>,
+    /// Answers not yet sent.
+    outgoing: Vec<u8>,
+}
+
+impl Client {
+    /// Reads what has arrived, up to [`MAX_POLL_BYTES`], returning the complete lines; `Err`
+    /// once the connection is closed or broken, or a line grows past [`MAX_LINE_BYTES`].
+    fn receive(&mut self) -> io::Result<Vec<String>> {
+        let mut buffer = [0u8; 4096];
+        let mut lines = Vec::new();
+        let mut received = 0;
+        while received < MAX_POLL_BYTES {
+            let read = match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            received += read;
+            let mut chunk = &buffer[..read];
+            while let Some(end) = chunk.iter().position(|&byte| byte == b'\n') {
+                self.incoming.extend_from_slice(&chunk[..end]);
+                lines.push(String::from_utf8_lossy(&self.incoming).trim().to_string());
+                self.incoming.clear();
+                chunk = &chunk[end + 1..];
+            }
+            self.incoming.extend_from_slice(chunk);
+            if self.incoming.len() > MAX_LINE_BYTES {
+                return Err(io::Error::new(ErrorKind::InvalidData, "line too long"));
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Queues `message` as one line.
+    fn send(&mut self, message: &Value) {
+        self.outgoing.extend(message.to_string().bytes());
+        self.outgoing.push(b'\n');
+    }
+
+    /// Sends as much of the queued answers as the socket takes without blocking; `Err` if
+    /// the connection is broken or more than [`MAX_QUEUED_BYTES`] stay queued.
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(written) => {
+                    self.outgoing.drain(..written);
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        if self.outgoing.len() > MAX_QUEUED_BYTES {
+            return Err(io::Error::new(ErrorKind::OutOfMemory, "answers aren't being read"));
+        }
+        Ok(())
+    }
+}
+
+/// Listens for editors and applies the patches they send; see the
+/// [module documentation](self). Never blocks.
+#[derive(Debug)]
+pub struct LiveSync {
+    listener: TcpListener,
+    clients: Vec<Client>,
+}
+
+impl LiveSync {
+    /// Starts listening on `address`, e.g. `"127.0.0.1:7878"`.
+    pub fn listen(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, clients: Vec::new() })
+    }
+
+    /// The address listened on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Number of editors connected.
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accepts new editors, applies the patches received since the last poll to `scene` in
+    /// the order they arrived, and answers them. Returns the number of patches applied.
+    pub fn poll(&mut self, scene: &Scene) -> usize {
+        self.accept();
+        let mut applied = 0;
+        self.clients.retain_mut(|client| {
+            let lines = match client.receive() {
+                Ok(lines) => lines,
+                Err(error) => {
+                    if error.kind() != ErrorKind::UnexpectedEof {
+                        eprintln!("Warning: Live sync connection from {} failed: {error}", client.address);
+                    }
+                    return false;
+                }
+            };
+            for line in lines.iter().filter(|line| !line.is_empty()) {
+                let answer = answer(scene, line, &mut applied);
+                client.send(&answer);
+            }
+            client.flush().is_ok()
+        });
+        applied
+    }
+
+    /// Sends `patches` to every connected editor, as one line, e.g. the
+    /// [diff](SceneState::diff) of changes made in game.
+    pub fn broadcast(&mut self, patches: &[ScenePatch]) {
+        if patches.is_empty() {
+            return;
+        }
+        let message = json!(patches);
+        self.clients.retain_mut(|client| {
+            client.send(&message);
+            client.flush().is_ok()
+        });
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    if !address.ip().is_loopback() {
+                        eprintln!("Warning: Refused live sync connection from {address}, which isn't local");
+                        continue;
+                    }
+                    if let Err(error) = stream.set_nonblocking(true) {
+                        eprintln!("Warning: Live sync connection from {address} failed: {error}");
+                        continue;
+                    }
+                    self.clients.push(Client { stream, address, incoming: Vec::new(), outgoing: Vec::new() });
+                }
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    eprintln!("Warning: Live sync failed to accept a connection: {error}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Applies the patch or patches on `line` and returns the answer, counting applied patches.
+/// If one fails, those before it are reverted.
+fn answer(scene: &Scene, line: &str, applied: &mut usize) -> Value {
+    let patches = match serde_json::from_str::<Value>(line) {
+        Ok(Value::Array(values)) => values.into_iter().map(serde_json::from_value).collect(),
+        Ok(value) => serde_json::from_value(value).map(|patch| vec![patch]),
+        Err(error) => Err(error),
+    };
+    let patches: Vec<ScenePatch> = match patches {
+        Ok(patches) => patches,
+        Err(error) => return json!({ "ok": false, "error": format!("Invalid patch: {error}") }),
+    };
+    let mut answer = json!({ "ok": true });
+    let mut undo = Vec::with_capacity(patches.len());
+    for patch in &patches {
+        match patch.apply_undoable(scene) {
+            Ok(step) => undo.push(step),
+            Err(error) => {
+                // The line is applied as a whole or not at all
+                for step in undo.into_iter().rev() {
+                    step.revert(scene);
+                }
+                return json!({ "ok": false, "error": error });
+            }
+        }
+        if *patch == ScenePatch::Snapshot {
+            answer["scene"] = json!(SceneState::capture(scene));
+        }
+    }
+    *applied += patches.iter().filter(|patch| **patch != ScenePatch::Snapshot).count();
+    answer
+}
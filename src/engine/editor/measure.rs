@@ -0,0 +1,100 @@
+//! Distance measurement between picked points.
+//!
+//! An editor adds points to a [`Measurement`] as the user picks them (for example with
+//! [`SnapSettings::snap_screen_to_plane`](crate::engine::editor::snapping::SnapSettings::snap_screen_to_plane)
+//! or [`Scene::raycast`](crate::engine::scene::Scene::raycast)), and calls [`Measurement::draw`] every frame to show the
+//! path with the length of each segment and the total.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::editor::measure::Measurement;
+//! let mut tape = Measurement::new();
+//! tape.add_point([0.0, 0.0, 0.0]);
+//! tape.add_point([3.0, 0.0, 0.0]);
+//! tape.add_point([3.0, 4.0, 0.0]);
+//! assert_eq!(tape.segment_lengths(), vec![3.0, 4.0]);
+//! assert_eq!(tape.total_length(), 7.0);
+//! assert_eq!(tape.straight_distance(), Some(5.0));
+//! ```
+
+use crate::engine::debug::draw::DebugDraw;
+use crate::engine::math::color::Color;
+use crate::engine::math::vec::{distance, lerp};
+
+/// A path of picked points to measure along.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measurement {
+    /// Picked points, in order.
+    pub points: Vec<[f32; 3]>,
+
+    /// Color of the path, markers and labels.
+    pub color: Color,
+
+    /// Suffix appended to displayed lengths, e.g. `"m"`.
+    pub unit: String,
+}
+
+impl Default for Measurement {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Measurement {
+    /// An empty measurement drawn in yellow, in meters.
+    pub fn new() -> Self {
+        Self { points: Vec::new(), color: Color::linear_rgb(1.0, 0.85, 0.1), unit: "m".to_string() }
+    }
+
+    /// Appends a picked point.
+    pub fn add_point(&mut self, point: [f32; 3]) {
+        self.points.push(point);
+    }
+
+    /// Removes the last picked point, e.g. on undo.
+    pub fn pop_point(&mut self) -> Option<[f32; 3]> {
+        self.points.pop()
+    }
+
+    /// Removes all points.
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    /// Length of each segment between consecutive points.
+    pub fn segment_lengths(&self) -> Vec<f32> {
+        self.points.windows(2).map(|pair| distance(pair[0], pair[1])).collect()
+    }
+
+    /// Length of the whole path.
+    pub fn total_length(&self) -> f32 {
+        self.segment_lengths().iter().sum()
+    }
+
+    /// Direct distance from the first to the last point, if at least two points were picked.
+    pub fn straight_distance(&self) -> Option<f32> {
+        match self.points.as_slice() {
+            [first, .., last] => Some(distance(*first, *last)),
+            _ => None,
+        }
+    }
+
+    /// Submits the path to `debug`: a marker per point of size `marker_size`, each segment
+    /// labelled with its length and, for paths with several segments, the total at the end.
+    pub fn draw(&self, debug: &mut DebugDraw, marker_size: f32) {
+        for &point in &self.points {
+            debug.point(point, marker_size, self.color);
+        }
+        debug.polyline(&self.points, self.color);
+
+        for pair in self.points.windows(2) {
+            let length = distance(pair[0], pair[1]);
+            debug.label(lerp(pair[0], pair[1], 0.5), self.color, &format!("{:.3} {}", length, self.unit));
+        }
+        if self.points.len() > 2
+            && let Some(&last) = self.points.last()
+        {
+            debug.label(last, Color::WHITE, &format!("total {:.3} {}", self.total_length(), self.unit));
+        }
+    }
+}
@@ -0,0 +1,16 @@
+//! Editor-layer utilities: selection with GPU picking and outlines, a clipboard for copying
+//! subtrees, snapping for gizmo drags, measurement tools, and live sync with an external
+//! editor process.
+//!
+//! These are independent of any particular editor UI; an editor feeds them the values its
+//! gizmos produce (dragged positions, rotation angles, picked points) and draws the results
+//! through the renderer's [`DebugDraw`](crate::engine::debug::draw::DebugDraw) layer. The
+//! renderer owns a [`Selection`](selection::Selection) and draws its outlines itself.
+
+pub mod snapping;
+pub mod measure;
+pub mod picking;
+pub mod outline;
+pub mod selection;
+pub mod clipboard;
+pub mod live_sync;
@@ -0,0 +1,122 @@
+//! Selection outlines.
+//!
+//! [`draw_outlines`] draws a solid silhouette border around nodes (and their descendants)
+//! on top of the already rendered scene. It uses the stencil buffer: the nodes are first
+//! written into the stencil as a mask, then drawn again pushed outwards by the outline width
+//! wherever the mask is not set, so only the border around the silhouette is colored. The
+//! outline stays visible through occluders, which is what an editor wants for selections.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::camera::Camera;
+use crate::engine::color_space::output_color;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::normal_matrix;
+use crate::engine::object3d::Object3D;
+use crate::engine::shader::builtin_program;
+
+/// Appearance of selection outlines.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutlineStyle {
+    /// Outline color.
+    pub color: Color,
+
+    /// Outline width in pixels.
+    pub width: f32,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self { color: Color::srgb(1.0, 0.6, 0.1), width: 3.0 }
+    }
+}
+
+/// Draws outlines around `nodes` and their descendants into the bound framebuffer, which
+/// must have a stencil buffer. `viewport_size` is the framebuffer size in pixels. Requires a
+/// current GL context; depth, stencil, blend, cull and write-mask state is restored to the
+/// engine defaults afterwards.
+pub fn draw_outlines(
+    nodes: &[Rc<RefCell<Object3D>>],
+    camera: &Camera,
+    viewport_size: [u32; 2],
+    style: &OutlineStyle,
+) {
+    let mut meshes = Vec::new();
+    for node in nodes {
+        collect_meshes(node, &mut meshes);
+    }
+    if meshes.is_empty() {
+        return;
+    }
+
+    let shader = builtin_program(
+        "outline",
+        include_str!("../shaders/outline.vert"),
+        include_str!("../shaders/outline.frag"),
+    );
+    shader.use_program();
+    shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+    shader.set_uniform_vec2("u_viewport_size", [viewport_size[0].max(1) as f32, viewport_size[1].max(1) as f32]);
+    shader.set_uniform_vec4("u_color", output_color(style.color));
+
+    let draw_all = |width: f32| {
+        shader.set_uniform_float("u_width", width);
+        for (node, world_matrix) in &meshes {
+            let node = node.borrow();
+            shader.set_uniform_matrix4("u_model", world_matrix);
+            shader.set_uniform_matrix3("u_normal_matrix", &normal_matrix(world_matrix));
+            shader.set_uniform_int("u_instanced", node.instances().is_some() as i32);
+            node.draw_geometry();
+        }
+    };
+
+    unsafe {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::FALSE);
+        gl::Disable(gl::CULL_FACE);
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::STENCIL_TEST);
+        gl::StencilMask(0xFF);
+        gl::ClearStencil(0);
+        gl::Clear(gl::STENCIL_BUFFER_BIT);
+
+        // Pass 1: mark the silhouettes in the stencil buffer without touching color
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+    }
+    draw_all(0.0);
+
+    unsafe {
+        // Pass 2: draw the extruded shapes only outside the silhouettes
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        gl::StencilFunc(gl::NOTEQUAL, 1, 0xFF);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+    }
+    draw_all(style.width);
+
+    unsafe {
+        gl::Disable(gl::STENCIL_TEST);
+        gl::StencilFunc(gl::ALWAYS, 0, 0xFF);
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::TRUE);
+    }
+}
+
+/// Gathers `node` and its descendants that have geometry and aren't hidden, with their
+/// world matrices.
+fn collect_meshes(node: &Rc<RefCell<Object3D>>, meshes: &mut Vec<(Rc<RefCell<Object3D>>, [f32; 16])>) {
+    let children = {
+        let mut object = node.borrow_mut();
+        if !object.visible() {
+            return;
+        }
+        if object.geometry().is_some() {
+            meshes.push((node.clone(), object.world_matrix()));
+        }
+        object.children().to_vec()
+    };
+    for child in &children {
+        collect_meshes(child, meshes);
+    }
+}
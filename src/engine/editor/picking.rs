@@ -0,0 +1,251 @@
+//! GPU picking buffer: which node is under a pixel.
+//!
+//! A [`PickingBuffer`] renders every visible node with geometry into an offscreen integer
+//! texture, writing a per-node id instead of a color. Reading the id back under the cursor
+//! answers "what did the user click on" exactly, with depth testing and instancing taken into
+//! account, and reading a rectangle gives every node visible inside a selection box.
+//!
+//! ```no_run
+//! # use rustge::engine::{camera::Camera, editor::picking::PickingBuffer, scene::Scene};
+//! # let (scene, camera) = (Scene::new(), Camera::new(1.0));
+//! let mut picking = PickingBuffer::new();
+//! picking.render(scene.root(), &camera, [800, 600]);
+//! if let Some(node) = picking.node_at([400, 300]) {
+//!     node.borrow_mut().set_scale([1.1, 1.1, 1.1]);
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::{Rc, Weak};
+use gl::types::{GLint, GLsizei, GLuint};
+use crate::engine::camera::Camera;
+use crate::engine::object3d::Object3D;
+use crate::engine::readback::{Readback, ReadbackFormat};
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// An offscreen buffer of node ids, rendered on demand.
+#[derive(Debug, Default)]
+pub struct PickingBuffer {
+    /// GL objects, created on the first render and recreated when the size changes.
+    target: Option<PickingTarget>,
+
+    /// The node drawn with id `index + 1` in the last render.
+    nodes: Vec<Weak<RefCell<Object3D>>>,
+}
+
+impl PickingBuffer {
+    /// An empty picking buffer. GL resources are created on the first render.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the ids of every node under `root` visible from `camera` into a buffer of
+    /// `size` pixels (usually the window size). Requires a current GL context; the default
+    /// framebuffer and viewport are restored afterwards.
+    pub fn render(&mut self, root: &Rc<RefCell<Object3D>>, camera: &Camera, size: [u32; 2]) {
+        let size = [size[0].max(1), size[1].max(1)];
+        if self.target.as_ref().is_none_or(|target| target.size != size) {
+            self.target = Some(PickingTarget::new(size));
+        }
+        let Some(target) = &self.target else {
+            return;
+        };
+
+        let shader = builtin_program(
+            "picking",
+            include_str!("../shaders/picking.vert"),
+            include_str!("../shaders/picking.frag"),
+        );
+
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+            gl::Viewport(0, 0, size[0] as GLsizei, size[1] as GLsizei);
+            gl::ClearBufferuiv(gl::COLOR, 0, [0u32; 4].as_ptr());
+            gl::DepthMask(gl::TRUE);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+        }
+
+        shader.use_program();
+        shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+
+        self.nodes.clear();
+        for node in Object3D::visible_set(root, camera) {
+            let mut object = node.borrow_mut();
+            if object.geometry().is_none() {
+                continue;
+            }
+            self.nodes.push(Rc::downgrade(&node));
+            shader.set_uniform_uint("u_id", self.nodes.len() as u32);
+            shader.set_uniform_matrix4("u_model", &object.world_matrix());
+            shader.set_uniform_int("u_instanced", object.instances().is_some() as i32);
+            object.draw_geometry();
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+        }
+    }
+
+    /// The node at `pixel` (origin top-left) in the last render, if any.
+    pub fn node_at(&self, pixel: [u32; 2]) -> Option<Rc<RefCell<Object3D>>> {
+        self.nodes_in_rect(pixel, pixel).into_iter().next()
+    }
+
+    /// Every node visible inside the rectangle between the pixels `a` and `b` (inclusive,
+    /// origin top-left) in the last render, in scene order. Waits for the GPU to finish the
+    /// render; see [`nodes_in_rect_async`](Self::nodes_in_rect_async) for a read that doesn't.
+    pub fn nodes_in_rect(&self, a: [u32; 2], b: [u32; 2]) -> Vec<Rc<RefCell<Object3D>>> {
+        let (Some(target), Some([x, y, w, h])) = (&self.target, self.gl_rect(a, b)) else {
+            return Vec::new();
+        };
+
+        let mut ids = vec![0u32; (w * h) as usize];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, target.fbo);
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+            gl::ReadPixels(
+                x as GLint,
+                y as GLint,
+                w as GLsizei,
+                h as GLsizei,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                ids.as_mut_ptr() as *mut _,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+        resolve_ids(ids, &self.nodes)
+    }
+
+    /// Starts reading the same rectangle as [`nodes_in_rect`](Self::nodes_in_rect) without
+    /// waiting for the GPU. Poll the returned pick on later frames; it keeps the node list of
+    /// the last render, so rendering again meanwhile doesn't change its result.
+    pub fn nodes_in_rect_async(&self, a: [u32; 2], b: [u32; 2]) -> PendingPick {
+        let readback = match (&self.target, self.gl_rect(a, b)) {
+            (Some(target), Some(rect)) => Some(Readback::framebuffer(target.fbo, rect, ReadbackFormat::R32Uint)),
+            _ => None,
+        };
+        PendingPick { readback, nodes: self.nodes.clone(), taken: false }
+    }
+
+    /// The rectangle between `a` and `b` (origin top-left) clamped to the buffer, as
+    /// `[x, y, width, height]` with GL's bottom-left origin.
+    fn gl_rect(&self, a: [u32; 2], b: [u32; 2]) -> Option<[u32; 4]> {
+        let [width, height] = self.target.as_ref()?.size;
+        let x0 = a[0].min(b[0]).min(width - 1);
+        let x1 = a[0].max(b[0]).min(width - 1);
+        let y0 = a[1].min(b[1]).min(height - 1);
+        let y1 = a[1].max(b[1]).min(height - 1);
+        // GL rows start at the bottom
+        Some([x0, height - 1 - y1, x1 - x0 + 1, y1 - y0 + 1])
+    }
+}
+
+/// A pick started by [`PickingBuffer::nodes_in_rect_async`].
+#[derive(Debug)]
+pub struct PendingPick {
+    /// `None` if nothing had been rendered, so the pick is empty.
+    readback: Option<Readback>,
+    nodes: Vec<Weak<RefCell<Object3D>>>,
+    taken: bool,
+}
+
+impl PendingPick {
+    /// The picked nodes once the GPU has caught up, in scene order; `None` while it hasn't,
+    /// and after the result was taken once. Never blocks.
+    pub fn try_take(&mut self) -> Option<Vec<Rc<RefCell<Object3D>>>> {
+        if self.taken {
+            return None;
+        }
+        let ids = match &mut self.readback {
+            Some(readback) => {
+                let bytes = readback.try_take()?;
+                bytes.chunks_exact(4).map(|id| u32::from_ne_bytes([id[0], id[1], id[2], id[3]])).collect()
+            }
+            None => Vec::new(),
+        };
+        self.taken = true;
+        Some(resolve_ids(ids, &self.nodes))
+    }
+}
+
+/// The distinct live nodes behind the non-zero `ids`, in id order.
+fn resolve_ids(ids: Vec<u32>, nodes: &[Weak<RefCell<Object3D>>]) -> Vec<Rc<RefCell<Object3D>>> {
+    ids.into_iter()
+        .filter(|&id| id > 0)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|id| nodes.get(id as usize - 1)?.upgrade())
+        .collect()
+}
+
+/// Framebuffer with an id texture and a depth buffer.
+#[derive(Debug)]
+struct PickingTarget {
+    fbo: GLuint,
+    ids: GLuint,
+    depth: GLuint,
+    size: [u32; 2],
+}
+
+impl PickingTarget {
+    fn new(size: [u32; 2]) -> Self {
+        let (width, height) = (size[0] as GLsizei, size[1] as GLsizei);
+        let (mut fbo, mut ids, mut depth) = (0, 0, 0);
+        unsafe {
+            gl::GenTextures(1, &mut ids);
+            gl::BindTexture(gl::TEXTURE_2D, ids);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::R32UI as GLint,
+                width,
+                height,
+                0,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, ids, 0);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        let bytes = (size[0] * size[1] * 4) as usize;
+        track_gpu_allocation(GpuResourceKind::RenderTarget, ids, bytes, "picking ids");
+        track_gpu_allocation(GpuResourceKind::RenderTarget, depth, bytes, "picking depth");
+        Self { fbo, ids, depth, size }
+    }
+}
+
+impl Drop for PickingTarget {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.ids);
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.depth);
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.ids);
+            gl::DeleteRenderbuffers(1, &self.depth);
+        }
+    }
+}
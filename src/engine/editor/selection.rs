@@ -0,0 +1,273 @@
+//! Selection set of scene nodes and the mouse gestures that edit it.
+//!
+//! A [`Selection`] is the engine-level answer to "which nodes is the user working on". The
+//! renderer owns one (see
+//! [`Renderer::selection`](crate::engine::renderer::Renderer::selection)), outlines its nodes
+//! every frame, and with click selection enabled updates it from the mouse: a click selects
+//! the node under the cursor, shift-click toggles it in the selection, and ctrl-dragging a
+//! box selects every node visible inside it. Inspectors and gizmos read the set back through
+//! [`nodes`](Selection::nodes), [`primary`](Selection::primary) and
+//! [`pivot`](Selection::pivot), and can poll [`revision`](Selection::revision) to notice
+//! changes.
+//!
+//! The selection holds weak references, so deleting a node from the scene also removes it
+//! from the selection.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{editor::selection::Selection, object3d::Object3D};
+//! let (a, b) = (Object3D::new(), Object3D::new());
+//! let mut selection = Selection::new();
+//! selection.click(Some(a.clone()), false);
+//! selection.click(Some(b.clone()), true); // shift-click adds
+//! assert_eq!(selection.len(), 2);
+//! assert!(std::rc::Rc::ptr_eq(&selection.primary().unwrap(), &b));
+//!
+//! selection.click(Some(a.clone()), true); // shift-click again removes
+//! assert!(!selection.contains(&a));
+//! selection.click(None, false); // clicking empty space clears
+//! assert!(selection.is_empty());
+//! ```
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use glutin::event::{ElementState, MouseButton, WindowEvent};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::vec::{add, scale};
+use crate::engine::object3d::Object3D;
+
+/// An ordered set of selected nodes. The most recently selected node is the primary one.
+#[derive(Debug, Default)]
+pub struct Selection {
+    /// Selected nodes in selection order.
+    nodes: Vec<Weak<RefCell<Object3D>>>,
+
+    /// Bumped on every change.
+    revision: u64,
+}
+
+impl Selection {
+    /// An empty selection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the selection with `node` alone.
+    pub fn select(&mut self, node: &Rc<RefCell<Object3D>>) {
+        self.nodes = vec![Rc::downgrade(node)];
+        self.changed();
+    }
+
+    /// Adds `node`, making it the primary node.
+    pub fn add(&mut self, node: &Rc<RefCell<Object3D>>) {
+        self.nodes.retain(|selected| !selected.ptr_eq(&Rc::downgrade(node)) && selected.strong_count() > 0);
+        self.nodes.push(Rc::downgrade(node));
+        self.changed();
+    }
+
+    /// Removes `node`, returning whether it was selected.
+    pub fn remove(&mut self, node: &Rc<RefCell<Object3D>>) -> bool {
+        let before = self.nodes.len();
+        self.nodes.retain(|selected| !selected.ptr_eq(&Rc::downgrade(node)));
+        let removed = self.nodes.len() != before;
+        if removed {
+            self.changed();
+        }
+        removed
+    }
+
+    /// Removes `node` if it is selected, adds it otherwise.
+    pub fn toggle(&mut self, node: &Rc<RefCell<Object3D>>) {
+        if !self.remove(node) {
+            self.add(node);
+        }
+    }
+
+    /// Deselects everything.
+    pub fn clear(&mut self) {
+        if !self.nodes.is_empty() {
+            self.nodes.clear();
+            self.changed();
+        }
+    }
+
+    /// Applies a click on `hit` (the picked node, or `None` for empty space). A plain click
+    /// selects the node alone or clears the selection; an additive (shift) click toggles the
+    /// node and ignores empty space.
+    pub fn click(&mut self, hit: Option<Rc<RefCell<Object3D>>>, additive: bool) {
+        match (hit, additive) {
+            (Some(node), false) => self.select(&node),
+            (Some(node), true) => self.toggle(&node),
+            (None, false) => self.clear(),
+            (None, true) => {}
+        }
+    }
+
+    /// Applies a box selection: replaces the selection with `nodes`, or adds them to it if
+    /// `additive`.
+    pub fn select_many(&mut self, nodes: impl IntoIterator<Item = Rc<RefCell<Object3D>>>, additive: bool) {
+        if !additive {
+            self.nodes.clear();
+        }
+        for node in nodes {
+            self.nodes.retain(|selected| !selected.ptr_eq(&Rc::downgrade(&node)));
+            self.nodes.push(Rc::downgrade(&node));
+        }
+        self.changed();
+    }
+
+    /// Whether `node` is selected.
+    pub fn contains(&self, node: &Rc<RefCell<Object3D>>) -> bool {
+        self.nodes.iter().any(|selected| selected.ptr_eq(&Rc::downgrade(node)))
+    }
+
+    /// The selected nodes still alive, in selection order.
+    pub fn nodes(&self) -> Vec<Rc<RefCell<Object3D>>> {
+        self.nodes.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// The most recently selected node still alive, which inspectors show and gizmos
+    /// usually attach to.
+    pub fn primary(&self) -> Option<Rc<RefCell<Object3D>>> {
+        self.nodes.iter().rev().find_map(Weak::upgrade)
+    }
+
+    /// Number of selected nodes still alive.
+    pub fn len(&self) -> usize {
+        self.nodes.iter().filter(|node| node.strong_count() > 0).count()
+    }
+
+    /// Whether no live node is selected.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// World-space bounds of every selected subtree, or `None` if none has geometry.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.nodes()
+            .iter()
+            .filter_map(Object3D::subtree_bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Where a gizmo for the whole selection goes: the average world position of the
+    /// selected nodes, or `None` if nothing is selected.
+    pub fn pivot(&self) -> Option<[f32; 3]> {
+        let nodes = self.nodes();
+        if nodes.is_empty() {
+            return None;
+        }
+        let sum = nodes.iter().fold([0.0; 3], |sum, node| {
+            let world = node.borrow_mut().world_matrix();
+            add(sum, [world[12], world[13], world[14]])
+        });
+        Some(scale(sum, 1.0 / nodes.len() as f32))
+    }
+
+    /// A counter increased by every change to the selection, for cheap change detection.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn changed(&mut self) {
+        self.revision += 1;
+    }
+}
+
+/// A completed selection gesture, in window pixels with the origin at the top-left.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionGesture {
+    /// The left button was clicked without dragging.
+    Click { pixel: [u32; 2], additive: bool },
+
+    /// A box was dragged with ctrl and the left button held.
+    Box { corner_a: [u32; 2], corner_b: [u32; 2], additive: bool },
+}
+
+/// Turns window mouse events into [`SelectionGesture`]s.
+///
+/// A left press and release less than [`click_tolerance`](Self::click_tolerance) pixels
+/// apart is a click, so orbit drags don't change the selection. Holding ctrl while pressing
+/// starts a box selection; holding shift makes either gesture additive.
+#[derive(Clone, Debug)]
+pub struct SelectionInput {
+    /// How far in pixels the cursor may move between press and release for a click.
+    pub click_tolerance: f64,
+
+    cursor: Option<[f64; 2]>,
+    press: Option<[f64; 2]>,
+    box_select: bool,
+    shift: bool,
+    ctrl: bool,
+}
+
+impl Default for SelectionInput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SelectionInput {
+    /// Input tracking with a 4 pixel click tolerance.
+    pub fn new() -> Self {
+        Self { click_tolerance: 4.0, cursor: None, press: None, box_select: false, shift: false, ctrl: false }
+    }
+
+    /// Feeds a window event, returning the gesture it completes, if any.
+    pub fn handle_window_event(&mut self, event: &WindowEvent) -> Option<SelectionGesture> {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift = modifiers.shift();
+                self.ctrl = modifiers.ctrl();
+            }
+            WindowEvent::CursorMoved { position, .. } => self.cursor = Some([position.x, position.y]),
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                let cursor = self.cursor?;
+                if *state == ElementState::Pressed {
+                    self.press = Some(cursor);
+                    self.box_select = self.ctrl;
+                    return None;
+                }
+                let press = self.press.take()?;
+                let box_select = std::mem::take(&mut self.box_select);
+                let to_pixel = |p: [f64; 2]| [p[0].max(0.0) as u32, p[1].max(0.0) as u32];
+                let moved = (cursor[0] - press[0]).hypot(cursor[1] - press[1]);
+                if moved <= self.click_tolerance {
+                    return Some(SelectionGesture::Click { pixel: to_pixel(cursor), additive: self.shift });
+                }
+                if box_select {
+                    return Some(SelectionGesture::Box {
+                        corner_a: to_pixel(press),
+                        corner_b: to_pixel(cursor),
+                        additive: self.shift,
+                    });
+                }
+            }
+            WindowEvent::CursorLeft { .. } | WindowEvent::Focused(false) => {
+                self.cursor = None;
+                self.press = None;
+                self.box_select = false;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Whether a box selection drag is in progress. Mouse events should not reach camera
+    /// controllers meanwhile.
+    pub fn is_box_selecting(&self) -> bool {
+        self.box_select
+    }
+
+    /// The rectangle of the box selection in progress as `[x, y, width, height]` in window
+    /// pixels, for drawing a rubber band.
+    pub fn box_rect(&self) -> Option<[f32; 4]> {
+        let (press, cursor) = (self.press?, self.cursor?);
+        if !self.box_select {
+            return None;
+        }
+        let x = press[0].min(cursor[0]) as f32;
+        let y = press[1].min(cursor[1]) as f32;
+        Some([x, y, (press[0] - cursor[0]).abs() as f32, (press[1] - cursor[1]).abs() as f32])
+    }
+}
@@ -0,0 +1,146 @@
+//! Grid, angle and scale snapping for gizmo-dragged objects.
+//!
+//! A translate gizmo usually drags a point along an axis or across a plane under the mouse
+//! cursor; [`SnapSettings::snap_screen_to_plane`] turns a cursor position straight into a
+//! snapped world position on such a plane. Rotate and scale gizmos pass their raw angle or
+//! factor through [`SnapSettings::snap_angle`] / [`SnapSettings::snap_scale`].
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::editor::snapping::SnapSettings;
+//! let snap = SnapSettings { grid: Some(0.5), angle_degrees: Some(15.0), ..SnapSettings::default() };
+//! assert_eq!(snap.snap_position([0.3, 1.1, -0.74]), [0.5, 1.0, -0.5]);
+//! assert!((snap.snap_angle(20f32.to_radians()).to_degrees() - 15.0).abs() < 1e-4);
+//! ```
+
+use crate::engine::camera::Camera;
+use crate::engine::math::quat;
+use crate::engine::math::vec::{add, dot, scale, sub};
+
+/// Snapping increments. `None` disables snapping for that kind of transform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SnapSettings {
+    /// Grid cell size for positions.
+    pub grid: Option<f32>,
+
+    /// A point on the grid; positions snap to `grid_origin + n * grid`.
+    pub grid_origin: [f32; 3],
+
+    /// Angle increment for rotations, in degrees.
+    pub angle_degrees: Option<f32>,
+
+    /// Increment for scale factors.
+    pub scale_step: Option<f32>,
+}
+
+impl Default for SnapSettings {
+    /// Snapping disabled, with the grid anchored at the origin.
+    fn default() -> Self {
+        Self { grid: None, grid_origin: [0.0; 3], angle_degrees: None, scale_step: None }
+    }
+}
+
+impl SnapSettings {
+    /// Typical editor increments: a 1 unit grid, 15 degree rotations and 0.1 scale steps.
+    pub fn editor_defaults() -> Self {
+        Self { grid: Some(1.0), angle_degrees: Some(15.0), scale_step: Some(0.1), ..Self::default() }
+    }
+
+    /// Snaps every axis of `position` to the nearest grid point.
+    pub fn snap_position(&self, position: [f32; 3]) -> [f32; 3] {
+        match self.grid {
+            Some(step) if step > 0.0 => {
+                let local = sub(position, self.grid_origin);
+                add(local.map(|v| snap_value(v, step)), self.grid_origin)
+            }
+            _ => position,
+        }
+    }
+
+    /// Snaps only the axes whose entry in `axes` is `true`, e.g. just the dragged axis of a
+    /// translate gizmo.
+    pub fn snap_position_axes(&self, position: [f32; 3], axes: [bool; 3]) -> [f32; 3] {
+        let snapped = self.snap_position(position);
+        std::array::from_fn(|i| if axes[i] { snapped[i] } else { position[i] })
+    }
+
+    /// Snaps an angle in radians to the nearest multiple of the angle increment.
+    pub fn snap_angle(&self, radians: f32) -> f32 {
+        match self.angle_degrees {
+            Some(step) if step > 0.0 => snap_value(radians.to_degrees(), step).to_radians(),
+            _ => radians,
+        }
+    }
+
+    /// The rotation a rotate gizmo should apply: `start` turned by the snapped `angle`
+    /// (radians) around the world-space `axis`.
+    pub fn snap_rotation(&self, start: [f32; 4], axis: [f32; 3], angle: f32) -> [f32; 4] {
+        quat::normalize(quat::mul(quat::from_axis_angle(axis, self.snap_angle(angle)), start))
+    }
+
+    /// Snaps a scale factor to the nearest multiple of the scale step, never reaching zero.
+    pub fn snap_scale(&self, factor: f32) -> f32 {
+        match self.scale_step {
+            Some(step) if step > 0.0 => {
+                let snapped = snap_value(factor, step);
+                if snapped == 0.0 { step.copysign(factor) } else { snapped }
+            }
+            _ => factor,
+        }
+    }
+
+    /// Casts the ray under pixel `cursor` of a `screen_size` viewport onto the plane through
+    /// `plane_point` with normal `plane_normal`, and snaps the hit to the grid. The snapped
+    /// point is moved back onto the plane along its normal, so on tilted planes it is the
+    /// plane point closest to the grid point.
+    ///
+    /// Returns `None` if the ray misses the plane (parallel, or the plane is behind the camera).
+    ///
+    /// ```
+    /// # use rustge::engine::{camera::Camera, editor::snapping::SnapSettings};
+    /// let snap = SnapSettings { grid: Some(1.0), ..SnapSettings::default() };
+    /// // The camera looks down -Z at the plane y + z = 0.3, hitting it at (0, 0, 0.3)
+    /// let camera = Camera::new(1.0);
+    /// let plane = ([0.0, 0.3, 0.0], [0.0, 1.0, 1.0]);
+    /// let [x, y, z] = snap.snap_screen_to_plane(&camera, [50.0, 50.0], [100, 100], plane.0, plane.1).unwrap();
+    /// assert!(x.abs() < 1e-5 && (y - 0.15).abs() < 1e-5 && (z - 0.15).abs() < 1e-5);
+    /// ```
+    pub fn snap_screen_to_plane(
+        &self,
+        camera: &Camera,
+        cursor: [f32; 2],
+        screen_size: [u32; 2],
+        plane_point: [f32; 3],
+        plane_normal: [f32; 3],
+    ) -> Option<[f32; 3]> {
+        let (origin, direction) = camera.screen_ray(cursor, screen_size);
+        let hit = ray_plane_intersection(origin, direction, plane_point, plane_normal)?;
+
+        // Project the grid point back onto the plane; this leaves axis-aligned planes' own
+        // coordinate untouched and keeps tilted planes' points on the plane
+        let snapped = self.snap_position(hit);
+        let distance = dot(sub(snapped, plane_point), plane_normal) / dot(plane_normal, plane_normal);
+        Some(sub(snapped, scale(plane_normal, distance)))
+    }
+}
+
+/// Where the ray `origin + t * direction` (`t >= 0`) crosses the plane through `plane_point`
+/// with normal `plane_normal`, if it does.
+pub fn ray_plane_intersection(
+    origin: [f32; 3],
+    direction: [f32; 3],
+    plane_point: [f32; 3],
+    plane_normal: [f32; 3],
+) -> Option<[f32; 3]> {
+    let denominator = dot(direction, plane_normal);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let t = dot(sub(plane_point, origin), plane_normal) / denominator;
+    (t >= 0.0).then(|| add(origin, scale(direction, t)))
+}
+
+/// Rounds `value` to the nearest multiple of `step`.
+fn snap_value(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
@@ -0,0 +1,241 @@
+//! Window and input events in engine types.
+//!
+//! The renderer's event loop translates the window events games care about (focus, size,
+//! keyboard, mouse and file drops) into [`EngineEvent`]s and passes each to the handlers
+//! registered with [`Renderer::on_event`](crate::engine::renderer::Renderer::on_event), before
+//! its own handling (camera controller, click selection, model drops). Code using them
+//! doesn't depend on glutin or winit, whose event types change between versions.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{display::FullscreenMode, event::{ButtonState, EngineEvent, Key}, renderer::Renderer};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! renderer.on_event(|renderer, event| match event {
+//!     EngineEvent::Key { key: Key::F11, state: ButtonState::Pressed, repeat: false, .. } => {
+//!         let mode = match renderer.fullscreen_mode() {
+//!             FullscreenMode::Windowed => FullscreenMode::Borderless { monitor: None },
+//!             _ => FullscreenMode::Windowed,
+//!         };
+//!         renderer.set_fullscreen(mode);
+//!     }
+//!     EngineEvent::FileDropped(path) => println!("Dropped {}", path.display()),
+//!     _ => {}
+//! });
+//! ```
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use glutin::event::{
+    ElementState,
+    ModifiersState,
+    MouseButton as WinitMouseButton,
+    MouseScrollDelta,
+    VirtualKeyCode,
+    WindowEvent,
+};
+
+/// Whether a key or button went down or up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+/// A mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    /// Extra buttons, numbered by the platform.
+    Other(u16),
+}
+
+/// Modifier keys held down, left or right.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows, Command or Super key.
+    pub logo: bool,
+}
+
+/// Unit of [`EngineEvent::MouseWheel`] deltas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScrollUnit {
+    /// Lines or notches, from a mouse wheel.
+    Lines,
+    /// Pixels, from a touchpad.
+    Pixels,
+}
+
+macro_rules! keys {
+    ($($key:ident = $winit:ident),+ $(,)?) => {
+        /// A key by its meaning in the current keyboard layout (`Key::Z` is wherever the
+        /// layout puts Z). Use the scancode of [`EngineEvent::Key`] for layout-independent
+        /// bindings such as WASD.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum Key {
+            $($key,)+
+            /// A key without an engine name, or none reported by the platform.
+            Unknown,
+        }
+
+        impl Key {
+            fn from_winit(key: Option<VirtualKeyCode>) -> Self {
+                match key {
+                    $(Some(VirtualKeyCode::$winit) => Key::$key,)+
+                    _ => Key::Unknown,
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    Digit0 = Key0, Digit1 = Key1, Digit2 = Key2, Digit3 = Key3, Digit4 = Key4,
+    Digit5 = Key5, Digit6 = Key6, Digit7 = Key7, Digit8 = Key8, Digit9 = Key9,
+    A = A, B = B, C = C, D = D, E = E, F = F, G = G, H = H, I = I, J = J, K = K, L = L, M = M,
+    N = N, O = O, P = P, Q = Q, R = R, S = S, T = T, U = U, V = V, W = W, X = X, Y = Y, Z = Z,
+    F1 = F1, F2 = F2, F3 = F3, F4 = F4, F5 = F5, F6 = F6,
+    F7 = F7, F8 = F8, F9 = F9, F10 = F10, F11 = F11, F12 = F12,
+    Escape = Escape, Tab = Tab, Space = Space, Enter = Return, Backspace = Back,
+    Insert = Insert, Delete = Delete, Home = Home, End = End, PageUp = PageUp, PageDown = PageDown,
+    Left = Left, Right = Right, Up = Up, Down = Down,
+    LeftShift = LShift, RightShift = RShift, LeftCtrl = LControl, RightCtrl = RControl,
+    LeftAlt = LAlt, RightAlt = RAlt, LeftLogo = LWin, RightLogo = RWin,
+    CapsLock = Capital, NumLock = Numlock, ScrollLock = Scroll, PrintScreen = Snapshot, Pause = Pause,
+    Numpad0 = Numpad0, Numpad1 = Numpad1, Numpad2 = Numpad2, Numpad3 = Numpad3, Numpad4 = Numpad4,
+    Numpad5 = Numpad5, Numpad6 = Numpad6, Numpad7 = Numpad7, Numpad8 = Numpad8, Numpad9 = Numpad9,
+    NumpadAdd = NumpadAdd, NumpadSubtract = NumpadSubtract, NumpadMultiply = NumpadMultiply,
+    NumpadDivide = NumpadDivide, NumpadDecimal = NumpadDecimal, NumpadEnter = NumpadEnter,
+    Minus = Minus, Equals = Equals, LeftBracket = LBracket, RightBracket = RBracket,
+    Backslash = Backslash, Semicolon = Semicolon, Apostrophe = Apostrophe, Grave = Grave,
+    Comma = Comma, Period = Period, Slash = Slash,
+}
+
+/// A window or input event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    /// The user asked to close the window. The renderer exits after the handlers ran.
+    CloseRequested,
+
+    /// The window gained (`true`) or lost (`false`) keyboard focus. Keys held while focus
+    /// is lost may never report their release.
+    Focused(bool),
+
+    /// The window's drawable area changed size, in physical pixels.
+    Resized { width: u32, height: u32 },
+
+    /// A key went down, repeated while held, or went up.
+    Key {
+        key: Key,
+        /// Platform scancode: the physical key, whatever the layout.
+        scancode: u32,
+        state: ButtonState,
+        /// Whether this is an auto-repeat of a key that is already down.
+        repeat: bool,
+    },
+
+    /// Text typed, after layout and dead-key processing; use for text fields rather than
+    /// [`Key`](EngineEvent::Key) events.
+    Text(char),
+
+    /// The modifier keys held changed.
+    ModifiersChanged(Modifiers),
+
+    /// The cursor moved, in physical pixels from the window's top-left corner.
+    CursorMoved { position: [f64; 2] },
+
+    /// The cursor entered the window.
+    CursorEntered,
+
+    /// The cursor left the window.
+    CursorLeft,
+
+    /// A mouse button went down or up.
+    MouseButton { button: MouseButton, state: ButtonState },
+
+    /// The mouse wheel or touchpad scrolled; positive `y` is away from the user.
+    MouseWheel { delta: [f32; 2], unit: ScrollUnit },
+
+    /// A file is being dragged over the window.
+    FileHovered(PathBuf),
+
+    /// The dragged files left the window without being dropped.
+    FileHoverCancelled,
+
+    /// A file was dropped onto the window.
+    FileDropped(PathBuf),
+}
+
+/// Translates window events, remembering held keys to tell repeats from presses.
+#[derive(Debug, Default)]
+pub(crate) struct EventTranslator {
+    held: HashSet<u32>,
+}
+
+impl EventTranslator {
+    /// The engine event for `event`, or `None` for events the engine doesn't expose.
+    pub(crate) fn translate(&mut self, event: &WindowEvent) -> Option<EngineEvent> {
+        Some(match event {
+            WindowEvent::CloseRequested => EngineEvent::CloseRequested,
+            WindowEvent::Focused(focused) => {
+                if !focused {
+                    self.held.clear();
+                }
+                EngineEvent::Focused(*focused)
+            }
+            WindowEvent::Resized(size) => EngineEvent::Resized { width: size.width, height: size.height },
+            WindowEvent::KeyboardInput { input, .. } => {
+                let state = button_state(input.state);
+                let repeat = match state {
+                    ButtonState::Pressed => !self.held.insert(input.scancode),
+                    ButtonState::Released => {
+                        self.held.remove(&input.scancode);
+                        false
+                    }
+                };
+                EngineEvent::Key { key: Key::from_winit(input.virtual_keycode), scancode: input.scancode, state, repeat }
+            }
+            // Control characters arrive as key events already
+            WindowEvent::ReceivedCharacter(character) if !character.is_control() => EngineEvent::Text(*character),
+            WindowEvent::ModifiersChanged(modifiers) => EngineEvent::ModifiersChanged(modifiers_from(*modifiers)),
+            WindowEvent::CursorMoved { position, .. } => EngineEvent::CursorMoved { position: [position.x, position.y] },
+            WindowEvent::CursorEntered { .. } => EngineEvent::CursorEntered,
+            WindowEvent::CursorLeft { .. } => EngineEvent::CursorLeft,
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    WinitMouseButton::Left => MouseButton::Left,
+                    WinitMouseButton::Right => MouseButton::Right,
+                    WinitMouseButton::Middle => MouseButton::Middle,
+                    WinitMouseButton::Other(index) => MouseButton::Other(*index),
+                };
+                EngineEvent::MouseButton { button, state: button_state(*state) }
+            }
+            WindowEvent::MouseWheel { delta, .. } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => EngineEvent::MouseWheel { delta: [*x, *y], unit: ScrollUnit::Lines },
+                MouseScrollDelta::PixelDelta(position) => {
+                    EngineEvent::MouseWheel { delta: [position.x as f32, position.y as f32], unit: ScrollUnit::Pixels }
+                }
+            },
+            WindowEvent::HoveredFile(path) => EngineEvent::FileHovered(path.clone()),
+            WindowEvent::HoveredFileCancelled => EngineEvent::FileHoverCancelled,
+            WindowEvent::DroppedFile(path) => EngineEvent::FileDropped(path.clone()),
+            _ => return None,
+        })
+    }
+}
+
+fn button_state(state: ElementState) -> ButtonState {
+    match state {
+        ElementState::Pressed => ButtonState::Pressed,
+        ElementState::Released => ButtonState::Released,
+    }
+}
+
+fn modifiers_from(state: ModifiersState) -> Modifiers {
+    Modifiers { shift: state.shift(), ctrl: state.ctrl(), alt: state.alt(), logo: state.logo() }
+}
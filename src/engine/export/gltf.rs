@@ -0,0 +1,466 @@
+//! glTF 2.0 export of a scene graph.
+//!
+//! [`write_gltf`] (or [`Scene::export_gltf`](crate::engine::scene::Scene::export_gltf)) writes
+//! the node hierarchy under a root, with each node's name, transform, geometry, material and light,
+//! so procedurally built or edited scenes can be opened in DCC tools and other engines. Node
+//! [metadata](crate::engine::object3d::Object3D::metadata) is written as the node's extras.
+//!
+//! - Paths ending in `.glb` are written as a single binary glTF file; any other path gets a
+//!   JSON `.gltf` file with the geometry in a `.bin` file of the same name next to it.
+//! - Geometry shared between nodes (see
+//!   [`Object3D::set_shared_geometry`](crate::engine::object3d::Object3D::set_shared_geometry))
+//!   is written once and referenced by every node using it.
+//! - Materials become metallic-roughness materials with the material color as base color.
+//!   [PBR materials](crate::engine::pbr) keep their metallic, roughness and emissive factors;
+//!   others get a roughness derived from the Phong `u_shininess` and are marked in their
+//!   extras, so importing brings them back as Phong materials. Textures and custom shaders
+//!   live only on the GPU and are not exported.
+//! - Point, spot and directional lights use the `KHR_lights_punctual` extension with their
+//!   intensity copied as is; ambient lights have no glTF equivalent and are skipped.
+//! - Instanced nodes get one child node per instance.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use serde_json::{json, Map, Value};
+use crate::engine::light::{Light, LightKind};
+use crate::engine::material::{BlendMode, CullMode, Material};
+use crate::engine::math::matrixfuncs::decompose_matrix;
+use crate::engine::object3d::{Geometry, Indices, Object3D, Topology, Vertex};
+use crate::engine::shader::UniformValue;
+
+/// Key of the material extras member naming the engine shading a material was exported from.
+pub(crate) const SHADING_EXTRA: &str = "shading";
+
+/// [`SHADING_EXTRA`] value of materials drawn with [`Material::phong`].
+pub(crate) const PHONG_SHADING: &str = "blinn-phong";
+
+/// glTF buffer view targets.
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// glTF accessor component types.
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// GLB chunk types.
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// Writes the hierarchy under `root` to `path`, as binary glTF if the path ends in `.glb`
+/// and as a `.gltf` file plus a `.bin` buffer next to it otherwise.
+pub fn write_gltf(root: &Rc<RefCell<Object3D>>, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let (document, buffer) = GltfBuilder::build(root);
+
+    let is_glb = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("glb"));
+    if is_glb {
+        return std::fs::write(path, encode_glb(document, &buffer));
+    }
+
+    let mut document = document;
+    if !buffer.is_empty() {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("scene");
+        let bin_name = format!("{stem}.bin");
+        std::fs::write(path.with_file_name(&bin_name), &buffer)?;
+        document["buffers"][0]["uri"] = json!(bin_name);
+    }
+    let text = serde_json::to_string_pretty(&document).map_err(io::Error::other)?;
+    std::fs::write(path, text)
+}
+
+/// The hierarchy under `root` encoded as a binary glTF (`.glb`) file.
+///
+/// ```
+/// # use rustge::engine::{export::gltf::glb_bytes, object3d::{Geometry, Object3D, Topology}};
+/// let line = Object3D::new();
+/// line.borrow_mut().set_geometry(Geometry::from_positions(Topology::LineStrip, &[[0.0; 3], [1.0, 2.0, 3.0]]));
+/// let root = Object3D::new();
+/// Object3D::add_child(&root, line);
+///
+/// let glb = glb_bytes(&root);
+/// assert_eq!(&glb[0..4], b"glTF");
+/// assert_eq!(glb.len() % 4, 0);
+/// ```
+pub fn glb_bytes(root: &Rc<RefCell<Object3D>>) -> Vec<u8> {
+    let (document, buffer) = GltfBuilder::build(root);
+    encode_glb(document, &buffer)
+}
+
+/// Several subtrees encoded as one binary glTF file, each node becoming a root of the glTF
+/// scene with its world transform. The nodes stay where they are in their own scene graph.
+///
+/// ```
+/// # use rustge::engine::{export::gltf::glb_bytes_for_nodes, object3d::Object3D};
+/// let parent = Object3D::new();
+/// parent.borrow_mut().set_position([5.0, 0.0, 0.0]);
+/// let child = Object3D::new();
+/// Object3D::add_child(&parent, child.clone());
+///
+/// let glb = glb_bytes_for_nodes(&[child.clone(), Object3D::new()]);
+/// assert_eq!(&glb[0..4], b"glTF");
+/// assert!(std::rc::Rc::ptr_eq(&child.borrow().parent().unwrap(), &parent));
+/// ```
+pub fn glb_bytes_for_nodes(nodes: &[Rc<RefCell<Object3D>>]) -> Vec<u8> {
+    let (document, buffer) = GltfBuilder::build_world_roots(nodes);
+    encode_glb(document, &buffer)
+}
+
+/// Packs the JSON document and binary buffer into a GLB container.
+fn encode_glb(document: Value, buffer: &[u8]) -> Vec<u8> {
+    let mut json = document.to_string().into_bytes();
+    pad_to_four(&mut json, b' ');
+    let mut bin = buffer.to_vec();
+    pad_to_four(&mut bin, 0);
+
+    let mut chunks = vec![(CHUNK_JSON, json)];
+    if !bin.is_empty() {
+        chunks.push((CHUNK_BIN, bin));
+    }
+    let length = 12 + chunks.iter().map(|(_, data)| 8 + data.len()).sum::<usize>();
+
+    let mut out = Vec::with_capacity(length);
+    for word in [GLB_MAGIC, 2, length as u32] {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+    for (kind, data) in chunks {
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(&kind.to_le_bytes());
+        out.extend_from_slice(&data);
+    }
+    out
+}
+
+/// Pads `bytes` with `fill` to a multiple of four bytes, as glTF requires for chunk and
+/// buffer view alignment.
+fn pad_to_four(bytes: &mut Vec<u8>, fill: u8) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(fill);
+    }
+}
+
+/// Collects the glTF arrays while walking the scene graph.
+#[derive(Default)]
+struct GltfBuilder {
+    nodes: Vec<Value>,
+    meshes: Vec<Value>,
+    materials: Vec<Value>,
+    accessors: Vec<Value>,
+    buffer_views: Vec<Value>,
+    lights: Vec<Value>,
+    buffer: Vec<u8>,
+
+    /// Primitive attributes (and indices) already written for each shared geometry.
+    primitives: HashMap<*const Geometry, Value>,
+
+    /// Mesh index for each (geometry, material index) pair.
+    mesh_ids: HashMap<(*const Geometry, Option<usize>), usize>,
+}
+
+impl GltfBuilder {
+    /// Builds the JSON document and binary buffer for the hierarchy under `root`.
+    fn build(root: &Rc<RefCell<Object3D>>) -> (Value, Vec<u8>) {
+        let mut builder = Self::default();
+        let root_index = builder.add_node(&root.borrow());
+        builder.finish(vec![root_index])
+    }
+
+    /// Builds a document with each of `roots` as a scene root, placed at its world transform.
+    fn build_world_roots(roots: &[Rc<RefCell<Object3D>>]) -> (Value, Vec<u8>) {
+        let mut builder = Self::default();
+        let mut root_indices = Vec::new();
+        for root in roots {
+            let world = root.borrow_mut().world_matrix();
+            let index = builder.add_node(&root.borrow());
+
+            // Replace the local transform written by add_node with the world one
+            let (position, rotation, scale) = decompose_matrix(&world);
+            if let Value::Object(node) = &mut builder.nodes[index] {
+                node.insert("translation".into(), json!(position));
+                node.insert("rotation".into(), json!(rotation));
+                node.insert("scale".into(), json!(scale));
+            }
+            root_indices.push(index);
+        }
+        builder.finish(root_indices)
+    }
+
+    /// Assembles the document from the collected arrays, with `roots` as the scene's nodes.
+    fn finish(self, roots: Vec<usize>) -> (Value, Vec<u8>) {
+        let mut document = Map::new();
+        document.insert("asset".into(), json!({ "version": "2.0", "generator": "rustge" }));
+        document.insert("scene".into(), json!(0));
+        document.insert("scenes".into(), json!([{ "nodes": roots }]));
+        for (key, values) in [
+            ("nodes", self.nodes),
+            ("meshes", self.meshes),
+            ("materials", self.materials),
+            ("accessors", self.accessors),
+            ("bufferViews", self.buffer_views),
+        ] {
+            // glTF forbids empty top-level arrays
+            if !values.is_empty() {
+                document.insert(key.into(), Value::Array(values));
+            }
+        }
+        if !self.buffer.is_empty() {
+            document.insert("buffers".into(), json!([{ "byteLength": self.buffer.len() }]));
+        }
+        if !self.lights.is_empty() {
+            document.insert("extensionsUsed".into(), json!(["KHR_lights_punctual"]));
+            document.insert("extensions".into(), json!({ "KHR_lights_punctual": { "lights": self.lights } }));
+        }
+        (Value::Object(document), self.buffer)
+    }
+
+    /// Adds `node` and its descendants, returning the node's index.
+    fn add_node(&mut self, node: &Object3D) -> usize {
+        let mut json = Map::new();
+        if let Some(name) = node.name() {
+            json.insert("name".into(), json!(name));
+        }
+        if node.position != [0.0; 3] {
+            json.insert("translation".into(), json!(node.position));
+        }
+        if node.rotation != [0.0, 0.0, 0.0, 1.0] {
+            json.insert("rotation".into(), json!(node.rotation));
+        }
+        if node.scale != [1.0; 3] {
+            json.insert("scale".into(), json!(node.scale));
+        }
+        if let Some(light) = node.light().and_then(|light| self.add_light(light)) {
+            json.insert("extensions".into(), json!({ "KHR_lights_punctual": { "light": light } }));
+        }
+        if !node.metadata_entries().is_empty() {
+            json.insert("extras".into(), json!(node.metadata_entries()));
+        }
+
+        let mesh = node
+            .shared_geometry()
+            .filter(|geometry| !geometry.vertices.is_empty() && !geometry.indices.is_empty())
+            .map(|geometry| self.add_mesh(&geometry, node.material()));
+
+        // Reserve the slot so parents come before their children
+        let index = self.nodes.len();
+        self.nodes.push(Value::Null);
+
+        let mut children = Vec::new();
+        match (mesh, node.instances()) {
+            (Some(mesh), Some(instances)) => {
+                for (_, matrix) in instances.iter() {
+                    children.push(self.nodes.len());
+                    self.nodes.push(json!({ "mesh": mesh, "matrix": matrix }));
+                }
+            }
+            (Some(mesh), None) => {
+                json.insert("mesh".into(), json!(mesh));
+            }
+            (None, _) => {}
+        }
+        for child in node.children() {
+            children.push(self.add_node(&child.borrow()));
+        }
+        if !children.is_empty() {
+            json.insert("children".into(), json!(children));
+        }
+
+        self.nodes[index] = Value::Object(json);
+        index
+    }
+
+    /// Returns the mesh drawing `geometry` with `material`, writing it on first use.
+    fn add_mesh(&mut self, geometry: &Rc<Geometry>, material: Option<&Material>) -> usize {
+        let material = material.map(|material| self.add_material(material));
+        let key = (Rc::as_ptr(geometry), material);
+        if let Some(&mesh) = self.mesh_ids.get(&key) {
+            return mesh;
+        }
+
+        let mut primitive = match self.primitives.get(&key.0) {
+            Some(primitive) => primitive.clone(),
+            None => {
+                let primitive = self.add_primitive(geometry);
+                self.primitives.insert(key.0, primitive.clone());
+                primitive
+            }
+        };
+        if let Some(material) = material {
+            primitive["material"] = json!(material);
+        }
+
+        let mesh = self.meshes.len();
+        self.meshes.push(json!({ "primitives": [primitive] }));
+        self.mesh_ids.insert(key, mesh);
+        mesh
+    }
+
+    /// Writes the vertex and index data of `geometry`, returning the primitive without a
+    /// material.
+    fn add_primitive(&mut self, geometry: &Geometry) -> Value {
+        let stride = std::mem::size_of::<Vertex>();
+        let vertex_bytes: Vec<u8> = geometry
+            .vertices
+            .iter()
+            .flat_map(|vertex| {
+                // glTF requires unit normals, so degenerate ones point up rather than fail validation
+                let [x, y, z] = vertex.normal;
+                let length = (x * x + y * y + z * z).sqrt();
+                let normal = if length > f32::EPSILON { [x / length, y / length, z / length] } else { [0.0, 1.0, 0.0] };
+                // glTF puts the texture origin at the top-left corner, the engine at the bottom-left
+                let uv = [vertex.uv[0], 1.0 - vertex.uv[1]];
+                vertex.position.into_iter().chain(normal).chain(uv)
+            })
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let vertex_view = self.add_buffer_view(&vertex_bytes, Some(stride), ARRAY_BUFFER);
+
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for vertex in &geometry.vertices {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex.position[axis]);
+                max[axis] = max[axis].max(vertex.position[axis]);
+            }
+        }
+
+        let count = geometry.vertices.len();
+        let position = self.add_accessor(json!({
+            "bufferView": vertex_view, "byteOffset": 0, "componentType": FLOAT,
+            "count": count, "type": "VEC3", "min": min, "max": max,
+        }));
+        let normal = self.add_accessor(json!({
+            "bufferView": vertex_view, "byteOffset": 12, "componentType": FLOAT, "count": count, "type": "VEC3",
+        }));
+        let uv = self.add_accessor(json!({
+            "bufferView": vertex_view, "byteOffset": 24, "componentType": FLOAT, "count": count, "type": "VEC2",
+        }));
+
+        let (index_bytes, component_type): (Vec<u8>, u32) = match &geometry.indices {
+            Indices::U16(indices) => (indices.iter().flat_map(|i| i.to_le_bytes()).collect(), UNSIGNED_SHORT),
+            Indices::U32(indices) => (indices.iter().flat_map(|i| i.to_le_bytes()).collect(), UNSIGNED_INT),
+        };
+        let index_view = self.add_buffer_view(&index_bytes, None, ELEMENT_ARRAY_BUFFER);
+        let indices = self.add_accessor(json!({
+            "bufferView": index_view, "componentType": component_type,
+            "count": geometry.indices.len(), "type": "SCALAR",
+        }));
+
+        let mode = match geometry.topology {
+            Topology::Points => 0,
+            Topology::Lines => 1,
+            Topology::LineStrip => 3,
+            Topology::Triangles => 4,
+        };
+        json!({
+            "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": uv },
+            "indices": indices,
+            "mode": mode,
+        })
+    }
+
+    /// Appends `bytes` to the buffer, 4-byte aligned, and returns the new buffer view.
+    fn add_buffer_view(&mut self, bytes: &[u8], stride: Option<usize>, target: u32) -> usize {
+        pad_to_four(&mut self.buffer, 0);
+        let mut view = json!({
+            "buffer": 0, "byteOffset": self.buffer.len(), "byteLength": bytes.len(), "target": target,
+        });
+        if let Some(stride) = stride {
+            view["byteStride"] = json!(stride);
+        }
+        self.buffer.extend_from_slice(bytes);
+        self.buffer_views.push(view);
+        self.buffer_views.len() - 1
+    }
+
+    fn add_accessor(&mut self, accessor: Value) -> usize {
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Returns the index of a material equivalent to `material`, adding it if new.
+    fn add_material(&mut self, material: &Material) -> usize {
+        let color = material.color.to_linear();
+        let float = |name: &str| match material.uniforms.get(name) {
+            Some(UniformValue::Float(value)) => Some(*value),
+            _ => None,
+        };
+        let alpha_mode = match material.blend {
+            BlendMode::Opaque => "OPAQUE",
+            BlendMode::Alpha | BlendMode::Additive => "BLEND",
+        };
+        let mut json = json!({
+            "alphaMode": alpha_mode,
+            "doubleSided": material.cull == CullMode::None,
+        });
+        match (float("u_metallic"), float("u_roughness")) {
+            (Some(metallic), Some(roughness)) => {
+                json["pbrMetallicRoughness"] = json!({
+                    "baseColorFactor": color,
+                    "metallicFactor": metallic,
+                    "roughnessFactor": roughness,
+                });
+                if let Some(UniformValue::Vec3(emissive)) = material.uniforms.get("u_emissive")
+                    && emissive.iter().any(|&channel| channel > 0.0)
+                {
+                    // glTF caps the factor at 1; brighter emission would need KHR_materials_emissive_strength
+                    json["emissiveFactor"] = json!(emissive.map(|channel| channel.min(1.0)));
+                }
+            }
+            _ => {
+                // Blinn-Phong exponent to roughness: shininess = 2 / roughness^2 - 2
+                let shininess = float("u_shininess").unwrap_or(32.0);
+                json["pbrMetallicRoughness"] = json!({
+                    "baseColorFactor": color,
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": (2.0 / (shininess.max(0.0) + 2.0)).sqrt(),
+                });
+                json["extras"] = json!({ SHADING_EXTRA: PHONG_SHADING });
+            }
+        }
+
+        if let Some(index) = self.materials.iter().position(|existing| *existing == json) {
+            return index;
+        }
+        self.materials.push(json);
+        self.materials.len() - 1
+    }
+
+    /// Adds `light` as a `KHR_lights_punctual` light, or returns `None` for ambient lights.
+    fn add_light(&mut self, light: &Light) -> Option<usize> {
+        let color = light.color.to_linear();
+        let mut json = json!({
+            "color": [color[0], color[1], color[2]],
+            "intensity": light.intensity,
+        });
+        let attenuation = match light.kind {
+            LightKind::Ambient => return None,
+            LightKind::Directional => {
+                json["type"] = json!("directional");
+                None
+            }
+            LightKind::Point { attenuation } => {
+                json["type"] = json!("point");
+                Some(attenuation)
+            }
+            LightKind::Spot { attenuation, inner_angle, outer_angle } => {
+                let outer = outer_angle.clamp(f32::EPSILON, std::f32::consts::FRAC_PI_2);
+                json["type"] = json!("spot");
+                json["spot"] = json!({ "innerConeAngle": inner_angle.clamp(0.0, outer * 0.999), "outerConeAngle": outer });
+                Some(attenuation)
+            }
+        };
+        // Inverse of Attenuation::for_range
+        if let Some(attenuation) = attenuation
+            && attenuation.linear > 0.0
+        {
+            json["range"] = json!(4.5 / attenuation.linear);
+        }
+
+        self.lights.push(json);
+        Some(self.lights.len() - 1)
+    }
+}
@@ -0,0 +1,95 @@
+//! Wavefront OBJ export of a single geometry.
+//!
+//! [`write_obj`] dumps a [`Geometry`] as it is, in its own space, without materials: a quick
+//! way to look at procedurally generated or [simplified](crate::engine::mesh::simplify)
+//! meshes in a modeling tool, or to hand them to an artist. Every vertex is written with its
+//! texture coordinate and normal. Triangles become faces, lines and line strips `l`
+//! statements, and points `p` statements. Skinning data is not exported.
+//!
+//! The result reads back with [`parse_obj`](crate::engine::import::obj::parse_obj) to the
+//! same triangles.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::export::obj::encode_obj;
+//! # use rustge::engine::import::obj::parse_obj;
+//! # use rustge::engine::object3d::{Geometry, Topology};
+//! let mut quad = Geometry::from_positions(Topology::Triangles, &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]]);
+//! quad.indices = vec![0u16, 1, 2, 0, 2, 3].into();
+//!
+//! let source = encode_obj(&quad, Some("quad"));
+//! assert!(source.contains("f 1/1/1 2/2/2 3/3/3\n"));
+//!
+//! let (meshes, _) = parse_obj(&source).unwrap();
+//! assert_eq!(meshes[0].name.as_deref(), Some("quad"));
+//! assert_eq!(meshes[0].geometry.indices.len(), 6);
+//! ```
+
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use crate::engine::object3d::{Geometry, Topology};
+
+/// Writes `geometry` to an OBJ file at `path`, as one object named after the file.
+pub fn write_obj(geometry: &Geometry, path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path.file_stem().and_then(|stem| stem.to_str());
+    std::fs::write(path, encode_obj(geometry, name))
+}
+
+/// OBJ source text for `geometry`, as one object named `name` if given.
+pub fn encode_obj(geometry: &Geometry, name: Option<&str>) -> String {
+    let mut out = String::new();
+    // Writing to a String never fails
+    let _ = write_obj_source(&mut out, geometry, name);
+    out
+}
+
+fn write_obj_source(out: &mut String, geometry: &Geometry, name: Option<&str>) -> std::fmt::Result {
+    writeln!(out, "# Exported by rustge: {} vertices, {} indices", geometry.vertices.len(), geometry.indices.len())?;
+    if let Some(name) = name {
+        writeln!(out, "o {name}")?;
+    }
+    for vertex in &geometry.vertices {
+        let [x, y, z] = vertex.position;
+        writeln!(out, "v {x} {y} {z}")?;
+    }
+    for vertex in &geometry.vertices {
+        let [u, v] = vertex.uv;
+        writeln!(out, "vt {u} {v}")?;
+    }
+    for vertex in &geometry.vertices {
+        let [x, y, z] = vertex.normal;
+        writeln!(out, "vn {x} {y} {z}")?;
+    }
+
+    // OBJ indices start at 1
+    let indices: Vec<u32> = geometry.indices.iter().map(|index| index + 1).collect();
+    match geometry.topology {
+        Topology::Triangles => {
+            for triangle in indices.chunks_exact(3) {
+                let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+                writeln!(out, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+            }
+        }
+        Topology::Lines => {
+            for line in indices.chunks_exact(2) {
+                writeln!(out, "l {}/{} {}/{}", line[0], line[0], line[1], line[1])?;
+            }
+        }
+        Topology::LineStrip if indices.len() >= 2 => {
+            out.push('l');
+            for index in &indices {
+                write!(out, " {index}/{index}")?;
+            }
+            out.push('\n');
+        }
+        Topology::LineStrip => {}
+        Topology::Points => {
+            for index in &indices {
+                writeln!(out, "p {index}")?;
+            }
+        }
+    }
+    Ok(())
+}
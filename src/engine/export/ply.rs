@@ -0,0 +1,104 @@
+//! Stanford PLY export of a single geometry.
+//!
+//! [`write_ply`] dumps a [`Geometry`] in its own space, like the
+//! [OBJ writer](crate::engine::export::obj), in a format point cloud and scanning tools read
+//! as well as modeling tools. Vertices carry their position, normal and texture coordinate
+//! (`x y z nx ny nz s t`). Triangles become a `face` element, lines and line strips an
+//! `edge` element, and points need neither.
+//!
+//! Files are binary (little endian) by default, compact for large meshes; the ASCII
+//! variant is easier to read when debugging small ones.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::export::ply::{encode_ply, PlyFormat};
+//! # use rustge::engine::object3d::{Geometry, Topology};
+//! let path = Geometry::from_positions(Topology::LineStrip, &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 2.0, 0.0]]);
+//! let text = String::from_utf8(encode_ply(&path, PlyFormat::Ascii)).unwrap();
+//! assert!(text.starts_with("ply\nformat ascii 1.0\n"));
+//! assert!(text.contains("element vertex 3\n"));
+//! assert!(text.contains("element edge 2\n"));
+//! assert!(text.ends_with("1 0 0 0 1 0 0 0\n1 2 0 0 1 0 0 0\n0 1\n1 2\n"));
+//! ```
+
+use std::io;
+use std::path::Path;
+use crate::engine::object3d::{Geometry, Topology};
+
+/// Encoding of the element data of a PLY file; the header is always text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlyFormat {
+    /// One line of text per element.
+    Ascii,
+    /// Little-endian binary, the most widely read binary variant.
+    #[default]
+    BinaryLittleEndian,
+}
+
+/// Writes `geometry` to a PLY file at `path`.
+pub fn write_ply(geometry: &Geometry, path: impl AsRef<Path>, format: PlyFormat) -> io::Result<()> {
+    std::fs::write(path, encode_ply(geometry, format))
+}
+
+/// The contents of a PLY file holding `geometry`.
+pub fn encode_ply(geometry: &Geometry, format: PlyFormat) -> Vec<u8> {
+    let indices: Vec<u32> = geometry.indices.iter().collect();
+    let (faces, edges): (Vec<&[u32]>, Vec<&[u32]>) = match geometry.topology {
+        Topology::Triangles => (indices.chunks_exact(3).collect(), Vec::new()),
+        Topology::Lines => (Vec::new(), indices.chunks_exact(2).collect()),
+        Topology::LineStrip => (Vec::new(), indices.windows(2).collect()),
+        Topology::Points => (Vec::new(), Vec::new()),
+    };
+
+    let mut header = String::from("ply\n");
+    header += match format {
+        PlyFormat::Ascii => "format ascii 1.0\n",
+        PlyFormat::BinaryLittleEndian => "format binary_little_endian 1.0\n",
+    };
+    header += "comment Exported by rustge\n";
+    header += &format!("element vertex {}\n", geometry.vertices.len());
+    for property in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        header += &format!("property float {property}\n");
+    }
+    if !faces.is_empty() {
+        header += &format!("element face {}\nproperty list uchar uint vertex_indices\n", faces.len());
+    }
+    if !edges.is_empty() {
+        header += &format!("element edge {}\nproperty uint vertex1\nproperty uint vertex2\n", edges.len());
+    }
+    header += "end_header\n";
+
+    let mut out = header.into_bytes();
+    let vertices = geometry.vertices.iter().map(|vertex| {
+        let ([x, y, z], [nx, ny, nz], [s, t]) = (vertex.position, vertex.normal, vertex.uv);
+        [x, y, z, nx, ny, nz, s, t]
+    });
+    match format {
+        PlyFormat::Ascii => {
+            let line = |values: Vec<String>| values.join(" ") + "\n";
+            for vertex in vertices {
+                out.extend(line(vertex.map(|value| value.to_string()).to_vec()).bytes());
+            }
+            for face in &faces {
+                let counted = std::iter::once(face.len() as u32).chain(face.iter().copied());
+                out.extend(line(counted.map(|index| index.to_string()).collect()).bytes());
+            }
+            for edge in &edges {
+                out.extend(line(edge.iter().map(|index| index.to_string()).collect()).bytes());
+            }
+        }
+        PlyFormat::BinaryLittleEndian => {
+            for vertex in vertices {
+                out.extend(vertex.iter().flat_map(|value| value.to_le_bytes()));
+            }
+            for face in &faces {
+                out.push(face.len() as u8);
+                out.extend(face.iter().flat_map(|index| index.to_le_bytes()));
+            }
+            for edge in &edges {
+                out.extend(edge.iter().flat_map(|index| index.to_le_bytes()));
+            }
+        }
+    }
+    out
+}
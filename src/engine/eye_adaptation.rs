@@ -0,0 +1,269 @@
+//! Automatic exposure, adapting to the brightness of the scene like an eye.
+//!
+//! A fixed exposure suits one lighting level: walk from a sunny courtyard into an unlit
+//! cellar and the image goes black, walk back out and it burns white. With
+//! [eye adaptation](crate::engine::renderer::Renderer::set_eye_adaptation) on, the renderer
+//! measures each frame's brightness and scales the exposure towards the level that brings
+//! it to a mid-grey [target](EyeAdaptation::target_luminance), gradually, within bounds.
+//!
+//! Measuring happens on a 64 × 64 copy of the frame, before post effects and overlays,
+//! read back [asynchronously](crate::engine::readback) so it never stalls the GPU. The
+//! pixels' luminance goes into a [histogram](luminance_histogram) of log luminance, and the
+//! [average](histogram_average) leaves out the darkest and brightest pixels, so a bright
+//! window in a dark room or a black sky above a lit street don't throw the exposure off.
+//!
+//! The adapted exposure multiplies the camera's own (see
+//! [`Camera::exposure`](crate::engine::camera::Camera::exposure)), which keeps working as
+//! the base level: a [`PhysicalCamera`](crate::engine::camera::PhysicalCamera) set up for
+//! daylight adapts from there.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::eye_adaptation::{histogram_average, luminance_histogram, EyeAdaptation};
+//! // A dark frame: mostly near-black pixels, a few lamps
+//! let mut pixels = vec![[12u8, 12, 16, 255]; 990];
+//! pixels.extend([[255, 240, 200, 255]; 10]);
+//! let histogram = luminance_histogram(pixels.as_flattened());
+//! let luminance = histogram_average(&histogram, 0.1, 0.95).unwrap();
+//! assert!(luminance < 0.01);
+//!
+//! let adaptation = EyeAdaptation::new();
+//! let target = adaptation.target_exposure(luminance, 1.0);
+//! assert_eq!(target, adaptation.max_exposure); // as bright as allowed
+//! let exposure = adaptation.adapt(1.0, target, 0.5);
+//! assert!(exposure > 1.0 && exposure < target);
+//! ```
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use gl::types::GLuint;
+use crate::engine::math::color::srgb_to_linear;
+use crate::engine::readback::Readback;
+use crate::engine::render_target::RenderTarget;
+
+/// Number of bins of a [`luminance_histogram`].
+pub const HISTOGRAM_BINS: usize = 64;
+
+/// Log2 luminance covered by the histogram; darker pixels count in the first bin.
+const MIN_LOG_LUMINANCE: f32 = -12.0;
+const MAX_LOG_LUMINANCE: f32 = 0.0;
+
+/// Width and height of the frame copy measured.
+const METER_SIZE: u32 = 64;
+
+/// Readbacks in flight at most; frames beyond go unmeasured.
+const MAX_PENDING: usize = 3;
+
+thread_local! {
+    /// Exposure multiplier of the frame being drawn, applied on top of the camera's.
+    static FRAME_EXPOSURE: Cell<f32> = const { Cell::new(1.0) };
+}
+
+/// Sets the exposure multiplier every lit material applies while drawing the current frame.
+pub(crate) fn set_frame_exposure(exposure: f32) {
+    FRAME_EXPOSURE.with(|frame_exposure| frame_exposure.set(exposure));
+}
+
+/// The exposure multiplier of the frame being drawn.
+pub(crate) fn frame_exposure() -> f32 {
+    FRAME_EXPOSURE.with(Cell::get)
+}
+
+/// How the exposure adapts; see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EyeAdaptation {
+    /// Average linear luminance the adapted image aims for.
+    pub target_luminance: f32,
+
+    /// Lowest exposure multiplier, reached in the brightest scenes.
+    pub min_exposure: f32,
+
+    /// Highest exposure multiplier, reached in the darkest scenes; dark places should stay
+    /// somewhat dark.
+    pub max_exposure: f32,
+
+    /// Fraction of the darkest pixels left out of the average.
+    pub low_percentile: f32,
+
+    /// Fraction of the pixels, from the darkest, above which the brightest are left out.
+    pub high_percentile: f32,
+
+    /// Rate (per second) of adapting to a brighter scene; eyes adapt to glare quickly.
+    pub bright_speed: f32,
+
+    /// Rate (per second) of adapting to a darker scene, usually slower.
+    pub dark_speed: f32,
+}
+
+impl EyeAdaptation {
+    /// Adapts to mid-grey between a quarter and eight times the camera's exposure,
+    /// ignoring the darkest 40 % and brightest 5 % of the pixels; to bright scenes within
+    /// about a second, to dark ones within about three.
+    pub fn new() -> Self {
+        Self {
+            target_luminance: 0.18,
+            min_exposure: 0.25,
+            max_exposure: 8.0,
+            low_percentile: 0.4,
+            high_percentile: 0.95,
+            bright_speed: 3.0,
+            dark_speed: 1.0,
+        }
+    }
+
+    /// The exposure multiplier bringing an image whose average luminance is `luminance`,
+    /// drawn with multiplier `exposure`, to the target, within bounds.
+    pub fn target_exposure(&self, luminance: f32, exposure: f32) -> f32 {
+        let target = exposure * self.target_luminance / luminance.max(1e-6);
+        target.clamp(self.min_exposure, self.max_exposure.max(self.min_exposure))
+    }
+
+    /// `current` moved towards `target` over `delta` seconds, exponentially in stops, so
+    /// adapting takes as long from bright to dim as from dim to dark.
+    pub fn adapt(&self, current: f32, target: f32, delta: f32) -> f32 {
+        let speed = if target < current { self.bright_speed } else { self.dark_speed };
+        let t = 1.0 - (-delta.max(0.0) * speed.max(0.0)).exp();
+        let (from, to) = (current.max(1e-6).log2(), target.max(1e-6).log2());
+        (from + (to - from) * t).exp2()
+    }
+}
+
+impl Default for EyeAdaptation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Histogram of the log2 luminance of sRGB-encoded RGBA8 `pixels`, over
+/// [`HISTOGRAM_BINS`] bins from 2⁻¹² (and darker) to 1.
+pub fn luminance_histogram(pixels: &[u8]) -> [u32; HISTOGRAM_BINS] {
+    let mut histogram = [0; HISTOGRAM_BINS];
+    for pixel in pixels.chunks_exact(4) {
+        let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|c| srgb_to_linear(c as f32 / 255.0));
+        let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        histogram[bin(luminance)] += 1;
+    }
+    histogram
+}
+
+/// Average luminance of the pixels counted in `histogram`, leaving out the darkest
+/// `low_percentile` and those above `high_percentile` (fractions of the pixels); `None`
+/// for an empty histogram. Averages are geometric, in log luminance, as eyes perceive
+/// brightness.
+pub fn histogram_average(histogram: &[u32; HISTOGRAM_BINS], low_percentile: f32, high_percentile: f32) -> Option<f32> {
+    let total: u32 = histogram.iter().sum();
+    if total == 0 {
+        return None;
+    }
+    let low = low_percentile.clamp(0.0, 1.0) * total as f32;
+    let high = high_percentile.clamp(low_percentile.clamp(0.0, 1.0), 1.0) * total as f32;
+
+    // Count each bin's pixels between the percentiles, at the bin's center
+    let (mut below, mut sum, mut count) = (0.0, 0.0, 0.0);
+    for (i, &pixels) in histogram.iter().enumerate() {
+        let pixels = pixels as f32;
+        let kept = (below + pixels).min(high) - below.max(low);
+        if kept > 0.0 {
+            sum += bin_center(i) * kept;
+            count += kept;
+        }
+        below += pixels;
+    }
+    if count <= 0.0 {
+        // Percentiles too close together: the bin holding them
+        let mut seen = 0;
+        let index = histogram.iter().position(|&pixels| {
+            seen += pixels;
+            seen as f32 >= low
+        });
+        return Some(bin_center(index.unwrap_or(0)).exp2());
+    }
+    Some((sum / count).exp2())
+}
+
+/// The histogram bin of `luminance`.
+fn bin(luminance: f32) -> usize {
+    let t = (luminance.max(1e-9).log2() - MIN_LOG_LUMINANCE) / (MAX_LOG_LUMINANCE - MIN_LOG_LUMINANCE);
+    ((t * HISTOGRAM_BINS as f32) as isize).clamp(0, HISTOGRAM_BINS as isize - 1) as usize
+}
+
+/// The log2 luminance at the center of bin `index`.
+fn bin_center(index: usize) -> f32 {
+    MIN_LOG_LUMINANCE + (index as f32 + 0.5) / HISTOGRAM_BINS as f32 * (MAX_LOG_LUMINANCE - MIN_LOG_LUMINANCE)
+}
+
+/// Measures frames and tracks the adapted exposure; owned by the renderer.
+#[derive(Debug)]
+pub(crate) struct ExposureMeter {
+    /// Small copy of the frame, read back; created on the first measurement.
+    target: Option<RenderTarget>,
+
+    /// Readbacks in flight, with the exposure multiplier their frame was drawn with.
+    pending: VecDeque<(Readback, f32)>,
+
+    exposure: f32,
+    luminance: Option<f32>,
+
+    /// Exposure the latest measurement asks for.
+    target_exposure: f32,
+}
+
+impl ExposureMeter {
+    pub(crate) fn new() -> Self {
+        Self { target: None, pending: VecDeque::new(), exposure: 1.0, luminance: None, target_exposure: 1.0 }
+    }
+
+    /// The adapted exposure multiplier.
+    pub(crate) fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// Average luminance of the last frame measured, as drawn.
+    pub(crate) fn luminance(&self) -> Option<f32> {
+        self.luminance
+    }
+
+    /// Starts measuring the color attachment of `fbo` (the back buffer for 0), of `size`
+    /// pixels, drawn with the current exposure. Leaves the read framebuffer unbound.
+    pub(crate) fn measure(&mut self, fbo: GLuint, size: [u32; 2]) {
+        if self.pending.len() >= MAX_PENDING {
+            return;
+        }
+        let target = self.target.get_or_insert_with(|| RenderTarget::new(METER_SIZE, METER_SIZE, "exposure meter"));
+        let (width, height) = (size[0] as i32, size[1] as i32);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+            gl::ReadBuffer(if fbo == 0 { gl::BACK } else { gl::COLOR_ATTACHMENT0 });
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.fbo());
+            let meter = METER_SIZE as i32;
+            gl::BlitFramebuffer(0, 0, width, height, 0, 0, meter, meter, gl::COLOR_BUFFER_BIT, gl::LINEAR);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        }
+        self.pending.push_back((target.read_color_async(), self.exposure));
+    }
+
+    /// Takes in finished measurements and moves the exposure towards the latest one's
+    /// target over `delta` seconds.
+    pub(crate) fn update(&mut self, settings: &EyeAdaptation, delta: f32) {
+        while let Some((readback, _)) = self.pending.front_mut() {
+            let Some(pixels) = readback.try_take() else {
+                break;
+            };
+            let Some((_, exposure)) = self.pending.pop_front() else {
+                break;
+            };
+            let histogram = luminance_histogram(&pixels);
+            if let Some(luminance) = histogram_average(&histogram, settings.low_percentile, settings.high_percentile) {
+                self.luminance = Some(luminance);
+                self.target_exposure = settings.target_exposure(luminance, exposure);
+            }
+        }
+        self.exposure = settings.adapt(self.exposure, self.target_exposure, delta);
+    }
+
+    /// Forgets measurements and returns to the camera's own exposure.
+    pub(crate) fn reset(&mut self) {
+        self.pending.clear();
+        (self.exposure, self.target_exposure, self.luminance) = (1.0, 1.0, None);
+    }
+}
@@ -0,0 +1,363 @@
+//! Fonts: TrueType and OpenType faces, shaped with rustybuzz, in fallback chains.
+//!
+//! A [`Font`] is loaded from a `.ttf` or `.otf` file. No single font covers every script,
+//! so text is drawn with a [`FontChain`]: an ordered list of fonts, e.g. a Latin font, then
+//! a CJK one, then an Arabic one. Each character is drawn with the first font in the chain
+//! that has a glyph for it; spaces and combining marks stay with the font of the text around
+//! them, so accents land on their letters.
+//!
+//! [`FontChain::shape`] splits a line into runs of one font each and shapes every run with
+//! rustybuzz, the Rust port of HarfBuzz: text is read as UTF-8 and turned into positioned
+//! glyphs, with ligatures, kerning, Arabic joining forms and Indic reordering as the font
+//! defines them. The direction and script of each run are detected from its text, so a
+//! right-to-left run comes out in visual order. Lines mixing directions are laid out run by
+//! run in logical order; full bidirectional reordering is not done.
+//!
+//! Shaped text is drawn by
+//! [`TextBatch::font_text`](crate::engine::debug::text::TextBatch::font_text), which
+//! rasterizes glyphs on first use into an atlas shared by all batches.
+//!
+//! # Example
+//! ```
+//! # use std::rc::Rc;
+//! # use rustge::engine::font::{Font, FontChain};
+//! // Two tiny test fonts: `A` and `V` with a kerning pair between them, and `Ж` alone
+//! let chain = FontChain::new(vec![
+//!     Rc::new(Font::load("assets/fonts/test_latin.ttf").unwrap()),
+//!     Rc::new(Font::load("assets/fonts/test_cyrillic.ttf").unwrap()),
+//! ]);
+//! let shaped = chain.shape("AVЖ", 10.0);
+//!
+//! // The first font has no `Ж`, so it comes from the second
+//! let fonts: Vec<usize> = shaped.glyphs.iter().map(|glyph| glyph.font).collect();
+//! assert_eq!(fonts, [0, 0, 1]);
+//!
+//! // `A` advances 6 pixels, less 1 for the kerning the shaper applies before `V`
+//! assert_eq!(shaped.glyphs[1].position[0], 5.0);
+//! ```
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use rustybuzz::ttf_parser::{GlyphId, OutlineBuilder};
+use rustybuzz::{Face, UnicodeBuffer};
+
+/// Source of [`Font::id`]s.
+static NEXT_FONT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A TrueType or OpenType face; see the [module documentation](self).
+pub struct Font {
+    id: u64,
+    name: String,
+
+    /// The face, parsed once from `data` and borrowing it. Declared first so it is dropped
+    /// before the bytes it points into.
+    face: Face<'static>,
+
+    data: Vec<u8>,
+}
+
+impl Font {
+    /// Parses the face `index` of font file contents (0 unless `data` is a collection),
+    /// named `name` in messages. Fails with `InvalidData` if `data` isn't a font.
+    pub fn from_bytes(name: &str, data: Vec<u8>, index: u32) -> io::Result<Self> {
+        // SAFETY: the bytes are on the heap, so they stay in place when `data` moves into the
+        // font, which never modifies them and drops `face` first. `face()` only lends the
+        // face out for as long as the font is borrowed.
+        let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) };
+        let Some(face) = Face::from_slice(bytes, index) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{name} is not a TrueType or OpenType font")));
+        };
+        Ok(Self { id: NEXT_FONT_ID.fetch_add(1, Ordering::Relaxed), name: name.to_string(), face, data })
+    }
+
+    /// Loads the first face of a `.ttf`, `.otf` or `.ttc` file, named after its path.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::from_bytes(&path.display().to_string(), std::fs::read(path)?, 0)
+    }
+
+    /// The name given when loading, usually the file path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Identifies the font in glyph caches; unique among the fonts loaded by the process.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The font file contents.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Whether the font has a glyph for `ch`.
+    pub fn has_glyph(&self, ch: char) -> bool {
+        self.face.glyph_index(ch).is_some()
+    }
+
+    /// Distance from the baseline to the top of the tallest glyphs, and to the bottom of the
+    /// lowest (negative), in pixels at `size` pixels per em.
+    pub fn ascent_descent(&self, size: f32) -> (f32, f32) {
+        let face = &self.face;
+        let scale = size / face.units_per_em() as f32;
+        (face.ascender() as f32 * scale, face.descender() as f32 * scale)
+    }
+
+    /// Distance between baselines of consecutive lines, in pixels at `size` pixels per em.
+    pub fn line_height(&self, size: f32) -> f32 {
+        let face = &self.face;
+        let scale = size / face.units_per_em() as f32;
+        (face.ascender() - face.descender() + face.line_gap()) as f32 * scale
+    }
+
+    /// Renders `glyph` at `size` pixels per em as coverage from 0 to 255. `None` for glyphs
+    /// without an outline, such as spaces, and for bitmap-only glyphs.
+    pub fn rasterize(&self, glyph: u16, size: f32) -> Option<GlyphBitmap> {
+        let face = &self.face;
+        let scale = size / face.units_per_em() as f32;
+        let bounds = face.glyph_bounding_box(GlyphId(glyph))?;
+
+        // One pixel of padding keeps bilinear filtering from bleeding into neighbours
+        let left = (bounds.x_min as f32 * scale).floor() as i32 - 1;
+        let top = (bounds.y_max as f32 * scale).ceil() as i32 + 1;
+        let width = ((bounds.x_max as f32 * scale).ceil() as i32 + 1 - left).max(1) as u32;
+        let height = (top - (bounds.y_min as f32 * scale).floor() as i32 + 1).max(1) as u32;
+
+        let mut outline = Outline {
+            rasterizer: Rasterizer::new(width as usize, height as usize),
+            transform: [scale, -left as f32, top as f32],
+            start: point(0.0, 0.0),
+            last: point(0.0, 0.0),
+        };
+        face.outline_glyph(GlyphId(glyph), &mut outline)?;
+
+        let mut coverage = vec![0; (width * height) as usize];
+        outline.rasterizer.for_each_pixel_2d(|x, y, alpha| {
+            coverage[(y * width + x) as usize] = (alpha.clamp(0.0, 1.0) * 255.0).round() as u8;
+        });
+        Some(GlyphBitmap { width, height, left, top, coverage })
+    }
+
+    /// The parsed face, for shaping.
+    pub(crate) fn face(&self) -> &Face<'_> {
+        &self.face
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font").field("id", &self.id).field("name", &self.name).field("bytes", &self.data.len()).finish()
+    }
+}
+
+/// A rasterized glyph: coverage rows from top to bottom, placed relative to the pen
+/// position on the baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphBitmap {
+    pub width: u32,
+    pub height: u32,
+
+    /// Pixels from the pen position to the bitmap's left edge.
+    pub left: i32,
+
+    /// Pixels from the baseline up to the bitmap's top edge.
+    pub top: i32,
+
+    /// `width * height` coverage values, 255 inside the outline.
+    pub coverage: Vec<u8>,
+}
+
+/// Feeds a glyph outline in font units to the rasterizer, in bitmap pixels with y down.
+struct Outline {
+    rasterizer: Rasterizer,
+
+    /// Scale, then x and y offset of the font-unit origin in pixels.
+    transform: [f32; 3],
+
+    start: Point,
+    last: Point,
+}
+
+impl Outline {
+    fn point(&self, x: f32, y: f32) -> Point {
+        let [scale, x_offset, y_offset] = self.transform;
+        point(x * scale + x_offset, y_offset - y * scale)
+    }
+}
+
+impl OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let end = self.point(x, y);
+        self.rasterizer.draw_line(self.last, end);
+        self.last = end;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (control, end) = (self.point(x1, y1), self.point(x, y));
+        self.rasterizer.draw_quad(self.last, control, end);
+        self.last = end;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (first, second, end) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.rasterizer.draw_cubic(self.last, first, second, end);
+        self.last = end;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
+
+/// A glyph positioned by [`FontChain::shape`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapedGlyph {
+    /// Index of the glyph's font in the chain.
+    pub font: usize,
+
+    /// Glyph index in that font.
+    pub glyph: u16,
+
+    /// Pen position on the baseline, in pixels from the top-left corner of the text.
+    pub position: [f32; 2],
+
+    /// Byte offset in the text of the first character the glyph draws.
+    pub cluster: usize,
+}
+
+/// Text shaped by [`FontChain::shape`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShapedText {
+    /// The glyphs, line by line.
+    pub glyphs: Vec<ShapedGlyph>,
+
+    /// Advance of the widest line, in pixels.
+    pub width: f32,
+
+    /// From the top of the first line to the bottom of the last, in pixels.
+    pub height: f32,
+}
+
+/// Fonts tried in order for each character; see the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct FontChain {
+    fonts: Vec<Rc<Font>>,
+}
+
+impl FontChain {
+    /// A chain trying `fonts` in order. The first sets the line height.
+    pub fn new(fonts: Vec<Rc<Font>>) -> Self {
+        Self { fonts }
+    }
+
+    /// Appends a fallback font, tried after the others.
+    pub fn push(&mut self, font: Rc<Font>) {
+        self.fonts.push(font);
+    }
+
+    /// The fonts, in the order they are tried.
+    pub fn fonts(&self) -> &[Rc<Font>] {
+        &self.fonts
+    }
+
+    /// Index of the first font with a glyph for `ch`, or `None` if no font has one.
+    pub fn font_for(&self, ch: char) -> Option<usize> {
+        self.fonts.iter().position(|font| font.has_glyph(ch))
+    }
+
+    /// Distance between baselines, from the first font; 0 for an empty chain.
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.fonts.first().map_or(0.0, |font| font.line_height(size))
+    }
+
+    /// Shapes `text` at `size` pixels per em; `\n` starts a new line. Characters no font
+    /// covers are drawn with the missing-glyph box of the font around them.
+    pub fn shape(&self, text: &str, size: f32) -> ShapedText {
+        let mut shaped = ShapedText::default();
+        let Some(first) = self.fonts.first() else {
+            return shaped;
+        };
+        let (ascent, _) = first.ascent_descent(size);
+        let (line_height, mut line_offset) = (first.line_height(size), 0);
+
+        for (index, line) in text.split('\n').enumerate() {
+            let baseline = ascent + index as f32 * line_height;
+            let mut pen = 0.0;
+            for (start, end, font) in font_runs(line, &self.fonts) {
+                let face = self.fonts[font].face();
+                let scale = size / face.units_per_em() as f32;
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(&line[start..end]);
+                buffer.guess_segment_properties();
+                let glyphs = rustybuzz::shape(face, &[], buffer);
+                for (info, position) in glyphs.glyph_infos().iter().zip(glyphs.glyph_positions()) {
+                    shaped.glyphs.push(ShapedGlyph {
+                        font,
+                        glyph: info.glyph_id as u16,
+                        position: [pen + position.x_offset as f32 * scale, baseline - position.y_offset as f32 * scale],
+                        cluster: line_offset + start + info.cluster as usize,
+                    });
+                    pen += position.x_advance as f32 * scale;
+                }
+            }
+            shaped.width = shaped.width.max(pen);
+            shaped.height = (index + 1) as f32 * line_height;
+            line_offset += line.len() + 1;
+        }
+        shaped
+    }
+
+    /// Width and height in pixels of `text` shaped at `size`.
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        let shaped = self.shape(text, size);
+        [shaped.width, shaped.height]
+    }
+}
+
+/// Splits `line` into byte ranges drawn with one font each: the first of `fonts` with a
+/// glyph for the character, except that whitespace, joiners and combining marks, and
+/// characters no font has, continue the run they follow.
+fn font_runs(line: &str, fonts: &[Rc<Font>]) -> Vec<(usize, usize, usize)> {
+    let mut runs: Vec<(usize, usize, usize)> = Vec::new();
+    for (offset, ch) in line.char_indices() {
+        let end = offset + ch.len_utf8();
+        let current = runs.last().map(|&(_, _, font)| font);
+        let font = match current {
+            Some(font) if ch.is_whitespace() || is_combining(ch) => font,
+            _ => fonts.iter().position(|font| font.has_glyph(ch)).or(current).unwrap_or(0),
+        };
+        match runs.last_mut() {
+            Some(run) if run.2 == font => run.1 = end,
+            _ => runs.push((offset, end, font)),
+        }
+    }
+    runs
+}
+
+/// Whether `ch` attaches to the character before it: combining diacritics, variation
+/// selectors and zero-width joiners.
+fn is_combining(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
@@ -0,0 +1,247 @@
+//! Render pass recording and timing.
+//!
+//! The renderer describes each frame as a sequence of named passes (clear, background,
+//! scene, overlays, and later shadows and post effects). The [`FrameGraph`] records them in
+//! execution order along with their render target and size, and measures each pass on the
+//! CPU and, through `GL_TIME_ELAPSED` queries, on the GPU.
+//!
+//! GPU results arrive a few frames late; queries are only read back once the driver reports
+//! them available, so timing never stalls the pipeline. [`FrameGraph::passes`] therefore
+//! reports the most recent frame whose GPU timings have all resolved.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use gl::types::GLuint;
+
+/// Frames that may be waiting on GPU timer results before older ones are dropped.
+const MAX_FRAMES_IN_FLIGHT: usize = 4;
+
+/// Timing and target information for one executed pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    /// Pass name, e.g. `"scene"`.
+    pub name: String,
+
+    /// Name of the render target the pass draws into, e.g. `"backbuffer"`.
+    pub target: String,
+
+    /// Size of the render target in pixels.
+    pub target_size: [u32; 2],
+
+    /// Wall-clock time spent issuing the pass on the CPU.
+    pub cpu_time: Duration,
+
+    /// Time the GPU spent executing the pass, if timer queries are available.
+    pub gpu_time: Option<Duration>,
+}
+
+/// A pass recorded this frame, possibly still waiting for its GPU query.
+#[derive(Debug)]
+struct PendingPass {
+    timing: PassTiming,
+    query: Option<GLuint>,
+}
+
+/// Records the passes executed each frame with their CPU and GPU timings.
+#[derive(Debug)]
+pub struct FrameGraph {
+    /// Passes of the frame being recorded.
+    current: Vec<PendingPass>,
+
+    /// The pass currently open: its index in `current` and CPU start time.
+    open: Option<(usize, Instant)>,
+
+    /// Finished frames whose GPU queries haven't all resolved yet, oldest first.
+    in_flight: VecDeque<Vec<PendingPass>>,
+
+    /// The most recent fully resolved frame.
+    latest: Vec<PassTiming>,
+
+    /// The frame most recently ended, with CPU timings only.
+    recorded: Vec<PassTiming>,
+
+    /// Query objects ready for reuse.
+    free_queries: Vec<GLuint>,
+
+    /// Whether GPU timer queries are issued.
+    gpu_timing: bool,
+}
+
+impl FrameGraph {
+    /// Creates an empty frame graph with GPU timing enabled. No GL calls are made until
+    /// the first pass is recorded.
+    pub fn new() -> Self {
+        Self {
+            current: Vec::new(),
+            open: None,
+            in_flight: VecDeque::new(),
+            latest: Vec::new(),
+            recorded: Vec::new(),
+            free_queries: Vec::new(),
+            gpu_timing: true,
+        }
+    }
+
+    /// Enables or disables GPU timer queries. CPU timings are always recorded.
+    pub fn set_gpu_timing(&mut self, enabled: bool) {
+        self.gpu_timing = enabled;
+    }
+
+    /// Whether GPU timer queries are issued.
+    pub fn gpu_timing(&self) -> bool {
+        self.gpu_timing
+    }
+
+    /// Starts recording a new frame, collecting any earlier frames whose GPU timings are ready.
+    pub fn begin_frame(&mut self) {
+        self.current.clear();
+        self.open = None;
+        self.resolve();
+    }
+
+    /// Starts a pass drawing into `target` of the given size. Passes must not overlap;
+    /// an open pass is ended first.
+    pub fn begin_pass(&mut self, name: &str, target: &str, target_size: [u32; 2]) {
+        if self.open.is_some() {
+            self.end_pass();
+        }
+
+        let query = self.gpu_timing.then(|| {
+            let query = self.free_queries.pop().unwrap_or_else(|| {
+                let mut id = 0;
+                unsafe {
+                    gl::GenQueries(1, &mut id);
+                }
+                id
+            });
+            unsafe {
+                gl::BeginQuery(gl::TIME_ELAPSED, query);
+            }
+            query
+        });
+
+        self.current.push(PendingPass {
+            timing: PassTiming {
+                name: name.to_string(),
+                target: target.to_string(),
+                target_size,
+                cpu_time: Duration::ZERO,
+                gpu_time: None,
+            },
+            query,
+        });
+        self.open = Some((self.current.len() - 1, Instant::now()));
+    }
+
+    /// Ends the open pass, if any.
+    pub fn end_pass(&mut self) {
+        let Some((index, start)) = self.open.take() else {
+            return;
+        };
+        let pass = &mut self.current[index];
+        pass.timing.cpu_time = start.elapsed();
+        if pass.query.is_some() {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+        }
+    }
+
+    /// Records `f` as a pass, returning its result.
+    pub fn pass<R>(&mut self, name: &str, target: &str, target_size: [u32; 2], f: impl FnOnce() -> R) -> R {
+        self.begin_pass(name, target, target_size);
+        let result = f();
+        self.end_pass();
+        result
+    }
+
+    /// Finishes the frame. Its timings become visible through [`passes`](Self::passes) once
+    /// the GPU has executed it.
+    pub fn end_frame(&mut self) {
+        self.end_pass();
+        let frame = std::mem::take(&mut self.current);
+        self.recorded = frame.iter().map(|pass| pass.timing.clone()).collect();
+        if frame.iter().all(|pass| pass.query.is_none()) {
+            self.latest = frame.into_iter().map(|pass| pass.timing).collect();
+            return;
+        }
+
+        self.in_flight.push_back(frame);
+        // Never let unresolved frames pile up if the driver is slow to report
+        while self.in_flight.len() > MAX_FRAMES_IN_FLIGHT {
+            if let Some(frame) = self.in_flight.pop_front() {
+                self.recycle(frame);
+            }
+        }
+    }
+
+    /// Passes of the most recent resolved frame, in execution order.
+    pub fn passes(&self) -> &[PassTiming] {
+        &self.latest
+    }
+
+    /// Passes of the frame just ended by [`end_frame`](Self::end_frame). Only CPU timings are
+    /// known at that point; GPU timings arrive later through [`passes`](Self::passes).
+    pub fn recorded_passes(&self) -> &[PassTiming] {
+        &self.recorded
+    }
+
+    /// Total CPU and GPU time of the most recent resolved frame's passes.
+    pub fn totals(&self) -> (Duration, Option<Duration>) {
+        let cpu = self.latest.iter().map(|pass| pass.cpu_time).sum();
+        let gpu = self.latest.iter().map(|pass| pass.gpu_time).sum();
+        (cpu, gpu)
+    }
+
+    /// Publishes the newest in-flight frame whose queries have all completed.
+    fn resolve(&mut self) {
+        while let Some(frame) = self.in_flight.front() {
+            let ready = frame.iter().filter_map(|pass| pass.query).all(|query| {
+                let mut available = 0;
+                unsafe {
+                    gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                }
+                available != 0
+            });
+            if !ready {
+                break;
+            }
+
+            let mut frame = self.in_flight.pop_front().expect("front frame exists");
+            for pass in &mut frame {
+                if let Some(query) = pass.query {
+                    let mut nanos = 0u64;
+                    unsafe {
+                        gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanos);
+                    }
+                    pass.timing.gpu_time = Some(Duration::from_nanos(nanos));
+                }
+            }
+            self.latest = frame.iter().map(|pass| pass.timing.clone()).collect();
+            self.recycle(frame);
+        }
+    }
+
+    /// Returns a frame's query objects to the pool.
+    fn recycle(&mut self, frame: Vec<PendingPass>) {
+        self.free_queries.extend(frame.into_iter().filter_map(|pass| pass.query));
+    }
+}
+
+impl Default for FrameGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FrameGraph {
+    fn drop(&mut self) {
+        let pending = self.in_flight.drain(..).flatten().chain(self.current.drain(..));
+        self.free_queries.extend(pending.filter_map(|pass| pass.query));
+        if !self.free_queries.is_empty() {
+            unsafe {
+                gl::DeleteQueries(self.free_queries.len() as i32, self.free_queries.as_ptr());
+            }
+        }
+    }
+}
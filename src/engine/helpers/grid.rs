@@ -0,0 +1,138 @@
+//! Reference grid / ground plane helper.
+//!
+//! [`grid_helper`] builds an [`Object3D`] drawing a grid on its local XZ plane, the way
+//! every 3D tool shows one by default: thin minor lines, stronger major lines every few
+//! cells, the X and Z axes highlighted, and lines fading out with distance from the camera.
+//!
+//! The lines are computed per-pixel in the shader, so they stay crisp at any zoom level
+//! and the grid can be made effectively infinite with a single quad.
+//!
+//! ```no_run
+//! # use rustge::engine::{helpers::grid::{grid_helper, GridOptions}, object3d::Object3D};
+//! # let root = Object3D::new();
+//! Object3D::add_child(&root, grid_helper(&GridOptions::default()));
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::color_space::output_color;
+use crate::engine::material::{BlendMode, CullMode, Material};
+use crate::engine::math::color::Color;
+use crate::engine::object3d::{Geometry, Indices, Object3D, Topology, Vertex};
+use crate::engine::shader::{builtin_program, UniformValue};
+
+/// Appearance and extent of a reference grid.
+#[derive(Clone, Copy, Debug)]
+pub struct GridOptions {
+    /// Side length of the grid, or `None` for an infinite grid that follows the camera.
+    pub size: Option<f32>,
+
+    /// Distance between minor lines, in the node's local units.
+    pub cell_size: f32,
+
+    /// Number of cells between major lines.
+    pub major_every: u32,
+
+    /// Color of minor lines (alpha is respected).
+    pub minor_color: Color,
+
+    /// Color of major lines.
+    pub major_color: Color,
+
+    /// Color of the X axis line (along `z == 0`).
+    pub x_axis_color: Color,
+
+    /// Color of the Z axis line (along `x == 0`).
+    pub z_axis_color: Color,
+
+    /// Distance from the camera at which lines start fading out.
+    pub fade_start: f32,
+
+    /// Distance from the camera beyond which lines are fully transparent.
+    /// Also bounds the extent of an infinite grid.
+    pub fade_end: f32,
+}
+
+impl GridOptions {
+    /// An infinite grid with 1-unit cells and major lines every 10 cells.
+    pub fn infinite() -> Self {
+        Self {
+            size: None,
+            cell_size: 1.0,
+            major_every: 10,
+            minor_color: Color::srgba(0.5, 0.5, 0.5, 0.35),
+            major_color: Color::srgba(0.6, 0.6, 0.6, 0.7),
+            x_axis_color: Color::srgb(0.9, 0.25, 0.25),
+            z_axis_color: Color::srgb(0.25, 0.4, 0.9),
+            fade_start: 20.0,
+            fade_end: 80.0,
+        }
+    }
+
+    /// A square grid of the given side length, centered on the node's origin.
+    pub fn finite(size: f32) -> Self {
+        Self { size: Some(size), ..Self::infinite() }
+    }
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        Self::infinite()
+    }
+}
+
+/// Creates a grid node ready to be added to a scene.
+///
+/// The grid lies on the node's local XZ plane, so it can be raised, tilted or scaled like any
+/// other object. It is alpha-blended, doesn't write depth, and is drawn from both sides.
+/// Grids are excluded from frustum culling, since they usually extend far beyond their origin.
+pub fn grid_helper(options: &GridOptions) -> Rc<RefCell<Object3D>> {
+    let shader = builtin_program(
+        "grid",
+        include_str!("../shaders/grid.vert"),
+        include_str!("../shaders/grid.frag"),
+    );
+
+    let half_size = options.size.map_or(0.0, |size| size * 0.5);
+    let extent = if half_size > 0.0 { half_size } else { options.fade_end };
+
+    let mut material = Material::new(shader);
+    material.blend = BlendMode::Alpha;
+    material.cull = CullMode::None;
+    material.depth_write = false;
+    material.set_uniform("u_extent", UniformValue::Float(extent));
+    material.set_uniform("u_follow_camera", UniformValue::Int(options.size.is_none() as i32));
+    material.set_uniform("u_half_size", UniformValue::Float(half_size));
+    material.set_uniform("u_cell_size", UniformValue::Float(options.cell_size));
+    material.set_uniform("u_major_every", UniformValue::Float(options.major_every.max(1) as f32));
+    material.set_uniform("u_fade_start", UniformValue::Float(options.fade_start));
+    material.set_uniform("u_fade_end", UniformValue::Float(options.fade_end.max(options.fade_start + f32::EPSILON)));
+    material.set_uniform("u_minor_color", UniformValue::Vec4(output_color(options.minor_color)));
+    material.set_uniform("u_major_color", UniformValue::Vec4(output_color(options.major_color)));
+    material.set_uniform("u_x_axis_color", UniformValue::Vec4(output_color(options.x_axis_color)));
+    material.set_uniform("u_z_axis_color", UniformValue::Vec4(output_color(options.z_axis_color)));
+
+    let node = Object3D::new();
+    {
+        let mut grid = node.borrow_mut();
+        grid.set_geometry(unit_quad());
+        grid.set_material(material);
+        grid.set_frustum_culled(false);
+    }
+    node
+}
+
+/// A quad spanning `[-1, 1]` on the XZ plane; the vertex shader scales it to the grid's extent.
+fn unit_quad() -> Geometry {
+    let corner = |x: f32, z: f32| Vertex {
+        position: [x, 0.0, z],
+        normal: [0.0, 1.0, 0.0],
+        uv: [(x + 1.0) * 0.5, (z + 1.0) * 0.5],
+    };
+    Geometry {
+        vertices: vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+        indices: Indices::U16(vec![0, 2, 1, 0, 3, 2]),
+        topology: Topology::Triangles,
+        skin: None,
+    }
+}
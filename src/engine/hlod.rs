@@ -0,0 +1,196 @@
+//! Hierarchical level of detail (HLOD): merged proxies for distant clusters of static objects.
+//!
+//! Far away, a city block or a patch of forest covers a handful of pixels but still costs a
+//! draw call per building or tree. An [`HlodBuilder`] groups static objects into clusters on
+//! a grid, merges each cluster's meshes into one simplified proxy mesh (see
+//! [`mesh::simplify`](crate::engine::mesh::simplify)), and sets
+//! [draw distances](crate::engine::object3d::DrawDistance) so the proxy replaces the
+//! originals beyond a switch distance: one draw call per cluster instead of one per object.
+//!
+//! Only static content is clustered: triangle meshes with an opaque material, no instances,
+//! no existing draw distance, and no update callback on the node or any ancestor below the
+//! root. Objects are only merged with others using the same material, since the proxy is
+//! drawn with one material. Rebuild (after [`HlodCluster::remove`]) when static content
+//! changes.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{hlod::HlodBuilder, scene::Scene};
+//! # let scene = Scene::new();
+//! // Clusters of 50 x 50 x 50 units, swapped for proxies beyond 200 units
+//! let clusters = HlodBuilder::new(50.0, 200.0).build(scene.root());
+//! println!("{} HLOD proxies", clusters.len());
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::{Rc, Weak};
+use crate::engine::material::Material;
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::matrixfuncs::{matrix_inverse_or_identity, matrix_mul_4x4};
+use crate::engine::mesh::simplify::{merge, simplify};
+use crate::engine::object3d::{DrawDistance, Geometry, Object3D, Topology};
+
+/// Settings for building HLOD proxies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HlodBuilder {
+    /// Edge length of the grid cells objects are clustered by.
+    pub cluster_size: f32,
+
+    /// Camera distance from a cluster's center beyond which its proxy replaces the objects.
+    pub switch_distance: f32,
+
+    /// Cell size of the vertex clustering that simplifies proxies; `0.0` merges without
+    /// simplifying.
+    pub simplify_cell: f32,
+
+    /// Fewest objects worth merging into a proxy.
+    pub min_objects: usize,
+}
+
+/// One proxy and the objects it stands in for.
+#[derive(Debug)]
+pub struct HlodCluster {
+    proxy: Rc<RefCell<Object3D>>,
+    members: Vec<Weak<RefCell<Object3D>>>,
+    center: [f32; 3],
+}
+
+/// A static object found while walking the graph.
+struct Candidate {
+    node: Rc<RefCell<Object3D>>,
+    geometry: Rc<Geometry>,
+    world: [f32; 16],
+    bounds: Aabb,
+}
+
+impl HlodBuilder {
+    /// Clusters of `cluster_size` edge length, swapped beyond `switch_distance`, simplified
+    /// on a grid 1/50th of the cluster size, merging at least two objects.
+    pub fn new(cluster_size: f32, switch_distance: f32) -> Self {
+        Self { cluster_size, switch_distance, simplify_cell: cluster_size / 50.0, min_objects: 2 }
+    }
+
+    /// Builds proxies for the static objects under `root`, adds them as children of `root`
+    /// and limits the draw distances of the merged objects. Requires no GL context; proxies
+    /// are uploaded when first drawn.
+    pub fn build(&self, root: &Rc<RefCell<Object3D>>) -> Vec<HlodCluster> {
+        let mut candidates = Vec::new();
+        let children = root.borrow().children().to_vec();
+        for child in &children {
+            collect_static(child, &mut candidates);
+        }
+
+        // Group by grid cell, then by material within the cell (ordered, so builds are repeatable)
+        let mut groups: BTreeMap<[i32; 3], Vec<(Material, Vec<Candidate>)>> = BTreeMap::new();
+        for candidate in candidates {
+            let cell = candidate.bounds.center().map(|c| (c / self.cluster_size.max(f32::EPSILON)).floor() as i32);
+            let Some(material) = candidate.node.borrow().material().cloned() else {
+                continue;
+            };
+            let cell_groups = groups.entry(cell).or_default();
+            match cell_groups
+                .iter_mut()
+                .find(|(other, _)| other.same_parameters(&material) && other.same_state(&material))
+            {
+                Some((_, members)) => members.push(candidate),
+                None => cell_groups.push((material, vec![candidate])),
+            }
+        }
+
+        let to_root = matrix_inverse_or_identity(&root.borrow_mut().world_matrix());
+        let mut clusters = Vec::new();
+        for (material, members) in groups.into_values().flatten() {
+            if members.len() < self.min_objects.max(1) {
+                continue;
+            }
+            clusters.push(self.build_cluster(root, &to_root, material, members));
+        }
+        clusters
+    }
+
+    /// Merges `members` into a proxy under `root` and sets up the distance swap.
+    fn build_cluster(
+        &self,
+        root: &Rc<RefCell<Object3D>>,
+        to_root: &[f32; 16],
+        material: Material,
+        members: Vec<Candidate>,
+    ) -> HlodCluster {
+        let parts: Vec<(&Geometry, [f32; 16])> = members
+            .iter()
+            .map(|member| (&*member.geometry, matrix_mul_4x4(to_root, &member.world)))
+            .collect();
+        let merged = merge(&parts);
+        let geometry = if self.simplify_cell > 0.0 { simplify(&merged, self.simplify_cell) } else { merged };
+
+        let bounds = members.iter().skip(1).fold(members[0].bounds, |bounds, member| bounds.union(&member.bounds));
+        let center = bounds.center();
+        let pivot = Some(center);
+        for member in &members {
+            let range = DrawDistance { min: 0.0, max: self.switch_distance, pivot };
+            member.node.borrow_mut().set_draw_distance(Some(range));
+        }
+
+        let proxy = Object3D::new();
+        {
+            let mut proxy = proxy.borrow_mut();
+            proxy.set_geometry(geometry);
+            proxy.set_material(material);
+            proxy.set_draw_distance(Some(DrawDistance { min: self.switch_distance, max: f32::INFINITY, pivot }));
+        }
+        Object3D::add_child(root, proxy.clone());
+
+        HlodCluster { proxy, members: members.iter().map(|member| Rc::downgrade(&member.node)).collect(), center }
+    }
+}
+
+impl HlodCluster {
+    /// The merged proxy node.
+    pub fn proxy(&self) -> &Rc<RefCell<Object3D>> {
+        &self.proxy
+    }
+
+    /// The objects the proxy replaces at a distance, if still alive.
+    pub fn members(&self) -> Vec<Rc<RefCell<Object3D>>> {
+        self.members.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    /// World-space center the switch distance is measured from.
+    pub fn center(&self) -> [f32; 3] {
+        self.center
+    }
+
+    /// Detaches the proxy and lets the members draw at any distance again.
+    pub fn remove(&self) {
+        Object3D::detach(&self.proxy);
+        for member in self.members() {
+            member.borrow_mut().set_draw_distance(None);
+        }
+    }
+}
+
+/// Gathers the static objects in the subtree of `node`. Subtrees under a node with an
+/// update callback may move and are skipped.
+fn collect_static(node: &Rc<RefCell<Object3D>>, candidates: &mut Vec<Candidate>) {
+    let children = {
+        let mut object = node.borrow_mut();
+        if object.has_update() {
+            return;
+        }
+        let geometry = object.shared_geometry().filter(|geometry| geometry.topology == Topology::Triangles);
+        let is_static = object.material().is_some_and(|material| !material.is_transparent())
+            && object.instances().is_none()
+            && object.draw_distance().is_none();
+        if let Some(geometry) = geometry.filter(|_| is_static)
+            && let Some(local_bounds) = geometry.aabb()
+        {
+            let world = object.world_matrix();
+            candidates.push(Candidate { node: node.clone(), bounds: local_bounds.transformed(&world), geometry, world });
+        }
+        object.children().to_vec()
+    };
+    for child in &children {
+        collect_static(child, candidates);
+    }
+}
@@ -0,0 +1,217 @@
+//! Reloading shaders and textures when their files change on disk.
+//!
+//! Tweaking a shader or repainting a texture shouldn't mean restarting the game. Shaders and
+//! textures loaded through the renderer's [`HotReload`]
+//! ([`Renderer::hot_reload_mut`](crate::engine::renderer::Renderer::hot_reload_mut)), or
+//! registered with it after loading, have their files checked a few times a second. When a
+//! file changes, the shader is recompiled or the texture uploaded again, in place: materials
+//! sharing the `Rc` draw with the new version from the next frame on.
+//!
+//! A shader that no longer compiles keeps running its previous version, and the compiler's
+//! errors are logged; fixing the file and saving again picks it up. The same goes for a
+//! texture file that can't be decoded, e.g. because it was caught half written.
+//!
+//! Assets are watched for as long as something else holds them.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::material::Material;
+//! # use rustge::engine::renderer::Renderer;
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! let hot_reload = renderer.hot_reload_mut();
+//! let water = hot_reload.load_program("shaders/water.vert", "shaders/water.frag").expect("water shader");
+//! let ripples = hot_reload.load_texture("textures/ripples.png").expect("ripple texture");
+//!
+//! let mut material = Material::new(water);
+//! material.set_texture("u_ripples", ripples);
+//! // Saving either file now updates the material while the game runs
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use crate::engine::shader::GLShaderProgram;
+use crate::engine::texture::Texture;
+
+/// A watched asset, held weakly.
+#[derive(Debug)]
+enum Asset {
+    /// A program from a vertex and a fragment shader file.
+    Program(Weak<GLShaderProgram>),
+    /// A compute program from one file.
+    ComputeProgram(Weak<GLShaderProgram>),
+    Texture(Weak<Texture>),
+}
+
+impl Asset {
+    fn is_alive(&self) -> bool {
+        match self {
+            Asset::Program(program) | Asset::ComputeProgram(program) => program.strong_count() > 0,
+            Asset::Texture(texture) => texture.strong_count() > 0,
+        }
+    }
+}
+
+/// An asset and the files it is made from, with their modification times when last loaded.
+#[derive(Debug)]
+struct Watched {
+    asset: Asset,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// Watches shader and texture files and reloads them when they change; see the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct HotReload {
+    /// Time between checks of the watched files.
+    pub interval: Duration,
+
+    watched: Vec<Watched>,
+    next_check: Option<Instant>,
+}
+
+impl HotReload {
+    /// Watches nothing yet, and checks files every quarter of a second.
+    pub fn new() -> Self {
+        Self { interval: Duration::from_millis(250), watched: Vec::new(), next_check: None }
+    }
+
+    /// Loads a program from vertex and fragment shader files, see
+    /// [`GLShaderProgram::load`], and watches them. Requires a current GL context.
+    pub fn load_program(&mut self, vertex_path: impl AsRef<Path>, fragment_path: impl AsRef<Path>) -> io::Result<Rc<GLShaderProgram>> {
+        let program = Rc::new(GLShaderProgram::load(&vertex_path, &fragment_path)?);
+        self.watch_program(&program, vertex_path, fragment_path);
+        Ok(program)
+    }
+
+    /// Loads a compute program from a file, see [`GLShaderProgram::load_compute`], and
+    /// watches it. Requires a current GL context.
+    pub fn load_compute_program(&mut self, path: impl AsRef<Path>) -> io::Result<Rc<GLShaderProgram>> {
+        let program = Rc::new(GLShaderProgram::load_compute(&path)?);
+        self.watch_compute_program(&program, path);
+        Ok(program)
+    }
+
+    /// Loads a PNG texture, see [`Texture::load_png`], and watches its file. Requires a
+    /// current GL context.
+    pub fn load_texture(&mut self, path: impl AsRef<Path>) -> io::Result<Rc<Texture>> {
+        let texture = Rc::new(Texture::load_png(&path)?);
+        self.watch_texture(&texture, path);
+        Ok(texture)
+    }
+
+    /// Recompiles `program` from the vertex and fragment shader files at `vertex_path` and
+    /// `fragment_path` whenever either changes.
+    pub fn watch_program(&mut self, program: &Rc<GLShaderProgram>, vertex_path: impl AsRef<Path>, fragment_path: impl AsRef<Path>) {
+        self.watch(Asset::Program(Rc::downgrade(program)), &[vertex_path.as_ref(), fragment_path.as_ref()]);
+    }
+
+    /// Recompiles compute program `program` from the file at `path` whenever it changes.
+    pub fn watch_compute_program(&mut self, program: &Rc<GLShaderProgram>, path: impl AsRef<Path>) {
+        self.watch(Asset::ComputeProgram(Rc::downgrade(program)), &[path.as_ref()]);
+    }
+
+    /// Uploads `texture` again from the PNG file at `path` whenever it changes.
+    pub fn watch_texture(&mut self, texture: &Rc<Texture>, path: impl AsRef<Path>) {
+        self.watch(Asset::Texture(Rc::downgrade(texture)), &[path.as_ref()]);
+    }
+
+    fn watch(&mut self, asset: Asset, paths: &[&Path]) {
+        let files = paths.iter().map(|path| (path.to_path_buf(), modified(path))).collect();
+        self.watched.push(Watched { asset, files });
+    }
+
+    /// Number of assets watched, including ones dropped since the last check.
+    pub fn len(&self) -> usize {
+        self.watched.len()
+    }
+
+    /// Returns `true` if no assets are watched.
+    pub fn is_empty(&self) -> bool {
+        self.watched.is_empty()
+    }
+
+    /// Stops watching every asset.
+    pub fn clear(&mut self) {
+        self.watched.clear();
+    }
+
+    /// Checks the watched files now and reloads the assets whose files changed, returning how
+    /// many reloaded successfully. Failures are logged, and the assets keep their previous
+    /// version until their files change again. Requires a current GL context.
+    pub fn check(&mut self) -> usize {
+        self.watched.retain(|watched| watched.asset.is_alive());
+        let mut reloaded = 0;
+        for watched in &mut self.watched {
+            let mut changed = false;
+            for (path, last_modified) in &mut watched.files {
+                // A file missing for a moment, as editors replace it, is checked again later
+                if let Some(time) = modified(path)
+                    && Some(time) != *last_modified
+                {
+                    *last_modified = Some(time);
+                    changed = true;
+                }
+            }
+            if !changed {
+                continue;
+            }
+            let paths: Vec<&Path> = watched.files.iter().map(|(path, _)| path.as_path()).collect();
+            let names = paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(", ");
+            match reload(&watched.asset, &paths) {
+                Ok(()) => {
+                    eprintln!("Reloaded {names}");
+                    reloaded += 1;
+                }
+                Err(error) => eprintln!("Warning: Could not reload {names}, keeping the previous version:\n{error}"),
+            }
+        }
+        reloaded
+    }
+
+    /// Checks the watched files if the [interval](Self::interval) has passed since the last
+    /// check; called by the renderer every frame.
+    pub(crate) fn poll(&mut self) {
+        if self.watched.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        if self.next_check.is_some_and(|next| now < next) {
+            return;
+        }
+        self.next_check = Some(now + self.interval);
+        self.check();
+    }
+}
+
+impl Default for HotReload {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reloads `asset` from the files at `paths`, leaving it as it was on error.
+fn reload(asset: &Asset, paths: &[&Path]) -> Result<(), String> {
+    match asset {
+        Asset::Program(program) => {
+            let vs_src = fs::read_to_string(paths[0]).map_err(|error| error.to_string())?;
+            let fs_src = fs::read_to_string(paths[1]).map_err(|error| error.to_string())?;
+            program.upgrade().map_or(Ok(()), |program| program.reload(&vs_src, &fs_src))
+        }
+        Asset::ComputeProgram(program) => {
+            let cs_src = fs::read_to_string(paths[0]).map_err(|error| error.to_string())?;
+            program.upgrade().map_or(Ok(()), |program| program.reload_compute(&cs_src))
+        }
+        Asset::Texture(texture) => match texture.upgrade() {
+            Some(texture) => texture.reload_png(paths[0]).map_err(|error| error.to_string()),
+            None => Ok(()),
+        },
+    }
+}
+
+/// When the file at `path` was last modified, if it can be read.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
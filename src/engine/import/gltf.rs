@@ -0,0 +1,434 @@
+//! glTF 2.0 import (`.gltf` with external or embedded buffers, and binary `.glb`).
+//!
+//! Loads the default scene's node hierarchy with names, transforms, meshes, metallic-roughness
+//! materials (as [`PbrMaterial`]s with their textures) and `KHR_lights_punctual` lights.
+//! Meshes used by several nodes share one [`Geometry`], materials sharing an image share
+//! one [`Texture`]. Textures are read with the first UV set, repeating and trilinearly
+//! filtered whatever their sampler says; materials exported by this engine from
+//! [`Material::phong`] come back as such. Morph targets are not imported. Node extras, like the custom
+//! properties Blender exports, become node [metadata](Object3D::metadata): each member of an
+//! extras object under its own key.
+//!
+//! Skinned meshes get a [`Skin`] component bound to a [`Skeleton`] of the imported joint
+//! nodes (see [`skeleton`](crate::engine::animation::skeleton)). Animations become
+//! [`AnimationClip`]s in an [`AnimationLibrary`] component on the returned node, ready to be
+//! played with an [`AnimationPlayer`](crate::engine::animation::clip::AnimationPlayer);
+//! channels animating morph target weights are skipped.
+//!
+//! glTF puts the texture origin at the top-left corner and the engine at the bottom-left,
+//! so V coordinates are flipped on the way in.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use ::gltf::animation::util::ReadOutputs;
+use ::gltf::image::Format;
+use ::gltf::khr_lights_punctual::Kind;
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::Mode;
+use serde_json::Value;
+use crate::engine::animation::clip::{AnimatedProperty, AnimationClip, AnimationLibrary, Channel, Interpolation};
+use crate::engine::animation::skeleton::{Skeleton, Skin};
+use crate::engine::export::gltf::{PHONG_SHADING, SHADING_EXTRA};
+use crate::engine::light::{Attenuation, Light, LightKind, LightLodOverride};
+use crate::engine::material::{BlendMode, CullMode, Material};
+use crate::engine::math::color::Color;
+use crate::engine::object3d::{Geometry, Index, Indices, Object3D, SkinVertex, Topology, Vertex};
+use crate::engine::pbr::PbrMaterial;
+use crate::engine::readback::flip_rows;
+use crate::engine::shader::UniformValue;
+use crate::engine::texture::Texture;
+
+/// Loads a `.gltf` or `.glb` file into a node holding the default scene (or the first scene
+/// if none is marked default). Requires a current GL context for the materials.
+pub fn load_gltf(path: impl AsRef<Path>) -> io::Result<Rc<RefCell<Object3D>>> {
+    let (document, buffers, images) = ::gltf::import(path).map_err(convert_error)?;
+    Ok(convert_document(&document, &buffers, &images))
+}
+
+/// Loads a binary glTF (`.glb`) file, or a `.gltf` file with embedded buffers, from memory,
+/// e.g. as written by [`glb_bytes`](crate::engine::export::gltf::glb_bytes). Requires a
+/// current GL context for the materials.
+pub fn load_gltf_slice(bytes: &[u8]) -> io::Result<Rc<RefCell<Object3D>>> {
+    let (document, buffers, images) = ::gltf::import_slice(bytes).map_err(convert_error)?;
+    Ok(convert_document(&document, &buffers, &images))
+}
+
+fn convert_error(err: ::gltf::Error) -> io::Error {
+    match err {
+        ::gltf::Error::Io(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, err),
+    }
+}
+
+/// Converts the default scene of a loaded document into a node holding its root nodes.
+fn convert_document(document: &::gltf::Document, buffers: &[::gltf::buffer::Data], images: &[::gltf::image::Data]) -> Rc<RefCell<Object3D>> {
+    let mut loader = Loader {
+        buffers,
+        images,
+        geometries: HashMap::new(),
+        materials: HashMap::new(),
+        textures: HashMap::new(),
+        nodes: HashMap::new(),
+        skinned: Vec::new(),
+    };
+    let root = Object3D::new();
+    if let Some(scene) = document.default_scene().or_else(|| document.scenes().next()) {
+        for node in scene.nodes() {
+            Object3D::add_child(&root, loader.node(&node));
+        }
+    }
+    loader.bind_skins(document);
+
+    let clips: Vec<_> = document.animations().filter_map(|animation| loader.animation(&animation)).collect();
+    if !clips.is_empty() {
+        root.borrow_mut().insert_component(AnimationLibrary::new(clips));
+    }
+    root
+}
+
+/// Converts glTF nodes, sharing geometry and materials between nodes that reuse them.
+struct Loader<'a> {
+    buffers: &'a [::gltf::buffer::Data],
+    images: &'a [::gltf::image::Data],
+
+    /// Converted primitives by (mesh index, primitive index).
+    geometries: HashMap<(usize, usize), Rc<Geometry>>,
+
+    /// Converted materials by material index (`None` for the glTF default material).
+    materials: HashMap<Option<usize>, Material>,
+
+    /// Uploaded textures by image index and whether they hold sRGB color.
+    textures: HashMap<(usize, bool), Rc<Texture>>,
+
+    /// Converted nodes by node index, for resolving joints and animation targets.
+    nodes: HashMap<usize, Rc<RefCell<Object3D>>>,
+
+    /// Nodes holding skinned geometry, with the index of their skin.
+    skinned: Vec<(Rc<RefCell<Object3D>>, usize)>,
+}
+
+impl Loader<'_> {
+    /// Converts `node` and its descendants.
+    fn node(&mut self, node: &::gltf::Node) -> Rc<RefCell<Object3D>> {
+        let object = Object3D::new();
+        if let Some(name) = node.name() {
+            object.borrow_mut().set_name(name);
+        }
+        for (key, value) in extras(node.extras()) {
+            object.borrow_mut().set_metadata(&key, value);
+        }
+        let (position, rotation, scale) = node.transform().decomposed();
+        object.borrow_mut().set_transform(position, rotation, scale);
+        object.borrow_mut().set_light(node.light().map(|light| convert_light(&light)));
+
+        if let Some(mesh) = node.mesh() {
+            let primitives: Vec<_> = mesh
+                .primitives()
+                .filter_map(|primitive| {
+                    let geometry = self.geometry(mesh.index(), &primitive)?;
+                    Some((geometry, self.material(&primitive.material())))
+                })
+                .collect();
+
+            // A single primitive goes on the node itself, several get a child node each
+            let skin = node.skin().map(|skin| skin.index());
+            if let [(geometry, material)] = primitives.as_slice() {
+                object.borrow_mut().set_shared_geometry(geometry.clone());
+                object.borrow_mut().set_material(material.clone());
+                self.skinned.extend(skin.map(|skin| (object.clone(), skin)));
+            } else {
+                for (geometry, material) in primitives {
+                    let child = Object3D::new();
+                    child.borrow_mut().set_shared_geometry(geometry);
+                    child.borrow_mut().set_material(material);
+                    self.skinned.extend(skin.map(|skin| (child.clone(), skin)));
+                    Object3D::add_child(&object, child);
+                }
+            }
+        }
+        self.nodes.insert(node.index(), object.clone());
+
+        for child in node.children() {
+            Object3D::add_child(&object, self.node(&child));
+        }
+        object
+    }
+
+    /// The geometry of `primitive`, or `None` if it has no positions.
+    fn geometry(&mut self, mesh: usize, primitive: &::gltf::Primitive) -> Option<Rc<Geometry>> {
+        let key = (mesh, primitive.index());
+        if let Some(geometry) = self.geometries.get(&key) {
+            return Some(geometry.clone());
+        }
+
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+        let positions: Vec<[f32; 3]> = reader.read_positions()?.collect();
+        let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(Iterator::collect);
+        let uvs: Option<Vec<[f32; 2]>> = reader.read_tex_coords(0).map(|uvs| uvs.into_f32().collect());
+        let skin = reader.read_joints(0).zip(reader.read_weights(0)).map(|(joints, weights)| {
+            joints
+                .into_u16()
+                .zip(weights.into_f32())
+                .map(|(joints, weights)| SkinVertex { joints, weights })
+                .collect::<Vec<_>>()
+        });
+        let indices: Vec<Index> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as Index).collect(),
+        };
+
+        let vertices = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| Vertex {
+                position,
+                normal: normals.as_ref().and_then(|normals| normals.get(i).copied()).unwrap_or([0.0, 1.0, 0.0]),
+                uv: uvs.as_ref().and_then(|uvs| uvs.get(i)).map_or([0.0, 0.0], |uv| [uv[0], 1.0 - uv[1]]),
+            })
+            .collect();
+        let (topology, indices) = convert_topology(primitive.mode(), indices);
+
+        let skin = skin.filter(|skin| skin.len() == positions.len());
+        let mut geometry = Geometry { vertices, indices: Indices::from_u32(indices), topology, skin };
+        if normals.is_none() {
+            geometry.compute_normals();
+        }
+        let geometry = Rc::new(geometry);
+        self.geometries.insert(key, geometry.clone());
+        Some(geometry)
+    }
+
+    /// Adds a [`Skin`] to every node with skinned geometry, sharing one [`Skeleton`] per
+    /// glTF skin. Skins with joints outside the loaded scene are skipped.
+    fn bind_skins(&self, document: &::gltf::Document) {
+        let mut skeletons: HashMap<usize, Option<Rc<Skeleton>>> = HashMap::new();
+        for (object, index) in &self.skinned {
+            let skeleton = skeletons.entry(*index).or_insert_with(|| {
+                let skin = document.skins().nth(*index)?;
+                let joints: Option<Vec<_>> = skin.joints().map(|joint| self.nodes.get(&joint.index()).cloned()).collect();
+                let Some(joints) = joints else {
+                    eprintln!("Warning: skipping glTF skin {index}, its joints are not all in the scene");
+                    return None;
+                };
+                let reader = skin.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+                let inverse_bind_matrices = reader
+                    .read_inverse_bind_matrices()
+                    .map(|matrices| matrices.map(|columns| std::array::from_fn(|i| columns[i / 4][i % 4])).collect())
+                    .unwrap_or_default();
+                Some(Rc::new(Skeleton::new(&joints, inverse_bind_matrices)))
+            });
+            if let Some(skeleton) = skeleton {
+                object.borrow_mut().insert_component(Skin::new(skeleton.clone()));
+            }
+        }
+    }
+
+    /// Converts `animation` into a clip of its channels targeting loaded nodes, or `None`
+    /// if it has none. Unnamed animations are called `animation <index>`.
+    fn animation(&self, animation: &::gltf::Animation) -> Option<Rc<AnimationClip>> {
+        let channels: Vec<Channel> = animation
+            .channels()
+            .filter_map(|channel| {
+                let target = self.nodes.get(&channel.target().node().index())?;
+                let reader = channel.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+                let times = reader.read_inputs()?.collect();
+                let (property, values) = match reader.read_outputs()? {
+                    ReadOutputs::Translations(values) => (AnimatedProperty::Translation, values.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+                    ReadOutputs::Rotations(values) => (AnimatedProperty::Rotation, values.into_f32().collect()),
+                    ReadOutputs::Scales(values) => (AnimatedProperty::Scale, values.map(|[x, y, z]| [x, y, z, 0.0]).collect()),
+                    ReadOutputs::MorphTargetWeights(_) => return None,
+                };
+                let interpolation = match channel.sampler().interpolation() {
+                    ::gltf::animation::Interpolation::Step => Interpolation::Step,
+                    ::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    ::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+                Some(Channel { target: Rc::downgrade(target), property, interpolation, times, values })
+            })
+            .collect();
+        if channels.is_empty() {
+            return None;
+        }
+        let name = animation.name().map_or_else(|| format!("animation {}", animation.index()), str::to_string);
+        Some(Rc::new(AnimationClip::new(&name, channels)))
+    }
+
+    /// The engine material for `material`, converted on first use.
+    fn material(&mut self, material: &::gltf::Material) -> Material {
+        if let Some(converted) = self.materials.get(&material.index()) {
+            return converted.clone();
+        }
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, a] = pbr.base_color_factor();
+        let mut converted = if is_phong(material) {
+            let mut phong = Material::phong(Color::linear_rgba(r, g, b, a));
+            // Inverse of the exporter's roughness = sqrt(2 / (shininess + 2))
+            let roughness = pbr.roughness_factor().clamp(0.05, 1.0);
+            phong.set_uniform("u_shininess", UniformValue::Float(2.0 / (roughness * roughness) - 2.0));
+            phong
+        } else {
+            let [er, eg, eb] = material.emissive_factor();
+            PbrMaterial {
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                emissive: Color::linear_rgb(er, eg, eb),
+                normal_scale: material.normal_texture().map_or(1.0, |normal| normal.scale()),
+                occlusion_strength: material.occlusion_texture().map_or(1.0, |occlusion| occlusion.strength()),
+                alpha_cutoff: (material.alpha_mode() == AlphaMode::Mask).then(|| material.alpha_cutoff().unwrap_or(0.5)),
+                albedo_map: pbr.base_color_texture().and_then(|info| self.texture(info.texture(), info.tex_coord(), true)),
+                metallic_roughness_map: pbr.metallic_roughness_texture().and_then(|info| self.texture(info.texture(), info.tex_coord(), false)),
+                normal_map: material.normal_texture().and_then(|normal| self.texture(normal.texture(), normal.tex_coord(), false)),
+                occlusion_map: material.occlusion_texture().and_then(|occlusion| self.texture(occlusion.texture(), occlusion.tex_coord(), false)),
+                emissive_map: material.emissive_texture().and_then(|info| self.texture(info.texture(), info.tex_coord(), true)),
+                ..PbrMaterial::new(Color::linear_rgba(r, g, b, a))
+            }
+            .material()
+        };
+        if material.alpha_mode() == AlphaMode::Blend {
+            converted.blend = BlendMode::Alpha;
+        }
+        if material.double_sided() {
+            converted.cull = CullMode::None;
+        }
+        self.materials.insert(material.index(), converted.clone());
+        converted
+    }
+
+    /// The texture showing `texture`'s image, uploaded on first use as sRGB color or as data.
+    /// `None`, with a warning, for images read with a UV set other than the first or in a
+    /// format that can't be converted.
+    fn texture(&mut self, texture: ::gltf::Texture, tex_coord: u32, srgb: bool) -> Option<Rc<Texture>> {
+        if tex_coord != 0 {
+            eprintln!("Warning: Skipping glTF texture {} on UV set {}; only the first set is imported", texture.index(), tex_coord);
+            return None;
+        }
+        let index = texture.source().index();
+        if let Some(uploaded) = self.textures.get(&(index, srgb)) {
+            return Some(uploaded.clone());
+        }
+        let image = self.images.get(index)?;
+        let Some(mut pixels) = image_rgba8(image) else {
+            eprintln!("Warning: Skipping glTF image {index} in unsupported format {:?}", image.format);
+            return None;
+        };
+        // glTF images start at the top row, textures at the bottom one
+        flip_rows(&mut pixels, image.width as usize * 4);
+        let label = texture.source().name().map_or_else(|| format!("glTF image {index}"), str::to_string);
+        let uploaded = Rc::new(if srgb {
+            Texture::from_rgba8(image.width, image.height, &pixels, &label)
+        } else {
+            Texture::from_linear_rgba8(image.width, image.height, &pixels, &label)
+        });
+        self.textures.insert((index, srgb), uploaded.clone());
+        Some(uploaded)
+    }
+}
+
+/// Whether `material` was exported from a [`Material::phong`], see
+/// [`export::gltf`](crate::engine::export::gltf).
+fn is_phong(material: &::gltf::Material) -> bool {
+    extras(material.extras()).iter().any(|(key, value)| key == SHADING_EXTRA && value == PHONG_SHADING)
+}
+
+/// `image`'s pixels as tightly packed 8-bit RGBA, or `None` for floating-point formats.
+/// Gray images are spread over the color channels, 16-bit channels keep their high byte.
+fn image_rgba8(image: &::gltf::image::Data) -> Option<Vec<u8>> {
+    let (channels, bytes_per_channel) = match image.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => return None,
+    };
+    let pixels = image.pixels.chunks_exact(channels * bytes_per_channel).flat_map(|pixel| {
+        let channel = |i: usize| match bytes_per_channel {
+            1 => pixel[i],
+            _ => (u16::from_ne_bytes([pixel[i * 2], pixel[i * 2 + 1]]) >> 8) as u8,
+        };
+        match channels {
+            1 => [channel(0), channel(0), channel(0), 255],
+            2 => [channel(0), channel(0), channel(0), channel(1)],
+            3 => [channel(0), channel(1), channel(2), 255],
+            _ => [channel(0), channel(1), channel(2), channel(3)],
+        }
+    });
+    Some(pixels.collect())
+}
+
+/// Maps a glTF primitive mode to a topology, rewriting strips, fans and loops the engine
+/// has no mode for into plain lists.
+/// The entries of glTF `extras`: the members of an object, or anything else as `"extras"`.
+fn extras(extras: &::gltf::json::Extras) -> Vec<(String, Value)> {
+    let Some(raw) = extras else {
+        return Vec::new();
+    };
+    match serde_json::from_str(raw.get()) {
+        Ok(Value::Object(members)) => members.into_iter().collect(),
+        Ok(Value::Null) => Vec::new(),
+        Ok(value) => vec![("extras".to_string(), value)],
+        Err(error) => {
+            eprintln!("Warning: Ignoring unreadable glTF extras: {error}");
+            Vec::new()
+        }
+    }
+}
+
+fn convert_topology(mode: Mode, indices: Vec<Index>) -> (Topology, Vec<Index>) {
+    match mode {
+        Mode::Points => (Topology::Points, indices),
+        Mode::Lines => (Topology::Lines, indices),
+        Mode::LineStrip => (Topology::LineStrip, indices),
+        Mode::LineLoop => {
+            let mut indices = indices;
+            if let Some(&first) = indices.first() {
+                indices.push(first);
+            }
+            (Topology::LineStrip, indices)
+        }
+        Mode::Triangles => (Topology::Triangles, indices),
+        Mode::TriangleStrip => {
+            // Every other triangle of a strip is wound the other way
+            let triangles = indices
+                .windows(3)
+                .enumerate()
+                .flat_map(|(i, w)| if i % 2 == 0 { [w[0], w[1], w[2]] } else { [w[1], w[0], w[2]] })
+                .collect();
+            (Topology::Triangles, triangles)
+        }
+        Mode::TriangleFan => {
+            let triangles = match indices.split_first() {
+                Some((&center, rest)) => rest.windows(2).flat_map(|w| [center, w[0], w[1]]).collect(),
+                None => Vec::new(),
+            };
+            (Topology::Triangles, triangles)
+        }
+    }
+}
+
+/// Converts a `KHR_lights_punctual` light. Without a range, point and spot lights fall off
+/// with the inverse square of the distance, as the extension specifies.
+fn convert_light(light: &::gltf::khr_lights_punctual::Light) -> Light {
+    let [r, g, b] = light.color();
+    let color = Color::linear_rgb(r, g, b);
+    let attenuation = match light.range() {
+        Some(range) => Attenuation::for_range(range),
+        None => Attenuation { constant: 1.0, linear: 0.0, quadratic: 1.0 },
+    };
+    let kind = match light.kind() {
+        Kind::Directional => LightKind::Directional,
+        Kind::Point => LightKind::Point { attenuation },
+        Kind::Spot { inner_cone_angle, outer_cone_angle } => LightKind::Spot {
+            attenuation,
+            inner_angle: inner_cone_angle.min(outer_cone_angle * 0.999),
+            outer_angle: outer_cone_angle,
+        },
+    };
+    Light { kind, color, intensity: light.intensity(), cookie: None, lod_override: LightLodOverride::default() }
+}
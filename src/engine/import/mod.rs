@@ -0,0 +1,56 @@
+//! Model importers.
+//!
+//! [`load_model`] picks the importer from the file extension: Wavefront OBJ (`.obj`, see
+//! [`obj`]) or glTF 2.0 (`.gltf`/`.glb`, see [`gltf`](self::gltf)). Either way the model
+//! arrives as a single node to add to a scene.
+//!
+//! ```no_run
+//! # use rustge::engine::{import::load_model, renderer::Renderer};
+//! # let renderer = Renderer::new("Example", 800, 600);
+//! let model = load_model("assets/helmet.glb").expect("failed to load model");
+//! renderer.get_scene().unwrap().add(model);
+//! ```
+
+pub mod gltf;
+pub mod obj;
+
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use crate::engine::object3d::Object3D;
+
+/// File extensions [`load_model`] understands, lowercase.
+pub const MODEL_EXTENSIONS: &[&str] = &["obj", "gltf", "glb"];
+
+/// Whether `path` has an extension [`load_model`] can import.
+///
+/// ```
+/// # use rustge::engine::import::is_model_file;
+/// assert!(is_model_file("scene.GLB"));
+/// assert!(!is_model_file("notes.txt"));
+/// ```
+pub fn is_model_file(path: impl AsRef<Path>) -> bool {
+    extension(path.as_ref()).is_some_and(|ext| MODEL_EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// Loads an OBJ or glTF model, chosen by file extension, into a new node. Requires a current
+/// GL context for the materials.
+///
+/// Unknown extensions fail with [`io::ErrorKind::Unsupported`].
+pub fn load_model(path: impl AsRef<Path>) -> io::Result<Rc<RefCell<Object3D>>> {
+    let path = path.as_ref();
+    match extension(path).as_deref() {
+        Some("obj") => obj::load_obj(path),
+        Some("gltf" | "glb") => self::gltf::load_gltf(path),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{}: unsupported model format", path.display()),
+        )),
+    }
+}
+
+/// The lowercase extension of `path`.
+fn extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase)
+}
@@ -0,0 +1,325 @@
+//! Wavefront OBJ import.
+//!
+//! Reads positions, texture coordinates, normals and polygonal faces (triangulated as fans),
+//! split into one mesh per object/group and material. Materials come from the `mtllib` files
+//! next to the model: diffuse color (`Kd`), opacity (`d` or `Tr`) and shininess (`Ns`).
+//! Lines, points, curves and texture maps are ignored. Meshes without normals get smooth
+//! normals computed from their faces.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use crate::engine::material::{BlendMode, Material};
+use crate::engine::math::color::Color;
+use crate::engine::object3d::{Geometry, Index, Indices, Object3D, Topology, Vertex};
+use crate::engine::shader::UniformValue;
+
+/// A mesh read from an OBJ file: the faces of one object/group using one material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMesh {
+    /// Name of the `o` or `g` statement the faces belong to, if any.
+    pub name: Option<String>,
+
+    /// Name of the object (`o`) the faces belong to, if any.
+    pub object: Option<String>,
+
+    /// Names of the groups (`g`) the faces belong to, in the order listed.
+    pub groups: Vec<String>,
+
+    /// Name of the material selected with `usemtl`, if any.
+    pub material: Option<String>,
+
+    /// The triangulated faces.
+    pub geometry: Geometry,
+}
+
+/// A material read from an MTL file.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjMaterial {
+    /// Diffuse color (`Kd`) and opacity (`d`, or `1 - Tr`).
+    pub color: Color,
+
+    /// Specular exponent (`Ns`).
+    pub shininess: f32,
+}
+
+impl Default for ObjMaterial {
+    fn default() -> Self {
+        Self { color: Color::WHITE, shininess: 32.0 }
+    }
+}
+
+/// Parses OBJ source text into meshes, returning them with the `mtllib` files they reference.
+///
+/// Faces referring to missing vertices are an error.
+///
+/// ```
+/// # use rustge::engine::import::obj::parse_obj;
+/// let source = "
+///     o quad
+///     v 0 0 0
+///     v 1 0 0
+///     v 1 1 0
+///     v 0 1 0
+///     f 1 2 3 4
+/// ";
+/// let (meshes, libraries) = parse_obj(source).unwrap();
+/// assert_eq!(meshes[0].name.as_deref(), Some("quad"));
+/// assert_eq!(meshes[0].object.as_deref(), Some("quad"));
+/// assert_eq!(meshes[0].geometry.indices.len(), 6);
+/// assert_eq!(meshes[0].geometry.vertices[0].normal, [0.0, 0.0, 1.0]);
+/// assert!(libraries.is_empty());
+/// ```
+pub fn parse_obj(source: &str) -> io::Result<(Vec<ObjMesh>, Vec<String>)> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut libraries = Vec::new();
+    let mut meshes = Vec::new();
+    let mut builder = MeshBuilder::default();
+
+    for (line_number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let error = |message: &str| invalid_data(format!("OBJ line {}: {message}", line_number + 1));
+
+        match keyword {
+            "v" => positions.push(parse_floats(words).ok_or_else(|| error("bad vertex position"))?),
+            "vt" => {
+                // The third (w) coordinate is optional and unused
+                let values: Vec<f32> = words.map(str::parse).collect::<Result<_, _>>().map_err(|_| error("bad texture coordinate"))?;
+                uvs.push([values.first().copied().unwrap_or(0.0), values.get(1).copied().unwrap_or(0.0)]);
+            }
+            "vn" => normals.push(parse_floats(words).ok_or_else(|| error("bad vertex normal"))?),
+            "f" => {
+                let corners = words
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| error("bad face index"))?;
+                if corners.len() < 3 {
+                    return Err(error("face with fewer than three vertices"));
+                }
+                let indices: Vec<Index> = corners
+                    .iter()
+                    .map(|&corner| builder.vertex(corner, &positions, &uvs, &normals))
+                    .collect();
+                for i in 1..indices.len() - 1 {
+                    builder.indices.extend_from_slice(&[indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            "o" | "g" => {
+                let words: Vec<String> = words.map(str::to_string).collect();
+                let name = words.join(" ");
+                let (material, object) = (builder.material.clone(), builder.object.clone());
+                builder.finish_into(&mut meshes);
+                builder.material = material;
+                if keyword == "o" {
+                    // Groups don't carry over to the next object
+                    builder.object = (!name.is_empty()).then(|| name.clone());
+                } else {
+                    builder.object = object;
+                    builder.groups = words;
+                }
+                builder.name = (!name.is_empty()).then_some(name);
+            }
+            "usemtl" => {
+                let (name, object, groups) = (builder.name.clone(), builder.object.clone(), builder.groups.clone());
+                builder.finish_into(&mut meshes);
+                (builder.name, builder.object, builder.groups) = (name, object, groups);
+                builder.material = words.next().map(str::to_string);
+            }
+            "mtllib" => libraries.extend(words.map(str::to_string)),
+            _ => {}
+        }
+    }
+    builder.finish_into(&mut meshes);
+    Ok((meshes, libraries))
+}
+
+/// Parses MTL source text into materials by name.
+pub fn parse_mtl(source: &str) -> HashMap<String, ObjMaterial> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+
+    for line in source.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.extend(current.take());
+            current = Some((words.collect::<Vec<_>>().join(" "), ObjMaterial::default()));
+            continue;
+        }
+        let Some((_, material)) = &mut current else {
+            continue;
+        };
+        let values: Vec<f32> = words.filter_map(|word| word.parse().ok()).collect();
+        match (keyword, values.as_slice()) {
+            ("Kd", [r, g, b, ..]) => material.color = Color::linear_rgba(*r, *g, *b, material.color.a),
+            ("d", [d, ..]) => material.color.a = d.clamp(0.0, 1.0),
+            ("Tr", [tr, ..]) => material.color.a = (1.0 - tr).clamp(0.0, 1.0),
+            ("Ns", [ns, ..]) => material.shininess = ns.max(1.0),
+            _ => {}
+        }
+    }
+    materials.extend(current);
+    materials
+}
+
+/// Loads an OBJ file and the MTL files it references into a node with one child per mesh,
+/// named after its object or group, drawn with [`Material::phong`]. Requires a current GL context for the materials.
+///
+/// The object, groups and material names of each mesh are kept as the node's
+/// [metadata](Object3D::metadata) `"object"`, `"groups"` (an array) and `"material"`.
+///
+/// A missing or unreadable MTL file is logged and its materials fall back to white.
+pub fn load_obj(path: impl AsRef<Path>) -> io::Result<Rc<RefCell<Object3D>>> {
+    let path = path.as_ref();
+    let (meshes, libraries) = parse_obj(&std::fs::read_to_string(path)?)?;
+
+    let mut materials = HashMap::new();
+    for library in libraries {
+        let library_path = path.with_file_name(&library);
+        match std::fs::read_to_string(&library_path) {
+            Ok(source) => materials.extend(parse_mtl(&source)),
+            Err(err) => eprintln!("Failed to read material library {}: {err}", library_path.display()),
+        }
+    }
+
+    let root = Object3D::new();
+    for mesh in meshes {
+        let settings = mesh
+            .material
+            .as_ref()
+            .and_then(|name| materials.get(name))
+            .copied()
+            .unwrap_or_default();
+        let mut material = Material::phong(settings.color);
+        material.set_uniform("u_shininess", UniformValue::Float(settings.shininess));
+        if settings.color.a < 1.0 {
+            material.blend = BlendMode::Alpha;
+        }
+
+        let node = Object3D::new();
+        if let Some(name) = &mesh.name {
+            node.borrow_mut().set_name(name);
+        }
+        if let Some(object) = mesh.object {
+            node.borrow_mut().set_metadata("object", object.into());
+        }
+        if !mesh.groups.is_empty() {
+            node.borrow_mut().set_metadata("groups", mesh.groups.into());
+        }
+        if let Some(material) = mesh.material {
+            node.borrow_mut().set_metadata("material", material.into());
+        }
+        node.borrow_mut().set_geometry(mesh.geometry);
+        node.borrow_mut().set_material(material);
+        Object3D::add_child(&root, node);
+    }
+    Ok(root)
+}
+
+/// Collects the faces of the current object/group and material.
+#[derive(Default)]
+struct MeshBuilder {
+    name: Option<String>,
+    object: Option<String>,
+    groups: Vec<String>,
+    material: Option<String>,
+    vertices: Vec<Vertex>,
+    indices: Vec<Index>,
+
+    /// Vertex index of each (position, uv, normal) combination used so far.
+    lookup: HashMap<Corner, Index>,
+
+    /// Whether a corner without a normal was used.
+    missing_normals: bool,
+}
+
+/// Zero-based (position, uv, normal) indices of a face corner.
+type Corner = (usize, Option<usize>, Option<usize>);
+
+impl MeshBuilder {
+    /// The vertex index for `corner`, adding the vertex on first use.
+    fn vertex(&mut self, corner: Corner, positions: &[[f32; 3]], uvs: &[[f32; 2]], normals: &[[f32; 3]]) -> Index {
+        if let Some(&index) = self.lookup.get(&corner) {
+            return index;
+        }
+        let (position, uv, normal) = corner;
+        self.missing_normals |= normal.is_none();
+        let index = self.vertices.len() as Index;
+        self.vertices.push(Vertex {
+            position: positions[position],
+            normal: normal.map_or([0.0, 1.0, 0.0], |n| normals[n]),
+            uv: uv.map_or([0.0, 0.0], |t| uvs[t]),
+        });
+        self.lookup.insert(corner, index);
+        index
+    }
+
+    /// Moves the collected faces into `meshes` (if there are any) and starts over.
+    fn finish_into(&mut self, meshes: &mut Vec<ObjMesh>) {
+        let builder = std::mem::take(self);
+        if builder.indices.is_empty() {
+            return;
+        }
+        let mut geometry = Geometry {
+            vertices: builder.vertices,
+            indices: Indices::from_u32(builder.indices),
+            topology: Topology::Triangles,
+            skin: None,
+        };
+        if builder.missing_normals {
+            geometry.compute_normals();
+        }
+        meshes.push(ObjMesh {
+            name: builder.name,
+            object: builder.object,
+            groups: builder.groups,
+            material: builder.material,
+            geometry,
+        });
+    }
+}
+
+/// Parses a face corner (`v`, `v/vt`, `v//vn` or `v/vt/vn`, 1-based or negative relative
+/// indices) into zero-based indices, or `None` if malformed or out of range.
+fn parse_corner(corner: &str, positions: usize, uvs: usize, normals: usize) -> Option<Corner> {
+    let mut parts = corner.split('/');
+    let position = resolve_index(parts.next()?, positions)?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(uv) => Some(resolve_index(uv, uvs)?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(normal) => Some(resolve_index(normal, normals)?),
+    };
+    Some((position, uv, normal))
+}
+
+/// Resolves a 1-based (or negative, counting back from the end) OBJ index against `count`
+/// elements read so far.
+fn resolve_index(text: &str, count: usize) -> Option<usize> {
+    let index: i64 = text.parse().ok()?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    (0..count as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+/// Parses the first three words as floats.
+fn parse_floats<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<[f32; 3]> {
+    let mut value = || words.next()?.parse().ok();
+    Some([value()?, value()?, value()?])
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,371 @@
+//! Named actions and axes bound to keys, mouse buttons and gamepad inputs.
+//!
+//! Game code asks "is `jump` pressed?" or "how far is `move_forward` pushed?" instead of
+//! testing keys, so players can rebind controls and the same code works with keyboard and
+//! gamepad. An [`InputMap`] holds the bindings and tracks input state:
+//!
+//! - **Actions** are digital: down while any bound [`Input`] is held.
+//!   [`is_action_pressed`](InputMap::is_action_pressed) and
+//!   [`is_action_released`](InputMap::is_action_released) report the frame it changed.
+//! - **Axes** are analog, in `[-1, 1]`: a gamepad stick or trigger, or a pair of digital
+//!   inputs pushing towards either end (W/S, arrow keys).
+//!
+//! The renderer owns one ([`Renderer::input`](crate::engine::renderer::Renderer::input)), feeds
+//! it keyboard and mouse events and clears its per-frame edges after each frame's updates.
+//! Consoles and UIs layered over the game get their own maps in an
+//! [input context](crate::engine::input_context), which can keep events from the game's.
+//! The engine has no gamepad backend yet; forward gamepad state from one with
+//! [`set_gamepad_button`](InputMap::set_gamepad_button) and
+//! [`set_gamepad_axis`](InputMap::set_gamepad_axis).
+//!
+//! Bindings (not state) serialize to JSON for a controls config file, and
+//! [`rebind_next`](InputMap::rebind_next) binds whatever the player presses next, for a
+//! controls menu.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{event::{ButtonState, EngineEvent, Key}, input::{AxisBinding, GamepadAxis, Input, InputMap}};
+//! let mut input = InputMap::new();
+//! input.bind_action("jump", Input::Key(Key::Space));
+//! input.bind_axis("move_forward", AxisBinding::keys(Key::W, Key::S).with_gamepad(GamepadAxis::LeftStickY));
+//!
+//! input.handle_event(&EngineEvent::Key { key: Key::Space, scancode: 57, state: ButtonState::Pressed, repeat: false });
+//! input.handle_event(&EngineEvent::Key { key: Key::W, scancode: 17, state: ButtonState::Pressed, repeat: false });
+//! assert!(input.is_action_pressed("jump"));
+//! assert_eq!(input.axis("move_forward"), 1.0);
+//!
+//! input.end_frame();
+//! assert!(input.is_action_down("jump") && !input.is_action_pressed("jump"));
+//!
+//! let config = input.to_json().unwrap();
+//! assert_eq!(InputMap::from_json(&config).unwrap().action_bindings("jump"), &[Input::Key(Key::Space)]);
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::engine::event::{ButtonState, EngineEvent, Key, MouseButton};
+
+/// A standard gamepad button, by position (the bottom face button is `South`, whether it
+/// is labelled A or ✕).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// A standard gamepad axis. Sticks range over `[-1, 1]` with up and right positive,
+/// triggers over `[0, 1]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// A digital input an action or axis can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Input {
+    /// A key by its meaning in the keyboard layout.
+    Key(Key),
+    /// A key by physical position, whatever the layout (e.g. WASD on AZERTY keyboards).
+    Scancode(u32),
+    MouseButton(MouseButton),
+    GamepadButton(GamepadButton),
+}
+
+/// What drives an axis.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisBinding {
+    /// Inputs pushing the axis to 1.
+    pub positive: Vec<Input>,
+
+    /// Inputs pushing the axis to -1.
+    pub negative: Vec<Input>,
+
+    /// Gamepad axis read when no digital input is held.
+    pub gamepad: Option<GamepadAxis>,
+
+    /// Gamepad values closer to zero than this read as zero, hiding stick drift; the rest
+    /// of the range is rescaled to start at zero.
+    pub dead_zone: f32,
+}
+
+impl Default for AxisBinding {
+    fn default() -> Self {
+        Self { positive: Vec::new(), negative: Vec::new(), gamepad: None, dead_zone: 0.15 }
+    }
+}
+
+impl AxisBinding {
+    /// An axis driven by two keys.
+    pub fn keys(positive: Key, negative: Key) -> Self {
+        Self { positive: vec![Input::Key(positive)], negative: vec![Input::Key(negative)], ..Self::default() }
+    }
+
+    /// Also reads `axis` of the gamepad.
+    pub fn with_gamepad(mut self, axis: GamepadAxis) -> Self {
+        self.gamepad = Some(axis);
+        self
+    }
+}
+
+/// Bindings of actions and axes, and the input state they are evaluated against.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    actions: BTreeMap<String, Vec<Input>>,
+    axes: BTreeMap<String, AxisBinding>,
+
+    /// Inputs held down.
+    #[serde(skip)]
+    held: HashSet<Input>,
+
+    /// Inputs that went down since the last [`end_frame`](Self::end_frame).
+    #[serde(skip)]
+    pressed: HashSet<Input>,
+
+    /// Inputs that went up since the last [`end_frame`](Self::end_frame).
+    #[serde(skip)]
+    released: HashSet<Input>,
+
+    /// Latest gamepad axis values.
+    #[serde(skip)]
+    gamepad_axes: HashMap<GamepadAxis, f32>,
+
+    /// Action the next pressed input gets bound to.
+    #[serde(skip)]
+    rebinding: Option<String>,
+}
+
+impl InputMap {
+    /// No bindings and nothing held.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `input` to the inputs triggering `action`.
+    pub fn bind_action(&mut self, action: &str, input: Input) {
+        let inputs = self.actions.entry(action.to_string()).or_default();
+        if !inputs.contains(&input) {
+            inputs.push(input);
+        }
+    }
+
+    /// Replaces every binding of `action`.
+    pub fn set_action_bindings(&mut self, action: &str, inputs: Vec<Input>) {
+        self.actions.insert(action.to_string(), inputs);
+    }
+
+    /// Removes `input` from `action`; returns whether it was bound.
+    pub fn unbind_action(&mut self, action: &str, input: Input) -> bool {
+        let Some(inputs) = self.actions.get_mut(action) else {
+            return false;
+        };
+        let count = inputs.len();
+        inputs.retain(|other| *other != input);
+        inputs.len() != count
+    }
+
+    /// The inputs bound to `action`.
+    pub fn action_bindings(&self, action: &str) -> &[Input] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The bound actions, in name order.
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.keys().map(String::as_str)
+    }
+
+    /// Sets what drives `axis`, replacing its previous binding.
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.insert(axis.to_string(), binding);
+    }
+
+    /// What drives `axis`, if it is bound.
+    pub fn axis_binding(&self, axis: &str) -> Option<&AxisBinding> {
+        self.axes.get(axis)
+    }
+
+    /// Mutable access to the binding of `axis`, e.g. to rebind one direction.
+    pub fn axis_binding_mut(&mut self, axis: &str) -> Option<&mut AxisBinding> {
+        self.axes.get_mut(axis)
+    }
+
+    /// Copies the bindings of `other`, keeping the current input state.
+    pub fn set_bindings(&mut self, other: &InputMap) {
+        self.actions = other.actions.clone();
+        self.axes = other.axes.clone();
+    }
+
+    /// Binds the next key, mouse button or gamepad button pressed to `action`, replacing
+    /// its bindings, for a controls menu. That press doesn't trigger any action.
+    pub fn rebind_next(&mut self, action: &str) {
+        self.rebinding = Some(action.to_string());
+    }
+
+    /// The action waiting for [`rebind_next`](Self::rebind_next)'s input, if any.
+    pub fn rebinding(&self) -> Option<&str> {
+        self.rebinding.as_deref()
+    }
+
+    /// Stops waiting for a rebinding input.
+    pub fn cancel_rebind(&mut self) {
+        self.rebinding = None;
+    }
+
+    /// Whether any input bound to `action` is held.
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| self.held.contains(input))
+    }
+
+    /// Whether an input bound to `action` went down this frame.
+    pub fn is_action_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| self.pressed.contains(input))
+    }
+
+    /// Whether an input bound to `action` went up this frame and none is held anymore.
+    pub fn is_action_released(&self, action: &str) -> bool {
+        self.action_bindings(action).iter().any(|input| self.released.contains(input)) && !self.is_action_down(action)
+    }
+
+    /// The value of `axis` in `[-1, 1]`: from its digital inputs if any is held, otherwise
+    /// from its gamepad axis. 0 for unbound axes.
+    pub fn axis(&self, axis: &str) -> f32 {
+        let Some(binding) = self.axes.get(axis) else {
+            return 0.0;
+        };
+        let any_held = |inputs: &[Input]| inputs.iter().any(|input| self.held.contains(input));
+        let digital = any_held(&binding.positive) as i32 - any_held(&binding.negative) as i32;
+        if digital != 0 {
+            return digital as f32;
+        }
+        let value = binding.gamepad.and_then(|axis| self.gamepad_axes.get(&axis)).copied().unwrap_or(0.0);
+        let dead_zone = binding.dead_zone.clamp(0.0, 0.99);
+        if value.abs() <= dead_zone {
+            return 0.0;
+        }
+        (value.signum() * (value.abs() - dead_zone) / (1.0 - dead_zone)).clamp(-1.0, 1.0)
+    }
+
+    /// Updates input state from a window event. Losing focus releases everything, since the
+    /// releases happen elsewhere.
+    pub fn handle_event(&mut self, event: &EngineEvent) {
+        match *event {
+            EngineEvent::Key { key, scancode, state, repeat: false } => {
+                let down = state == ButtonState::Pressed;
+                if down && key != Key::Unknown && self.take_rebind(Input::Key(key)) {
+                    return;
+                }
+                // A key counts under both names, so bindings of either kind see it
+                if key != Key::Unknown {
+                    self.set_input(Input::Key(key), down);
+                }
+                self.set_input(Input::Scancode(scancode), down);
+            }
+            EngineEvent::MouseButton { button, state } => {
+                let input = Input::MouseButton(button);
+                if state == ButtonState::Pressed && self.take_rebind(input) {
+                    return;
+                }
+                self.set_input(input, state == ButtonState::Pressed);
+            }
+            EngineEvent::Focused(false) => {
+                self.released.extend(self.held.drain());
+                self.gamepad_axes.clear();
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets a gamepad button's state, from a gamepad backend.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        let input = Input::GamepadButton(button);
+        if pressed && !self.held.contains(&input) && self.take_rebind(input) {
+            return;
+        }
+        self.set_input(input, pressed);
+    }
+
+    /// Sets a gamepad axis's value, from a gamepad backend.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        self.gamepad_axes.insert(axis, value);
+    }
+
+    /// Clears this frame's pressed and released edges. The renderer calls it after each
+    /// frame's updates; call it yourself when driving an input map manually.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// Releases the held inputs matching `filter`, and zeroes the gamepad axes if
+    /// `gamepad_axes`; for an [input context](crate::engine::input_context) taking them over.
+    pub(crate) fn release_where(&mut self, filter: impl Fn(&Input) -> bool, gamepad_axes: bool) {
+        let released: Vec<Input> = self.held.iter().filter(|input| filter(input)).copied().collect();
+        for input in released {
+            self.set_input(input, false);
+        }
+        if gamepad_axes {
+            self.gamepad_axes.clear();
+        }
+    }
+
+    fn set_input(&mut self, input: Input, down: bool) {
+        if down {
+            if self.held.insert(input) {
+                self.pressed.insert(input);
+            }
+        } else if self.held.remove(&input) {
+            self.released.insert(input);
+        }
+    }
+
+    /// Binds `input` to the action waiting for a rebind, if any.
+    fn take_rebind(&mut self, input: Input) -> bool {
+        let Some(action) = self.rebinding.take() else {
+            return false;
+        };
+        self.set_action_bindings(&action, vec![input]);
+        true
+    }
+
+    /// The bindings as pretty-printed JSON.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    /// Parses bindings from JSON.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes the bindings to a config file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_json()?)
+    }
+
+    /// Reads bindings from a config file written by [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,272 @@
+//! Prioritized input contexts: gameplay, UI, console.
+//!
+//! Several systems read input at once, and they shouldn't all react to the same key: typing
+//! "wasd" into an open console must not walk the character, and dragging a UI slider must
+//! not orbit the camera. An [`InputContextStack`] orders them: each [`InputContext`] has its
+//! own [`InputMap`] of bindings, and [captures](Capture) whole kinds of input (keyboard,
+//! mouse, gamepad). Events go to the top context first and on down the stack until a
+//! context that captures them, which consumes them; the contexts below never see them.
+//!
+//! The bottom (base) context is the game's and captures nothing. Opening the console pushes
+//! a context capturing the keyboard, closing it pops it again. Pushing a capturing context
+//! releases the inputs it captures in every context below, so a key held when the console
+//! opened doesn't stay down until it closes.
+//!
+//! The renderer owns a stack ([`Renderer::input_contexts`](crate::engine::renderer::Renderer::input_contexts)),
+//! whose base map is [`Renderer::input`](crate::engine::renderer::Renderer::input). Its camera
+//! controller and selection tools belong to the base context, and
+//! [event handlers](crate::engine::renderer::Renderer::on_event) ask
+//! [`reaches`](InputContextStack::reaches) whether an event is theirs.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::event::{ButtonState, EngineEvent, Key};
+//! # use rustge::engine::input::{AxisBinding, Input, InputMap};
+//! # use rustge::engine::input_context::{Capture, InputContext, InputContextStack};
+//! let mut game = InputMap::new();
+//! game.bind_axis("move_forward", AxisBinding::keys(Key::W, Key::S));
+//! let mut contexts = InputContextStack::new(game);
+//! let w = EngineEvent::Key { key: Key::W, scancode: 17, state: ButtonState::Pressed, repeat: false };
+//!
+//! // The console swallows every key but its toggle
+//! let mut console = InputContext::new("console", Capture::KEYBOARD).pass_through(Input::Key(Key::Grave));
+//! console.input.bind_action("submit", Input::Key(Key::Enter));
+//! contexts.push(console);
+//! contexts.handle_event(&w);
+//! assert_eq!(contexts.base().axis("move_forward"), 0.0);
+//! assert!(!contexts.reaches("game", &w));
+//!
+//! contexts.pop();
+//! contexts.handle_event(&w);
+//! assert_eq!(contexts.base().axis("move_forward"), 1.0);
+//! ```
+
+use crate::engine::event::EngineEvent;
+use crate::engine::input::{GamepadAxis, GamepadButton, Input, InputMap};
+
+/// Name of the base context of an [`InputContextStack`].
+pub const BASE_CONTEXT: &str = "game";
+
+/// The kinds of input a context consumes, keeping them from the contexts below it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capture {
+    /// Key and text events.
+    pub keyboard: bool,
+
+    /// Mouse buttons, cursor movement and the wheel.
+    pub mouse: bool,
+
+    /// Gamepad buttons and axes.
+    pub gamepad: bool,
+}
+
+impl Capture {
+    /// Consumes nothing: the context reads input alongside the ones below.
+    pub const NONE: Capture = Capture { keyboard: false, mouse: false, gamepad: false };
+
+    /// Consumes everything, e.g. a modal menu.
+    pub const ALL: Capture = Capture { keyboard: true, mouse: true, gamepad: true };
+
+    /// Consumes the keyboard, e.g. a console or text field.
+    pub const KEYBOARD: Capture = Capture { keyboard: true, mouse: false, gamepad: false };
+
+    /// Consumes the mouse, e.g. a UI under the cursor.
+    pub const MOUSE: Capture = Capture { keyboard: false, mouse: true, gamepad: false };
+
+    /// Whether this captures `input`.
+    fn covers(&self, input: &Input) -> bool {
+        match input {
+            Input::Key(_) | Input::Scancode(_) => self.keyboard,
+            Input::MouseButton(_) => self.mouse,
+            Input::GamepadButton(_) => self.gamepad,
+        }
+    }
+}
+
+/// One layer of an [`InputContextStack`]: bindings, and what it keeps from the layers below.
+#[derive(Clone, Debug)]
+pub struct InputContext {
+    /// Name to find, and remove, the context by.
+    pub name: String,
+
+    /// The context's bindings and input state, fed with the events reaching it.
+    pub input: InputMap,
+
+    /// What the context consumes.
+    pub capture: Capture,
+
+    /// Inputs let through to the contexts below even though they are captured, e.g. the
+    /// key that closes the console.
+    pub passthrough: Vec<Input>,
+}
+
+impl InputContext {
+    /// A context named `name` with no bindings, consuming `capture`.
+    pub fn new(name: &str, capture: Capture) -> Self {
+        Self { name: name.to_string(), input: InputMap::new(), capture, passthrough: Vec::new() }
+    }
+
+    /// Also lets `input` through to the contexts below.
+    pub fn pass_through(mut self, input: Input) -> Self {
+        self.passthrough.push(input);
+        self
+    }
+
+    /// Whether the context consumes `event`.
+    fn consumes(&self, event: &EngineEvent) -> bool {
+        match *event {
+            EngineEvent::Key { key, scancode, .. } => {
+                let passes = self.passthrough.iter().any(|input| *input == Input::Key(key) || *input == Input::Scancode(scancode));
+                self.capture.keyboard && !passes
+            }
+            EngineEvent::Text(_) => self.capture.keyboard,
+            EngineEvent::MouseButton { button, .. } => {
+                self.capture.mouse && !self.passthrough.contains(&Input::MouseButton(button))
+            }
+            EngineEvent::CursorMoved { .. } | EngineEvent::MouseWheel { .. } => self.capture.mouse,
+            _ => false,
+        }
+    }
+
+    /// Whether the context consumes `input` from a gamepad.
+    fn consumes_input(&self, input: &Input) -> bool {
+        self.capture.covers(input) && !self.passthrough.contains(input)
+    }
+}
+
+/// Input contexts in priority order, the base context at the bottom; see the
+/// [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct InputContextStack {
+    /// Bottom to top; the base context is always first.
+    contexts: Vec<InputContext>,
+}
+
+impl Default for InputContextStack {
+    fn default() -> Self {
+        Self::new(InputMap::new())
+    }
+}
+
+impl InputContextStack {
+    /// A stack holding only the base context, named [`BASE_CONTEXT`], with the bindings of
+    /// `base`.
+    pub fn new(base: InputMap) -> Self {
+        Self { contexts: vec![InputContext { input: base, ..InputContext::new(BASE_CONTEXT, Capture::NONE) }] }
+    }
+
+    /// Pushes `context` on top, replacing any context of the same name, and releases the
+    /// inputs it captures in the contexts below.
+    pub fn push(&mut self, context: InputContext) {
+        self.remove(&context.name);
+        for below in &mut self.contexts {
+            below.input.release_where(|input| context.consumes_input(input), context.capture.gamepad);
+        }
+        self.contexts.push(context);
+    }
+
+    /// Removes and returns the top context; the base context is never popped.
+    pub fn pop(&mut self) -> Option<InputContext> {
+        (self.contexts.len() > 1).then(|| self.contexts.pop()).flatten()
+    }
+
+    /// Removes and returns the context named `name`, wherever it is in the stack; the base
+    /// context is never removed.
+    pub fn remove(&mut self, name: &str) -> Option<InputContext> {
+        let index = self.contexts.iter().skip(1).position(|context| context.name == name)? + 1;
+        Some(self.contexts.remove(index))
+    }
+
+    /// Whether a context named `name` is in the stack.
+    pub fn contains(&self, name: &str) -> bool {
+        self.contexts.iter().any(|context| context.name == name)
+    }
+
+    /// The context named `name`, if it is in the stack.
+    pub fn context(&self, name: &str) -> Option<&InputContext> {
+        self.contexts.iter().find(|context| context.name == name)
+    }
+
+    /// Mutable access to the context named `name`, e.g. to change its bindings.
+    pub fn context_mut(&mut self, name: &str) -> Option<&mut InputContext> {
+        self.contexts.iter_mut().find(|context| context.name == name)
+    }
+
+    /// The topmost context, which sees every event first.
+    pub fn top(&self) -> &InputContext {
+        self.contexts.last().unwrap_or(&self.contexts[0])
+    }
+
+    /// The contexts from the top down.
+    pub fn iter(&self) -> impl Iterator<Item = &InputContext> {
+        self.contexts.iter().rev()
+    }
+
+    /// The base context's input map, the game's bindings.
+    pub fn base(&self) -> &InputMap {
+        &self.contexts[0].input
+    }
+
+    /// Mutable access to the base context's input map.
+    pub fn base_mut(&mut self) -> &mut InputMap {
+        &mut self.contexts[0].input
+    }
+
+    /// Feeds `event` to each context from the top down, stopping after the first that
+    /// consumes it. Returns whether it reached the base context.
+    pub fn handle_event(&mut self, event: &EngineEvent) -> bool {
+        for context in self.contexts.iter_mut().rev() {
+            context.input.handle_event(event);
+            if context.consumes(event) {
+                return context.name == BASE_CONTEXT;
+            }
+        }
+        true
+    }
+
+    /// Whether `event` reaches the context named `name`: it is in the stack and no context
+    /// above it consumes the event. Event handlers belonging to a context check this before
+    /// reacting.
+    pub fn reaches(&self, name: &str, event: &EngineEvent) -> bool {
+        for context in self.contexts.iter().rev() {
+            if context.name == name {
+                return true;
+            }
+            if context.consumes(event) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// Sets a gamepad button's state in the contexts down to the first one capturing it,
+    /// from a gamepad backend.
+    pub fn set_gamepad_button(&mut self, button: GamepadButton, pressed: bool) {
+        let input = Input::GamepadButton(button);
+        for context in self.contexts.iter_mut().rev() {
+            context.input.set_gamepad_button(button, pressed);
+            if context.consumes_input(&input) {
+                break;
+            }
+        }
+    }
+
+    /// Sets a gamepad axis's value in the contexts down to the first one capturing the
+    /// gamepad, from a gamepad backend.
+    pub fn set_gamepad_axis(&mut self, axis: GamepadAxis, value: f32) {
+        for context in self.contexts.iter_mut().rev() {
+            context.input.set_gamepad_axis(axis, value);
+            if context.capture.gamepad {
+                break;
+            }
+        }
+    }
+
+    /// Clears this frame's pressed and released edges in every context, see
+    /// [`InputMap::end_frame`].
+    pub fn end_frame(&mut self) {
+        for context in &mut self.contexts {
+            context.input.end_frame();
+        }
+    }
+}
@@ -0,0 +1,378 @@
+//! Engine-wide thread pool and job system.
+//!
+//! Work that splits across cores (culling large scenes, decoding images, generating terrain)
+//! runs as jobs on one shared [`JobPool`] instead of each subsystem spawning its own threads,
+//! so the engine never oversubscribes the machine. Game code uses the same pool through the
+//! free functions of this module, which run on [`JobPool::global`].
+//!
+//! Each worker thread has its own queue: jobs spawned from a worker go to its queue and are
+//! taken newest-first, keeping nested work hot in cache, while idle workers steal the oldest
+//! jobs from the others. Jobs spawned from other threads go to a shared queue. A thread
+//! waiting on a job ([`JobHandle::join`], [`parallel_for`]) runs queued jobs meanwhile
+//! instead of blocking, so waiting inside a job cannot starve the pool.
+//!
+//! Jobs must be `Send`: scene nodes (`Rc<RefCell<Object3D>>`) and GL calls stay on the main
+//! thread, so the usual pattern is to gather plain data from the scene, process it in
+//! parallel and apply the results back on the main thread.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::jobs;
+//! let heights: Vec<f32> = (0..10_000).map(|i| i as f32).collect();
+//!
+//! // Runs on the pool while this thread carries on
+//! let total = jobs::spawn(|| (1..=100u64).sum::<u64>());
+//!
+//! // Splits the slice across the workers, results in input order
+//! let doubled = jobs::parallel_map(&heights, |height| height * 2.0);
+//! assert_eq!(doubled[5000], 10_000.0);
+//!
+//! assert_eq!(total.join(), 5050);
+//! ```
+
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+type Panic = Box<dyn Any + Send + 'static>;
+
+/// How long a waiting thread sleeps before looking for queued jobs to help with again.
+const HELP_INTERVAL: Duration = Duration::from_millis(1);
+
+thread_local! {
+    /// The pool (by address of its queues) and index of the worker running on this thread.
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Locks `mutex`, ignoring poisoning: jobs run under `catch_unwind`, so a poisoned lock
+/// only means a panic was already forwarded to whoever waits for the job.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// The queues shared by a pool's workers and the threads spawning jobs.
+struct Queues {
+    /// Jobs spawned from threads outside the pool.
+    injector: Mutex<VecDeque<Job>>,
+
+    /// One queue per worker, for the jobs it spawns.
+    locals: Vec<Mutex<VecDeque<Job>>>,
+
+    /// Number of jobs in all queues, guarding the sleep of idle workers.
+    queued: Mutex<usize>,
+    available: Condvar,
+    shutdown: AtomicBool,
+}
+
+impl Queues {
+    /// The index of the calling thread if it is one of this pool's workers.
+    fn current_worker(self: &Arc<Self>) -> Option<usize> {
+        let id = Arc::as_ptr(self) as usize;
+        WORKER.with(Cell::get).filter(|&(pool, _)| pool == id).map(|(_, index)| index)
+    }
+
+    fn push(self: &Arc<Self>, job: Job) {
+        // Counted before it can be taken, so the count never drops below zero
+        *lock(&self.queued) += 1;
+        match self.current_worker() {
+            Some(index) => lock(&self.locals[index]).push_back(job),
+            None => lock(&self.injector).push_back(job),
+        }
+        self.available.notify_one();
+    }
+
+    /// Takes a job: the newest from the worker's own queue, else the oldest shared one,
+    /// else the oldest from another worker.
+    fn take(&self, worker: Option<usize>) -> Option<Job> {
+        let own = worker.and_then(|index| lock(&self.locals[index]).pop_back());
+        let job = own.or_else(|| lock(&self.injector).pop_front()).or_else(|| {
+            let start = worker.map_or(0, |index| index + 1);
+            (0..self.locals.len())
+                .map(|offset| (start + offset) % self.locals.len())
+                .filter(|&victim| Some(victim) != worker)
+                .find_map(|victim| lock(&self.locals[victim]).pop_front())
+        })?;
+        *lock(&self.queued) -= 1;
+        Some(job)
+    }
+
+    /// Runs queued jobs on the calling thread until `done` holds, sleeping on `wait` when
+    /// there is nothing to help with.
+    fn help_until(self: &Arc<Self>, mut done: impl FnMut() -> bool, mut wait: impl FnMut()) {
+        let worker = self.current_worker();
+        while !done() {
+            match self.take(worker) {
+                Some(job) => job(),
+                None => wait(),
+            }
+        }
+    }
+}
+
+fn run_worker(queues: Arc<Queues>, index: usize) {
+    WORKER.with(|worker| worker.set(Some((Arc::as_ptr(&queues) as usize, index))));
+    loop {
+        if let Some(job) = queues.take(Some(index)) {
+            job();
+            continue;
+        }
+        let queued = lock(&queues.queued);
+        // Queued jobs are drained before shutting down, so no handle waits forever
+        if *queued == 0 {
+            if queues.shutdown.load(Ordering::Acquire) {
+                return;
+            }
+            drop(queues.available.wait(queued));
+        }
+    }
+}
+
+/// A pool of worker threads running jobs.
+///
+/// Most code uses the engine's shared pool, [`JobPool::global`], through the free
+/// functions of this module; a separate pool isolates work that shouldn't compete with the
+/// engine's, e.g. long-running background tasks.
+pub struct JobPool {
+    queues: Arc<Queues>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for JobPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobPool")
+            .field("threads", &self.workers.len())
+            .field("queued", &*lock(&self.queues.queued))
+            .finish()
+    }
+}
+
+impl JobPool {
+    /// A pool of `threads` workers. With 0, jobs run on the threads waiting for them.
+    pub fn new(threads: usize) -> Self {
+        let queues = Arc::new(Queues {
+            injector: Mutex::new(VecDeque::new()),
+            locals: (0..threads).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: Mutex::new(0),
+            available: Condvar::new(),
+            shutdown: AtomicBool::new(false),
+        });
+        let workers = (0..threads)
+            .map(|index| {
+                let queues = queues.clone();
+                thread::Builder::new()
+                    .name(format!("job worker {index}"))
+                    .spawn(move || run_worker(queues, index))
+                    .expect("failed to spawn job worker thread")
+            })
+            .collect();
+        Self { queues, workers }
+    }
+
+    /// The engine's shared pool, created on first use with one worker per core besides the
+    /// main thread (at least one).
+    pub fn global() -> &'static JobPool {
+        static GLOBAL: OnceLock<JobPool> = OnceLock::new();
+        GLOBAL.get_or_init(|| {
+            let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+            JobPool::new(cores.saturating_sub(1).max(1))
+        })
+    }
+
+    /// Number of worker threads.
+    pub fn thread_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues `job` and returns a handle to its result.
+    pub fn spawn<T, F>(&self, job: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let slot = Arc::new(Slot { result: Mutex::new(None), ready: Condvar::new() });
+        let filled = slot.clone();
+        self.queues.push(Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+            *lock(&filled.result) = Some(result);
+            filled.ready.notify_all();
+        }));
+        JobHandle { slot, queues: self.queues.clone() }
+    }
+
+    /// Calls `body` with consecutive sub-ranges of `0..len` covering it, in parallel, and
+    /// returns once all calls have. Ranges hold at least `min_chunk` indices (except the
+    /// last), so per-call overhead stays small against the work in each.
+    ///
+    /// `body` may borrow from the caller, unlike [`spawn`](Self::spawn)ed jobs.
+    ///
+    /// # Panics
+    /// Re-raises a panic of `body` once every call has finished.
+    pub fn parallel_for(&self, len: usize, min_chunk: usize, body: impl Fn(Range<usize>) + Sync) {
+        let chunks = (self.thread_count() + 1) * 4;
+        let chunk = len.div_ceil(chunks).max(min_chunk).max(1);
+        let tasks = len.div_ceil(chunk);
+        self.run_scoped(tasks, &|task| body(task * chunk..((task + 1) * chunk).min(len)));
+    }
+
+    /// Applies `f` to every item in parallel, returning the results in the order of `items`.
+    ///
+    /// # Panics
+    /// Re-raises a panic of `f` once every item has been processed.
+    pub fn parallel_map<T: Sync, R: Send>(&self, items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+        let chunks = Mutex::new(Vec::new());
+        self.parallel_for(items.len(), 1, |range| {
+            let results: Vec<R> = items[range.clone()].iter().map(&f).collect();
+            lock(&chunks).push((range.start, results));
+        });
+        let mut chunks = chunks.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+        chunks.sort_unstable_by_key(|(start, _)| *start);
+        chunks.into_iter().flat_map(|(_, results)| results).collect()
+    }
+
+    /// Runs `task(0)` to `task(tasks - 1)` across the pool and the calling thread, returning
+    /// once all have finished.
+    fn run_scoped(&self, tasks: usize, task: &(dyn Fn(usize) + Sync)) {
+        if tasks <= 1 || self.workers.is_empty() {
+            (0..tasks).for_each(task);
+            return;
+        }
+
+        // SAFETY: the jobs below are the only users of the extended borrow, and this function
+        // doesn't return (or unwind) until the latch has counted every one of them finished
+        let task: &'static (dyn Fn(usize) + Sync) = unsafe { std::mem::transmute(task) };
+        let latch = Arc::new(Latch { state: Mutex::new((tasks - 1, None)), finished: Condvar::new() });
+        for index in 1..tasks {
+            let latch = latch.clone();
+            self.queues.push(Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| task(index)));
+                latch.count_down(result.err());
+            }));
+        }
+
+        let first = panic::catch_unwind(AssertUnwindSafe(|| task(0)));
+        self.queues.help_until(
+            || lock(&latch.state).0 == 0,
+            || {
+                let state = lock(&latch.state);
+                if state.0 > 0 {
+                    drop(latch.finished.wait_timeout(state, HELP_INTERVAL));
+                }
+            },
+        );
+
+        let panicked = first.err().or_else(|| lock(&latch.state).1.take());
+        if let Some(payload) = panicked {
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl Drop for JobPool {
+    /// Lets the workers finish the queued jobs, then joins them.
+    fn drop(&mut self) {
+        {
+            let _queued = lock(&self.queues.queued);
+            self.queues.shutdown.store(true, Ordering::Release);
+            self.queues.available.notify_all();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Where a spawned job leaves its result.
+struct Slot<T> {
+    result: Mutex<Option<thread::Result<T>>>,
+    ready: Condvar,
+}
+
+/// Counts down the outstanding tasks of [`JobPool::run_scoped`], keeping the first panic.
+struct Latch {
+    state: Mutex<(usize, Option<Panic>)>,
+    finished: Condvar,
+}
+
+impl Latch {
+    fn count_down(&self, panicked: Option<Panic>) {
+        let mut state = lock(&self.state);
+        state.0 -= 1;
+        if state.1.is_none() {
+            state.1 = panicked;
+        }
+        self.finished.notify_all();
+    }
+}
+
+/// The pending result of a job started with [`JobPool::spawn`] or [`spawn`].
+///
+/// Dropping the handle detaches the job: it still runs, and its result (or panic) is
+/// discarded.
+pub struct JobHandle<T> {
+    slot: Arc<Slot<T>>,
+    queues: Arc<Queues>,
+}
+
+impl<T> std::fmt::Debug for JobHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JobHandle").field("finished", &self.is_finished()).finish()
+    }
+}
+
+impl<T> JobHandle<T> {
+    /// Whether the job has finished, so [`join`](Self::join) returns immediately.
+    pub fn is_finished(&self) -> bool {
+        lock(&self.slot.result).is_some()
+    }
+
+    /// Waits for the job and returns its result, running other queued jobs meanwhile.
+    ///
+    /// # Panics
+    /// Re-raises the job's panic.
+    pub fn join(self) -> T {
+        let mut result = None;
+        self.queues.help_until(
+            || {
+                result = lock(&self.slot.result).take();
+                result.is_some()
+            },
+            || {
+                let pending = lock(&self.slot.result);
+                if pending.is_none() {
+                    drop(self.slot.ready.wait_timeout(pending, HELP_INTERVAL));
+                }
+            },
+        );
+        match result {
+            Some(Ok(value)) => value,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => unreachable!("help_until returns once the result is taken"),
+        }
+    }
+}
+
+/// Runs `job` on the [global pool](JobPool::global). See [`JobPool::spawn`].
+pub fn spawn<T, F>(job: F) -> JobHandle<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    JobPool::global().spawn(job)
+}
+
+/// Splits `0..len` across the [global pool](JobPool::global). See [`JobPool::parallel_for`].
+pub fn parallel_for(len: usize, min_chunk: usize, body: impl Fn(Range<usize>) + Sync) {
+    JobPool::global().parallel_for(len, min_chunk, body);
+}
+
+/// Maps `items` on the [global pool](JobPool::global). See [`JobPool::parallel_map`].
+pub fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    JobPool::global().parallel_map(items, f)
+}
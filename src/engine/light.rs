@@ -0,0 +1,734 @@
+//! Scene lights.
+//!
+//! A [`Light`] is attached to an [`Object3D`](crate::engine::object3d::Object3D) with
+//! [`set_light`](crate::engine::object3d::Object3D::set_light) and takes its position and
+//! direction from the node's world transform: lights shine along the node's local -Z axis,
+//! the same convention the camera uses.
+//!
+//! Each frame the renderer gathers every light in the scene into a [`LightSet`] and uploads it
+//! to any shader that declares the lighting uniform block, such as the built-in Blinn-Phong
+//! shader used by [`Material::phong`](crate::engine::material::Material::phong).
+//!
+//! ```no_run
+//! # use rustge::engine::{light::Light, math::color::Color, object3d::Object3D};
+//! let lamp = Object3D::new();
+//! lamp.borrow_mut().set_position([0.0, 3.0, 0.0]);
+//! lamp.borrow_mut().set_light(Some(Light::point(Color::srgb(1.0, 0.9, 0.7), 2.0, 10.0)));
+//! ```
+//!
+//! Spot and directional lights can project a [`LightCookie`] texture that tints and masks
+//! their light, for window frames, flashlight patterns or caustics:
+//!
+//! ```no_run
+//! # use std::rc::Rc;
+//! # use rustge::engine::{light::{Light, LightCookie}, math::color::Color, texture::Texture};
+//! # let window_frame = Rc::new(Texture::from_rgba8(1, 1, &[255; 4], "window frame"));
+//! let spot = Light::spot(Color::WHITE, 5.0, 20.0, 20.0, 30.0).with_cookie(LightCookie::new(window_frame));
+//! ```
+//!
+//! Scenes with dozens of lights stay within the [`MAX_LIGHTS`] a shader can take through
+//! light level of detail: [`LightSet::apply_lod`] keeps the lights nearest the camera and
+//! merges the rest into the ambient term, and a [`LightLod`] reduces point and spot lights
+//! with distance, dropping their shadows first, then their specular highlights, and finally
+//! merging them into ambient too. A light's [`LightLodOverride`] exempts it from any of
+//! these steps.
+
+use std::rc::Rc;
+use std::sync::OnceLock;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::{look_at_matrix, matrix_mul_4x4, perspective_matrix, scale_matrix, IDENTITY_MATRIX};
+use crate::engine::math::vec::{add, distance, normalize_or};
+use crate::engine::shader::GLShaderProgram;
+use crate::engine::shadow::{ShadowMaps, MAX_SHADOW_TILES};
+use crate::engine::texture::{Cubemap, Texture};
+use crate::engine::uniform_buffer::{upload_lights_block, Std140Writer, LIGHTS_BLOCK};
+
+/// Maximum number of lights uploaded to a shader. Lights beyond this are ignored, or merged
+/// into ambient by [`LightSet::apply_lod`].
+pub const MAX_LIGHTS: usize = 8;
+
+/// Maximum number of lights with a cookie per shader. Cookies beyond this are ignored, so
+/// those lights shine unmasked.
+pub const MAX_COOKIES: usize = 4;
+
+/// First texture unit cookies are bound to, above the units materials use.
+const COOKIE_TEXTURE_UNIT: u32 = 8;
+
+/// Texture unit of the shadow atlas, above the cookies.
+const SHADOW_TEXTURE_UNIT: u32 = COOKIE_TEXTURE_UNIT + MAX_COOKIES as u32;
+
+/// Texture unit of the environment map, above the shadow atlas.
+const ENVIRONMENT_TEXTURE_UNIT: u32 = SHADOW_TEXTURE_UNIT + 1;
+
+/// Distance falloff of a point or spot light: `1 / (constant + linear * d + quadratic * d²)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    /// Falloff that fades to near zero (~1%) at the given distance.
+    pub fn for_range(range: f32) -> Self {
+        let range = range.max(f32::EPSILON);
+        Self { constant: 1.0, linear: 4.5 / range, quadratic: 75.0 / (range * range) }
+    }
+
+    /// No falloff with distance.
+    pub fn none() -> Self {
+        Self { constant: 1.0, linear: 0.0, quadratic: 0.0 }
+    }
+
+    /// The light's intensity factor at distance `d`.
+    pub fn factor(&self, d: f32) -> f32 {
+        1.0 / (self.constant + self.linear * d + self.quadratic * d * d).max(f32::EPSILON)
+    }
+
+    /// The distance at which the light has faded to 1% of its intensity, or `None` if it
+    /// never does.
+    ///
+    /// ```
+    /// # use rustge::engine::light::Attenuation;
+    /// let range = Attenuation::for_range(10.0).range().unwrap();
+    /// assert!(range > 10.0 && range < 12.0);
+    /// assert_eq!(Attenuation::none().range(), None);
+    /// ```
+    pub fn range(&self) -> Option<f32> {
+        // Solve quadratic * d² + linear * d + constant = 100
+        let c = self.constant - 100.0;
+        if self.quadratic > 0.0 {
+            let discriminant = self.linear * self.linear - 4.0 * self.quadratic * c;
+            Some(((-self.linear + discriminant.max(0.0).sqrt()) / (2.0 * self.quadratic)).max(0.0))
+        } else if self.linear > 0.0 {
+            Some((-c / self.linear).max(0.0))
+        } else {
+            None
+        }
+    }
+}
+
+/// The shape of a light's emission.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Uniform light from every direction, with no position or shading falloff.
+    Ambient,
+    /// Parallel rays along the node's -Z axis, like the sun. Position is ignored.
+    Directional,
+    /// Light radiating from the node's position in all directions.
+    Point { attenuation: Attenuation },
+    /// A cone along the node's -Z axis. Angles are half-angles in radians: full intensity
+    /// inside `inner_angle`, fading to zero at `outer_angle`.
+    Spot { attenuation: Attenuation, inner_angle: f32, outer_angle: f32 },
+}
+
+/// A texture projected by a spot or directional light, multiplying its color.
+///
+/// The texture's up points along the light node's local +Y axis, so rotating the node
+/// around its -Z axis rotates the pattern. Point and ambient lights ignore cookies.
+#[derive(Clone, Debug)]
+pub struct LightCookie {
+    /// The projected pattern, sRGB-encoded. Black blocks the light.
+    pub texture: Rc<Texture>,
+
+    /// World-space width of one repetition of the texture for directional lights, which
+    /// tile it across the scene. Spot lights stretch the texture once over their outer cone.
+    pub tile_size: f32,
+}
+
+impl LightCookie {
+    /// A cookie tiling every 10 units under a directional light.
+    pub fn new(texture: Rc<Texture>) -> Self {
+        Self { texture, tile_size: 10.0 }
+    }
+
+    /// A cookie tiling every `tile_size` units under a directional light.
+    pub fn tiled(texture: Rc<Texture>, tile_size: f32) -> Self {
+        Self { texture, tile_size }
+    }
+}
+
+impl PartialEq for LightCookie {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.texture, &other.texture) && self.tile_size == other.tile_size
+    }
+}
+
+/// How much of a light's effect is rendered, from cheapest to most expensive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LightDetail {
+    /// Merged into the ambient term: no direction, no shading.
+    Ambient,
+    /// Diffuse lighting only.
+    Diffuse,
+    /// Diffuse and specular lighting, without shadows.
+    Specular,
+    /// Everything, including shadows.
+    #[default]
+    Full,
+}
+
+/// Flags exempting a light from steps of its level-of-detail reduction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LightLodOverride {
+    /// Keep shadows at any distance.
+    pub keep_shadows: bool,
+
+    /// Keep specular highlights at any distance.
+    pub keep_specular: bool,
+
+    /// Never merge the light into ambient, neither for distance nor to stay within
+    /// [`MAX_LIGHTS`]: such lights are kept before all others.
+    pub keep_direct: bool,
+}
+
+impl LightLodOverride {
+    /// Exempt from every step: the light always renders in full detail.
+    pub const NEVER_REDUCE: Self = Self { keep_shadows: true, keep_specular: true, keep_direct: true };
+}
+
+/// Camera distances beyond which point and spot lights are rendered in less detail.
+///
+/// ```
+/// # use rustge::engine::{light::{Light, LightDetail, LightLod}, math::color::Color};
+/// let lod = LightLod::default();
+/// let lamp = Light::point(Color::WHITE, 1.0, 10.0);
+/// assert_eq!(lod.detail(&lamp, 5.0), LightDetail::Full);
+/// assert_eq!(lod.detail(&lamp, 30.0), LightDetail::Specular);
+/// assert_eq!(lod.detail(&lamp, 500.0), LightDetail::Ambient);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightLod {
+    /// Beyond this distance lights cast no shadows.
+    pub shadow_distance: f32,
+
+    /// Beyond this distance lights lose their specular highlights.
+    pub specular_distance: f32,
+
+    /// Beyond this distance lights are merged into the ambient term.
+    pub ambient_distance: f32,
+}
+
+impl LightLod {
+    /// The detail `light` is rendered in at `distance` from the camera, after its
+    /// [`lod_override`](Light::lod_override). Ambient and directional lights are always
+    /// rendered in full.
+    pub fn detail(&self, light: &Light, distance: f32) -> LightDetail {
+        if matches!(light.kind, LightKind::Ambient | LightKind::Directional) {
+            return LightDetail::Full;
+        }
+        let flags = light.lod_override;
+        if distance > self.ambient_distance && !flags.keep_direct {
+            LightDetail::Ambient
+        } else if distance > self.specular_distance && !flags.keep_specular {
+            LightDetail::Diffuse
+        } else if distance > self.shadow_distance && !flags.keep_shadows {
+            LightDetail::Specular
+        } else {
+            LightDetail::Full
+        }
+    }
+}
+
+impl Default for LightLod {
+    /// No shadows beyond 20 units, no specular beyond 40, merged into ambient beyond 80.
+    fn default() -> Self {
+        Self { shadow_distance: 20.0, specular_distance: 40.0, ambient_distance: 80.0 }
+    }
+}
+
+/// A light source attached to a scene node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Light {
+    /// Emission shape and falloff.
+    pub kind: LightKind,
+
+    /// Light color.
+    pub color: Color,
+
+    /// Multiplier applied to `color`.
+    pub intensity: f32,
+
+    /// Optional projected texture, for spot and directional lights.
+    pub cookie: Option<LightCookie>,
+
+    /// Steps of distance-based detail reduction this light is exempt from.
+    pub lod_override: LightLodOverride,
+}
+
+impl Light {
+    /// An ambient light.
+    pub fn ambient(color: Color, intensity: f32) -> Self {
+        Self { kind: LightKind::Ambient, color, intensity, cookie: None, lod_override: LightLodOverride::default() }
+    }
+
+    /// A directional light shining along the node's -Z axis.
+    pub fn directional(color: Color, intensity: f32) -> Self {
+        Self { kind: LightKind::Directional, color, intensity, cookie: None, lod_override: LightLodOverride::default() }
+    }
+
+    /// Direct sunlight as a directional light along the node's -Z axis, with `illuminance`
+    /// in lux (around 100 000 at noon on a clear day, 10 000 under overcast skies).
+    ///
+    /// Physical units only look right through a camera with
+    /// [`PhysicalCamera`](crate::engine::camera::PhysicalCamera) exposure.
+    pub fn sun(illuminance: f32) -> Self {
+        Self::directional(Color::linear_rgb(1.0, 0.96, 0.9), illuminance)
+    }
+
+    /// A point light fading out over `range`.
+    pub fn point(color: Color, intensity: f32, range: f32) -> Self {
+        Self { kind: LightKind::Point { attenuation: Attenuation::for_range(range) }, color, intensity, cookie: None, lod_override: LightLodOverride::default() }
+    }
+
+    /// A spot light along the node's -Z axis fading out over `range`, with cone half-angles
+    /// given in degrees.
+    pub fn spot(color: Color, intensity: f32, range: f32, inner_degrees: f32, outer_degrees: f32) -> Self {
+        let outer = outer_degrees.to_radians();
+        Self {
+            kind: LightKind::Spot {
+                attenuation: Attenuation::for_range(range),
+                // Keep the cone edges distinct so the falloff is well-defined
+                inner_angle: inner_degrees.to_radians().min(outer * 0.999),
+                outer_angle: outer,
+            },
+            color,
+            intensity,
+            cookie: None,
+            lod_override: LightLodOverride::default(),
+        }
+    }
+
+    /// The light with `cookie` projected through it.
+    pub fn with_cookie(self, cookie: LightCookie) -> Self {
+        Self { cookie: Some(cookie), ..self }
+    }
+
+    /// The light exempt from the detail reductions flagged in `lod_override`.
+    pub fn with_lod_override(self, lod_override: LightLodOverride) -> Self {
+        Self { lod_override, ..self }
+    }
+}
+
+/// A light resolved to world space, ready to upload.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorldLight {
+    pub light: Light,
+    /// World-space position of the light's node.
+    pub position: [f32; 3],
+    /// World-space unit direction the light shines in (the node's -Z axis).
+    pub direction: [f32; 3],
+    /// Maps world space to the cookie's clip space, whose x and y in -1..1 cover the
+    /// texture. Identity for lights without a cookie.
+    pub cookie_matrix: [f32; 16],
+    /// How much of the light is rendered, as decided by [`LightSet::apply_lod`].
+    pub detail: LightDetail,
+    /// Index of the light's first tile in [`LightSet::shadows`], if it is shadowed this frame
+    /// (see [`ShadowAtlas`](crate::engine::shadow::ShadowAtlas)).
+    pub shadow: Option<usize>,
+}
+
+impl WorldLight {
+    /// Whether the light is close enough to the camera to cast shadows.
+    pub fn casts_shadows(&self) -> bool {
+        self.detail == LightDetail::Full
+    }
+}
+
+/// All lights affecting a frame, gathered from the scene graph.
+#[derive(Clone, Debug, Default)]
+pub struct LightSet {
+    /// Sum of all ambient lights (linear color times intensity).
+    pub ambient: [f32; 3],
+
+    /// Directional, point and spot lights. Only the first [`MAX_LIGHTS`] are uploaded.
+    pub lights: Vec<WorldLight>,
+
+    /// The shadow atlas tiles of this frame, if the scene renders shadows.
+    pub shadows: Option<ShadowMaps>,
+
+    /// The cube map lighting surfaces from every direction, if the scene has one.
+    pub environment: Option<EnvironmentLight>,
+}
+
+/// Image-based ambient light: a cube map, usually the skybox, that
+/// [PBR materials](crate::engine::pbr) reflect and are lit by. Its average color is part of
+/// [`LightSet::ambient`] as well, for the shaders without image-based lighting.
+#[derive(Clone, Debug)]
+pub struct EnvironmentLight {
+    /// The environment, with a full mip chain: blurrier levels stand in for rougher
+    /// reflections.
+    pub cubemap: Rc<Cubemap>,
+
+    /// Multiplier of the cube map's colors.
+    pub intensity: f32,
+}
+
+impl EnvironmentLight {
+    /// The light's share of the ambient term: the cube map's average color times the
+    /// intensity.
+    pub fn ambient(&self) -> [f32; 3] {
+        let color = self.cubemap.average_color();
+        [color.r, color.g, color.b].map(|channel| channel * self.intensity)
+    }
+}
+
+impl LightSet {
+    /// An empty light set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a light placed by the given world matrix, in full detail. Ambient lights are
+    /// accumulated.
+    pub fn add(&mut self, light: Light, world_matrix: &[f32; 16]) {
+        if let LightKind::Ambient = light.kind {
+            let [r, g, b, _] = light.color.to_linear();
+            self.ambient[0] += r * light.intensity;
+            self.ambient[1] += g * light.intensity;
+            self.ambient[2] += b * light.intensity;
+            return;
+        }
+
+        let forward = [-world_matrix[8], -world_matrix[9], -world_matrix[10]];
+        let direction = normalize_or(forward, [0.0, 0.0, -1.0]);
+        let position = [world_matrix[12], world_matrix[13], world_matrix[14]];
+        let cookie_matrix = cookie_matrix(&light, world_matrix, position, direction);
+
+        self.lights.push(WorldLight { light, position, direction, cookie_matrix, detail: LightDetail::Full, shadow: None });
+    }
+
+    /// Fits the lights to what a shader takes for a camera at `eye`: sets each light's
+    /// detail by its distance according to `lod` (or leaves it full with `None`), orders
+    /// the lights nearest first, and merges every light reduced to
+    /// [`LightDetail::Ambient`], as well as any beyond [`MAX_LIGHTS`], into the ambient term.
+    ///
+    /// A merged light adds the color it casts at the camera's distance, spread evenly in
+    /// all directions, so distant groups of lights still brighten their surroundings.
+    ///
+    /// ```
+    /// # use rustge::engine::{light::{Light, LightLod, LightSet, MAX_LIGHTS}, math::color::Color};
+    /// # use rustge::engine::math::matrixfuncs::translation_matrix;
+    /// let mut lights = LightSet::new();
+    /// for i in 0..20 {
+    ///     lights.add(Light::point(Color::WHITE, 1.0, 10.0), &translation_matrix([i as f32 * 10.0, 0.0, 0.0]));
+    /// }
+    /// lights.apply_lod([0.0; 3], Some(&LightLod::default()));
+    /// assert!(lights.lights.len() <= MAX_LIGHTS);
+    /// assert_eq!(lights.lights[0].position, [0.0; 3]);
+    /// assert!(lights.ambient[0] > 0.0);
+    /// ```
+    pub fn apply_lod(&mut self, eye: [f32; 3], lod: Option<&LightLod>) {
+        let light_distance = |world: &WorldLight| match world.light.kind {
+            LightKind::Directional => 0.0,
+            _ => distance(eye, world.position),
+        };
+        for world in &mut self.lights {
+            world.detail = lod.map_or(LightDetail::Full, |lod| lod.detail(&world.light, light_distance(world)));
+        }
+        self.lights.sort_by(|a, b| {
+            let key = |world: &WorldLight| (!world.light.lod_override.keep_direct, light_distance(world));
+            key(a).partial_cmp(&key(b)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let mut kept = 0;
+        let mut ambient = self.ambient;
+        self.lights.retain(|world| {
+            if world.detail != LightDetail::Ambient && (kept < MAX_LIGHTS || world.light.lod_override.keep_direct) {
+                kept += 1;
+                return true;
+            }
+            let [r, g, b, _] = world.light.color.to_linear();
+            let strength = world.light.intensity * ambient_share(&world.light, light_distance(world));
+            ambient[0] += r * strength;
+            ambient[1] += g * strength;
+            ambient[2] += b * strength;
+            false
+        });
+        self.ambient = ambient;
+    }
+
+    /// Uploads the lights to `shader`, which must be the current program.
+    ///
+    /// Does nothing for shaders that declare neither the standard `Lights`
+    /// [uniform block](crate::engine::uniform_buffer) nor `u_light_count`. Lighting shaders
+    /// declare:
+    ///
+    /// ```glsl
+    /// struct Light {
+    ///     int kind; vec3 position; vec3 direction; vec3 color; vec3 attenuation; vec2 cone;
+    ///     int cookie; mat4 cookie_matrix; float specular; int shadow;
+    /// };
+    /// layout(std140) uniform Lights {
+    ///     Light u_lights[8];
+    ///     int u_light_count;
+    ///     vec3 u_ambient;
+    ///     mat4 u_shadow_matrices[16];
+    ///     vec4 u_shadow_rects[16];
+    ///     float u_shadow_bias;
+    /// };
+    /// uniform sampler2D u_cookies[4];
+    /// uniform sampler2DShadow u_shadow_atlas;
+    /// ```
+    ///
+    /// The block's members may also be declared as plain uniforms, which are then set one
+    /// by one. With the block, this uploads its buffer when the lights changed; the render
+    /// queue does so once per pass instead and only binds the textures per program.
+    ///
+    /// `kind` is 0 for directional, 1 for point and 2 for spot lights; `color` is linear and
+    /// pre-multiplied by intensity; `cone` holds the cosines of the inner and outer angles.
+    /// `cookie` indexes `u_cookies`, or is -1 without a cookie; `cookie_matrix` is
+    /// [`WorldLight::cookie_matrix`]. Cookie textures are bound to units 8 and up.
+    /// `specular` is 1, or 0 for lights whose [detail](WorldLight::detail) drops
+    /// highlights.
+    ///
+    /// `shadow` indexes the light's first [shadow atlas](crate::engine::shadow) tile, or is -1
+    /// without a shadow. `u_shadow_matrices` map world space to each tile's clip space and
+    /// `u_shadow_rects` place the tiles in the atlas (x, y, width and height in texture
+    /// coordinates). The atlas is bound to unit 12.
+    ///
+    /// The [environment](Self::environment) is set as plain uniforms, for shaders that
+    /// declare them: `samplerCube u_environment` on unit 13, `float u_environment_intensity`
+    /// (0 without an environment), `float u_environment_levels` (its mip levels) and
+    /// `vec3 u_environment_ambient` (its share of `u_ambient`).
+    pub fn upload(&self, shader: &GLShaderProgram) {
+        if shader.has_uniform_block(LIGHTS_BLOCK) {
+            upload_lights_block(self);
+            self.bind_textures(shader);
+            return;
+        }
+        if shader.uniform_location("u_light_count") < 0 {
+            return;
+        }
+
+        self.bind_textures(shader);
+        shader.set_uniform_int("u_light_count", self.lights.len().min(MAX_LIGHTS) as i32);
+        shader.set_uniform_vec3("u_ambient", self.ambient);
+        if let Some(shadows) = &self.shadows {
+            shader.set_uniform_float("u_shadow_bias", shadows.depth_bias);
+            for (tile, names) in shadows.tiles.iter().zip(shadow_tile_names()) {
+                shader.set_uniform_matrix4(&names[0], &tile.matrix);
+                shader.set_uniform_vec4(&names[1], shadows.tile_rect(tile));
+            }
+        }
+
+        let mut cookies = 0;
+        for (world, names) in self.lights.iter().zip(uniform_names()) {
+            let params = self.shader_params(world, &mut cookies);
+            shader.set_uniform_int(&names[0], params.kind);
+            shader.set_uniform_vec3(&names[1], world.position);
+            shader.set_uniform_vec3(&names[2], world.direction);
+            shader.set_uniform_vec3(&names[3], params.color);
+            shader.set_uniform_vec3(&names[4], params.attenuation);
+            shader.set_uniform_vec2(&names[5], params.cone);
+            shader.set_uniform_int(&names[6], params.cookie);
+            if params.cookie >= 0 {
+                shader.set_uniform_matrix4(&names[7], &world.cookie_matrix);
+            }
+            shader.set_uniform_float(&names[8], params.specular);
+            shader.set_uniform_int(&names[9], params.shadow);
+        }
+    }
+
+    /// Writes the members of the standard `Lights` block (see [`upload`](Self::upload)), for
+    /// uploading to a [`UniformBuffer`](crate::engine::uniform_buffer::UniformBuffer).
+    ///
+    /// ```
+    /// # use rustge::engine::{light::{Light, LightSet}, math::color::Color, uniform_buffer::Std140Writer};
+    /// # use rustge::engine::math::matrixfuncs::IDENTITY_MATRIX;
+    /// let mut lights = LightSet::new();
+    /// lights.add(Light::directional(Color::WHITE, 1.0), &IDENTITY_MATRIX);
+    /// let mut block = Std140Writer::new();
+    /// lights.write_block(&mut block);
+    /// assert_eq!(block.finish().len(), 2736);
+    /// ```
+    pub fn write_block(&self, block: &mut Std140Writer) {
+        let mut cookies = 0;
+        for index in 0..MAX_LIGHTS {
+            // Structs and their array elements are aligned to 16 bytes
+            block.align(16);
+            let Some(world) = self.lights.get(index) else {
+                block.int(0);
+                block.vec3([0.0; 3]);
+                block.vec3([0.0; 3]);
+                block.vec3([0.0; 3]);
+                block.vec3([0.0; 3]);
+                block.vec2([0.0; 2]);
+                block.int(-1);
+                block.mat4(&IDENTITY_MATRIX);
+                block.float(0.0);
+                block.int(-1);
+                continue;
+            };
+            let params = self.shader_params(world, &mut cookies);
+            block.int(params.kind);
+            block.vec3(world.position);
+            block.vec3(world.direction);
+            block.vec3(params.color);
+            block.vec3(params.attenuation);
+            block.vec2(params.cone);
+            block.int(params.cookie);
+            block.mat4(&world.cookie_matrix);
+            block.float(params.specular);
+            block.int(params.shadow);
+        }
+        block.align(16);
+
+        block.int(self.lights.len().min(MAX_LIGHTS) as i32);
+        block.vec3(self.ambient);
+        let tiles = self.shadows.as_ref().map_or(&[][..], |shadows| &shadows.tiles[..]);
+        for index in 0..MAX_SHADOW_TILES {
+            block.mat4(tiles.get(index).map_or(&IDENTITY_MATRIX, |tile| &tile.matrix));
+        }
+        for index in 0..MAX_SHADOW_TILES {
+            let rect = match (&self.shadows, tiles.get(index)) {
+                (Some(shadows), Some(tile)) => shadows.tile_rect(tile),
+                _ => [0.0; 4],
+            };
+            block.vec4(rect);
+        }
+        block.float(self.shadows.as_ref().map_or(0.0, |shadows| shadows.depth_bias));
+    }
+
+    /// Binds the cookie textures, the shadow atlas and the environment map and points
+    /// `shader`'s samplers, which can't live in uniform blocks, at them. `shader` must be the
+    /// current program.
+    pub(crate) fn bind_textures(&self, shader: &GLShaderProgram) {
+        // Set even without shadows: samplers of different types may not share a unit
+        shader.set_sampler("u_shadow_atlas", SHADOW_TEXTURE_UNIT);
+        if let Some(shadows) = &self.shadows {
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + SHADOW_TEXTURE_UNIT);
+                gl::BindTexture(gl::TEXTURE_2D, shadows.texture);
+            }
+        }
+        for (slot, cookie) in self.cookies().enumerate() {
+            cookie.texture.bind(COOKIE_TEXTURE_UNIT + slot as u32);
+            shader.set_sampler(&cookie_sampler_names()[slot], COOKIE_TEXTURE_UNIT + slot as u32);
+        }
+
+        if shader.uniform_location("u_environment") < 0 {
+            return;
+        }
+        shader.set_sampler("u_environment", ENVIRONMENT_TEXTURE_UNIT);
+        match &self.environment {
+            Some(environment) => {
+                environment.cubemap.bind(ENVIRONMENT_TEXTURE_UNIT);
+                shader.set_uniform_float("u_environment_intensity", environment.intensity);
+                shader.set_uniform_float("u_environment_levels", environment.cubemap.levels() as f32);
+                shader.set_uniform_vec3("u_environment_ambient", environment.ambient());
+            }
+            None => shader.set_uniform_float("u_environment_intensity", 0.0),
+        }
+    }
+
+    /// The cookies given a slot in `u_cookies`, in light order: those of the uploaded spot
+    /// and directional lights, up to [`MAX_COOKIES`].
+    fn cookies(&self) -> impl Iterator<Item = &LightCookie> {
+        self.lights
+            .iter()
+            .take(MAX_LIGHTS)
+            .filter(|world| !matches!(world.light.kind, LightKind::Point { .. }))
+            .filter_map(|world| world.light.cookie.as_ref())
+            .take(MAX_COOKIES)
+    }
+
+    /// The shader's view of `world`, one of this set's lights. `cookies` counts the cookie
+    /// slots taken by the lights before it, in the order of [`cookies`](Self::cookies).
+    fn shader_params(&self, world: &WorldLight, cookies: &mut usize) -> LightParams {
+        let light = &world.light;
+        let [r, g, b, _] = light.color.to_linear();
+        let (kind, attenuation, cone) = match light.kind {
+            LightKind::Point { attenuation } => (1, attenuation, [-1.0, -1.0]),
+            LightKind::Spot { attenuation, inner_angle, outer_angle } => (2, attenuation, [inner_angle.cos(), outer_angle.cos()]),
+            _ => (0, Attenuation::none(), [-1.0, -1.0]),
+        };
+        let cookie = match &light.cookie {
+            Some(_) if kind != 1 && *cookies < MAX_COOKIES => {
+                *cookies += 1;
+                *cookies as i32 - 1
+            }
+            _ => -1,
+        };
+        let shadow = world.shadow.filter(|_| self.shadows.is_some() && kind != 0);
+        LightParams {
+            kind,
+            color: [r * light.intensity, g * light.intensity, b * light.intensity],
+            attenuation: [attenuation.constant, attenuation.linear, attenuation.quadratic],
+            cone,
+            cookie,
+            specular: if world.detail >= LightDetail::Specular { 1.0 } else { 0.0 },
+            shadow: shadow.map_or(-1, |tile| tile as i32),
+        }
+    }
+}
+
+/// The members of a light's shader struct that aren't copied straight from [`WorldLight`].
+struct LightParams {
+    kind: i32,
+    color: [f32; 3],
+    attenuation: [f32; 3],
+    cone: [f32; 2],
+    cookie: i32,
+    specular: f32,
+    shadow: i32,
+}
+
+/// The world-to-cookie projection of `light` placed by `world_matrix`: a perspective
+/// projection over the outer cone for spot lights, an orthographic one repeating every
+/// [`tile_size`](LightCookie::tile_size) units for directional lights.
+fn cookie_matrix(light: &Light, world_matrix: &[f32; 16], position: [f32; 3], direction: [f32; 3]) -> [f32; 16] {
+    let Some(cookie) = &light.cookie else {
+        return IDENTITY_MATRIX;
+    };
+    let up = normalize_or([world_matrix[4], world_matrix[5], world_matrix[6]], [0.0, 1.0, 0.0]);
+    let view = look_at_matrix(position, add(position, direction), up);
+    match light.kind {
+        LightKind::Spot { outer_angle, .. } => {
+            // Only x, y and w are used, so the depth range is arbitrary
+            let projection = perspective_matrix(2.0 * outer_angle, 1.0, 0.1, 100.0);
+            matrix_mul_4x4(&projection, &view)
+        }
+        LightKind::Directional => {
+            let scale = 2.0 / cookie.tile_size.max(f32::EPSILON);
+            matrix_mul_4x4(&scale_matrix([scale, scale, 1.0]), &view)
+        }
+        _ => IDENTITY_MATRIX,
+    }
+}
+
+/// The fraction of `light`'s intensity it adds to ambient when merged at `distance` from
+/// the camera: its falloff there, scaled for spot lights by the share of directions their
+/// cone covers.
+fn ambient_share(light: &Light, distance: f32) -> f32 {
+    match light.kind {
+        LightKind::Point { attenuation } => attenuation.factor(distance),
+        LightKind::Spot { attenuation, outer_angle, .. } => attenuation.factor(distance) * (1.0 - outer_angle.cos()) / 2.0,
+        _ => 1.0,
+    }
+}
+
+/// Uniform names for each light slot, built once to avoid formatting strings every draw.
+fn uniform_names() -> &'static [[String; 10]] {
+    static NAMES: OnceLock<Vec<[String; 10]>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        (0..MAX_LIGHTS)
+            .map(|i| {
+                ["kind", "position", "direction", "color", "attenuation", "cone", "cookie", "cookie_matrix", "specular", "shadow"]
+                    .map(|field| format!("u_lights[{i}].{field}"))
+            })
+            .collect()
+    })
+}
+
+/// Matrix and rectangle uniform names for each shadow tile.
+fn shadow_tile_names() -> &'static [[String; 2]] {
+    static NAMES: OnceLock<Vec<[String; 2]>> = OnceLock::new();
+    NAMES.get_or_init(|| (0..MAX_SHADOW_TILES).map(|i| [format!("u_shadow_matrices[{i}]"), format!("u_shadow_rects[{i}]")]).collect())
+}
+
+/// Sampler uniform names for each cookie slot.
+fn cookie_sampler_names() -> &'static [String] {
+    static NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    NAMES.get_or_init(|| (0..MAX_COOKIES).map(|i| format!("u_cookies[{i}]")).collect())
+}
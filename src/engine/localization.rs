@@ -0,0 +1,233 @@
+//! String tables for shipping a game in several languages.
+//!
+//! User-facing text is looked up by key (`"menu.start"`) instead of being written into the
+//! code. Each language has a [`StringTable`], usually loaded from a JSON file per locale, and
+//! a [`Localization`] picks the string for the current locale, falling back along a chain:
+//! the full locale (`pt-BR`), its language (`pt`), then the default locale. A key missing
+//! everywhere shows as the key itself, so untranslated text stands out without crashing.
+//!
+//! Strings may contain `{name}` placeholders, filled in by [`Localization::format`]; word
+//! order differs between languages, so placeholders are named rather than positional.
+//!
+//! Translated strings are drawn with
+//! [`TextBatch::font_text`](crate::engine::debug::text::TextBatch::font_text) through a
+//! [`FontChain`](crate::engine::font::FontChain): each character comes from the first font
+//! that has it, and runs are shaped with rustybuzz, so Cyrillic, Arabic, CJK and other
+//! scripts render given fonts that cover them. The built-in ASCII font of
+//! [`TextBatch::text`](crate::engine::debug::text::TextBatch::text) only suits Latin text.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::localization::{Localization, StringTable};
+//! let mut localization = Localization::new("en");
+//! localization.add_table(StringTable::from_json("en", r#"{ "menu": { "start": "Start", "greeting": "Hello, {name}!" } }"#).unwrap());
+//! localization.add_table(StringTable::from_json("de", r#"{ "menu.greeting": "Hallo, {name}!" }"#).unwrap());
+//!
+//! localization.set_locale("de-AT");
+//! assert_eq!(localization.format("menu.greeting", &[("name", "Ada")]), "Hallo, Ada!");
+//! assert_eq!(localization.get("menu.start"), "Start"); // not translated yet
+//! assert_eq!(localization.get("menu.quit"), "menu.quit");
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use serde_json::Value;
+
+/// The strings of one locale, by key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StringTable {
+    locale: String,
+    strings: HashMap<String, String>,
+}
+
+impl StringTable {
+    /// An empty table for `locale`, a language tag like `"en"` or `"pt-BR"`.
+    pub fn new(locale: &str) -> Self {
+        Self { locale: locale.to_string(), strings: HashMap::new() }
+    }
+
+    /// Parses a table from a JSON object of strings. Nested objects are flattened with `.`,
+    /// so `{"menu": {"start": "Start"}}` defines `menu.start`. Values other than strings and
+    /// objects are skipped with a warning.
+    pub fn from_json(locale: &str, json: &str) -> io::Result<Self> {
+        let value: Value = serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let Value::Object(_) = value else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "string table must be a JSON object"));
+        };
+        let mut table = Self::new(locale);
+        table.flatten("", &value);
+        Ok(table)
+    }
+
+    /// Loads a table from a JSON file named after its locale, like `strings/pt-BR.json`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let locale = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("no locale in {}", path.display())))?;
+        Self::from_json(locale, &std::fs::read_to_string(path)?)
+    }
+
+    fn flatten(&mut self, prefix: &str, value: &Value) {
+        match value {
+            Value::String(string) => {
+                self.strings.insert(prefix.to_string(), string.clone());
+            }
+            Value::Object(entries) => {
+                for (key, value) in entries {
+                    let key = if prefix.is_empty() { key.clone() } else { format!("{prefix}.{key}") };
+                    self.flatten(&key, value);
+                }
+            }
+            _ => eprintln!("Warning: Skipping non-string {prefix} in {} string table", self.locale),
+        }
+    }
+
+    /// The table's locale.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Sets the string for `key`.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.strings.insert(key.to_string(), value.to_string());
+    }
+
+    /// The string for `key`, if the table has it.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.strings.get(key).map(String::as_str)
+    }
+
+    /// Number of strings in the table.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Whether the table has no strings.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// String tables of every shipped locale and the locale currently shown.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Localization {
+    tables: Vec<StringTable>,
+    default_locale: String,
+    locale: String,
+}
+
+impl Localization {
+    /// No tables yet, showing `default_locale`, the language every string exists in.
+    pub fn new(default_locale: &str) -> Self {
+        Self { tables: Vec::new(), default_locale: default_locale.to_string(), locale: default_locale.to_string() }
+    }
+
+    /// Adds a table, merging it into an existing one of the same locale (its strings win).
+    pub fn add_table(&mut self, table: StringTable) {
+        match self.tables.iter_mut().find(|existing| existing.locale.eq_ignore_ascii_case(&table.locale)) {
+            Some(existing) => existing.strings.extend(table.strings),
+            None => self.tables.push(table),
+        }
+    }
+
+    /// Loads every `.json` table in `directory` (see [`StringTable::load`]). Returns the
+    /// number loaded; files that fail to parse are skipped with a warning.
+    pub fn load_directory(&mut self, directory: impl AsRef<Path>) -> io::Result<usize> {
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if !path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+                continue;
+            }
+            match StringTable::load(&path) {
+                Ok(table) => {
+                    self.add_table(table);
+                    loaded += 1;
+                }
+                Err(err) => eprintln!("Warning: Failed to load string table {}: {err}", path.display()),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Switches to `locale`. It doesn't need a table of its own: `pt-BR` falls back to `pt`
+    /// and then to the default locale.
+    pub fn set_locale(&mut self, locale: &str) {
+        self.locale = locale.to_string();
+    }
+
+    /// The current locale.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// The locales with a table, e.g. for a language menu.
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.tables.iter().map(StringTable::locale)
+    }
+
+    /// The locales strings are looked up in, most specific first: `pt-BR`, `pt`, then the
+    /// default locale.
+    pub fn fallback_chain(&self) -> Vec<&str> {
+        let mut chain = Vec::new();
+        for locale in [self.locale.as_str(), self.default_locale.as_str()] {
+            let mut tag = locale;
+            loop {
+                if !chain.iter().any(|other: &&str| other.eq_ignore_ascii_case(tag)) {
+                    chain.push(tag);
+                }
+                match tag.rfind(['-', '_']) {
+                    Some(end) => tag = &tag[..end],
+                    None => break,
+                }
+            }
+        }
+        chain
+    }
+
+    /// The string for `key` in the first locale of the [fallback chain](Self::fallback_chain)
+    /// that has it, or `None`.
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.fallback_chain().into_iter().find_map(|locale| {
+            self.tables.iter().find(|table| table.locale.eq_ignore_ascii_case(locale)).and_then(|table| table.get(key))
+        })
+    }
+
+    /// The string for `key` (see [`lookup`](Self::lookup)), or `key` itself if no locale has it.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    /// The string for `key` with its `{name}` placeholders replaced by the matching
+    /// `args`. Unknown placeholders are left as they are; `{{` and `}}` are literal braces.
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        let template = self.get(key);
+        let mut result = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                result.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let placeholder = rest.strip_prefix('{').and_then(|inner| inner.find('}').map(|end| &inner[..end]));
+            match placeholder.and_then(|name| args.iter().find(|(arg, _)| *arg == name).map(|(_, value)| (name, value))) {
+                Some((name, value)) => {
+                    result.push_str(value);
+                    rest = &rest[name.len() + 2..];
+                }
+                None => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
@@ -0,0 +1,263 @@
+//! Materials describe *how* an object is drawn: which shader to use, the values fed to it,
+//! and the fixed-function GL state (blending, culling, depth) around the draw call.
+//!
+//! A `Material` references a shared [`GLShaderProgram`] through an `Rc`, so many objects can
+//! use the same compiled program while each keeps its own color, textures, and state.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::engine::math::color::Color;
+use crate::engine::pbr::PbrMaterial;
+use crate::engine::shader::{builtin_program, GLShaderProgram, UniformValue};
+use crate::engine::texture::{frame_lod_bias, Texture};
+
+/// How a material's output is combined with what's already in the framebuffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// No blending; the fragment replaces the framebuffer color.
+    Opaque,
+    /// Classic alpha blending: `src * a + dst * (1 - a)`.
+    Alpha,
+    /// Additive blending: `src * a + dst`. Useful for glows and particles.
+    Additive,
+}
+
+/// Which triangle faces are discarded before rasterization.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullMode {
+    /// Draw both faces.
+    None,
+    /// Discard back faces (the usual choice for closed meshes).
+    Back,
+    /// Discard front faces.
+    Front,
+}
+
+/// Surface description used when drawing an [`Object3D`](crate::engine::object3d::Object3D).
+///
+/// # Example
+/// ```no_run
+/// # use std::rc::Rc;
+/// # use rustge::engine::{material::Material, math::color::Color, shader::GLShaderProgram};
+/// # let (vs_src, fs_src) = ("", "");
+/// let shader = Rc::new(GLShaderProgram::from_sources(vs_src, fs_src));
+///
+/// let mut glass = Material::new(shader.clone());
+/// glass.color = Color::srgba(0.6, 0.8, 1.0, 0.3);
+/// glass.set_transparent(true);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Material {
+    /// The shader program used to draw with this material. Shared between materials.
+    pub shader: Rc<GLShaderProgram>,
+
+    /// Base (diffuse) color, uploaded as the `u_color` uniform.
+    pub color: Color,
+
+    /// Textures bound when the material is bound, as (sampler uniform name, texture) pairs.
+    /// Each texture is bound to the texture unit matching its position in this list.
+    pub textures: Vec<(String, Rc<Texture>)>,
+
+    /// Additional named uniform values uploaded when the material is bound.
+    pub uniforms: HashMap<String, UniformValue>,
+
+    /// Framebuffer blending mode.
+    pub blend: BlendMode,
+
+    /// Face culling mode.
+    pub cull: CullMode,
+
+    /// Whether fragments are tested against the depth buffer.
+    /// Has no effect when depth testing is disabled on the renderer.
+    pub depth_test: bool,
+
+    /// Whether fragments write to the depth buffer.
+    /// Usually disabled for transparent materials so they don't occlude each other.
+    pub depth_write: bool,
+
+    /// Rasterized point size in pixels, used when drawing `Points` geometry.
+    pub point_size: f32,
+
+    /// Rasterized line width in pixels, used when drawing `Lines`/`LineStrip` geometry.
+    /// Core-profile drivers are only required to support a width of 1.0.
+    pub line_width: f32,
+
+    /// Mip level bias of the material's textures, added to the renderer's global
+    /// [`TextureLod`](crate::engine::texture::TextureLod): negative for sharper textures (e.g. text on signs), positive for
+    /// softer ones (e.g. noisy detail that shimmers).
+    pub lod_bias: f32,
+}
+
+impl Material {
+    /// Creates an opaque, white, back-face-culled material using the given shader.
+    pub fn new(shader: Rc<GLShaderProgram>) -> Self {
+        Self {
+            shader,
+            color: Color::WHITE,
+            textures: Vec::new(),
+            uniforms: HashMap::new(),
+            blend: BlendMode::Opaque,
+            cull: CullMode::Back,
+            depth_test: true,
+            depth_write: true,
+            point_size: 1.0,
+            line_width: 1.0,
+            lod_bias: 0.0,
+        }
+    }
+
+    /// Creates a material using the built-in Blinn-Phong shader, lit by the scene's
+    /// [`Light`](crate::engine::light::Light)s.
+    ///
+    /// `color` is the diffuse color. The specular response is controlled by the `u_specular`
+    /// (linear RGB, default 0.25 grey) and `u_shininess` (exponent, default 32) uniforms.
+    ///
+    /// Must be called with a current GL context.
+    pub fn phong(color: Color) -> Self {
+        let shader = builtin_program("phong", include_str!("shaders/phong.vert"), &lit_fragment_source(include_str!("shaders/phong.frag")));
+        let mut material = Self::new(shader);
+        material.color = color;
+        material.set_uniform("u_specular", UniformValue::Vec3([0.25, 0.25, 0.25]));
+        material.set_uniform("u_shininess", UniformValue::Float(32.0));
+        material
+    }
+
+    /// Creates a material using the built-in physically based shader, a shorthand for a
+    /// textureless [`PbrMaterial`] with the given albedo, metallic and roughness.
+    ///
+    /// Must be called with a current GL context.
+    pub fn pbr(albedo: Color, metallic: f32, roughness: f32) -> Self {
+        PbrMaterial { metallic, roughness, ..PbrMaterial::new(albedo) }.material()
+    }
+
+    /// Sets the base color.
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    /// Makes the material translucent (alpha blended, without depth writes, so objects
+    /// behind it still show through) or opaque again.
+    ///
+    /// Translucent materials are drawn after all opaque geometry, sorted back to front; see
+    /// [`RenderQueue`](crate::engine::render_queue::RenderQueue).
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.blend = if transparent { BlendMode::Alpha } else { BlendMode::Opaque };
+        self.depth_write = !transparent;
+    }
+
+    /// Whether the material blends with the framebuffer (any [`BlendMode`] other than
+    /// `Opaque`), which puts it in the transparent pass.
+    pub fn is_transparent(&self) -> bool {
+        self.blend != BlendMode::Opaque
+    }
+
+    /// Assigns a texture to a sampler uniform, replacing any texture already bound to that name.
+    pub fn set_texture(&mut self, sampler: &str, texture: Rc<Texture>) {
+        match self.textures.iter_mut().find(|(name, _)| name == sampler) {
+            Some(slot) => slot.1 = texture,
+            None => self.textures.push((sampler.to_string(), texture)),
+        }
+    }
+
+    /// Sets a named uniform value uploaded whenever this material is bound.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+        self.uniforms.insert(name.to_string(), value);
+    }
+
+    /// Applies the material's GL state, binds its textures and uploads its uniforms.
+    ///
+    /// Must be called with a current GL context, before issuing the draw call.
+    pub fn bind(&self) {
+        self.shader.use_program();
+        self.apply_state();
+        self.upload_parameters();
+    }
+
+    /// Applies the fixed-function GL state (blending, culling, depth, point and line size)
+    /// without touching the program or uniforms.
+    pub fn apply_state(&self) {
+        unsafe {
+            match self.blend {
+                BlendMode::Opaque => gl::Disable(gl::BLEND),
+                BlendMode::Alpha => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                }
+                BlendMode::Additive => {
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+                }
+            }
+
+            match self.cull {
+                CullMode::None => gl::Disable(gl::CULL_FACE),
+                CullMode::Back => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::BACK);
+                }
+                CullMode::Front => {
+                    gl::Enable(gl::CULL_FACE);
+                    gl::CullFace(gl::FRONT);
+                }
+            }
+
+            // GL_DEPTH_TEST itself is owned by the renderer; a material that opts out of
+            // depth testing simply lets every fragment pass.
+            gl::DepthFunc(if self.depth_test { gl::LESS } else { gl::ALWAYS });
+            gl::DepthMask(if self.depth_write { gl::TRUE } else { gl::FALSE });
+
+            gl::PointSize(self.point_size);
+            gl::LineWidth(self.line_width);
+        }
+    }
+
+    /// Uploads the color, textures and uniforms to the material's shader, which must be
+    /// the current program.
+    pub fn upload_parameters(&self) {
+        // Output goes to a non-sRGB framebuffer, so shaders work on sRGB-encoded colors
+        self.shader.set_uniform_vec4("u_color", self.color.to_srgb());
+
+        let lod_bias = frame_lod_bias() + self.lod_bias;
+        for (unit, (sampler, texture)) in self.textures.iter().enumerate() {
+            texture.bind(unit as u32);
+            texture.apply_lod_bias(lod_bias);
+            self.shader.set_sampler(sampler, unit as u32);
+        }
+
+        for (name, value) in &self.uniforms {
+            self.shader.set_uniform(name, value);
+        }
+    }
+
+    /// Whether drawing with `other` needs no change to the fixed-function GL state set by
+    /// [`apply_state`](Self::apply_state).
+    pub fn same_state(&self, other: &Material) -> bool {
+        self.blend == other.blend
+            && self.cull == other.cull
+            && self.depth_test == other.depth_test
+            && self.depth_write == other.depth_write
+            && self.point_size == other.point_size
+            && self.line_width == other.line_width
+    }
+
+    /// Whether `other` uses the same shader and uploads the same color, textures and
+    /// uniforms, so switching between the two needs no GL calls at all.
+    pub fn same_parameters(&self, other: &Material) -> bool {
+        Rc::ptr_eq(&self.shader, &other.shader)
+            && self.color == other.color
+            && self.lod_bias == other.lod_bias
+            && self.textures.len() == other.textures.len()
+            && self
+                .textures
+                .iter()
+                .zip(&other.textures)
+                .all(|((a_name, a), (b_name, b))| a_name == b_name && Rc::ptr_eq(a, b))
+            && self.uniforms == other.uniforms
+    }
+}
+
+/// A lit fragment shader's complete source: the version line and the scene lighting
+/// declarations shared by the built-in lit shaders (`shaders/lighting.glsl`), then `body`.
+pub(crate) fn lit_fragment_source(body: &str) -> String {
+    format!("#version 330 core\n{}\n{}", include_str!("shaders/lighting.glsl"), body)
+}
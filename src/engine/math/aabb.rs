@@ -0,0 +1,97 @@
+//! Axis-aligned bounding boxes.
+//!
+//! An [`Aabb`] is the tightest box around a set of points whose faces are parallel to the
+//! coordinate planes. It is cheap to build, merge and transform, which makes it the usual
+//! way to describe "everything in this subtree" for camera framing and editor tools.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::aabb::Aabb;
+//! let a = Aabb::new([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+//! let b = Aabb::from_points([[2.0, -1.0, 0.5]]).unwrap();
+//! let both = a.union(&b);
+//! assert_eq!(both.min, [0.0, -1.0, 0.0]);
+//! assert_eq!(both.max, [2.0, 1.0, 1.0]);
+//! assert_eq!(both.center(), [1.0, 0.0, 0.5]);
+//! ```
+
+use crate::engine::math::matrixfuncs::transform_point;
+use crate::engine::math::vec::{lerp, sub};
+
+/// An axis-aligned box spanning `min` to `max` on every axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: [f32; 3],
+
+    /// The corner with the largest coordinates.
+    pub max: [f32; 3],
+}
+
+impl Aabb {
+    /// The box between two opposite corners, in any order.
+    pub fn new(a: [f32; 3], b: [f32; 3]) -> Self {
+        Self {
+            min: std::array::from_fn(|axis| a[axis].min(b[axis])),
+            max: std::array::from_fn(|axis| a[axis].max(b[axis])),
+        }
+    }
+
+    /// The smallest box containing every point, or `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = [f32; 3]>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let mut bounds = Self { min: first, max: first };
+        for point in points {
+            bounds.expand(point);
+        }
+        Some(bounds)
+    }
+
+    /// Grows the box to contain `point`.
+    pub fn expand(&mut self, point: [f32; 3]) {
+        self.min = std::array::from_fn(|axis| self.min[axis].min(point[axis]));
+        self.max = std::array::from_fn(|axis| self.max[axis].max(point[axis]));
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Self {
+            min: std::array::from_fn(|axis| self.min[axis].min(other.min[axis])),
+            max: std::array::from_fn(|axis| self.max[axis].max(other.max[axis])),
+        }
+    }
+
+    /// The middle of the box.
+    pub fn center(&self) -> [f32; 3] {
+        lerp(self.min, self.max, 0.5)
+    }
+
+    /// Edge lengths along each axis.
+    pub fn size(&self) -> [f32; 3] {
+        sub(self.max, self.min)
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [[f32; 3]; 8] {
+        std::array::from_fn(|i| {
+            [
+                if i & 1 == 0 { self.min[0] } else { self.max[0] },
+                if i & 2 == 0 { self.min[1] } else { self.max[1] },
+                if i & 4 == 0 { self.min[2] } else { self.max[2] },
+            ]
+        })
+    }
+
+    /// Whether `point` lies inside the box or on its surface.
+    pub fn contains_point(&self, point: [f32; 3]) -> bool {
+        (0..3).all(|axis| point[axis] >= self.min[axis] && point[axis] <= self.max[axis])
+    }
+
+    /// The box around this box's corners after transforming them by `matrix`
+    /// (column-major), e.g. to move local bounds into world space.
+    pub fn transformed(&self, matrix: &[f32; 16]) -> Aabb {
+        let corners = self.corners().map(|corner| transform_point(matrix, corner));
+        Self::from_points(corners).unwrap_or(*self)
+    }
+}
@@ -0,0 +1,141 @@
+//! RGBA colors with explicit sRGB / linear handling.
+//!
+//! Colors picked in tools, written as hex codes or typed as "0.5 grey" are sRGB-encoded,
+//! while blending, lighting and interpolation are only correct on linear values. [`Color`]
+//! always stores linear RGBA; every constructor and accessor names the space it works in,
+//! so there is no guessing which encoding a `[f32; 4]` holds.
+
+/// An RGBA color, stored as linear-light components with straight (non-premultiplied) alpha.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::math::color::Color;
+/// let orange = Color::hex(0xFF8000);
+/// let sky = Color::srgb(0.4, 0.6, 0.9);
+/// let hue = Color::from_hsv(200.0, 0.5, 1.0);
+/// let halfway = orange.lerp(sky, 0.5);
+/// # let _ = (hue, halfway);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct Color {
+    /// Linear red.
+    pub r: f32,
+    /// Linear green.
+    pub g: f32,
+    /// Linear blue.
+    pub b: f32,
+    /// Alpha (opacity); not affected by the sRGB transfer function.
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::linear_rgba(1.0, 1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::linear_rgba(0.0, 0.0, 0.0, 1.0);
+    pub const TRANSPARENT: Color = Color::linear_rgba(0.0, 0.0, 0.0, 0.0);
+    pub const RED: Color = Color::linear_rgba(1.0, 0.0, 0.0, 1.0);
+    pub const GREEN: Color = Color::linear_rgba(0.0, 1.0, 0.0, 1.0);
+    pub const BLUE: Color = Color::linear_rgba(0.0, 0.0, 1.0, 1.0);
+
+    /// An opaque color from linear components.
+    pub const fn linear_rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    /// A color from linear components and alpha.
+    pub const fn linear_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// An opaque color from sRGB-encoded components in `[0, 1]`.
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Self::srgba(r, g, b, 1.0)
+    }
+
+    /// A color from sRGB-encoded components and (linear) alpha in `[0, 1]`.
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r: srgb_to_linear(r), g: srgb_to_linear(g), b: srgb_to_linear(b), a }
+    }
+
+    /// An opaque color from a `0xRRGGBB` sRGB hex code.
+    pub fn hex(rgb: u32) -> Self {
+        let channel = |shift: u32| ((rgb >> shift) & 0xFF) as f32 / 255.0;
+        Self::srgb(channel(16), channel(8), channel(0))
+    }
+
+    /// An opaque color from hue (degrees), saturation and value, all in sRGB space,
+    /// as in a typical color picker.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let s = saturation.clamp(0.0, 1.0);
+        let v = value.clamp(0.0, 1.0);
+
+        let chroma = v * s;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = v - chroma;
+        Self::srgb(r + m, g + m, b + m)
+    }
+
+    /// Returns the same color with a different alpha.
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Linearly interpolates towards `other` by `t` (in linear space, which keeps
+    /// gradients free of the dark band sRGB interpolation produces).
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        Self {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    /// Linear RGBA components, for shading math or uploading to an sRGB framebuffer.
+    pub fn to_linear(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// sRGB-encoded RGBA components (alpha unchanged), for display on a non-sRGB framebuffer.
+    pub fn to_srgb(self) -> [f32; 4] {
+        [linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a]
+    }
+
+    /// Hue (degrees), saturation and value of the sRGB-encoded color.
+    pub fn to_hsv(self) -> [f32; 3] {
+        let [r, g, b, _] = self.to_srgb();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let delta = max - min;
+
+        let hue = if delta <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max > 0.0 { delta / max } else { 0.0 };
+        [hue, saturation, max]
+    }
+}
+
+/// Converts one sRGB-encoded channel to linear.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Converts one linear channel to sRGB encoding.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
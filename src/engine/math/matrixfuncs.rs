@@ -1,3 +1,13 @@
+use crate::engine::math::vec::{cross, dot, length_squared, normalize, normalize_or, sub};
+
+/// Identity matrix (4x4) representing 'no transformation'.
+/// This matrix leaves points unchanged when multiplied.
+pub const IDENTITY_MATRIX: [f32; 16] = [
+    1.0, 0.0, 0.0, 0.0,  // Column 1
+    0.0, 1.0, 0.0, 0.0,  // Column 2
+    0.0, 0.0, 1.0, 0.0,  // Column 3
+    0.0, 0.0, 0.0, 1.0,  // Column 4
+];
 
 // -- Helper functions -- //
 
@@ -95,10 +105,10 @@ pub fn matrix_mul_4x4(a: &[f32; 16], b: &[f32; 16]) -> [f32; 16] {
     for row in 0..4 {
         for col in 0..4 {
             result[col * 4 + row] =
-                a[0 * 4 + row] * b[col * 4 + 0] +
-                    a[1 * 4 + row] * b[col * 4 + 1] +
-                    a[2 * 4 + row] * b[col * 4 + 2] +
-                    a[3 * 4 + row] * b[col * 4 + 3];
+                a[row] * b[col * 4] +
+                    a[4 + row] * b[col * 4 + 1] +
+                    a[8 + row] * b[col * 4 + 2] +
+                    a[12 + row] * b[col * 4 + 3];
         }
     }
 
@@ -115,4 +125,211 @@ pub fn perspective_matrix(fovy: f32, aspect: f32, near: f32, far: f32) -> [f32;
         0.0, 0.0, (far + near) * nf, -1.0,
         0.0, 0.0, (2.0 * far * near) * nf, 0.0,
     ]
-}
\ No newline at end of file
+}
+
+/// Builds a view matrix for an eye at `eye` looking towards `target`, with `up` pointing
+/// roughly upwards on screen (right-handed, looking down -Z in view space).
+///
+/// Degenerate input is tolerated: if `eye == target` the view looks down -Z, and if `up` is
+/// parallel to the view direction another up axis is chosen.
+pub fn look_at_matrix(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> [f32; 16] {
+    let forward = normalize_or(sub(target, eye), [0.0, 0.0, -1.0]);
+    let mut right = cross(forward, up);
+    if length_squared(right) <= f32::EPSILON {
+        // Looking along `up`; any perpendicular axis will do
+        right = cross(forward, if forward[2].abs() < 0.9 { [0.0, 0.0, 1.0] } else { [1.0, 0.0, 0.0] });
+    }
+    let right = normalize(right);
+    let up = cross(right, forward);
+
+    // Rows are the camera's right, up and backward axes
+    [
+        right[0], up[0], -forward[0], 0.0,
+        right[1], up[1], -forward[1], 0.0,
+        right[2], up[2], -forward[2], 0.0,
+        -dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0,
+    ]
+}
+
+/// Computes the inverse of a 4x4 matrix (column-major) by cofactor expansion.
+///
+/// # Returns
+/// `None` if the matrix is singular (its determinant is zero or not finite), e.g. a
+/// transform with a zero scale on some axis.
+pub fn matrix_inverse_4x4(m: &[f32; 16]) -> Option<[f32; 16]> {
+    let mut inv = [0.0f32; 16];
+
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+        + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+        - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+        + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+        - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+        - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+        + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+        - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+        + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+        + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+        - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+        + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+        - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+        - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+        + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+        - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+        + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det == 0.0 || !det.is_finite() {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    Some(inv.map(|v| v * inv_det))
+}
+
+/// Inverse of `m`, or the identity matrix if `m` is singular.
+///
+/// Convenient where a degenerate transform (such as a zero scale) should simply have no
+/// effect rather than be handled explicitly.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::math::matrixfuncs::{matrix_inverse_or_identity, scale_matrix, IDENTITY_MATRIX};
+/// assert_eq!(matrix_inverse_or_identity(&scale_matrix([2.0, 4.0, 0.5]))[5], 0.25);
+/// assert_eq!(matrix_inverse_or_identity(&scale_matrix([0.0, 1.0, 1.0])), IDENTITY_MATRIX);
+/// ```
+pub fn matrix_inverse_or_identity(m: &[f32; 16]) -> [f32; 16] {
+    matrix_inverse_4x4(m).unwrap_or(IDENTITY_MATRIX)
+}
+
+/// Transposes a 4x4 matrix, swapping rows and columns.
+///
+/// The transpose of a rotation matrix is its inverse, and the inverse-transpose of a model
+/// matrix transforms normals.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::math::matrixfuncs::{matrix_transpose_4x4, translation_matrix};
+/// let transposed = matrix_transpose_4x4(&translation_matrix([1.0, 2.0, 3.0]));
+/// assert_eq!([transposed[3], transposed[7], transposed[11]], [1.0, 2.0, 3.0]);
+/// ```
+pub fn matrix_transpose_4x4(m: &[f32; 16]) -> [f32; 16] {
+    let mut result = [0.0f32; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            result[row * 4 + col] = m[col * 4 + row];
+        }
+    }
+    result
+}
+
+/// The normal matrix of a model matrix: the inverse-transpose of its upper-left 3x3, as a
+/// column-major `mat3`.
+///
+/// Transforming normals by the model matrix itself tilts them under non-uniform scale; the
+/// normal matrix keeps them perpendicular to the transformed surface. For a singular matrix
+/// (a zero scale on some axis) the cofactor matrix is returned instead, which points the same
+/// way and is fine for normals that are renormalized afterwards.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::math::matrixfuncs::{normal_matrix, scale_matrix};
+/// let normals = normal_matrix(&scale_matrix([2.0, 1.0, 4.0]));
+/// assert_eq!([normals[0], normals[4], normals[8]], [0.5, 1.0, 0.25]);
+/// ```
+pub fn normal_matrix(m: &[f32; 16]) -> [f32; 9] {
+    // Columns of the upper-left 3x3
+    let a = [m[0], m[1], m[2]];
+    let b = [m[4], m[5], m[6]];
+    let c = [m[8], m[9], m[10]];
+
+    // The cofactor matrix has the cross products of column pairs as its columns
+    let (ca, cb, cc) = (cross(b, c), cross(c, a), cross(a, b));
+    let det = dot(a, ca);
+    let scale = if det != 0.0 && det.is_finite() { 1.0 / det } else { 1.0 };
+
+    [
+        ca[0] * scale, ca[1] * scale, ca[2] * scale,
+        cb[0] * scale, cb[1] * scale, cb[2] * scale,
+        cc[0] * scale, cc[1] * scale, cc[2] * scale,
+    ]
+}
+
+/// Transforms a point by a 4x4 matrix (w = 1), dividing by the resulting w if the matrix
+/// is projective.
+pub fn transform_point(m: &[f32; 16], p: [f32; 3]) -> [f32; 3] {
+    let x = m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12];
+    let y = m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13];
+    let z = m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14];
+    let w = m[3] * p[0] + m[7] * p[1] + m[11] * p[2] + m[15];
+    if w != 0.0 && w != 1.0 {
+        [x / w, y / w, z / w]
+    } else {
+        [x, y, z]
+    }
+}
+
+/// Transforms a direction by a 4x4 matrix (w = 0), ignoring translation.
+pub fn transform_direction(m: &[f32; 16], d: [f32; 3]) -> [f32; 3] {
+    [
+        m[0] * d[0] + m[4] * d[1] + m[8] * d[2],
+        m[1] * d[0] + m[5] * d[1] + m[9] * d[2],
+        m[2] * d[0] + m[6] * d[1] + m[10] * d[2],
+    ]
+}
+
+/// Splits an affine transform matrix into position, rotation quaternion `[x, y, z, w]`
+/// and scale, the inverse of [`compute_local_matrix`].
+///
+/// Shear (from non-uniformly scaled parents with rotated children) cannot be represented
+/// and is dropped. A negative determinant is expressed as a negative X scale.
+pub fn decompose_matrix(m: &[f32; 16]) -> ([f32; 3], [f32; 4], [f32; 3]) {
+    let position = [m[12], m[13], m[14]];
+
+    let column = |c: usize| [m[c * 4], m[c * 4 + 1], m[c * 4 + 2]];
+    let length = |v: [f32; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let (c0, c1, c2) = (column(0), column(1), column(2));
+
+    let det = c0[0] * (c1[1] * c2[2] - c2[1] * c1[2]) - c1[0] * (c0[1] * c2[2] - c2[1] * c0[2])
+        + c2[0] * (c0[1] * c1[2] - c1[1] * c0[2]);
+    let mut scale = [length(c0), length(c1), length(c2)];
+    if det < 0.0 {
+        scale[0] = -scale[0];
+    }
+
+    // Remove scale to get the pure rotation (zero scales leave that axis' rotation undefined)
+    let unscale = |v: [f32; 3], s: f32| if s.abs() > f32::EPSILON { [v[0] / s, v[1] / s, v[2] / s] } else { v };
+    let (r0, r1, r2) = (unscale(c0, scale[0]), unscale(c1, scale[1]), unscale(c2, scale[2]));
+
+    // Element (row, col) of the rotation matrix is r{col}[row]
+    let trace = r0[0] + r1[1] + r2[2];
+    let rotation = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(r1[2] - r2[1]) / s, (r2[0] - r0[2]) / s, (r0[1] - r1[0]) / s, 0.25 * s]
+    } else if r0[0] > r1[1] && r0[0] > r2[2] {
+        let s = (1.0 + r0[0] - r1[1] - r2[2]).sqrt() * 2.0;
+        [0.25 * s, (r1[0] + r0[1]) / s, (r2[0] + r0[2]) / s, (r1[2] - r2[1]) / s]
+    } else if r1[1] > r2[2] {
+        let s = (1.0 + r1[1] - r0[0] - r2[2]).sqrt() * 2.0;
+        [(r1[0] + r0[1]) / s, 0.25 * s, (r2[1] + r1[2]) / s, (r2[0] - r0[2]) / s]
+    } else {
+        let s = (1.0 + r2[2] - r0[0] - r1[1]).sqrt() * 2.0;
+        [(r2[0] + r0[2]) / s, (r2[1] + r1[2]) / s, 0.25 * s, (r0[1] - r1[0]) / s]
+    };
+
+    (position, rotation, scale)
+}
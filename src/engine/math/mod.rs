@@ -1 +1,9 @@
-pub mod matrixfuncs;
\ No newline at end of file
+pub mod matrixfuncs;
+pub mod color;
+pub mod vec;
+pub mod quat;
+pub mod sequence;
+pub mod aabb;
+pub mod random;
+pub mod ray;
+pub mod noise;
@@ -0,0 +1,87 @@
+//! Quaternion helpers for the `[x, y, z, w]` rotations used by nodes and cameras.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::quat::{from_axis_angle, rotate_vector};
+//! let quarter_turn = from_axis_angle([0.0, 1.0, 0.0], std::f32::consts::FRAC_PI_2);
+//! let v = rotate_vector(quarter_turn, [1.0, 0.0, 0.0]);
+//! assert!(v[0].abs() < 1e-6 && (v[2] + 1.0).abs() < 1e-6);
+//! ```
+
+use crate::engine::math::vec::{add, cross, dot, normalize_or, scale};
+
+/// The identity rotation.
+pub const IDENTITY: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+/// A rotation of `angle` radians (counter-clockwise when looking down the axis) around `axis`.
+/// The axis doesn't need to be normalized; a zero axis yields the identity.
+pub fn from_axis_angle(axis: [f32; 3], angle: f32) -> [f32; 4] {
+    let axis = normalize_or(axis, [0.0; 3]);
+    let (sin, cos) = (angle / 2.0).sin_cos();
+    [axis[0] * sin, axis[1] * sin, axis[2] * sin, cos]
+}
+
+/// The rotation `a * b`: applies `b` first, then `a`.
+pub fn mul(a: [f32; 4], b: [f32; 4]) -> [f32; 4] {
+    [
+        a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+        a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+        a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+        a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2],
+    ]
+}
+
+/// The shortest rotation taking direction `from` to direction `to`. Neither needs to be
+/// normalized; zero vectors yield the identity.
+pub fn rotation_between(from: [f32; 3], to: [f32; 3]) -> [f32; 4] {
+    let (from, to) = (normalize_or(from, [0.0; 3]), normalize_or(to, [0.0; 3]));
+    let cos = dot(from, to);
+    if cos < -0.9999 {
+        // Opposite directions: half a turn around any perpendicular axis
+        let axis = cross(from, [1.0, 0.0, 0.0]);
+        let axis = if dot(axis, axis) < 1e-6 { cross(from, [0.0, 1.0, 0.0]) } else { axis };
+        return from_axis_angle(axis, std::f32::consts::PI);
+    }
+    let [x, y, z] = cross(from, to);
+    normalize([x, y, z, 1.0 + cos])
+}
+
+/// The inverse of a unit quaternion.
+pub fn conjugate(q: [f32; 4]) -> [f32; 4] {
+    [-q[0], -q[1], -q[2], q[3]]
+}
+
+/// `q` scaled to unit length, or the identity if it is (nearly) zero.
+pub fn normalize(q: [f32; 4]) -> [f32; 4] {
+    normalize_or(q, IDENTITY)
+}
+
+/// Rotates `v` by the unit quaternion `q`.
+pub fn rotate_vector(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    // v' = v + 2w (u x v) + 2 u x (u x v), with u the vector part
+    let u = [q[0], q[1], q[2]];
+    let t = scale(cross(u, v), 2.0);
+    add(add(v, scale(t, q[3])), cross(u, t))
+}
+
+/// Spherical linear interpolation from `a` (`t = 0`) to `b` (`t = 1`) along the shortest arc.
+pub fn slerp(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    let mut cos = dot(a, b);
+    let b = if cos < 0.0 {
+        cos = -cos;
+        b.map(|v| -v)
+    } else {
+        b
+    };
+
+    // Nearly identical rotations: fall back to a normalized lerp
+    if cos > 0.9995 {
+        return normalize(std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t));
+    }
+
+    let angle = cos.acos();
+    let sin = angle.sin();
+    let wa = ((1.0 - t) * angle).sin() / sin;
+    let wb = (t * angle).sin() / sin;
+    std::array::from_fn(|i| a[i] * wa + b[i] * wb)
+}
@@ -0,0 +1,112 @@
+//! Rays and ray intersection tests.
+//!
+//! A [`Ray`] is a half-line `origin + t * direction` for `t >= 0`. Intersection tests
+//! return the `t` of the hit rather than a point, so the closest of several hits is the
+//! smallest `t`. Transforming a ray into another space (see [`Ray::transformed`]) keeps its
+//! direction's length as the matrix makes it, so a `t` found in the new space names the
+//! same point as in the old: meshes are tested in their own space without converting
+//! distances back.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::{aabb::Aabb, ray::Ray};
+//! let ray = Ray::new([0.0, 0.0, 5.0], [0.0, 0.0, -2.0]);
+//! assert_eq!(ray.direction, [0.0, 0.0, -1.0]);
+//!
+//! let unit_box = Aabb::new([-1.0; 3], [1.0; 3]);
+//! assert_eq!(ray.intersect_aabb(&unit_box), Some(4.0));
+//!
+//! let t = ray.intersect_triangle([-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]).unwrap();
+//! assert_eq!(ray.at(t), [0.0, 0.0, 0.0]);
+//!
+//! assert_eq!(ray.intersect_sphere([0.0, 0.0, -1.0], 2.0), Some(4.0));
+//! ```
+
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::matrixfuncs::{transform_direction, transform_point};
+use crate::engine::math::vec::{add, cross, dot, normalize_or, scale, sub};
+
+/// A half-line from `origin` along `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    /// Where the ray starts.
+    pub origin: [f32; 3],
+
+    /// Which way it goes; unit length unless the ray was [transformed](Self::transformed).
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    /// A ray from `origin` along `direction`, normalized so `t` is a distance. A zero
+    /// direction points down -Z.
+    pub fn new(origin: [f32; 3], direction: [f32; 3]) -> Self {
+        Self { origin, direction: normalize_or(direction, [0.0, 0.0, -1.0]) }
+    }
+
+    /// The point at parameter `t` along the ray.
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        add(self.origin, scale(self.direction, t))
+    }
+
+    /// The ray transformed by `matrix`, without renormalizing the direction: `t` values
+    /// along it name the same points as along `self`, transformed.
+    pub fn transformed(&self, matrix: &[f32; 16]) -> Ray {
+        Ray { origin: transform_point(matrix, self.origin), direction: transform_direction(matrix, self.direction) }
+    }
+
+    /// The `t` where the ray hits the triangle `a`, `b`, `c` from either side, if it does
+    /// (Möller–Trumbore).
+    pub fn intersect_triangle(&self, a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> Option<f32> {
+        let (edge1, edge2) = (sub(b, a), sub(c, a));
+        let p = cross(self.direction, edge2);
+        let determinant = dot(edge1, p);
+        // Parallel to the triangle's plane, relative to the triangle's and ray's size
+        if determinant.abs() <= f32::EPSILON * dot(edge1, edge1).max(dot(edge2, edge2)) * dot(self.direction, self.direction).sqrt() {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let to_origin = sub(self.origin, a);
+        let u = dot(to_origin, p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = cross(to_origin, edge1);
+        let v = dot(self.direction, q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = dot(edge2, q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// The `t` where the ray enters the sphere around `center` (0 if it starts inside), if it
+    /// hits the sphere.
+    pub fn intersect_sphere(&self, center: [f32; 3], radius: f32) -> Option<f32> {
+        // |origin + t * direction - center|^2 = radius^2, with the linear term halved
+        let to_origin = sub(self.origin, center);
+        let a = dot(self.direction, self.direction);
+        let half_b = dot(to_origin, self.direction);
+        let c = dot(to_origin, to_origin) - radius * radius;
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 || a == 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let far = (-half_b + root) / a;
+        (far >= 0.0).then(|| ((-half_b - root) / a).max(0.0))
+    }
+
+    /// The `t` where the ray enters `bounds` (0 if it starts inside), if it hits the box.
+    pub fn intersect_aabb(&self, bounds: &Aabb) -> Option<f32> {
+        let (mut near, mut far) = (0.0f32, f32::INFINITY);
+        for axis in 0..3 {
+            let inverse = 1.0 / self.direction[axis];
+            let t0 = (bounds.min[axis] - self.origin[axis]) * inverse;
+            let t1 = (bounds.max[axis] - self.origin[axis]) * inverse;
+            // NaN (a ray in the slab's plane) fails both comparisons and leaves the range alone
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+        }
+        (near <= far).then_some(near)
+    }
+}
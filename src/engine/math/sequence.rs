@@ -0,0 +1,33 @@
+//! Low-discrepancy sequences for sampling patterns.
+//!
+//! Temporal techniques (TAA, temporal upsampling) move the projection by a different
+//! sub-pixel offset every frame; a Halton sequence spreads those offsets evenly over the
+//! pixel while staying deterministic.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::sequence::{halton, halton_2d};
+//! assert_eq!(halton(1, 2), 0.5);
+//! assert_eq!(halton(3, 2), 0.75);
+//! assert_eq!(halton_2d(1), [0.5, 1.0 / 3.0]);
+//! ```
+
+/// Element `index` of the Halton (radical inverse) sequence in `base`, in `[0, 1)`.
+///
+/// Index 0 yields 0; start at 1 to skip it. `base` should be a prime of at least 2.
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let base = base.max(2);
+    let mut fraction = 1.0f32;
+    let mut result = 0.0f32;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+/// Element `index` of the 2D Halton sequence (bases 2 and 3), in `[0, 1)^2`.
+pub fn halton_2d(index: u32) -> [f32; 2] {
+    [halton(index, 2), halton(index, 3)]
+}
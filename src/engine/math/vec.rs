@@ -0,0 +1,87 @@
+//! Vector helpers for the bare `[f32; N]` arrays used throughout the engine.
+//!
+//! Positions, directions and colors stay plain arrays (`[f32; 2]`, `[f32; 3]`, `[f32; 4]`)
+//! so they can be passed straight to GL. These free functions work on any of those sizes;
+//! [`cross`] is 3D only.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::vec::{cross, distance, dot, normalize, sub};
+//! let a = [1.0, 2.0, 2.0];
+//! let b = [4.0, 6.0, 2.0];
+//! assert_eq!(distance(a, b), 5.0);
+//! assert_eq!(normalize(sub(b, a)), [0.6, 0.8, 0.0]);
+//! assert_eq!(cross([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+//! assert_eq!(dot([1.0, 2.0], [3.0, 4.0]), 11.0);
+//! ```
+
+/// Component-wise `a + b`.
+pub fn add<const N: usize>(a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+/// Component-wise `a - b`.
+pub fn sub<const N: usize>(a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+/// Component-wise `a * b`.
+pub fn mul<const N: usize>(a: [f32; N], b: [f32; N]) -> [f32; N] {
+    std::array::from_fn(|i| a[i] * b[i])
+}
+
+/// `a` multiplied by the scalar `s`.
+pub fn scale<const N: usize>(a: [f32; N], s: f32) -> [f32; N] {
+    a.map(|v| v * s)
+}
+
+/// `-a`.
+pub fn negate<const N: usize>(a: [f32; N]) -> [f32; N] {
+    a.map(|v| -v)
+}
+
+/// Dot product.
+pub fn dot<const N: usize>(a: [f32; N], b: [f32; N]) -> f32 {
+    a.iter().zip(b.iter()).map(|(a, b)| a * b).sum()
+}
+
+/// Cross product `a x b` (right-handed).
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+/// Squared length; cheaper than [`length`] for comparisons.
+pub fn length_squared<const N: usize>(a: [f32; N]) -> f32 {
+    dot(a, a)
+}
+
+/// Euclidean length.
+pub fn length<const N: usize>(a: [f32; N]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// `a` scaled to unit length, or the zero vector if `a` is (nearly) zero.
+pub fn normalize<const N: usize>(a: [f32; N]) -> [f32; N] {
+    normalize_or(a, [0.0; N])
+}
+
+/// `a` scaled to unit length, or `fallback` if `a` is (nearly) zero.
+pub fn normalize_or<const N: usize>(a: [f32; N], fallback: [f32; N]) -> [f32; N] {
+    let len = length(a);
+    if len > f32::EPSILON { scale(a, 1.0 / len) } else { fallback }
+}
+
+/// Linear interpolation from `a` (`t = 0`) to `b` (`t = 1`).
+pub fn lerp<const N: usize>(a: [f32; N], b: [f32; N], t: f32) -> [f32; N] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+/// Squared distance between two points.
+pub fn distance_squared<const N: usize>(a: [f32; N], b: [f32; N]) -> f32 {
+    length_squared(sub(a, b))
+}
+
+/// Distance between two points.
+pub fn distance<const N: usize>(a: [f32; N], b: [f32; N]) -> f32 {
+    length(sub(a, b))
+}
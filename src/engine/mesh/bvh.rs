@@ -0,0 +1,226 @@
+//! Bounding volume hierarchies over mesh triangles, for raycasts.
+//!
+//! Testing a ray against every triangle of a mesh costs a test per triangle. A [`MeshBvh`]
+//! groups the triangles into a binary tree of boxes, split at the median along the longest
+//! axis of their centers, so a raycast only descends into boxes the ray passes through and
+//! visits the nearer child first: once a hit is found, farther boxes are skipped. Building
+//! takes `O(n log n)` for `n` triangles, and a raycast typically tests a few dozen.
+//!
+//! The hierarchy stores triangle numbers, not positions, and is only valid for the geometry
+//! it was built from. [`MeshBvh::shared`] keeps one hierarchy per geometry handle for as long
+//! as the geometry lives, so every node drawing a shared `Rc<Geometry>` (and every raycast
+//! for picking, placing decals or line of sight) uses the same one. Nodes look theirs up
+//! when their geometry is [set](crate::engine::object3d::Object3D::set_geometry), so it is
+//! built while loading rather than on the first
+//! [scene raycast](crate::engine::scene::Scene::raycast).
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{math::ray::Ray, mesh::bvh::MeshBvh};
+//! # use rustge::engine::object3d::{Geometry, Topology};
+//! let quad = Geometry::from_positions(Topology::Triangles, &[
+//!     [-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [1.0, 1.0, 0.0],
+//!     [-1.0, -1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0],
+//! ]);
+//! let bvh = MeshBvh::build(&quad).unwrap();
+//!
+//! let ray = Ray::new([-0.5, 0.5, 3.0], [0.0, 0.0, -1.0]);
+//! assert_eq!(bvh.raycast(&quad, &ray, f32::INFINITY), Some((3.0, 1)));
+//! assert_eq!(bvh.raycast(&quad, &ray, 2.0), None);
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::ray::Ray;
+use crate::engine::object3d::{Geometry, Topology};
+
+/// Most triangles in a leaf; larger ranges are split.
+const LEAF_SIZE: usize = 4;
+
+/// A geometry's hierarchy, `None` for geometry without triangles, with a weak handle that
+/// tells the live geometry from a new one at a reused address.
+type BvhEntry = (Weak<Geometry>, Option<Rc<MeshBvh>>);
+
+/// Fewest entries the cache holds before dropped geometries' entries are pruned.
+const MIN_PRUNE_SIZE: usize = 64;
+
+/// Hierarchies by the address of the geometry they were built for. Entries of dropped
+/// geometry are pruned whenever the cache has doubled since the last prune, so inserting
+/// stays cheap on average.
+#[derive(Debug, Default)]
+struct BvhCache {
+    entries: HashMap<*const Geometry, BvhEntry>,
+    /// Size at which the next insert prunes first.
+    prune_at: usize,
+}
+
+thread_local! {
+    static BVH_CACHE: RefCell<BvhCache> = RefCell::new(BvhCache::default());
+}
+
+/// Number of geometries with a live entry in the [shared](MeshBvh::shared) hierarchy cache.
+pub fn cached_bvh_count() -> usize {
+    BVH_CACHE.with(|cache| cache.borrow().entries.values().filter(|(geometry, _)| geometry.strong_count() > 0).count())
+}
+
+/// A box over a range of triangles: a leaf, or the parent of the next node and `second`.
+#[derive(Clone, Debug)]
+struct BvhNode {
+    bounds: Aabb,
+    /// Start of the node's range in [`MeshBvh::triangles`].
+    first: u32,
+    /// Number of triangles for a leaf, 0 for an inner node.
+    count: u32,
+    /// Index of an inner node's second child; the first follows the node directly.
+    second: u32,
+}
+
+/// A bounding volume hierarchy over the triangles of a [`Geometry`]; see the
+/// [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct MeshBvh {
+    /// Nodes in depth-first order, the root first.
+    nodes: Vec<BvhNode>,
+    /// Triangle numbers (index of the triangle's first index divided by 3), grouped by leaf.
+    triangles: Vec<u32>,
+}
+
+impl MeshBvh {
+    /// Builds the hierarchy over `geometry`'s triangles. Returns `None` for other
+    /// topologies and geometry without triangles; triangles with out-of-range indices are
+    /// left out.
+    pub fn build(geometry: &Geometry) -> Option<Self> {
+        if geometry.topology != Topology::Triangles {
+            return None;
+        }
+        let mut triangles = Vec::new();
+        let mut boxes = Vec::new();
+        for triangle in 0..geometry.indices.len() / 3 {
+            let corners = [0, 1, 2].map(|corner| geometry.indices.get(triangle * 3 + corner) as usize);
+            if corners.iter().any(|&i| i >= geometry.vertices.len()) {
+                continue;
+            }
+            let bounds = Aabb::from_points(corners.map(|i| geometry.vertices[i].position))?;
+            triangles.push(triangle as u32);
+            boxes.push((bounds, bounds.center()));
+        }
+        if triangles.is_empty() {
+            return None;
+        }
+        let mut bvh = Self { nodes: Vec::with_capacity(2 * triangles.len() / LEAF_SIZE + 1), triangles };
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        bvh.split(&boxes, &mut order, 0);
+        bvh.triangles = order.iter().map(|&i| bvh.triangles[i]).collect();
+        Some(bvh)
+    }
+
+    /// Appends the subtree over `order` (a non-empty range starting at `first` of the final
+    /// order), which it sorts so each child's triangles are contiguous.
+    fn split(&mut self, boxes: &[(Aabb, [f32; 3])], order: &mut [usize], first: usize) {
+        let bounds = order[1..].iter().fold(boxes[order[0]].0, |bounds, &i| bounds.union(&boxes[i].0));
+        let node = self.nodes.len();
+        self.nodes.push(BvhNode { bounds, first: first as u32, count: order.len() as u32, second: 0 });
+        if order.len() <= LEAF_SIZE {
+            return;
+        }
+        let centers = Aabb::from_points(order.iter().map(|&i| boxes[i].1)).unwrap_or(bounds);
+        let extent = centers.size();
+        let axis = (0..3).fold(0, |best, axis| if extent[axis] > extent[best] { axis } else { best });
+        if extent[axis] <= 0.0 {
+            // Every center coincides: no split separates them
+            return;
+        }
+        let middle = order.len() / 2;
+        order.select_nth_unstable_by(middle, |&a, &b| boxes[a].1[axis].total_cmp(&boxes[b].1[axis]));
+        self.nodes[node].count = 0;
+        let (near, far) = order.split_at_mut(middle);
+        self.split(boxes, near, first);
+        self.nodes[node].second = self.nodes.len() as u32;
+        self.split(boxes, far, first + middle);
+    }
+
+    /// The nearest triangle of `geometry` hit by `ray` within `max_distance` (in units of
+    /// the ray's direction), as the hit's `t` and the triangle number: the triangle's
+    /// corners are indices `3 * n` to `3 * n + 2`. Triangles are hit from either side.
+    /// `geometry` must be the geometry the hierarchy was built from.
+    pub fn raycast(&self, geometry: &Geometry, ray: &Ray, max_distance: f32) -> Option<(f32, usize)> {
+        let mut nearest: Option<(f32, usize)> = None;
+        let mut limit = max_distance;
+        let mut stack = vec![0usize];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if ray.intersect_aabb(&node.bounds).is_none_or(|t| t > limit) {
+                continue;
+            }
+            if node.count > 0 {
+                let range = node.first as usize..(node.first + node.count) as usize;
+                for &triangle in &self.triangles[range] {
+                    let [a, b, c] = [0, 1, 2].map(|corner| {
+                        geometry.vertices[geometry.indices.get(triangle as usize * 3 + corner) as usize].position
+                    });
+                    if let Some(t) = ray.intersect_triangle(a, b, c)
+                        && t <= limit
+                    {
+                        limit = t;
+                        nearest = Some((t, triangle as usize));
+                    }
+                }
+                continue;
+            }
+            // Visit the child the ray enters first first, so the other is more often skipped
+            let (first, second) = (index + 1, node.second as usize);
+            let entry = |child: usize| ray.intersect_aabb(&self.nodes[child].bounds).unwrap_or(f32::INFINITY);
+            if entry(first) <= entry(second) {
+                stack.extend([second, first]);
+            } else {
+                stack.extend([first, second]);
+            }
+        }
+        nearest
+    }
+
+    /// The hierarchy of `geometry`, built on the first call for the geometry and kept until it
+    /// is dropped. `None` for geometry without triangles, like [`build`](Self::build).
+    ///
+    /// ```
+    /// # use std::rc::Rc;
+    /// # use rustge::engine::{mesh::bvh::MeshBvh, object3d::{Geometry, Topology}};
+    /// let triangle = Rc::new(Geometry::from_positions(Topology::Triangles, &[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]));
+    /// let first = MeshBvh::shared(&triangle).unwrap();
+    /// assert!(Rc::ptr_eq(&first, &MeshBvh::shared(&triangle).unwrap()));
+    /// ```
+    pub fn shared(geometry: &Rc<Geometry>) -> Option<Rc<MeshBvh>> {
+        let key = Rc::as_ptr(geometry);
+        let cached = BVH_CACHE.with(|cache| {
+            let cache = cache.borrow();
+            let (handle, bvh) = cache.entries.get(&key)?;
+            handle.upgrade().is_some_and(|live| Rc::ptr_eq(&live, geometry)).then(|| bvh.clone())
+        });
+        if let Some(bvh) = cached {
+            return bvh;
+        }
+
+        let bvh = Self::build(geometry).map(Rc::new);
+        BVH_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.entries.len() >= cache.prune_at {
+                cache.entries.retain(|_, (handle, _)| handle.strong_count() > 0);
+                cache.prune_at = (cache.entries.len() * 2).max(MIN_PRUNE_SIZE);
+            }
+            cache.entries.insert(key, (Rc::downgrade(geometry), bvh.clone()));
+        });
+        bvh
+    }
+
+    /// The box around every triangle in the hierarchy.
+    pub fn bounds(&self) -> Aabb {
+        self.nodes[0].bounds
+    }
+
+    /// Number of triangles in the hierarchy.
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+}
@@ -0,0 +1,310 @@
+//! GPU-side meshes shared between nodes.
+//!
+//! Uploading a [`Geometry`] creates a [`GpuMesh`] (vertex array, vertex buffer and index
+//! buffer). Nodes drawing the same geometry, whether through a shared `Rc<Geometry>` (see
+//! [`Object3D::set_shared_geometry`](crate::engine::object3d::Object3D::set_shared_geometry))
+//! or separate copies with identical contents, reuse one upload through [`GpuMesh::shared`]:
+//! a thread-local cache keyed by the geometry's contents hands out `Rc<GpuMesh>`s, and the
+//! buffers are freed when the last node drops its reference.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::{Rc, Weak};
+use gl::types::{GLenum, GLsizei, GLsizeiptr, GLuint};
+use crate::engine::debug::gl_debug::label_object;
+use crate::engine::object3d::{Geometry, SkinVertex, Vertex};
+use crate::engine::stats::{record_gpu_upload, release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Vertex attribute location of [`SkinVertex::joints`].
+pub const SKIN_JOINTS_LOCATION: GLuint = 7;
+
+/// Vertex attribute location of [`SkinVertex::weights`].
+pub const SKIN_WEIGHTS_LOCATION: GLuint = 8;
+
+/// A cached upload and the geometry it was created from.
+struct CacheEntry {
+    geometry: Rc<Geometry>,
+    mesh: Weak<GpuMesh>,
+}
+
+thread_local! {
+    /// Live GPU meshes by content hash. Collisions are resolved by comparing geometry.
+    static MESH_CACHE: RefCell<HashMap<u64, Vec<CacheEntry>>> = RefCell::new(HashMap::new());
+}
+
+/// Number of distinct geometries currently uploaded through [`GpuMesh::shared`].
+pub fn cached_mesh_count() -> usize {
+    MESH_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .values()
+            .flatten()
+            .filter(|entry| entry.mesh.strong_count() > 0)
+            .count()
+    })
+}
+
+/// Hash of everything that ends up in the GPU buffers.
+fn content_hash(geometry: &Geometry) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    geometry.topology.hash(&mut hasher);
+    geometry.indices.hash(&mut hasher);
+    for vertex in &geometry.vertices {
+        for value in vertex.position.iter().chain(&vertex.normal).chain(&vertex.uv) {
+            value.to_bits().hash(&mut hasher);
+        }
+    }
+    for skin in geometry.skin.iter().flatten() {
+        skin.joints.hash(&mut hasher);
+        for weight in skin.weights {
+            weight.to_bits().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// A geometry uploaded to the GPU (VAO, VBO and IBO). Created on demand by
+/// [`GpuMesh::shared`] and shared by every node drawing the same geometry.
+#[derive(Debug)]
+pub struct GpuMesh {
+    pub vao: GLuint,
+    pub vbo: GLuint,
+    pub ibo: GLuint,
+    /// Buffer of [`SkinVertex`] data, 0 for rigid geometry.
+    pub skin_vbo: GLuint,
+    pub index_count: usize,
+    /// GL type of the indices (`GL_UNSIGNED_SHORT` or `GL_UNSIGNED_INT`).
+    pub index_type: GLenum,
+    /// OpenGL primitive mode (`GL_TRIANGLES`, `GL_LINES`, ...) matching the geometry's topology.
+    pub mode: GLenum,
+    /// Size of the vertex and index buffers together.
+    pub bytes: usize,
+    /// Size of the vertex buffer, which [`update_vertices`](Self::update_vertices) rewrites.
+    vertex_bytes: usize,
+    /// Content hash the mesh is cached under, if it is in the cache.
+    cache_key: Option<u64>,
+}
+
+impl GpuMesh {
+    /// Returns the GPU mesh for `geometry`, uploading it only if no live mesh was created
+    /// from the same geometry (the same `Rc`, or equal contents) yet.
+    ///
+    /// The mesh stays cached for as long as some node holds it. Requires a current GL context.
+    pub fn shared(geometry: &Rc<Geometry>, label: &str) -> Rc<GpuMesh> {
+        let key = content_hash(geometry);
+        let cached = MESH_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let entries = cache.entry(key).or_default();
+            entries.retain(|entry| entry.mesh.strong_count() > 0);
+            entries
+                .iter()
+                .find(|entry| Rc::ptr_eq(&entry.geometry, geometry) || *entry.geometry == **geometry)
+                .and_then(|entry| entry.mesh.upgrade())
+        });
+        if let Some(mesh) = cached {
+            return mesh;
+        }
+
+        Self::from_geometry(geometry, label).cache(geometry, key)
+    }
+
+    /// Uploads the geometry's vertex and index buffers to the GPU and configures a VAO.
+    ///
+    /// Attribute layout matches [`Vertex`]:
+    /// - location 0: position (vec3)
+    /// - location 1: normal (vec3)
+    /// - location 2: uv (vec2)
+    ///
+    /// and, for skinned geometry, [`SkinVertex`] from a second buffer:
+    /// - location 7: joint indices (uvec4)
+    /// - location 8: joint weights (vec4)
+    ///
+    /// Both buffers are registered with the GPU memory registry under `label`. The mesh is
+    /// private to the caller; use [`shared`](Self::shared) to reuse uploads.
+    pub fn from_geometry(geometry: &Geometry, label: &str) -> Self {
+        let [vertices, indices, skin] = geometry_bytes(geometry);
+        Self::from_buffers(geometry, upload_buffers(vertices, indices, skin, gl::STATIC_DRAW), label)
+    }
+
+    /// Uploads the geometry like [`from_geometry`](Self::from_geometry), into buffers meant
+    /// to be rewritten every frame by [`update_vertices`](Self::update_vertices), e.g. for
+    /// simulated cloth. Dynamic meshes are never shared.
+    pub fn dynamic(geometry: &Geometry, label: &str) -> Self {
+        let [vertices, indices, skin] = geometry_bytes(geometry);
+        Self::from_buffers(geometry, upload_buffers(vertices, indices, skin, gl::DYNAMIC_DRAW), label)
+    }
+
+    /// Overwrites the vertex buffer in place with `vertices`, without reallocating it or
+    /// touching the index and skin buffers. Requires a current GL context.
+    ///
+    /// # Panics
+    /// Panics if `vertices` isn't as many vertices as the mesh was created with.
+    pub fn update_vertices(&self, vertices: &[Vertex]) {
+        let bytes = std::mem::size_of_val(vertices);
+        assert_eq!(bytes, self.vertex_bytes, "vertex count of a mesh update must not change");
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, bytes as GLsizeiptr, vertices.as_ptr() as *const _);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        record_gpu_upload(bytes);
+    }
+
+    /// Configures a VAO over the vertex, index and skin buffers `buffers` of `geometry`,
+    /// filled by [`upload_buffers`] (possibly on the [upload context](crate::engine::upload)),
+    /// taking ownership of them.
+    pub(crate) fn from_buffers(geometry: &Geometry, buffers: [GLuint; 3], label: &str) -> Self {
+        let [vbo, ibo, skin_vbo] = buffers;
+        let [vertex_bytes, index_bytes, skin_bytes] = geometry_bytes(geometry).map(<[u8]>::len);
+        let stride = std::mem::size_of::<Vertex>() as GLsizei;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo);
+
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, position) as *const _);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, normal) as *const _);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, std::mem::offset_of!(Vertex, uv) as *const _);
+
+            if skin_vbo != 0 {
+                gl::BindBuffer(gl::ARRAY_BUFFER, skin_vbo);
+                let stride = std::mem::size_of::<SkinVertex>() as GLsizei;
+                gl::EnableVertexAttribArray(SKIN_JOINTS_LOCATION);
+                gl::VertexAttribIPointer(
+                    SKIN_JOINTS_LOCATION,
+                    4,
+                    gl::UNSIGNED_SHORT,
+                    stride,
+                    std::mem::offset_of!(SkinVertex, joints) as *const _,
+                );
+                gl::EnableVertexAttribArray(SKIN_WEIGHTS_LOCATION);
+                gl::VertexAttribPointer(
+                    SKIN_WEIGHTS_LOCATION,
+                    4,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    stride,
+                    std::mem::offset_of!(SkinVertex, weights) as *const _,
+                );
+            }
+
+            // The element buffer binding is part of VAO state, so unbind the VAO first
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+
+        label_object(gl::VERTEX_ARRAY, vao, label);
+        track_gpu_allocation(GpuResourceKind::VertexBuffer, vbo, vertex_bytes, &format!("{label} vertices"));
+        track_gpu_allocation(GpuResourceKind::IndexBuffer, ibo, index_bytes, &format!("{label} indices"));
+        if skin_vbo != 0 {
+            track_gpu_allocation(GpuResourceKind::VertexBuffer, skin_vbo, skin_bytes, &format!("{label} skin"));
+        }
+
+        Self {
+            vao,
+            vbo,
+            ibo,
+            skin_vbo,
+            index_count: geometry.indices.len(),
+            index_type: geometry.indices.gl_type(),
+            mode: geometry.topology.gl_mode(),
+            bytes: vertex_bytes + index_bytes + skin_bytes,
+            vertex_bytes,
+            cache_key: None,
+        }
+    }
+
+    /// Puts a mesh uploaded from `geometry` into the cache [`shared`](Self::shared) looks in,
+    /// for as long as it is alive.
+    pub(crate) fn into_shared(self, geometry: &Rc<Geometry>) -> Rc<GpuMesh> {
+        self.cache(geometry, content_hash(geometry))
+    }
+
+    /// Caches the mesh under content hash `key`.
+    fn cache(mut self, geometry: &Rc<Geometry>, key: u64) -> Rc<GpuMesh> {
+        self.cache_key = Some(key);
+        let mesh = Rc::new(self);
+        MESH_CACHE.with(|cache| {
+            cache.borrow_mut().entry(key).or_default().push(CacheEntry {
+                geometry: geometry.clone(),
+                mesh: Rc::downgrade(&mesh),
+            });
+        });
+        mesh
+    }
+}
+
+/// The vertex, index and skin data of `geometry` as bytes; the skin data is empty for rigid
+/// geometry.
+pub(crate) fn geometry_bytes(geometry: &Geometry) -> [&[u8]; 3] {
+    // Vertices and skin vertices are plain floats and integers without padding
+    let vertices = unsafe {
+        std::slice::from_raw_parts(geometry.vertices.as_ptr() as *const u8, std::mem::size_of_val(geometry.vertices.as_slice()))
+    };
+    let indices = unsafe { std::slice::from_raw_parts(geometry.indices.as_ptr() as *const u8, geometry.indices.byte_size()) };
+    let skin = geometry.skin.as_deref().map_or(&[][..], |skin| unsafe {
+        std::slice::from_raw_parts(skin.as_ptr() as *const u8, std::mem::size_of_val(skin))
+    });
+    [vertices, indices, skin]
+}
+
+/// Creates and fills the vertex, index and (unless `skin` is empty) skin buffers of a mesh,
+/// with the usage hint `usage` (`GL_STATIC_DRAW`, `GL_DYNAMIC_DRAW`, ...) in the context
+/// current on this thread, and returns their names; 0 for no skin buffer.
+pub(crate) fn upload_buffers(vertices: &[u8], indices: &[u8], skin: &[u8], usage: GLenum) -> [GLuint; 3] {
+    let upload = |data: &[u8]| {
+        let mut buffer = 0;
+        unsafe {
+            // Filled through the array buffer target: element buffers only bind with a VAO
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer);
+            gl::BufferData(gl::ARRAY_BUFFER, data.len() as GLsizeiptr, data.as_ptr() as *const _, usage);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        buffer
+    };
+    let skin_vbo = if skin.is_empty() { 0 } else { upload(skin) };
+    [upload(vertices), upload(indices), skin_vbo]
+}
+
+impl Drop for GpuMesh {
+    fn drop(&mut self) {
+        // Forget the cache entry (and the geometry it keeps alive). The cache may already be
+        // gone when meshes are dropped during thread shutdown.
+        if let Some(key) = self.cache_key {
+            let _ = MESH_CACHE.try_with(|cache| {
+                if let Ok(mut cache) = cache.try_borrow_mut()
+                    && let Some(entries) = cache.get_mut(&key)
+                {
+                    entries.retain(|entry| entry.mesh.strong_count() > 0);
+                    if entries.is_empty() {
+                        cache.remove(&key);
+                    }
+                }
+            });
+        }
+
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        release_gpu_allocation(GpuResourceKind::IndexBuffer, self.ibo);
+        if self.skin_vbo != 0 {
+            release_gpu_allocation(GpuResourceKind::VertexBuffer, self.skin_vbo);
+        }
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ibo);
+            if self.skin_vbo != 0 {
+                gl::DeleteBuffers(1, &self.skin_vbo);
+            }
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
@@ -0,0 +1,171 @@
+//! Convex hulls of meshes.
+//!
+//! [`convex_hull`] wraps a [`Geometry`]'s vertices in the smallest convex triangle mesh
+//! containing them (quickhull): start from a tetrahedron of extreme points, then repeatedly
+//! take the point farthest outside a face, remove every face it sees and close the hole
+//! with a fan of faces to it. Points inside the hull are never visited again, so typical
+//! meshes of `n` vertices take about `O(n log n)`.
+//!
+//! Hulls are a cheap stand-in for a detailed mesh: a tight proxy for collision queries
+//! and [fitting primitives](crate::engine::physics::fit), or a convex shape to export to
+//! tools that need one. Faces wind counter-clockwise seen from outside, and the hull only
+//! uses the input's corner vertices, shared between its faces.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::mesh::hull::convex_hull;
+//! # use rustge::engine::object3d::{Geometry, Topology};
+//! // The corners of a cube plus its center, which the hull leaves out
+//! let mut points: Vec<[f32; 3]> = (0..8).map(|i| [i & 1, (i >> 1) & 1, (i >> 2) & 1].map(|c| c as f32)).collect();
+//! points.push([0.5; 3]);
+//! let hull = convex_hull(&Geometry::from_positions(Topology::Points, &points)).unwrap();
+//!
+//! assert_eq!(hull.vertices.len(), 8);
+//! assert_eq!(hull.indices.len(), 12 * 3);
+//! ```
+
+use std::collections::HashSet;
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::vec::{add, cross, distance_squared, dot, length, normalize_or, scale, sub};
+use crate::engine::object3d::{Geometry, Index, Indices, Topology, Vertex};
+
+/// Tolerance of the outside test, relative to the size of the input.
+const RELATIVE_EPSILON: f32 = 1e-5;
+
+/// A hull face during construction.
+struct Face {
+    /// Corners, counter-clockwise seen from outside.
+    corners: [usize; 3],
+    /// Outward unit normal.
+    normal: [f32; 3],
+    /// Distance of the face's plane from the origin along `normal`.
+    offset: f32,
+    /// Points outside the face that no earlier face claimed.
+    outside: Vec<usize>,
+    /// Whether the face is still part of the hull.
+    alive: bool,
+}
+
+impl Face {
+    /// The face through `corners`, turned away from `inside`.
+    fn new(points: &[[f32; 3]], corners: [usize; 3], inside: [f32; 3]) -> Self {
+        let [a, b, c] = corners.map(|i| points[i]);
+        let mut normal = normalize_or(cross(sub(b, a), sub(c, a)), [0.0, 1.0, 0.0]);
+        let mut corners = corners;
+        if dot(normal, sub(inside, a)) > 0.0 {
+            normal = scale(normal, -1.0);
+            corners.swap(1, 2);
+        }
+        Self { corners, normal, offset: dot(normal, a), outside: Vec::new(), alive: true }
+    }
+
+    /// Signed distance of `point` above the face's plane.
+    fn height(&self, point: [f32; 3]) -> f32 {
+        dot(self.normal, point) - self.offset
+    }
+}
+
+/// The convex hull of `geometry`'s vertex positions as a triangle mesh; see the
+/// [module documentation](self). Every topology is accepted, only the vertices matter.
+/// Normals are smooth and UVs zero. Returns `None` when the points don't span a volume
+/// (fewer than four, or all on a plane).
+pub fn convex_hull(geometry: &Geometry) -> Option<Geometry> {
+    let points: Vec<[f32; 3]> = geometry.vertices.iter().map(|vertex| vertex.position).collect();
+    let bounds = Aabb::from_points(points.iter().copied())?;
+    let epsilon = RELATIVE_EPSILON * length(bounds.size()).max(f32::MIN_POSITIVE);
+
+    let initial = initial_tetrahedron(&points, epsilon)?;
+    let inside = scale(initial.iter().fold([0.0; 3], |sum, &i| add(sum, points[i])), 0.25);
+    let mut faces: Vec<Face> = [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]]
+        .iter()
+        .map(|corners| Face::new(&points, corners.map(|corner| initial[corner]), inside))
+        .collect();
+    let unassigned: Vec<usize> = (0..points.len()).filter(|i| !initial.contains(i)).collect();
+    assign_outside(&points, &mut faces, 0, unassigned, epsilon);
+
+    while let Some(face) = faces.iter().position(|face| face.alive && !face.outside.is_empty()) {
+        let eye = *faces[face].outside.iter().max_by(|&&a, &&b| {
+            faces[face].height(points[a]).total_cmp(&faces[face].height(points[b]))
+        })?;
+        // Every face the eye sees; they form one connected patch of a convex hull
+        let visible: Vec<usize> = (0..faces.len())
+            .filter(|&i| faces[i].alive && faces[i].height(points[eye]) > epsilon)
+            .collect();
+        let edges: HashSet<(usize, usize)> = visible
+            .iter()
+            .flat_map(|&i| {
+                let [a, b, c] = faces[i].corners;
+                [(a, b), (b, c), (c, a)]
+            })
+            .collect();
+        // The horizon: edges of the patch whose other face stays
+        let horizon: Vec<(usize, usize)> = edges.iter().copied().filter(|&(a, b)| !edges.contains(&(b, a))).collect();
+
+        let mut orphans = Vec::new();
+        for &i in &visible {
+            faces[i].alive = false;
+            orphans.append(&mut faces[i].outside);
+        }
+        let first_new = faces.len();
+        for (a, b) in horizon {
+            faces.push(Face::new(&points, [a, b, eye], inside));
+        }
+        orphans.retain(|&point| point != eye);
+        assign_outside(&points, &mut faces, first_new, orphans, epsilon);
+    }
+
+    let mut remap = vec![None; points.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for face in faces.iter().filter(|face| face.alive) {
+        for corner in face.corners {
+            let index = *remap[corner].get_or_insert_with(|| {
+                vertices.push(Vertex { position: points[corner], normal: [0.0, 1.0, 0.0], uv: [0.0, 0.0] });
+                (vertices.len() - 1) as Index
+            });
+            indices.push(index);
+        }
+    }
+    let mut hull = Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None };
+    hull.compute_normals();
+    Some(hull)
+}
+
+/// Four points spanning the largest volume found among extremes, or `None` if all points
+/// lie within `epsilon` of a plane.
+fn initial_tetrahedron(points: &[[f32; 3]], epsilon: f32) -> Option<[usize; 4]> {
+    // The farthest apart pair among the extremes along each axis
+    let extremes: Vec<usize> = (0..3)
+        .flat_map(|axis| {
+            let along = |&a: &usize, &b: &usize| points[a][axis].total_cmp(&points[b][axis]);
+            [(0..points.len()).min_by(along), (0..points.len()).max_by(along)]
+        })
+        .flatten()
+        .collect();
+    let (a, b) = extremes
+        .iter()
+        .flat_map(|&a| extremes.iter().map(move |&b| (a, b)))
+        .max_by(|&(a, b), &(c, d)| {
+            distance_squared(points[a], points[b]).total_cmp(&distance_squared(points[c], points[d]))
+        })?;
+    let line = sub(points[b], points[a]);
+    let off_line = |&i: &usize| length(cross(line, sub(points[i], points[a])));
+    let c = (0..points.len()).max_by(|i, j| off_line(i).total_cmp(&off_line(j)))?;
+    if off_line(&c) <= epsilon * length(line) {
+        return None;
+    }
+    let plane = normalize_or(cross(line, sub(points[c], points[a])), [0.0, 1.0, 0.0]);
+    let off_plane = |&i: &usize| dot(plane, sub(points[i], points[a])).abs();
+    let d = (0..points.len()).max_by(|i, j| off_plane(i).total_cmp(&off_plane(j)))?;
+    (off_plane(&d) > epsilon).then_some([a, b, c, d])
+}
+
+/// Gives each of `candidates` to the first face from `first_face` on that it is more than
+/// `epsilon` above; the others are inside the hull.
+fn assign_outside(points: &[[f32; 3]], faces: &mut [Face], first_face: usize, candidates: Vec<usize>, epsilon: f32) {
+    for point in candidates {
+        if let Some(face) = faces[first_face..].iter_mut().find(|face| face.height(points[point]) > epsilon) {
+            face.outside.push(point);
+        }
+    }
+}
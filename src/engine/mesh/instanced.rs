@@ -0,0 +1,294 @@
+//! Hardware instancing for repeated geometry.
+//!
+//! Drawing thousands of trees or rocks as individual nodes costs one draw call (and one
+//! scene-graph node) each. An [`InstancedMesh`] attached to a node with
+//! [`Object3D::set_instances`](crate::engine::object3d::Object3D::set_instances) instead
+//! draws the node's geometry once per instance transform with a single
+//! `glDrawElementsInstanced` call.
+//!
+//! Instance matrices are relative to the node, so moving the node moves every instance.
+//! They are stored in a per-instance vertex buffer bound at attribute locations 3 to 6
+//! (one `vec4` column each); shaders opt in with
+//!
+//! ```glsl
+//! layout(location = 3) in mat4 a_instance_matrix;
+//! uniform int u_instanced;
+//! // ...
+//! mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+//! ```
+//!
+//! as the built-in Phong shader does.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::mesh::instanced::InstancedMesh;
+//! # use rustge::engine::object3d::Object3D;
+//! let forest = Object3D::new();
+//! let mut trees = InstancedMesh::new();
+//! for i in 0..1000 {
+//!     let (x, z) = ((i % 40) as f32 * 3.0, (i / 40) as f32 * 3.0);
+//!     trees.add_instance_trs([x, 0.0, z], [0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0]);
+//! }
+//! forest.borrow_mut().set_instances(Some(trees));
+//! ```
+
+use std::cell::{Cell, OnceCell};
+use std::collections::HashMap;
+use gl::types::{GLsizei, GLsizeiptr, GLuint};
+use crate::engine::math::matrixfuncs::{compute_local_matrix, transform_point};
+use crate::engine::math::vec::{distance, length};
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// First vertex attribute location of the per-instance matrix (columns use 3 to 6).
+pub const INSTANCE_MATRIX_LOCATION: GLuint = 3;
+
+/// Identifies an instance of an [`InstancedMesh`]. Ids stay valid when other instances
+/// are removed and are never reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InstanceId(u32);
+
+/// Geometry bounds and the instance bounds computed from them, both as (center, radius).
+type BoundsCache = (([f32; 3], f32), ([f32; 3], f32));
+
+/// A set of per-instance transforms drawn with a node's geometry in one draw call.
+///
+/// ```
+/// # use rustge::engine::mesh::instanced::InstancedMesh;
+/// let mut rocks = InstancedMesh::new();
+/// let a = rocks.add_instance_trs([1.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], [1.0; 3]);
+/// let b = rocks.add_instance_trs([2.0, 0.0, 0.0], [0.0, 0.0, 0.0, 1.0], [1.0; 3]);
+/// assert!(rocks.remove_instance(a));
+/// assert!(!rocks.remove_instance(a));
+/// assert_eq!(rocks.instance(b).map(|m| m[12]), Some(2.0));
+/// assert_eq!(rocks.len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct InstancedMesh {
+    /// Instance matrices, densely packed in upload order.
+    matrices: Vec<[f32; 16]>,
+
+    /// Id of the instance at each index of `matrices`.
+    ids: Vec<InstanceId>,
+
+    /// Index into `matrices` for each live id.
+    indices: HashMap<InstanceId, usize>,
+
+    /// Next id to hand out.
+    next_id: u32,
+
+    /// Whether `matrices` changed since the last upload.
+    dirty: Cell<bool>,
+
+    /// Cached enclosing sphere, with the geometry bounds it was computed for.
+    bounds: Cell<Option<BoundsCache>>,
+
+    /// Per-instance vertex buffer, created on first draw.
+    buffer: OnceCell<InstanceBuffer>,
+}
+
+impl InstancedMesh {
+    /// An empty instance set. GL resources are created on the first draw.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of instances.
+    pub fn len(&self) -> usize {
+        self.matrices.len()
+    }
+
+    /// Returns `true` if there are no instances, in which case nothing is drawn.
+    pub fn is_empty(&self) -> bool {
+        self.matrices.is_empty()
+    }
+
+    /// Adds an instance with the given node-relative transform matrix (column-major).
+    pub fn add_instance(&mut self, matrix: [f32; 16]) -> InstanceId {
+        let id = InstanceId(self.next_id);
+        self.next_id += 1;
+        self.indices.insert(id, self.matrices.len());
+        self.matrices.push(matrix);
+        self.ids.push(id);
+        self.invalidate();
+        id
+    }
+
+    /// Adds an instance from a position, rotation quaternion `[x, y, z, w]` and scale.
+    pub fn add_instance_trs(&mut self, position: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> InstanceId {
+        self.add_instance(compute_local_matrix(position, rotation, scale))
+    }
+
+    /// The transform of instance `id`, or `None` if it was removed.
+    pub fn instance(&self, id: InstanceId) -> Option<[f32; 16]> {
+        self.indices.get(&id).map(|&index| self.matrices[index])
+    }
+
+    /// Replaces the transform of instance `id`. Returns `false` if it was removed.
+    pub fn set_instance(&mut self, id: InstanceId, matrix: [f32; 16]) -> bool {
+        let Some(&index) = self.indices.get(&id) else {
+            return false;
+        };
+        self.matrices[index] = matrix;
+        self.invalidate();
+        true
+    }
+
+    /// Replaces the transform of instance `id` from a position, rotation and scale.
+    /// Returns `false` if it was removed.
+    pub fn set_instance_trs(&mut self, id: InstanceId, position: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> bool {
+        self.set_instance(id, compute_local_matrix(position, rotation, scale))
+    }
+
+    /// Removes instance `id`. Returns `false` if it was already removed.
+    ///
+    /// The last instance takes the removed one's slot, so draw order is not preserved.
+    pub fn remove_instance(&mut self, id: InstanceId) -> bool {
+        let Some(index) = self.indices.remove(&id) else {
+            return false;
+        };
+        self.matrices.swap_remove(index);
+        self.ids.swap_remove(index);
+        if let Some(&moved) = self.ids.get(index) {
+            self.indices.insert(moved, index);
+        }
+        self.invalidate();
+        true
+    }
+
+    /// Removes every instance.
+    pub fn clear(&mut self) {
+        self.matrices.clear();
+        self.ids.clear();
+        self.indices.clear();
+        self.invalidate();
+    }
+
+    /// Iterates over the live instances and their transforms.
+    pub fn iter(&self) -> impl Iterator<Item = (InstanceId, &[f32; 16])> {
+        self.ids.iter().copied().zip(self.matrices.iter())
+    }
+
+    /// A sphere, relative to the node, enclosing every instance of geometry whose own
+    /// bounding sphere is `geometry_bounds` (center, radius).
+    ///
+    /// Returns `None` without instances.
+    pub fn bounding_sphere(&self, geometry_bounds: ([f32; 3], f32)) -> Option<([f32; 3], f32)> {
+        if self.matrices.is_empty() {
+            return None;
+        }
+        if let Some((input, bounds)) = self.bounds.get()
+            && input == geometry_bounds
+        {
+            return Some(bounds);
+        }
+
+        let (center, radius) = geometry_bounds;
+        let sphere = |matrix: &[f32; 16]| {
+            let axis_scale = (0..3)
+                .map(|c| length([matrix[c * 4], matrix[c * 4 + 1], matrix[c * 4 + 2]]))
+                .fold(0.0f32, f32::max);
+            (transform_point(matrix, center), radius * axis_scale)
+        };
+
+        // Center of the instance centers' bounding box, grown to enclose every sphere
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for (center, _) in self.matrices.iter().map(sphere) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(center[axis]);
+                max[axis] = max[axis].max(center[axis]);
+            }
+        }
+        let middle = std::array::from_fn(|axis| (min[axis] + max[axis]) / 2.0);
+        let enclosing = self
+            .matrices
+            .iter()
+            .map(sphere)
+            .map(|(center, radius)| distance(middle, center) + radius)
+            .fold(0.0f32, f32::max);
+
+        self.bounds.set(Some((geometry_bounds, (middle, enclosing))));
+        Some((middle, enclosing))
+    }
+
+    /// Marks the instances changed: re-uploaded on the next draw, bounds recomputed.
+    fn invalidate(&mut self) {
+        self.dirty.set(true);
+        self.bounds.set(None);
+    }
+
+    /// Binds the instance matrices to attribute locations 3 to 6 of `vao`, uploading them
+    /// first if they changed. Requires a current GL context.
+    pub(crate) fn bind(&self, vao: GLuint) {
+        let buffer = self.buffer.get_or_init(InstanceBuffer::new);
+        let column_bytes = std::mem::size_of::<[f32; 4]>();
+        let stride = std::mem::size_of::<[f32; 16]>() as GLsizei;
+        unsafe {
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer.vbo);
+            if self.dirty.replace(false) {
+                let bytes = std::mem::size_of_val(self.matrices.as_slice());
+                gl::BufferData(gl::ARRAY_BUFFER, bytes as GLsizeiptr, self.matrices.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+                track_gpu_allocation(GpuResourceKind::VertexBuffer, buffer.vbo, bytes, "instance matrices");
+            }
+            for column in 0..4 {
+                let location = INSTANCE_MATRIX_LOCATION + column;
+                gl::EnableVertexAttribArray(location);
+                gl::VertexAttribPointer(location, 4, gl::FLOAT, gl::FALSE, stride, (column as usize * column_bytes) as *const _);
+                gl::VertexAttribDivisor(location, 1);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    /// Disables the instance attributes on `vao` again, so the VAO can be drawn without instancing.
+    pub(crate) fn unbind(vao: GLuint) {
+        unsafe {
+            gl::BindVertexArray(vao);
+            for column in 0..4 {
+                gl::DisableVertexAttribArray(INSTANCE_MATRIX_LOCATION + column);
+            }
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Clone for InstancedMesh {
+    /// Copies the instances; the copy uploads its own buffer on first draw.
+    fn clone(&self) -> Self {
+        Self {
+            matrices: self.matrices.clone(),
+            ids: self.ids.clone(),
+            indices: self.indices.clone(),
+            next_id: self.next_id,
+            dirty: Cell::new(true),
+            bounds: Cell::new(self.bounds.get()),
+            buffer: OnceCell::new(),
+        }
+    }
+}
+
+/// Vertex buffer holding the instance matrices.
+#[derive(Debug)]
+struct InstanceBuffer {
+    vbo: GLuint,
+}
+
+impl InstanceBuffer {
+    fn new() -> Self {
+        let mut vbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut vbo);
+        }
+        Self { vbo }
+    }
+}
+
+impl Drop for InstanceBuffer {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::VertexBuffer, self.vbo);
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
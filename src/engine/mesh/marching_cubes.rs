@@ -0,0 +1,463 @@
+//! Marching cubes polygonization of scalar density fields and voxel grids.
+//!
+//! Space is sampled on a regular lattice; every lattice cell whose corners straddle the
+//! iso level emits up to five triangles approximating the surface `density == iso`.
+//! Density values **above** the iso level are considered solid (inside the surface).
+//!
+//! Two entry points are provided:
+//! - [`polygonize`] turns any density function (SDFs, metaballs, noise) into a single [`Geometry`].
+//! - [`VoxelGrid`] stores densities for an editable volume split into chunks, and only
+//!   re-polygonizes the chunks touched since the last regeneration — suited to digging
+//!   and building games.
+//!
+//! Vertices are shared between triangles of the same mesh, and normals are taken from the
+//! density gradient so the surface shades smoothly.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use crate::engine::math::vec;
+use crate::engine::object3d::{Geometry, Index, Indices, Topology, Vertex};
+
+/// Unit cube corner offsets, indexed by corner number.
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0], [1, 0, 0], [1, 1, 0], [0, 1, 0],
+    [0, 0, 1], [1, 0, 1], [1, 1, 1], [0, 1, 1],
+];
+
+/// Cube edges as pairs of corner numbers.
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1], [1, 2], [2, 3], [3, 0],
+    [4, 5], [5, 6], [6, 7], [7, 4],
+    [0, 4], [1, 5], [2, 6], [3, 7],
+];
+
+/// Cube faces as corner loops, counter-clockwise when seen from outside the cube.
+const FACES: [[usize; 4]; 6] = [
+    [0, 3, 2, 1], // -Z
+    [4, 5, 6, 7], // +Z
+    [0, 1, 5, 4], // -Y
+    [3, 7, 6, 2], // +Y
+    [0, 4, 7, 3], // -X
+    [1, 2, 6, 5], // +X
+];
+
+/// For each of the 256 inside/outside corner configurations, the triangles to emit,
+/// as triples of cube edge numbers.
+///
+/// The table is derived once rather than hard-coded: on each cube face the iso contour
+/// is traced as segments between crossed edges, segments are chained into closed loops,
+/// and each loop is fan-triangulated. Ambiguous faces (diagonally opposite solid corners)
+/// always keep the solid corners separated; because that decision depends only on the
+/// face itself, neighbouring cells agree and the resulting surface has no cracks.
+fn triangle_table() -> &'static [Vec<[usize; 3]>; 256] {
+    static TABLE: OnceLock<[Vec<[usize; 3]>; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(build_case))
+}
+
+fn edge_between(a: usize, b: usize) -> usize {
+    EDGES
+        .iter()
+        .position(|&[x, y]| (x == a && y == b) || (x == b && y == a))
+        .expect("corners are not adjacent")
+}
+
+fn build_case(case: usize) -> Vec<[usize; 3]> {
+    let inside = |corner: usize| case & (1 << corner) != 0;
+
+    // Contour segments on the cube faces, stored as `next[entry_edge] = exit_edge`.
+    // Walking each face counter-clockwise (from outside), every crossing is either an
+    // entry into the solid region or an exit from it. Each entry is paired with the exit
+    // that follows it, which keeps diagonal solid corners on a face separated. Chaining
+    // entry → exit winds the resulting loops counter-clockwise seen from the empty side.
+    let mut next: [Option<usize>; 12] = [None; 12];
+    for face in FACES {
+        let mut crossings = Vec::with_capacity(4);
+        for i in 0..4 {
+            let (a, b) = (face[i], face[(i + 1) % 4]);
+            if inside(a) != inside(b) {
+                crossings.push((edge_between(a, b), inside(b)));
+            }
+        }
+        for (i, &(edge, entering)) in crossings.iter().enumerate() {
+            if entering {
+                let (exit_edge, _) = crossings[(i + 1) % crossings.len()];
+                next[edge] = Some(exit_edge);
+            }
+        }
+    }
+
+    // Chain segments into closed loops and fan-triangulate them
+    let mut triangles = Vec::new();
+    let mut visited = [false; 12];
+    for start in 0..12 {
+        if visited[start] || next[start].is_none() {
+            continue;
+        }
+        let mut polygon = Vec::new();
+        let mut edge = start;
+        while !visited[edge] {
+            visited[edge] = true;
+            polygon.push(edge);
+            edge = next[edge].expect("iso contour loop is not closed");
+        }
+        for i in 1..polygon.len() - 1 {
+            triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+        }
+    }
+    triangles
+}
+
+/// Densities sampled on a regular lattice.
+struct Lattice<'a> {
+    values: &'a [f32],
+    dims: [usize; 3],
+    origin: [f32; 3],
+    spacing: f32,
+}
+
+impl Lattice<'_> {
+    fn value(&self, p: [usize; 3]) -> f32 {
+        self.values[p[0] + self.dims[0] * (p[1] + self.dims[1] * p[2])]
+    }
+
+    fn position(&self, p: [usize; 3]) -> [f32; 3] {
+        [
+            self.origin[0] + p[0] as f32 * self.spacing,
+            self.origin[1] + p[1] as f32 * self.spacing,
+            self.origin[2] + p[2] as f32 * self.spacing,
+        ]
+    }
+
+    /// Density gradient at a lattice point, using central differences (one-sided at the borders).
+    fn gradient(&self, p: [usize; 3]) -> [f32; 3] {
+        let mut gradient = [0.0; 3];
+        for (axis, component) in gradient.iter_mut().enumerate() {
+            let mut lo = p;
+            let mut hi = p;
+            lo[axis] = p[axis].saturating_sub(1);
+            hi[axis] = (p[axis] + 1).min(self.dims[axis] - 1);
+            let steps = (hi[axis] - lo[axis]).max(1) as f32;
+            *component = (self.value(hi) - self.value(lo)) / (steps * self.spacing);
+        }
+        gradient
+    }
+
+    /// Polygonizes the cells whose minimum corner lies in `[cell_min, cell_max)`.
+    fn polygonize(&self, cell_min: [usize; 3], cell_max: [usize; 3], iso: f32) -> Geometry {
+        let table = triangle_table();
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut indices: Vec<Index> = Vec::new();
+        // Lattice edge (lower corner, axis) → vertex index, so neighbouring cells share vertices
+        let mut edge_vertices: HashMap<([usize; 3], usize), Index> = HashMap::new();
+
+        for z in cell_min[2]..cell_max[2] {
+            for y in cell_min[1]..cell_max[1] {
+                for x in cell_min[0]..cell_max[0] {
+                    let corner = |c: usize| [x + CORNERS[c][0], y + CORNERS[c][1], z + CORNERS[c][2]];
+
+                    let mut case = 0;
+                    for c in 0..8 {
+                        if self.value(corner(c)) > iso {
+                            case |= 1 << c;
+                        }
+                    }
+
+                    for triangle in &table[case] {
+                        for &edge in triangle {
+                            let (a, b) = (corner(EDGES[edge][0]), corner(EDGES[edge][1]));
+                            let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+                            let axis = (0..3).find(|&i| lo[i] != hi[i]).unwrap();
+
+                            let index = *edge_vertices.entry((lo, axis)).or_insert_with(|| {
+                                vertices.push(self.edge_vertex(lo, hi, iso));
+                                (vertices.len() - 1) as Index
+                            });
+                            indices.push(index);
+                        }
+                    }
+                }
+            }
+        }
+
+        Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+    }
+
+    /// Creates the surface vertex on the lattice edge `a`-`b`.
+    fn edge_vertex(&self, a: [usize; 3], b: [usize; 3], iso: f32) -> Vertex {
+        let (va, vb) = (self.value(a), self.value(b));
+        let t = if (vb - va).abs() > f32::EPSILON { ((iso - va) / (vb - va)).clamp(0.0, 1.0) } else { 0.5 };
+
+        let (pa, pb) = (self.position(a), self.position(b));
+        let (ga, gb) = (self.gradient(a), self.gradient(b));
+        let mut position = [0.0; 3];
+        let mut normal = [0.0; 3];
+        for i in 0..3 {
+            position[i] = pa[i] + (pb[i] - pa[i]) * t;
+            // Density grows towards the inside, so the outward normal is the negated gradient
+            normal[i] = -(ga[i] + (gb[i] - ga[i]) * t);
+        }
+        // A vanishing gradient (flat density) keeps its raw value
+        let normal = vec::normalize_or(normal, normal);
+
+        Vertex { position, normal, uv: [0.0, 0.0] }
+    }
+}
+
+/// Polygonizes a density function over a box-shaped region.
+///
+/// # Parameters
+/// - `density`: the scalar field; values above `iso` are solid.
+/// - `origin`: world-space position of the region's minimum corner.
+/// - `cell_size`: edge length of one lattice cell.
+/// - `cells`: number of cells along x, y and z. The field is sampled at `cells + 1` points per axis.
+/// - `iso`: the iso level the surface is extracted at.
+///
+/// Large regions produce 32-bit indices; for editable terrain prefer a chunked [`VoxelGrid`].
+///
+/// # Example
+/// ```
+/// # use rustge::engine::mesh::marching_cubes::polygonize;
+/// // Two metaballs merging into one blob
+/// let balls = [([-0.4f32, 0.0, 0.0], 0.5f32), ([0.4, 0.0, 0.0], 0.5)];
+/// let field = |p: [f32; 3]| {
+///     balls.iter().map(|(c, r)| {
+///         let d2 = (p[0] - c[0]).powi(2) + (p[1] - c[1]).powi(2) + (p[2] - c[2]).powi(2);
+///         r * r / d2.max(1e-6)
+///     }).sum::<f32>()
+/// };
+/// let geometry = polygonize(field, [-1.5, -1.5, -1.5], 0.1, [30, 30, 30], 1.0);
+/// assert!(!geometry.indices.is_empty());
+/// ```
+pub fn polygonize<F: Fn([f32; 3]) -> f32>(
+    density: F,
+    origin: [f32; 3],
+    cell_size: f32,
+    cells: [usize; 3],
+    iso: f32,
+) -> Geometry {
+    let dims = [cells[0] + 1, cells[1] + 1, cells[2] + 1];
+    let mut values = Vec::with_capacity(dims[0] * dims[1] * dims[2]);
+    for z in 0..dims[2] {
+        for y in 0..dims[1] {
+            for x in 0..dims[0] {
+                values.push(density([
+                    origin[0] + x as f32 * cell_size,
+                    origin[1] + y as f32 * cell_size,
+                    origin[2] + z as f32 * cell_size,
+                ]));
+            }
+        }
+    }
+
+    let lattice = Lattice { values: &values, dims, origin, spacing: cell_size };
+    lattice.polygonize([0, 0, 0], cells, iso)
+}
+
+/// An editable voxel volume polygonized chunk by chunk.
+///
+/// The grid stores one density value per voxel (lattice point). Edits mark the chunks they
+/// touch as dirty, and [`regenerate_dirty`](Self::regenerate_dirty) rebuilds only those,
+/// so digging a hole doesn't re-mesh the whole world. Each chunk's mesh is typically assigned
+/// to its own [`Object3D`](crate::engine::object3d::Object3D) with `set_geometry`.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::mesh::marching_cubes::VoxelGrid;
+/// let mut grid = VoxelGrid::new([48, 32, 48], 0.5, [0.0, 0.0, 0.0]);
+/// // Flat ground 8 units high, then dig a crater into it
+/// grid.fill_with(|p| 8.0 - p[1]);
+/// grid.apply_sphere([12.0, 8.0, 12.0], 3.0, -1.0);
+///
+/// for (chunk, geometry) in grid.regenerate_dirty() {
+///     // Update the scene node for `chunk` with `geometry`
+/// #   let _ = (chunk, geometry);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct VoxelGrid {
+    /// Number of voxels (lattice points) along x, y, z.
+    dims: [usize; 3],
+    /// Distance between neighbouring voxels.
+    voxel_size: f32,
+    /// World position of voxel (0, 0, 0).
+    origin: [f32; 3],
+    /// Density iso level; values above it are solid.
+    iso: f32,
+    /// Number of cells along each axis of a chunk.
+    chunk_size: usize,
+    /// Density per voxel, x-major.
+    densities: Vec<f32>,
+    /// Chunks whose mesh is out of date.
+    dirty_chunks: HashSet<[usize; 3]>,
+}
+
+impl VoxelGrid {
+    /// Default number of cells along each axis of a chunk.
+    ///
+    /// Small enough that a chunk mesh always fits 16-bit indices and rebuilds quickly.
+    pub const DEFAULT_CHUNK_SIZE: usize = 16;
+
+    /// Creates an empty (all air) grid with `dims` voxels per axis.
+    ///
+    /// Densities start at `-1.0` and the iso level at `0.0`. All chunks start dirty.
+    pub fn new(dims: [usize; 3], voxel_size: f32, origin: [f32; 3]) -> Self {
+        assert!(dims.iter().all(|&d| d >= 2), "a voxel grid needs at least 2 voxels per axis");
+        let mut grid = Self {
+            dims,
+            voxel_size,
+            origin,
+            iso: 0.0,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            densities: vec![-1.0; dims[0] * dims[1] * dims[2]],
+            dirty_chunks: HashSet::new(),
+        };
+        grid.mark_all_dirty();
+        grid
+    }
+
+    /// Sets the number of cells per chunk axis and marks everything dirty.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero. Chunks larger than 32 cells may need 32-bit indices.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        assert!(chunk_size >= 1, "chunk size must be at least 1");
+        self.chunk_size = chunk_size;
+        self.mark_all_dirty();
+    }
+
+    /// Sets the iso level and marks everything dirty.
+    pub fn set_iso_level(&mut self, iso: f32) {
+        self.iso = iso;
+        self.mark_all_dirty();
+    }
+
+    /// Number of voxels along x, y, z.
+    pub fn dims(&self) -> [usize; 3] {
+        self.dims
+    }
+
+    /// Number of chunks along x, y, z.
+    pub fn chunk_counts(&self) -> [usize; 3] {
+        std::array::from_fn(|i| (self.dims[i] - 1).div_ceil(self.chunk_size))
+    }
+
+    /// World-space position of a voxel.
+    pub fn voxel_position(&self, voxel: [usize; 3]) -> [f32; 3] {
+        std::array::from_fn(|i| self.origin[i] + voxel[i] as f32 * self.voxel_size)
+    }
+
+    /// Density of a voxel.
+    pub fn density(&self, voxel: [usize; 3]) -> f32 {
+        self.densities[self.voxel_index(voxel)]
+    }
+
+    /// Sets the density of a voxel and marks every chunk that samples it as dirty.
+    pub fn set_density(&mut self, voxel: [usize; 3], density: f32) {
+        let index = self.voxel_index(voxel);
+        if self.densities[index] != density {
+            self.densities[index] = density;
+            self.mark_voxel_dirty(voxel);
+        }
+    }
+
+    /// Overwrites every voxel with a density function of its world position.
+    pub fn fill_with<F: Fn([f32; 3]) -> f32>(&mut self, density: F) {
+        for z in 0..self.dims[2] {
+            for y in 0..self.dims[1] {
+                for x in 0..self.dims[0] {
+                    let index = self.voxel_index([x, y, z]);
+                    self.densities[index] = density(self.voxel_position([x, y, z]));
+                }
+            }
+        }
+        self.mark_all_dirty();
+    }
+
+    /// Adds (`strength > 0`, building) or removes (`strength < 0`, digging) material inside
+    /// a sphere, with a smooth falloff towards its edge.
+    pub fn apply_sphere(&mut self, center: [f32; 3], radius: f32, strength: f32) {
+        let to_voxel = |w: f32, axis: usize| (w - self.origin[axis]) / self.voxel_size;
+        let lo: [usize; 3] = std::array::from_fn(|i| to_voxel(center[i] - radius, i).floor().max(0.0) as usize);
+        let hi: [usize; 3] = std::array::from_fn(|i| {
+            (to_voxel(center[i] + radius, i).ceil().max(0.0) as usize).min(self.dims[i] - 1)
+        });
+
+        for z in lo[2]..=hi[2] {
+            for y in lo[1]..=hi[1] {
+                for x in lo[0]..=hi[0] {
+                    let p = self.voxel_position([x, y, z]);
+                    let distance = vec::distance(p, center);
+                    if distance < radius {
+                        let falloff = 1.0 - distance / radius;
+                        let current = self.density([x, y, z]);
+                        self.set_density([x, y, z], current + strength * falloff * 2.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if any chunk needs regenerating.
+    pub fn has_dirty_chunks(&self) -> bool {
+        !self.dirty_chunks.is_empty()
+    }
+
+    /// Marks every chunk dirty.
+    pub fn mark_all_dirty(&mut self) {
+        let counts = self.chunk_counts();
+        for z in 0..counts[2] {
+            for y in 0..counts[1] {
+                for x in 0..counts[0] {
+                    self.dirty_chunks.insert([x, y, z]);
+                }
+            }
+        }
+    }
+
+    /// Polygonizes a single chunk, regardless of whether it is dirty.
+    pub fn polygonize_chunk(&self, chunk: [usize; 3]) -> Geometry {
+        let cell_min: [usize; 3] = std::array::from_fn(|i| chunk[i] * self.chunk_size);
+        let cell_max: [usize; 3] = std::array::from_fn(|i| (cell_min[i] + self.chunk_size).min(self.dims[i] - 1));
+        let lattice = Lattice {
+            values: &self.densities,
+            dims: self.dims,
+            origin: self.origin,
+            spacing: self.voxel_size,
+        };
+        lattice.polygonize(cell_min, cell_max, self.iso)
+    }
+
+    /// Re-polygonizes every dirty chunk and clears the dirty set.
+    ///
+    /// Returns `(chunk coordinate, geometry)` pairs; chunks that became empty are returned
+    /// with empty geometry so callers can clear the matching node.
+    pub fn regenerate_dirty(&mut self) -> Vec<([usize; 3], Geometry)> {
+        let mut chunks: Vec<[usize; 3]> = self.dirty_chunks.drain().collect();
+        chunks.sort_unstable();
+        chunks.into_iter().map(|chunk| (chunk, self.polygonize_chunk(chunk))).collect()
+    }
+
+    fn voxel_index(&self, voxel: [usize; 3]) -> usize {
+        assert!(voxel.iter().zip(self.dims).all(|(&v, d)| v < d), "voxel {voxel:?} out of bounds");
+        voxel[0] + self.dims[0] * (voxel[1] + self.dims[1] * voxel[2])
+    }
+
+    /// Marks all chunks whose cells touch `voxel` as dirty. A voxel on a chunk boundary is a
+    /// corner of cells in up to eight chunks, and gradients reach one voxel further, so the
+    /// neighbouring voxels' chunks are included too.
+    fn mark_voxel_dirty(&mut self, voxel: [usize; 3]) {
+        let counts = self.chunk_counts();
+        let range = |axis: usize| {
+            let lo = voxel[axis].saturating_sub(2) / self.chunk_size;
+            let hi = ((voxel[axis] + 1) / self.chunk_size).min(counts[axis] - 1);
+            lo..=hi
+        };
+        for z in range(2) {
+            for y in range(1) {
+                for x in range(0) {
+                    self.dirty_chunks.insert([x, y, z]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod gpu;
+pub mod bvh;
+pub mod hull;
+pub mod instanced;
+pub mod marching_cubes;
+pub mod uv_unwrap;
+pub mod simplify;
+pub mod vertex_animation;
@@ -0,0 +1,143 @@
+//! Mesh merging and simplification for distant proxies.
+//!
+//! [`merge`] bakes several transformed triangle meshes into one, so they can be drawn with a
+//! single call, and [`simplify`] reduces a mesh by vertex clustering: vertices falling in
+//! the same cell of a uniform grid collapse into one, and triangles that collapse to a line
+//! or point disappear. Clustering is fast and robust on arbitrary (even non-manifold) input
+//! but doesn't preserve UV seams or sharp edges, which is acceptable for geometry only seen
+//! from far away, such as [HLOD](crate::engine::hlod) proxies.
+
+use std::collections::{HashMap, HashSet};
+use crate::engine::math::matrixfuncs::{normal_matrix, transform_point};
+use crate::engine::math::vec::{add, normalize_or, scale};
+use crate::engine::object3d::{Geometry, Index, Indices, Topology, Vertex};
+
+/// Concatenates the triangles of `parts`, each transformed by its column-major matrix.
+/// Parts with another topology are skipped, and the result is rigid (skin weights are
+/// dropped).
+///
+/// ```
+/// # use rustge::engine::{math::matrixfuncs::IDENTITY_MATRIX, mesh::simplify::merge};
+/// # use rustge::engine::object3d::{Geometry, Topology};
+/// let triangle = Geometry::from_positions(Topology::Triangles, &[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+/// let mut moved = IDENTITY_MATRIX;
+/// moved[12] = 5.0;
+///
+/// let merged = merge(&[(&triangle, IDENTITY_MATRIX), (&triangle, moved)]);
+/// assert_eq!(merged.vertices.len(), 6);
+/// assert_eq!(merged.indices.get(3), 3);
+/// assert_eq!(merged.vertices[3].position, [5.0, 0.0, 0.0]);
+/// ```
+pub fn merge(parts: &[(&Geometry, [f32; 16])]) -> Geometry {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (geometry, matrix) in parts {
+        if geometry.topology != Topology::Triangles {
+            continue;
+        }
+        let normals = normal_matrix(matrix);
+        let base = vertices.len() as Index;
+        vertices.extend(geometry.vertices.iter().map(|vertex| Vertex {
+            position: transform_point(matrix, vertex.position),
+            normal: normalize_or(transform_normal(&normals, vertex.normal), vertex.normal),
+            uv: vertex.uv,
+        }));
+        indices.extend(geometry.indices.iter().map(|index| base + index));
+    }
+    Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+}
+
+/// Simplifies triangle geometry by merging all vertices within each `cell_size` cube of a
+/// grid into their average, then dropping collapsed and duplicate triangles. Normals are
+/// recomputed and skin weights dropped. Larger cells give coarser results; other topologies
+/// are returned unchanged.
+///
+/// ```
+/// # use rustge::engine::{mesh::simplify::simplify, object3d::{Geometry, Index, Indices, Topology, Vertex}};
+/// // A 10x10 grid of quads on a unit square
+/// let n = 11;
+/// let vertices = (0..n * n)
+///     .map(|i| Vertex {
+///         position: [(i % n) as f32 / 10.0, 0.0, (i / n) as f32 / 10.0],
+///         normal: [0.0, 1.0, 0.0],
+///         uv: [0.0, 0.0],
+///     })
+///     .collect();
+/// let mut indices: Vec<Index> = Vec::new();
+/// for z in 0..n - 1 {
+///     for x in 0..n - 1 {
+///         let i = (z * n + x) as Index;
+///         let n = n as Index;
+///         indices.extend([i, i + n, i + 1, i + 1, i + n, i + n + 1]);
+///     }
+/// }
+/// let grid = Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None };
+///
+/// let coarse = simplify(&grid, 0.5);
+/// assert!(coarse.indices.len() < grid.indices.len() / 10);
+/// assert!(coarse.vertices.iter().all(|vertex| vertex.normal[1] > 0.99));
+/// ```
+pub fn simplify(geometry: &Geometry, cell_size: f32) -> Geometry {
+    if geometry.topology != Topology::Triangles || cell_size <= 0.0 {
+        return geometry.clone();
+    }
+
+    // Accumulate the vertices of each occupied cell
+    let mut cells: HashMap<[i32; 3], Index> = HashMap::new();
+    let mut sums: Vec<([f32; 3], [f32; 2], f32)> = Vec::new();
+    let cluster: Vec<Index> = geometry
+        .vertices
+        .iter()
+        .map(|vertex| {
+            let cell = vertex.position.map(|c| (c / cell_size).floor() as i32);
+            let id = *cells.entry(cell).or_insert_with(|| {
+                sums.push(([0.0; 3], [0.0; 2], 0.0));
+                (sums.len() - 1) as Index
+            });
+            let sum = &mut sums[id as usize];
+            sum.0 = add(sum.0, vertex.position);
+            sum.1 = [sum.1[0] + vertex.uv[0], sum.1[1] + vertex.uv[1]];
+            sum.2 += 1.0;
+            id
+        })
+        .collect();
+
+    let mut indices = Vec::new();
+    let mut seen = HashSet::new();
+    for triangle in 0..geometry.indices.len() / 3 {
+        let corners = [0, 1, 2].map(|corner| geometry.indices.get(triangle * 3 + corner) as usize);
+        if corners.iter().any(|&i| i >= cluster.len()) {
+            continue;
+        }
+        let [a, b, c] = corners.map(|i| cluster[i]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        // Same triangle with the same winding, in any rotation
+        let rotation = if a < b && a < c { [a, b, c] } else if b < c { [b, c, a] } else { [c, a, b] };
+        if seen.insert(rotation) {
+            indices.extend([a, b, c]);
+        }
+    }
+
+    let vertices = sums
+        .into_iter()
+        .map(|(position, uv, count)| Vertex {
+            position: scale(position, 1.0 / count),
+            normal: [0.0, 1.0, 0.0],
+            uv: [uv[0] / count, uv[1] / count],
+        })
+        .collect();
+    let mut simplified = Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None };
+    simplified.compute_normals();
+    simplified
+}
+
+/// Multiplies a normal by a column-major 3x3 normal matrix.
+fn transform_normal(m: &[f32; 9], n: [f32; 3]) -> [f32; 3] {
+    [
+        m[0] * n[0] + m[3] * n[1] + m[6] * n[2],
+        m[1] * n[0] + m[4] * n[1] + m[7] * n[2],
+        m[2] * n[0] + m[5] * n[1] + m[8] * n[2],
+    ]
+}
@@ -0,0 +1,280 @@
+//! Automatic UV unwrapping and atlas packing.
+//!
+//! Generated meshes (marching cubes, procedural or boolean geometry) usually have no
+//! authored texture coordinates. [`unwrap`] computes a non-overlapping parameterization
+//! so textures can be applied and lightmaps baked:
+//!
+//! 1. **Chart segmentation** – triangles are grouped into connected charts whose normals
+//!    stay within an angle of the chart's seed normal, so each chart is close to planar.
+//! 2. **Parameterization** – each chart is projected onto the plane of its average normal.
+//! 3. **Packing** – chart rectangles are shelf-packed into the unit square with padding
+//!    between them, preserving relative texel density across the mesh.
+//!
+//! Vertices on chart borders are duplicated, since they need a different UV on each side
+//! of the seam.
+
+use std::collections::HashMap;
+use crate::engine::math::vec::{add, cross, dot, length, normalize_or, sub};
+use crate::engine::object3d::{Geometry, Index, Indices, Topology, Vertex};
+
+/// Fallback direction for degenerate (zero-area) normals.
+const FALLBACK_NORMAL: [f32; 3] = [0.0, 0.0, 1.0];
+
+/// Parameters controlling chart segmentation and packing.
+#[derive(Clone, Copy, Debug)]
+pub struct UnwrapOptions {
+    /// Maximum angle (in degrees) between a triangle's normal and its chart's seed normal.
+    ///
+    /// Smaller values produce flatter charts with less distortion but more seams.
+    pub max_chart_angle: f32,
+
+    /// Gap left around each chart, as a fraction of the atlas size.
+    ///
+    /// Prevents texture filtering and mipmapping from bleeding between charts; for a
+    /// lightmap of resolution `N`, `2.0 / N` leaves roughly two texels.
+    pub padding: f32,
+}
+
+impl Default for UnwrapOptions {
+    fn default() -> Self {
+        Self {
+            max_chart_angle: 60.0,
+            padding: 1.0 / 128.0,
+        }
+    }
+}
+
+/// A group of connected, roughly coplanar triangles sharing one UV island.
+#[derive(Clone, Debug)]
+pub struct Chart {
+    /// Indices of the triangles (into the source geometry's triangle list) in this chart.
+    pub triangles: Vec<usize>,
+
+    /// Minimum corner of the chart's rectangle in the atlas, in UV space.
+    pub uv_min: [f32; 2],
+
+    /// Maximum corner of the chart's rectangle in the atlas, in UV space.
+    pub uv_max: [f32; 2],
+}
+
+/// The result of unwrapping a mesh.
+#[derive(Clone, Debug)]
+pub struct UvAtlas {
+    /// The unwrapped geometry, with atlas coordinates in each vertex's `uv`.
+    pub geometry: Geometry,
+
+    /// The charts the mesh was split into, with their placement in the atlas.
+    pub charts: Vec<Chart>,
+}
+
+/// Unwraps `geometry` into a packed UV atlas.
+///
+/// Existing UVs are replaced; positions, normals and skin weights are preserved. The geometry is expected
+/// to use [`Topology::Triangles`]. Splitting vertices along seams may push the result into
+/// 32-bit indices.
+pub fn unwrap(geometry: &Geometry, options: &UnwrapOptions) -> UvAtlas {
+    let triangle_count = geometry.indices.len() / 3;
+    let corner = |triangle: usize, k: usize| geometry.indices.get(triangle * 3 + k) as usize;
+    let position = |triangle: usize, k: usize| geometry.vertices[corner(triangle, k)].position;
+
+    // Per-triangle normals (unnormalized length is twice the area)
+    let face_normals: Vec<[f32; 3]> = (0..triangle_count)
+        .map(|t| cross(sub(position(t, 1), position(t, 0)), sub(position(t, 2), position(t, 0))))
+        .collect();
+
+    let charts = segment_charts(geometry, &face_normals, options.max_chart_angle.to_radians().cos());
+
+    // Project every chart onto the plane of its area-weighted normal
+    let mut projected: Vec<ProjectedChart> = charts
+        .into_iter()
+        .map(|triangles| {
+            let mut normal = [0.0; 3];
+            for &t in &triangles {
+                normal = add(normal, face_normals[t]);
+            }
+            let (axis_u, axis_v) = plane_basis(normalize_or(normal, FALLBACK_NORMAL));
+
+            let mut uvs = Vec::with_capacity(triangles.len() * 3);
+            let mut min = [f32::MAX; 2];
+            let mut max = [f32::MIN; 2];
+            for &t in &triangles {
+                for k in 0..3 {
+                    let p = position(t, k);
+                    let uv = [dot(p, axis_u), dot(p, axis_v)];
+                    min = [min[0].min(uv[0]), min[1].min(uv[1])];
+                    max = [max[0].max(uv[0]), max[1].max(uv[1])];
+                    uvs.push(uv);
+                }
+            }
+            ProjectedChart { triangles, uvs, min, size: [max[0] - min[0], max[1] - min[1]], offset: [0.0; 2] }
+        })
+        .collect();
+
+    let scale = pack_charts(&mut projected, options.padding);
+
+    // Emit the output mesh: one vertex per (chart, source vertex) pair
+    let mut vertices: Vec<Vertex> = Vec::new();
+    let mut sources: Vec<usize> = Vec::new();
+    let mut indices: Vec<Index> = vec![0; geometry.indices.len()];
+    let mut result_charts = Vec::with_capacity(projected.len());
+    for chart in &projected {
+        let mut remap: HashMap<usize, Index> = HashMap::new();
+        for (i, &t) in chart.triangles.iter().enumerate() {
+            for k in 0..3 {
+                let source = corner(t, k);
+                let uv = chart.uvs[i * 3 + k];
+                let index = *remap.entry(source).or_insert_with(|| {
+                    vertices.push(Vertex {
+                        uv: [
+                            (uv[0] - chart.min[0] + chart.offset[0]) * scale,
+                            (uv[1] - chart.min[1] + chart.offset[1]) * scale,
+                        ],
+                        ..geometry.vertices[source]
+                    });
+                    sources.push(source);
+                    (vertices.len() - 1) as Index
+                });
+                indices[t * 3 + k] = index;
+            }
+        }
+        result_charts.push(Chart {
+            triangles: chart.triangles.clone(),
+            uv_min: [chart.offset[0] * scale, chart.offset[1] * scale],
+            uv_max: [(chart.offset[0] + chart.size[0]) * scale, (chart.offset[1] + chart.size[1]) * scale],
+        });
+    }
+
+    UvAtlas {
+        geometry: Geometry {
+            vertices,
+            indices: Indices::from_u32(indices),
+            topology: Topology::Triangles,
+            skin: geometry.skin.as_ref().map(|skin| sources.iter().map(|&source| skin[source]).collect()),
+        },
+        charts: result_charts,
+    }
+}
+
+/// A chart projected to 2D, before and after packing.
+struct ProjectedChart {
+    triangles: Vec<usize>,
+    /// Projected UV per triangle corner, in chart-local world units.
+    uvs: Vec<[f32; 2]>,
+    min: [f32; 2],
+    size: [f32; 2],
+    /// Placement of the chart's minimum corner in the (unscaled) atlas.
+    offset: [f32; 2],
+}
+
+/// Groups triangles into charts by flood-filling across shared edges while the
+/// neighbour's normal stays within `min_cos` of the chart's seed normal.
+fn segment_charts(geometry: &Geometry, face_normals: &[[f32; 3]], min_cos: f32) -> Vec<Vec<usize>> {
+    let triangle_count = face_normals.len();
+
+    // Weld vertices by position so charts can grow across existing seams (e.g. flat-shaded meshes)
+    let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+    let position_ids: Vec<usize> = geometry
+        .vertices
+        .iter()
+        .map(|v| {
+            let key = v.position.map(f32::to_bits);
+            let next_id = welded.len();
+            *welded.entry(key).or_insert(next_id)
+        })
+        .collect();
+
+    let mut edge_triangles: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for t in 0..triangle_count {
+        for k in 0..3 {
+            let a = position_ids[geometry.indices.get(t * 3 + k) as usize];
+            let b = position_ids[geometry.indices.get(t * 3 + (k + 1) % 3) as usize];
+            edge_triangles.entry((a.min(b), a.max(b))).or_default().push(t);
+        }
+    }
+
+    let unit_normals: Vec<[f32; 3]> = face_normals.iter().map(|&n| normalize_or(n, FALLBACK_NORMAL)).collect();
+    let mut chart_of = vec![usize::MAX; triangle_count];
+    let mut charts = Vec::new();
+
+    // Seed charts from the largest triangles first; they give the most representative normals
+    let mut seeds: Vec<usize> = (0..triangle_count).collect();
+    seeds.sort_by(|&a, &b| length(face_normals[b]).total_cmp(&length(face_normals[a])));
+
+    for seed in seeds {
+        if chart_of[seed] != usize::MAX {
+            continue;
+        }
+        let chart_id = charts.len();
+        let seed_normal = unit_normals[seed];
+        let mut members = vec![seed];
+        let mut stack = vec![seed];
+        chart_of[seed] = chart_id;
+
+        while let Some(t) = stack.pop() {
+            for k in 0..3 {
+                let a = position_ids[geometry.indices.get(t * 3 + k) as usize];
+                let b = position_ids[geometry.indices.get(t * 3 + (k + 1) % 3) as usize];
+                for &neighbour in &edge_triangles[&(a.min(b), a.max(b))] {
+                    if chart_of[neighbour] == usize::MAX && dot(unit_normals[neighbour], seed_normal) >= min_cos {
+                        chart_of[neighbour] = chart_id;
+                        members.push(neighbour);
+                        stack.push(neighbour);
+                    }
+                }
+            }
+        }
+        charts.push(members);
+    }
+
+    charts
+}
+
+/// Shelf-packs chart rectangles (tallest first) and returns the scale that maps the packed
+/// layout into the unit square. Chart `offset`s are filled in, in unscaled units.
+fn pack_charts(charts: &mut [ProjectedChart], padding: f32) -> f32 {
+    let total_area: f32 = charts.iter().map(|c| c.size[0] * c.size[1]).sum();
+    let widest = charts.iter().map(|c| c.size[0]).fold(0.0, f32::max);
+    if total_area <= 0.0 && widest <= 0.0 {
+        return 1.0;
+    }
+
+    // Padding is specified relative to the final atlas; estimate its size in world units
+    let estimated_side = total_area.sqrt().max(widest);
+    let gap = padding * estimated_side;
+    let shelf_width = estimated_side.max(widest + 2.0 * gap);
+
+    let mut order: Vec<usize> = (0..charts.len()).collect();
+    order.sort_by(|&a, &b| charts[b].size[1].total_cmp(&charts[a].size[1]));
+
+    let (mut x, mut y, mut shelf_height, mut used_width) = (gap, gap, 0.0f32, 0.0f32);
+    for i in order {
+        let chart = &mut charts[i];
+        if x + chart.size[0] + gap > shelf_width && x > gap {
+            x = gap;
+            y += shelf_height + gap;
+            shelf_height = 0.0;
+        }
+        chart.offset = [x, y];
+        x += chart.size[0] + gap;
+        used_width = used_width.max(x);
+        shelf_height = shelf_height.max(chart.size[1]);
+    }
+    let used_height = y + shelf_height + gap;
+
+    1.0 / used_width.max(used_height).max(f32::EPSILON)
+}
+
+/// Two unit vectors spanning the plane perpendicular to `normal`.
+fn plane_basis(normal: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    // Pick the world axis least aligned with the normal to build a stable tangent
+    let helper = if normal[0].abs() < 0.57 {
+        [1.0, 0.0, 0.0]
+    } else if normal[1].abs() < 0.57 {
+        [0.0, 1.0, 0.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    };
+    let u = normalize_or(cross(helper, normal), FALLBACK_NORMAL);
+    let v = cross(normal, u);
+    (u, v)
+}
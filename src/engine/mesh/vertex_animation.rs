@@ -0,0 +1,229 @@
+//! Vertex animation textures: mesh animations baked into textures and played back in the
+//! vertex shader.
+//!
+//! Flags in the wind, swaying plants, cloth-like props or a crowd of waving spectators don't
+//! need bones or a physics simulation at runtime. A [`BakedVertexAnimation`] samples a
+//! deformation once per frame, up front, and stores every frame's vertex positions and
+//! normals; uploaded as a [`VertexAnimation`], those become two float textures that a
+//! vertex shader reads by vertex index and frame, blending between neighbouring frames.
+//! Playback costs no CPU time at all, and instanced copies each play at their own phase.
+//!
+//! Only whole-mesh playback of a looping clip is supported. Frustum culling still uses the
+//! node's (rest pose) geometry, so for motion reaching well outside it, widen the geometry
+//! or disable culling on the node.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{math::color::Color, mesh::vertex_animation::{self, BakedVertexAnimation, VertexAnimation}};
+//! # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+//! # let flag_geometry = Geometry::from_positions(Topology::Triangles, &[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+//! // Two seconds of a flag waving along x, at 30 frames per second
+//! let baked = BakedVertexAnimation::bake(&flag_geometry, 60, 30.0, |time, geometry| {
+//!     for vertex in &mut geometry.vertices {
+//!         let [x, _, _] = vertex.position;
+//!         vertex.position[2] += (x * 3.0 - time * std::f32::consts::PI).sin() * 0.1 * x;
+//!     }
+//! });
+//! let animation = VertexAnimation::upload(&baked, "flag");
+//!
+//! let flag = Object3D::new();
+//! flag.borrow_mut().set_geometry(flag_geometry);
+//! flag.borrow_mut().set_material(animation.material(Color::RED));
+//! flag.borrow_mut().on_update(|node, clock| {
+//!     if let Some(material) = node.material_mut() {
+//!         vertex_animation::set_time(material, clock.elapsed() as f32);
+//!     }
+//! });
+//! ```
+
+use std::rc::Rc;
+use crate::engine::material::{lit_fragment_source, Material};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::color::Color;
+use crate::engine::object3d::{Geometry, Topology};
+use crate::engine::shader::{builtin_program, UniformValue};
+use crate::engine::texture::Texture;
+
+/// Widest data texture the baker produces. Longer animations wrap onto more rows; every
+/// GL 3.3 implementation supports at least this size.
+const MAX_TEXTURE_WIDTH: usize = 4096;
+
+/// Per-frame vertex positions and normals of a looping mesh animation, on the CPU.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BakedVertexAnimation {
+    /// Vertices per frame.
+    pub vertex_count: usize,
+
+    /// Number of frames in the loop.
+    pub frame_count: usize,
+
+    /// Playback speed in frames per second.
+    pub frame_rate: f32,
+
+    /// Positions of every vertex, frame after frame (`frame * vertex_count + vertex`).
+    pub positions: Vec<[f32; 3]>,
+
+    /// Normals, laid out like `positions`.
+    pub normals: Vec<[f32; 3]>,
+}
+
+impl BakedVertexAnimation {
+    /// Bakes `frame_count` frames at `frame_rate` frames per second. For each frame, `pose`
+    /// receives the frame's time in seconds and a fresh copy of `rest` to deform; normals
+    /// of triangle meshes are recomputed afterwards. `pose` must not add or remove vertices.
+    ///
+    /// ```
+    /// # use rustge::engine::{mesh::vertex_animation::BakedVertexAnimation, object3d::{Geometry, Topology}};
+    /// let triangle = Geometry::from_positions(Topology::Triangles, &[[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+    /// let baked = BakedVertexAnimation::bake(&triangle, 4, 2.0, |time, geometry| {
+    ///     geometry.vertices[2].position[1] += time;
+    /// });
+    /// assert_eq!(baked.duration(), 2.0);
+    /// assert_eq!(baked.position(3, 2), [0.0, 2.5, 0.0]);
+    /// ```
+    pub fn bake(rest: &Geometry, frame_count: usize, frame_rate: f32, mut pose: impl FnMut(f32, &mut Geometry)) -> Self {
+        let frames: Vec<Geometry> = (0..frame_count)
+            .map(|frame| {
+                let mut geometry = rest.clone();
+                pose(frame as f32 / frame_rate, &mut geometry);
+                if geometry.topology == Topology::Triangles {
+                    geometry.compute_normals();
+                }
+                geometry
+            })
+            .collect();
+        Self::from_frames(&frames, frame_rate)
+    }
+
+    /// Builds an animation from already posed frames, e.g. an imported keyframe sequence.
+    /// Every frame needs the same number of vertices as the first; frames with another
+    /// count are skipped.
+    pub fn from_frames(frames: &[Geometry], frame_rate: f32) -> Self {
+        let vertex_count = frames.first().map_or(0, |frame| frame.vertices.len());
+        let frames: Vec<&Geometry> = frames.iter().filter(|frame| frame.vertices.len() == vertex_count).collect();
+        let vertices = || frames.iter().flat_map(|frame| &frame.vertices);
+        Self {
+            vertex_count,
+            frame_count: frames.len(),
+            frame_rate,
+            positions: vertices().map(|vertex| vertex.position).collect(),
+            normals: vertices().map(|vertex| vertex.normal).collect(),
+        }
+    }
+
+    /// Length of the loop in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frame_count as f32 / self.frame_rate
+    }
+
+    /// Position of `vertex` in `frame`.
+    pub fn position(&self, frame: usize, vertex: usize) -> [f32; 3] {
+        self.positions[frame * self.vertex_count + vertex]
+    }
+
+    /// Bounds of every vertex over the whole animation, or `None` if it is empty.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.positions.iter().copied())
+    }
+
+    /// Width and height of the data textures.
+    fn texture_size(&self) -> [usize; 2] {
+        let texels = (self.vertex_count * self.frame_count).max(1);
+        let width = texels.min(MAX_TEXTURE_WIDTH);
+        [width, texels.div_ceil(width)]
+    }
+}
+
+/// A baked animation uploaded as position and normal textures.
+#[derive(Clone, Debug)]
+pub struct VertexAnimation {
+    positions: Rc<Texture>,
+    normals: Rc<Texture>,
+    vertex_count: usize,
+    frame_count: usize,
+    frame_rate: f32,
+}
+
+impl VertexAnimation {
+    /// Uploads `baked` to the GPU. Requires a current GL context.
+    pub fn upload(baked: &BakedVertexAnimation, label: &str) -> Self {
+        let [width, height] = baked.texture_size();
+        let texels = |data: &[[f32; 3]]| {
+            let mut texels: Vec<[f32; 4]> = data.iter().map(|&[x, y, z]| [x, y, z, 1.0]).collect();
+            texels.resize(width * height, [0.0; 4]);
+            texels
+        };
+        let (width, height) = (width as u32, height as u32);
+        Self {
+            positions: Rc::new(Texture::from_rgba32f(width, height, &texels(&baked.positions), &format!("{label} positions"))),
+            normals: Rc::new(Texture::from_rgba32f(width, height, &texels(&baked.normals), &format!("{label} normals"))),
+            vertex_count: baked.vertex_count,
+            frame_count: baked.frame_count,
+            frame_rate: baked.frame_rate,
+        }
+    }
+
+    /// A Blinn-Phong material in `color` that plays the animation on whatever geometry it
+    /// is drawn with, which must be the geometry it was baked from (vertices are matched by
+    /// index). Instances spread randomly over the loop; see [`set_phase_spread`].
+    pub fn material(&self, color: Color) -> Material {
+        let shader = builtin_program(
+            "vertex_animation",
+            include_str!("../shaders/vertex_animation.vert"),
+            &lit_fragment_source(include_str!("../shaders/phong.frag")),
+        );
+        let mut material = Material::new(shader);
+        material.color = color;
+        material.set_uniform("u_specular", UniformValue::Vec3([0.25, 0.25, 0.25]));
+        material.set_uniform("u_shininess", UniformValue::Float(32.0));
+        self.apply(&mut material);
+        material
+    }
+
+    /// Binds the animation to `material`, whose shader reads it like the built-in
+    /// `vertex_animation.vert` does:
+    ///
+    /// ```glsl
+    /// uniform sampler2D u_vat_positions;   // RGBA32F, xyz per texel
+    /// uniform sampler2D u_vat_normals;
+    /// uniform int u_vat_vertex_count;      // texel of vertex v in frame f: f * count + v,
+    /// uniform int u_vat_width;             // at (texel % width, texel / width)
+    /// uniform int u_vat_frame_count;
+    /// uniform float u_vat_frame_rate;
+    /// uniform float u_vat_time;            // seconds, see set_time
+    /// uniform float u_vat_phase_spread;    // see set_phase_spread
+    /// ```
+    pub fn apply(&self, material: &mut Material) {
+        let [width, _] = self.texture_size();
+        material.set_texture("u_vat_positions", self.positions.clone());
+        material.set_texture("u_vat_normals", self.normals.clone());
+        material.set_uniform("u_vat_vertex_count", UniformValue::Int(self.vertex_count as i32));
+        material.set_uniform("u_vat_width", UniformValue::Int(width as i32));
+        material.set_uniform("u_vat_frame_count", UniformValue::Int(self.frame_count as i32));
+        material.set_uniform("u_vat_frame_rate", UniformValue::Float(self.frame_rate));
+        set_time(material, 0.0);
+        set_phase_spread(material, 1.0);
+    }
+
+    /// Length of the loop in seconds.
+    pub fn duration(&self) -> f32 {
+        self.frame_count as f32 / self.frame_rate
+    }
+
+    fn texture_size(&self) -> [usize; 2] {
+        [self.positions.width() as usize, self.positions.height() as usize]
+    }
+}
+
+/// Sets the playback time in seconds of a material playing a vertex animation. The clip
+/// loops, so an ever-growing clock time works.
+pub fn set_time(material: &mut Material, seconds: f32) {
+    material.set_uniform("u_vat_time", UniformValue::Float(seconds));
+}
+
+/// Sets how far instances of a node drawn with `material` are offset in the loop: 0 plays
+/// them in sync, 1 (the default) spreads them pseudo-randomly over the whole loop so a
+/// crowd doesn't move in lockstep.
+pub fn set_phase_spread(material: &mut Material, spread: f32) {
+    material.set_uniform("u_vat_phase_spread", UniformValue::Float(spread));
+}
@@ -1,5 +1,58 @@
 pub mod renderer;
+pub mod display;
+pub mod event;
+pub mod input;
+pub mod input_context;
+pub mod ui;
 pub mod object3d;
 pub mod camera;
 pub mod shader;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod stats;
+pub mod texture;
+pub mod quality;
+pub mod material;
+pub mod mesh;
+pub mod time;
+pub mod jobs;
+pub mod tween;
+pub mod debug;
+pub mod font;
+pub mod localization;
+pub mod helpers;
+pub mod light;
+pub mod scene;
+pub mod ecs;
+pub mod query;
+pub mod activation;
+pub mod simulation;
+pub mod physics;
+pub mod animation;
+pub mod ragdoll;
+pub mod save;
+pub mod snapshot;
+pub mod background;
+pub mod frame_graph;
+pub mod render_target;
+pub mod post;
+pub mod eye_adaptation;
+pub mod motion;
+pub mod render_queue;
+pub mod hlod;
+pub mod readback;
+pub mod upload;
+pub mod hot_reload;
+pub mod program_cache;
+pub mod editor;
+pub mod pool;
+pub mod watchdog;
+pub mod profiler;
+pub mod terrain;
+pub mod export;
+pub mod import;
+pub mod shadow;
+pub mod uniform_buffer;
+pub mod plugin;
+pub mod color_space;
+pub mod pbr;
+pub mod timeline;
@@ -0,0 +1,187 @@
+//! Per-object motion vectors for temporal effects.
+//!
+//! Temporal anti-aliasing blends each pixel with where the same surface was in the previous
+//! frame, and motion blur smears it along the way it moved. Camera motion alone can be
+//! reconstructed from depth, but a moving object needs its own previous transform. A
+//! [`MotionVectors`] buffer renders every visible node with both its current world matrix
+//! and the one it had the frame before (see
+//! [`Object3D::previous_world_matrix`](crate::engine::object3d::Object3D::previous_world_matrix)),
+//! storing per pixel how far the surface moved on screen.
+//!
+//! Motion is in UV units (one unit is the whole screen), pointing from the previous position
+//! to the current one: the surface at `uv` was at `uv - motion` last frame. It excludes the
+//! camera's [jitter](crate::engine::camera::Camera::jitter), so a still scene under TAA has
+//! none. Pixels without geometry (the background) hold zero. Only nodes move: skinned and
+//! vertex animated meshes show their node's motion, not their deformation's.
+//!
+//! With [`Renderer::set_motion_vectors`](crate::engine::renderer::Renderer::set_motion_vectors)
+//! enabled, the renderer fills a buffer after drawing the scene each frame and hands it to
+//! [post effects](crate::engine::post) as `u_motion_vectors`.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{post::PostEffect, renderer::Renderer};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! renderer.set_motion_vectors(true);
+//! renderer.post_chain_mut().push(PostEffect::new("motion blur", r#"
+//!     #version 330 core
+//!     in vec2 v_uv;
+//!     uniform sampler2D u_scene_color;
+//!     uniform sampler2D u_motion_vectors;
+//!     out vec4 frag_color;
+//!     void main() {
+//!         vec2 motion = texture(u_motion_vectors, v_uv).xy;
+//!         vec4 sum = vec4(0.0);
+//!         for (int i = 0; i < 8; i++) {
+//!             sum += texture(u_scene_color, v_uv - motion * (float(i) / 7.0));
+//!         }
+//!         frag_color = sum / 8.0;
+//!     }
+//! "#));
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use gl::types::{GLint, GLsizei, GLuint};
+use crate::engine::camera::Camera;
+use crate::engine::math::matrixfuncs::matrix_mul_4x4;
+use crate::engine::object3d::Object3D;
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+use crate::engine::texture::Texture;
+
+/// An offscreen buffer of screen-space motion, rendered once per frame; see the
+/// [module documentation](self). Use one buffer per scene: nodes remember which of its
+/// frames they were last drawn in.
+#[derive(Debug, Default)]
+pub struct MotionVectors {
+    /// GL objects, created on the first render and recreated when the size changes.
+    target: Option<MotionTarget>,
+
+    /// The camera's unjittered projection-view matrix at the last render.
+    previous_proj_view: Option<[f32; 16]>,
+
+    /// Number of the last render, under which nodes record their world matrices.
+    frame: u64,
+}
+
+impl MotionVectors {
+    /// An empty buffer. GL resources are created on the first render.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renders the motion of every node under `root` visible from `camera` since the
+    /// previous render into a buffer of `size` pixels (usually the window size). The first
+    /// render, and nodes that weren't drawn by the previous one, show only camera motion.
+    /// Requires a current GL context; the framebuffer and viewport are restored afterwards.
+    pub fn render(&mut self, root: &Rc<RefCell<Object3D>>, camera: &Camera, size: [u32; 2]) {
+        let size = [size[0].max(1), size[1].max(1)];
+        if self.target.as_ref().is_none_or(|target| target.size != size) {
+            self.target = Some(MotionTarget::new(size));
+        }
+        let Some(target) = &self.target else {
+            return;
+        };
+        self.frame += 1;
+        let current_proj_view = matrix_mul_4x4(&camera.unjittered_projection_matrix(), &camera.view_matrix());
+        let previous_proj_view = self.previous_proj_view.replace(current_proj_view).unwrap_or(current_proj_view);
+
+        let shader = builtin_program(
+            "motion vectors",
+            include_str!("shaders/motion.vert"),
+            include_str!("shaders/motion.frag"),
+        );
+
+        let mut previous_fbo: GLint = 0;
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_fbo);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+            gl::Viewport(0, 0, size[0] as GLsizei, size[1] as GLsizei);
+            gl::ClearBufferfv(gl::COLOR, 0, [0.0f32; 4].as_ptr());
+            gl::DepthMask(gl::TRUE);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+        }
+
+        shader.use_program();
+        shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+        shader.set_uniform_matrix4("u_current_proj_view", &current_proj_view);
+        shader.set_uniform_matrix4("u_previous_proj_view", &previous_proj_view);
+
+        for node in Object3D::visible_set(root, camera) {
+            let mut object = node.borrow_mut();
+            if object.geometry().is_none() {
+                continue;
+            }
+            let previous_model = object.advance_motion(self.frame);
+            shader.set_uniform_matrix4("u_model", &object.world_matrix());
+            shader.set_uniform_matrix4("u_previous_model", &previous_model);
+            shader.set_uniform_int("u_instanced", object.instances().is_some() as i32);
+            object.draw_geometry();
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_fbo as GLuint);
+            let [x, y, width, height] = previous_viewport;
+            gl::Viewport(x, y, width, height);
+        }
+    }
+
+    /// The motion of the last render as an RG16F texture, to sample in effects and
+    /// materials; `None` before the first render.
+    pub fn texture(&self) -> Option<&Rc<Texture>> {
+        self.target.as_ref().map(|target| &target.motion)
+    }
+
+    /// Forgets the camera's previous transform, so the next render shows no camera motion.
+    /// Call on camera cuts, which would otherwise blur the whole first frame of the new shot.
+    pub fn reset(&mut self) {
+        self.previous_proj_view = None;
+    }
+}
+
+/// Framebuffer with a motion texture and a depth buffer.
+#[derive(Debug)]
+struct MotionTarget {
+    fbo: GLuint,
+    motion: Rc<Texture>,
+    depth: GLuint,
+    size: [u32; 2],
+}
+
+impl MotionTarget {
+    fn new(size: [u32; 2]) -> Self {
+        let motion = Rc::new(Texture::empty_rg16f(size[0], size[1], "motion vectors"));
+        let (mut fbo, mut depth) = (0, 0);
+        unsafe {
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, size[0] as GLsizei, size[1] as GLsizei);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, motion.id(), 0);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::RenderTarget, depth, (size[0] * size[1] * 4) as usize, "motion depth");
+        Self { fbo, motion, depth, size }
+    }
+}
+
+impl Drop for MotionTarget {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.depth);
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteRenderbuffers(1, &self.depth);
+        }
+    }
+}
@@ -1,9 +1,33 @@
 use std::{rc::{Rc, Weak}, cell::RefCell};
 use std::cell::OnceCell;
+use std::any::TypeId;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use gl::{self, types::*};
-use crate::engine::camera::{Camera};
-use crate::engine::math::matrixfuncs::{compute_local_matrix, matrix_mul_4x4};
+use serde_json::Value;
+use crate::engine::animation::skeleton::{set_skin_uniforms, Skin};
+use crate::engine::activation::{Activation, ActivationSettings};
+use crate::engine::camera::{Camera, Frustum};
+use crate::engine::math::matrixfuncs::{
+    compute_local_matrix, decompose_matrix, matrix_inverse_4x4, matrix_inverse_or_identity, matrix_mul_4x4, normal_matrix,
+    transform_point, IDENTITY_MATRIX,
+};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::ray::Ray;
+use crate::engine::math::vec::{add, cross, distance, distance_squared, dot, length, lerp, normalize_or, scale, sub};
+use crate::engine::debug::normals::NormalsDebug;
+use crate::engine::jobs;
+use crate::engine::light::{Light, LightSet};
+use crate::engine::material::Material;
+use crate::engine::mesh::bvh::MeshBvh;
+use crate::engine::mesh::gpu::GpuMesh;
+use crate::engine::mesh::instanced::InstancedMesh;
 use crate::engine::shader::GLShaderProgram;
+use crate::engine::query::{index_component, index_tag, unindex_component, unindex_tag, Components};
+use crate::engine::stats::{record_draw_call, record_instanced_draw_call, SceneStatistics};
+use crate::engine::texture::Texture;
+use crate::engine::uniform_buffer::{apply_frame_uniforms, upload_frame_blocks};
+use crate::engine::time::Clock;
 
 /// Represents a 3D object/node in a scene graph with position, rotation, scale,
 /// and parent/children relationships for hierarchical transformations.
@@ -54,14 +78,261 @@ pub struct Object3D {
     /// Children are owned strongly to keep them alive as long as the parent exists.
     children: Vec<Rc<RefCell<Object3D>>>,
 
-    /// Holds the geometry
-    geometry: Option<Geometry>,
+    /// Holds the geometry, possibly shared with other nodes.
+    geometry: Option<Rc<Geometry>>,
 
-    /// Cached GL mesh built from the geometry (VAO, VBO, IBO).
-    gl_mesh: OnceCell<GLMesh>,
+    /// GPU mesh built from the geometry (VAO, VBO, IBO), shared with every node drawing the
+    /// same geometry unless the geometry is dynamic.
+    gl_mesh: OnceCell<Rc<GpuMesh>>,
 
-    shader: Option<GLShaderProgram>
+    /// Whether the geometry is rewritten often through
+    /// [`update_geometry`](Self::update_geometry), so its GPU mesh is private and updated
+    /// in place.
+    dynamic_geometry: bool,
 
+    /// Cached local-space bounding sphere of the geometry, as (center, radius).
+    bounds: OnceCell<([f32; 3], f32)>,
+
+    /// Triangle hierarchy of the geometry for raycasts, shared by every node with the same
+    /// geometry and looked up when the geometry is set; `None` for geometry without
+    /// triangles.
+    bvh: Option<Rc<MeshBvh>>,
+
+    /// The material used to draw the geometry (shader, color, textures, render state).
+    material: Option<Material>,
+
+    /// Optional normals/tangents debug visualization drawn on top of the geometry.
+    debug_normals: Option<NormalsDebug>,
+
+    /// Optional light emitted from this node's position, along its -Z axis.
+    light: Option<Light>,
+
+    /// Optional per-instance transforms; the geometry is drawn once per instance.
+    instances: Option<InstancedMesh>,
+
+    /// Whether the object is skipped when outside the camera's view.
+    /// Disabled for helpers that cover the whole view, like infinite grids.
+    frustum_culled: bool,
+
+    /// Whether the node and its subtree are drawn; hidden nodes keep all their state.
+    visible: bool,
+
+    /// Render layers the node is in, one bit each; cameras draw it if their cull mask
+    /// shares a bit.
+    layers: u32,
+
+    /// Whether the update callbacks of the node and its subtree run.
+    enabled: bool,
+
+    /// Per-frame update callback (animation, behaviour), run by [`Scene::update`](crate::engine::scene::Scene::update).
+    update: Option<UpdateCallback>,
+
+    /// When the update callback is allowed to run.
+    update_policy: UpdatePolicy,
+
+    /// Whether the update callback was skipped on the last scene update.
+    update_suspended: bool,
+
+    /// Distance band the node updates in, see [`activation`](crate::engine::activation).
+    activation: Activation,
+
+    /// Clock time of the last update callback run, if it has been running since; reduced-rate
+    /// nodes get the time since as their delta.
+    last_update: Option<f64>,
+
+    /// Camera distances the node is drawn at, if limited.
+    draw_distance: Option<DrawDistance>,
+
+    /// World matrix at the last motion vector render that drew the node, with that render's
+    /// frame number, see [`motion`](crate::engine::motion).
+    previous_world: Option<(u64, [f32; 16])>,
+
+    /// Unique id, stable for the node's lifetime.
+    id: NodeId,
+
+    /// The node's own `Rc`, to register it in the tag and component indices.
+    this: Weak<RefCell<Object3D>>,
+
+    /// Name to find the node by, see [`Scene::find_by_name`](crate::engine::scene::Scene::find_by_name).
+    name: Option<String>,
+
+    /// Labels gameplay code finds nodes by, see [`Scene::with_tag`](crate::engine::scene::Scene::with_tag).
+    tags: Vec<String>,
+
+    /// Key the node's dynamic state is stored under in save games; `None` if not saved.
+    persistent_id: Option<String>,
+
+    /// Typed gameplay data, at most one value per type.
+    components: Components,
+
+    /// Typed data attached by the game, at most one value per type; unlike components, not
+    /// indexed or saved.
+    user_data: Components,
+
+    /// Annotations authored in DCC tools, like glTF extras, by key.
+    metadata: BTreeMap<String, Value>,
+}
+
+/// The render layers of new nodes: layer 0 only.
+pub const DEFAULT_LAYERS: u32 = 1;
+
+/// Per-node update callback: receives the node itself and the frame clock.
+pub type NodeUpdate = Box<dyn FnMut(&mut Object3D, &Clock)>;
+
+/// Wrapper so `Object3D` can keep deriving `Debug` with a boxed closure inside.
+struct UpdateCallback(NodeUpdate);
+
+impl std::fmt::Debug for UpdateCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("UpdateCallback")
+    }
+}
+
+/// Controls when a node's update callback runs, so content the player can't see doesn't
+/// pay for animation and simulation.
+///
+/// The default runs the update every frame.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::object3d::{Object3D, UpdatePolicy};
+/// let crowd_member = Object3D::new();
+/// crowd_member.borrow_mut().set_update_policy(UpdatePolicy {
+///     pause_when_offscreen: true,
+///     max_distance: Some(50.0),
+///     ..UpdatePolicy::default()
+/// });
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UpdatePolicy {
+    /// Never run the update (manual pause, e.g. for a paused cutscene actor).
+    pub paused: bool,
+
+    /// Skip the update while the node is outside the camera's view.
+    pub pause_when_offscreen: bool,
+
+    /// Skip the update while the node is farther than this from the camera.
+    pub max_distance: Option<f32>,
+
+    /// Ignore `pause_when_offscreen` and `max_distance`, e.g. for gameplay-critical objects
+    /// whose state must stay correct even when unseen. Does not override `paused`.
+    pub always_simulate: bool,
+}
+
+impl UpdatePolicy {
+    /// Whether an update should run for a node with the world-space bounding sphere
+    /// (`world_position`, `radius`), seen from `camera`.
+    ///
+    /// Without a camera, only `paused` is taken into account.
+    pub fn allows(&self, world_position: [f32; 3], radius: f32, camera: Option<&Camera>) -> bool {
+        if self.paused {
+            return false;
+        }
+        let Some(camera) = camera else {
+            return true;
+        };
+        if self.always_simulate {
+            return true;
+        }
+
+        if let Some(max_distance) = self.max_distance
+            && distance_squared(world_position, camera.position) > max_distance * max_distance
+        {
+            return false;
+        }
+        !self.pause_when_offscreen || camera.intersects_sphere(world_position, radius)
+    }
+}
+
+/// Identifies a node for its whole lifetime; never reused while the program runs. Unlike
+/// an `Rc` it is `Send`, so it can refer to nodes from other threads, e.g. in a
+/// [`FrameSnapshot`](crate::engine::snapshot::FrameSnapshot).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(u64);
+
+impl NodeId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        NodeId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Limits the camera distances at which a node is drawn, for level-of-detail swaps: each
+/// detail level of an object gets an adjacent range and exactly one of them is drawn.
+///
+/// Distances are measured from the camera to `pivot`, or to the node's world bounding
+/// sphere center if `None`. Nodes that replace each other should share a pivot, so their
+/// ranges are compared against the same distance and never both (or neither) draw.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::object3d::{DrawDistance, Object3D};
+/// let (detailed, proxy) = (Object3D::new(), Object3D::new());
+/// let pivot = Some([0.0, 0.0, 0.0]);
+/// detailed.borrow_mut().set_draw_distance(Some(DrawDistance { min: 0.0, max: 100.0, pivot }));
+/// proxy.borrow_mut().set_draw_distance(Some(DrawDistance { min: 100.0, max: f32::INFINITY, pivot }));
+///
+/// let range = proxy.borrow().draw_distance().unwrap();
+/// assert!(!range.includes([0.0, 0.0, 0.0], [0.0, 0.0, 50.0]));
+/// assert!(range.includes([0.0, 0.0, 0.0], [0.0, 0.0, 150.0]));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawDistance {
+    /// Closest distance the node is drawn at (inclusive).
+    pub min: f32,
+
+    /// Distance from which the node is no longer drawn (exclusive).
+    pub max: f32,
+
+    /// World-space point distances are measured to, instead of the node's bounds center.
+    pub pivot: Option<[f32; 3]>,
+}
+
+impl DrawDistance {
+    /// Whether a node whose bounds are centered at `center` is drawn from `eye`.
+    pub fn includes(&self, center: [f32; 3], eye: [f32; 3]) -> bool {
+        let distance = distance_squared(self.pivot.unwrap_or(center), eye).sqrt();
+        distance >= self.min && distance < self.max
+    }
+}
+
+/// Subtrees with at least this many nodes are culled in parallel by
+/// [`Object3D::visible_set`].
+const PARALLEL_CULL_THRESHOLD: usize = 1024;
+
+/// What the culling test needs of a node, gathered on the main thread so the test itself
+/// can run on the job pool.
+#[derive(Clone, Copy, Debug)]
+struct CullRecord {
+    world_matrix: [f32; 16],
+    local_bounds: ([f32; 3], f32),
+    draw_distance: Option<DrawDistance>,
+    frustum_culled: bool,
+    layers: u32,
+}
+
+impl CullRecord {
+    fn passes(&self, frustum: &Frustum, eye: [f32; 3], cull_mask: u32) -> bool {
+        if self.layers & cull_mask == 0 {
+            return false;
+        }
+        let (center, radius) = transform_sphere(&self.world_matrix, self.local_bounds);
+        if let Some(range) = &self.draw_distance
+            && !range.includes(center, eye)
+        {
+            return false;
+        }
+        !self.frustum_culled || frustum.intersects_sphere(center, radius)
+    }
+}
+
+/// Moves a local-space bounding sphere into world space.
+fn transform_sphere(world_matrix: &[f32; 16], (center, radius): ([f32; 3], f32)) -> ([f32; 3], f32) {
+    // Non-uniform scale stretches the sphere; the longest axis bounds it
+    let axis_scale = (0..3)
+        .map(|c| length([world_matrix[c * 4], world_matrix[c * 4 + 1], world_matrix[c * 4 + 2]]))
+        .fold(0.0f32, f32::max);
+    (transform_point(world_matrix, center), radius * axis_scale)
 }
 
 impl Object3D {
@@ -70,12 +341,13 @@ impl Object3D {
     /// - identity rotation (no rotation)
     /// - uniform scale of 1 on all axes
     /// - identity matrices cached (no transform)
+    ///
     /// The object initially marked dirty to force matrix calculation on first use.
     ///
     /// Returns a reference-counted, mutable Object3D wrapped in `Rc<RefCell<>>`
     /// to enable shared ownership and interior mutability.
     pub fn new() -> Rc<RefCell<Self>> {
-        Rc::new(RefCell::new(Self {
+        Rc::new_cyclic(|this| RefCell::new(Self {
             position: [0.0, 0.0, 0.0],
             rotation: [0.0, 0.0, 0.0, 1.0], // identity quaternion
             scale: [1.0, 1.0, 1.0],
@@ -86,7 +358,32 @@ impl Object3D {
             children: Vec::new(),
             geometry: None,
             gl_mesh: OnceCell::new(),
-            shader: None,
+            dynamic_geometry: false,
+            bounds: OnceCell::new(),
+            bvh: None,
+            material: None,
+            debug_normals: None,
+            light: None,
+            instances: None,
+            frustum_culled: true,
+            visible: true,
+            layers: DEFAULT_LAYERS,
+            enabled: true,
+            update: None,
+            update_policy: UpdatePolicy::default(),
+            update_suspended: false,
+            activation: Activation::default(),
+            last_update: None,
+            draw_distance: None,
+            previous_world: None,
+            id: NodeId::next(),
+            this: this.clone(),
+            name: None,
+            tags: Vec::new(),
+            persistent_id: None,
+            components: Components::default(),
+            user_data: Components::default(),
+            metadata: BTreeMap::new(),
         }))
     }
 
@@ -108,6 +405,184 @@ impl Object3D {
         this.borrow_mut().children.push(child);
     }
 
+    /// Adds a child at position `index` among this object's children (clamped to the end),
+    /// like [`add_child`](Self::add_child) does at the end.
+    pub fn insert_child(this: &Rc<RefCell<Self>>, index: usize, child: Rc<RefCell<Self>>) {
+        {
+            let mut child_borrow = child.borrow_mut();
+            child_borrow.parent = Some(Rc::downgrade(this));
+            child_borrow.mark_dirty();
+        }
+        let mut parent = this.borrow_mut();
+        let index = index.min(parent.children.len());
+        parent.children.insert(index, child);
+    }
+
+    /// Removes `child` from this object's children.
+    ///
+    /// The child's parent link is cleared and it (with its subtree) is marked dirty, so its
+    /// world transform becomes its local transform. Returns `false` if `child` was not a
+    /// direct child of this object.
+    pub fn remove_child(this: &Rc<RefCell<Self>>, child: &Rc<RefCell<Self>>) -> bool {
+        let removed = {
+            let mut parent = this.borrow_mut();
+            let count = parent.children.len();
+            parent.children.retain(|c| !Rc::ptr_eq(c, child));
+            parent.children.len() != count
+        };
+        if removed {
+            let mut child_borrow = child.borrow_mut();
+            child_borrow.parent = None;
+            child_borrow.mark_dirty();
+        }
+        removed
+    }
+
+    /// Removes this object from its parent, if it has one.
+    ///
+    /// Returns `false` if the object had no (live) parent.
+    pub fn detach(this: &Rc<RefCell<Self>>) -> bool {
+        let parent = this.borrow().parent.as_ref().and_then(Weak::upgrade);
+        match parent {
+            Some(parent) => Self::remove_child(&parent, this),
+            None => {
+                this.borrow_mut().parent = None;
+                false
+            }
+        }
+    }
+
+    /// Returns the object's parent, if it has one that is still alive.
+    pub fn parent(&self) -> Option<Rc<RefCell<Self>>> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    /// Returns the object's direct children.
+    pub fn children(&self) -> &[Rc<RefCell<Self>>] {
+        &self.children
+    }
+
+    /// Returns `true` if `node` is `this` or one of its descendants.
+    pub fn contains(this: &Rc<RefCell<Self>>, node: &Rc<RefCell<Self>>) -> bool {
+        Rc::ptr_eq(this, node) || this.borrow().children.iter().any(|child| Self::contains(child, node))
+    }
+
+    /// Calls `f` with `this` and each of its descendants, depth first, parents before their
+    /// children. No node is borrowed during the call, and children `f` adds to the node it
+    /// was called with are visited too.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// let (car, wheel, hubcap) = (Object3D::new(), Object3D::new(), Object3D::new());
+    /// Object3D::add_child(&wheel, hubcap);
+    /// Object3D::add_child(&car, wheel);
+    ///
+    /// let mut count = 0;
+    /// Object3D::traverse(&car, |node| {
+    ///     node.borrow_mut().set_frustum_culled(false);
+    ///     count += 1;
+    /// });
+    /// assert_eq!(count, 3);
+    /// ```
+    pub fn traverse(this: &Rc<RefCell<Self>>, mut f: impl FnMut(&Rc<RefCell<Self>>)) {
+        Self::traverse_until(this, &mut |node| {
+            f(node);
+            false
+        });
+    }
+
+    /// Visits nodes like [`traverse`](Self::traverse) until `f` returns `true`; returns
+    /// whether it did.
+    fn traverse_until(this: &Rc<RefCell<Self>>, f: &mut dyn FnMut(&Rc<RefCell<Self>>) -> bool) -> bool {
+        if f(this) {
+            return true;
+        }
+        let children = this.borrow().children.clone();
+        children.iter().any(|child| Self::traverse_until(child, f))
+    }
+
+    /// The first node, depth first from `this` (included), for which `predicate` holds.
+    ///
+    /// # Panics
+    /// Panics if a node visited is mutably borrowed.
+    pub fn find(this: &Rc<RefCell<Self>>, mut predicate: impl FnMut(&Object3D) -> bool) -> Option<Rc<RefCell<Self>>> {
+        let mut found = None;
+        Self::traverse_until(this, &mut |node| {
+            let matches = predicate(&node.borrow());
+            if matches {
+                found = Some(node.clone());
+            }
+            matches
+        });
+        found
+    }
+
+    /// The first node named `name` (see [`set_name`](Self::set_name)), depth first from
+    /// `this` (included), e.g. a part of an imported model.
+    ///
+    /// # Panics
+    /// Panics if a node visited is mutably borrowed.
+    pub fn find_by_name(this: &Rc<RefCell<Self>>, name: &str) -> Option<Rc<RefCell<Self>>> {
+        Self::find(this, |node| node.name() == Some(name))
+    }
+
+    /// Every node with `tag` from `this` (included) down, depth first.
+    ///
+    /// # Panics
+    /// Panics if a node visited is mutably borrowed.
+    pub fn find_all_with_tag(this: &Rc<RefCell<Self>>, tag: &str) -> Vec<Rc<RefCell<Self>>> {
+        let mut found = Vec::new();
+        Self::traverse(this, |node| {
+            if node.borrow().has_tag(tag) {
+                found.push(node.clone());
+            }
+        });
+        found
+    }
+
+    /// Moves this object under `new_parent` (or to no parent, with `None`) while keeping
+    /// its world transform, by recomputing its local position, rotation and scale.
+    ///
+    /// Shear cannot be represented by the local transform, so reparenting under a
+    /// non-uniformly scaled parent with a rotated child is approximate. If the new parent's
+    /// transform is singular (zero scale), the local transform is left unchanged.
+    ///
+    /// # Panics
+    /// Panics if `new_parent` is this object or one of its descendants.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// let (vehicle, passenger) = (Object3D::new(), Object3D::new());
+    /// vehicle.borrow_mut().set_position([10.0, 0.0, 0.0]);
+    /// passenger.borrow_mut().set_position([12.0, 0.0, 0.0]);
+    ///
+    /// Object3D::reparent_keep_world(&passenger, Some(&vehicle));
+    /// assert_eq!(passenger.borrow().position(), [2.0, 0.0, 0.0]);
+    /// ```
+    pub fn reparent_keep_world(this: &Rc<RefCell<Self>>, new_parent: Option<&Rc<RefCell<Self>>>) {
+        if let Some(new_parent) = new_parent {
+            assert!(!Self::contains(this, new_parent), "cannot reparent an object under itself or its descendant");
+        }
+
+        let world = this.borrow_mut().world_matrix();
+        Self::detach(this);
+
+        let Some(new_parent) = new_parent else {
+            let (position, rotation, scale) = decompose_matrix(&world);
+            this.borrow_mut().set_transform(position, rotation, scale);
+            return;
+        };
+
+        let parent_world = new_parent.borrow_mut().world_matrix();
+        if let Some(parent_inverse) = matrix_inverse_4x4(&parent_world) {
+            let (position, rotation, scale) = decompose_matrix(&matrix_mul_4x4(&parent_inverse, &world));
+            this.borrow_mut().set_transform(position, rotation, scale);
+        }
+        Self::add_child(new_parent, this.clone());
+    }
+
     /// Recursively marks this object and all its children as 'dirty',
     /// indicating their local/world matrices need recalculating.
     ///
@@ -122,11 +597,956 @@ impl Object3D {
         }
     }
 
+    /// Replaces the object's geometry.
+    ///
+    /// The node's reference to its previous GPU mesh is released; the new geometry is uploaded
+    /// on the next draw, unless a node drawing identical geometry already uploaded it. Its
+    /// [triangle hierarchy](MeshBvh::shared) for raycasts is built right away, so the first
+    /// pick or line-of-sight check doesn't hitch.
     pub fn set_geometry(&mut self, geometry: Geometry) {
-        self.geometry = Option::from(geometry.to_owned());
+        self.set_shared_geometry(Rc::new(geometry));
+    }
+
+    /// Replaces the object's geometry with one shared with other nodes. Nodes sharing a
+    /// geometry also share its GPU buffers and triangle hierarchy, and the contents are only
+    /// compared once.
+    ///
+    /// ```
+    /// # use std::rc::Rc;
+    /// # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+    /// let rock = Rc::new(Geometry::from_positions(Topology::Points, &[[0.0, 0.0, 0.0]]));
+    /// let (a, b) = (Object3D::new(), Object3D::new());
+    /// a.borrow_mut().set_shared_geometry(rock.clone());
+    /// b.borrow_mut().set_shared_geometry(rock.clone());
+    /// assert_eq!(Rc::strong_count(&rock), 3);
+    /// ```
+    pub fn set_shared_geometry(&mut self, geometry: Rc<Geometry>) {
+        self.bvh = MeshBvh::shared(&geometry);
+        self.geometry = Some(geometry);
+        self.gl_mesh = OnceCell::new();
+        self.dynamic_geometry = false;
+        self.bounds = OnceCell::new();
+        if let Some(ref mut debug) = self.debug_normals {
+            debug.invalidate();
+        }
+        self.mark_dirty();
+    }
+
+    /// Replaces the object's geometry with a new version of itself, e.g. a simulated cloth
+    /// step. The node gets a private [dynamic](GpuMesh::dynamic) GPU mesh; while only the
+    /// vertices change (same topology, vertex count, indices and skin) it is rewritten in
+    /// place instead of being uploaded and hashed again like with
+    /// [`set_geometry`](Self::set_geometry). Requires a current GL context once the mesh
+    /// was uploaded.
+    pub fn update_geometry(&mut self, geometry: Geometry) {
+        let same_layout = self.geometry.as_deref().is_some_and(|old| {
+            old.topology == geometry.topology
+                && old.vertices.len() == geometry.vertices.len()
+                && old.indices == geometry.indices
+                && old.skin == geometry.skin
+        });
+        match self.gl_mesh.get() {
+            Some(mesh) if self.dynamic_geometry && same_layout => mesh.update_vertices(&geometry.vertices),
+            _ => self.gl_mesh = OnceCell::new(),
+        }
+        self.dynamic_geometry = true;
+        self.bvh = MeshBvh::build(&geometry).map(Rc::new);
+        self.geometry = Some(Rc::new(geometry));
+        self.bounds = OnceCell::new();
+        if let Some(ref mut debug) = self.debug_normals {
+            debug.invalidate();
+        }
+        self.mark_dirty();
+    }
+
+    /// The object's geometry, if any.
+    pub fn geometry(&self) -> Option<&Geometry> {
+        self.geometry.as_deref()
+    }
+
+    /// The object's geometry as a shareable reference, e.g. to give another node the same mesh.
+    pub fn shared_geometry(&self) -> Option<Rc<Geometry>> {
+        self.geometry.clone()
+    }
+
+    /// The geometry's GPU mesh, uploading it on first use. Requires a current GL context.
+    pub(crate) fn gpu_mesh(&self) -> Option<&Rc<GpuMesh>> {
+        let geometry = self.geometry.as_ref()?;
+        Some(self.gl_mesh.get_or_init(|| self.create_gpu_mesh(geometry)))
+    }
+
+    /// Uploads `geometry`, privately if it is dynamic.
+    fn create_gpu_mesh(&self, geometry: &Rc<Geometry>) -> Rc<GpuMesh> {
+        if self.dynamic_geometry {
+            Rc::new(GpuMesh::dynamic(geometry, "Object3D dynamic mesh"))
+        } else {
+            GpuMesh::shared(geometry, "Object3D mesh")
+        }
+    }
+
+    /// The vertex array of the node's compute-skinned vertices, if the last
+    /// [compute skinning](crate::engine::animation::compute_skinning) pass deformed its
+    /// current geometry.
+    pub(crate) fn deformed_vao(&self) -> Option<GLuint> {
+        let deformed = self.component::<Skin>()?.deformed.as_ref()?;
+        deformed.vao_for(self.gl_mesh.get()?)
+    }
+
+    /// Uploads the geometry to the GPU now instead of on first draw, e.g. during a loading
+    /// screen so spawning the object later doesn't hitch. Does nothing without geometry or
+    /// if it is already uploaded. Requires a current GL context.
+    pub fn upload_geometry(&self) {
+        if let Some(geometry) = &self.geometry {
+            self.gl_mesh.get_or_init(|| self.create_gpu_mesh(geometry));
+        }
+    }
+
+    /// Sets the material used to draw this object's geometry.
+    pub fn set_material(&mut self, material: Material) {
+        self.material = Some(material);
+    }
+
+    /// Returns the object's material, if any.
+    pub fn material(&self) -> Option<&Material> {
+        self.material.as_ref()
+    }
+
+    /// Returns the object's material mutably, e.g. to tweak its color or blend state.
+    pub fn material_mut(&mut self) -> Option<&mut Material> {
+        self.material.as_mut()
+    }
+
+    /// Enables (`Some`) or disables (`None`) drawing this object's vertex normals and tangents
+    /// as colored lines, for diagnosing lighting artifacts.
+    ///
+    /// ```no_run
+    /// # use rustge::engine::{debug::normals::NormalsDebug, object3d::Object3D};
+    /// let object = Object3D::new();
+    /// object.borrow_mut().set_debug_normals(Some(NormalsDebug::with_tangents(0.1)));
+    /// ```
+    pub fn set_debug_normals(&mut self, debug: Option<NormalsDebug>) {
+        self.debug_normals = debug;
+    }
+
+    /// Attaches (`Some`) or removes (`None`) a light on this node.
+    ///
+    /// The light follows the node's world transform; directional and spot lights shine along
+    /// the node's local -Z axis.
+    pub fn set_light(&mut self, light: Option<Light>) {
+        self.light = light;
+    }
+
+    /// Returns the node's light, if any.
+    pub fn light(&self) -> Option<&Light> {
+        self.light.as_ref()
+    }
+
+    /// Returns the node's light mutably, e.g. to animate its color or intensity.
+    pub fn light_mut(&mut self) -> Option<&mut Light> {
+        self.light.as_mut()
+    }
+
+    /// Turns this node into an instanced mesh (`Some`), drawing its geometry once per
+    /// instance transform in a single draw call, or back into a plain mesh (`None`).
+    ///
+    /// The material's shader must read the instance matrix; see
+    /// [`InstancedMesh`](crate::engine::mesh::instanced) for the attribute layout.
+    pub fn set_instances(&mut self, instances: Option<InstancedMesh>) {
+        self.instances = instances;
+    }
+
+    /// Returns the node's instances, if it is instanced.
+    pub fn instances(&self) -> Option<&InstancedMesh> {
+        self.instances.as_ref()
+    }
+
+    /// Returns the node's instances mutably, e.g. to add, move or remove instances.
+    pub fn instances_mut(&mut self) -> Option<&mut InstancedMesh> {
+        self.instances.as_mut()
+    }
+
+    /// Gathers the lights of this node and its descendants, in world space, into `lights`.
+    pub fn collect_lights(&mut self, lights: &mut LightSet) {
+        let world_matrix = self.world_matrix();
+        self.collect_lights_under(&world_matrix, lights);
+    }
+
+    fn collect_lights_under(&mut self, world_matrix: &[f32; 16], lights: &mut LightSet) {
+        if !self.visible {
+            return;
+        }
+        if let Some(light) = &self.light {
+            lights.add(light.clone(), world_matrix);
+        }
+        for child in &self.children {
+            let mut child = child.borrow_mut();
+            let child_world = child.update_world_matrix(Some(world_matrix));
+            child.collect_lights_under(&child_world, lights);
+        }
+    }
+
+    /// Counts the nodes, geometry, materials and lights of this node and its descendants.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+    /// let node = Object3D::new();
+    /// let points = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+    /// node.borrow_mut().set_geometry(Geometry::from_positions(Topology::Triangles, &points));
+    /// Object3D::add_child(&node, Object3D::new());
+    ///
+    /// let stats = node.borrow().statistics();
+    /// assert_eq!((stats.nodes, stats.meshes, stats.triangles), (2, 1, 1));
+    /// ```
+    pub fn statistics(&self) -> SceneStatistics {
+        let mut stats = SceneStatistics::default();
+        let mut shaders = HashSet::new();
+        let mut textures = HashSet::new();
+        let mut gpu_meshes = HashSet::new();
+        let mut materials = Vec::new();
+        self.collect_statistics(&mut stats, &mut shaders, &mut textures, &mut gpu_meshes, &mut materials);
+        stats.materials = materials.len();
+        stats.shaders = shaders.len();
+        stats.textures = textures.len();
+        stats
+    }
+
+    /// Accumulates this subtree into `stats`; shaders, textures and GPU meshes are
+    /// deduplicated by identity, materials by what they upload and the state they set.
+    fn collect_statistics(
+        &self,
+        stats: &mut SceneStatistics,
+        shaders: &mut HashSet<*const GLShaderProgram>,
+        textures: &mut HashSet<*const Texture>,
+        gpu_meshes: &mut HashSet<*const GpuMesh>,
+        materials: &mut Vec<Material>,
+    ) {
+        stats.nodes += 1;
+
+        if let Some(geometry) = &self.geometry {
+            let indices = geometry.indices.len();
+            let bytes = std::mem::size_of_val(geometry.vertices.as_slice())
+                + geometry.indices.byte_size();
+            // Instanced geometry is stored once but drawn once per instance
+            let copies = self.instances.as_ref().map_or(1, InstancedMesh::len);
+            stats.meshes += 1;
+            stats.instances += self.instances.as_ref().map_or(0, InstancedMesh::len);
+            stats.vertices += geometry.vertices.len();
+            match geometry.topology {
+                Topology::Triangles => stats.triangles += copies * (indices / 3),
+                Topology::Lines => stats.line_segments += copies * (indices / 2),
+                Topology::LineStrip => stats.line_segments += copies * indices.saturating_sub(1),
+                Topology::Points => stats.points += copies * indices,
+            }
+            stats.geometry_bytes += bytes;
+            if let Some(mesh) = self.gl_mesh.get()
+                && gpu_meshes.insert(Rc::as_ptr(mesh))
+            {
+                stats.gpu_geometry_bytes += mesh.bytes;
+            }
+        }
+
+        if let Some(material) = &self.material {
+            if !materials.iter().any(|seen| seen.same_parameters(material) && seen.same_state(material)) {
+                materials.push(material.clone());
+            }
+            shaders.insert(Rc::as_ptr(&material.shader));
+            for (_, texture) in &material.textures {
+                if textures.insert(Rc::as_ptr(texture)) {
+                    // Base level plus roughly a third for the mip chain
+                    let base = (texture.width() * texture.height() * 4) as usize;
+                    stats.texture_bytes += base + base / 3;
+                }
+            }
+        }
+
+        if self.light.is_some() {
+            stats.lights += 1;
+        }
+
+        for child in &self.children {
+            child.borrow().collect_statistics(stats, shaders, textures, gpu_meshes, materials);
+        }
+    }
+
+    /// Registers this node's per-frame update callback, replacing any previous one.
+    ///
+    /// The callback receives the node and the frame clock; use it to animate or simulate the
+    /// node. Whether it runs each frame is controlled by the node's [`UpdatePolicy`].
+    ///
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// let spinner = Object3D::new();
+    /// spinner.borrow_mut().on_update(|node, clock| {
+    ///     let angle = clock.elapsed() as f32;
+    ///     node.set_rotation([0.0, (angle / 2.0).sin(), 0.0, (angle / 2.0).cos()]);
+    /// });
+    /// ```
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Object3D, &Clock) + 'static,
+    {
+        self.update = Some(UpdateCallback(Box::new(callback)));
+    }
+
+    /// Removes the node's update callback.
+    pub fn clear_update(&mut self) {
+        self.update = None;
+    }
+
+    /// Whether the node has an update callback.
+    pub fn has_update(&self) -> bool {
+        self.update.is_some()
+    }
+
+    /// Sets when the node's update callback runs.
+    pub fn set_update_policy(&mut self, policy: UpdatePolicy) {
+        self.update_policy = policy;
+    }
+
+    /// The node's update policy.
+    pub fn update_policy(&self) -> UpdatePolicy {
+        self.update_policy
+    }
+
+    /// Returns `true` if the node has an update callback that was skipped by its policy, or
+    /// is asleep beyond the scene's last [activation band](crate::engine::activation), on the
+    /// last scene update.
+    pub fn is_update_suspended(&self) -> bool {
+        self.update_suspended
+    }
+
+    /// The distance band the node updated in on the last scene update, or whether it slept.
+    /// Always [`Activation::Active(0)`](Activation::Active) without
+    /// [`ActivationSettings`] on the scene. Systems other than update callbacks, like audio
+    /// emitters, can use it to throttle themselves the same way.
+    pub fn activation(&self) -> Activation {
+        self.activation
+    }
+
+    /// Runs the update callbacks of this node and its descendants, as allowed by each
+    /// node's [`UpdatePolicy`] relative to `camera`. [Disabled](Self::set_enabled) nodes and
+    /// their subtrees are skipped.
+    pub fn update(&mut self, camera: Option<&Camera>, clock: &Clock) {
+        self.update_with_activation(camera, clock, None);
+    }
+
+    /// Runs the update callbacks as [`update`](Self::update) does, additionally throttling
+    /// or putting to sleep distant nodes by `activation`.
+    pub fn update_with_activation(&mut self, camera: Option<&Camera>, clock: &Clock, activation: Option<&ActivationSettings>) {
+        let parent_world = self
+            .parent
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map(|parent_rc| parent_rc.borrow_mut().world_matrix());
+        self.update_under(parent_world.as_ref(), camera, clock, activation);
+    }
+
+    fn update_under(
+        &mut self,
+        parent_world: Option<&[f32; 16]>,
+        camera: Option<&Camera>,
+        clock: &Clock,
+        activation: Option<&ActivationSettings>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        if let Some(mut callback) = self.update.take() {
+            let world = self.update_world_matrix(parent_world);
+            let (center, radius) = self.bounding_sphere_for(&world);
+            let run = self.update_policy.allows(center, radius, camera);
+            self.update_suspended = !run;
+            if run {
+                match self.activated_delta(center, camera, clock, activation) {
+                    Some(delta) if delta == clock.delta() => (callback.0)(self, clock),
+                    Some(delta) => (callback.0)(self, &clock.with_delta(delta)),
+                    None => {}
+                }
+            } else {
+                self.last_update = None;
+            }
+            // Keep it unless the callback registered a replacement
+            if self.update.is_none() {
+                self.update = Some(callback);
+            }
+        } else {
+            self.update_suspended = false;
+        }
+
+        // The callback may have moved the node; children need the fresh transform
+        let world = self.update_world_matrix(parent_world);
+        for child in &self.children {
+            child.borrow_mut().update_under(Some(&world), camera, clock, activation);
+        }
+    }
+
+    /// Moves the node to its activation band for this frame and returns the delta to run its
+    /// update callback with, or `None` if it skips this frame or sleeps.
+    fn activated_delta(
+        &mut self,
+        center: [f32; 3],
+        camera: Option<&Camera>,
+        clock: &Clock,
+        settings: Option<&ActivationSettings>,
+    ) -> Option<f32> {
+        let focus = settings.and_then(|settings| settings.focus.or(camera.map(|camera| camera.position)));
+        let (Some(settings), Some(focus)) = (settings, focus) else {
+            self.activation = Activation::default();
+            self.last_update = Some(clock.elapsed());
+            return Some(clock.delta());
+        };
+        if self.update_policy.always_simulate {
+            self.activation = Activation::Active(0);
+        } else {
+            self.activation = settings.activation(distance(center, focus), self.activation);
+        }
+        let Some(interval) = settings.interval(self.activation) else {
+            // Asleep: on waking, resume from the current frame instead of catching up
+            self.update_suspended = true;
+            self.last_update = None;
+            return None;
+        };
+        // Spread nodes sharing an interval over its frames rather than updating them all at once
+        let phase = (self.this.as_ptr() as usize as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+        if !(clock.frame_count() + phase).is_multiple_of(u64::from(interval)) {
+            return None;
+        }
+        let delta = match self.last_update {
+            Some(last) if interval > 1 => (clock.elapsed() - last) as f32,
+            _ => clock.delta(),
+        };
+        self.last_update = Some(clock.elapsed());
+        Some(delta)
+    }
+
+    /// Enables or disables frustum culling for this object (enabled by default). Children are
+    /// culled individually by their own bounds.
+    ///
+    /// Disable it for objects that position themselves relative to the camera in their
+    /// shader, or whose vertices are displaced beyond their geometry's bounds.
+    pub fn set_frustum_culled(&mut self, culled: bool) {
+        self.frustum_culled = culled;
+    }
+
+    /// Whether frustum culling is enabled for this object.
+    pub fn frustum_culled(&self) -> bool {
+        self.frustum_culled
+    }
+
+    /// Shows or hides the node and its descendants (shown by default). Hidden nodes stay in
+    /// the scene graph with their transforms, materials and callbacks, but aren't drawn,
+    /// picked or lit by their lights.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// # use rustge::engine::camera::Camera;
+    /// let door = Object3D::new();
+    /// Object3D::add_child(&door, Object3D::new());
+    /// door.borrow_mut().set_visible(false);
+    ///
+    /// let camera = Camera::new(1.0);
+    /// assert!(Object3D::visible_set(&door, &camera).is_empty());
+    /// ```
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Whether the node is shown; it is still hidden if an ancestor isn't.
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Puts the node in the render layers set in `layers`, one bit per layer (only layer 0,
+    /// [`DEFAULT_LAYERS`], by default). Cameras draw the node if their
+    /// [cull mask](Camera::cull_mask) includes one of its layers. Children have their own
+    /// layers.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::camera::Camera;
+    /// # use rustge::engine::object3d::{Object3D, DEFAULT_LAYERS};
+    /// const EFFECTS: u32 = 1 << 1;
+    /// let sparks = Object3D::new();
+    /// sparks.borrow_mut().set_layers(EFFECTS);
+    ///
+    /// let mut minimap_camera = Camera::new(1.0);
+    /// minimap_camera.cull_mask = DEFAULT_LAYERS;
+    /// assert!(!minimap_camera.is_visible(&sparks));
+    /// assert!(Camera::new(1.0).is_visible(&sparks));
+    /// ```
+    pub fn set_layers(&mut self, layers: u32) {
+        self.layers = layers;
+    }
+
+    /// The render layers the node is in, one bit per layer.
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Enables or disables the update callbacks of the node and its descendants (enabled by
+    /// default). Disabled nodes are still drawn, and resume where they were when enabled
+    /// again; hide them as well with [`set_visible`](Self::set_visible) to take them out of
+    /// the game entirely without losing their state.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Whether the node's update callback may run; it still doesn't if an ancestor is
+    /// disabled.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Limits the camera distances this object is drawn at (`None`, the default, draws it at
+    /// any distance). Children have their own ranges.
+    pub fn set_draw_distance(&mut self, range: Option<DrawDistance>) {
+        self.draw_distance = range;
+    }
+
+    /// The camera distances this object is drawn at, if limited.
+    pub fn draw_distance(&self) -> Option<DrawDistance> {
+        self.draw_distance
+    }
+
+    /// The bounding sphere of this node's geometry in world space, as (center, radius).
+    ///
+    /// Nodes without geometry are treated as a point at their world position (radius 0).
+    pub fn world_bounding_sphere(&mut self) -> ([f32; 3], f32) {
+        let world_matrix = self.world_matrix();
+        self.bounding_sphere_for(&world_matrix)
+    }
+
+    /// A world-space sphere enclosing the geometry of `this` and all its descendants, as
+    /// (center, radius), or `None` if no node in the subtree has geometry.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+    /// let parent = Object3D::new();
+    /// let child = Object3D::new();
+    /// child.borrow_mut().set_position([10.0, 0.0, 0.0]);
+    /// child.borrow_mut().set_geometry(Geometry::from_positions(Topology::Points, &[[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]]));
+    /// Object3D::add_child(&parent, child);
+    /// assert_eq!(Object3D::subtree_bounding_sphere(&parent), Some(([10.0, 0.0, 0.0], 1.0)));
+    /// ```
+    pub fn subtree_bounding_sphere(this: &Rc<RefCell<Self>>) -> Option<([f32; 3], f32)> {
+        let mut spheres = Vec::new();
+        Self::collect_bounding_spheres(this, &mut spheres);
+        if spheres.is_empty() {
+            return None;
+        }
+
+        // Center of the spheres' bounding box, grown to enclose every sphere
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for (center, radius) in &spheres {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(center[axis] - radius);
+                max[axis] = max[axis].max(center[axis] + radius);
+            }
+        }
+        let middle = lerp(min, max, 0.5);
+        let radius = spheres
+            .iter()
+            .map(|(center, radius)| distance(middle, *center) + radius)
+            .fold(0.0f32, f32::max);
+        Some((middle, radius))
+    }
+
+    /// The world-space axis-aligned box around the geometry of `this` and all its
+    /// descendants (including every instance), or `None` if no node in the subtree has geometry.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+    /// let node = Object3D::new();
+    /// node.borrow_mut().set_scale([2.0, 2.0, 2.0]);
+    /// node.borrow_mut().set_geometry(Geometry::from_positions(Topology::Points, &[[-1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]));
+    /// let bounds = Object3D::subtree_bounds(&node).unwrap();
+    /// assert_eq!((bounds.min, bounds.max), ([-2.0, 0.0, 0.0], [2.0, 2.0, 0.0]));
+    /// ```
+    pub fn subtree_bounds(this: &Rc<RefCell<Self>>) -> Option<Aabb> {
+        let (world_matrix, local, children) = {
+            let mut node = this.borrow_mut();
+            let world_matrix = node.world_matrix();
+            let mut local = node.geometry.as_ref().and_then(|geometry| geometry.aabb());
+            if let (Some(geometry_bounds), Some(instances)) = (local, &node.instances) {
+                local = instances
+                    .iter()
+                    .map(|(_, matrix)| geometry_bounds.transformed(matrix))
+                    .reduce(|a, b| a.union(&b));
+            }
+            (world_matrix, local, node.children.clone())
+        };
+        let own = local.map(|local| local.transformed(&world_matrix));
+        children
+            .iter()
+            .filter_map(Self::subtree_bounds)
+            .fold(own, |bounds, child| Some(bounds.map_or(child, |bounds| bounds.union(&child))))
+    }
+
+    /// The nearest hit of a world-space `ray` on the node's own triangles (not its
+    /// children's) within `max_distance`, as the distance along the ray (in units of its
+    /// direction) and the world-space face normal, turned to face the ray. Triangles are hit
+    /// from either side, every instance is tested, and skinned meshes are tested in their
+    /// bind pose. Uses the geometry's [shared](MeshBvh::shared) [`MeshBvh`], built when the
+    /// geometry was set.
+    ///
+    /// See [`Scene::raycast`](crate::engine::scene::Scene::raycast) to test a whole scene.
+    ///
+    /// ```
+    /// # use rustge::engine::{math::ray::Ray, object3d::{Geometry, Object3D, Topology}};
+    /// let node = Object3D::new();
+    /// node.borrow_mut().set_geometry(Geometry::from_positions(
+    ///     Topology::Triangles,
+    ///     &[[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+    /// ));
+    /// node.borrow_mut().set_position([0.0, 0.0, -4.0]);
+    ///
+    /// let ray = Ray::new([0.0; 3], [0.0, 0.0, -1.0]);
+    /// let (distance, normal) = node.borrow_mut().raycast(&ray, f32::INFINITY).unwrap();
+    /// assert_eq!((distance, normal), (4.0, [0.0, 0.0, 1.0]));
+    /// ```
+    pub fn raycast(&mut self, ray: &Ray, max_distance: f32) -> Option<(f32, [f32; 3])> {
+        let world_matrix = self.world_matrix();
+        let geometry = self.geometry.as_ref()?;
+        let bvh = self.bvh.as_ref()?;
+        let models: Vec<[f32; 16]> = match &self.instances {
+            Some(instances) => instances.iter().map(|(_, instance)| matrix_mul_4x4(&world_matrix, instance)).collect(),
+            None => vec![world_matrix],
+        };
+
+        let mut nearest = None;
+        let mut limit = max_distance;
+        for model in &models {
+            let Some(inverse) = matrix_inverse_4x4(model) else {
+                continue;
+            };
+            // The local ray keeps the world ray's parameterization, so t needs no conversion
+            if let Some((t, triangle)) = bvh.raycast(geometry, &ray.transformed(&inverse), limit) {
+                limit = t;
+                nearest = Some((t, model, triangle));
+            }
+        }
+        let (t, model, triangle) = nearest?;
+        let [a, b, c] = [0, 1, 2].map(|corner| {
+            transform_point(model, geometry.vertices[geometry.indices.get(triangle * 3 + corner) as usize].position)
+        });
+        let normal = normalize_or(cross(sub(b, a), sub(c, a)), [0.0, 1.0, 0.0]);
+        let facing = if dot(normal, ray.direction) > 0.0 { scale(normal, -1.0) } else { normal };
+        Some((t, facing))
+    }
+
+    fn collect_bounding_spheres(this: &Rc<RefCell<Self>>, spheres: &mut Vec<([f32; 3], f32)>) {
+        let children = {
+            let mut node = this.borrow_mut();
+            if node.geometry.is_some() {
+                spheres.push(node.world_bounding_sphere());
+            }
+            node.children.clone()
+        };
+        for child in &children {
+            Self::collect_bounding_spheres(child, spheres);
+        }
+    }
+
+    fn bounding_sphere_for(&self, world_matrix: &[f32; 16]) -> ([f32; 3], f32) {
+        transform_sphere(world_matrix, self.local_bounds())
+    }
+
+    /// The bounding sphere in local space, including instances; a point at the origin for
+    /// nodes without geometry.
+    fn local_bounds(&self) -> ([f32; 3], f32) {
+        let Some(geometry) = &self.geometry else {
+            return ([0.0; 3], 0.0);
+        };
+        let bounds = *self.bounds.get_or_init(|| geometry.bounding_sphere());
+        self.instances.as_ref().and_then(|instances| instances.bounding_sphere(bounds)).unwrap_or(bounds)
+    }
+
+    /// Whether this node passes the renderer's culling test (layers, draw distance and
+    /// frustum) for `camera`.
+    ///
+    /// See [`Camera::is_visible`], which does the same for a shared node.
+    pub fn is_visible(&mut self, camera: &Camera) -> bool {
+        let world_matrix = self.world_matrix();
+        self.visible_in(&world_matrix, &camera.frustum(), camera)
+    }
+
+    /// The culling test: the node is in a layer of `camera`'s cull mask, its draw distance
+    /// includes the camera and, unless frustum culling is off, its bounds intersect `frustum`.
+    fn visible_in(&self, world_matrix: &[f32; 16], frustum: &Frustum, camera: &Camera) -> bool {
+        self.cull_record(world_matrix).passes(frustum, camera.position, camera.cull_mask)
+    }
+
+    fn cull_record(&self, world_matrix: &[f32; 16]) -> CullRecord {
+        CullRecord {
+            world_matrix: *world_matrix,
+            local_bounds: self.local_bounds(),
+            draw_distance: self.draw_distance,
+            frustum_culled: self.frustum_culled,
+            layers: self.layers,
+        }
+    }
+
+    /// Gathers `this` and every descendant that passes the culling test for `camera` (layers,
+    /// draw distance and frustum), in depth-first order. [Hidden](Self::set_visible) nodes and
+    /// their subtrees are left out.
+    ///
+    /// Nodes without geometry are tested as points at their position. Large subtrees are
+    /// tested in parallel on the [job pool](crate::engine::jobs).
+    pub fn visible_set(this: &Rc<RefCell<Self>>, camera: &Camera) -> Vec<Rc<RefCell<Self>>> {
+        let parent_world = this
+            .borrow()
+            .parent
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map(|parent_rc| parent_rc.borrow_mut().world_matrix());
+        let (mut nodes, mut records) = (Vec::new(), Vec::new());
+        Self::collect_cull_records(this, parent_world.as_ref(), &mut nodes, &mut records);
+
+        let (frustum, eye, mask) = (camera.frustum(), camera.position, camera.cull_mask);
+        let passes: Vec<bool> = if records.len() >= PARALLEL_CULL_THRESHOLD {
+            jobs::parallel_map(&records, |record| record.passes(&frustum, eye, mask))
+        } else {
+            records.iter().map(|record| record.passes(&frustum, eye, mask)).collect()
+        };
+        nodes.into_iter().zip(passes).filter_map(|(node, passes)| passes.then_some(node)).collect()
+    }
+
+    /// Updates the world matrices of `this` and its descendants, gathering what the culling
+    /// test needs of each in depth-first order. The nodes aren't `Send`, their records are.
+    fn collect_cull_records(
+        this: &Rc<RefCell<Self>>,
+        parent_world: Option<&[f32; 16]>,
+        nodes: &mut Vec<Rc<RefCell<Self>>>,
+        records: &mut Vec<CullRecord>,
+    ) {
+        let (world_matrix, children) = {
+            let mut node = this.borrow_mut();
+            if !node.visible {
+                return;
+            }
+            let world_matrix = node.update_world_matrix(parent_world);
+            records.push(node.cull_record(&world_matrix));
+            nodes.push(this.clone());
+            (world_matrix, node.children.clone())
+        };
+        for child in &children {
+            Self::collect_cull_records(child, Some(&world_matrix), nodes, records);
+        }
+    }
+
+    /// Names the node, e.g. "player", to find it by with
+    /// [`Scene::find_by_name`](crate::engine::scene::Scene::find_by_name). Names needn't be
+    /// unique; an empty name removes it.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = (!name.is_empty()).then(|| name.to_string());
+    }
+
+    /// The node's name, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Adds `tag` to the node, if it doesn't have it yet. Tagged nodes are indexed, so
+    /// [`Scene::with_tag`](crate::engine::scene::Scene::with_tag) finds them without
+    /// searching the graph.
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+            index_tag(tag, &self.this);
+        }
+    }
+
+    /// Removes `tag` from the node; returns whether it had it.
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let had = self.has_tag(tag);
+        if had {
+            self.tags.retain(|other| other != tag);
+            unindex_tag(tag, &self.this);
+        }
+        had
+    }
+
+    /// Whether the node has `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    /// The node's tags, in the order they were added.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The node's unique id.
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Marks the node as persistent under `id`, or not with `None`: its transform and
+    /// registered components are captured in [save games](crate::engine::save) and restored
+    /// into the node with the same id when loading. Ids must be unique within a scene and
+    /// stable across game versions, e.g. `"castle/gate"`.
+    pub fn set_persistent_id(&mut self, id: Option<&str>) {
+        self.persistent_id = id.map(str::to_string);
+    }
+
+    /// The id the node is saved under, if it is persistent.
+    pub fn persistent_id(&self) -> Option<&str> {
+        self.persistent_id.as_deref()
+    }
+
+    /// Attaches a component, replacing and returning one of the same type. Nodes are
+    /// indexed by component type, see [`Scene::query`](crate::engine::scene::Scene::query).
+    ///
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// struct Health(f32);
+    /// let node = Object3D::new();
+    /// node.borrow_mut().insert_component(Health(100.0));
+    /// assert!(node.borrow().has_component::<Health>());
+    /// assert_eq!(node.borrow_mut().remove_component::<Health>().map(|health| health.0), Some(100.0));
+    /// ```
+    pub fn insert_component<T: 'static>(&mut self, component: T) -> Option<T> {
+        let previous = self.components.insert(component);
+        if previous.is_none() {
+            index_component(TypeId::of::<T>(), &self.this);
+        }
+        previous
+    }
+
+    /// The node's component of type `T`, if any.
+    pub fn component<T: 'static>(&self) -> Option<&T> {
+        self.components.get()
+    }
+
+    /// The node's component of type `T` for modification, if any.
+    pub fn component_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.components.get_mut()
+    }
+
+    /// Detaches and returns the node's component of type `T`, if any.
+    pub fn remove_component<T: 'static>(&mut self) -> Option<T> {
+        let removed = self.components.remove();
+        if removed.is_some() {
+            unindex_component(TypeId::of::<T>(), &self.this);
+        }
+        removed
+    }
+
+    /// Whether the node has a component of type `T`.
+    pub fn has_component<T: 'static>(&self) -> bool {
+        self.has_component_type(TypeId::of::<T>())
+    }
+
+    pub(crate) fn has_component_type(&self, type_id: TypeId) -> bool {
+        self.components.contains(type_id)
+    }
+
+    /// Takes the component of type `T` out without unindexing the node, to be put back
+    /// with [`restore_component`](Self::restore_component).
+    pub(crate) fn take_component<T: 'static>(&mut self) -> Option<T> {
+        self.components.remove()
+    }
+
+    /// Puts back a component taken with [`take_component`](Self::take_component), unless
+    /// one of its type was inserted meanwhile (which then stays).
+    pub(crate) fn restore_component<T: 'static>(&mut self, component: T) {
+        if let Some(replacement) = self.components.insert(component) {
+            // The replacement's insertion indexed the node a second time
+            self.components.insert(replacement);
+            unindex_component(TypeId::of::<T>(), &self.this);
+            index_component(TypeId::of::<T>(), &self.this);
+        }
+    }
+
+    /// Attaches a value of any type to the node, replacing and returning one of the same
+    /// type. Unlike [components](Self::insert_component), user data isn't indexed for
+    /// queries or captured in save games, so it costs nothing beyond its storage.
+    ///
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// struct SpawnedBy(&'static str);
+    /// let node = Object3D::new();
+    /// node.borrow_mut().set_user_data(SpawnedBy("wave 3"));
+    /// assert_eq!(node.borrow().user_data::<SpawnedBy>().map(|spawner| spawner.0), Some("wave 3"));
+    /// ```
+    pub fn set_user_data<T: 'static>(&mut self, value: T) -> Option<T> {
+        self.user_data.insert(value)
+    }
+
+    /// The node's user data of type `T`, if any.
+    pub fn user_data<T: 'static>(&self) -> Option<&T> {
+        self.user_data.get()
+    }
+
+    /// The node's user data of type `T` for modification, if any.
+    pub fn user_data_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.user_data.get_mut()
+    }
+
+    /// Detaches and returns the node's user data of type `T`, if any.
+    pub fn remove_user_data<T: 'static>(&mut self) -> Option<T> {
+        self.user_data.remove()
+    }
+
+    /// Sets the metadata entry `key`, replacing and returning its previous value.
+    ///
+    /// Metadata holds annotations authored in DCC tools, read by the importers: the extras
+    /// of glTF nodes, and the object, groups and material of OBJ meshes. It is written back
+    /// as extras by the [glTF exporter](crate::engine::export::gltf).
+    ///
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// let trigger = Object3D::new();
+    /// trigger.borrow_mut().set_metadata("on_enter", "open_gate".into());
+    /// assert_eq!(trigger.borrow().metadata("on_enter").and_then(|value| value.as_str()), Some("open_gate"));
+    /// ```
+    pub fn set_metadata(&mut self, key: &str, value: Value) -> Option<Value> {
+        self.metadata.insert(key.to_string(), value)
+    }
+
+    /// The metadata entry `key`, if any.
+    pub fn metadata(&self, key: &str) -> Option<&Value> {
+        self.metadata.get(key)
+    }
+
+    /// Removes and returns the metadata entry `key`, if any.
+    pub fn remove_metadata(&mut self, key: &str) -> Option<Value> {
+        self.metadata.remove(key)
+    }
+
+    /// Every metadata entry, by key.
+    pub fn metadata_entries(&self) -> &BTreeMap<String, Value> {
+        &self.metadata
+    }
+
+    /// Sets position, rotation and scale at once and marks the object dirty.
+    pub fn set_transform(&mut self, position: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) {
+        self.position = position;
+        self.rotation = rotation;
+        self.scale = scale;
         self.mark_dirty();
     }
 
+    /// The local position [x, y, z].
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    /// The local rotation quaternion [x, y, z, w].
+    pub fn rotation(&self) -> [f32; 4] {
+        self.rotation
+    }
+
+    /// The local scale [x, y, z].
+    pub fn scale(&self) -> [f32; 3] {
+        self.scale
+    }
+
     /// Updates the object's position and marks it dirty for recalculation.
     ///
     /// `pos` is the new position vector [x, y, z].
@@ -173,27 +1593,80 @@ impl Object3D {
     ///
     /// If there is no parent, the world matrix is the same as the local matrix.
     pub fn world_matrix(&mut self) -> [f32; 16] {
+        if self.dirty {
+            // Borrow the parent mutably to get its world matrix (recursive update if needed).
+            // No parent, or a dropped one, leaves the world matrix equal to the local matrix.
+            let parent_world = self
+                .parent
+                .as_ref()
+                .and_then(Weak::upgrade)
+                .map(|parent_rc| parent_rc.borrow_mut().world_matrix());
+            self.update_world_matrix(parent_world.as_ref());
+        }
+
+        self.world_matrix
+    }
+
+    /// The world matrix the node had when [motion vectors](crate::engine::motion) were last
+    /// rendered with it in view; `None` if they never were or after
+    /// [`reset_motion`](Self::reset_motion).
+    pub fn previous_world_matrix(&self) -> Option<[f32; 16]> {
+        self.previous_world.map(|(_, matrix)| matrix)
+    }
+
+    /// Forgets the previous world matrix, so the next motion vectors show the node as not
+    /// having moved. Call after teleporting it, or it smears across the screen in motion blur.
+    pub fn reset_motion(&mut self) {
+        self.previous_world = None;
+    }
+
+    /// Records the current world matrix for motion vector frame `frame`, returning the one
+    /// to measure motion from: the matrix of frame `frame - 1`, or the current one if the
+    /// node wasn't drawn then (e.g. it was culled, so its old matrix is stale).
+    pub(crate) fn advance_motion(&mut self, frame: u64) -> [f32; 16] {
+        let current = self.world_matrix();
+        let previous = match self.previous_world {
+            Some((recorded, matrix)) if recorded + 1 == frame => matrix,
+            _ => current,
+        };
+        self.previous_world = Some((frame, current));
+        previous
+    }
+
+    /// Converts a point from world space into this object's local space.
+    ///
+    /// If the world transform is singular (e.g. a zero scale) the point is returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::object3d::Object3D;
+    /// let node = Object3D::new();
+    /// node.borrow_mut().set_position([1.0, 2.0, 3.0]);
+    /// assert_eq!(node.borrow_mut().world_to_local([1.0, 2.0, 4.0]), [0.0, 0.0, 1.0]);
+    /// ```
+    pub fn world_to_local(&mut self, point: [f32; 3]) -> [f32; 3] {
+        transform_point(&matrix_inverse_or_identity(&self.world_matrix()), point)
+    }
+
+    /// Converts a point from this object's local space into world space.
+    pub fn local_to_world(&mut self, point: [f32; 3]) -> [f32; 3] {
+        transform_point(&self.world_matrix(), point)
+    }
+
+    /// Recomputes the cached matrices if dirty, given the parent's up-to-date world matrix.
+    ///
+    /// Used when walking the tree top-down, where the parent is already borrowed and can't
+    /// be reached through the weak reference.
+    fn update_world_matrix(&mut self, parent_world: Option<&[f32; 16]>) -> [f32; 16] {
         if self.dirty {
             // Recompute the local matrix first if dirty
             self.local_matrix = compute_local_matrix(self.position, self.rotation, self.scale);
 
-            // Compute world matrix by combining with parent's world matrix
-            if let Some(ref weak_parent) = self.parent {
-                if let Some(parent_rc) = weak_parent.upgrade() {
-                    // Borrow parent mutably to get its world matrix (recursive update if needed)
-                    let mut parent = parent_rc.borrow_mut();
-                    let parent_world = parent.world_matrix();
-
-                    // Multiply parent's world matrix by local matrix to get world matrix
-                    self.world_matrix = matrix_mul_4x4(&parent_world, &self.local_matrix);
-                } else {
-                    // Parent was dropped; fallback to local matrix
-                    self.world_matrix = self.local_matrix;
-                }
-            } else {
-                // No parent, so world matrix is just local matrix
-                self.world_matrix = self.local_matrix;
-            }
+            // Multiply parent's world matrix by local matrix to get world matrix
+            self.world_matrix = match parent_world {
+                Some(parent_world) => matrix_mul_4x4(parent_world, &self.local_matrix),
+                None => self.local_matrix,
+            };
 
             // Mark as clean (not dirty)
             self.dirty = false;
@@ -207,53 +1680,106 @@ impl Object3D {
     ///
     /// Uploads vertex/index data to the GPU on the first draw call,
     /// then issues a glDrawElements command.
-    /// Renders the object and all of its children using the provided shader and camera.
+    /// Renders the object and all of its children using their materials and the provided camera.
     ///
-    /// Performs frustum culling and sets the "u_model" uniform before drawing.
+    /// Culls each object by its bounding sphere, binds the object's material, and sets the "u_model"
+    /// and "u_normal_matrix" (see [`normal_matrix`]) uniforms before drawing. The camera and
+    /// lights go to the [standard uniform blocks](crate::engine::uniform_buffer), uploaded once
+    /// per call, or to "u_proj_view", "u_camera_position", "u_exposure" and the lighting
+    /// uniforms of shaders without them.
     ///
     /// # Parameters
-    /// - `shader`: Compiled OpenGL shader program used for rendering.
-    /// - `camera`: The active camera providing projection and view matrices.
-    /// - `frustum`: Frustum derived from the camera, used for basic culling.
-    pub fn draw(&mut self, camera: &Camera) {
+    /// - `camera`: The active camera providing projection and view matrices, also used for culling.
+    /// - `lights`: The frame's lights, usually gathered with [`collect_lights`](Self::collect_lights).
+    pub fn draw(&mut self, camera: &Camera, lights: &LightSet) {
         // Recalculate transforms if needed
         let world_matrix = self.world_matrix();
+        upload_frame_blocks(camera, lights);
+        self.draw_under(world_matrix, camera, &camera.frustum(), lights);
+    }
 
-        // Naive bounding-sphere culling: assume unit bounding radius
-        let world_pos = [
-            world_matrix[12],
-            world_matrix[13],
-            world_matrix[14],
-        ];
-
-        if !camera.intersects_sphere(world_pos, 1.0f32) {
-            return; // skip drawing this object and its children
+    /// Draws this object, whose world matrix is already up to date, and its subtree.
+    fn draw_under(&mut self, world_matrix: [f32; 16], camera: &Camera, frustum: &Frustum, lights: &LightSet) {
+        if !self.visible {
+            return;
+        }
+        if self.visible_in(&world_matrix, frustum, camera) {
+            self.draw_self(&world_matrix, camera, lights);
         }
 
-        // Upload transform to shader
+        // Children have their own bounds and are culled individually
+        for child in &self.children {
+            let mut child = child.borrow_mut();
+            let child_world = child.update_world_matrix(Some(&world_matrix));
+            child.draw_under(child_world, camera, frustum, lights);
+        }
+    }
 
-        if let Some(ref shader) = self.shader {
-            shader.set_uniform_matrix4("u_model", &world_matrix);
-            shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+    /// Draws this object's geometry, without its children.
+    fn draw_self(&self, world_matrix: &[f32; 16], camera: &Camera, lights: &LightSet) {
+        // Bind the material (render state, textures, uniforms) and upload transforms
+        if let Some(ref material) = self.material {
+            material.bind();
+            material.shader.set_uniform_matrix4("u_model", world_matrix);
+            material.shader.set_uniform_matrix3("u_normal_matrix", &normal_matrix(world_matrix));
+            material.shader.set_uniform_int("u_instanced", self.instances.is_some() as i32);
+            set_skin_uniforms(&material.shader, self);
+            apply_frame_uniforms(&material.shader, camera, lights);
         }
 
-        // Draw geometry if present
-        if let Some(mesh) = self.gl_mesh.get() {
-            unsafe {
-                gl::BindVertexArray(mesh.vao);
-                gl::DrawElements(
-                    gl::TRIANGLES,
-                    mesh.index_count as GLsizei,
-                    gl::UNSIGNED_SHORT,
-                    std::ptr::null(),
-                );
-                gl::BindVertexArray(0);
-            }
+        self.draw_geometry();
+        self.draw_debug_overlays(world_matrix, camera);
+    }
+
+    /// Overlays this node's debug views (normals), if enabled. Returns whether anything was
+    /// drawn, in which case a different program is now bound.
+    pub(crate) fn draw_debug_overlays(&self, world_matrix: &[f32; 16], camera: &Camera) -> bool {
+        if let (Some(debug), Some(geometry)) = (&self.debug_normals, &self.geometry) {
+            debug.draw(geometry, world_matrix, camera);
+            return true;
         }
+        false
+    }
 
-        // Draw all children
-        for child in &self.children {
-            child.borrow_mut().draw(camera);
+    /// Issues the draw call for this node's geometry with whatever program is bound,
+    /// instanced if the node has instances. Uploads the geometry on first use.
+    ///
+    /// Used by passes that draw nodes with their own shader, such as picking and outlines;
+    /// they set `u_model` and `u_instanced` themselves.
+    pub(crate) fn draw_geometry(&self) {
+        // Upload geometry on first draw, then draw it if present. Compute-skinned nodes
+        // draw their deformed copy of the vertices.
+        let mesh = self.gpu_mesh();
+        let vao = self.deformed_vao().or(mesh.map(|mesh| mesh.vao)).unwrap_or(0);
+        match (mesh, &self.instances) {
+            (Some(mesh), Some(instances)) if !instances.is_empty() => {
+                instances.bind(vao);
+                unsafe {
+                    gl::DrawElementsInstanced(
+                        mesh.mode,
+                        mesh.index_count as GLsizei,
+                        mesh.index_type,
+                        std::ptr::null(),
+                        instances.len() as GLsizei,
+                    );
+                }
+                InstancedMesh::unbind(vao);
+                record_instanced_draw_call(mesh.mode, mesh.index_count, instances.len());
+            }
+            (Some(mesh), None) => {
+                unsafe {
+                    gl::BindVertexArray(vao);
+                    gl::DrawElements(
+                        mesh.mode,
+                        mesh.index_count as GLsizei,
+                        mesh.index_type,
+                        std::ptr::null(),
+                    );
+                    gl::BindVertexArray(0);
+                }
+                record_draw_call(mesh.mode, mesh.index_count);
+            }
+            _ => {}
         }
     }
 
@@ -262,16 +1788,153 @@ impl Object3D {
 /// Vertex format storing position, normal, and uv texture coordinates.
 /// Use `f32` as 3D floats are standard on GPUs.
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vertex {
     pub position: [f32; 3], // x, y, z
     pub normal: [f32; 3],   // nx, ny, nz (for lighting)
     pub uv: [f32; 2],       // texture coordinates u, v
 }
 
-/// Index buffer using 16-bit indices for compactness.
-/// Use u32 if you expect large meshes.
-pub type Index = u16;
+/// The joints deforming a vertex of a skinned mesh and how much each pulls, see
+/// [`skeleton`](crate::engine::animation::skeleton). Stored next to the [`Vertex`] stream
+/// in [`Geometry::skin`] and uploaded as attributes 7 (joints) and 8 (weights).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SkinVertex {
+    /// Indices into the mesh's [`Skeleton`](crate::engine::animation::skeleton::Skeleton).
+    pub joints: [u16; 4],
+
+    /// Influence of each joint, summing to 1. Unused slots have weight 0.
+    pub weights: [f32; 4],
+}
+
+/// A vertex index as produced by mesh builders and loaders. Stored as 16 bits when the
+/// mesh is small enough; see [`Indices`].
+pub type Index = u32;
+
+/// A geometry's index buffer, stored with 16-bit indices when every index fits and 32-bit
+/// indices otherwise. The GL index type is chosen from the variant at draw time.
+///
+/// Builders should collect `u32` indices and call [`Indices::from_u32`], which picks the
+/// compact form automatically; [`push`](Indices::push) promotes to 32 bits when needed.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::object3d::Indices;
+/// let mut indices = Indices::from_u32(vec![0, 1, 2]);
+/// assert!(matches!(indices, Indices::U16(_)));
+/// indices.push(70_000);
+/// assert!(matches!(indices, Indices::U32(_)));
+/// assert_eq!(indices.iter().collect::<Vec<_>>(), [0, 1, 2, 70_000]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Indices {
+    /// 16-bit indices, for meshes of up to 65,536 vertices.
+    U16(Vec<u16>),
+    /// 32-bit indices, for larger meshes.
+    U32(Vec<u32>),
+}
+
+impl Default for Indices {
+    fn default() -> Self {
+        Indices::U16(Vec::new())
+    }
+}
+
+impl From<Vec<u16>> for Indices {
+    fn from(indices: Vec<u16>) -> Self {
+        Indices::U16(indices)
+    }
+}
+
+impl From<Vec<u32>> for Indices {
+    /// Keeps 32-bit storage; use [`Indices::from_u32`] to compact small meshes.
+    fn from(indices: Vec<u32>) -> Self {
+        Indices::U32(indices)
+    }
+}
+
+impl Indices {
+    /// Stores `indices` as 16-bit if they all fit, as 32-bit otherwise.
+    pub fn from_u32(indices: Vec<u32>) -> Self {
+        if indices.iter().all(|&index| index <= u16::MAX as u32) {
+            Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            Indices::U32(indices)
+        }
+    }
+
+    /// Number of indices.
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    /// Returns `true` if there are no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The index at position `i`.
+    ///
+    /// # Panics
+    /// Panics if `i` is out of range.
+    pub fn get(&self, i: usize) -> Index {
+        match self {
+            Indices::U16(indices) => indices[i] as Index,
+            Indices::U32(indices) => indices[i],
+        }
+    }
+
+    /// Iterates over the indices.
+    pub fn iter(&self) -> impl Iterator<Item = Index> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// Appends an index, switching to 32-bit storage if it doesn't fit 16 bits.
+    pub fn push(&mut self, index: Index) {
+        match self {
+            Indices::U16(indices) if index <= u16::MAX as u32 => indices.push(index as u16),
+            Indices::U16(indices) => {
+                let mut promoted: Vec<u32> = indices.iter().map(|&index| index as u32).collect();
+                promoted.push(index);
+                *self = Indices::U32(promoted);
+            }
+            Indices::U32(indices) => indices.push(index),
+        }
+    }
+
+    /// The indices widened to 32 bits.
+    pub fn to_vec(&self) -> Vec<Index> {
+        self.iter().collect()
+    }
+
+    /// Size of the index data in bytes.
+    pub fn byte_size(&self) -> usize {
+        match self {
+            Indices::U16(indices) => std::mem::size_of_val(indices.as_slice()),
+            Indices::U32(indices) => std::mem::size_of_val(indices.as_slice()),
+        }
+    }
+
+    /// The GL index type (`GL_UNSIGNED_SHORT` or `GL_UNSIGNED_INT`) matching the storage.
+    pub fn gl_type(&self) -> GLenum {
+        match self {
+            Indices::U16(_) => gl::UNSIGNED_SHORT,
+            Indices::U32(_) => gl::UNSIGNED_INT,
+        }
+    }
+
+    /// Pointer to the index data, for uploading.
+    pub fn as_ptr(&self) -> *const std::ffi::c_void {
+        match self {
+            Indices::U16(indices) => indices.as_ptr() as *const _,
+            Indices::U32(indices) => indices.as_ptr() as *const _,
+        }
+    }
+}
 
 /// Represents the geometric data (mesh) used to define the shape of a 3D object.
 ///
@@ -294,23 +1957,29 @@ pub type Index = u16;
 ///
 /// # Fields
 /// - `vertices`: A list of `Vertex` structs that define the attributes per vertex (e.g., position, normals, UVs).
-/// - `indices`: A list of `Index` values that define the mesh's connectivity (which vertices make up each triangle).
+/// - `indices`: The [`Indices`] (16- or 32-bit) that define the mesh's connectivity (which vertices make up each primitive).
+/// - `topology`: How the indices are assembled into primitives (triangles, lines, line strip, points).
+/// - `skin`: Optional joint influences per vertex, for skinned meshes.
 ///
 /// # Example Usage
 /// ```rust
+/// # use rustge::engine::object3d::{Geometry, Indices, Object3D, Topology};
 /// let geometry = Geometry {
 ///     vertices: vec![/* ... */],
-///     indices: vec![0, 1, 2, 2, 3, 0], // A simple quad made of two triangles
+///     indices: Indices::U16(vec![0, 1, 2, 2, 3, 0]), // A simple quad made of two triangles
+///     topology: Topology::Triangles,
+///     skin: None,
 /// };
 ///
-/// let mut object = Object3D::new();
-/// object.borrow_mut().geometry = Some(geometry);
+/// let object = Object3D::new();
+/// object.borrow_mut().set_geometry(geometry);
 /// ```
 ///
 /// # Performance Considerations
 /// - Vertex/index buffers should be uploaded to GPU memory (e.g., via OpenGL VBO/IBO) during initialization.
 /// - Regenerative geometry (e.g. dynamic terrain) should update buffers only when marked dirty.
-/// - Use 16-bit indices (`u16`) when possible for lower memory footprint; switch to `u32` for large meshes.
+/// - 16-bit indices are used when possible for lower memory footprint; [`Indices::from_u32`]
+///   switches to 32-bit indices for large meshes automatically.
 ///
 /// # See Also
 /// - [`Vertex`](struct.Vertex.html) – the structure defining per-vertex data.
@@ -318,7 +1987,7 @@ pub type Index = u16;
 ///
 /// # Note
 /// This struct only holds CPU-side mesh data. Integration with GPU buffers (VBO/VAO) must be handled separately.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Geometry {
     /// A list of vertices that define the shape of the mesh.
     ///
@@ -332,25 +2001,102 @@ pub struct Geometry {
     ///
     /// These indices reference positions in the `vertices` array.
     /// For example, [0, 1, 2] creates one triangle using the first three vertices.
-    pub indices: Vec<Index>,
+    pub indices: Indices,
+
+    /// How the indices are assembled into primitives when drawn.
+    pub topology: Topology,
+
+    /// Joint influences per vertex (same order as `vertices`) for skinned meshes, `None`
+    /// for rigid ones.
+    pub skin: Option<Vec<SkinVertex>>,
 }
 
-/// Internal OpenGL mesh representation. Automatically created from Geometry.
-#[derive(Debug)]
-pub struct GLMesh {
-    pub vao: GLuint,
-    pub vbo: GLuint,
-    pub ibo: GLuint,
-    pub index_count: usize,
+impl Geometry {
+    /// Builds geometry from a list of points drawn in order with the given topology,
+    /// e.g. a trajectory as a `LineStrip` or a point cloud as `Points`.
+    ///
+    /// Normals point up (+Y) and UVs are zero.
+    pub fn from_positions(topology: Topology, positions: &[[f32; 3]]) -> Self {
+        Self {
+            vertices: positions
+                .iter()
+                .map(|&position| Vertex { position, normal: [0.0, 1.0, 0.0], uv: [0.0, 0.0] })
+                .collect(),
+            indices: Indices::from_u32((0..positions.len()).map(|i| i as Index).collect()),
+            topology,
+            skin: None,
+        }
+    }
+
+    /// A sphere enclosing all vertices, as (center, radius): centered on the axis-aligned
+    /// bounding box, which is tight enough for culling. Empty geometry gives a zero sphere.
+    pub fn bounding_sphere(&self) -> ([f32; 3], f32) {
+        let Some(bounds) = self.aabb() else {
+            return ([0.0; 3], 0.0);
+        };
+        let center = bounds.center();
+        let radius = self
+            .vertices
+            .iter()
+            .map(|vertex| distance_squared(center, vertex.position))
+            .fold(0.0f32, f32::max)
+            .sqrt();
+        (center, radius)
+    }
+
+    /// The axis-aligned box around all vertices, or `None` for empty geometry.
+    pub fn aabb(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|vertex| vertex.position))
+    }
+
+    /// Recomputes smooth vertex normals from the triangles, weighting each face by its area.
+    ///
+    /// Used by importers for meshes that come without normals. Vertices not used by any
+    /// triangle (and non-triangle topologies) get +Y.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![[0.0f32; 3]; self.vertices.len()];
+        if self.topology == Topology::Triangles {
+            for triangle in 0..self.indices.len() / 3 {
+                let corners = [0, 1, 2].map(|corner| self.indices.get(triangle * 3 + corner) as usize);
+                if corners.iter().any(|&i| i >= self.vertices.len()) {
+                    continue;
+                }
+                let [a, b, c] = corners.map(|i| self.vertices[i].position);
+                // The cross product's length is twice the area, which weights the sum
+                let face = cross(sub(b, a), sub(c, a));
+                for i in corners {
+                    normals[i] = add(normals[i], face);
+                }
+            }
+        }
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = normalize_or(normal, [0.0, 1.0, 0.0]);
+        }
+    }
 }
 
-// -- Constants --
-/// Identity matrix (4x4) representing 'no transformation'.
-/// This matrix leaves points unchanged when multiplied.
-const IDENTITY_MATRIX: [f32; 16] = [
-    1.0, 0.0, 0.0, 0.0,  // Column 1
-    0.0, 1.0, 0.0, 0.0,  // Column 2
-    0.0, 0.0, 1.0, 0.0,  // Column 3
-    0.0, 0.0, 0.0, 1.0,  // Column 4
-];
+/// How a [`Geometry`]'s indices are assembled into primitives.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// Every three indices form a triangle.
+    #[default]
+    Triangles,
+    /// Every two indices form an independent line segment (grids, wireframes).
+    Lines,
+    /// Consecutive indices are connected into one polyline (paths, trajectories).
+    LineStrip,
+    /// Every index is drawn as a point (point clouds).
+    Points,
+}
 
+impl Topology {
+    /// The OpenGL primitive mode for this topology.
+    pub fn gl_mode(self) -> GLenum {
+        match self {
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::Lines => gl::LINES,
+            Topology::LineStrip => gl::LINE_STRIP,
+            Topology::Points => gl::POINTS,
+        }
+    }
+}
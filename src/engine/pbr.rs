@@ -0,0 +1,140 @@
+//! Physically based materials: the metallic-roughness model of glTF, shaded with a
+//! Cook-Torrance BRDF.
+//!
+//! A [`PbrMaterial`] describes a surface by its albedo (base color), how metallic and how
+//! rough it is, and optionally a normal map, ambient occlusion and emission, each a factor
+//! multiplied by an optional texture. [`material`](PbrMaterial::material) turns it into a
+//! [`Material`] drawn with the built-in PBR shader, lit by the same
+//! [`Light`](crate::engine::light::Light)s as [`Material::phong`] and in the same units.
+//!
+//! Ambient light comes from the scene's skybox when
+//! [environment lighting](crate::engine::scene::Scene::set_environment_lighting) is on: the
+//! cube map's mip levels stand in for prefiltered irradiance and reflections, so metals
+//! mirror the sky and rough surfaces pick up its colors. Without a skybox the ambient term
+//! lights every direction alike.
+//!
+//! glTF files imported with [`load_gltf`](crate::engine::import::gltf::load_gltf) get PBR
+//! materials with their textures.
+//!
+//! # Example
+//! ```no_run
+//! # use std::rc::Rc;
+//! # use rustge::engine::{math::color::Color, pbr::PbrMaterial, texture::Texture};
+//! let brushed_steel = PbrMaterial {
+//!     metallic: 1.0,
+//!     roughness: 0.35,
+//!     normal_map: Some(Rc::new(Texture::load_png_linear("assets/brushed_normal.png").unwrap())),
+//!     ..PbrMaterial::new(Color::srgb(0.8, 0.8, 0.82))
+//! }
+//! .material();
+//! ```
+
+use std::rc::Rc;
+use crate::engine::material::{lit_fragment_source, Material};
+use crate::engine::math::color::Color;
+use crate::engine::shader::{builtin_program, UniformValue};
+use crate::engine::texture::Texture;
+
+/// A metallic-roughness surface; see the [module documentation](self).
+///
+/// Color textures ([`albedo_map`](Self::albedo_map), [`emissive_map`](Self::emissive_map))
+/// are sRGB images as loaded with [`Texture::load_png`]; the others hold data and are loaded
+/// with [`Texture::load_png_linear`]. All are sampled with the mesh's UVs.
+#[derive(Clone, Debug)]
+pub struct PbrMaterial {
+    /// Base color and alpha: the diffuse color of dielectrics and the reflection color of
+    /// metals.
+    pub albedo: Color,
+
+    /// 0 for dielectrics (plastic, wood, stone), 1 for metals.
+    pub metallic: f32,
+
+    /// Perceptual roughness, from 0 (mirror) to 1 (fully diffuse reflections).
+    pub roughness: f32,
+
+    /// Light the surface gives off, in linear units; may exceed 1.
+    pub emissive: Color,
+
+    /// Strength of the normal map's bumps, 1 as authored.
+    pub normal_scale: f32,
+
+    /// How much the occlusion map darkens ambient light, from 0 (not at all) to 1.
+    pub occlusion_strength: f32,
+
+    /// Discards fragments whose alpha is below the cutoff, for foliage and fences; `None`
+    /// keeps every fragment.
+    pub alpha_cutoff: Option<f32>,
+
+    /// Multiplies the albedo and alpha.
+    pub albedo_map: Option<Rc<Texture>>,
+
+    /// Multiplies the roughness (green channel) and metallic (blue channel), as glTF packs them.
+    pub metallic_roughness_map: Option<Rc<Texture>>,
+
+    /// Tangent-space normals, with +Y pointing up the texture as in glTF.
+    pub normal_map: Option<Rc<Texture>>,
+
+    /// Ambient occlusion in the red channel.
+    pub occlusion_map: Option<Rc<Texture>>,
+
+    /// Multiplies the emissive color.
+    pub emissive_map: Option<Rc<Texture>>,
+}
+
+impl PbrMaterial {
+    /// A non-metallic, fairly rough surface in `albedo`, without textures.
+    pub fn new(albedo: Color) -> Self {
+        Self {
+            albedo,
+            metallic: 0.0,
+            roughness: 0.5,
+            emissive: Color::BLACK,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+            alpha_cutoff: None,
+            albedo_map: None,
+            metallic_roughness_map: None,
+            normal_map: None,
+            occlusion_map: None,
+            emissive_map: None,
+        }
+    }
+
+    /// A [`Material`] drawing the surface with the built-in PBR shader. Translucent albedos
+    /// still need [`Material::set_transparent`].
+    ///
+    /// The factors become uniforms of the same names (`u_metallic`, `u_roughness`,
+    /// `u_emissive`, `u_normal_scale`, `u_occlusion_strength`, `u_alpha_cutoff`) and the
+    /// albedo the material color, so they can be changed on the material afterwards.
+    ///
+    /// Must be called with a current GL context.
+    pub fn material(&self) -> Material {
+        let shader = builtin_program("pbr", include_str!("shaders/phong.vert"), &lit_fragment_source(include_str!("shaders/pbr.frag")));
+        let mut material = Material::new(shader);
+        material.color = self.albedo;
+        material.set_uniform("u_metallic", UniformValue::Float(self.metallic));
+        material.set_uniform("u_roughness", UniformValue::Float(self.roughness));
+        material.set_uniform("u_emissive", UniformValue::Vec3([self.emissive.r, self.emissive.g, self.emissive.b]));
+        material.set_uniform("u_normal_scale", UniformValue::Float(self.normal_scale));
+        material.set_uniform("u_occlusion_strength", UniformValue::Float(self.occlusion_strength));
+        material.set_uniform("u_alpha_cutoff", UniformValue::Float(self.alpha_cutoff.unwrap_or(-1.0)));
+
+        let maps = [
+            ("u_albedo_map", &self.albedo_map),
+            ("u_metallic_roughness_map", &self.metallic_roughness_map),
+            ("u_normal_map", &self.normal_map),
+            ("u_occlusion_map", &self.occlusion_map),
+            ("u_emissive_map", &self.emissive_map),
+        ];
+        let mut bound = 0;
+        for (bit, (sampler, texture)) in maps.into_iter().enumerate() {
+            if let Some(texture) = texture {
+                material.set_texture(sampler, texture.clone());
+                bound |= 1 << bit;
+            }
+        }
+        material.set_uniform("u_maps", UniformValue::Int(bound));
+        material
+    }
+}
+
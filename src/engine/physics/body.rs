@@ -0,0 +1,117 @@
+//! Rigid bodies: the mass and motion of physically simulated nodes.
+//!
+//! A [`RigidBody`] component makes a node part of the simulation run by a
+//! [`PhysicsWorld`](crate::engine::physics::world::PhysicsWorld). Its
+//! [`BodyType`] decides who moves it: the solver ([`Dynamic`](BodyType::Dynamic)), its
+//! own velocity regardless of what it hits ([`Kinematic`](BodyType::Kinematic)), or
+//! nothing ([`Static`](BodyType::Static)). A node with a
+//! [`Collider`](crate::engine::physics::collider::Collider) but no rigid body is static.
+//!
+//! The body's position and rotation are the node's world transform: the world reads them
+//! before simulating, so moving a node by hand teleports its body, and writes the simulated
+//! transform back afterwards. The center of mass is the node's origin.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::physics::body::RigidBody;
+//! let crate_node = Object3D::new();
+//! let mut body = RigidBody::dynamic(20.0);
+//! body.apply_impulse([0.0, 100.0, 0.0]); // a kick upwards
+//! assert_eq!(body.velocity, [0.0, 5.0, 0.0]);
+//! crate_node.borrow_mut().insert_component(body);
+//! ```
+
+/// How a [`RigidBody`] moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BodyType {
+    /// Moved by the solver: falls under gravity, is pushed by contacts and pushes back.
+    #[default]
+    Dynamic,
+
+    /// Moved only by its velocity, which the game sets; pushes dynamic bodies out of its
+    /// way as if infinitely heavy. For platforms, doors and animated obstacles.
+    Kinematic,
+
+    /// Never moves; dynamic bodies collide with it.
+    Static,
+}
+
+/// Component giving a node mass and velocity; see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RigidBody {
+    /// Who moves the body.
+    pub body_type: BodyType,
+
+    /// Mass in kilograms (or any unit, as long as it's the same for every body). Only
+    /// used by dynamic bodies.
+    pub mass: f32,
+
+    /// World-space velocity in units per second.
+    pub velocity: [f32; 3],
+
+    /// World-space angular velocity in radians per second, around its direction.
+    pub angular_velocity: [f32; 3],
+
+    /// Fraction of velocity lost per second, like air resistance.
+    pub linear_damping: f32,
+
+    /// Fraction of angular velocity lost per second.
+    pub angular_damping: f32,
+
+    /// Multiplier of the scene's gravity for this body; `0.0` makes it float.
+    pub gravity_scale: f32,
+
+    /// Whether the body keeps its rotation when hit, e.g. for upright characters.
+    pub lock_rotation: bool,
+}
+
+impl Default for RigidBody {
+    /// A dynamic body of unit mass at rest.
+    fn default() -> Self {
+        Self::dynamic(1.0)
+    }
+}
+
+impl RigidBody {
+    /// A dynamic body of `mass`, at rest.
+    pub fn dynamic(mass: f32) -> Self {
+        Self {
+            body_type: BodyType::Dynamic,
+            mass,
+            velocity: [0.0; 3],
+            angular_velocity: [0.0; 3],
+            linear_damping: 0.05,
+            angular_damping: 0.1,
+            gravity_scale: 1.0,
+            lock_rotation: false,
+        }
+    }
+
+    /// A kinematic body, at rest until its velocity is set.
+    pub fn kinematic() -> Self {
+        Self { body_type: BodyType::Kinematic, ..Self::dynamic(0.0) }
+    }
+
+    /// A static body.
+    pub fn fixed() -> Self {
+        Self { body_type: BodyType::Static, ..Self::dynamic(0.0) }
+    }
+
+    /// Changes the velocity of a dynamic body as an instantaneous push of `impulse` (mass
+    /// times velocity) through its center of mass would. Other bodies ignore impulses.
+    pub fn apply_impulse(&mut self, impulse: [f32; 3]) {
+        if self.body_type == BodyType::Dynamic && self.mass > 0.0 {
+            self.velocity = std::array::from_fn(|axis| self.velocity[axis] + impulse[axis] / self.mass);
+        }
+    }
+
+    /// The inverse of the mass as the solver sees it: zero (immovable) for kinematic and
+    /// static bodies.
+    pub fn inverse_mass(&self) -> f32 {
+        match self.body_type {
+            BodyType::Dynamic if self.mass > 0.0 => 1.0 / self.mass,
+            _ => 0.0,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Kinematic character movement: walking, sliding along walls, climbing steps and jumping.
+//!
+//! A [`CharacterController`] moves a node as an upright capsule through the scene's
+//! [colliders](crate::engine::physics::collider), the way a player character is expected to
+//! move rather than the way a rigid body would: it goes exactly where the input says until
+//! it touches something, then slides along it. Walkable ground (no steeper than
+//! [`max_slope`](CharacterController::max_slope)) holds the character up without sliding
+//! it downhill, ledges up to [`step_offset`](CharacterController::step_offset) high are
+//! stepped onto, and the character stays glued to the ground walking down stairs and
+//! slopes. Gravity is the scene's (see
+//! [`SimulationSettings`](crate::engine::simulation::SimulationSettings)) times
+//! [`gravity_scale`](CharacterController::gravity_scale), and "up" is against it.
+//!
+//! The node's origin is the character's feet; the capsule stands on it. The controller
+//! only moves the node, so other bodies don't notice the character unless the node also has
+//! a capsule [`Collider`](crate::engine::physics::collider::Collider) (which its own
+//! controller ignores), letting dynamic bodies bump into it. Sensors are walked through.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{object3d::Object3D, renderer::Renderer};
+//! # use rustge::engine::physics::character::CharacterController;
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! let player = Object3D::new();
+//! renderer.get_scene().unwrap().add(player.clone());
+//! let mut controller = CharacterController::new(player, 0.4, 0.5);
+//!
+//! renderer.on_update(move |renderer, clock| {
+//!     let input = renderer.input();
+//!     let movement = [input.axis("strafe") * 4.0, 0.0, -input.axis("forward") * 4.0];
+//!     if input.is_action_pressed("jump") {
+//!         controller.jump();
+//!     }
+//!     if let Some(scene) = renderer.get_scene() {
+//!         controller.update(scene, movement, clock.delta());
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::math::matrixfuncs::decompose_matrix;
+use crate::engine::math::vec::{add, dot, length, normalize_or, scale, sub};
+use crate::engine::object3d::Object3D;
+use crate::engine::physics::collider::{collide, Collider, WorldShape};
+use crate::engine::scene::Scene;
+
+/// Depenetration passes over the nearby colliders per move.
+const RESOLVE_PASSES: usize = 4;
+
+/// Size of the probe checking whether the top of a ledge the character touches is walkable.
+const LEDGE_PROBE: f32 = 0.05;
+
+/// How far below its feet the character looks for ground while in the air or jumping.
+const GROUND_PROBE: f32 = 0.05;
+
+/// A collider the character touched during its last update.
+#[derive(Clone, Debug)]
+pub struct CharacterHit {
+    /// The node of the collider.
+    pub node: Rc<RefCell<Object3D>>,
+
+    /// World-space contact point.
+    pub point: [f32; 3],
+
+    /// Surface normal at the contact, pointing out of the collider towards the character.
+    pub normal: [f32; 3],
+}
+
+/// Moves a node as a walking character; see the [module documentation](self).
+#[derive(Debug)]
+pub struct CharacterController {
+    node: Rc<RefCell<Object3D>>,
+
+    /// Radius of the capsule.
+    pub radius: f32,
+
+    /// Distance from the capsule's center to the center of each cap; the character is
+    /// `2 * (half_height + radius)` tall.
+    pub half_height: f32,
+
+    /// Tallest ledge the character steps onto while walking, e.g. a stair.
+    pub step_offset: f32,
+
+    /// Steepest walkable slope, in radians from level. Steeper surfaces are walls: the
+    /// character slides down them.
+    pub max_slope: f32,
+
+    /// Multiplier of the scene's gravity for the character.
+    pub gravity_scale: f32,
+
+    /// Upward speed a [`jump`](Self::jump) starts with, in units per second.
+    pub jump_speed: f32,
+
+    /// Speed against gravity; positive while rising.
+    vertical_speed: f32,
+    grounded: bool,
+    ground_normal: [f32; 3],
+    velocity: [f32; 3],
+    hits: Vec<CharacterHit>,
+}
+
+/// A collider the character may touch this update.
+struct Obstacle {
+    node: Rc<RefCell<Object3D>>,
+    shape: WorldShape,
+}
+
+impl CharacterController {
+    /// A controller moving `node` as a capsule of `radius` whose cap centers are
+    /// `half_height` above and below its center. Steps up 0.3 units, walks slopes up to 45
+    /// degrees and jumps at 5 units per second.
+    pub fn new(node: Rc<RefCell<Object3D>>, radius: f32, half_height: f32) -> Self {
+        Self {
+            node,
+            radius,
+            half_height,
+            step_offset: 0.3,
+            max_slope: std::f32::consts::FRAC_PI_4,
+            gravity_scale: 1.0,
+            jump_speed: 5.0,
+            vertical_speed: 0.0,
+            grounded: false,
+            ground_normal: [0.0, 1.0, 0.0],
+            velocity: [0.0; 3],
+            hits: Vec::new(),
+        }
+    }
+
+    /// The moved node.
+    pub fn node(&self) -> &Rc<RefCell<Object3D>> {
+        &self.node
+    }
+
+    /// Whether the character stood on walkable ground after the last update.
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Normal of the ground the character stands on; straight up while in the air.
+    pub fn ground_normal(&self) -> [f32; 3] {
+        self.ground_normal
+    }
+
+    /// World-space velocity of the last update, as actually moved (after collisions).
+    pub fn velocity(&self) -> [f32; 3] {
+        self.velocity
+    }
+
+    /// The colliders touched during the last update.
+    pub fn hits(&self) -> &[CharacterHit] {
+        &self.hits
+    }
+
+    /// Starts a jump if the character is on the ground; returns whether it did.
+    pub fn jump(&mut self) -> bool {
+        if !self.grounded {
+            return false;
+        }
+        self.vertical_speed = self.jump_speed;
+        self.grounded = false;
+        true
+    }
+
+    /// Moves the character for a frame of `delta` seconds: `movement` (world-space units
+    /// per second; the part along gravity is ignored) plus falling or jumping, colliding
+    /// with the colliders in `scene`.
+    ///
+    /// # Panics
+    /// Panics if the node, its parent or a collider's node is already borrowed.
+    pub fn update(&mut self, scene: &Scene, movement: [f32; 3], delta: f32) {
+        let gravity = scale(scene.simulation_settings().gravity, self.gravity_scale);
+        let up = normalize_or(scale(gravity, -1.0), [0.0, 1.0, 0.0]);
+        let delta = delta.max(0.0);
+
+        let feet = {
+            let mut node = self.node.borrow_mut();
+            decompose_matrix(&node.world_matrix()).0
+        };
+        let lift = self.half_height + self.radius;
+        let start = add(feet, scale(up, lift));
+
+        let horizontal = sub(movement, scale(up, dot(movement, up)));
+        if !self.grounded || self.vertical_speed > 0.0 {
+            self.vertical_speed += dot(gravity, up) * delta;
+        }
+        let mut velocity = add(horizontal, scale(up, self.vertical_speed));
+
+        let reach = length(velocity) * delta + self.step_offset + lift + self.radius + GROUND_PROBE;
+        let obstacles = self.obstacles(scene, start, reach);
+        self.hits.clear();
+
+        // Substeps no longer than half the radius, so thin colliders aren't skipped
+        let displacement = length(velocity) * delta;
+        let substeps = (displacement / (self.radius * 0.5).max(1e-3)).ceil().max(1.0) as u32;
+        let dt = delta / substeps as f32;
+        let climb = self.grounded;
+        let mut center = start;
+        let mut ground = None;
+        for _ in 0..substeps {
+            center = add(center, scale(velocity, dt));
+            ground = self.resolve(&obstacles, &mut center, &mut velocity, up, climb).or(ground);
+        }
+
+        // Look for ground below, snapping down onto it when walking down steps and slopes
+        let rising = dot(velocity, up) > 0.0;
+        if !rising {
+            let probe = if climb { self.step_offset.max(GROUND_PROBE) } else { GROUND_PROBE };
+            let mut probed = sub(center, scale(up, probe));
+            let mut probe_velocity = [0.0; 3];
+            ground = self.resolve(&obstacles, &mut probed, &mut probe_velocity, up, climb);
+            if ground.is_some() {
+                center = probed;
+            }
+        }
+
+        self.grounded = ground.is_some() && !rising;
+        self.ground_normal = ground.filter(|_| self.grounded).unwrap_or(up);
+        self.vertical_speed = if self.grounded { 0.0 } else { dot(velocity, up) };
+        self.velocity = if delta > 0.0 { scale(sub(center, start), 1.0 / delta) } else { [0.0; 3] };
+
+        let feet = sub(center, scale(up, lift));
+        let mut node = self.node.borrow_mut();
+        let local = match node.parent() {
+            Some(parent) => parent.borrow_mut().world_to_local(feet),
+            None => feet,
+        };
+        node.set_position(local);
+    }
+
+    /// The capsule centered on `center`, upright along `up`.
+    fn capsule_at(&self, center: [f32; 3], up: [f32; 3]) -> WorldShape {
+        let axis = scale(up, self.half_height);
+        WorldShape::Capsule { start: sub(center, axis), end: add(center, axis), radius: self.radius }
+    }
+
+    /// The solid colliders within `reach` of `center`, except the character's own.
+    fn obstacles(&self, scene: &Scene, center: [f32; 3], reach: f32) -> Vec<Obstacle> {
+        scene
+            .query::<(Collider,)>()
+            .filter(|node| !Rc::ptr_eq(node, &self.node))
+            .filter_map(|node| {
+                let shape = {
+                    let mut object = node.borrow_mut();
+                    let collider = object.component::<Collider>().copied().filter(|collider| !collider.sensor)?;
+                    let (position, rotation, scale) = decompose_matrix(&object.world_matrix());
+                    collider.world_shape(position, rotation, scale)
+                };
+                let bounds = shape.aabb();
+                let near = (0..3)
+                    .all(|axis| bounds.min[axis] <= center[axis] + reach && bounds.max[axis] >= center[axis] - reach);
+                near.then_some(Obstacle { node, shape })
+            })
+            .collect()
+    }
+
+    /// Pushes the capsule at `center` out of the obstacles, removing the part of `velocity`
+    /// heading into them, and returns the normal of the walkable ground touched, if any.
+    /// Walkable ground pushes straight up, so the character doesn't slide down slopes it
+    /// stands on; with `climb`, so do the edges of ledges low enough to step onto.
+    fn resolve(
+        &mut self,
+        obstacles: &[Obstacle],
+        center: &mut [f32; 3],
+        velocity: &mut [f32; 3],
+        up: [f32; 3],
+        climb: bool,
+    ) -> Option<[f32; 3]> {
+        let min_ground_dot = self.max_slope.cos();
+        let mut ground = None;
+        for _ in 0..RESOLVE_PASSES {
+            let mut pushed = false;
+            for obstacle in obstacles {
+                let Some(manifold) = collide(&self.capsule_at(*center, up), &obstacle.shape) else {
+                    continue;
+                };
+                let normal = scale(manifold.normal, -1.0);
+                let upness = dot(normal, up);
+                let flat = normalize_or(sub(normal, scale(up, upness)), normal);
+                let point = manifold.points[0];
+                let surface = if upness >= min_ground_dot {
+                    Some(normal)
+                } else if climb && upness > 0.0 && self.height_above_feet(*center, point, up) <= self.step_offset {
+                    ledge_top(&obstacle.shape, point, flat, up).filter(|top| dot(*top, up) >= min_ground_dot)
+                } else {
+                    None
+                };
+
+                // Ground is left straight up and only stops falling; walls facing upwards
+                // (steep slopes, ledge edges) are treated as vertical. Sliding along either
+                // would launch the character up ramps and over ledges.
+                let axis = match surface {
+                    Some(_) => up,
+                    None if upness > 0.0 => flat,
+                    None => normal,
+                };
+                *center = add(*center, scale(axis, manifold.depth / dot(normal, axis).max(1e-3)));
+                pushed = true;
+
+                let into = dot(*velocity, axis);
+                if into < 0.0 {
+                    *velocity = sub(*velocity, scale(axis, into));
+                }
+                ground = surface.or(ground);
+                if !self.hits.iter().any(|hit| Rc::ptr_eq(&hit.node, &obstacle.node)) {
+                    self.hits.push(CharacterHit { node: obstacle.node.clone(), point, normal });
+                }
+            }
+            if !pushed {
+                break;
+            }
+        }
+        ground
+    }
+
+    /// How far `point` is above the bottom of the capsule at `center`.
+    fn height_above_feet(&self, center: [f32; 3], point: [f32; 3], up: [f32; 3]) -> f32 {
+        dot(sub(point, center), up) + self.half_height + self.radius
+    }
+}
+
+/// The normal of the top of the ledge `shape` whose edge touches the character at `point`,
+/// found by dipping a small sphere onto the ledge just past the edge (against `outward`, the
+/// level direction out of the ledge). A steep slope has no top there: the sphere ends up
+/// inside it, against its face.
+fn ledge_top(shape: &WorldShape, point: [f32; 3], outward: [f32; 3], up: [f32; 3]) -> Option<[f32; 3]> {
+    let center = add(point, add(scale(outward, -LEDGE_PROBE), scale(up, LEDGE_PROBE * 0.8)));
+    let probe = WorldShape::Sphere { center, radius: LEDGE_PROBE };
+    collide(&probe, shape).map(|manifold| scale(manifold.normal, -1.0))
+}
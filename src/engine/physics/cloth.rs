@@ -0,0 +1,523 @@
+//! Cloth: meshes that hang, sway and drape, for capes, flags and banners.
+//!
+//! A [`Cloth`] component simulates the geometry of its node vertex by vertex. Every vertex
+//! becomes a particle (vertices at the same position, such as both sides of a UV seam,
+//! share one); every triangle edge becomes a distance constraint keeping the cloth from
+//! stretching, and every pair of triangles sharing an edge a weaker one across it resisting
+//! folds. [Pinned](Cloth::pin_where) particles stay attached to the node and carry the rest
+//! along as it moves: parent a cape to the joint it hangs from, or a flag to its pole.
+//!
+//! The simulation is position based (Verlet integration with iterated constraints), in
+//! world space, with the gravity and fixed timestep of the scene's [`SimulationSettings`].
+//! Wind pushes on the triangles facing it, so flags flutter rather than just lean. Particles
+//! collide with the scene's [`Collider`]s (spheres, boxes and capsules, but not sensors),
+//! kept out by the cloth's [thickness](ClothSettings::thickness), so a capsule on a
+//! character's body keeps the cape off it. The cloth does not collide with itself.
+//!
+//! [`Scene::update`](crate::engine::scene::Scene::update) advances every cloth after the
+//! update callbacks, and [updates](Object3D::update_geometry) the node's geometry to the
+//! simulated one whenever a step ran, rewriting its vertex buffer in place. Skinning data is dropped from the simulated geometry; cloth moves with its node, not
+//! with joints. Both faces of cloth are usually visible, so give its material
+//! [`CullMode::None`](crate::engine::material::CullMode::None).
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::physics::cloth::{Cloth, ClothSettings};
+//! # use rustge::engine::scene::Scene;
+//! # use rustge::engine::time::Clock;
+//! // A 2 x 1 banner hanging from its top edge
+//! let banner = Object3D::new();
+//! let geometry = Cloth::grid([2.0, 1.0], [16, 8]);
+//! let mut cloth = Cloth::new(&geometry, ClothSettings { wind: [3.0, 0.0, 1.0], ..ClothSettings::default() });
+//! assert_eq!(cloth.pin_where(|position| position[1] >= 0.0), 17);
+//! banner.borrow_mut().set_geometry(geometry);
+//! banner.borrow_mut().insert_component(cloth);
+//!
+//! let scene = Scene::new();
+//! scene.add(banner);
+//! scene.update(None, &Clock::new());
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::engine::debug::draw_line;
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::{decompose_matrix, matrix_inverse_or_identity, transform_point};
+use crate::engine::math::vec::{add, cross, dot, length, lerp, normalize_or, scale, sub};
+use crate::engine::object3d::{Geometry, Index, Indices, Object3D, Topology, Vertex};
+use crate::engine::physics::collider::{collide, Collider, WorldShape};
+use crate::engine::scene::Scene;
+use crate::engine::simulation::{FixedStepper, SimulationSettings};
+
+/// How a [`Cloth`] behaves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClothSettings {
+    /// How strongly edges resist stretching, from 0 (rubber) to 1 (inextensible).
+    pub stiffness: f32,
+
+    /// How strongly the cloth resists folding, from 0 (silk) to 1 (canvas).
+    pub bend_stiffness: f32,
+
+    /// Fraction of velocity lost per step, to settle the cloth.
+    pub damping: f32,
+
+    /// Constraint solver passes per substep; more make the cloth stiffer and collisions
+    /// firmer.
+    pub iterations: u32,
+
+    /// Distance the cloth keeps from colliders.
+    pub thickness: f32,
+
+    /// Fraction of sliding velocity lost per step while touching a collider.
+    pub friction: f32,
+
+    /// Wind velocity in world space, in units per second.
+    pub wind: [f32; 3],
+
+    /// How strongly air, still or moving, pushes on the cloth's faces.
+    pub drag: f32,
+}
+
+impl Default for ClothSettings {
+    /// Fairly stiff fabric in still air, 2 cm thick.
+    fn default() -> Self {
+        Self {
+            stiffness: 1.0,
+            bend_stiffness: 0.1,
+            damping: 0.01,
+            iterations: 8,
+            thickness: 0.02,
+            friction: 0.3,
+            wind: [0.0; 3],
+            drag: 1.0,
+        }
+    }
+}
+
+/// A simulated vertex, in world space.
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: [f32; 3],
+    previous: [f32; 3],
+
+    /// Position in the node's local space before simulation; where pins hold the particle.
+    rest: [f32; 3],
+
+    pinned: bool,
+}
+
+/// A distance constraint between two particles.
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    a: usize,
+    b: usize,
+    length: f32,
+
+    /// Whether the link spans two triangles, resisting folds rather than stretching.
+    bend: bool,
+}
+
+/// Component simulating its node's geometry as cloth; see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Cloth {
+    /// How the cloth behaves; may be changed at any time, e.g. to gust the wind.
+    pub settings: ClothSettings,
+
+    /// The geometry as given, whose positions are replaced by the simulated ones.
+    rest: Geometry,
+
+    /// Particle of each vertex.
+    vertex_particles: Vec<usize>,
+
+    particles: Vec<Particle>,
+    links: Vec<Link>,
+
+    /// Triangles as particle indices, for wind.
+    triangles: Vec<[usize; 3]>,
+
+    /// Number of triangles each particle is a corner of.
+    triangle_counts: Vec<u32>,
+
+    /// The node's world matrix at the last update, `None` until the particles were placed.
+    last_world: Option<[f32; 16]>,
+
+    stepper: FixedStepper,
+}
+
+impl Cloth {
+    /// Cloth simulating `geometry`, which should be the node's geometry: vertex indices and
+    /// positions in the node's local space refer to it. Nothing is pinned yet.
+    ///
+    /// Only [`Topology::Triangles`] geometry holds together; other topologies fall freely.
+    pub fn new(geometry: &Geometry, settings: ClothSettings) -> Self {
+        if geometry.topology != Topology::Triangles {
+            eprintln!("Warning: Cloth geometry has {:?} topology, so no constraints hold it together", geometry.topology);
+        }
+
+        // Weld vertices sharing a position into one particle
+        let mut welded: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut particles = Vec::new();
+        let vertex_particles = geometry
+            .vertices
+            .iter()
+            .map(|vertex| {
+                *welded.entry(vertex.position.map(f32::to_bits)).or_insert_with(|| {
+                    let position = vertex.position;
+                    particles.push(Particle { position, previous: position, rest: position, pinned: false });
+                    particles.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut triangles = Vec::new();
+        if geometry.topology == Topology::Triangles {
+            for triangle in 0..geometry.indices.len() / 3 {
+                let corners = [0, 1, 2].map(|corner| geometry.indices.get(triangle * 3 + corner) as usize);
+                if corners.iter().any(|&vertex| vertex >= vertex_particles.len()) {
+                    continue;
+                }
+                let [a, b, c] = corners.map(|vertex| vertex_particles[vertex]);
+                if a != b && b != c && c != a {
+                    triangles.push([a, b, c]);
+                }
+            }
+        }
+
+        // Each edge links its ends; triangles sharing it link their opposite corners
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        let mut triangle_counts = vec![0; particles.len()];
+        for &[a, b, c] in &triangles {
+            for (start, end, opposite) in [(a, b, c), (b, c, a), (c, a, b)] {
+                edges.entry((start.min(end), start.max(end))).or_default().push(opposite);
+            }
+            for corner in [a, b, c] {
+                triangle_counts[corner] += 1;
+            }
+        }
+        let distance = |a: usize, b: usize| length(sub(particles[b].rest, particles[a].rest));
+        let mut edge_list: Vec<_> = edges.into_iter().collect();
+        edge_list.sort_unstable_by_key(|&(edge, _)| edge);
+        let mut links = Vec::new();
+        for ((a, b), opposites) in edge_list {
+            links.push(Link { a, b, length: distance(a, b), bend: false });
+            for (index, &c) in opposites.iter().enumerate() {
+                for &d in &opposites[index + 1..] {
+                    if c != d {
+                        links.push(Link { a: c, b: d, length: distance(c, d), bend: true });
+                    }
+                }
+            }
+        }
+
+        Self {
+            settings,
+            rest: geometry.clone(),
+            vertex_particles,
+            particles,
+            links,
+            triangles,
+            triangle_counts,
+            last_world: None,
+            stepper: FixedStepper::new(),
+        }
+    }
+
+    /// A rectangle of cloth `size[0]` wide and `size[1]` tall, divided into `segments`
+    /// columns and rows: centered on the local X axis and hanging from it, down to
+    /// `-size[1]`, facing +Z. UVs span the texture with V up.
+    pub fn grid(size: [f32; 2], segments: [u32; 2]) -> Geometry {
+        let [columns, rows] = segments.map(|count| count.max(1));
+        let mut vertices = Vec::with_capacity(((columns + 1) * (rows + 1)) as usize);
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (u, down) = (column as f32 / columns as f32, row as f32 / rows as f32);
+                vertices.push(Vertex {
+                    position: [(u - 0.5) * size[0], -down * size[1], 0.0],
+                    normal: [0.0, 0.0, 1.0],
+                    uv: [u, 1.0 - down],
+                });
+            }
+        }
+        let mut indices: Vec<Index> = Vec::with_capacity((columns * rows * 6) as usize);
+        for row in 0..rows {
+            for column in 0..columns {
+                let top_left = row * (columns + 1) + column;
+                let bottom_left = top_left + columns + 1;
+                indices.extend([top_left, bottom_left, top_left + 1, top_left + 1, bottom_left, bottom_left + 1].map(|index| index as Index));
+            }
+        }
+        Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+    }
+
+    /// Pins vertex `vertex` (and any welded to it) to the node, or `false` if there is no
+    /// such vertex.
+    pub fn pin(&mut self, vertex: usize) -> bool {
+        self.set_pinned(vertex, true)
+    }
+
+    /// Releases a pinned vertex, e.g. to tear a banner loose, or `false` if there is no such
+    /// vertex.
+    pub fn unpin(&mut self, vertex: usize) -> bool {
+        self.set_pinned(vertex, false)
+    }
+
+    fn set_pinned(&mut self, vertex: usize, pinned: bool) -> bool {
+        match self.vertex_particles.get(vertex) {
+            Some(&particle) => {
+                self.particles[particle].pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pins every particle whose rest position in the node's local space satisfies
+    /// `predicate`, e.g. the top edge of a banner. Returns how many were pinned.
+    pub fn pin_where(&mut self, predicate: impl Fn([f32; 3]) -> bool) -> usize {
+        let mut pinned = 0;
+        for particle in &mut self.particles {
+            if predicate(particle.rest) {
+                particle.pinned = true;
+                pinned += 1;
+            }
+        }
+        pinned
+    }
+
+    /// Whether vertex `vertex` is pinned.
+    pub fn is_pinned(&self, vertex: usize) -> bool {
+        self.vertex_particles.get(vertex).is_some_and(|&particle| self.particles[particle].pinned)
+    }
+
+    /// Puts the cloth back into its rest shape at the next update, at rest; e.g. after
+    /// teleporting its node.
+    pub fn reset(&mut self) {
+        self.last_world = None;
+        self.stepper.reset();
+    }
+
+    /// World-space positions of the simulated vertices, as of the last update.
+    pub fn positions(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        self.vertex_particles.iter().map(|&particle| self.particles[particle].position)
+    }
+
+    /// Draws the stretch constraints as lines for this frame (see
+    /// [`debug::draw_line`](crate::engine::debug::draw_line)).
+    pub fn draw_debug(&self, color: Color) {
+        for link in self.links.iter().filter(|link| !link.bend) {
+            draw_line(self.particles[link.a].position, self.particles[link.b].position, color);
+        }
+    }
+
+    /// Advances the cloth by a frame of `delta` seconds with its node at `world`, colliding
+    /// with `colliders`. Returns the new geometry in the node's local space if it changed.
+    fn update(&mut self, world: &[f32; 16], colliders: &[WorldShape], settings: &SimulationSettings, delta: f32) -> Option<Geometry> {
+        let Some(last_world) = self.last_world else {
+            for particle in &mut self.particles {
+                particle.position = transform_point(world, particle.rest);
+                particle.previous = particle.position;
+            }
+            self.last_world = Some(*world);
+            return Some(self.geometry(world));
+        };
+
+        let steps = self.stepper.advance(settings, delta);
+        if steps == 0 {
+            return None;
+        }
+        let substeps = steps * settings.substeps.max(1);
+        let dt = settings.substep_duration();
+        let colliders = self.nearby(colliders, (&last_world, world), settings.gravity, dt, substeps);
+        for substep in 1..=substeps {
+            // Pins move from the last frame's node transform to this one's over the steps
+            let t = substep as f32 / substeps as f32;
+            for particle in self.particles.iter_mut().filter(|particle| particle.pinned) {
+                let target = lerp(transform_point(&last_world, particle.rest), transform_point(world, particle.rest), t);
+                particle.previous = particle.position;
+                particle.position = target;
+            }
+            self.integrate(settings.gravity, dt);
+            for _ in 0..self.settings.iterations.max(1) {
+                self.solve();
+                self.collide(&colliders);
+            }
+        }
+        self.last_world = Some(*world);
+        Some(self.geometry(world))
+    }
+
+    /// The colliders that the cloth may touch over `substeps` of `dt` seconds while its
+    /// node moves between the `worlds`.
+    fn nearby(&self, colliders: &[WorldShape], worlds: (&[f32; 16], &[f32; 16]), gravity: [f32; 3], dt: f32, substeps: u32) -> Vec<WorldShape> {
+        let Some(bounds) = Aabb::from_points(self.particles.iter().map(|particle| particle.position)) else {
+            return Vec::new();
+        };
+        // How far a particle may get: carried along by its speed, gravity and wind, and
+        // dragged by the pins as far as the node moves
+        let (last_world, world) = worlds;
+        let duration = dt * substeps as f32;
+        let speed = self.particles.iter().map(|particle| length(sub(particle.position, particle.previous))).fold(0.0, f32::max) / dt.max(f32::EPSILON);
+        let acceleration = length(gravity) + self.settings.drag * length(self.settings.wind);
+        let travel = self
+            .particles
+            .iter()
+            .filter(|particle| particle.pinned)
+            .map(|particle| length(sub(transform_point(world, particle.rest), transform_point(last_world, particle.rest))))
+            .fold(0.0, f32::max);
+        let margin = self.settings.thickness + travel + speed * duration + 0.5 * acceleration * duration * duration;
+        let (low, high) = (sub(bounds.min, [margin; 3]), add(bounds.max, [margin; 3]));
+        colliders
+            .iter()
+            .filter(|shape| {
+                let shape_bounds = shape.aabb();
+                (0..3).all(|axis| shape_bounds.min[axis] <= high[axis] && shape_bounds.max[axis] >= low[axis])
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Verlet integration of the free particles over `dt` seconds, with gravity and wind.
+    fn integrate(&mut self, gravity: [f32; 3], dt: f32) {
+        // Air pushes each face along its normal, by how fast the air moves through it
+        let mut air = vec![[0.0f32; 3]; self.particles.len()];
+        if self.settings.drag > 0.0 && dt > 0.0 {
+            for &[a, b, c] in &self.triangles {
+                let [pa, pb, pc] = [a, b, c].map(|index| self.particles[index]);
+                let normal = normalize_or(cross(sub(pb.position, pa.position), sub(pc.position, pa.position)), [0.0; 3]);
+                let velocity = scale(
+                    sub(add(add(pa.position, pb.position), pc.position), add(add(pa.previous, pb.previous), pc.previous)),
+                    1.0 / (3.0 * dt),
+                );
+                let push = scale(normal, self.settings.drag * dot(normal, sub(self.settings.wind, velocity)));
+                for index in [a, b, c] {
+                    air[index] = add(air[index], push);
+                }
+            }
+        }
+
+        let keep = 1.0 - self.settings.damping;
+        for (index, particle) in self.particles.iter_mut().enumerate() {
+            if particle.pinned {
+                continue;
+            }
+            let acceleration = match self.triangle_counts[index] {
+                0 => gravity,
+                count => add(gravity, scale(air[index], 1.0 / count as f32)),
+            };
+            let velocity = scale(sub(particle.position, particle.previous), keep);
+            particle.previous = particle.position;
+            particle.position = add(add(particle.position, velocity), scale(acceleration, dt * dt));
+        }
+    }
+
+    /// One pass over every constraint. Pinned particles don't move.
+    fn solve(&mut self) {
+        for link in &self.links {
+            let (a, b) = (self.particles[link.a], self.particles[link.b]);
+            let weight = [a, b].iter().filter(|particle| !particle.pinned).count() as f32;
+            let offset = sub(b.position, a.position);
+            let current = length(offset);
+            if weight == 0.0 || current <= f32::EPSILON {
+                continue;
+            }
+            let stiffness = if link.bend { self.settings.bend_stiffness } else { self.settings.stiffness };
+            let correction = scale(offset, stiffness.clamp(0.0, 1.0) * (current - link.length) / (current * weight));
+            if !a.pinned {
+                self.particles[link.a].position = add(a.position, correction);
+            }
+            if !b.pinned {
+                self.particles[link.b].position = sub(b.position, correction);
+            }
+        }
+    }
+
+    /// Pushes the free particles out of `colliders`, slowing their sliding by the friction.
+    fn collide(&mut self, colliders: &[WorldShape]) {
+        if colliders.is_empty() {
+            return;
+        }
+        let (radius, friction) = (self.settings.thickness.max(0.0), self.settings.friction.clamp(0.0, 1.0));
+        for particle in self.particles.iter_mut().filter(|particle| !particle.pinned) {
+            for shape in colliders {
+                let Some(contact) = collide(&WorldShape::Sphere { center: particle.position, radius }, shape) else {
+                    continue;
+                };
+                // The normal points from the particle into the collider
+                particle.position = sub(particle.position, scale(contact.normal, contact.depth));
+                let moved = sub(particle.position, particle.previous);
+                let sliding = sub(moved, scale(contact.normal, dot(moved, contact.normal)));
+                particle.previous = add(particle.previous, scale(sliding, friction));
+            }
+        }
+    }
+
+    /// The rest geometry with the simulated positions, in the local space of a node at
+    /// `world`, and normals recomputed from them.
+    fn geometry(&self, world: &[f32; 16]) -> Geometry {
+        let inverse = matrix_inverse_or_identity(world);
+        let local: Vec<[f32; 3]> = self.particles.iter().map(|particle| transform_point(&inverse, particle.position)).collect();
+
+        // Normals per particle, so welded vertices stay smooth across seams
+        let mut normals = vec![[0.0f32; 3]; local.len()];
+        for &[a, b, c] in &self.triangles {
+            let face = cross(sub(local[b], local[a]), sub(local[c], local[a]));
+            for corner in [a, b, c] {
+                normals[corner] = add(normals[corner], face);
+            }
+        }
+
+        let mut geometry = self.rest.clone();
+        geometry.skin = None;
+        for (vertex, &particle) in geometry.vertices.iter_mut().zip(&self.vertex_particles) {
+            vertex.position = local[particle];
+            vertex.normal = normalize_or(normals[particle], vertex.normal);
+        }
+        geometry
+    }
+}
+
+/// Advances every [`Cloth`] in `scene` by a frame of `delta` seconds, colliding with the
+/// scene's colliders other than the cloth's own node's, and updates the nodes' geometry in
+/// place to the simulated one.
+pub(crate) fn update_cloth(scene: &Scene, delta: f32) {
+    let nodes: Vec<Rc<RefCell<Object3D>>> = scene.query::<(Cloth,)>().collect();
+    if nodes.is_empty() {
+        return;
+    }
+
+    let colliders: Vec<(Rc<RefCell<Object3D>>, WorldShape)> = scene
+        .query::<(Collider,)>()
+        .filter_map(|node| {
+            let shape = {
+                let mut object = node.borrow_mut();
+                let collider = *object.component::<Collider>()?;
+                if collider.sensor {
+                    return None;
+                }
+                let (position, rotation, scale) = decompose_matrix(&object.world_matrix());
+                collider.world_shape(position, rotation, scale)
+            };
+            Some((node, shape))
+        })
+        .collect();
+
+    let settings = *scene.simulation_settings();
+    for node in nodes {
+        let geometry = {
+            let mut object = node.borrow_mut();
+            let world = object.world_matrix();
+            let Some(cloth) = object.component_mut::<Cloth>() else {
+                continue;
+            };
+            let shapes: Vec<WorldShape> =
+                colliders.iter().filter(|(other, _)| !Rc::ptr_eq(other, &node)).map(|&(_, shape)| shape).collect();
+            cloth.update(&world, &shapes, &settings, delta)
+        };
+        if let Some(geometry) = geometry {
+            node.borrow_mut().update_geometry(geometry);
+        }
+    }
+}
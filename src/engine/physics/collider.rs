@@ -0,0 +1,380 @@
+//! Collision shapes and contact generation.
+//!
+//! A [`Collider`] component gives a node a shape for the
+//! [`PhysicsWorld`](crate::engine::physics::world::PhysicsWorld) to collide: a sphere, an
+//! oriented box or a capsule, in the node's local space. The node's world scale scales the
+//! shape (spheres and capsule radii by the largest axis). Each node has at most one
+//! collider; build compound shapes from child nodes with their own bodies.
+//!
+//! Colliders also carry the surface's friction and bounciness, and can be
+//! [sensors](Collider::sensor): trigger volumes that report overlaps as collision events
+//! without pushing anything.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::physics::{body::RigidBody, collider::Collider};
+//! let ball = Object3D::new();
+//! let mut collider = Collider::sphere(0.5);
+//! collider.restitution = 0.8; // bouncy
+//! ball.borrow_mut().insert_component(collider);
+//! ball.borrow_mut().insert_component(RigidBody::dynamic(1.0));
+//!
+//! // Colliders without a rigid body are static, e.g. the ground
+//! let ground = Object3D::new();
+//! ground.borrow_mut().insert_component(Collider::cuboid([50.0, 0.5, 50.0]));
+//! ```
+
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::quat;
+use crate::engine::math::vec::{add, cross, distance, dot, length, lerp, normalize_or, scale, sub};
+
+/// Contact points closer than this are merged.
+const POINT_MERGE_DISTANCE: f32 = 1e-3;
+
+/// The geometry of a [`Collider`], centered on the collider's offset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColliderShape {
+    /// A ball.
+    Sphere { radius: f32 },
+
+    /// A box, from `-half_extents` to `half_extents` along the local axes.
+    Box { half_extents: [f32; 3] },
+
+    /// A cylinder with hemispherical caps along the local Y axis, `half_height` from the
+    /// center to each cap's center.
+    Capsule { half_height: f32, radius: f32 },
+}
+
+/// Component giving a node a collision shape; see the [module documentation](self).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collider {
+    /// The shape, in the node's local space.
+    pub shape: ColliderShape,
+
+    /// Position of the shape's center in the node's local space.
+    pub offset: [f32; 3],
+
+    /// Coulomb friction coefficient; `0.0` is ice. Two colliders in contact use the
+    /// geometric mean of theirs.
+    pub friction: f32,
+
+    /// Bounciness in `[0, 1]`: the fraction of the approach speed kept after an impact.
+    /// Two colliders in contact use the larger of theirs.
+    pub restitution: f32,
+
+    /// Whether the collider only reports overlaps instead of colliding.
+    pub sensor: bool,
+}
+
+impl Collider {
+    /// A collider of `shape` centered on the node, with moderate friction and no bounce.
+    pub fn new(shape: ColliderShape) -> Self {
+        Self { shape, offset: [0.0; 3], friction: 0.5, restitution: 0.0, sensor: false }
+    }
+
+    /// A ball of `radius`.
+    pub fn sphere(radius: f32) -> Self {
+        Self::new(ColliderShape::Sphere { radius })
+    }
+
+    /// A box reaching `half_extents` from its center along each axis.
+    pub fn cuboid(half_extents: [f32; 3]) -> Self {
+        Self::new(ColliderShape::Box { half_extents })
+    }
+
+    /// An upright capsule: caps of `radius` whose centers are `half_height` above and
+    /// below the center.
+    pub fn capsule(half_height: f32, radius: f32) -> Self {
+        Self::new(ColliderShape::Capsule { half_height, radius })
+    }
+
+    /// A sensor of `shape`: reports overlaps, pushes nothing.
+    pub fn sensor(shape: ColliderShape) -> Self {
+        Self { sensor: true, ..Self::new(shape) }
+    }
+
+    /// The shape placed in the world by a node at `position` with `rotation` and `scale`.
+    pub(crate) fn world_shape(&self, position: [f32; 3], rotation: [f32; 4], node_scale: [f32; 3]) -> WorldShape {
+        let node_scale = node_scale.map(f32::abs);
+        let uniform = node_scale.into_iter().fold(0.0, f32::max);
+        let offset = std::array::from_fn(|axis| self.offset[axis] * node_scale[axis]);
+        let center = add(position, quat::rotate_vector(rotation, offset));
+        match self.shape {
+            ColliderShape::Sphere { radius } => WorldShape::Sphere { center, radius: radius * uniform },
+            ColliderShape::Box { half_extents } => WorldShape::Box {
+                center,
+                axes: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]].map(|axis| quat::rotate_vector(rotation, axis)),
+                half_extents: std::array::from_fn(|axis| half_extents[axis] * node_scale[axis]),
+            },
+            ColliderShape::Capsule { half_height, radius } => {
+                let up = scale(quat::rotate_vector(rotation, [0.0, 1.0, 0.0]), half_height * node_scale[1]);
+                WorldShape::Capsule { start: sub(center, up), end: add(center, up), radius: radius * uniform }
+            }
+        }
+    }
+
+    /// Diagonal of the inertia tensor per unit mass around the node's origin, in local
+    /// space, with the node's world `scale` applied.
+    pub(crate) fn unit_inertia(&self, node_scale: [f32; 3]) -> [f32; 3] {
+        let node_scale = node_scale.map(f32::abs);
+        let uniform = node_scale.into_iter().fold(0.0, f32::max);
+        match self.shape {
+            ColliderShape::Sphere { radius } => [0.4 * (radius * uniform).powi(2); 3],
+            ColliderShape::Box { half_extents } => {
+                let [x, y, z] = std::array::from_fn(|axis| (half_extents[axis] * node_scale[axis]).powi(2));
+                [(y + z) / 3.0, (x + z) / 3.0, (x + y) / 3.0]
+            }
+            ColliderShape::Capsule { half_height, radius } => {
+                // As a solid cylinder spanning the whole capsule
+                let (radius, height) = (radius * uniform, 2.0 * (half_height * node_scale[1] + radius * uniform));
+                let across = (3.0 * radius * radius + height * height) / 12.0;
+                [across, radius * radius / 2.0, across]
+            }
+        }
+    }
+}
+
+/// A collider's shape placed in the world for one simulation step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum WorldShape {
+    Sphere { center: [f32; 3], radius: f32 },
+    Box { center: [f32; 3], axes: [[f32; 3]; 3], half_extents: [f32; 3] },
+    Capsule { start: [f32; 3], end: [f32; 3], radius: f32 },
+}
+
+/// Where two shapes touch.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Manifold {
+    /// Contact normal, pointing from the first shape to the second.
+    pub normal: [f32; 3],
+
+    /// How far the shapes overlap along the normal.
+    pub depth: f32,
+
+    /// World-space contact points.
+    pub points: Vec<[f32; 3]>,
+}
+
+impl Manifold {
+    fn flipped(mut self) -> Self {
+        self.normal = scale(self.normal, -1.0);
+        self
+    }
+}
+
+impl WorldShape {
+    /// World-space bounds of the shape.
+    pub(crate) fn aabb(&self) -> Aabb {
+        match *self {
+            WorldShape::Sphere { center, radius } => Aabb::new(sub(center, [radius; 3]), add(center, [radius; 3])),
+            WorldShape::Box { center, axes, half_extents } => {
+                let reach: [f32; 3] =
+                    std::array::from_fn(|i| (0..3).map(|axis| (axes[axis][i] * half_extents[axis]).abs()).sum());
+                Aabb::new(sub(center, reach), add(center, reach))
+            }
+            WorldShape::Capsule { start, end, radius } => {
+                let mut bounds = Aabb::new(sub(start, [radius; 3]), add(start, [radius; 3]));
+                bounds.expand(sub(end, [radius; 3]));
+                bounds.expand(add(end, [radius; 3]));
+                bounds
+            }
+        }
+    }
+}
+
+/// The contact between `a` and `b`, with the normal pointing from `a` to `b`, or `None` if
+/// they don't overlap.
+pub(crate) fn collide(a: &WorldShape, b: &WorldShape) -> Option<Manifold> {
+    use WorldShape::*;
+    match (*a, *b) {
+        (Sphere { center: ca, radius: ra }, Sphere { center: cb, radius: rb }) => spheres(ca, ra, cb, rb),
+        (Sphere { center, radius }, Box { .. }) => sphere_box(center, radius, b),
+        (Box { .. }, Sphere { center, radius }) => sphere_box(center, radius, a).map(Manifold::flipped),
+        (Sphere { center, radius: ra }, Capsule { start, end, radius: rb }) => {
+            spheres(center, ra, closest_on_segment(center, start, end), rb)
+        }
+        (Capsule { start, end, radius: ra }, Sphere { center, radius: rb }) => {
+            spheres(closest_on_segment(center, start, end), ra, center, rb)
+        }
+        (Capsule { start: a0, end: a1, radius: ra }, Capsule { start: b0, end: b1, radius: rb }) => {
+            let (pa, pb) = closest_between_segments(a0, a1, b0, b1);
+            spheres(pa, ra, pb, rb)
+        }
+        (Capsule { start, end, radius }, Box { .. }) => capsule_box(start, end, radius, b),
+        (Box { .. }, Capsule { start, end, radius }) => capsule_box(start, end, radius, a).map(Manifold::flipped),
+        (Box { .. }, Box { .. }) => boxes(a, b),
+    }
+}
+
+fn spheres(ca: [f32; 3], ra: f32, cb: [f32; 3], rb: f32) -> Option<Manifold> {
+    let offset = sub(cb, ca);
+    let gap = length(offset);
+    if gap >= ra + rb {
+        return None;
+    }
+    let normal = normalize_or(offset, [0.0, 1.0, 0.0]);
+    let depth = ra + rb - gap;
+    Some(Manifold { normal, depth, points: vec![add(ca, scale(normal, ra - depth / 2.0))] })
+}
+
+/// Sphere against a [`WorldShape::Box`], normal from the sphere to the box.
+fn sphere_box(center: [f32; 3], radius: f32, cuboid: &WorldShape) -> Option<Manifold> {
+    let WorldShape::Box { center: box_center, axes, half_extents } = *cuboid else {
+        return None;
+    };
+    let local: [f32; 3] = std::array::from_fn(|axis| dot(sub(center, box_center), axes[axis]));
+    let clamped: [f32; 3] = std::array::from_fn(|axis| local[axis].clamp(-half_extents[axis], half_extents[axis]));
+
+    if clamped == local {
+        // Center inside the box: push out through the nearest face
+        let axis = (0..3)
+            .min_by(|&i, &j| (half_extents[i] - local[i].abs()).total_cmp(&(half_extents[j] - local[j].abs())))
+            .unwrap_or(0);
+        let outward = scale(axes[axis], if local[axis] < 0.0 { -1.0 } else { 1.0 });
+        let depth = radius + half_extents[axis] - local[axis].abs();
+        return Some(Manifold { normal: scale(outward, -1.0), depth, points: vec![center] });
+    }
+
+    let closest = (0..3).fold(box_center, |point, axis| add(point, scale(axes[axis], clamped[axis])));
+    let offset = sub(center, closest);
+    let gap = length(offset);
+    if gap >= radius {
+        return None;
+    }
+    let outward = normalize_or(offset, [0.0, 1.0, 0.0]);
+    Some(Manifold { normal: scale(outward, -1.0), depth: radius - gap, points: vec![closest] })
+}
+
+/// Capsule against a [`WorldShape::Box`], normal from the capsule to the box: the deepest
+/// of the spheres at the capsule's ends and nearest the box, with all their contact points,
+/// so a capsule lying on a box rests on both ends.
+fn capsule_box(start: [f32; 3], end: [f32; 3], radius: f32, cuboid: &WorldShape) -> Option<Manifold> {
+    let WorldShape::Box { center, .. } = *cuboid else {
+        return None;
+    };
+    let samples = [start, end, closest_on_segment(center, start, end)];
+    let mut deepest: Option<Manifold> = None;
+    let mut points: Vec<[f32; 3]> = Vec::new();
+    for sample in samples {
+        let Some(contact) = sphere_box(sample, radius, cuboid) else {
+            continue;
+        };
+        for &point in &contact.points {
+            if points.iter().all(|&other| distance(other, point) > POINT_MERGE_DISTANCE) {
+                points.push(point);
+            }
+        }
+        if deepest.as_ref().is_none_or(|deepest| contact.depth > deepest.depth) {
+            deepest = Some(contact);
+        }
+    }
+    deepest.map(|deepest| Manifold { points, ..deepest })
+}
+
+/// Two [`WorldShape::Box`]es, by the separating axis test; contacts are the corners of
+/// each box inside the other.
+fn boxes(a: &WorldShape, b: &WorldShape) -> Option<Manifold> {
+    let (WorldShape::Box { center: ca, axes: aa, half_extents: ha }, WorldShape::Box { center: cb, axes: ab, half_extents: hb }) =
+        (*a, *b)
+    else {
+        return None;
+    };
+    let between = sub(cb, ca);
+    let project = |axes: &[[f32; 3]; 3], half: &[f32; 3], direction: [f32; 3]| -> f32 {
+        (0..3).map(|i| half[i] * dot(axes[i], direction).abs()).sum()
+    };
+
+    let mut candidates: Vec<([f32; 3], bool)> = aa.iter().chain(&ab).map(|&axis| (axis, true)).collect();
+    for x in aa {
+        for y in ab {
+            let axis = cross(x, y);
+            if length(axis) > 1e-4 {
+                candidates.push((normalize_or(axis, x), false));
+            }
+        }
+    }
+
+    // Least overlapping axis; edge axes must win clearly over face axes, which give steadier
+    // contacts for resting boxes
+    let mut best: Option<([f32; 3], f32)> = None;
+    for (axis, is_face) in candidates {
+        let overlap = project(&aa, &ha, axis) + project(&ab, &hb, axis) - dot(between, axis).abs();
+        if overlap <= 0.0 {
+            return None;
+        }
+        let biased = if is_face { overlap } else { overlap * 1.05 + 1e-3 };
+        if best.is_none_or(|(_, best)| biased < best) {
+            let normal = if dot(between, axis) < 0.0 { scale(axis, -1.0) } else { axis };
+            best = Some((normal, biased));
+        }
+    }
+    let (normal, _) = best?;
+    let depth = project(&aa, &ha, normal) + project(&ab, &hb, normal) - dot(between, normal).abs();
+
+    let corners = |center: [f32; 3], axes: [[f32; 3]; 3], half: [f32; 3]| -> [[f32; 3]; 8] {
+        std::array::from_fn(|i| {
+            (0..3).fold(center, |point, axis| {
+                let sign = if i & (1 << axis) == 0 { -1.0 } else { 1.0 };
+                add(point, scale(axes[axis], half[axis] * sign))
+            })
+        })
+    };
+    let inside = |point: [f32; 3], center: [f32; 3], axes: [[f32; 3]; 3], half: [f32; 3]| {
+        (0..3).all(|axis| dot(sub(point, center), axes[axis]).abs() <= half[axis] + depth + POINT_MERGE_DISTANCE)
+    };
+    let mut points: Vec<[f32; 3]> = Vec::new();
+    let a_in_b = corners(ca, aa, ha).into_iter().filter(|&corner| inside(corner, cb, ab, hb));
+    let b_in_a = corners(cb, ab, hb).into_iter().filter(|&corner| inside(corner, ca, aa, ha));
+    for point in a_in_b.chain(b_in_a) {
+        if points.iter().all(|&other| distance(other, point) > POINT_MERGE_DISTANCE) {
+            points.push(point);
+        }
+    }
+    if points.is_empty() {
+        // Edge against edge: between the boxes' deepest points along the normal
+        let support = |center: [f32; 3], axes: [[f32; 3]; 3], half: [f32; 3], direction: [f32; 3]| {
+            (0..3).fold(center, |point, axis| {
+                add(point, scale(axes[axis], half[axis] * dot(axes[axis], direction).signum()))
+            })
+        };
+        points.push(lerp(support(ca, aa, ha, normal), support(cb, ab, hb, scale(normal, -1.0)), 0.5));
+    }
+    Some(Manifold { normal, depth, points })
+}
+
+/// The point of the segment from `a` to `b` closest to `point`.
+pub(crate) fn closest_on_segment(point: [f32; 3], a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    let ab = sub(b, a);
+    let length_squared = dot(ab, ab);
+    if length_squared <= f32::EPSILON {
+        return a;
+    }
+    lerp(a, b, (dot(sub(point, a), ab) / length_squared).clamp(0.0, 1.0))
+}
+
+/// The closest points between segments `a0`–`a1` and `b0`–`b1`.
+fn closest_between_segments(a0: [f32; 3], a1: [f32; 3], b0: [f32; 3], b1: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let (da, db, r) = (sub(a1, a0), sub(b1, b0), sub(a0, b0));
+    let (aa, bb, ab) = (dot(da, da), dot(db, db), dot(da, db));
+    let (ar, br) = (dot(da, r), dot(db, r));
+    let (s, t) = if aa <= f32::EPSILON && bb <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if aa <= f32::EPSILON {
+        (0.0, (br / bb).clamp(0.0, 1.0))
+    } else if bb <= f32::EPSILON {
+        ((-ar / aa).clamp(0.0, 1.0), 0.0)
+    } else {
+        let denominator = aa * bb - ab * ab;
+        let s = if denominator > f32::EPSILON { ((ab * br - ar * bb) / denominator).clamp(0.0, 1.0) } else { 0.0 };
+        let t = (ab * s + br) / bb;
+        if t < 0.0 {
+            ((-ar / aa).clamp(0.0, 1.0), 0.0)
+        } else if t > 1.0 {
+            (((ab - ar) / aa).clamp(0.0, 1.0), 1.0)
+        } else {
+            (s, t)
+        }
+    };
+    (lerp(a0, a1, s), lerp(b0, b1, t))
+}
@@ -0,0 +1,183 @@
+//! Collision shapes fitted to meshes.
+//!
+//! Imported models come with render meshes but rarely with collision shapes. The functions
+//! here fit a [`Collider`] around a [`Geometry`]'s vertices, in the geometry's own space, so
+//! it can go straight onto the node drawing it: [`fit_sphere`], [`fit_box`] and
+//! [`fit_capsule`], or [`fit_collider`] choosing by [`FitShape`]. [`add_fitted_colliders`]
+//! does this for every mesh of an imported model at once.
+//!
+//! Colliders are aligned with their node's axes, so boxes are the geometry's axis-aligned
+//! bounds and capsules stand upright along Y, as characters and pillars do. Fits enclose
+//! every vertex; a model posed diagonally in its own space gets a loose box. For a tight
+//! convex proxy of the mesh itself, see [`convex_hull`](crate::engine::mesh::hull::convex_hull).
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::import::load_model;
+//! # use rustge::engine::physics::fit::{add_fitted_colliders, FitShape};
+//! let crate_model = load_model("assets/crate.glb").expect("failed to load model");
+//! add_fitted_colliders(&crate_model, FitShape::Tightest);
+//! ```
+
+use std::cell::RefCell;
+use std::f32::consts::PI;
+use std::rc::Rc;
+use crate::engine::math::vec::{add, distance, lerp, scale, sub};
+use crate::engine::object3d::{Geometry, Object3D};
+use crate::engine::physics::collider::{Collider, ColliderShape};
+
+/// Which primitive [`fit_collider`] fits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FitShape {
+    /// A ball, see [`fit_sphere`].
+    Sphere,
+    /// An axis-aligned box, see [`fit_box`].
+    Box,
+    /// An upright capsule, see [`fit_capsule`].
+    Capsule,
+    /// Whichever of the three encloses the least volume.
+    #[default]
+    Tightest,
+}
+
+/// A sphere around every vertex of `geometry`, or `None` without vertices.
+///
+/// Ritter's approximation: usually within a few percent of the smallest enclosing sphere,
+/// and never larger than the sphere around the bounding box.
+///
+/// ```
+/// # use rustge::engine::object3d::{Geometry, Topology};
+/// # use rustge::engine::physics::{collider::ColliderShape, fit::fit_sphere};
+/// let points = Geometry::from_positions(Topology::Points, &[[-1.0, 2.0, 0.0], [1.0, 2.0, 0.0], [0.0, 2.5, 0.0]]);
+/// let collider = fit_sphere(&points).unwrap();
+/// assert_eq!(collider.offset, [0.0, 2.0, 0.0]);
+/// assert_eq!(collider.shape, ColliderShape::Sphere { radius: 1.0 });
+/// ```
+pub fn fit_sphere(geometry: &Geometry) -> Option<Collider> {
+    let (center, radius) = ritter_sphere(geometry)?;
+    let (box_center, box_radius) = geometry.bounding_sphere();
+    let (center, radius) = if box_radius < radius { (box_center, box_radius) } else { (center, radius) };
+    Some(Collider { offset: center, ..Collider::sphere(radius) })
+}
+
+/// The axis-aligned box around every vertex of `geometry`, or `None` without vertices.
+///
+/// ```
+/// # use rustge::engine::object3d::{Geometry, Topology};
+/// # use rustge::engine::physics::{collider::ColliderShape, fit::fit_box};
+/// let points = Geometry::from_positions(Topology::Points, &[[0.0, 0.0, 0.0], [2.0, 1.0, 4.0]]);
+/// let collider = fit_box(&points).unwrap();
+/// assert_eq!(collider.offset, [1.0, 0.5, 2.0]);
+/// assert_eq!(collider.shape, ColliderShape::Box { half_extents: [1.0, 0.5, 2.0] });
+/// ```
+pub fn fit_box(geometry: &Geometry) -> Option<Collider> {
+    let bounds = geometry.aabb()?;
+    Some(Collider { offset: bounds.center(), ..Collider::cuboid(scale(bounds.size(), 0.5)) })
+}
+
+/// The upright (Y axis) capsule around every vertex of `geometry`, or `None` without
+/// vertices. The radius reaches the farthest vertex from the vertical axis through the
+/// center of the bounds, and the caps are placed as close together as that radius allows;
+/// geometry wider than it is tall gets a sphere (zero half height).
+///
+/// ```
+/// # use rustge::engine::object3d::{Geometry, Topology};
+/// # use rustge::engine::physics::{collider::ColliderShape, fit::fit_capsule};
+/// // A 2 m tall, 1 m wide figure standing on the origin
+/// let figure = Geometry::from_positions(
+///     Topology::Points,
+///     &[[0.0, 0.0, 0.0], [-0.5, 1.0, 0.0], [0.5, 1.0, 0.0], [0.0, 2.0, 0.0]],
+/// );
+/// let collider = fit_capsule(&figure).unwrap();
+/// assert_eq!(collider.offset, [0.0, 1.0, 0.0]);
+/// assert_eq!(collider.shape, ColliderShape::Capsule { half_height: 0.5, radius: 0.5 });
+/// ```
+pub fn fit_capsule(geometry: &Geometry) -> Option<Collider> {
+    let center = geometry.aabb()?.center();
+    let across = |position: [f32; 3]| distance([position[0], 0.0, position[2]], [center[0], 0.0, center[2]]);
+    let radius = geometry.vertices.iter().map(|vertex| across(vertex.position)).fold(0.0f32, f32::max);
+    // A vertex at distance d from the axis is inside if the segment between the cap centers
+    // comes within the half chord sqrt(r² - d²) of its height: the top center must be at
+    // least that far below it, the bottom center at most that far above it.
+    let (mut top, mut bottom) = (f32::NEG_INFINITY, f32::INFINITY);
+    for vertex in &geometry.vertices {
+        let chord = (radius * radius - across(vertex.position).powi(2)).max(0.0).sqrt();
+        top = top.max(vertex.position[1] - chord);
+        bottom = bottom.min(vertex.position[1] + chord);
+    }
+    let half_height = ((top - bottom) / 2.0).max(0.0);
+    let offset = [center[0], (top + bottom) / 2.0, center[2]];
+    Some(Collider { offset, ..Collider::capsule(half_height, radius) })
+}
+
+/// Fits `shape` around every vertex of `geometry`, or `None` without vertices.
+pub fn fit_collider(geometry: &Geometry, shape: FitShape) -> Option<Collider> {
+    match shape {
+        FitShape::Sphere => fit_sphere(geometry),
+        FitShape::Box => fit_box(geometry),
+        FitShape::Capsule => fit_capsule(geometry),
+        FitShape::Tightest => [fit_sphere(geometry), fit_box(geometry), fit_capsule(geometry)]
+            .into_iter()
+            .flatten()
+            .min_by(|a, b| volume(&a.shape).total_cmp(&volume(&b.shape))),
+    }
+}
+
+/// Gives `root` and every node under it that has geometry but no [`Collider`] yet one of
+/// `shape` fitted to its geometry, e.g. right after
+/// [importing](crate::engine::import::load_model) a model. Returns the number of colliders
+/// added. Without a [`RigidBody`](crate::engine::physics::body::RigidBody) the colliders are
+/// static.
+pub fn add_fitted_colliders(root: &Rc<RefCell<Object3D>>, shape: FitShape) -> usize {
+    let (mut added, children) = {
+        let mut node = root.borrow_mut();
+        let collider = match node.geometry() {
+            Some(geometry) if !node.has_component::<Collider>() => fit_collider(geometry, shape),
+            _ => None,
+        };
+        let added = match collider {
+            Some(collider) => {
+                node.insert_component(collider);
+                1
+            }
+            None => 0,
+        };
+        (added, node.children().to_vec())
+    };
+    for child in &children {
+        added += add_fitted_colliders(child, shape);
+    }
+    added
+}
+
+/// Volume enclosed by `shape`.
+fn volume(shape: &ColliderShape) -> f32 {
+    match *shape {
+        ColliderShape::Sphere { radius } => 4.0 / 3.0 * PI * radius.powi(3),
+        ColliderShape::Box { half_extents: [x, y, z] } => 8.0 * x * y * z,
+        ColliderShape::Capsule { half_height, radius } => PI * radius * radius * (2.0 * half_height + 4.0 / 3.0 * radius),
+    }
+}
+
+/// Ritter's bounding sphere: span the sphere between a vertex far from the first and the
+/// vertex farthest from that one, then grow it over every vertex still outside.
+fn ritter_sphere(geometry: &Geometry) -> Option<([f32; 3], f32)> {
+    let positions = || geometry.vertices.iter().map(|vertex| vertex.position);
+    let farthest_from = |from: [f32; 3]| {
+        positions().max_by(|&a, &b| distance(from, a).total_cmp(&distance(from, b)))
+    };
+    let start = farthest_from(positions().next()?)?;
+    let end = farthest_from(start)?;
+    let mut center = lerp(start, end, 0.5);
+    let mut radius = distance(start, end) / 2.0;
+    for position in positions() {
+        let gap = distance(center, position);
+        if gap > radius {
+            // Grow just enough to reach the vertex, keeping the far side where it is
+            let grown = (radius + gap) / 2.0;
+            center = add(center, scale(sub(position, center), (grown - radius) / gap));
+            radius = grown;
+        }
+    }
+    Some((center, radius))
+}
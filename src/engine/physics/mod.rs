@@ -0,0 +1,7 @@
+pub mod body;
+pub mod character;
+pub mod cloth;
+pub mod collider;
+pub mod fit;
+pub mod spatial_hash;
+pub mod world;
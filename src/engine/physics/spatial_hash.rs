@@ -0,0 +1,194 @@
+//! Uniform grid broadphase for many moving objects.
+//!
+//! A [`SpatialHash`] buckets items by the grid cells their bounds overlap, hashing cell
+//! coordinates so the grid is unbounded and costs memory only where items are. Finding the
+//! items near a point, or every pair of items whose bounds overlap, then only looks at a
+//! few cells instead of every item: the neighbors of each of a thousand boids, or the
+//! enemies each projectile might hit, in roughly linear time.
+//!
+//! The grid suits items of similar size, with cells about as large as the items or the
+//! query radius. Rebuilding it every frame is cheap, and simpler than moving items between
+//! cells. Items spanning too many cells, such as the ground, are kept aside and tested
+//! against every query instead.
+//!
+//! The physics world finds its colliding pairs with one, and
+//! [`Scene::neighbors_within`](crate::engine::scene::Scene::neighbors_within) finds nodes
+//! near a point with another.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::aabb::Aabb;
+//! # use rustge::engine::physics::spatial_hash::SpatialHash;
+//! let mut grid = SpatialHash::new(2.0);
+//! for i in 0..100 {
+//!     grid.insert_point(i, [i as f32, 0.0, 0.0]);
+//! }
+//! let mut near: Vec<i32> = grid.within([50.0, 0.0, 0.0], 1.5).into_iter().copied().collect();
+//! near.sort();
+//! assert_eq!(near, [49, 50, 51]);
+//!
+//! assert!(grid.pairs().is_empty());
+//!
+//! // The ground spans too many cells for the grid, but is still found
+//! grid.insert(100, Aabb::new([-1000.0, -1.0, -1000.0], [1000.0, 0.0, 1000.0]));
+//! assert_eq!(grid.pairs().len(), 100);
+//! assert_eq!(grid.query(&Aabb::new([0.0, -2.0, 0.0], [0.0, -1.0, 0.0])), [&100]);
+//! ```
+
+use std::collections::HashMap;
+use crate::engine::math::aabb::Aabb;
+
+/// Items covering more cells than this are kept out of the grid.
+const MAX_CELLS_PER_ITEM: usize = 64;
+
+/// Items bucketed by grid cell; see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    /// Indices into `items` by cell coordinates.
+    cells: HashMap<[i32; 3], Vec<usize>>,
+    items: Vec<(T, Aabb)>,
+    /// Items too large for the grid, tested against every query.
+    oversized: Vec<usize>,
+}
+
+impl<T> SpatialHash<T> {
+    /// An empty grid of cubic cells `cell_size` units across.
+    pub fn new(cell_size: f32) -> Self {
+        let cell_size = if cell_size.is_finite() && cell_size > 0.0 { cell_size } else { 1.0 };
+        Self { cell_size, cells: HashMap::new(), items: Vec::new(), oversized: Vec::new() }
+    }
+
+    /// Size of the cells.
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Number of items inserted.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if nothing has been inserted since the grid was created or cleared.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Removes every item, keeping the table's allocation for the next frame's items.
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.items.clear();
+        self.oversized.clear();
+    }
+
+    /// Inserts `item` with world-space `bounds`.
+    pub fn insert(&mut self, item: T, bounds: Aabb) {
+        let index = self.items.len();
+        self.items.push((item, bounds));
+        let (low, high) = (self.cell(bounds.min), self.cell(bounds.max));
+        let count = cell_count(low, high);
+        if count > MAX_CELLS_PER_ITEM {
+            self.oversized.push(index);
+            return;
+        }
+        for x in low[0]..=high[0] {
+            for y in low[1]..=high[1] {
+                for z in low[2]..=high[2] {
+                    self.cells.entry([x, y, z]).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    /// Inserts `item` at `point`.
+    pub fn insert_point(&mut self, item: T, point: [f32; 3]) {
+        self.insert(item, Aabb::new(point, point));
+    }
+
+    /// The items whose bounds overlap `bounds`, each once, in insertion order.
+    pub fn query(&self, bounds: &Aabb) -> Vec<&T> {
+        self.candidates(bounds)
+            .into_iter()
+            .filter(|&index| overlaps(&self.items[index].1, bounds))
+            .map(|index| &self.items[index].0)
+            .collect()
+    }
+
+    /// The items whose bounds come within `radius` of `center`, each once, in insertion
+    /// order. For items inserted as points, those at most `radius` away.
+    pub fn within(&self, center: [f32; 3], radius: f32) -> Vec<&T> {
+        let reach = Aabb::new(center.map(|c| c - radius), center.map(|c| c + radius));
+        self.candidates(&reach)
+            .into_iter()
+            .filter(|&index| distance_squared_to(&self.items[index].1, center) <= radius * radius)
+            .map(|index| &self.items[index].0)
+            .collect()
+    }
+
+    /// Every pair of items whose bounds overlap, each once, the earlier inserted item first.
+    /// Pairs are sorted by insertion order, so the result is the same from run to run.
+    pub fn pairs(&self) -> Vec<(&T, &T)> {
+        let mut pairs = Vec::new();
+        for members in self.cells.values() {
+            for (k, &a) in members.iter().enumerate() {
+                pairs.extend(members[k + 1..].iter().map(|&b| (a.min(b), a.max(b))));
+            }
+        }
+        for &a in &self.oversized {
+            pairs.extend((0..self.items.len()).filter(|&b| b != a).map(|b| (a.min(b), a.max(b))));
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+            .into_iter()
+            .filter(|&(a, b)| overlaps(&self.items[a].1, &self.items[b].1))
+            .map(|(a, b)| (&self.items[a].0, &self.items[b].0))
+            .collect()
+    }
+
+    /// Indices of the items sharing a cell with `bounds`, or too large for the grid,
+    /// sorted and without repeats.
+    fn candidates(&self, bounds: &Aabb) -> Vec<usize> {
+        let mut found = self.oversized.clone();
+        let (low, high) = (self.cell(bounds.min), self.cell(bounds.max));
+        let count = cell_count(low, high);
+        if count > self.cells.len() {
+            // Fewer occupied cells than cells covered: check those instead
+            for (cell, members) in &self.cells {
+                if (0..3).all(|axis| (low[axis]..=high[axis]).contains(&cell[axis])) {
+                    found.extend(members);
+                }
+            }
+        } else {
+            for x in low[0]..=high[0] {
+                for y in low[1]..=high[1] {
+                    for z in low[2]..=high[2] {
+                        found.extend(self.cells.get(&[x, y, z]).into_iter().flatten());
+                    }
+                }
+            }
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+
+    /// Coordinates of the cell containing `point`, clamped far beyond any real scene.
+    fn cell(&self, point: [f32; 3]) -> [i32; 3] {
+        point.map(|c| (c / self.cell_size).floor().clamp(-1e9, 1e9) as i32)
+    }
+}
+
+/// Number of cells from `low` to `high` inclusive, saturating.
+fn cell_count(low: [i32; 3], high: [i32; 3]) -> usize {
+    (0..3).fold(1usize, |count, axis| count.saturating_mul((high[axis] as i64 - low[axis] as i64 + 1) as usize))
+}
+
+fn overlaps(a: &Aabb, b: &Aabb) -> bool {
+    (0..3).all(|axis| a.min[axis] <= b.max[axis] && b.min[axis] <= a.max[axis])
+}
+
+/// Squared distance from `point` to the nearest point of `bounds`.
+fn distance_squared_to(bounds: &Aabb, point: [f32; 3]) -> f32 {
+    (0..3).map(|axis| (point[axis].clamp(bounds.min[axis], bounds.max[axis]) - point[axis]).powi(2)).sum()
+}
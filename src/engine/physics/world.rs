@@ -0,0 +1,496 @@
+//! The rigid body simulation.
+//!
+//! A [`PhysicsWorld`] steps every node of a scene that has a
+//! [`RigidBody`] or a [`Collider`]: dynamic bodies fall under the scene's gravity (see
+//! [`SimulationSettings`]), collide and come to rest on each other, and the simulated
+//! transforms are written back into the nodes. The renderer owns a world and steps it
+//! every frame with the clock's delta, after the update callback and systems (see
+//! [`Renderer::physics_mut`](crate::engine::renderer::Renderer::physics_mut)); a world can
+//! also be stepped by hand, e.g. on a server or in tests.
+//!
+//! The simulation runs in fixed steps of the scene's timestep, each divided into its
+//! substeps. Contacts are solved with sequential impulses: friction, restitution and a
+//! gentle push apart of overlapping shapes. Fast, small bodies can tunnel through thin
+//! colliders within one step; more substeps help. Bodies never sleep, so large piles cost
+//! the same at rest as in motion. Colliding pairs are found with a
+//! [spatial hash](crate::engine::physics::spatial_hash), so the cost grows with the number
+//! of bodies near each other rather than with the square of all bodies.
+//!
+//! When two colliders start or stop touching, the world calls its
+//! [collision callbacks](PhysicsWorld::on_collision) once per pair, after the step's
+//! transforms are written back. Sensors report overlaps the same way.
+//!
+//! # Example
+//! ```
+//! # use std::{cell::Cell, rc::Rc};
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::physics::{body::RigidBody, collider::Collider, world::{CollisionPhase, PhysicsWorld}};
+//! # use rustge::engine::scene::Scene;
+//! let scene = Scene::new();
+//! let ground = Object3D::new();
+//! ground.borrow_mut().insert_component(Collider::cuboid([10.0, 0.5, 10.0]));
+//! scene.add(ground);
+//!
+//! let ball = Object3D::new();
+//! ball.borrow_mut().set_position([0.0, 3.0, 0.0]);
+//! ball.borrow_mut().insert_component(Collider::sphere(0.5));
+//! ball.borrow_mut().insert_component(RigidBody::dynamic(1.0));
+//! scene.add(ball.clone());
+//!
+//! let landed = Rc::new(Cell::new(false));
+//! let mut physics = PhysicsWorld::new();
+//! let flag = landed.clone();
+//! physics.on_collision(move |event| flag.set(flag.get() || event.phase == CollisionPhase::Started));
+//!
+//! for _ in 0..120 {
+//!     physics.step(&scene, 1.0 / 60.0);
+//! }
+//! assert!(landed.get());
+//! let height = ball.borrow().position()[1];
+//! assert!((height - 1.0).abs() < 0.05, "resting on the ground, not {height}");
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::matrixfuncs::decompose_matrix;
+use crate::engine::math::quat;
+use crate::engine::math::vec::{add, cross, dot, mul, normalize_or, scale, sub};
+use crate::engine::object3d::{NodeId, Object3D};
+use crate::engine::physics::body::{BodyType, RigidBody};
+use crate::engine::physics::collider::{collide, Collider, WorldShape};
+use crate::engine::physics::spatial_hash::SpatialHash;
+use crate::engine::scene::Scene;
+use crate::engine::simulation::{FixedStepper, SimulationSettings};
+
+/// Velocity solver passes per substep by default.
+const DEFAULT_ITERATIONS: u32 = 10;
+
+/// Overlap left alone, so resting contacts stay in touch instead of jittering.
+const PENETRATION_SLOP: f32 = 0.01;
+
+/// Fraction of the overlap beyond the slop pushed apart per step.
+const POSITION_CORRECTION: f32 = 0.2;
+
+/// Approach speed (units per second) below which contacts don't bounce, so resting bodies
+/// don't vibrate.
+const RESTITUTION_THRESHOLD: f32 = 1.0;
+
+/// Whether two colliders started or stopped touching.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CollisionPhase {
+    /// The colliders touch now and didn't after the previous step.
+    Started,
+
+    /// The colliders touched after the previous step and don't now.
+    Ended,
+}
+
+/// Two colliders starting or stopping to touch, passed to the
+/// [collision callbacks](PhysicsWorld::on_collision).
+#[derive(Clone, Debug)]
+pub struct CollisionEvent {
+    /// Whether the contact started or ended.
+    pub phase: CollisionPhase,
+
+    /// The first node; of the two, the one created first.
+    pub a: Rc<RefCell<Object3D>>,
+
+    /// The second node.
+    pub b: Rc<RefCell<Object3D>>,
+
+    /// A world-space contact point (the last one, for ended contacts).
+    pub point: [f32; 3],
+
+    /// The contact normal, pointing from `a` to `b`.
+    pub normal: [f32; 3],
+
+    /// Whether either collider is a sensor, so the contact had no physical effect.
+    pub sensor: bool,
+}
+
+type CollisionCallback = Box<dyn FnMut(&CollisionEvent)>;
+
+/// A pair of colliders in contact.
+#[derive(Clone, Debug)]
+struct Touch {
+    a: Weak<RefCell<Object3D>>,
+    b: Weak<RefCell<Object3D>>,
+    point: [f32; 3],
+    normal: [f32; 3],
+    sensor: bool,
+}
+
+/// Steps the bodies of a scene; see the [module documentation](self).
+pub struct PhysicsWorld {
+    stepper: FixedStepper,
+    iterations: u32,
+
+    /// Pairs in contact after the last step, by ascending node id.
+    touching: HashMap<(NodeId, NodeId), Touch>,
+    callbacks: Vec<CollisionCallback>,
+}
+
+impl std::fmt::Debug for PhysicsWorld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PhysicsWorld")
+            .field("iterations", &self.iterations)
+            .field("touching", &self.touching.len())
+            .field("callbacks", &self.callbacks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node's body during a step.
+struct Body {
+    node: Rc<RefCell<Object3D>>,
+    id: NodeId,
+    body: RigidBody,
+    collider: Option<Collider>,
+    position: [f32; 3],
+    rotation: [f32; 4],
+    scale: [f32; 3],
+    inverse_mass: f32,
+
+    /// Inverse inertia per local axis; zero for bodies that don't rotate.
+    inverse_inertia: [f32; 3],
+    shape: Option<WorldShape>,
+}
+
+impl Body {
+    fn is_dynamic(&self) -> bool {
+        self.body.body_type == BodyType::Dynamic
+    }
+
+    /// Applies the world-space inverse inertia tensor to `v`.
+    fn inverse_inertia_times(&self, v: [f32; 3]) -> [f32; 3] {
+        let local = quat::rotate_vector(quat::conjugate(self.rotation), v);
+        quat::rotate_vector(self.rotation, mul(self.inverse_inertia, local))
+    }
+
+    fn velocity_at(&self, offset: [f32; 3]) -> [f32; 3] {
+        add(self.body.velocity, cross(self.body.angular_velocity, offset))
+    }
+
+    fn apply_impulse(&mut self, impulse: [f32; 3], offset: [f32; 3]) {
+        self.body.velocity = add(self.body.velocity, scale(impulse, self.inverse_mass));
+        let spin = self.inverse_inertia_times(cross(offset, impulse));
+        self.body.angular_velocity = add(self.body.angular_velocity, spin);
+    }
+
+    /// Inverse of the mass felt by an impulse along `direction` at `offset` from the center.
+    fn inverse_mass_along(&self, offset: [f32; 3], direction: [f32; 3]) -> f32 {
+        self.inverse_mass + dot(direction, cross(self.inverse_inertia_times(cross(offset, direction)), offset))
+    }
+}
+
+/// One point of a [`Contact`], with the impulses accumulated over the solver passes.
+struct ContactPoint {
+    offset_a: [f32; 3],
+    offset_b: [f32; 3],
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+
+    /// Normal velocity the solver aims for: the bounce, or the push out of overlap.
+    target_velocity: f32,
+    normal_impulse: f32,
+    tangent_impulse: [f32; 2],
+}
+
+/// The contact points between two bodies, solved together.
+struct Contact {
+    a: usize,
+    b: usize,
+    normal: [f32; 3],
+    tangents: [[f32; 3]; 2],
+    friction: f32,
+    points: Vec<ContactPoint>,
+}
+
+impl PhysicsWorld {
+    /// A world with no collision callbacks.
+    pub fn new() -> Self {
+        Self { stepper: FixedStepper::new(), iterations: DEFAULT_ITERATIONS, touching: HashMap::new(), callbacks: Vec::new() }
+    }
+
+    /// Velocity solver passes per substep.
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Sets the velocity solver passes per substep (default 10). More passes make stacks
+    /// steadier at a proportional cost.
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.max(1);
+    }
+
+    /// Registers `callback` to be called for each pair of colliders starting or stopping
+    /// to touch.
+    pub fn on_collision(&mut self, callback: impl FnMut(&CollisionEvent) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Whether the colliders of `a` and `b` touched after the last step.
+    pub fn is_touching(&self, a: &Rc<RefCell<Object3D>>, b: &Rc<RefCell<Object3D>>) -> bool {
+        let (a, b) = (a.borrow().id(), b.borrow().id());
+        self.touching.contains_key(&(a.min(b), a.max(b)))
+    }
+
+    /// Drops accumulated time and forgets contacts without reporting them as ended, e.g.
+    /// after loading a level.
+    pub fn reset(&mut self) {
+        self.stepper.reset();
+        self.touching.clear();
+    }
+
+    /// Advances the bodies in `scene` by a frame of `delta` seconds, in fixed steps of the
+    /// scene's [simulation settings](Scene::simulation_settings), writes their transforms
+    /// and velocities back into the nodes and reports contacts that started or ended.
+    ///
+    /// # Panics
+    /// Panics if a simulated node (or its parent) is already borrowed.
+    pub fn step(&mut self, scene: &Scene, delta: f32) {
+        let settings = *scene.simulation_settings();
+        let steps = self.stepper.advance(&settings, delta);
+        if steps == 0 {
+            return;
+        }
+
+        let mut bodies = gather_bodies(scene);
+        let mut touching = HashMap::new();
+        for _ in 0..steps {
+            for _ in 0..settings.substeps.max(1) {
+                touching.clear();
+                self.substep(&mut bodies, &settings, &mut touching);
+            }
+        }
+        write_back(&bodies);
+
+        let mut events = Vec::new();
+        for (key, touch) in &touching {
+            if !self.touching.contains_key(key) {
+                events.push((*key, CollisionPhase::Started, touch.clone()));
+            }
+        }
+        for (key, touch) in &self.touching {
+            if !touching.contains_key(key) {
+                events.push((*key, CollisionPhase::Ended, touch.clone()));
+            }
+        }
+        self.touching = touching;
+
+        events.sort_by_key(|(key, phase, _)| (*key, *phase == CollisionPhase::Started));
+        for (_, phase, touch) in events {
+            let (Some(a), Some(b)) = (touch.a.upgrade(), touch.b.upgrade()) else {
+                continue;
+            };
+            let event = CollisionEvent { phase, a, b, point: touch.point, normal: touch.normal, sensor: touch.sensor };
+            for callback in &mut self.callbacks {
+                callback(&event);
+            }
+        }
+    }
+
+    fn substep(&self, bodies: &mut [Body], settings: &SimulationSettings, touching: &mut HashMap<(NodeId, NodeId), Touch>) {
+        let dt = settings.substep_duration();
+        for body in bodies.iter_mut().filter(|body| body.is_dynamic()) {
+            let gravity = scale(settings.gravity, body.body.gravity_scale * dt);
+            body.body.velocity = scale(add(body.body.velocity, gravity), 1.0 / (1.0 + dt * body.body.linear_damping));
+            body.body.angular_velocity = scale(body.body.angular_velocity, 1.0 / (1.0 + dt * body.body.angular_damping));
+        }
+        for body in bodies.iter_mut() {
+            body.shape = body.collider.map(|collider| collider.world_shape(body.position, body.rotation, body.scale));
+        }
+
+        let mut contacts = Vec::new();
+        for (a, b) in broadphase(bodies) {
+            let (Some(shape_a), Some(shape_b)) = (&bodies[a].shape, &bodies[b].shape) else {
+                continue;
+            };
+            let Some(manifold) = collide(shape_a, shape_b) else {
+                continue;
+            };
+            let surface = |body: &Body| body.collider.unwrap_or(Collider::sphere(0.0));
+            let (collider_a, collider_b) = (surface(&bodies[a]), surface(&bodies[b]));
+            let sensor = collider_a.sensor || collider_b.sensor;
+
+            let (id_a, id_b) = (bodies[a].id, bodies[b].id);
+            let (first, second, normal) =
+                if id_a < id_b { (a, b, manifold.normal) } else { (b, a, scale(manifold.normal, -1.0)) };
+            touching.insert(
+                (id_a.min(id_b), id_a.max(id_b)),
+                Touch {
+                    a: Rc::downgrade(&bodies[first].node),
+                    b: Rc::downgrade(&bodies[second].node),
+                    point: manifold.points[0],
+                    normal,
+                    sensor,
+                },
+            );
+            if sensor || bodies[a].inverse_mass + bodies[b].inverse_mass <= 0.0 {
+                continue;
+            }
+
+            let normal = manifold.normal;
+            let reference = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+            let tangent = normalize_or(cross(normal, reference), [0.0, 0.0, 1.0]);
+            let tangents = [tangent, cross(normal, tangent)];
+            let restitution = collider_a.restitution.max(collider_b.restitution);
+            let push_out = POSITION_CORRECTION / dt * (manifold.depth - PENETRATION_SLOP).max(0.0);
+
+            let points = manifold
+                .points
+                .iter()
+                .map(|&point| {
+                    let (offset_a, offset_b) = (sub(point, bodies[a].position), sub(point, bodies[b].position));
+                    let mass_along = |direction: [f32; 3]| {
+                        let inverse = bodies[a].inverse_mass_along(offset_a, direction)
+                            + bodies[b].inverse_mass_along(offset_b, direction);
+                        if inverse > 0.0 { 1.0 / inverse } else { 0.0 }
+                    };
+                    let approach = dot(sub(bodies[b].velocity_at(offset_b), bodies[a].velocity_at(offset_a)), normal);
+                    let bounce = if approach < -RESTITUTION_THRESHOLD { -restitution * approach } else { 0.0 };
+                    ContactPoint {
+                        offset_a,
+                        offset_b,
+                        normal_mass: mass_along(normal),
+                        tangent_mass: tangents.map(mass_along),
+                        target_velocity: bounce.max(push_out),
+                        normal_impulse: 0.0,
+                        tangent_impulse: [0.0; 2],
+                    }
+                })
+                .collect();
+            let friction = (collider_a.friction * collider_b.friction).max(0.0).sqrt();
+            contacts.push(Contact { a, b, normal, tangents, friction, points });
+        }
+
+        for _ in 0..self.iterations {
+            for contact in &mut contacts {
+                solve_contact(bodies, contact);
+            }
+        }
+
+        for body in bodies.iter_mut().filter(|body| body.body.body_type != BodyType::Static) {
+            body.position = add(body.position, scale(body.body.velocity, dt));
+            let [x, y, z] = scale(body.body.angular_velocity, dt / 2.0);
+            let spin = quat::mul([x, y, z, 0.0], body.rotation);
+            body.rotation = quat::normalize(std::array::from_fn(|i| body.rotation[i] + spin[i]));
+        }
+    }
+}
+
+/// One solver pass over the points of `contact`.
+fn solve_contact(bodies: &mut [Body], contact: &mut Contact) {
+    let (a, b, normal) = (contact.a, contact.b, contact.normal);
+    for point in &mut contact.points {
+        let relative = |bodies: &[Body]| sub(bodies[b].velocity_at(point.offset_b), bodies[a].velocity_at(point.offset_a));
+
+        let approach = dot(relative(bodies), normal);
+        let total = (point.normal_impulse + (point.target_velocity - approach) * point.normal_mass).max(0.0);
+        let impulse = scale(normal, total - point.normal_impulse);
+        point.normal_impulse = total;
+        bodies[a].apply_impulse(scale(impulse, -1.0), point.offset_a);
+        bodies[b].apply_impulse(impulse, point.offset_b);
+
+        let limit = contact.friction * point.normal_impulse;
+        for (i, tangent) in contact.tangents.into_iter().enumerate() {
+            let slide = dot(relative(bodies), tangent);
+            let total = (point.tangent_impulse[i] - slide * point.tangent_mass[i]).clamp(-limit, limit);
+            let impulse = scale(tangent, total - point.tangent_impulse[i]);
+            point.tangent_impulse[i] = total;
+            bodies[a].apply_impulse(scale(impulse, -1.0), point.offset_a);
+            bodies[b].apply_impulse(impulse, point.offset_b);
+        }
+    }
+}
+
+/// Reads the simulated nodes of `scene`, in id order so steps are repeatable.
+fn gather_bodies(scene: &Scene) -> Vec<Body> {
+    let mut nodes: Vec<Rc<RefCell<Object3D>>> = scene.query::<(Collider,)>().collect();
+    nodes.extend(scene.query::<(RigidBody,)>());
+    let mut nodes: Vec<(NodeId, Rc<RefCell<Object3D>>)> = nodes
+        .into_iter()
+        .map(|node| {
+            let id = node.borrow().id();
+            (id, node)
+        })
+        .collect();
+    nodes.sort_by_key(|(id, _)| *id);
+    nodes.dedup_by_key(|(id, _)| *id);
+
+    nodes
+        .into_iter()
+        .map(|(id, node)| {
+            let (position, rotation, scale, body, collider) = {
+                let mut object = node.borrow_mut();
+                let (position, rotation, scale) = decompose_matrix(&object.world_matrix());
+                let body = object.component::<RigidBody>().copied().unwrap_or_else(RigidBody::fixed);
+                (position, rotation, scale, body, object.component::<Collider>().copied())
+            };
+            let inverse_mass = body.inverse_mass();
+            let inverse_inertia = if inverse_mass > 0.0 && !body.lock_rotation {
+                // Bodies without a collider spin like a ball of unit diameter
+                let unit = collider.map_or([0.1; 3], |collider| collider.unit_inertia(scale));
+                unit.map(|inertia| if inertia > 0.0 { inverse_mass / inertia } else { 0.0 })
+            } else {
+                [0.0; 3]
+            };
+            Body { node, id, body, collider, position, rotation, scale, inverse_mass, inverse_inertia, shape: None }
+        })
+        .collect()
+}
+
+/// Pairs of bodies whose bounds overlap, from a [`SpatialHash`] with cells twice the
+/// typical body size, leaving out pairs that can neither move each other nor report
+/// anything.
+fn broadphase(bodies: &[Body]) -> Vec<(usize, usize)> {
+    let bounds: Vec<(usize, Aabb)> =
+        bodies.iter().enumerate().filter_map(|(i, body)| Some((i, body.shape?.aabb()))).collect();
+    let mut sizes: Vec<f32> = bounds.iter().map(|(_, aabb)| aabb.size().into_iter().fold(0.0, f32::max)).collect();
+    sizes.sort_by(f32::total_cmp);
+    let typical = sizes.get(sizes.len() / 2).copied().unwrap_or(1.0);
+
+    let mut grid = SpatialHash::new(2.0 * typical);
+    for (i, aabb) in bounds {
+        grid.insert(i, aabb);
+    }
+    grid.pairs()
+        .into_iter()
+        .map(|(&i, &j)| (i, j))
+        .filter(|&(i, j)| {
+            let (first, second) = (&bodies[i], &bodies[j]);
+            let both_static = first.body.body_type == BodyType::Static && second.body.body_type == BodyType::Static;
+            let sensor = first.collider.is_some_and(|c| c.sensor) || second.collider.is_some_and(|c| c.sensor);
+            !both_static && (first.is_dynamic() || second.is_dynamic() || sensor)
+        })
+        .collect()
+}
+
+/// Writes the simulated transforms and velocities of moving bodies into their nodes.
+fn write_back(bodies: &[Body]) {
+    for body in bodies.iter().filter(|body| body.body.body_type != BodyType::Static) {
+        let mut node = body.node.borrow_mut();
+        let (position, rotation) = match node.parent() {
+            Some(parent) => {
+                let mut parent = parent.borrow_mut();
+                let (_, parent_rotation, _) = decompose_matrix(&parent.world_matrix());
+                (parent.world_to_local(body.position), quat::mul(quat::conjugate(parent_rotation), body.rotation))
+            }
+            None => (body.position, body.rotation),
+        };
+        node.set_position(position);
+        node.set_rotation(quat::normalize(rotation));
+        if let Some(rigid_body) = node.component_mut::<RigidBody>() {
+            rigid_body.velocity = body.body.velocity;
+            rigid_body.angular_velocity = body.body.angular_velocity;
+        }
+    }
+}
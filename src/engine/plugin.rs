@@ -0,0 +1,195 @@
+//! Engine plugins: subsystems added to the renderer as a unit.
+//!
+//! Callbacks and systems suit game code; a subsystem such as audio, networking or a UI
+//! toolkit needs several hooks at once and state of its own. A [`Plugin`] bundles them:
+//!
+//! - [`startup`](Plugin::startup) once, before the first frame it takes part in, to create
+//!   resources, add systems or register input bindings.
+//! - [`update`](Plugin::update) at every [`FrameStage`] of every frame.
+//! - [`event`](Plugin::event) for each window and input event, after the input contexts and
+//!   before the handlers registered with
+//!   [`Renderer::on_event`](crate::engine::renderer::Renderer::on_event).
+//! - [`shutdown`](Plugin::shutdown) when the plugin is removed or the window closes.
+//!
+//! Every hook gets the renderer, so a plugin can reach the scene, world, physics and the
+//! other plugins. Plugins run in the order they were added, which makes it the order of
+//! their [dependencies](Plugin::dependencies); hooks are timed as
+//! [profiler scopes](crate::engine::profiler) named after the plugin.
+//!
+//! The trait only uses public engine types, so extensions can ship as separate crates that
+//! depend on this one.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{event::EngineEvent, plugin::{FrameStage, Plugin}, renderer::Renderer, time::Clock};
+//! #[derive(Default)]
+//! struct FpsLog {
+//!     frames: u64,
+//! }
+//!
+//! impl Plugin for FpsLog {
+//!     fn update(&mut self, renderer: &mut Renderer, stage: FrameStage, clock: &Clock) {
+//!         if stage == FrameStage::PostRender {
+//!             self.frames += 1;
+//!             if self.frames % 60 == 0 {
+//!                 println!("{:.1} fps", clock.fps());
+//!             }
+//!         }
+//!     }
+//! }
+//!
+//! let mut renderer = Renderer::new("Example", 800, 600);
+//! renderer.add_plugin(FpsLog::default());
+//! renderer.run();
+//! ```
+
+use std::any::Any;
+use crate::engine::event::EngineEvent;
+use crate::engine::renderer::Renderer;
+use crate::engine::time::Clock;
+
+/// Points of the frame at which plugins [update](Plugin::update), in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameStage {
+    /// After the clock ticked and uploads and reloads were polled, before the update callback.
+    PreUpdate,
+    /// After the update and UI callbacks, before tweens, systems and physics.
+    Update,
+    /// After systems, physics, the camera controller and the scene update, before rendering.
+    PostUpdate,
+    /// After the frame was presented.
+    PostRender,
+}
+
+impl FrameStage {
+    /// Every stage, in frame order.
+    pub const ALL: [FrameStage; 4] = [FrameStage::PreUpdate, FrameStage::Update, FrameStage::PostUpdate, FrameStage::PostRender];
+
+    /// Name of the profiler scope and watchdog entry timing the plugins at this stage.
+    pub(crate) fn scope_name(self) -> &'static str {
+        match self {
+            FrameStage::PreUpdate => "plugins (pre-update)",
+            FrameStage::Update => "plugins (update)",
+            FrameStage::PostUpdate => "plugins (post-update)",
+            FrameStage::PostRender => "plugins (post-render)",
+        }
+    }
+}
+
+/// An engine extension, added with
+/// [`Renderer::add_plugin`](crate::engine::renderer::Renderer::add_plugin); see the
+/// [module documentation](self). Every hook does nothing by default.
+pub trait Plugin: Any {
+    /// The plugin's name, unique among the renderer's plugins. Defaults to the type name.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Names of the plugins this one needs, which must be added before it.
+    fn dependencies(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Runs once before the first frame the plugin takes part in: when
+    /// [`run`](crate::engine::renderer::Renderer::run) starts, or at the start of the next
+    /// frame for plugins added while running.
+    fn startup(&mut self, _renderer: &mut Renderer) {}
+
+    /// Runs at every `stage` of every frame after startup.
+    fn update(&mut self, _renderer: &mut Renderer, _stage: FrameStage, _clock: &Clock) {}
+
+    /// Receives each window and input event after startup.
+    fn event(&mut self, _renderer: &mut Renderer, _event: &EngineEvent) {}
+
+    /// Runs when the plugin is removed or the window closes, if it was started. Plugins shut
+    /// down in reverse order.
+    fn shutdown(&mut self, _renderer: &mut Renderer) {}
+}
+
+/// A registered plugin.
+struct PluginEntry {
+    name: String,
+    /// Taken out while one of its hooks runs.
+    plugin: Option<Box<dyn Plugin>>,
+    started: bool,
+}
+
+/// The renderer's plugins, in the order they were added.
+#[derive(Default)]
+pub(crate) struct PluginRegistry {
+    entries: Vec<PluginEntry>,
+}
+
+impl PluginRegistry {
+    /// Adds `plugin` unless one of the same name is registered. Warns about dependencies
+    /// that weren't added before it.
+    pub(crate) fn add(&mut self, plugin: Box<dyn Plugin>) -> bool {
+        let name = plugin.name().to_string();
+        if self.contains(&name) {
+            return false;
+        }
+        for dependency in plugin.dependencies() {
+            if !self.contains(dependency) {
+                eprintln!("Warning: Plugin '{name}' depends on '{dependency}', which was not added before it");
+            }
+        }
+        self.entries.push(PluginEntry { name, plugin: Some(plugin), started: false });
+        true
+    }
+
+    /// Unregisters the plugin `name`, returning it, unless it is running, and whether it was
+    /// started. `None` if there was no such plugin.
+    pub(crate) fn remove(&mut self, name: &str) -> Option<(Option<Box<dyn Plugin>>, bool)> {
+        let index = self.entries.iter().position(|entry| entry.name == name)?;
+        let entry = self.entries.remove(index);
+        Some((entry.plugin, entry.started))
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.name == name)
+    }
+
+    /// Names of the plugins, in order, optionally only the started or not yet started ones.
+    pub(crate) fn names(&self, started: Option<bool>) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|entry| started.is_none_or(|started| entry.started == started))
+            .map(|entry| entry.name.clone())
+            .collect()
+    }
+
+    /// Takes the plugin `name` out to run one of its hooks, marking it started.
+    pub(crate) fn take(&mut self, name: &str) -> Option<Box<dyn Plugin>> {
+        let entry = self.entries.iter_mut().find(|entry| entry.name == name)?;
+        entry.started = true;
+        entry.plugin.take()
+    }
+
+    /// Puts back a plugin taken with [`take`](Self::take). Returns it if it was removed
+    /// meanwhile.
+    pub(crate) fn restore(&mut self, name: &str, plugin: Box<dyn Plugin>) -> Option<Box<dyn Plugin>> {
+        match self.entries.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                entry.plugin = Some(plugin);
+                None
+            }
+            None => Some(plugin),
+        }
+    }
+
+    /// The first plugin of type `T`, unless it is running.
+    pub(crate) fn get<T: Plugin>(&self) -> Option<&T> {
+        self.entries.iter().find_map(|entry| {
+            let plugin: &dyn Any = entry.plugin.as_deref()?;
+            plugin.downcast_ref()
+        })
+    }
+
+    /// The first plugin of type `T`, mutably, unless it is running.
+    pub(crate) fn get_mut<T: Plugin>(&mut self) -> Option<&mut T> {
+        self.entries.iter_mut().find_map(|entry| {
+            let plugin: &mut dyn Any = entry.plugin.as_deref_mut()?;
+            plugin.downcast_mut()
+        })
+    }
+}
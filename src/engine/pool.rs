@@ -0,0 +1,157 @@
+//! Recycling of frequently spawned nodes.
+//!
+//! Creating an [`Object3D`] for every bullet, debris chunk or pickup allocates a node and,
+//! on its first draw, uploads its geometry to the GPU. A [`NodePool`] keeps released nodes,
+//! along with their uploaded meshes, and hands them out again on the next spawn.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::object3d::Object3D;
+
+/// Builds a new pooled node when no released one is available.
+pub type NodeFactory = Box<dyn FnMut() -> Rc<RefCell<Object3D>>>;
+
+/// Resets a node as it is returned to the pool.
+pub type NodeReset = Box<dyn FnMut(&mut Object3D)>;
+
+/// A pool of interchangeable nodes created by a factory.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::{object3d::Object3D, pool::NodePool};
+/// let scene_root = Object3D::new();
+/// let mut bullets = NodePool::new(Some(64), || {
+///     let bullet = Object3D::new();
+///     // set geometry and material here
+///     bullet
+/// });
+///
+/// let bullet = bullets.spawn(&scene_root).expect("pool exhausted");
+/// bullet.borrow_mut().set_position([0.0, 1.0, 0.0]);
+/// assert_eq!(scene_root.borrow().children().len(), 1);
+///
+/// bullets.release(&bullet);
+/// assert_eq!(scene_root.borrow().children().len(), 0);
+/// assert_eq!((bullets.active_count(), bullets.free_count()), (0, 1));
+///
+/// // Nodes the pool didn't hand out are left alone
+/// bullets.release(&Object3D::new());
+/// assert_eq!(bullets.free_count(), 1);
+/// ```
+pub struct NodePool {
+    factory: NodeFactory,
+    reset: Option<NodeReset>,
+
+    /// Released nodes ready for reuse.
+    free: Vec<Rc<RefCell<Object3D>>>,
+
+    /// Nodes currently spawned, so `release` only takes back nodes the pool handed out.
+    active: Vec<Rc<RefCell<Object3D>>>,
+
+    /// Upper bound on nodes owned by the pool (active + free), if any.
+    max_size: Option<usize>,
+}
+
+impl std::fmt::Debug for NodePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodePool")
+            .field("free", &self.free.len())
+            .field("active", &self.active.len())
+            .field("max_size", &self.max_size)
+            .finish()
+    }
+}
+
+impl NodePool {
+    /// Creates an empty pool. `max_size` caps how many nodes may exist at once (spawned and
+    /// waiting for reuse together); `None` lets the pool grow without limit.
+    pub fn new<F>(max_size: Option<usize>, factory: F) -> Self
+    where
+        F: FnMut() -> Rc<RefCell<Object3D>> + 'static,
+    {
+        Self { factory: Box::new(factory), reset: None, free: Vec::new(), active: Vec::new(), max_size }
+    }
+
+    /// Registers a function run on every node as it is released, e.g. to clear its update
+    /// callback or hide effects. Released nodes are always detached from their parent.
+    pub fn set_on_release<F>(&mut self, reset: F)
+    where
+        F: FnMut(&mut Object3D) + 'static,
+    {
+        self.reset = Some(Box::new(reset));
+    }
+
+    /// Changes the size limit. Nodes beyond a lowered limit are dropped as they are released.
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
+        self.trim();
+    }
+
+    /// Creates nodes up front until `count` are waiting for reuse (bounded by the size limit),
+    /// uploading their geometry so the first spawns don't hitch. Requires a current GL context
+    /// if the factory's nodes have geometry.
+    pub fn warm_up(&mut self, count: usize) {
+        while self.free.len() < count && self.has_capacity() {
+            let node = (self.factory)();
+            node.borrow().upload_geometry();
+            self.free.push(node);
+        }
+    }
+
+    /// Takes a node from the pool (or creates one) and adds it under `parent`.
+    ///
+    /// Returns `None` if the pool is at its size limit with every node in use. The node keeps
+    /// whatever transform it had when released; set it after spawning.
+    pub fn spawn(&mut self, parent: &Rc<RefCell<Object3D>>) -> Option<Rc<RefCell<Object3D>>> {
+        let node = match self.free.pop() {
+            Some(node) => node,
+            None if self.has_capacity() => (self.factory)(),
+            None => return None,
+        };
+        self.active.push(node.clone());
+        Object3D::add_child(parent, node.clone());
+        Some(node)
+    }
+
+    /// Returns a spawned node to the pool, detaching it from the scene.
+    ///
+    /// Releasing a node the pool didn't spawn, or one already released, does nothing.
+    pub fn release(&mut self, node: &Rc<RefCell<Object3D>>) {
+        let Some(index) = self.active.iter().position(|active| Rc::ptr_eq(active, node)) else {
+            return;
+        };
+        let node = self.active.swap_remove(index);
+        Object3D::detach(&node);
+        if let Some(reset) = &mut self.reset {
+            reset(&mut node.borrow_mut());
+        }
+        self.free.push(node);
+        self.trim();
+    }
+
+    /// Number of spawned nodes not yet released.
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Number of released nodes waiting for reuse.
+    pub fn free_count(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Drops every node waiting for reuse, freeing their GPU resources.
+    pub fn clear_free(&mut self) {
+        self.free.clear();
+    }
+
+    fn has_capacity(&self) -> bool {
+        self.max_size.is_none_or(|max| self.active.len() + self.free.len() < max)
+    }
+
+    fn trim(&mut self) {
+        if let Some(max) = self.max_size {
+            let keep = max.saturating_sub(self.active.len());
+            self.free.truncate(keep);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Post-processing: full-screen effects applied to the rendered frame.
+//!
+//! A [`PostChain`] is a list of [`PostEffect`]s, each a fragment shader run over the whole
+//! screen. While the chain has enabled effects (and the quality settings allow
+//! [post effects](crate::engine::quality::QualitySettings::post_effects)), the renderer draws
+//! the scene into an offscreen target instead of the window and feeds it to the first
+//! effect; each effect reads the previous one's output, and the last writes to the window.
+//! Overlays (the pass overlay, box selection) are drawn afterwards, unprocessed.
+//!
+//! Effects sample the input image as `u_scene_color` and the scene's depth as
+//! `u_scene_depth`, at `v_uv`; `u_texel_size` is the size of one pixel in UV units. Colors
+//! are linear in the [sRGB pipeline](crate::engine::color_space), where effects output
+//! linear colors too, and sRGB-encoded, as the scene shaders write them, in the legacy one. While the renderer has
+//! [motion vectors](crate::engine::motion) enabled, they are bound as `u_motion_vectors`. Effects may also use their material's
+//! own textures and uniforms, except `u_color`, which materials reserve.
+//!
+//! MSAA and post-processing work together: with
+//! [`msaa_samples`](crate::engine::quality::QualitySettings::msaa_samples) above 1, the scene
+//! is drawn into a [`MultisampleTarget`] and resolved before the first effect, color
+//! averaged and depth taken from a single sample per pixel (see
+//! [`MultisampleTarget::resolve_into`]). Without effects, MSAA still renders offscreen and
+//! the resolved image is copied to the window.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{post::PostEffect, renderer::Renderer, shader::UniformValue};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! let mut vignette = PostEffect::new("vignette", r#"
+//!     #version 330 core
+//!     in vec2 v_uv;
+//!     uniform sampler2D u_scene_color;
+//!     uniform float u_strength;
+//!     out vec4 frag_color;
+//!     void main() {
+//!         vec4 color = texture(u_scene_color, v_uv);
+//!         float falloff = 1.0 - u_strength * dot(v_uv - 0.5, v_uv - 0.5);
+//!         frag_color = vec4(color.rgb * falloff, color.a);
+//!     }
+//! "#);
+//! vignette.material_mut().set_uniform("u_strength", UniformValue::Float(1.5));
+//! renderer.post_chain_mut().push(vignette);
+//! ```
+
+use std::rc::Rc;
+use gl::types::{GLint, GLuint};
+use crate::engine::background::empty_vao;
+use crate::engine::material::{CullMode, Material};
+use crate::engine::render_target::{MultisampleTarget, RenderTarget};
+use crate::engine::shader::GLShaderProgram;
+use crate::engine::stats::record_draw_call;
+use crate::engine::texture::Texture;
+
+/// A full-screen effect: a fragment shader over the previous image of the chain.
+#[derive(Clone, Debug)]
+pub struct PostEffect {
+    name: String,
+    material: Material,
+
+    /// Whether the effect runs; disabled effects are skipped.
+    pub enabled: bool,
+}
+
+impl PostEffect {
+    /// Compiles `fragment_source` with the built-in full-screen vertex shader, which
+    /// outputs `v_uv`. Requires a current GL context.
+    ///
+    /// # Panics
+    /// Panics with the driver's info log if the shader fails to compile or link.
+    pub fn new(name: &str, fragment_source: &str) -> Self {
+        let shader = Rc::new(GLShaderProgram::from_sources(include_str!("shaders/post.vert"), fragment_source));
+        Self::with_shader(name, shader)
+    }
+
+    /// An effect with an already compiled program, whose vertex stage must cover the screen
+    /// when drawn as three vertices without buffers.
+    pub fn with_shader(name: &str, shader: Rc<GLShaderProgram>) -> Self {
+        let mut material = Material::new(shader);
+        material.cull = CullMode::None;
+        material.depth_test = false;
+        material.depth_write = false;
+        Self { name: name.to_string(), material, enabled: true }
+    }
+
+    /// The effect's name, shown in the frame graph as `post <name>`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The material drawing the effect, holding its shader, uniforms and textures.
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    /// Mutable access to the material, e.g. to change uniforms.
+    pub fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    /// Draws the effect into the bound framebuffer, reading `input` and `motion`.
+    fn draw(&self, input: &RenderTarget, motion: Option<&Rc<Texture>>) {
+        self.material.bind();
+        let shader = &self.material.shader;
+        let unit = self.material.textures.len() as u32;
+        input.color_texture().bind(unit);
+        shader.set_sampler("u_scene_color", unit);
+        input.depth_texture().bind(unit + 1);
+        shader.set_sampler("u_scene_depth", unit + 1);
+        if let Some(motion) = motion {
+            motion.bind(unit + 2);
+            shader.set_sampler("u_motion_vectors", unit + 2);
+        }
+        let [width, height] = input.size();
+        shader.set_uniform_vec2("u_texel_size", [1.0 / width as f32, 1.0 / height as f32]);
+        unsafe {
+            gl::BindVertexArray(empty_vao());
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            gl::BindVertexArray(0);
+        }
+        record_draw_call(gl::TRIANGLES, 3);
+    }
+}
+
+/// Effects applied in order to the rendered frame.
+#[derive(Clone, Debug, Default)]
+pub struct PostChain {
+    effects: Vec<PostEffect>,
+}
+
+impl PostChain {
+    /// An empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `effect`, replacing an effect of the same name in place.
+    pub fn push(&mut self, effect: PostEffect) {
+        match self.effects.iter_mut().find(|existing| existing.name == effect.name) {
+            Some(existing) => *existing = effect,
+            None => self.effects.push(effect),
+        }
+    }
+
+    /// Removes and returns the effect called `name`.
+    pub fn remove(&mut self, name: &str) -> Option<PostEffect> {
+        let index = self.effects.iter().position(|effect| effect.name == name)?;
+        Some(self.effects.remove(index))
+    }
+
+    /// The effect called `name`.
+    pub fn get(&self, name: &str) -> Option<&PostEffect> {
+        self.effects.iter().find(|effect| effect.name == name)
+    }
+
+    /// Mutable access to the effect called `name`.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    /// Every effect, in the order they run.
+    pub fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    /// Whether any effect is enabled.
+    pub fn is_active(&self) -> bool {
+        self.effects.iter().any(|effect| effect.enabled)
+    }
+}
+
+/// The offscreen targets the renderer draws the scene into when MSAA or post effects are
+/// on, recreated when the window size or sample count changes.
+#[derive(Debug)]
+pub(crate) struct SceneTargets {
+    /// Where the scene is drawn when multisampling.
+    multisampled: Option<MultisampleTarget>,
+
+    /// The single-sampled scene, drawn into directly or resolved into: the chain's input.
+    resolved: RenderTarget,
+
+    /// Intermediate images between effects, created when first needed.
+    scratch: Vec<RenderTarget>,
+
+    /// Requested sample count, which the multisampled target may have clamped.
+    samples: u32,
+}
+
+impl SceneTargets {
+    /// Targets of `size` with `samples` samples per pixel (1 for none).
+    pub(crate) fn new(size: [u32; 2], samples: u32) -> Self {
+        let multisampled = (samples > 1).then(|| MultisampleTarget::new(size[0], size[1], samples, "scene msaa"));
+        Self { multisampled, resolved: RenderTarget::new(size[0], size[1], "scene"), scratch: Vec::new(), samples }
+    }
+
+    /// Whether the targets were created for `size` and `samples`.
+    pub(crate) fn matches(&self, size: [u32; 2], samples: u32) -> bool {
+        self.resolved.size() == size && self.samples == samples
+    }
+
+    /// The framebuffer to draw the scene into.
+    pub(crate) fn scene_fbo(&self) -> GLuint {
+        self.multisampled.as_ref().map_or(self.resolved.fbo(), MultisampleTarget::fbo)
+    }
+
+    /// The framebuffer holding the single-sampled scene, once resolved.
+    pub(crate) fn resolved_fbo(&self) -> GLuint {
+        self.resolved.fbo()
+    }
+
+    /// Whether the scene is drawn multisampled and needs [`resolve`](Self::resolve).
+    pub(crate) fn is_multisampled(&self) -> bool {
+        self.multisampled.is_some()
+    }
+
+    /// Resolves the multisampled scene into the chain's input.
+    pub(crate) fn resolve(&self) {
+        if let Some(multisampled) = &self.multisampled {
+            multisampled.resolve_into(&self.resolved);
+        }
+    }
+
+    /// Runs the enabled effects of `chain`, the last one drawing into the default
+    /// framebuffer, with the frame's `motion` vectors if any. `pass` wraps each effect, for
+    /// frame graph timing. Copies the scene unchanged if no effect is enabled.
+    pub(crate) fn apply(&mut self, chain: &PostChain, motion: Option<&Rc<Texture>>, mut pass: impl FnMut(&str, &dyn Fn())) {
+        let effects: Vec<&PostEffect> = chain.effects.iter().filter(|effect| effect.enabled).collect();
+        let size = self.resolved.size();
+        let needed = effects.len().saturating_sub(1).min(2);
+        while self.scratch.len() < needed {
+            self.scratch.push(RenderTarget::new(size[0], size[1], "post scratch"));
+        }
+
+        if effects.is_empty() {
+            pass("post copy", &|| self.copy_to_window());
+            return;
+        }
+        for (i, effect) in effects.iter().enumerate() {
+            let input = if i == 0 { &self.resolved } else { &self.scratch[(i - 1) % 2] };
+            let output = if i + 1 == effects.len() { 0 } else { self.scratch[i % 2].fbo() };
+            pass(&format!("post {}", effect.name), &|| {
+                unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, output) };
+                effect.draw(input, motion);
+            });
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            // Effects turn depth writes off; the next frame's clear needs them on
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+        }
+    }
+
+    /// Copies the resolved scene's color to the default framebuffer.
+    fn copy_to_window(&self) {
+        let [width, height] = self.resolved.size().map(|v| v as GLint);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.resolved.fbo());
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+            gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, gl::COLOR_BUFFER_BIT, gl::NEAREST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Frame profiling: scoped CPU timers, GPU pass timings and per-frame counters.
+//!
+//! The [`FrameWatchdog`](crate::engine::watchdog::FrameWatchdog) only speaks up when a frame
+//! is slow; the [`Profiler`] keeps the breakdown of every recent frame, to watch while tuning.
+//! Each [`FrameProfile`] holds:
+//!
+//! - CPU time of named [scopes](scope), nested as they were opened. The renderer times its
+//!   own systems (update callback, physics, scene update, ...) as top-level scopes; games
+//!   add their own with [`scope`] or [`profile_scope!`](crate::profile_scope).
+//! - CPU and GPU time of each render pass, from the
+//!   [`FrameGraph`](crate::engine::frame_graph::FrameGraph)'s `GL_TIME_ELAPSED` queries. GPU
+//!   results arrive a few frames late, so these belong to the latest frame they resolved for.
+//! - The frame's [`FrameCounters`]: draw calls, triangles, program, material and state
+//!   changes, and uploads.
+//!
+//! The renderer owns a profiler (see
+//! [`Renderer::profiler`](crate::engine::renderer::Renderer::profiler)) and can draw it as an
+//! overlay with [`Renderer::set_profiler_overlay`](crate::engine::renderer::Renderer::set_profiler_overlay).
+//! Scopes are recorded per thread; the renderer's profiler collects those of the thread
+//! running the frame.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::profiler::{self, Profiler};
+//! let mut profiler = Profiler::new();
+//! profiler.begin_frame();
+//! {
+//!     let _ai = profiler::scope("ai");
+//!     for _ in 0..3 {
+//!         rustge::profile_scope!("pathfinding");
+//!     }
+//! }
+//! let profile = profiler.end_frame(1, &[]);
+//!
+//! let pathfinding = profile.scope("pathfinding").unwrap();
+//! assert_eq!((pathfinding.depth, pathfinding.calls), (1, 3));
+//! ```
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use crate::engine::frame_graph::PassTiming;
+use crate::engine::stats::{frame_counters, FrameCounters};
+
+/// Frames of history kept by default, two seconds at 60 Hz.
+const DEFAULT_HISTORY: usize = 120;
+
+/// CPU time spent in a named scope during one frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScopeTiming {
+    /// The scope's name.
+    pub name: String,
+
+    /// Number of enclosing scopes, 0 at the top level.
+    pub depth: usize,
+
+    /// Total time of every call this frame, including nested scopes.
+    pub time: Duration,
+
+    /// Number of times the scope was entered this frame.
+    pub calls: usize,
+}
+
+/// Everything measured for one frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameProfile {
+    /// Frame number, as counted by the renderer's clock.
+    pub frame: u64,
+
+    /// Time from the start of the frame until it was presented.
+    pub frame_time: Duration,
+
+    /// Scopes in the order they were first entered, each followed by the scopes nested in it.
+    pub scopes: Vec<ScopeTiming>,
+
+    /// Render passes of the latest frame whose GPU timings had resolved, in execution order.
+    pub passes: Vec<PassTiming>,
+
+    /// Draw calls, triangles, state changes and uploads issued during the frame.
+    pub counters: FrameCounters,
+}
+
+impl FrameProfile {
+    /// The first scope called `name`, at any depth.
+    pub fn scope(&self, name: &str) -> Option<&ScopeTiming> {
+        self.scopes.iter().find(|scope| scope.name == name)
+    }
+
+    /// Total CPU time of the render passes.
+    pub fn pass_cpu_time(&self) -> Duration {
+        self.passes.iter().map(|pass| pass.cpu_time).sum()
+    }
+
+    /// Total GPU time of the render passes, if timer queries are available.
+    pub fn pass_gpu_time(&self) -> Option<Duration> {
+        self.passes.iter().map(|pass| pass.gpu_time).sum()
+    }
+}
+
+/// A scope being recorded.
+#[derive(Debug)]
+struct ScopeEntry {
+    name: String,
+    parent: Option<usize>,
+    time: Duration,
+    calls: usize,
+}
+
+/// Scopes recorded on this thread since the last [`Profiler::begin_frame`].
+#[derive(Debug, Default)]
+struct ScopeRecorder {
+    entries: Vec<ScopeEntry>,
+    /// Entries of the scopes currently open, innermost last.
+    open: Vec<usize>,
+}
+
+impl ScopeRecorder {
+    /// The entry for `name` inside the innermost open scope, created if needed.
+    fn entry(&mut self, name: &str) -> usize {
+        let parent = self.open.last().copied();
+        let existing = self.entries.iter().position(|entry| entry.parent == parent && entry.name == name);
+        existing.unwrap_or_else(|| {
+            self.entries.push(ScopeEntry { name: name.to_string(), parent, time: Duration::ZERO, calls: 0 });
+            self.entries.len() - 1
+        })
+    }
+
+    /// The entries flattened so every scope is followed by the ones nested in it.
+    fn take(&mut self) -> Vec<ScopeTiming> {
+        let entries = std::mem::take(&mut self.entries);
+        self.open.clear();
+        let mut timings = Vec::with_capacity(entries.len());
+        let mut stack: Vec<(Option<usize>, usize)> = vec![(None, 0)];
+        while let Some((parent, depth)) = stack.pop() {
+            let children = entries.iter().enumerate().filter(|(_, entry)| entry.parent == parent);
+            // Pushed in reverse so the first entered is visited first
+            for (index, _) in children.collect::<Vec<_>>().into_iter().rev() {
+                stack.push((Some(index), depth + 1));
+            }
+            if let Some(index) = parent {
+                let entry = &entries[index];
+                timings.push(ScopeTiming { name: entry.name.clone(), depth: depth - 1, time: entry.time, calls: entry.calls });
+            }
+        }
+        timings
+    }
+}
+
+thread_local! {
+    static SCOPES: RefCell<ScopeRecorder> = RefCell::new(ScopeRecorder::default());
+}
+
+/// Times a scope until dropped; see [`scope`].
+#[must_use = "the scope ends when this is dropped"]
+#[derive(Debug)]
+pub struct ProfileScope {
+    index: usize,
+    start: Instant,
+    /// Scopes belong to the thread that opened them.
+    _thread: PhantomData<*const ()>,
+}
+
+impl ProfileScope {
+    /// Ends the scope now, returning how long it was open.
+    pub fn finish(self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let _ = SCOPES.try_with(|scopes| {
+            let mut scopes = scopes.borrow_mut();
+            // A profiler that began a new frame meanwhile dropped the entry
+            if let Some(entry) = scopes.entries.get_mut(self.index) {
+                entry.time += elapsed;
+                entry.calls += 1;
+            }
+            if let Some(position) = scopes.open.iter().rposition(|&open| open == self.index) {
+                scopes.open.truncate(position);
+            }
+        });
+    }
+}
+
+/// Starts timing the scope `name` on this thread, nested in the scopes currently open, until
+/// the returned guard is dropped. Entering the same scope again in the same frame adds to
+/// its time and call count.
+pub fn scope(name: &str) -> ProfileScope {
+    let index = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let index = scopes.entry(name);
+        scopes.open.push(index);
+        index
+    });
+    ProfileScope { index, start: Instant::now(), _thread: PhantomData }
+}
+
+/// Records `time` spent in the scope `name`, measured by other means, nested in the scopes
+/// currently open.
+pub fn record(name: &str, time: Duration) {
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let index = scopes.entry(name);
+        let entry = &mut scopes.entries[index];
+        entry.time += time;
+        entry.calls += 1;
+    });
+}
+
+/// Times the rest of the enclosing block as the [profiler scope](crate::engine::profiler::scope)
+/// `name`.
+///
+/// # Example
+/// ```
+/// fn update_ai() {
+///     rustge::profile_scope!("ai");
+///     // ...
+/// }
+/// ```
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::engine::profiler::scope($name);
+    };
+}
+
+/// Collects a [`FrameProfile`] per frame and keeps the most recent ones.
+#[derive(Debug)]
+pub struct Profiler {
+    /// Recent frames, oldest first.
+    history: VecDeque<FrameProfile>,
+
+    /// Number of frames kept.
+    history_length: usize,
+
+    /// Start of the frame being profiled.
+    frame_start: Option<Instant>,
+}
+
+impl Profiler {
+    /// A profiler keeping the last 120 frames.
+    pub fn new() -> Self {
+        Self { history: VecDeque::new(), history_length: DEFAULT_HISTORY, frame_start: None }
+    }
+
+    /// Sets how many frames are kept (at least one).
+    pub fn set_history_length(&mut self, frames: usize) {
+        self.history_length = frames.max(1);
+        while self.history.len() > self.history_length {
+            self.history.pop_front();
+        }
+    }
+
+    /// How many frames are kept.
+    pub fn history_length(&self) -> usize {
+        self.history_length
+    }
+
+    /// Starts profiling a frame, discarding scopes left over on this thread. Frame counters
+    /// are reset separately, by [`reset_frame_counters`](crate::engine::stats::reset_frame_counters).
+    pub fn begin_frame(&mut self) {
+        SCOPES.with(|scopes| *scopes.borrow_mut() = ScopeRecorder::default());
+        self.frame_start = Some(Instant::now());
+    }
+
+    /// Finishes the frame started by [`begin_frame`](Self::begin_frame), collecting the
+    /// scopes recorded on this thread and the current frame counters. `passes` are usually
+    /// [`FrameGraph::passes`](crate::engine::frame_graph::FrameGraph::passes).
+    pub fn end_frame(&mut self, frame: u64, passes: &[PassTiming]) -> &FrameProfile {
+        let frame_time = self.frame_start.take().map_or(Duration::ZERO, |start| start.elapsed());
+        let profile = FrameProfile {
+            frame,
+            frame_time,
+            scopes: SCOPES.with(|scopes| scopes.borrow_mut().take()),
+            passes: passes.to_vec(),
+            counters: frame_counters(),
+        };
+        if self.history.len() >= self.history_length {
+            self.history.pop_front();
+        }
+        self.history.push_back(profile);
+        self.history.back().expect("profile just pushed")
+    }
+
+    /// The most recent frame, if one was profiled.
+    pub fn latest(&self) -> Option<&FrameProfile> {
+        self.history.back()
+    }
+
+    /// Recent frames, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &FrameProfile> {
+        self.history.iter()
+    }
+
+    /// Mean frame time over the kept frames.
+    pub fn average_frame_time(&self) -> Duration {
+        let total: Duration = self.history.iter().map(|profile| profile.frame_time).sum();
+        total / self.history.len().max(1) as u32
+    }
+
+    /// Longest frame time among the kept frames.
+    pub fn max_frame_time(&self) -> Duration {
+        self.history.iter().map(|profile| profile.frame_time).max().unwrap_or_default()
+    }
+
+    /// Frames per second from the mean frame time, 0 before any frame was profiled.
+    pub fn frames_per_second(&self) -> f64 {
+        let average = self.average_frame_time().as_secs_f64();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+
+    /// Mean time of scope `name` per frame over the kept frames, counting frames without it
+    /// as zero.
+    pub fn average_scope_time(&self, name: &str) -> Duration {
+        let total: Duration = self.history.iter().filter_map(|profile| profile.scope(name)).map(|scope| scope.time).sum();
+        total / self.history.len().max(1) as u32
+    }
+
+    /// Drops the kept frames.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
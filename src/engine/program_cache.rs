@@ -0,0 +1,257 @@
+//! On-disk cache of linked shader programs.
+//!
+//! Compiling and linking GLSL is slow, and a game with many materials and shader variants
+//! pays for it again on every start. With the cache [enabled](enable) (or
+//! [`RendererBuilder::program_cache`](crate::engine::renderer::RendererBuilder::program_cache)),
+//! every program the engine links is saved as the driver's binary (`glGetProgramBinary`),
+//! and later runs load that instead of compiling the sources again.
+//!
+//! Entries are keyed by a hash of the shader sources and of the GL vendor, renderer and
+//! version, so editing a shader or updating the driver simply misses the cache. A driver can
+//! still reject a binary it wrote itself; the program is then compiled from source and the
+//! entry replaced. Drivers without program binary support (before OpenGL 4.1 and
+//! `ARB_get_program_binary`) always compile from source.
+//!
+//! The cache never grows stale entries on its own, but it keeps every variant ever linked;
+//! [`clear`] empties it, e.g. from a settings menu.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::program_cache;
+//! # use rustge::engine::renderer::Renderer;
+//! let renderer = Renderer::builder().program_cache("cache/programs").build();
+//!
+//! // On the second run, the programs come from the cache
+//! let statistics = program_cache::statistics();
+//! println!("{} programs loaded from the cache, {} compiled", statistics.hits, statistics.misses);
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+
+/// Marks cache files, followed by the binary format and the binary itself.
+const MAGIC: &[u8; 8] = b"RGEPRG01";
+
+/// How the cache has done since it was enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramCacheStatistics {
+    /// Programs loaded from the cache.
+    pub hits: usize,
+
+    /// Programs not in the cache, or rejected by the driver, and compiled from source.
+    pub misses: usize,
+
+    /// Programs written to the cache.
+    pub stored: usize,
+}
+
+thread_local! {
+    /// Where programs are cached, if anywhere. Programs belong to the GL context's thread.
+    static DIRECTORY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+    static STATISTICS: Cell<ProgramCacheStatistics> = Cell::new(ProgramCacheStatistics::default());
+    /// Hash of the driver's vendor, renderer and version, computed on first use.
+    static DRIVER: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Caches programs in `directory`, creating it if needed. Programs linked from then on are
+/// loaded from and saved to it.
+pub fn enable(directory: impl AsRef<Path>) -> io::Result<()> {
+    let directory = directory.as_ref();
+    fs::create_dir_all(directory)?;
+    DIRECTORY.with(|current| *current.borrow_mut() = Some(directory.to_path_buf()));
+    STATISTICS.with(|statistics| statistics.set(ProgramCacheStatistics::default()));
+    Ok(())
+}
+
+/// Stops caching programs; the files stay.
+pub fn disable() {
+    DIRECTORY.with(|current| *current.borrow_mut() = None);
+}
+
+/// The directory programs are cached in, if enabled.
+pub fn directory() -> Option<PathBuf> {
+    DIRECTORY.with(|current| current.borrow().clone())
+}
+
+/// How the cache has done since it was enabled.
+pub fn statistics() -> ProgramCacheStatistics {
+    STATISTICS.with(Cell::get)
+}
+
+/// Deletes every cached program.
+pub fn clear() -> io::Result<()> {
+    let Some(directory) = directory() else {
+        return Ok(());
+    };
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "bin") {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether linked programs should be kept retrievable, for [`store`].
+pub(crate) fn is_enabled() -> bool {
+    DIRECTORY.with(|current| current.borrow().is_some()) && supports_binaries()
+}
+
+/// The cached program for shader `stages` (kind and source), if there is one the driver
+/// accepts. Requires a current GL context.
+pub(crate) fn load(stages: &[(GLenum, &str)]) -> Option<GLuint> {
+    if !is_enabled() {
+        return None;
+    }
+    let path = entry_path(stages)?;
+    let program = fs::read(&path).ok().and_then(|bytes| load_binary(&bytes));
+    if program.is_none() && path.exists() {
+        // Written by a driver that no longer accepts it; compiling replaces it
+        let _ = fs::remove_file(&path);
+    }
+    update_statistics(|statistics| match program {
+        Some(_) => statistics.hits += 1,
+        None => statistics.misses += 1,
+    });
+    program
+}
+
+/// Saves linked `program`, made from shader `stages`, to the cache if it is enabled.
+/// Failures are logged; the program works regardless.
+pub(crate) fn store(stages: &[(GLenum, &str)], program: GLuint) {
+    if !is_enabled() {
+        return;
+    }
+    let Some(path) = entry_path(stages) else {
+        return;
+    };
+    let Some((format, binary)) = program_binary(program) else {
+        return;
+    };
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend(format.to_le_bytes());
+    bytes.extend(binary);
+    // Write then rename, so another instance of the game never reads half a file
+    let temporary = path.with_extension(format!("tmp{}", std::process::id()));
+    match fs::write(&temporary, bytes).and_then(|()| fs::rename(&temporary, &path)) {
+        Ok(()) => update_statistics(|statistics| statistics.stored += 1),
+        Err(error) => {
+            let _ = fs::remove_file(&temporary);
+            eprintln!("Warning: Could not cache shader program in {}: {error}", path.display());
+        }
+    }
+}
+
+fn update_statistics(update: impl FnOnce(&mut ProgramCacheStatistics)) {
+    STATISTICS.with(|statistics| {
+        let mut current = statistics.get();
+        update(&mut current);
+        statistics.set(current);
+    });
+}
+
+/// Whether the driver can hand out and take back program binaries in some format.
+fn supports_binaries() -> bool {
+    if !gl::GetProgramBinary::is_loaded() || !gl::ProgramBinary::is_loaded() {
+        return false;
+    }
+    let mut formats = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+    }
+    formats > 0
+}
+
+/// The cache file for shader `stages` on the current driver.
+fn entry_path(stages: &[(GLenum, &str)]) -> Option<PathBuf> {
+    let directory = directory()?;
+    let mut hash = Fnv1a::new();
+    hash.write(&driver_hash().to_le_bytes());
+    for (kind, source) in stages {
+        hash.write(&kind.to_le_bytes());
+        hash.write(&(source.len() as u64).to_le_bytes());
+        hash.write(source.as_bytes());
+    }
+    Some(directory.join(format!("{:016x}.bin", hash.finish())))
+}
+
+/// Hash of the strings identifying the driver, which binaries are only valid for.
+fn driver_hash() -> u64 {
+    DRIVER.with(|driver| {
+        *driver.get().get_or_insert_with(|| {
+            let mut hash = Fnv1a::new();
+            for name in [gl::VENDOR, gl::RENDERER, gl::VERSION] {
+                hash.write(gl_string(name).as_bytes());
+                hash.write(&[0]);
+            }
+            hash.finish()
+        })
+    })
+}
+
+fn gl_string(name: GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string.cast()).to_string_lossy().into_owned()
+    }
+}
+
+/// The binary format and binary of linked `program`, if the driver provides one.
+fn program_binary(program: GLuint) -> Option<(GLenum, Vec<u8>)> {
+    unsafe {
+        let mut length = 0;
+        gl::GetProgramiv(program, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        if length <= 0 {
+            return None;
+        }
+        let mut binary = vec![0u8; length as usize];
+        let (mut written, mut format) = (0, 0);
+        gl::GetProgramBinary(program, length, &mut written, &mut format, binary.as_mut_ptr().cast());
+        binary.truncate(written.max(0) as usize);
+        (!binary.is_empty()).then_some((format, binary))
+    }
+}
+
+/// A program from the contents of a cache file, if the driver accepts the binary.
+fn load_binary(bytes: &[u8]) -> Option<GLuint> {
+    let rest = bytes.strip_prefix(MAGIC)?;
+    let (format, binary) = rest.split_first_chunk::<4>()?;
+    unsafe {
+        let program = gl::CreateProgram();
+        gl::ProgramBinary(program, GLenum::from_le_bytes(*format), binary.as_ptr().cast(), binary.len() as GLsizei);
+        let mut status: GLint = 0;
+        gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+        if status == 0 {
+            gl::DeleteProgram(program);
+            return None;
+        }
+        Some(program)
+    }
+}
+
+/// 64-bit FNV-1a, whose output, unlike the standard library's hashers, is stable across
+/// builds, as cache file names must be.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
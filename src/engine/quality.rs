@@ -0,0 +1,156 @@
+//! Render quality presets.
+//!
+//! Settings that trade image quality for frame time (shadow resolution, anti-aliasing,
+//! texture sharpness, post effects, the GPU memory warning threshold) are bundled in
+//! [`QualitySettings`], so a graphics menu can offer a handful of [`QualityPreset`]s and still
+//! let players tweak single values.
+//! [`Renderer::set_quality`](crate::engine::renderer::Renderer::set_quality) switches them
+//! at runtime: settings backed by GPU resources take effect by recreating those resources on
+//! the next frame, the rest immediately.
+//!
+//! Settings round-trip through JSON, for keeping the player's choice in a config file.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::quality::{QualityPreset, QualitySettings};
+//! let mut settings = QualityPreset::from_name("medium").unwrap().settings();
+//! settings.texture_lod_bias = 0.25;
+//! assert_eq!(settings.preset(), None); // customized
+//!
+//! let saved = settings.to_json().unwrap();
+//! assert_eq!(QualitySettings::from_json(&saved).unwrap(), settings);
+//! ```
+
+use std::io;
+use serde::{Deserialize, Serialize};
+
+/// A named bundle of [`QualitySettings`], from cheapest to most expensive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+}
+
+impl QualityPreset {
+    /// Every preset, from lowest to highest, e.g. for a settings menu.
+    pub const ALL: [QualityPreset; 4] = [QualityPreset::Low, QualityPreset::Medium, QualityPreset::High, QualityPreset::Ultra];
+
+    /// The preset's name in lowercase, as accepted by [`from_name`](Self::from_name).
+    pub fn name(self) -> &'static str {
+        match self {
+            QualityPreset::Low => "low",
+            QualityPreset::Medium => "medium",
+            QualityPreset::High => "high",
+            QualityPreset::Ultra => "ultra",
+        }
+    }
+
+    /// The preset called `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The settings the preset stands for.
+    pub fn settings(self) -> QualitySettings {
+        const MIB: usize = 1024 * 1024;
+        match self {
+            QualityPreset::Low => QualitySettings {
+                shadow_resolution: 512,
+                msaa_samples: 1,
+                taa: false,
+                memory_warning_threshold: Some(512 * MIB),
+                texture_lod_bias: 1.0,
+                post_effects: false,
+            },
+            QualityPreset::Medium => QualitySettings {
+                shadow_resolution: 1024,
+                msaa_samples: 2,
+                taa: false,
+                memory_warning_threshold: Some(1024 * MIB),
+                texture_lod_bias: 0.5,
+                post_effects: true,
+            },
+            QualityPreset::High => QualitySettings {
+                shadow_resolution: 2048,
+                msaa_samples: 4,
+                taa: false,
+                memory_warning_threshold: Some(2048 * MIB),
+                texture_lod_bias: 0.0,
+                post_effects: true,
+            },
+            QualityPreset::Ultra => QualitySettings {
+                shadow_resolution: 4096,
+                msaa_samples: 8,
+                taa: false,
+                memory_warning_threshold: None,
+                texture_lod_bias: 0.0,
+                post_effects: true,
+            },
+        }
+    }
+}
+
+/// Render settings trading quality for speed. Defaults to the [`High`](QualityPreset::High)
+/// preset.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualitySettings {
+    /// Edge length in texels of the largest [shadow atlas](crate::engine::shadow) tile a
+    /// light gets.
+    pub shadow_resolution: u32,
+
+    /// Samples per pixel of the multisampled scene target; 1 disables MSAA.
+    pub msaa_samples: u32,
+
+    /// Whether the camera may be jittered for temporal anti-aliasing. When off, the renderer
+    /// clears the camera's [jitter](crate::engine::camera::Camera::jitter) each frame and
+    /// skips the [TAA sharpening](crate::engine::texture::TextureLod::taa_sharpening) of
+    /// textures. Off in every preset: the engine has no TAA resolve pass, so turn it on only
+    /// when the game resolves the jittered frames itself.
+    pub taa: bool,
+
+    /// GPU memory use in bytes above which a warning is printed (see
+    /// [`set_gpu_memory_budget`](crate::engine::stats::set_gpu_memory_budget)); `None` for
+    /// no warning. Nothing is streamed out or evicted to stay under it.
+    pub memory_warning_threshold: Option<usize>,
+
+    /// Global texture LOD bias in mip levels (see
+    /// [`TextureLod::bias`](crate::engine::texture::TextureLod::bias)); positive values
+    /// sample smaller mip levels.
+    pub texture_lod_bias: f32,
+
+    /// Whether the renderer's [post-processing effects](crate::engine::post) run.
+    pub post_effects: bool,
+}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        QualityPreset::default().settings()
+    }
+}
+
+impl From<QualityPreset> for QualitySettings {
+    fn from(preset: QualityPreset) -> Self {
+        preset.settings()
+    }
+}
+
+impl QualitySettings {
+    /// The preset these settings are exactly, or `None` once customized.
+    pub fn preset(&self) -> Option<QualityPreset> {
+        QualityPreset::ALL.into_iter().find(|preset| preset.settings() == *self)
+    }
+
+    /// Serializes the settings as JSON.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    /// Parses settings from JSON; missing values take the default preset's.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
@@ -0,0 +1,198 @@
+//! Tags, typed components and indexed queries over scene nodes.
+//!
+//! Gameplay code often needs "every enemy" or "everything with health" each frame. Walking
+//! the whole graph for that gets slow in large scenes, so nodes register themselves in
+//! per-tag and per-component-type indices as tags and components are added (see
+//! [`Object3D::add_tag`] and [`Object3D::insert_component`]), and
+//! [`Scene::with_tag`](crate::engine::scene::Scene::with_tag),
+//! [`Scene::query`](crate::engine::scene::Scene::query) and
+//! [`Scene::for_each`](crate::engine::scene::Scene::for_each) only visit the indexed nodes.
+//!
+//! Components are plain Rust values, one per type and node. Queries for several component
+//! types take a tuple and start from the smallest index.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{object3d::Object3D, scene::Scene};
+//! struct Health(f32);
+//! struct Poisoned;
+//!
+//! let scene = Scene::new();
+//! let goblin = Object3D::new();
+//! goblin.borrow_mut().add_tag("enemy");
+//! goblin.borrow_mut().insert_component(Health(10.0));
+//! goblin.borrow_mut().insert_component(Poisoned);
+//! scene.add(goblin.clone());
+//!
+//! // Each frame
+//! for node in scene.query::<(Health, Poisoned)>() {
+//!     if let Some(health) = node.borrow_mut().component_mut::<Health>() {
+//!         health.0 -= 1.0;
+//!     }
+//! }
+//! scene.for_each::<Health>(|node, health| {
+//!     if health.0 <= 0.0 {
+//!         node.remove_tag("enemy");
+//!     }
+//! });
+//! assert_eq!(scene.with_tag("enemy").count(), 1);
+//! assert_eq!(goblin.borrow().component::<Health>().map(|health| health.0), Some(9.0));
+//! ```
+//!
+//! [`Object3D::add_tag`]: crate::engine::object3d::Object3D::add_tag
+//! [`Object3D::insert_component`]: crate::engine::object3d::Object3D::insert_component
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use crate::engine::object3d::Object3D;
+
+/// A set of component types nodes must all have to match a query: tuples of one to four
+/// `'static` types, like `(Health,)` or `(Health, Poisoned)`.
+pub trait ComponentQuery {
+    /// The component types, in order.
+    fn type_ids() -> Vec<TypeId>;
+}
+
+macro_rules! impl_component_query {
+    ($($name:ident),+) => {
+        impl<$($name: 'static),+> ComponentQuery for ($($name,)+) {
+            fn type_ids() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$name>()),+]
+            }
+        }
+    };
+}
+
+impl_component_query!(A);
+impl_component_query!(A, B);
+impl_component_query!(A, B, C);
+impl_component_query!(A, B, C, D);
+
+/// The components of one node, keyed by type.
+#[derive(Default)]
+pub(crate) struct Components(HashMap<TypeId, Box<dyn Any>>);
+
+impl Components {
+    pub(crate) fn get<T: 'static>(&self) -> Option<&T> {
+        self.0.get(&TypeId::of::<T>()).and_then(|component| component.downcast_ref())
+    }
+
+    pub(crate) fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.0.get_mut(&TypeId::of::<T>()).and_then(|component| component.downcast_mut())
+    }
+
+    pub(crate) fn contains(&self, type_id: TypeId) -> bool {
+        self.0.contains_key(&type_id)
+    }
+
+    /// Stores `component`, returning the one of the same type it replaces.
+    pub(crate) fn insert<T: 'static>(&mut self, component: T) -> Option<T> {
+        self.0.insert(TypeId::of::<T>(), Box::new(component)).and_then(|old| old.downcast().ok()).map(|old| *old)
+    }
+
+    pub(crate) fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.0.remove(&TypeId::of::<T>()).and_then(|old| old.downcast().ok()).map(|old| *old)
+    }
+}
+
+impl std::fmt::Debug for Components {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Components({})", self.0.len())
+    }
+}
+
+/// Nodes by tag and by component type. Entries of dropped nodes are pruned when queried.
+#[derive(Default)]
+struct Index {
+    tags: HashMap<String, Vec<Weak<RefCell<Object3D>>>>,
+    components: HashMap<TypeId, Vec<Weak<RefCell<Object3D>>>>,
+}
+
+thread_local! {
+    static INDEX: RefCell<Index> = RefCell::new(Index::default());
+}
+
+pub(crate) fn index_tag(tag: &str, node: &Weak<RefCell<Object3D>>) {
+    INDEX.with(|index| index.borrow_mut().tags.entry(tag.to_string()).or_default().push(node.clone()));
+}
+
+pub(crate) fn unindex_tag(tag: &str, node: &Weak<RefCell<Object3D>>) {
+    INDEX.with(|index| {
+        if let Some(nodes) = index.borrow_mut().tags.get_mut(tag) {
+            nodes.retain(|other| !Weak::ptr_eq(other, node));
+        }
+    });
+}
+
+pub(crate) fn index_component(type_id: TypeId, node: &Weak<RefCell<Object3D>>) {
+    INDEX.with(|index| index.borrow_mut().components.entry(type_id).or_default().push(node.clone()));
+}
+
+pub(crate) fn unindex_component(type_id: TypeId, node: &Weak<RefCell<Object3D>>) {
+    INDEX.with(|index| {
+        if let Some(nodes) = index.borrow_mut().components.get_mut(&type_id) {
+            nodes.retain(|other| !Weak::ptr_eq(other, node));
+        }
+    });
+}
+
+/// Every live node with `tag`, pruning dropped ones.
+pub(crate) fn tagged(tag: &str) -> Vec<Rc<RefCell<Object3D>>> {
+    INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        let Some(nodes) = index.tags.get_mut(tag) else {
+            return Vec::new();
+        };
+        nodes.retain(|node| node.strong_count() > 0);
+        nodes.iter().filter_map(Weak::upgrade).collect()
+    })
+}
+
+/// Every live node with all of `type_ids`, starting from the smallest index.
+pub(crate) fn with_components(type_ids: &[TypeId]) -> Vec<Rc<RefCell<Object3D>>> {
+    let candidates = INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        for type_id in type_ids {
+            if let Some(nodes) = index.components.get_mut(type_id) {
+                nodes.retain(|node| node.strong_count() > 0);
+            }
+        }
+        let smallest = type_ids
+            .iter()
+            .map(|type_id| index.components.get(type_id).map_or(0, Vec::len))
+            .enumerate()
+            .min_by_key(|&(_, len)| len);
+        match smallest {
+            Some((i, len)) if len > 0 => index.components[&type_ids[i]].iter().filter_map(Weak::upgrade).collect(),
+            _ => Vec::new(),
+        }
+    });
+    if type_ids.len() == 1 {
+        return candidates;
+    }
+    candidates
+        .into_iter()
+        .filter(|node| {
+            let node = node.borrow();
+            type_ids.iter().all(|&type_id| node.has_component_type(type_id))
+        })
+        .collect()
+}
+
+/// Whether `node` is `root` or below it, by walking up from `node`: cheaper than searching
+/// the scene for each indexed node.
+pub(crate) fn is_under(root: &Rc<RefCell<Object3D>>, node: &Rc<RefCell<Object3D>>) -> bool {
+    let mut current = node.clone();
+    loop {
+        if Rc::ptr_eq(&current, root) {
+            return true;
+        }
+        let parent = current.borrow().parent();
+        match parent {
+            Some(parent) => current = parent,
+            None => return false,
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! Ragdolls: skeletons that fall and tumble under physics, blended with their animation.
+//!
+//! A [`RagdollBuilder`] turns a skeleton (a hierarchy of [`Object3D`] joints, as imported
+//! from glTF) into a [`Ragdoll`]: every joint becomes a point mass, every bone a capsule
+//! between a joint and its child joints, held together by distance constraints and limited
+//! to a cone of swing around its rest direction. Per-bone [`BoneConfig`]s set the capsule
+//! radius, mass and swing limit, and leave out subtrees such as fingers, which then ride
+//! along rigidly with their parent.
+//!
+//! The simulation is position based (Verlet integration with iterated constraints), using
+//! the gravity and fixed timestep of the scene's [`SimulationSettings`]. Each update, the
+//! ragdoll reads the pose the animation left on the joints and writes back a blend between
+//! it and the simulated pose: [`set_blend`](Ragdoll::set_blend) `0.0` is pure animation,
+//! `1.0` a limp ragdoll, and [`fade_to`](Ragdoll::fade_to) eases between them, e.g. when a
+//! character is hit. While fully animated the point masses track the joints, so a ragdoll
+//! switched on mid-motion carries on with the animation's velocity.
+//!
+//! Bones swing but don't twist under physics (twist stays as animated), collide only with
+//! an optional ground plane, not with each other or the rest of the scene, and non-uniform
+//! scale on the skeleton is not supported.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{object3d::Object3D, renderer::Renderer, ragdoll::{BoneConfig, RagdollBuilder}};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! # let skeleton = Object3D::new();
+//! # let head = Object3D::new();
+//! let mut ragdoll = RagdollBuilder::new(BoneConfig::default())
+//!     .bone(&head, BoneConfig { radius: 0.12, tip_length: 0.2, ..BoneConfig::default() })
+//!     .build(&skeleton);
+//! ragdoll.set_ground(Some(0.0));
+//!
+//! renderer.on_update(move |renderer, clock| {
+//!     if clock.elapsed() > 2.0 && ragdoll.blend() == 0.0 {
+//!         ragdoll.fade_to(1.0, 0.2);
+//!     }
+//!     if let Some(scene) = renderer.get_scene() {
+//!         ragdoll.update(scene.simulation_settings(), clock.delta());
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::debug::draw_line;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::{decompose_matrix, transform_direction};
+use crate::engine::math::quat;
+use crate::engine::math::vec::{add, cross, distance, dot, length, lerp, normalize_or, scale, sub};
+use crate::engine::object3d::Object3D;
+use crate::engine::simulation::{FixedStepper, SimulationSettings};
+
+/// Constraint solver passes per substep; more make the skeleton stiffer.
+const DEFAULT_ITERATIONS: u32 = 8;
+
+/// How a joint and the bone(s) from it to its children are simulated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoneConfig {
+    /// Radius of the bone's capsules, for ground contact.
+    pub radius: f32,
+
+    /// Mass of the bone, split between the joints at its ends. Only ratios between bones
+    /// matter.
+    pub mass: f32,
+
+    /// Largest angle in radians the bone may swing away from its rest direction relative
+    /// to its parent bone.
+    pub swing_limit: f32,
+
+    /// Length of a capsule along the joint's local +Y for bones without simulated children
+    /// (heads, hands, feet); `0.0` gives them none, so they only follow their parent.
+    pub tip_length: f32,
+
+    /// Whether the joint is simulated at all. `false` leaves the joint and its whole
+    /// subtree out of the ragdoll; they move rigidly with their parent.
+    pub simulated: bool,
+}
+
+impl Default for BoneConfig {
+    /// 10 cm radius, unit mass, swinging up to 60 degrees, no tip.
+    fn default() -> Self {
+        Self { radius: 0.1, mass: 1.0, swing_limit: std::f32::consts::FRAC_PI_3, tip_length: 0.0, simulated: true }
+    }
+}
+
+/// Builds a [`Ragdoll`] from a skeleton, with per-bone settings.
+#[derive(Clone, Debug)]
+pub struct RagdollBuilder {
+    /// Settings of every joint without an override.
+    pub default: BoneConfig,
+
+    /// Per-joint overrides.
+    overrides: Vec<(Rc<RefCell<Object3D>>, BoneConfig)>,
+}
+
+/// One bone's capsule in world space, as of the last update.
+#[derive(Clone, Debug)]
+pub struct Capsule {
+    /// The joint the capsule starts at.
+    pub node: Rc<RefCell<Object3D>>,
+
+    /// Start of the capsule's axis.
+    pub start: [f32; 3],
+
+    /// End of the capsule's axis.
+    pub end: [f32; 3],
+
+    /// Radius around the axis.
+    pub radius: f32,
+}
+
+/// A simulated skeleton; see the [module documentation](self).
+#[derive(Debug)]
+pub struct Ragdoll {
+    bones: Vec<Bone>,
+    particles: Vec<Particle>,
+    links: Vec<Link>,
+
+    /// Weight of the simulated pose, in `[0, 1]`.
+    blend: f32,
+
+    /// Blend target and rate per second of a fade in progress.
+    fade: Option<(f32, f32)>,
+
+    /// Height of the ground plane, if any.
+    ground: Option<f32>,
+
+    /// Fraction of sliding velocity lost per step while touching the ground.
+    ground_friction: f32,
+
+    /// Fraction of velocity lost per step, to settle the ragdoll.
+    damping: f32,
+
+    /// Constraint solver passes per substep.
+    iterations: u32,
+
+    stepper: FixedStepper,
+
+    /// Substep length of the last update, to convert velocities.
+    substep: f32,
+}
+
+/// A simulated joint.
+#[derive(Debug)]
+struct Bone {
+    node: Rc<RefCell<Object3D>>,
+
+    /// Index of the parent bone; `None` for the root.
+    parent: Option<usize>,
+
+    /// The particle at the joint.
+    particle: usize,
+
+    /// Particles at the far ends of the bone's capsules: its children's joints, or its tip.
+    ends: Vec<usize>,
+
+    /// Direction to the first end in the joint's local frame, which the simulated rotation
+    /// aligns with the simulated direction.
+    end_direction: [f32; 3],
+
+    /// Tip length if the bone has a tip particle instead of child joints.
+    tip_length: Option<f32>,
+
+    radius: f32,
+    swing_limit: f32,
+
+    /// Rest directions from the parent's joint to this one, and from this joint to its
+    /// first end, for the swing limit.
+    rest_parent_direction: [f32; 3],
+    rest_direction: [f32; 3],
+
+    /// Local transform the animation last set.
+    animated: ([f32; 3], [f32; 4]),
+
+    /// Local transform the ragdoll last wrote, to tell animation updates from its own.
+    written: Option<([f32; 3], [f32; 4])>,
+}
+
+/// A point mass.
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: [f32; 3],
+    previous: [f32; 3],
+    inverse_mass: f32,
+    radius: f32,
+}
+
+/// A distance constraint between two particles.
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    a: usize,
+    b: usize,
+    length: f32,
+}
+
+impl RagdollBuilder {
+    /// A builder applying `default` to every joint.
+    pub fn new(default: BoneConfig) -> Self {
+        Self { default, overrides: Vec::new() }
+    }
+
+    /// Overrides the settings of the joint `node`.
+    pub fn bone(mut self, node: &Rc<RefCell<Object3D>>, config: BoneConfig) -> Self {
+        self.overrides.retain(|(other, _)| !Rc::ptr_eq(other, node));
+        self.overrides.push((node.clone(), config));
+        self
+    }
+
+    /// The settings of `node`.
+    fn config(&self, node: &Rc<RefCell<Object3D>>) -> BoneConfig {
+        self.overrides
+            .iter()
+            .find(|(other, _)| Rc::ptr_eq(other, node))
+            .map_or(self.default, |(_, config)| *config)
+    }
+
+    /// Builds the ragdoll of the skeleton rooted at `root`, in its current pose, which is
+    /// taken as the rest pose for swing limits. The ragdoll starts fully animated.
+    pub fn build(&self, root: &Rc<RefCell<Object3D>>) -> Ragdoll {
+        let mut ragdoll = Ragdoll {
+            bones: Vec::new(),
+            particles: Vec::new(),
+            links: Vec::new(),
+            blend: 0.0,
+            fade: None,
+            ground: None,
+            ground_friction: 0.5,
+            damping: 0.01,
+            iterations: DEFAULT_ITERATIONS,
+            stepper: FixedStepper::new(),
+            substep: SimulationSettings::default().substep_duration(),
+        };
+        if self.config(root).simulated {
+            self.add_bone(&mut ragdoll, root, None);
+        }
+
+        // Keep each joint's children at fixed distances from each other too, so branching
+        // joints (hips, chest) hold their shape
+        for bone in &ragdoll.bones {
+            for (i, &a) in bone.ends.iter().enumerate() {
+                for &b in &bone.ends[i + 1..] {
+                    let length = distance(ragdoll.particles[a].position, ragdoll.particles[b].position);
+                    ragdoll.links.push(Link { a, b, length });
+                }
+            }
+        }
+        for particle in &mut ragdoll.particles {
+            particle.inverse_mass = if particle.inverse_mass > 0.0 { 1.0 / particle.inverse_mass } else { 1.0 };
+        }
+        for index in 0..ragdoll.bones.len() {
+            let bone = &ragdoll.bones[index];
+            let position = ragdoll.particles[bone.particle].position;
+            let rest_direction = bone
+                .ends
+                .first()
+                .map_or([0.0; 3], |&end| normalize_or(sub(ragdoll.particles[end].position, position), [0.0; 3]));
+            let rest_parent_direction = bone.parent.map_or([0.0; 3], |parent| {
+                let parent_position = ragdoll.particles[ragdoll.bones[parent].particle].position;
+                normalize_or(sub(position, parent_position), [0.0; 3])
+            });
+            let bone = &mut ragdoll.bones[index];
+            bone.rest_direction = rest_direction;
+            bone.rest_parent_direction = rest_parent_direction;
+        }
+        ragdoll
+    }
+
+    /// Adds `node` and its simulated descendants; returns the joint's particle.
+    fn add_bone(&self, ragdoll: &mut Ragdoll, node: &Rc<RefCell<Object3D>>, parent: Option<usize>) -> usize {
+        let config = self.config(node);
+        let (world, local) = {
+            let mut object = node.borrow_mut();
+            (object.world_matrix(), (object.position(), object.rotation()))
+        };
+        let position = [world[12], world[13], world[14]];
+        let particle = ragdoll.add_particle(position, config.radius);
+        let index = ragdoll.bones.len();
+        ragdoll.bones.push(Bone {
+            node: node.clone(),
+            parent,
+            particle,
+            ends: Vec::new(),
+            end_direction: [0.0, 1.0, 0.0],
+            tip_length: None,
+            radius: config.radius,
+            swing_limit: config.swing_limit,
+            rest_parent_direction: [0.0; 3],
+            rest_direction: [0.0; 3],
+            animated: local,
+            written: None,
+        });
+
+        let children: Vec<_> = node.borrow().children().iter().filter(|child| self.config(child).simulated).cloned().collect();
+        let node_scale = node.borrow().scale();
+        for (i, child) in children.iter().enumerate() {
+            if i == 0 {
+                let offset = child.borrow().position();
+                ragdoll.bones[index].end_direction = normalize_or(std::array::from_fn(|axis| offset[axis] * node_scale[axis]), [0.0, 1.0, 0.0]);
+            }
+            let end = self.add_bone(ragdoll, child, Some(index));
+            ragdoll.bones[index].ends.push(end);
+        }
+        if children.is_empty() && config.tip_length > 0.0 {
+            let up = normalize_or(transform_direction(&world, [0.0, 1.0, 0.0]), [0.0, 1.0, 0.0]);
+            let tip = ragdoll.add_particle(add(position, scale(up, config.tip_length)), config.radius);
+            let bone = &mut ragdoll.bones[index];
+            bone.ends.push(tip);
+            bone.tip_length = Some(config.tip_length);
+        }
+
+        // Spread the bone's mass over its joint and ends (accumulated as mass here, inverted
+        // once the skeleton is complete)
+        let ends = ragdoll.bones[index].ends.clone();
+        let share = config.mass.max(f32::EPSILON) / (ends.len() + 1) as f32;
+        ragdoll.particles[particle].inverse_mass += share;
+        for end in ends {
+            ragdoll.particles[end].inverse_mass += share;
+            let length = distance(ragdoll.particles[end].position, position);
+            ragdoll.links.push(Link { a: particle, b: end, length });
+        }
+        particle
+    }
+}
+
+impl Ragdoll {
+    /// Adds a particle at `position` with no mass yet.
+    fn add_particle(&mut self, position: [f32; 3], radius: f32) -> usize {
+        self.particles.push(Particle { position, previous: position, inverse_mass: 0.0, radius });
+        self.particles.len() - 1
+    }
+
+    /// Number of simulated joints.
+    pub fn bone_count(&self) -> usize {
+        self.bones.len()
+    }
+
+    /// Weight of the simulated pose: `0.0` fully animated, `1.0` fully simulated.
+    pub fn blend(&self) -> f32 {
+        self.blend
+    }
+
+    /// Sets the weight of the simulated pose immediately, cancelling any fade.
+    pub fn set_blend(&mut self, blend: f32) {
+        self.blend = blend.clamp(0.0, 1.0);
+        self.fade = None;
+    }
+
+    /// Moves the blend weight linearly to `target` over `seconds` of updates.
+    pub fn fade_to(&mut self, target: f32, seconds: f32) {
+        let target = target.clamp(0.0, 1.0);
+        if seconds <= 0.0 {
+            self.set_blend(target);
+        } else {
+            self.fade = Some((target, (target - self.blend).abs() / seconds));
+        }
+    }
+
+    /// Sets the height of a horizontal ground plane the capsules rest on, or `None` to let
+    /// them fall forever.
+    pub fn set_ground(&mut self, height: Option<f32>) {
+        self.ground = height;
+    }
+
+    /// Sets the fraction of sliding velocity lost per step on the ground, in `[0, 1]`.
+    pub fn set_ground_friction(&mut self, friction: f32) {
+        self.ground_friction = friction.clamp(0.0, 1.0);
+    }
+
+    /// Sets the fraction of velocity lost per step, in `[0, 1]`; higher values settle the
+    /// ragdoll faster, like moving through a thicker medium.
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0.0, 1.0);
+    }
+
+    /// Sets the constraint solver passes per substep (default 8). More passes keep bones
+    /// at their lengths more rigidly at a proportional cost.
+    pub fn set_iterations(&mut self, iterations: u32) {
+        self.iterations = iterations.max(1);
+    }
+
+    /// Adds `velocity` (units per second) to the joint `node`, e.g. the push of a hit.
+    /// Joints that aren't part of the ragdoll are ignored. Only has a visible effect while
+    /// the pose is at least partly simulated.
+    pub fn add_velocity(&mut self, node: &Rc<RefCell<Object3D>>, velocity: [f32; 3]) {
+        if let Some(bone) = self.bones.iter().find(|bone| Rc::ptr_eq(&bone.node, node)) {
+            let particle = &mut self.particles[bone.particle];
+            particle.previous = sub(particle.previous, scale(velocity, self.substep));
+        }
+    }
+
+    /// The capsule of every bone, as simulated by the last update.
+    pub fn capsules(&self) -> Vec<Capsule> {
+        self.bones
+            .iter()
+            .flat_map(|bone| {
+                bone.ends.iter().map(|&end| Capsule {
+                    node: bone.node.clone(),
+                    start: self.particles[bone.particle].position,
+                    end: self.particles[end].position,
+                    radius: bone.radius,
+                })
+            })
+            .collect()
+    }
+
+    /// Draws the simulated bones as lines for this frame (see
+    /// [`debug::draw_line`](crate::engine::debug::draw_line)).
+    pub fn draw_debug(&self, color: Color) {
+        for capsule in self.capsules() {
+            draw_line(capsule.start, capsule.end, color);
+        }
+    }
+
+    /// Advances the ragdoll by a frame of `delta` seconds and poses the joints.
+    ///
+    /// Call once per frame after the animation has posed the skeleton (or not at all: the
+    /// last animated pose is kept) and before the scene is drawn.
+    pub fn update(&mut self, settings: &SimulationSettings, delta: f32) {
+        if let Some((target, rate)) = self.fade {
+            let step = rate * delta.max(0.0);
+            self.blend = if (target - self.blend).abs() <= step { target } else { self.blend + step * (target - self.blend).signum() };
+            if self.blend == target {
+                self.fade = None;
+            }
+        }
+        self.substep = settings.substep_duration();
+
+        // Put the animated pose back on the joints and measure it in world space
+        let mut animated_world = Vec::with_capacity(self.bones.len());
+        for bone in &mut self.bones {
+            let mut node = bone.node.borrow_mut();
+            let current = (node.position(), node.rotation());
+            if bone.written != Some(current) {
+                bone.animated = current;
+            }
+            node.set_position(bone.animated.0);
+            node.set_rotation(bone.animated.1);
+        }
+        for bone in &self.bones {
+            let (position, rotation, _) = decompose_matrix(&bone.node.borrow_mut().world_matrix());
+            animated_world.push((position, rotation));
+        }
+
+        if self.blend <= 0.0 {
+            self.track(&animated_world);
+            self.stepper.reset();
+            for bone in &mut self.bones {
+                bone.written = Some(bone.animated);
+            }
+            return;
+        }
+
+        for _ in 0..self.stepper.advance(settings, delta) {
+            for _ in 0..settings.substeps.max(1) {
+                self.integrate(settings.gravity, self.substep);
+                for _ in 0..self.iterations {
+                    self.solve();
+                }
+            }
+        }
+        self.write_pose(&animated_world);
+    }
+
+    /// Moves every particle onto the animated pose, keeping the motion since the last frame
+    /// as velocity.
+    fn track(&mut self, animated_world: &[([f32; 3], [f32; 4])]) {
+        for (bone, &(position, rotation)) in self.bones.iter().zip(animated_world) {
+            let targets = std::iter::once((bone.particle, position)).chain(bone.tip_length.and_then(|length| {
+                let up = quat::rotate_vector(rotation, [0.0, 1.0, 0.0]);
+                bone.ends.first().map(|&tip| (tip, add(position, scale(up, length))))
+            }));
+            for (index, target) in targets {
+                let particle = &mut self.particles[index];
+                particle.previous = particle.position;
+                particle.position = target;
+            }
+        }
+    }
+
+    /// Verlet integration of every particle over `dt` seconds.
+    fn integrate(&mut self, gravity: [f32; 3], dt: f32) {
+        let keep = 1.0 - self.damping;
+        for particle in &mut self.particles {
+            let velocity = scale(sub(particle.position, particle.previous), keep);
+            particle.previous = particle.position;
+            particle.position = add(add(particle.position, velocity), scale(gravity, dt * dt));
+        }
+    }
+
+    /// One pass over every constraint.
+    fn solve(&mut self) {
+        for link in &self.links {
+            let (a, b) = (self.particles[link.a], self.particles[link.b]);
+            let weight = a.inverse_mass + b.inverse_mass;
+            let offset = sub(b.position, a.position);
+            let current = length(offset);
+            if weight <= 0.0 || current <= f32::EPSILON {
+                continue;
+            }
+            let correction = scale(offset, (current - link.length) / (current * weight));
+            self.particles[link.a].position = add(a.position, scale(correction, a.inverse_mass));
+            self.particles[link.b].position = sub(b.position, scale(correction, b.inverse_mass));
+        }
+
+        for bone in &self.bones {
+            let (Some(parent), Some(&end)) = (bone.parent, bone.ends.first()) else {
+                continue;
+            };
+            if bone.rest_parent_direction == [0.0; 3] || bone.rest_direction == [0.0; 3] {
+                continue;
+            }
+            let joint = self.particles[bone.particle].position;
+            let parent_joint = self.particles[self.bones[parent].particle].position;
+            let parent_direction = normalize_or(sub(joint, parent_joint), bone.rest_parent_direction);
+            let allowed = quat::rotate_vector(
+                quat::rotation_between(bone.rest_parent_direction, parent_direction),
+                bone.rest_direction,
+            );
+            let offset = sub(self.particles[end].position, joint);
+            let reach = length(offset);
+            let direction = normalize_or(offset, allowed);
+            let angle = dot(direction, allowed).clamp(-1.0, 1.0).acos();
+            if angle > bone.swing_limit {
+                let axis = normalize_or(cross(allowed, direction), [0.0; 3]);
+                let limited = quat::rotate_vector(quat::from_axis_angle(axis, bone.swing_limit), allowed);
+                self.particles[end].position = add(joint, scale(limited, reach));
+            }
+        }
+
+        if let Some(ground) = self.ground {
+            let friction = self.ground_friction;
+            for particle in &mut self.particles {
+                let floor = ground + particle.radius;
+                if particle.position[1] < floor {
+                    particle.position[1] = floor;
+                    particle.previous[0] += (particle.position[0] - particle.previous[0]) * friction;
+                    particle.previous[2] += (particle.position[2] - particle.previous[2]) * friction;
+                }
+            }
+        }
+    }
+
+    /// Writes the blend of the animated and simulated pose onto the joints, root first.
+    fn write_pose(&mut self, animated_world: &[([f32; 3], [f32; 4])]) {
+        let mut simulated: Vec<[f32; 4]> = Vec::with_capacity(self.bones.len());
+        let mut blended: Vec<[f32; 4]> = Vec::with_capacity(self.bones.len());
+        for index in 0..self.bones.len() {
+            let bone = &self.bones[index];
+            let (animated_position, animated_rotation) = animated_world[index];
+            let joint = self.particles[bone.particle].position;
+            let rotation = match (bone.ends.first(), bone.parent) {
+                (Some(&end), _) => {
+                    let animated_direction = quat::rotate_vector(animated_rotation, bone.end_direction);
+                    let swing = quat::rotation_between(animated_direction, sub(self.particles[end].position, joint));
+                    quat::mul(swing, animated_rotation)
+                }
+                // Leaves follow the swing of their parent
+                (None, Some(parent)) => {
+                    let parent_swing = quat::mul(simulated[parent], quat::conjugate(animated_world[parent].1));
+                    quat::mul(parent_swing, animated_rotation)
+                }
+                (None, None) => animated_rotation,
+            };
+            simulated.push(rotation);
+            let world_rotation = quat::slerp(animated_rotation, rotation, self.blend);
+            blended.push(world_rotation);
+
+            let written = {
+                let mut node = bone.node.borrow_mut();
+                let parent_rotation = match bone.parent {
+                    Some(parent) => blended[parent],
+                    None => node.parent().map_or(quat::IDENTITY, |parent| decompose_matrix(&parent.borrow_mut().world_matrix()).1),
+                };
+                if bone.parent.is_none() {
+                    let target = lerp(animated_position, joint, self.blend);
+                    let local = match node.parent() {
+                        Some(parent) => parent.borrow_mut().world_to_local(target),
+                        None => target,
+                    };
+                    node.set_position(local);
+                }
+                node.set_rotation(quat::normalize(quat::mul(quat::conjugate(parent_rotation), world_rotation)));
+                (node.position(), node.rotation())
+            };
+            self.bones[index].written = Some(written);
+        }
+    }
+}
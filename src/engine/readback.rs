@@ -0,0 +1,229 @@
+//! Asynchronous GPU readback.
+//!
+//! Reading pixels straight into client memory (`glReadPixels` or `glGetTexImage` without a
+//! pixel pack buffer) makes the driver finish every queued command first, stalling the CPU
+//! until the GPU catches up. A [`Readback`] instead copies into a pixel buffer object and
+//! puts a fence behind the copy: the GPU performs the copy along with the following frames,
+//! and [`try_take`](Readback::try_take) hands the data over once the fence has signaled,
+//! usually a frame or two later, without ever waiting.
+//!
+//! The renderer uses readbacks for [screenshots](crate::engine::renderer::Renderer::save_screenshot),
+//! and [`PickingBuffer`](crate::engine::editor::picking::PickingBuffer) for
+//! [non-blocking picks](crate::engine::editor::picking::PickingBuffer::nodes_in_rect_async).
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::readback::{Readback, ReadbackFormat};
+//! // Right after drawing, start copying the back buffer
+//! let mut pending = Readback::framebuffer(0, [0, 0, 800, 600], ReadbackFormat::Rgba8);
+//!
+//! // In a later frame
+//! if let Some(pixels) = pending.try_take() {
+//!     println!("read {} bytes", pixels.len());
+//! }
+//! ```
+
+use std::time::Duration;
+use gl::types::{GLenum, GLint, GLsizei, GLsizeiptr, GLsync, GLuint};
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+use crate::engine::texture::Texture;
+
+/// Pixel format of the data a [`Readback`] returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadbackFormat {
+    /// 8-bit RGBA color.
+    Rgba8,
+    /// One 32-bit unsigned integer per pixel, as in integer id buffers.
+    R32Uint,
+    /// One 32-bit float depth value per pixel.
+    Depth32F,
+}
+
+impl ReadbackFormat {
+    /// Size of one pixel in bytes.
+    pub fn bytes_per_pixel(self) -> usize {
+        4
+    }
+
+    /// The `format` and `type` arguments of `glReadPixels`.
+    fn gl_format_type(self) -> (GLenum, GLenum) {
+        match self {
+            ReadbackFormat::Rgba8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+            ReadbackFormat::R32Uint => (gl::RED_INTEGER, gl::UNSIGNED_INT),
+            ReadbackFormat::Depth32F => (gl::DEPTH_COMPONENT, gl::FLOAT),
+        }
+    }
+}
+
+/// A GPU-to-CPU copy in flight.
+///
+/// Created on the GL thread, polled there every frame until the data is ready. Dropping it
+/// before then cancels the readback.
+#[derive(Debug)]
+pub struct Readback {
+    pbo: GLuint,
+    fence: GLsync,
+    size: [u32; 2],
+    format: ReadbackFormat,
+
+    /// Whether the data was already handed over.
+    taken: bool,
+}
+
+impl Readback {
+    /// Starts reading the `[x, y, width, height]` rectangle (origin bottom-left, as in GL)
+    /// of framebuffer `fbo`: color attachment 0 for color formats, or the back buffer when
+    /// `fbo` is 0; the depth buffer for [`ReadbackFormat::Depth32F`]. Requires a current GL
+    /// context; returns immediately.
+    pub fn framebuffer(fbo: GLuint, rect: [u32; 4], format: ReadbackFormat) -> Self {
+        let [x, y, width, height] = rect;
+        let pbo = Self::create_buffer([width, height], format, "framebuffer readback");
+        let (gl_format, gl_type) = format.gl_format_type();
+        let mut previous_read = 0;
+        unsafe {
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut previous_read);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+            if format != ReadbackFormat::Depth32F {
+                gl::ReadBuffer(if fbo == 0 { gl::BACK } else { gl::COLOR_ATTACHMENT0 });
+            }
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+            // With a pack buffer bound, the pointer is an offset into it
+            gl::ReadPixels(
+                x as GLint,
+                y as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                gl_format,
+                gl_type,
+                std::ptr::null_mut(),
+            );
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, previous_read as GLuint);
+        }
+        Self::fenced(pbo, [width, height], format)
+    }
+
+    /// Starts reading the base level of `texture` as RGBA8. Requires a current GL context;
+    /// returns immediately.
+    pub fn texture(texture: &Texture) -> Self {
+        let size = [texture.width(), texture.height()];
+        let pbo = Self::create_buffer(size, ReadbackFormat::Rgba8, "texture readback");
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+            gl::GetTexImage(gl::TEXTURE_2D, 0, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut());
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        Self::fenced(pbo, size, ReadbackFormat::Rgba8)
+    }
+
+    /// Width and height of the data in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Format of the data.
+    pub fn format(&self) -> ReadbackFormat {
+        self.format
+    }
+
+    /// Whether the copy has finished, so [`try_take`](Self::try_take) would return the data.
+    /// Never blocks.
+    pub fn is_ready(&self) -> bool {
+        !self.taken && self.client_wait(0)
+    }
+
+    /// The pixels, tightly packed with rows bottom to top, if the copy has finished. Returns
+    /// `None` while the GPU is still busy, and after the data was taken once. Never blocks.
+    pub fn try_take(&mut self) -> Option<Vec<u8>> {
+        self.is_ready().then(|| self.take())
+    }
+
+    /// Blocks until the copy has finished and returns the pixels, like a synchronous read.
+    /// For when the result is needed after all, e.g. before shutting down.
+    ///
+    /// # Panics
+    /// Panics if the data was already taken.
+    pub fn wait(mut self) -> Vec<u8> {
+        assert!(!self.taken, "Readback data was already taken");
+        while !self.client_wait(Duration::from_millis(100).as_nanos() as u64) {}
+        self.take()
+    }
+
+    fn create_buffer(size: [u32; 2], format: ReadbackFormat, label: &str) -> GLuint {
+        let bytes = size[0] as usize * size[1] as usize * format.bytes_per_pixel();
+        let mut pbo = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut pbo);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+            gl::BufferData(gl::PIXEL_PACK_BUFFER, bytes as GLsizeiptr, std::ptr::null(), gl::STREAM_READ);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::PixelBuffer, pbo, bytes, label);
+        pbo
+    }
+
+    fn fenced(pbo: GLuint, size: [u32; 2], format: ReadbackFormat) -> Self {
+        let fence = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        Self { pbo, fence, size, format, taken: false }
+    }
+
+    /// Waits up to `timeout` nanoseconds for the fence, returning whether it signaled.
+    fn client_wait(&self, timeout: u64) -> bool {
+        // Flushing makes sure the fence gets submitted, or it could never signal
+        let status = unsafe { gl::ClientWaitSync(self.fence, gl::SYNC_FLUSH_COMMANDS_BIT, timeout) };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+
+    /// Copies the finished data out of the pixel buffer.
+    fn take(&mut self) -> Vec<u8> {
+        self.taken = true;
+        let bytes = self.size[0] as usize * self.size[1] as usize * self.format.bytes_per_pixel();
+        let mut data = vec![0u8; bytes];
+        if bytes == 0 {
+            return data;
+        }
+        unsafe {
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbo);
+            let mapped = gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, bytes as GLsizeiptr, gl::MAP_READ_BIT);
+            if mapped.is_null() {
+                eprintln!("Failed to map readback buffer {}", self.pbo);
+            } else {
+                std::ptr::copy_nonoverlapping(mapped as *const u8, data.as_mut_ptr(), bytes);
+                gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+        data
+    }
+}
+
+impl Drop for Readback {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::PixelBuffer, self.pbo);
+        unsafe {
+            gl::DeleteSync(self.fence);
+            gl::DeleteBuffers(1, &self.pbo);
+        }
+    }
+}
+
+/// Reverses the row order of tightly packed pixels, turning GL's bottom-to-top rows into the
+/// top-to-bottom order image files use (and back).
+///
+/// ```
+/// # use rustge::engine::readback::flip_rows;
+/// let mut pixels = vec![1, 1, 2, 2, 3, 3];
+/// flip_rows(&mut pixels, 2);
+/// assert_eq!(pixels, [3, 3, 2, 2, 1, 1]);
+/// ```
+pub fn flip_rows(pixels: &mut [u8], row_bytes: usize) {
+    let rows = pixels.len() / row_bytes.max(1);
+    for row in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - row) * row_bytes);
+        top[row * row_bytes..(row + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+    }
+}
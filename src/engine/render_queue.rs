@@ -0,0 +1,206 @@
+//! Sorted submission of a scene's draw calls.
+//!
+//! Drawing the scene graph node by node in tree order switches shader programs, uploads the
+//! lights and rebinds render state for every node. A [`RenderQueue`] instead walks the graph
+//! once, collecting a draw command per visible node with geometry and a material, sorts the
+//! commands, and submits them while skipping binds that would not change anything:
+//!
+//! - Opaque commands come first, grouped by shader program, then render state, then
+//!   textures, then mesh. The camera and lights go to the
+//!   [standard uniform blocks](crate::engine::uniform_buffer) once per submission, the
+//!   program is bound once per group, render state only when it differs from the previous
+//!   command, and material uniforms only when the material's parameters differ.
+//! - Transparent commands (see [`Material::is_transparent`]) follow, sorted back to front
+//!   by distance from the camera so blending composites correctly.
+//!
+//! The two groups can be submitted separately, so other passes can run between the opaque
+//! and transparent geometry. The renderer draws [`Scene`](crate::engine::scene::Scene)s
+//! through a queue;
+//! [`Object3D::draw`] remains available for drawing a subtree immediately in tree order.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{camera::Camera, render_queue::RenderQueue, scene::Scene};
+//! # let (scene, camera) = (Scene::new(), Camera::new(1.0));
+//! let mut queue = RenderQueue::new();
+//! queue.collect(scene.root(), &camera);
+//! queue.sort();
+//! queue.submit(&camera, &scene.collect_lights());
+//! ```
+
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+use crate::engine::animation::skeleton::set_skin_uniforms;
+use crate::engine::camera::Camera;
+use crate::engine::light::LightSet;
+use crate::engine::material::Material;
+use crate::engine::math::matrixfuncs::normal_matrix;
+use crate::engine::math::vec::distance_squared;
+use crate::engine::object3d::Object3D;
+use crate::engine::stats::{record_material_bind, record_program_bind, record_state_change};
+use crate::engine::uniform_buffer::{apply_frame_uniforms, upload_frame_blocks};
+
+/// One node to draw, with what the queue sorts it by.
+#[derive(Debug)]
+struct DrawCommand {
+    node: Rc<RefCell<Object3D>>,
+    world_matrix: [f32; 16],
+
+    /// Drawn after every opaque command, back to front.
+    transparent: bool,
+
+    /// Squared distance from the camera to the node's bounding sphere center.
+    distance: f32,
+
+    /// Program, render state, texture and mesh identities, in sorting priority.
+    key: [usize; 4],
+}
+
+/// Collects, sorts and submits draw commands for a scene graph. Keep one around and reuse
+/// it every frame to avoid reallocating the command list.
+#[derive(Debug, Default)]
+pub struct RenderQueue {
+    commands: Vec<DrawCommand>,
+}
+
+impl RenderQueue {
+    /// An empty queue.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the queued commands with one per node under `root` (inclusive) that passes
+    /// frustum culling for `camera` and has both geometry and a material.
+    pub fn collect(&mut self, root: &Rc<RefCell<Object3D>>, camera: &Camera) {
+        self.commands.clear();
+        for node in Object3D::visible_set(root, camera) {
+            let command = {
+                let mut object = node.borrow_mut();
+                let (Some(material), Some(geometry)) = (object.material(), object.shared_geometry()) else {
+                    continue;
+                };
+                let transparent = material.is_transparent();
+                let key = [
+                    Rc::as_ptr(&material.shader) as usize,
+                    state_key(material),
+                    material.textures.first().map_or(0, |(_, texture)| Rc::as_ptr(texture) as usize),
+                    Rc::as_ptr(&geometry) as usize,
+                ];
+                let (center, _) = object.world_bounding_sphere();
+                DrawCommand {
+                    world_matrix: object.world_matrix(),
+                    transparent,
+                    distance: distance_squared(center, camera.position),
+                    key,
+                    node: node.clone(),
+                }
+            };
+            self.commands.push(command);
+        }
+    }
+
+    /// Sorts the queued commands: opaque ones grouped to minimize state changes, then
+    /// transparent ones from farthest to nearest the camera they were collected for.
+    pub fn sort(&mut self) {
+        self.commands.sort_by(|a, b| {
+            a.transparent.cmp(&b.transparent).then_with(|| {
+                if a.transparent {
+                    b.distance.total_cmp(&a.distance)
+                } else {
+                    a.key.cmp(&b.key)
+                }
+            })
+        });
+    }
+
+    /// Draws all queued commands in order, lit by `lights`. Requires a current GL context.
+    pub fn submit(&self, camera: &Camera, lights: &LightSet) {
+        self.submit_commands(&self.commands, camera, lights);
+    }
+
+    /// Draws only the opaque commands. Call after [`sort`](Self::sort).
+    pub fn submit_opaque(&self, camera: &Camera, lights: &LightSet) {
+        self.submit_commands(&self.commands[..self.opaque_len()], camera, lights);
+    }
+
+    /// Draws only the transparent commands, back to front. Call after [`sort`](Self::sort)
+    /// and after the opaque commands, so blending sees the opaque geometry behind.
+    pub fn submit_transparent(&self, camera: &Camera, lights: &LightSet) {
+        self.submit_commands(&self.commands[self.opaque_len()..], camera, lights);
+    }
+
+    /// Number of queued transparent commands.
+    pub fn transparent_len(&self) -> usize {
+        self.commands.iter().filter(|command| command.transparent).count()
+    }
+
+    /// Number of sorted commands before the first transparent one.
+    fn opaque_len(&self) -> usize {
+        self.commands.partition_point(|command| !command.transparent)
+    }
+
+    fn submit_commands(&self, commands: &[DrawCommand], camera: &Camera, lights: &LightSet) {
+        upload_frame_blocks(camera, lights);
+
+        // The node drawn last, whose material is what the GL state currently reflects
+        let mut previous: Option<Ref<Object3D>> = None;
+
+        for command in commands {
+            let node = command.node.borrow();
+            let Some(material) = node.material() else {
+                continue;
+            };
+            let shader = &material.shader;
+            let bound = previous.as_ref().and_then(|previous| previous.material());
+
+            let program_changed = bound.is_none_or(|bound| !Rc::ptr_eq(&bound.shader, shader));
+            if program_changed {
+                shader.use_program();
+                apply_frame_uniforms(shader, camera, lights);
+                record_program_bind();
+            }
+            if bound.is_none_or(|bound| !bound.same_state(material)) {
+                material.apply_state();
+                record_state_change();
+            }
+            if program_changed || bound.is_none_or(|bound| !bound.same_parameters(material)) {
+                material.upload_parameters();
+                record_material_bind();
+            }
+
+            shader.set_uniform_matrix4("u_model", &command.world_matrix);
+            shader.set_uniform_matrix3("u_normal_matrix", &normal_matrix(&command.world_matrix));
+            shader.set_uniform_int("u_instanced", node.instances().is_some() as i32);
+            set_skin_uniforms(shader, &node);
+            node.draw_geometry();
+
+            // Debug overlays bind their own programs, so the next command rebinds everything
+            let overlays_drawn = node.draw_debug_overlays(&command.world_matrix, camera);
+            previous = if overlays_drawn { None } else { Some(node) };
+        }
+    }
+
+    /// Number of queued commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Whether no commands are queued.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Removes every queued command, releasing the queue's references to the nodes.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+/// Packs the fixed-function state [`Material::apply_state`] sets into a sort key, so
+/// commands with identical state end up next to each other.
+fn state_key(material: &Material) -> usize {
+    (material.blend as usize)
+        | (material.cull as usize) << 2
+        | (material.depth_test as usize) << 4
+        | (material.depth_write as usize) << 5
+}
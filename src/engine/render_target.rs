@@ -0,0 +1,250 @@
+//! Offscreen render targets.
+//!
+//! A [`RenderTarget`] is a framebuffer object with a color texture and a depth-stencil
+//! texture. Rendering a scene into it instead of the window (see
+//! [`Renderer::render_to`](crate::engine::renderer::Renderer::render_to)) produces a texture
+//! that materials can sample like any other: mirrors and security monitors, minimaps,
+//! thumbnails, or the input of a post-processing pass.
+//!
+//! Textures can't be multisampled, so anti-aliased rendering goes into a
+//! [`MultisampleTarget`] first and is then [resolved](MultisampleTarget::resolve_into) into
+//! a `RenderTarget` of the same size. The renderer does this by itself for the window when
+//! [MSAA](crate::engine::quality::QualitySettings::msaa_samples) is enabled.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{camera::Camera, material::Material, render_target::RenderTarget, renderer::Renderer, shader::GLShaderProgram};
+//! # let renderer = Renderer::new("Example", 800, 600);
+//! # let screen_shader = std::rc::Rc::new(GLShaderProgram::from_sources("", ""));
+//! let minimap = RenderTarget::new(256, 256, "minimap");
+//! let mut overhead = Camera::new(minimap.aspect());
+//! overhead.position = [0.0, 50.0, 0.0];
+//! overhead.rotation = [-(0.5f32.sqrt()), 0.0, 0.0, 0.5f32.sqrt()];
+//! if let Some(scene) = renderer.get_scene() {
+//!     renderer.render_to(&minimap, scene, &overhead);
+//! }
+//!
+//! // A material whose shader samples `u_minimap`
+//! let mut screen = Material::new(screen_shader);
+//! screen.set_texture("u_minimap", minimap.color_texture().clone());
+//! ```
+
+use std::rc::Rc;
+use gl::types::{GLbitfield, GLint, GLsizei, GLuint};
+use crate::engine::color_space::color_texture_format;
+use crate::engine::readback::{Readback, ReadbackFormat};
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+use crate::engine::texture::Texture;
+
+/// A framebuffer object with an 8-bit color texture (sRGB in the
+/// [sRGB pipeline](crate::engine::color_space)) and a depth-stencil texture.
+#[derive(Debug)]
+pub struct RenderTarget {
+    fbo: GLuint,
+    color: Rc<Texture>,
+    depth: Rc<Texture>,
+    size: [u32; 2],
+}
+
+impl RenderTarget {
+    /// Creates a `width` x `height` target. Requires a current GL context.
+    pub fn new(width: u32, height: u32, label: &str) -> Self {
+        let size = [width.max(1), height.max(1)];
+        let color = Rc::new(Texture::empty(size[0], size[1], &format!("{label} color")));
+        let depth = Rc::new(Texture::empty_depth(size[0], size[1], &format!("{label} depth")));
+        let mut fbo = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, color.id(), 0);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::TEXTURE_2D, depth.id(), 0);
+            check_complete(label);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        Self { fbo, color, depth, size }
+    }
+
+    /// Width and height in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Width divided by height, for the camera rendering into the target.
+    pub fn aspect(&self) -> f32 {
+        self.size[0] as f32 / self.size[1] as f32
+    }
+
+    /// The color attachment, to sample in materials. Stays valid after the target is
+    /// dropped, holding the last rendered image.
+    pub fn color_texture(&self) -> &Rc<Texture> {
+        &self.color
+    }
+
+    /// The depth-stencil attachment, e.g. for post effects that need the scene's depth.
+    /// Samples as depth in `[0, 1]`, not linear distance.
+    pub fn depth_texture(&self) -> &Rc<Texture> {
+        &self.depth
+    }
+
+    /// The OpenGL framebuffer name.
+    pub fn fbo(&self) -> GLuint {
+        self.fbo
+    }
+
+    /// Runs `draw` with the target bound as the framebuffer and the viewport covering it,
+    /// then restores the previous framebuffer and viewport. For custom passes; see
+    /// [`Renderer::render_to`](crate::engine::renderer::Renderer::render_to) for drawing a
+    /// scene.
+    pub fn draw_with<R>(&self, draw: impl FnOnce() -> R) -> R {
+        draw_into(self.fbo, self.size, draw)
+    }
+
+    /// Starts copying the color attachment back to the CPU without stalling; see
+    /// [`Readback`].
+    pub fn read_color_async(&self) -> Readback {
+        Readback::framebuffer(self.fbo, [0, 0, self.size[0], self.size[1]], ReadbackFormat::Rgba8)
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
+
+/// A framebuffer object with multisampled 8-bit color and depth-stencil renderbuffers, for
+/// anti-aliased rendering. Its contents can't be sampled directly; resolve them into a
+/// [`RenderTarget`] first.
+#[derive(Debug)]
+pub struct MultisampleTarget {
+    fbo: GLuint,
+    color: GLuint,
+    depth: GLuint,
+    size: [u32; 2],
+    samples: u32,
+}
+
+impl MultisampleTarget {
+    /// Creates a `width` x `height` target with `samples` samples per pixel, clamped to
+    /// what the driver supports. Requires a current GL context.
+    pub fn new(width: u32, height: u32, samples: u32, label: &str) -> Self {
+        let size = [width.max(1), height.max(1)];
+        let (mut fbo, mut color, mut depth) = (0, 0, 0);
+        let mut max_samples: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+        }
+        let samples = samples.clamp(1, max_samples.max(1) as u32);
+        unsafe {
+            gl::GenRenderbuffers(1, &mut color);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, color);
+            gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples as GLsizei, color_texture_format(), size[0] as GLsizei, size[1] as GLsizei);
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorageMultisample(
+                gl::RENDERBUFFER,
+                samples as GLsizei,
+                gl::DEPTH24_STENCIL8,
+                size[0] as GLsizei,
+                size[1] as GLsizei,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::RENDERBUFFER, color);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, gl::RENDERBUFFER, depth);
+            check_complete(label);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        let bytes = (size[0] * size[1] * 4 * samples) as usize;
+        track_gpu_allocation(GpuResourceKind::RenderTarget, color, bytes, &format!("{label} color"));
+        track_gpu_allocation(GpuResourceKind::RenderTarget, depth, bytes, &format!("{label} depth"));
+        Self { fbo, color, depth, size, samples }
+    }
+
+    /// Width and height in pixels.
+    pub fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    /// Samples per pixel, after clamping to the driver's limit.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// The OpenGL framebuffer name.
+    pub fn fbo(&self) -> GLuint {
+        self.fbo
+    }
+
+    /// Runs `draw` with the target bound as the framebuffer and the viewport covering it,
+    /// then restores the previous framebuffer and viewport, like
+    /// [`RenderTarget::draw_with`].
+    pub fn draw_with<R>(&self, draw: impl FnOnce() -> R) -> R {
+        draw_into(self.fbo, self.size, draw)
+    }
+
+    /// Resolves the samples into `target`, which must be the same size: colors are averaged,
+    /// while depth and stencil take one sample per pixel, since an average of depths at a
+    /// silhouette would lie between the surfaces and match neither. Leaves no framebuffer
+    /// bound for drawing.
+    pub fn resolve_into(&self, target: &RenderTarget) {
+        if target.size != self.size {
+            eprintln!("Warning: can't resolve a {:?} multisample target into a {:?} target", self.size, target.size);
+            return;
+        }
+        let [width, height] = self.size.map(|v| v as GLint);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target.fbo);
+            // Depth and stencil blits must use nearest filtering; for color it also keeps
+            // the resolve exact, as the sizes match
+            let mask: GLbitfield = gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT;
+            gl::BlitFramebuffer(0, 0, width, height, 0, 0, width, height, mask, gl::NEAREST);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+}
+
+impl Drop for MultisampleTarget {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.color);
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.depth);
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteRenderbuffers(1, &self.color);
+            gl::DeleteRenderbuffers(1, &self.depth);
+        }
+    }
+}
+
+/// Logs if the bound framebuffer is incomplete.
+fn check_complete(label: &str) {
+    let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+    if status != gl::FRAMEBUFFER_COMPLETE {
+        eprintln!("Render target {} is incomplete: status 0x{:x}", label, status);
+    }
+}
+
+/// Binds `fbo` with a viewport of `size` around `draw`, restoring the previous framebuffer
+/// and viewport afterwards.
+fn draw_into<R>(fbo: GLuint, size: [u32; 2], draw: impl FnOnce() -> R) -> R {
+    let mut previous_fbo: GLint = 0;
+    let mut previous_viewport = [0; 4];
+    unsafe {
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_fbo);
+        gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        gl::Viewport(0, 0, size[0] as GLsizei, size[1] as GLsizei);
+    }
+    let result = draw();
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, previous_fbo as GLuint);
+        let [x, y, width, height] = previous_viewport;
+        gl::Viewport(x, y, width, height);
+    }
+    result
+}
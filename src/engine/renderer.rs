@@ -1,18 +1,123 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::net::ToSocketAddrs;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use glutin::{
     dpi::PhysicalSize,
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::WindowBuilder,
+    Api,
     ContextBuilder,
     ContextWrapper,
+    GlProfile,
+    GlRequest,
     PossiblyCurrent,
     window::Window,
 };
 use gl;
-use std::{rc::Rc, cell::RefCell};
-use std::cell::RefMut;
-use crate::engine::camera::Camera;
-use crate::engine::object3d::{GLMesh, Object3D};
+use crate::engine::animation::compute_skinning::{compute_skinning_supported, release_deformed, skin_on_gpu};
+use crate::engine::animation::skeleton::Skin;
+use crate::engine::camera::{AspectMode, Camera, OrbitController, PhysicalCamera, SensorFit};
+use crate::engine::color_space::{output_color, set_srgb_pipeline};
+use crate::engine::debug::draw::DebugDraw;
+use crate::engine::debug::pass_overlay::queue_pass_overlay;
+use crate::engine::debug::profiler_overlay::queue_profiler_overlay;
+use crate::engine::debug::text::TextBatch;
+use crate::engine::display::{primary_first, set_swap_interval, FullscreenMode, Monitor};
+use crate::engine::ecs::{sync_scene, Schedule, World};
+use crate::engine::editor::outline::{draw_outlines, OutlineStyle};
+use crate::engine::editor::picking::{PendingPick, PickingBuffer};
+use crate::engine::editor::selection::{Selection, SelectionGesture, SelectionInput};
+use crate::engine::event::{EngineEvent, EventTranslator};
+use crate::engine::eye_adaptation::{set_frame_exposure, ExposureMeter, EyeAdaptation};
+use crate::engine::frame_graph::FrameGraph;
+use crate::engine::hot_reload::HotReload;
+use crate::engine::debug::gl_debug::{self, GlDebug};
+use crate::engine::program_cache;
+use crate::engine::editor::live_sync::LiveSync;
+use crate::engine::import::{is_model_file, load_model};
+use crate::engine::input::InputMap;
+use crate::engine::input_context::{Capture, InputContext, InputContextStack};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::color::Color;
+use crate::engine::math::matrixfuncs::{decompose_matrix, look_at_matrix};
+use crate::engine::mesh::gpu::GpuMesh;
+use crate::engine::motion::MotionVectors;
+use crate::engine::object3d::{Geometry, Object3D};
+use crate::engine::post::{PostChain, SceneTargets};
+use crate::engine::quality::{QualityPreset, QualitySettings};
+use crate::engine::readback::{flip_rows, Readback, ReadbackFormat};
+use crate::engine::render_target::RenderTarget;
+use crate::engine::physics::world::PhysicsWorld;
+use crate::engine::plugin::{FrameStage, Plugin, PluginRegistry};
+use crate::engine::scene::Scene;
+use crate::engine::snapshot::{snapshot_channel, SnapshotPublisher, SnapshotReader};
+use crate::engine::stats::{release_gpu_allocation, set_gpu_memory_budget, track_gpu_allocation, GpuResourceKind};
+use crate::engine::texture::{set_frame_lod_bias, Cubemap, Texture, TextureLod};
+use crate::engine::time::Clock;
+use crate::engine::tween::TweenManager;
+use crate::engine::ui::UiContext;
+use crate::engine::upload::{PendingUpload, Uploader};
+use crate::engine::watchdog::FrameWatchdog;
+use crate::engine::profiler::{self, Profiler};
+
+/// Per-frame user callback, invoked before the scene is drawn.
+///
+/// Receives the renderer (to move the camera, edit the scene, change settings) and the
+/// frame clock (for delta time).
+pub type UpdateCallback = Box<dyn FnMut(&mut Renderer, &Clock)>;
+
+/// Window and input event handler, see [`Renderer::on_event`].
+pub type EventCallback = Box<dyn FnMut(&mut Renderer, &EngineEvent)>;
+
+/// Per-frame UI callback, see [`Renderer::on_ui`].
+pub type UiCallback = Box<dyn FnMut(&mut Renderer, &mut UiContext)>;
+
+/// Name of the input context the UI pushes while it uses the mouse or keyboard.
+pub const UI_INPUT_CONTEXT: &str = "ui";
+
+/// Identifies an event handler registered with [`Renderer::on_event`], to remove it again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EventHandlerId(u64);
+
+/// What the renderer does with OBJ and glTF files dropped onto its window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModelDrop {
+    /// Dropped files are ignored.
+    Ignore,
+    /// The dropped model replaces the previously dropped one; nodes added by the
+    /// application (lights, ground, ...) stay.
+    #[default]
+    Replace,
+    /// The dropped model is added alongside everything already in the scene.
+    Add,
+}
+
+/// How the renderer rasterizes scene geometry, for inspecting meshes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PolygonMode {
+    /// Filled triangles.
+    #[default]
+    Fill,
+    /// Triangle edges only (wireframe).
+    Line,
+    /// Vertices only, at each material's point size.
+    Point,
+}
+
+impl PolygonMode {
+    fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
 
 /// `Renderer` encapsulates the OpenGL rendering context,
 /// window creation, event handling loop, and basic rendering operations.
@@ -30,103 +135,489 @@ use crate::engine::object3d::{GLMesh, Object3D};
 /// - OpenGL functions are loaded dynamically using the `gl` crate's loader mechanism.
 /// - The `run` method drives the main event loop, processing events such as window close,
 ///   redraw, and requesting redraws efficiently.
+/// - A [`Clock`] is ticked every frame and passed, together with the renderer itself,
+///   to the optional update callback registered with [`on_update`](Renderer::on_update).
 ///
 /// # Threading & Ownership
 ///
-/// The struct owns the event loop and windowed context, and moves ownership into
-/// the `run` method. `Rc<RefCell<_>>` is used internally to enable multiple closures
-/// to access and mutate the context inside the event loop.
+/// The struct owns the event loop and windowed context. `run` takes the event loop out
+/// and moves the rest of the renderer into the event loop closure, so the update callback
+/// can freely access and mutate it.
 ///
 /// # Example Usage
 ///
 /// ```no_run
+/// # use rustge::engine::{math::color::Color, renderer::Renderer};
 /// let mut renderer = Renderer::new("Example", 800, 600);
-/// renderer.set_clear_color(0.0, 0.0, 0.0, 1.0);
+/// renderer.set_clear_color(Color::BLACK);
+/// renderer.on_update(|renderer, clock| {
+///     if let Some(camera) = renderer.get_camera_mut() {
+///         camera.position[0] = clock.elapsed().sin() as f32;
+///     }
+/// });
 /// renderer.run();
 /// ```
 pub struct Renderer {
-    /// The event loop responsible for driving window events and rendering
-    event_loop: EventLoop<()>,
+    /// The event loop responsible for driving window events and rendering.
+    /// Taken out when `run` starts.
+    event_loop: Option<EventLoop<()>>,
 
     /// The OpenGL context tied to a window, currently in the `PossiblyCurrent` state,
     /// meaning OpenGL commands can be issued.
     windowed_context: ContextWrapper<PossiblyCurrent, Window>,
 
-    /// The color used to clear the OpenGL framebuffer each frame, stored as RGBA floats.
-    clear_color: [f32; 4],
+    /// The color used to clear the OpenGL framebuffer each frame.
+    clear_color: Color,
+
+    /// Whether depth testing (GL_DEPTH_TEST) is enabled.
+    depth_test: bool,
+
+    /// How scene geometry is rasterized.
+    polygon_mode: PolygonMode,
 
     /// what camera are we rendering from?
     camera: Option<Camera>,
 
     /// What scene are we rendering?
-    scene: Option<Object3D>
+    scene: Option<Scene>,
+
+    /// Frame timing, ticked once per frame.
+    clock: Clock,
+
+    /// User callback run every frame before drawing.
+    update_callback: Option<UpdateCallback>,
+
+    /// User callback building the UI every frame, after the update callback.
+    ui_callback: Option<UiCallback>,
+
+    /// Immediate-mode UI state and draw list, taken out while the UI callback runs.
+    ui: UiContext,
+
+    /// Event handlers in registration order; a handler is taken out while it runs.
+    event_handlers: Vec<(EventHandlerId, Option<EventCallback>)>,
+
+    /// Id given to the next event handler.
+    next_event_handler: u64,
+
+    /// Turns window events into engine events.
+    event_translator: EventTranslator,
+
+    /// Input contexts, fed with every input event; the base context holds the game's action
+    /// and axis bindings.
+    input_contexts: InputContextStack,
+
+    /// Entities and components of data-oriented game logic.
+    world: World,
+
+    /// Systems run over `world` every frame, after the update callback.
+    schedule: Schedule,
+
+    /// Tweens advanced every frame, after the update callback.
+    tweens: TweenManager,
+
+    /// Rigid body simulation of the scene, stepped every frame after the systems.
+    physics: PhysicsWorld,
+
+    /// Engine extensions, updated at each frame stage; a plugin is taken out while it runs.
+    plugins: PluginRegistry,
+
+    /// Where each frame's scene snapshot goes; `None` until a reader is requested.
+    snapshot_publisher: Option<SnapshotPublisher>,
+
+    /// Records the passes of each frame and their timings.
+    frame_graph: FrameGraph,
+
+    /// Whether the pass timing overlay is drawn.
+    pass_overlay: bool,
+
+    /// Per-frame CPU scopes, pass timings and counters.
+    profiler: Profiler,
+
+    /// Whether the profiler overlay is drawn.
+    profiler_overlay: bool,
+
+    /// Text batch used by the debug overlays.
+    overlay_text: TextBatch,
+
+    /// Debug lines and labels submitted this frame.
+    debug_draw: DebugDraw,
+
+    /// Optional mouse-driven controller moving the active camera.
+    camera_controller: Option<OrbitController>,
+
+    /// Logs a breakdown of frames slower than its threshold.
+    frame_watchdog: FrameWatchdog,
+
+    /// How model files dropped onto the window are handled.
+    model_drop: ModelDrop,
+
+    /// Models loaded from dropped files and still in the scene.
+    dropped_models: Vec<Rc<RefCell<Object3D>>>,
+
+    /// Selected nodes, outlined every frame.
+    selection: Selection,
+
+    /// Mouse gesture tracking for click and box selection; `None` when disabled.
+    selection_input: Option<SelectionInput>,
+
+    /// Node id buffer for picking, created on the first pick.
+    picking: PickingBuffer,
+
+    /// How selected nodes are outlined.
+    outline_style: OutlineStyle,
+
+    /// Files to save the current frame to once it is drawn.
+    screenshot_requests: Vec<PathBuf>,
+
+    /// Screenshots being copied back from the GPU.
+    pending_screenshots: Vec<(PathBuf, Readback)>,
+
+    /// How the window is presented, as last requested.
+    fullscreen_mode: FullscreenMode,
+
+    /// Global texture sharpness.
+    texture_lod: TextureLod,
+
+    /// Render quality settings, as last set.
+    quality: QualitySettings,
+
+    /// Full-screen effects applied to each frame.
+    post_chain: PostChain,
+
+    /// Offscreen targets the scene is drawn into while MSAA or post effects are on.
+    scene_targets: Option<SceneTargets>,
+
+    /// Screen-space motion of each frame, rendered after the scene while enabled.
+    motion_vectors: Option<MotionVectors>,
+
+    /// How the exposure adapts to the scene's brightness; `None` keeps the camera's.
+    eye_adaptation: Option<EyeAdaptation>,
+
+    /// Measures frame brightness and tracks the adapted exposure.
+    exposure_meter: ExposureMeter,
+
+    /// Texture and mesh uploads, on the upload thread when there is one.
+    uploader: Uploader,
+
+    /// Shaders and textures reloaded when their files change.
+    hot_reload: HotReload,
+
+    /// Where external editors connect to patch the scene; `None` until enabled.
+    live_sync: Option<LiveSync>,
+
+    /// Whether skinned meshes are skinned once per frame in a compute pass.
+    compute_skinning: bool,
+
+    /// Whether buffer swaps wait for the vertical blank, as last requested.
+    vsync: bool,
+
+    /// Frame rate cap enforced by waiting between frames; `None` for no cap.
+    target_fps: Option<u32>,
+
+    /// When the frame limiter lets the next frame start.
+    next_frame: Option<Instant>,
 }
 
-impl Renderer {
-    /// Creates a new `Renderer` instance with the specified window title, width, and height.
+/// Window and OpenGL context settings for creating a [`Renderer`], from
+/// [`Renderer::builder`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererBuilder {
+    title: String,
+    size: [u32; 2],
+    vsync: bool,
+    resizable: bool,
+    fullscreen: FullscreenMode,
+    gl_version: Option<(u8, u8)>,
+    srgb: bool,
+    quality: QualitySettings,
+    async_uploads: bool,
+    program_cache: Option<PathBuf>,
+    gl_debug: GlDebug,
+}
+
+impl Default for RendererBuilder {
+    /// An 800x600 resizable window titled "rustge", with vsync, an sRGB-capable framebuffer
+    /// and the newest OpenGL version the driver offers, with a debug context in debug builds.
+    fn default() -> Self {
+        Self {
+            title: "rustge".to_string(),
+            size: [800, 600],
+            vsync: true,
+            resizable: true,
+            fullscreen: FullscreenMode::Windowed,
+            gl_version: None,
+            srgb: true,
+            quality: QualitySettings::default(),
+            async_uploads: false,
+            program_cache: None,
+            gl_debug: GlDebug::default(),
+        }
+    }
+}
+
+impl RendererBuilder {
+    /// Sets the window title.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Sets the initial size of the window's content area in physical pixels.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = [width, height];
+        self
+    }
+
+    /// Whether buffer swaps wait for the display's vertical refresh (default on). Turning
+    /// it off allows frame rates above the refresh rate, at the cost of tearing.
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Whether the user can resize the window (default on).
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    /// How the window starts out (default windowed); monitors without an index mean the
+    /// primary monitor. The size only applies once the window is windowed.
+    pub fn fullscreen(mut self, fullscreen: FullscreenMode) -> Self {
+        self.fullscreen = fullscreen;
+        self
+    }
+
+    /// Requests a specific OpenGL version with the core profile, instead of the newest
+    /// version the driver offers. The engine needs at least 3.3.
+    pub fn gl_version(mut self, major: u8, minor: u8) -> Self {
+        self.gl_version = Some((major, minor));
+        self
+    }
+
+    /// Whether to run the [sRGB pipeline](crate::engine::color_space) (default on): an
+    /// sRGB-capable default framebuffer with `GL_FRAMEBUFFER_SRGB` enabled and sRGB color
+    /// textures. Off selects the legacy pipeline, where shaders encode sRGB themselves; it is
+    /// also used, with a warning, when the driver offers no sRGB framebuffer.
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Sets the initial render quality (default the [`High`](QualityPreset::High) preset).
+    pub fn quality(mut self, quality: impl Into<QualitySettings>) -> Self {
+        self.quality = quality.into();
+        self
+    }
+
+    /// Whether to create a second context, sharing objects with the window's, for
+    /// [uploading textures and meshes on another thread](crate::engine::upload) (default
+    /// off). If the driver can't share contexts, uploads happen on the render thread.
+    pub fn async_uploads(mut self, async_uploads: bool) -> Self {
+        self.async_uploads = async_uploads;
+        self
+    }
+
+    /// Caches linked shader programs in `directory`, so later runs load them instead of
+    /// compiling them again (off by default); see [`program_cache`](crate::engine::program_cache).
+    pub fn program_cache(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.program_cache = Some(directory.into());
+        self
+    }
+
+    /// Sets what happens to the driver's [debug output](crate::engine::debug::gl_debug):
+    /// nothing, logged, or abort on errors (by default logged in debug builds and off in
+    /// release builds, where a debug context may run slower).
+    pub fn gl_debug(mut self, gl_debug: GlDebug) -> Self {
+        self.gl_debug = gl_debug;
+        self
+    }
+
+    /// Creates the window and OpenGL context and makes the context current on this thread.
     ///
-    /// # Parameters
-    /// - `title`: The window title shown in the title bar.
-    /// - `width`: The initial width of the window in physical pixels.
-    /// - `height`: The initial height of the window in physical pixels.
+    /// The context always has a 24-bit depth buffer and an 8-bit stencil buffer (used by
+    /// selection outlines).
     ///
     /// # Panics
     /// Panics if window or OpenGL context creation fails.
-    ///
-    /// # Detailed Explanation
-    /// 1. Initializes the event loop needed for window events and input.
-    /// 2. Configures a window builder with title and size.
-    /// 3. Creates an OpenGL context tied to this window with vsync enabled to avoid tearing.
-    /// 4. Makes the OpenGL context current on the thread to allow GL calls.
-    /// 5. Loads all OpenGL function pointers dynamically via the context.
-    /// 6. Sets a default clear color (dark blueish).
-    ///
-    /// This setup ensures that the OpenGL context is properly initialized and ready
-    /// for rendering commands.
-    pub fn new(title: &str, width: u32, height: u32) -> Self {
+    pub fn build(self) -> Renderer {
         // Create the event loop instance for handling window and input events
         let event_loop = EventLoop::new();
 
-        // Build a window with specified title and inner size (content area size)
+        // Build a window with the title and inner size (content area size)
+        let fullscreen = {
+            let monitors = primary_first(event_loop.available_monitors().collect(), event_loop.primary_monitor());
+            self.fullscreen.to_winit(monitors, event_loop.primary_monitor())
+        };
         let wb = WindowBuilder::new()
-            .with_title(title)
-            .with_inner_size(PhysicalSize::new(width, height));
+            .with_title(&self.title)
+            .with_inner_size(PhysicalSize::new(self.size[0], self.size[1]))
+            .with_resizable(self.resizable)
+            .with_fullscreen(fullscreen);
+
+        // A depth buffer so 3D geometry occludes correctly, and a stencil buffer for outlines
+        let gl_request = match self.gl_version {
+            Some(version) => GlRequest::Specific(Api::OpenGl, version),
+            None => GlRequest::Latest,
+        };
+        let mut context = ContextBuilder::new()
+            .with_gl(gl_request)
+            .with_vsync(self.vsync)
+            .with_srgb(self.srgb)
+            .with_depth_buffer(24)
+            .with_stencil_buffer(8)
+            .with_gl_debug_flag(self.gl_debug != GlDebug::Off);
+        if self.gl_version.is_some_and(|version| version >= (3, 2)) {
+            context = context.with_gl_profile(GlProfile::Core);
+        }
+        let windowed_context = context.build_windowed(wb, &event_loop).unwrap();
 
-        // Create a windowed OpenGL context with vsync enabled to sync buffer swaps to display refresh
-        let windowed_context = ContextBuilder::new()
-            .with_vsync(true)
-            .build_windowed(wb, &event_loop)
-            .unwrap();
+        // A context for the upload thread, created with the same settings, sharing objects
+        let upload_context = self.async_uploads.then(|| {
+            let mut context = ContextBuilder::new().with_gl(gl_request).with_shared_lists(windowed_context.context());
+            if self.gl_version.is_some_and(|version| version >= (3, 2)) {
+                context = context.with_gl_profile(GlProfile::Core);
+            }
+            context.build_headless(&event_loop, PhysicalSize::new(1, 1)).map_err(|error| {
+                eprintln!("Warning: Could not create a shared context for uploads ({error}); uploading on the render thread");
+            })
+        });
 
         // Make the OpenGL context current on this thread; required before issuing GL calls
         let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+        let srgb_framebuffer = windowed_context.get_pixel_format().srgb;
+
+        // Before the renderer builds its first programs
+        if let Some(directory) = &self.program_cache
+            && let Err(error) = program_cache::enable(directory)
+        {
+            eprintln!("Warning: Could not use {} for the shader program cache: {error}", directory.display());
+        }
+
+        let mut renderer = Renderer::from_context(event_loop, windowed_context);
+        // Before any texture, target or program is created in either format
+        if self.srgb && !srgb_framebuffer {
+            eprintln!("Warning: The driver offers no sRGB framebuffer; using the legacy color pipeline");
+        }
+        set_srgb_pipeline(self.srgb && srgb_framebuffer);
+        if self.gl_debug != GlDebug::Off && !gl_debug::install(self.gl_debug) {
+            eprintln!("Warning: The driver has no GL debug output (KHR_debug); use gl_check! to find GL errors");
+        }
+        if let Some(Ok(context)) = upload_context {
+            renderer.uploader = Uploader::threaded(context);
+        }
+        renderer.fullscreen_mode = self.fullscreen;
+        renderer.vsync = self.vsync;
+        renderer.set_quality(self.quality);
+        renderer
+    }
+}
+
+impl Renderer {
+    /// Creates a new `Renderer` instance with the specified window title, width, and height,
+    /// and otherwise default window and context settings (see [`RendererBuilder`]).
+    ///
+    /// # Parameters
+    /// - `title`: The window title shown in the title bar.
+    /// - `width`: The initial width of the window in physical pixels.
+    /// - `height`: The initial height of the window in physical pixels.
+    ///
+    /// # Panics
+    /// Panics if window or OpenGL context creation fails.
+    pub fn new(title: &str, width: u32, height: u32) -> Self {
+        Self::builder().title(title).size(width, height).build()
+    }
+
+    /// Starts configuring a renderer's window and OpenGL context.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// let renderer = Renderer::builder()
+    ///     .title("Benchmark")
+    ///     .size(1280, 720)
+    ///     .vsync(false)
+    ///     .resizable(false)
+    ///     .gl_version(3, 3)
+    ///     .build();
+    /// ```
+    pub fn builder() -> RendererBuilder {
+        RendererBuilder::default()
+    }
 
+    /// Finishes setting up a renderer around a freshly created, current context.
+    ///
+    /// 1. Loads all OpenGL function pointers dynamically via the context.
+    /// 2. Sets a default clear color (dark blueish) and enables depth testing.
+    /// 3. Creates an empty [`Scene`] to add objects to.
+    fn from_context(event_loop: EventLoop<()>, windowed_context: ContextWrapper<PossiblyCurrent, Window>) -> Self {
         // Load all OpenGL function pointers using the context's proc address loader
         gl::load_with(|symbol| windowed_context.get_proc_address(symbol) as *const _);
 
         // Set the default clear color to a pleasant dark blue shade
-        let clear_color = [0.1, 0.2, 0.3, 1.0];
+        let clear_color = Color::srgb(0.1, 0.2, 0.3);
+        apply_clear_color(clear_color);
         unsafe {
-            gl::ClearColor(clear_color[0], clear_color[1], clear_color[2], clear_color[3]);
+            // Nearer fragments win; the depth buffer is cleared to the far plane each frame
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::ClearDepth(1.0);
         }
 
-        let mut instance: Self = Self {
-            event_loop,
+        Self {
+            event_loop: Some(event_loop),
             windowed_context,
             clear_color,
+            depth_test: true,
+            polygon_mode: PolygonMode::default(),
             camera: None,
-            scene: None,
-        };
-
-        let obj_rc = Object3D::new();
-        let obj_raw = Rc::try_unwrap(obj_rc)
-            .expect("Object3D::new() should be uniquely owned")
-            .into_inner();
-
-        instance.set_scene(obj_raw);
-        
-        
-        instance
+            scene: Some(Scene::new()),
+            clock: Clock::new(),
+            update_callback: None,
+            ui_callback: None,
+            ui: UiContext::new(),
+            event_handlers: Vec::new(),
+            next_event_handler: 0,
+            event_translator: EventTranslator::default(),
+            input_contexts: InputContextStack::default(),
+            world: World::new(),
+            schedule: Schedule::new(),
+            tweens: TweenManager::new(),
+            physics: PhysicsWorld::new(),
+            plugins: PluginRegistry::default(),
+            snapshot_publisher: None,
+            frame_graph: FrameGraph::new(),
+            pass_overlay: false,
+            profiler: Profiler::new(),
+            profiler_overlay: false,
+            overlay_text: TextBatch::new(),
+            debug_draw: DebugDraw::new(),
+            camera_controller: None,
+            frame_watchdog: FrameWatchdog::new(),
+            model_drop: ModelDrop::default(),
+            dropped_models: Vec::new(),
+            selection: Selection::new(),
+            selection_input: None,
+            picking: PickingBuffer::new(),
+            outline_style: OutlineStyle::default(),
+            screenshot_requests: Vec::new(),
+            pending_screenshots: Vec::new(),
+            fullscreen_mode: FullscreenMode::Windowed,
+            texture_lod: TextureLod::default(),
+            quality: QualitySettings::default(),
+            post_chain: PostChain::new(),
+            eye_adaptation: None,
+            exposure_meter: ExposureMeter::new(),
+            uploader: Uploader::synchronous(),
+            hot_reload: HotReload::new(),
+            live_sync: None,
+            scene_targets: None,
+            motion_vectors: None,
+            compute_skinning: false,
+            vsync: true,
+            target_fps: None,
+            next_frame: None,
+        }
     }
 
     /// Sets the camera to be used for rendering the scene.
@@ -140,25 +631,31 @@ impl Renderer {
     /// # Behavior
     /// - Assigns the provided camera to the renderer.
     /// - If no camera was previously set, this initializes the rendering viewpoint.
+    /// - Fits the camera's aspect ratio to the window, now and whenever the window is
+    ///   resized, unless its [`aspect_mode`](Camera::aspect_mode) is fixed.
     /// - The camera will be used on the next render cycle in the event loop.
     ///
     /// # Example
     /// ```no_run
-    /// let camera = Camera::new(...);
+    /// # use rustge::engine::{camera::{AspectMode, Camera}, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let mut camera = Camera::new(1.0);
+    /// camera.aspect_mode = AspectMode::Window;
     /// renderer.set_camera(camera);
     /// ```
-    pub fn set_camera(&mut self, camera: Camera) {
+    pub fn set_camera(&mut self, mut camera: Camera) {
+        camera.fit_aspect(self.window_size());
         self.camera = Some(camera);
     }
 
-    /// Sets the 3D scene (root object) to be rendered.
+    /// Sets the 3D scene to be rendered.
     ///
-    /// This method assigns the root `Object3D` representing the scene graph to render.
-    /// The scene typically contains meshes, transformations, and child objects. Updating
+    /// This method assigns the [`Scene`] whose graph and background are drawn. The scene
+    /// typically contains meshes, transformations, lights, and child objects. Updating
     /// the scene here will change what is drawn each frame.
     ///
     /// # Parameters
-    /// - `scene`: The root `Object3D` of the scene graph to render.
+    /// - `scene`: The scene to render.
     ///
     /// # Behavior
     /// - Stores the scene object inside the renderer.
@@ -167,124 +664,1552 @@ impl Renderer {
     ///
     /// # Example
     /// ```no_run
-    /// let scene_root = Object3D::new(...);
-    /// renderer.set_scene(scene_root);
+    /// # use rustge::engine::{object3d::Object3D, renderer::Renderer, scene::Scene};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let scene = Scene::new();
+    /// scene.add(Object3D::new());
+    /// renderer.set_scene(scene);
     /// ```
-    pub fn set_scene(&mut self, scene: Object3D) {
+    pub fn set_scene(&mut self, scene: Scene) {
         self.scene = Some(scene);
     }
 
-    pub fn get_scene(&self) -> Option<&Object3D> {
+    /// Returns the scene being rendered, if any.
+    pub fn get_scene(&self) -> Option<&Scene> {
         self.scene.as_ref()
     }
 
+    /// Returns the scene mutably, e.g. to change its background from the update callback.
+    pub fn get_scene_mut(&mut self) -> Option<&mut Scene> {
+        self.scene.as_mut()
+    }
 
-    /// Clears the current OpenGL framebuffer using the stored clear color.
-    ///
-    /// # Safety
-    /// This function calls the unsafe OpenGL `glClear` command, which
-    /// relies on a valid current OpenGL context.
-    ///
-    /// # Usage
-    /// Call before rendering a new frame to reset the framebuffer.
-    pub fn clear(&self) {
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-        }
+    /// Returns the active camera, if one is set.
+    pub fn get_camera(&self) -> Option<&Camera> {
+        self.camera.as_ref()
     }
 
-    /// Swaps the front and back buffers, presenting the rendered frame to the window.
+    /// Returns the active camera mutably, e.g. to move it from the update callback.
+    pub fn get_camera_mut(&mut self) -> Option<&mut Camera> {
+        self.camera.as_mut()
+    }
+
+    /// Attaches a camera controller that receives window mouse input and moves the active
+    /// camera every frame (after the update callback), or detaches it with `None`.
     ///
-    /// # Panics
-    /// Panics if buffer swap fails, which can indicate a context or window error.
-    pub fn swap_buffers(&self) {
-        self.windowed_context.swap_buffers().unwrap();
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{camera::{Camera, OrbitController}, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let camera = Camera::new(4.0 / 3.0);
+    /// renderer.set_camera_controller(Some(OrbitController::from_camera(&camera, [0.0, 0.0, 0.0])));
+    /// renderer.set_camera(camera);
+    /// ```
+    pub fn set_camera_controller(&mut self, controller: Option<OrbitController>) {
+        self.camera_controller = controller;
     }
 
-    /// Updates the OpenGL clear color to the specified RGBA value and stores it internally.
+    /// Returns the attached camera controller, e.g. to retarget it.
+    pub fn camera_controller_mut(&mut self) -> Option<&mut OrbitController> {
+        self.camera_controller.as_mut()
+    }
+
+    /// Sets what happens when an OBJ or glTF file is dropped onto the window. By default the
+    /// model is loaded, replaces the previously dropped model and the camera is moved to
+    /// frame it (retargeting the camera controller, if any).
     ///
-    /// # Parameters
-    /// - `r`, `g`, `b`, `a`: Color components as floating-point values between 0.0 and 1.0.
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::{ModelDrop, Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// // A game, not a viewer: don't let players drop models into the level
+    /// renderer.set_model_drop(ModelDrop::Ignore);
+    /// ```
+    pub fn set_model_drop(&mut self, mode: ModelDrop) {
+        self.model_drop = mode;
+    }
+
+    /// How dropped model files are handled.
+    pub fn model_drop(&self) -> ModelDrop {
+        self.model_drop
+    }
+
+    /// Models loaded from files dropped onto the window that are still in the scene.
+    pub fn dropped_models(&self) -> &[Rc<RefCell<Object3D>>] {
+        &self.dropped_models
+    }
+
+    /// The selected nodes, outlined every frame. Inspectors and gizmos read it from here.
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// Mutable access to the selection, e.g. to select nodes from a scene tree panel.
+    pub fn selection_mut(&mut self) -> &mut Selection {
+        &mut self.selection
+    }
+
+    /// Enables or disables selecting with the mouse: clicking selects the node under the
+    /// cursor (or clears the selection on empty space), shift-click toggles a node, and
+    /// ctrl-dragging selects every node visible inside the box. Plain drags still reach the
+    /// camera controller. Disabled by default.
     ///
-    /// This will affect the color used in subsequent `clear` calls.
-    pub fn set_clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
-        self.clear_color = [r, g, b, a];
-        unsafe {
-            gl::ClearColor(r, g, b, a);
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{math::color::Color, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.set_click_selection(true);
+    /// renderer.on_update(|renderer, _| {
+    ///     if let Some(node) = renderer.selection().primary() {
+    ///         let position = node.borrow().position();
+    ///         renderer.debug_draw().label(position, Color::WHITE, "selected");
+    ///     }
+    /// });
+    /// ```
+    pub fn set_click_selection(&mut self, enabled: bool) {
+        if enabled != self.selection_input.is_some() {
+            self.selection_input = enabled.then(SelectionInput::new);
         }
     }
 
-    /// Resizes the window to the specified width and height in physical pixels.
+    /// Whether mouse selection is enabled.
+    pub fn click_selection(&self) -> bool {
+        self.selection_input.is_some()
+    }
+
+    /// The node drawn at `pixel` (window pixels, origin top-left) from the current camera,
+    /// using the GPU picking buffer. `None` on empty space or without a camera.
+    pub fn pick(&mut self, pixel: [u32; 2]) -> Option<Rc<RefCell<Object3D>>> {
+        self.pick_rect(pixel, pixel).into_iter().next()
+    }
+
+    /// Every node visible inside the rectangle between the window pixels `a` and `b`.
+    pub fn pick_rect(&mut self, a: [u32; 2], b: [u32; 2]) -> Vec<Rc<RefCell<Object3D>>> {
+        let (Some(camera), Some(scene)) = (&self.camera, &self.scene) else {
+            return Vec::new();
+        };
+        let size = self.windowed_context.window().inner_size();
+        self.picking.render(scene.root(), camera, [size.width, size.height]);
+        self.picking.nodes_in_rect(a, b)
+    }
+
+    /// Like [`pick_rect`](Self::pick_rect), but returns without waiting for the GPU to draw
+    /// the id buffer. Poll the result on later frames, e.g. for hover highlighting that
+    /// shouldn't stall every frame.
+    pub fn pick_rect_async(&mut self, a: [u32; 2], b: [u32; 2]) -> PendingPick {
+        if let (Some(camera), Some(scene)) = (&self.camera, &self.scene) {
+            let size = self.windowed_context.window().inner_size();
+            self.picking.render(scene.root(), camera, [size.width, size.height]);
+        }
+        self.picking.nodes_in_rect_async(a, b)
+    }
+
+    /// Sets how selected nodes are outlined.
+    pub fn set_outline_style(&mut self, style: OutlineStyle) {
+        self.outline_style = style;
+    }
+
+    /// How selected nodes are outlined.
+    pub fn outline_style(&self) -> OutlineStyle {
+        self.outline_style
+    }
+
+    /// Returns the frame clock (delta time, elapsed time, frame count, FPS).
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    /// Registers the per-frame update callback, replacing any previous one.
     ///
-    /// # Parameters
-    /// - `width`: New window width in physical pixels.
-    /// - `height`: New window height in physical pixels.
+    /// The callback runs once per frame, after the clock is ticked and before the scene
+    /// is drawn. Use `clock.delta()` to make movement and animation framerate-independent.
     ///
-    /// This method requests the underlying window to resize,
-    /// which will typically trigger a redraw event.
-    pub fn set_size(&self, width: u32, height: u32) {
-        self.windowed_context.window().set_inner_size(PhysicalSize::new(width, height));
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.on_update(|renderer, clock| {
+    ///     if clock.frame_count() % 60 == 0 {
+    ///         println!("{:.1} fps", clock.fps());
+    ///     }
+    ///     if let Some(scene) = renderer.get_scene() {
+    ///         let angle = clock.elapsed() as f32;
+    ///         scene.root().borrow_mut().set_rotation([0.0, (angle / 2.0).sin(), 0.0, (angle / 2.0).cos()]);
+    ///     }
+    /// });
+    /// ```
+    pub fn on_update<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Renderer, &Clock) + 'static,
+    {
+        self.update_callback = Some(Box::new(callback));
     }
 
-    /// Starts the renderer's event loop, handling window events and redraw requests.
+    /// Registers the per-frame UI callback, replacing any previous one (see
+    /// [`ui`](crate::engine::ui)).
     ///
-    /// This method **never returns** until the window is closed by the user or the event loop exits.
-    /// It processes:
-    /// - `WindowEvent::CloseRequested`: Exits the application.
-    /// - `Event::RedrawRequested`: Clears the framebuffer and swaps buffers to present the frame.
+    /// The callback runs once per frame, after the update callback, and the widgets it
+    /// creates are drawn last, over the scene, post effects and overlays. While the cursor is
+    /// over the UI or a text field has focus, the UI keeps mouse or keyboard input from the
+    /// camera controller, selection and the game's input map.
     ///
-    /// It also ensures the window continuously requests redraws,
-    /// driving a rendering loop at the native vsync rate.
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let mut spin_speed = 1.0;
+    /// renderer.on_ui(move |renderer, ui| {
+    ///     ui.panel("Debug", [10.0, 10.0], |ui| {
+    ///         ui.label(&format!("{:.1} fps", renderer.clock().fps()));
+    ///         ui.slider("spin speed", &mut spin_speed, 0.0..=5.0);
+    ///         if ui.button("Screenshot") {
+    ///             renderer.save_screenshot("screenshot.png");
+    ///         }
+    ///     });
+    /// });
+    /// ```
+    pub fn on_ui<F>(&mut self, callback: F)
+    where
+        F: FnMut(&mut Renderer, &mut UiContext) + 'static,
+    {
+        self.ui_callback = Some(Box::new(callback));
+    }
+
+    /// Registers a handler for window and input events (see [`event`](crate::engine::event)),
+    /// in addition to any registered before. Handlers run in registration order as events
+    /// arrive, before the renderer's own handling.
     ///
-    /// # Detailed Design Notes
-    /// - Wraps the `windowed_context` in `Rc<RefCell<_>>` to allow mutable access
-    ///   inside the closure passed to the event loop.
-    /// - Sets the control flow to `ControlFlow::Wait` to efficiently sleep until new events.
-    /// - On each redraw event, clears and swaps buffers to update the screen.
-    /// - Requests redraw on every iteration to keep the rendering loop alive.
-    pub fn run(self) {
-        let Renderer {
-            event_loop,
-            windowed_context,
-            clear_color: _,
-            camera,
-            scene,
-        } = self;
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{event::{EngineEvent, Key}, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let handler = renderer.on_event(|_, event| {
+    ///     if let EngineEvent::Key { key: Key::Space, .. } = event {
+    ///         println!("Jump");
+    ///     }
+    /// });
+    /// // Later, e.g. when a cutscene starts
+    /// renderer.remove_event_handler(handler);
+    /// ```
+    pub fn on_event<F>(&mut self, handler: F) -> EventHandlerId
+    where
+        F: FnMut(&mut Renderer, &EngineEvent) + 'static,
+    {
+        let id = EventHandlerId(self.next_event_handler);
+        self.next_event_handler += 1;
+        self.event_handlers.push((id, Some(Box::new(handler))));
+        id
+    }
+
+    /// The action and axis bindings, with the input state of the current frame (see
+    /// [`input`](crate::engine::input)).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{event::Key, input::{AxisBinding, Input}, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.input_mut().bind_action("jump", Input::Key(Key::Space));
+    /// renderer.input_mut().bind_axis("move_forward", AxisBinding::keys(Key::W, Key::S));
+    /// renderer.on_update(|renderer, clock| {
+    ///     let speed = renderer.input().axis("move_forward") * 5.0;
+    ///     if let Some(camera) = renderer.get_camera_mut() {
+    ///         camera.position[2] -= speed * clock.delta();
+    ///     }
+    /// });
+    /// ```
+    pub fn input(&self) -> &InputMap {
+        self.input_contexts.base()
+    }
 
-        let context = Rc::new(RefCell::new(windowed_context));
-        let camera = Rc::new(RefCell::new(camera));
-        let scene = Rc::new(RefCell::new(scene));
+    /// Mutable access to the input map, to change bindings or feed gamepad state.
+    pub fn input_mut(&mut self) -> &mut InputMap {
+        self.input_contexts.base_mut()
+    }
 
-        event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Wait;
+    /// The input contexts layered over the game's (see
+    /// [`input_context`](crate::engine::input_context)); the camera controller and selection
+    /// tools only see events reaching the base context.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{event::{ButtonState, EngineEvent, Key}, input::Input, renderer::Renderer};
+    /// # use rustge::engine::input_context::{Capture, InputContext};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.on_event(|renderer, event| {
+    ///     if let EngineEvent::Key { key: Key::Grave, state: ButtonState::Pressed, repeat: false, .. } = event {
+    ///         let contexts = renderer.input_contexts_mut();
+    ///         if contexts.remove("console").is_none() {
+    ///             contexts.push(InputContext::new("console", Capture::KEYBOARD).pass_through(Input::Key(Key::Grave)));
+    ///         }
+    ///     }
+    /// });
+    /// ```
+    pub fn input_contexts(&self) -> &InputContextStack {
+        &self.input_contexts
+    }
 
-            match event {
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                    _ => {}
-                },
-
-                Event::RedrawRequested(_) => {
-                    unsafe {
-                        gl::Clear(gl::COLOR_BUFFER_BIT);
-                    }
+    /// Mutable access to the input contexts, to push and pop them.
+    pub fn input_contexts_mut(&mut self) -> &mut InputContextStack {
+        &mut self.input_contexts
+    }
 
-                    let cam_ref = camera.borrow();
-                    let mut scene_ref = scene.borrow_mut();
+    /// A reader of the scene snapshots published every frame after the updates, for audio,
+    /// networking or recording threads (see [`snapshot`](crate::engine::snapshot)). Snapshots
+    /// are only taken once a reader has been requested.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// let reader = renderer.snapshot_reader();
+    /// std::thread::spawn(move || loop {
+    ///     let snapshot = reader.latest();
+    ///     if let Some(camera) = &snapshot.camera {
+    ///         // Position the audio listener
+    ///         let _ = camera.position;
+    ///     }
+    ///     std::thread::sleep(std::time::Duration::from_millis(10));
+    /// });
+    /// renderer.run();
+    /// ```
+    pub fn snapshot_reader(&mut self) -> SnapshotReader {
+        if let Some(publisher) = &self.snapshot_publisher {
+            return publisher.reader();
+        }
+        let (publisher, reader) = snapshot_channel();
+        self.snapshot_publisher = Some(publisher);
+        reader
+    }
 
-                    if let (Some(cam), Some(scene)) = (&*cam_ref, &mut *scene_ref) {
-                        scene.draw(cam);
-                    }
+    /// The entity-component world (see [`ecs`](crate::engine::ecs)).
+    pub fn world(&self) -> &World {
+        &self.world
+    }
 
-                    context.borrow().swap_buffers().unwrap();
-                }
+    /// The entity-component world, mutably, e.g. to spawn entities from the update callback.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
 
-                _ => {}
-            }
+    /// Adds a system run over the [`world`](Self::world) every frame, after the update
+    /// callback and before the scene update. Systems run in the order they were added; one
+    /// added with the name of an existing system replaces it. Afterwards, entity transforms
+    /// are synced with their scene nodes (see [`sync_scene`]).
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{ecs::Transform, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.add_system("spin", |world, clock| {
+    ///     let angle = clock.elapsed() as f32;
+    ///     for (_, transform) in world.write::<Transform>().iter_mut() {
+    ///         transform.rotation = [0.0, (angle / 2.0).sin(), 0.0, (angle / 2.0).cos()];
+    ///     }
+    /// });
+    /// ```
+    pub fn add_system<F>(&mut self, name: &str, system: F)
+    where
+        F: FnMut(&mut World, &Clock) + 'static,
+    {
+        self.schedule.add_system(name, system);
+    }
 
-            // Continuously redraw at vsync rate
-            context.borrow().window().request_redraw();
-        });
+    /// Removes the system named `name`. Returns `false` if there was none.
+    pub fn remove_system(&mut self, name: &str) -> bool {
+        self.schedule.remove_system(name)
     }
 
+    /// The tweens playing (see [`tween`](crate::engine::tween)).
+    pub fn tweens(&self) -> &TweenManager {
+        &self.tweens
+    }
+
+    /// The tween manager, to start or cancel tweens. It is advanced every frame by the
+    /// clock's delta, after the update callback and before the systems.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{object3d::Object3D, renderer::Renderer, tween::{Easing, Tween}};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// # let door = Object3D::new();
+    /// let open = Tween::position(&door, [0.0, 3.0, 0.0], 1.5).ease(Easing::EaseInOutCubic).delay(0.5);
+    /// renderer.tweens_mut().add(open);
+    /// ```
+    pub fn tweens_mut(&mut self) -> &mut TweenManager {
+        &mut self.tweens
+    }
+
+    /// The rigid body simulation (see [`physics`](crate::engine::physics)).
+    pub fn physics(&self) -> &PhysicsWorld {
+        &self.physics
+    }
+
+    /// The rigid body simulation, e.g. to register collision callbacks. It steps the
+    /// scene's bodies every frame by the clock's delta, after the systems and before the
+    /// scene update.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.physics_mut().on_collision(|event| {
+    ///     if event.sensor {
+    ///         println!("entered trigger {:?}", event.b.borrow().id());
+    ///     }
+    /// });
+    /// ```
+    pub fn physics_mut(&mut self) -> &mut PhysicsWorld {
+        &mut self.physics
+    }
+
+    /// Unregisters an event handler. Returns `false` if it was already removed.
+    pub fn remove_event_handler(&mut self, id: EventHandlerId) -> bool {
+        let count = self.event_handlers.len();
+        self.event_handlers.retain(|(handler, _)| *handler != id);
+        self.event_handlers.len() != count
+    }
+
+    /// Adds `plugin` (see [`plugin`](crate::engine::plugin)), which starts up before the next
+    /// frame. Returns `false`, leaving the plugins unchanged, if one with the same
+    /// [name](Plugin::name) was added already.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{event::Key, input::Input, plugin::Plugin, renderer::Renderer};
+    /// struct Jumping;
+    ///
+    /// impl Plugin for Jumping {
+    ///     fn startup(&mut self, renderer: &mut Renderer) {
+    ///         renderer.input_mut().bind_action("jump", Input::Key(Key::Space));
+    ///     }
+    /// }
+    ///
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.add_plugin(Jumping);
+    /// ```
+    pub fn add_plugin(&mut self, plugin: impl Plugin) -> bool {
+        self.plugins.add(Box::new(plugin))
+    }
+
+    /// Removes the plugin named `name`, shutting it down if it was started. Returns `false`
+    /// if there was none.
+    pub fn remove_plugin(&mut self, name: &str) -> bool {
+        match self.plugins.remove(name) {
+            Some((plugin, started)) => {
+                // A running plugin is shut down once its hook returns
+                if started && let Some(mut plugin) = plugin {
+                    plugin.shutdown(self);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a plugin named `name` was added.
+    pub fn has_plugin(&self, name: &str) -> bool {
+        self.plugins.contains(name)
+    }
+
+    /// The plugin of type `T`, to read its state. `None` if there is none, or from the
+    /// plugin's own hooks, while it is taken out to run.
+    pub fn plugin<T: Plugin>(&self) -> Option<&T> {
+        self.plugins.get()
+    }
+
+    /// The plugin of type `T`, mutably, like [`plugin`](Self::plugin).
+    pub fn plugin_mut<T: Plugin>(&mut self) -> Option<&mut T> {
+        self.plugins.get_mut()
+    }
+
+
+    /// Clears the current OpenGL framebuffer's color and depth using the stored clear color.
+    ///
+    /// # Safety
+    /// This function calls the unsafe OpenGL `glClear` command, which
+    /// relies on a valid current OpenGL context.
+    ///
+    /// # Usage
+    /// Call before rendering a new frame to reset the framebuffer.
+    pub fn clear(&self) {
+        clear_framebuffer(self.clear_color);
+    }
+
+    /// Enables or disables depth testing for everything the renderer draws.
+    ///
+    /// Depth testing is enabled by default. Disabling it makes objects draw in submission
+    /// order regardless of distance, which is occasionally useful for overlays and debugging.
+    /// Individual materials can still opt out of depth testing while it is enabled.
+    pub fn set_depth_test(&mut self, enabled: bool) {
+        self.depth_test = enabled;
+        unsafe {
+            if enabled {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        }
+    }
+
+    /// Returns whether depth testing is enabled.
+    pub fn depth_test(&self) -> bool {
+        self.depth_test
+    }
+
+    /// Sets how scene geometry is rasterized: filled (the default), as wireframe, or as
+    /// points. Only the scene passes are affected; the background, outlines, debug draw and
+    /// overlays always draw filled.
+    pub fn set_polygon_mode(&mut self, mode: PolygonMode) {
+        self.polygon_mode = mode;
+    }
+
+    /// Returns how scene geometry is rasterized.
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+
+    /// Sets the global texture LOD bias and the extra sharpening applied while the camera is
+    /// jittered for TAA, taking effect from the next frame. Materials add their own
+    /// [`lod_bias`](crate::engine::material::Material::lod_bias).
+    ///
+    /// ```no_run
+    /// # use rustge::engine::{renderer::Renderer, texture::TextureLod};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.set_texture_lod(TextureLod { bias: 0.0, taa_sharpening: 0.5 });
+    /// ```
+    pub fn set_texture_lod(&mut self, lod: TextureLod) {
+        self.texture_lod = lod;
+    }
+
+    /// The global texture sharpness settings.
+    pub fn texture_lod(&self) -> TextureLod {
+        self.texture_lod
+    }
+
+    /// Switches render quality (see [`quality`](crate::engine::quality)) to a preset or to
+    /// custom settings. Settings stored elsewhere are updated right away: the texture LOD
+    /// bias replaces [`texture_lod`](Self::texture_lod)'s, and the memory warning threshold
+    /// becomes the [GPU memory budget](crate::engine::stats::set_gpu_memory_budget). The
+    /// rest is read every frame: passes recreate their render targets when a setting they
+    /// depend on changed, and with TAA off the camera's jitter is cleared before drawing.
+    ///
+    /// ```no_run
+    /// # use rustge::engine::{quality::QualityPreset, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.set_quality(QualityPreset::Low);
+    /// let mut settings = renderer.quality();
+    /// settings.msaa_samples = 4;
+    /// renderer.set_quality(settings);
+    /// ```
+    pub fn set_quality(&mut self, quality: impl Into<QualitySettings>) {
+        let quality = quality.into();
+        self.texture_lod.bias = quality.texture_lod_bias;
+        set_gpu_memory_budget(quality.memory_warning_threshold);
+        self.quality = quality;
+    }
+
+    /// The render quality settings in use.
+    pub fn quality(&self) -> QualitySettings {
+        self.quality
+    }
+
+    /// The preset the quality settings match, or `None` if they were customized.
+    pub fn quality_preset(&self) -> Option<QualityPreset> {
+        self.quality.preset()
+    }
+
+    /// The post-processing effects applied to each frame (see [`post`](crate::engine::post)).
+    pub fn post_chain(&self) -> &PostChain {
+        &self.post_chain
+    }
+
+    /// Mutable access to the post-processing effects, to add, remove or adjust them.
+    pub fn post_chain_mut(&mut self) -> &mut PostChain {
+        &mut self.post_chain
+    }
+
+    /// Turns [motion vectors](crate::engine::motion) on or off. While on, every frame
+    /// renders them after the scene and post effects can sample them as
+    /// `u_motion_vectors`.
+    pub fn set_motion_vectors(&mut self, enabled: bool) {
+        if enabled != self.motion_vectors.is_some() {
+            self.motion_vectors = enabled.then(MotionVectors::new);
+        }
+    }
+
+    /// The motion vectors of the last frame, while enabled.
+    pub fn motion_vectors(&self) -> Option<&MotionVectors> {
+        self.motion_vectors.as_ref()
+    }
+
+    /// Mutable access to the motion vectors, e.g. to [reset](MotionVectors::reset) them on a
+    /// camera cut.
+    pub fn motion_vectors_mut(&mut self) -> Option<&mut MotionVectors> {
+        self.motion_vectors.as_mut()
+    }
+
+    /// Turns [eye adaptation](crate::engine::eye_adaptation) on with `settings`, or off with
+    /// `None`, returning to the camera's own exposure.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::{eye_adaptation::EyeAdaptation, renderer::Renderer};
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// // Dark interiors may brighten up to 16 times, slowly
+    /// renderer.set_eye_adaptation(Some(EyeAdaptation { max_exposure: 16.0, dark_speed: 0.5, ..EyeAdaptation::new() }));
+    /// ```
+    pub fn set_eye_adaptation(&mut self, settings: Option<EyeAdaptation>) {
+        if settings.is_none() {
+            self.exposure_meter.reset();
+        }
+        self.eye_adaptation = settings;
+    }
+
+    /// The eye adaptation settings, while enabled.
+    pub fn eye_adaptation(&self) -> Option<&EyeAdaptation> {
+        self.eye_adaptation.as_ref()
+    }
+
+    /// The exposure multiplier eye adaptation currently applies on top of the camera's; 1
+    /// while it is off.
+    pub fn adapted_exposure(&self) -> f32 {
+        self.exposure_meter.exposure()
+    }
+
+    /// Average luminance (0 to 1) of the last frame eye adaptation measured, as drawn.
+    pub fn scene_luminance(&self) -> Option<f32> {
+        self.exposure_meter.luminance()
+    }
+
+    /// Starts uploading tightly packed RGBA8 `pixels` (rows bottom to top) as a texture with
+    /// a full mip chain, on the [upload thread](crate::engine::upload) if there is one. The
+    /// texture is ready a frame or more later; without an upload thread, right away.
+    ///
+    /// # Panics
+    /// Panics if `pixels` is smaller than `width * height * 4` bytes.
+    pub fn upload_texture(&mut self, width: u32, height: u32, pixels: Vec<u8>, label: &str) -> PendingUpload<Rc<Texture>> {
+        self.uploader.upload_texture(width, height, pixels, label)
+    }
+
+    /// Starts uploading the vertex and index buffers of `geometry`, on the
+    /// [upload thread](crate::engine::upload) if there is one. Once ready, nodes drawing the
+    /// geometry use the uploaded mesh while it is held.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::rc::Rc;
+    /// # use rustge::engine::object3d::{Geometry, Object3D, Topology};
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::builder().async_uploads(true).build();
+    /// # let scan: Vec<[f32; 3]> = Vec::new();
+    /// let geometry = Rc::new(Geometry::from_positions(Topology::Points, &scan));
+    /// let mut pending = renderer.upload_geometry(&geometry, "point cloud");
+    /// renderer.on_update(move |renderer, _| {
+    ///     if let Some(_mesh) = pending.try_take() {
+    ///         let cloud = Object3D::new();
+    ///         cloud.borrow_mut().set_shared_geometry(geometry.clone());
+    ///         cloud.borrow_mut().upload_geometry(); // picks up the mesh just uploaded
+    ///         renderer.get_scene().unwrap().add(cloud);
+    ///     }
+    /// });
+    /// ```
+    pub fn upload_geometry(&mut self, geometry: &Rc<Geometry>, label: &str) -> PendingUpload<Rc<GpuMesh>> {
+        self.uploader.upload_geometry(geometry, label)
+    }
+
+    /// Whether uploads happen on an upload thread, see
+    /// [`RendererBuilder::async_uploads`].
+    pub fn has_async_uploads(&self) -> bool {
+        self.uploader.is_threaded()
+    }
+
+    /// Number of uploads on the upload thread not completed yet.
+    pub fn uploads_in_flight(&self) -> usize {
+        self.uploader.in_flight()
+    }
+
+    /// The shaders and textures [reloaded](crate::engine::hot_reload) when their files change.
+    pub fn hot_reload(&self) -> &HotReload {
+        &self.hot_reload
+    }
+
+    /// Mutable access to hot reloading, to load and watch shaders and textures.
+    pub fn hot_reload_mut(&mut self) -> &mut HotReload {
+        &mut self.hot_reload
+    }
+
+    /// Starts listening for external editors on `address`, e.g. `"127.0.0.1:7878"`, and
+    /// applying the [scene patches](crate::engine::editor::live_sync) they send every frame.
+    /// Replaces any previous listener.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.enable_live_sync("127.0.0.1:7878").expect("live sync port in use");
+    /// ```
+    pub fn enable_live_sync(&mut self, address: impl ToSocketAddrs) -> io::Result<()> {
+        self.live_sync = Some(LiveSync::listen(address)?);
+        Ok(())
+    }
+
+    /// Stops listening for external editors and disconnects them.
+    pub fn disable_live_sync(&mut self) {
+        self.live_sync = None;
+    }
+
+    /// The live sync listener, if enabled, e.g. to [broadcast](LiveSync::broadcast) changes
+    /// made in game.
+    pub fn live_sync_mut(&mut self) -> Option<&mut LiveSync> {
+        self.live_sync.as_mut()
+    }
+
+    /// Turns [compute skinning](crate::engine::animation::compute_skinning) on or off.
+    /// Stays off, with a warning, if the GL context doesn't support compute shaders.
+    pub fn set_compute_skinning(&mut self, enabled: bool) {
+        if enabled && !compute_skinning_supported() {
+            eprintln!("Warning: compute skinning needs OpenGL 4.3, skinning in vertex shaders instead");
+            return;
+        }
+        if !enabled && self.compute_skinning && let Some(scene) = &self.scene {
+            release_deformed(scene.query::<(Skin,)>());
+        }
+        self.compute_skinning = enabled;
+    }
+
+    /// Whether skinned meshes are skinned in a compute pass.
+    pub fn compute_skinning(&self) -> bool {
+        self.compute_skinning
+    }
+
+    /// The debug-draw layer: lines and labels submitted to it are drawn over the scene at the
+    /// end of the current frame, then discarded.
+    pub fn debug_draw(&mut self) -> &mut DebugDraw {
+        &mut self.debug_draw
+    }
+
+    /// Shows or hides the pass timing overlay: a table in the top-left corner listing the
+    /// frame's render passes in execution order with their render target, size and CPU/GPU time.
+    ///
+    /// GPU times lag a few frames behind, since they are read back only once available.
+    pub fn set_pass_overlay(&mut self, enabled: bool) {
+        self.pass_overlay = enabled;
+    }
+
+    /// Returns whether the pass timing overlay is shown.
+    pub fn pass_overlay(&self) -> bool {
+        self.pass_overlay
+    }
+
+    /// Shows or hides the profiler overlay in the top-right corner: frame time and rate, pass
+    /// CPU/GPU totals, draw calls, triangles and state changes, and the CPU time of each
+    /// [profiled scope](crate::engine::profiler::scope).
+    pub fn set_profiler_overlay(&mut self, enabled: bool) {
+        self.profiler_overlay = enabled;
+    }
+
+    /// Returns whether the profiler overlay is shown.
+    pub fn profiler_overlay(&self) -> bool {
+        self.profiler_overlay
+    }
+
+    /// The profiler holding the breakdown of recent frames.
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    /// Mutable access to the profiler, e.g. to change how many frames it keeps.
+    pub fn profiler_mut(&mut self) -> &mut Profiler {
+        &mut self.profiler
+    }
+
+    /// The frame graph recording each frame's passes, e.g. to log timings or disable GPU queries.
+    pub fn frame_graph(&self) -> &FrameGraph {
+        &self.frame_graph
+    }
+
+    /// Mutable access to the frame graph.
+    pub fn frame_graph_mut(&mut self) -> &mut FrameGraph {
+        &mut self.frame_graph
+    }
+
+    /// The frame-time spike watchdog. Disabled until a threshold is set.
+    pub fn frame_watchdog(&self) -> &FrameWatchdog {
+        &self.frame_watchdog
+    }
+
+    /// Mutable access to the frame-time spike watchdog, e.g. to set its threshold.
+    pub fn frame_watchdog_mut(&mut self) -> &mut FrameWatchdog {
+        &mut self.frame_watchdog
+    }
+
+    /// Saves the next frame to `path` as a PNG file.
+    ///
+    /// The window contents are copied just before the frame is presented, and the file is
+    /// written a frame or two later once the GPU has finished the copy, so screenshots never
+    /// stall rendering. Failures are logged.
+    pub fn save_screenshot(&mut self, path: impl Into<PathBuf>) {
+        self.screenshot_requests.push(path.into());
+    }
+
+    /// Renders the scene from `position` into the six faces of a new `resolution`-sized cube map.
+    ///
+    /// The faces are drawn with a 90 degree field of view using the active camera's near/far
+    /// planes and exposure (defaults if no camera is set), including the scene background.
+    /// The result can be used directly as a [`Background::Skybox`] or reflection probe, or
+    /// written to disk with [`Cubemap::save_png`].
+    ///
+    /// [`Background::Skybox`]: crate::engine::background::Background::Skybox
+    ///
+    /// # Errors
+    /// Returns an error if the driver can't render into the cube map at this resolution.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let renderer = Renderer::new("Example", 800, 600);
+    /// let probe = renderer.capture_cubemap([0.0, 1.0, 0.0], 256).expect("failed to capture probe");
+    /// probe.save_png(".", "probe").expect("failed to save probe");
+    /// ```
+    pub fn capture_cubemap(&self, position: [f32; 3], resolution: u32) -> io::Result<Cubemap> {
+        let mut cubemap = Cubemap::empty(resolution, "captured cubemap");
+
+        let mut camera = self.camera.clone().unwrap_or_else(|| Camera::new(1.0));
+        camera.position = position;
+        camera.aspect = 1.0;
+        camera.fov_y = 90.0_f32.to_radians();
+        // Keep the physical exposure, but with a lens that covers exactly one face
+        camera.physical = camera.physical.map(|physical| PhysicalCamera {
+            sensor_fit: SensorFit::Horizontal,
+            focal_length: physical.sensor_size[0] / 2.0,
+            ..physical
+        });
+
+        let scene_clear_color = self.scene.as_ref().and_then(Scene::clear_color);
+        let clear_color = scene_clear_color.unwrap_or(self.clear_color);
+
+        let mut previous_viewport = [0; 4];
+        let (mut fbo, mut depth) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+
+            gl::GenRenderbuffers(1, &mut depth);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, resolution as i32, resolution as i32);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth);
+            gl::Viewport(0, 0, resolution as i32, resolution as i32);
+        }
+        let depth_bytes = resolution as usize * resolution as usize * 4;
+        track_gpu_allocation(GpuResourceKind::RenderTarget, depth, depth_bytes, "cubemap capture depth");
+
+        let mut result = Ok(());
+        for face in 0..6 {
+            camera.rotation = cube_face_rotation(face);
+            unsafe {
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0,
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                    cubemap.id(),
+                    0,
+                );
+            }
+            let status = unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) };
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                let message = format!("cube map capture framebuffer is incomplete: status 0x{:x}", status);
+                result = Err(io::Error::other(message));
+                break;
+            }
+            clear_framebuffer(clear_color);
+            if let Some(scene) = &self.scene {
+                scene.draw(&camera);
+            }
+        }
+
+        release_gpu_allocation(GpuResourceKind::RenderTarget, depth);
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::DeleteFramebuffers(1, &fbo);
+            gl::DeleteRenderbuffers(1, &depth);
+            let [x, y, width, height] = previous_viewport;
+            gl::Viewport(x, y, width, height);
+        }
+
+        // The renderer's clear color stays current for the next frame
+        apply_clear_color(self.clear_color);
+        result?;
+        cubemap.finish_rendering();
+        Ok(cubemap)
+    }
+
+    /// Draws `scene` as seen by `camera` into `target` instead of the window: clears it to
+    /// the scene's clear color (or the renderer's), then draws the background and the scene
+    /// graph. The window's framebuffer and viewport are restored afterwards.
+    ///
+    /// Can be called from the update callback, so the result is ready for the frame's own
+    /// passes to sample. Give `camera` the target's [`aspect`](RenderTarget::aspect).
+    /// Selection outlines, debug drawing and overlays are not drawn into targets.
+    pub fn render_to(&self, target: &RenderTarget, scene: &Scene, camera: &Camera) {
+        let clear_color = scene.clear_color().unwrap_or(self.clear_color);
+        target.draw_with(|| {
+            clear_framebuffer(clear_color);
+            scene.draw(camera);
+        });
+        // The renderer's clear color stays current for the next frame
+        apply_clear_color(self.clear_color);
+    }
+
+    /// Swaps the front and back buffers, presenting the rendered frame to the window.
+    ///
+    /// # Panics
+    /// Panics if buffer swap fails, which can indicate a context or window error.
+    pub fn swap_buffers(&self) {
+        self.windowed_context.swap_buffers().unwrap();
+    }
+
+    /// Updates the OpenGL clear color and stores it internally.
+    ///
+    /// This will affect the color used in subsequent `clear` calls, and each frame unless the
+    /// scene sets its own [`Background`](crate::engine::background::Background).
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = color;
+        apply_clear_color(color);
+    }
+
+    /// The color the framebuffer is cleared to each frame.
+    pub fn clear_color(&self) -> Color {
+        self.clear_color
+    }
+
+    /// Resizes the window to the specified width and height in physical pixels.
+    ///
+    /// # Parameters
+    /// - `width`: New window width in physical pixels.
+    /// - `height`: New window height in physical pixels.
+    ///
+    /// This method requests the underlying window to resize,
+    /// which will typically trigger a redraw event.
+    pub fn set_size(&self, width: u32, height: u32) {
+        self.windowed_context.window().set_inner_size(PhysicalSize::new(width, height));
+    }
+
+    /// The window's size in physical pixels.
+    pub fn window_size(&self) -> [u32; 2] {
+        let size = self.windowed_context.window().inner_size();
+        [size.width, size.height]
+    }
+
+    /// The window's ratio of physical to logical pixels (e.g. 2.0 on high-DPI displays), to
+    /// convert logical positions with [`viewport::to_physical`](crate::engine::camera::viewport::to_physical).
+    pub fn scale_factor(&self) -> f64 {
+        self.windowed_context.window().scale_factor()
+    }
+
+    /// The connected monitors, primary first where the platform reports it, with their
+    /// supported video modes. Indices into this list select monitors in [`FullscreenMode`].
+    pub fn monitors(&self) -> Vec<Monitor> {
+        let window = self.windowed_context.window();
+        primary_first(window.available_monitors().collect(), window.primary_monitor()).iter().map(Monitor::from_winit).collect()
+    }
+
+    /// Switches the window between windowed, borderless and exclusive fullscreen (see
+    /// [`display`](crate::engine::display)). The framebuffer and viewport follow once the
+    /// window reports its new size.
+    pub fn set_fullscreen(&mut self, mode: FullscreenMode) {
+        let window = self.windowed_context.window();
+        let monitors = primary_first(window.available_monitors().collect(), window.primary_monitor());
+        window.set_fullscreen(mode.to_winit(monitors, window.current_monitor()));
+        self.fullscreen_mode = mode;
+    }
+
+    /// How the window is presented, as last set.
+    pub fn fullscreen_mode(&self) -> FullscreenMode {
+        self.fullscreen_mode
+    }
+
+    /// Turns vsync on or off by changing the context's swap interval. Without vsync, frames
+    /// are presented as soon as they are drawn: lower latency and an uncapped frame rate for
+    /// benchmarking, at the cost of tearing. Combine with
+    /// [`set_target_fps`](Self::set_target_fps) to cap the frame rate anyway.
+    ///
+    /// Logs a warning and keeps the current setting if the platform can't change the swap
+    /// interval at runtime; use [`RendererBuilder::vsync`] to choose it at creation instead.
+    ///
+    /// ```no_run
+    /// # use rustge::engine::renderer::Renderer;
+    /// # let mut renderer = Renderer::new("Example", 800, 600);
+    /// renderer.set_vsync(false);
+    /// renderer.set_target_fps(Some(144));
+    /// ```
+    pub fn set_vsync(&mut self, vsync: bool) {
+        if set_swap_interval(&self.windowed_context, i32::from(vsync)) {
+            self.vsync = vsync;
+        } else {
+            eprintln!("Warning: Changing vsync is not supported on this platform");
+        }
+    }
+
+    /// Whether vsync is on, as created or last changed by [`set_vsync`](Self::set_vsync).
+    pub fn vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// Caps the frame rate at `fps` frames per second by waiting on the CPU between frames,
+    /// or removes the cap with `None`. With vsync on, the lower of the two rates applies.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.target_fps = fps.filter(|&fps| fps > 0);
+        self.next_frame = None;
+    }
+
+    /// The frame rate cap, if any.
+    pub fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    /// Starts the renderer's event loop, handling window events and redraw requests.
+    ///
+    /// This method **never returns** until the window is closed by the user or the event loop exits.
+    /// It processes:
+    /// - `WindowEvent::CloseRequested`: Exits the application.
+    /// - `WindowEvent::Resized` and `ScaleFactorChanged`: Resize the framebuffer and viewport
+    ///   to the window's new size.
+    /// - `WindowEvent::DroppedFile`: Loads dropped OBJ and glTF models (see
+    ///   [`set_model_drop`](Self::set_model_drop)).
+    /// - Mouse events: Update the selection when click selection is enabled (see
+    ///   [`set_click_selection`](Self::set_click_selection)), then drive the camera controller.
+    /// - `Event::RedrawRequested`: Clears the color and depth buffers, draws the scene, and swaps buffers to present the frame.
+    ///
+    /// It also ensures the window continuously requests redraws,
+    /// driving a rendering loop at the native vsync rate, or at most the
+    /// [target frame rate](Self::set_target_fps) if one is set.
+    ///
+    /// # Detailed Design Notes
+    /// - Takes the event loop out of the renderer and moves the renderer into the closure
+    ///   passed to the event loop, so each frame has mutable access to it.
+    /// - Sets the control flow to `ControlFlow::Wait` to efficiently sleep until new events.
+    /// - On each redraw event, ticks the clock, runs the update callback, draws the scene
+    ///   and swaps buffers to update the screen.
+    /// - Requests redraw on every iteration to keep the rendering loop alive.
+    /// - Starts the [plugins](Self::add_plugin) before the first frame and shuts them down
+    ///   when the loop ends.
+    ///
+    /// # Panics
+    /// Panics if called on a renderer whose event loop has already been consumed.
+    pub fn run(mut self) {
+        let event_loop = self.event_loop.take().expect("Renderer::run can only be called once");
+
+        self.start_plugins();
+        // Don't count setup time as the first frame's delta
+        self.clock.reset();
+
+        event_loop.run(move |event, _, control_flow| {
+            *control_flow = ControlFlow::Wait;
+
+            let mut reaches_base = true;
+            if let Event::WindowEvent { event, .. } = &event {
+                reaches_base = self.dispatch_event(event);
+            }
+
+            match event {
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                    *control_flow = ControlFlow::Exit
+                }
+
+                Event::WindowEvent { event: WindowEvent::Resized(size), .. } => self.resize_framebuffer(size),
+
+                Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { new_inner_size, .. }, .. } => {
+                    self.resize_framebuffer(*new_inner_size)
+                }
+
+                Event::WindowEvent { event: WindowEvent::DroppedFile(path), .. } => {
+                    self.load_dropped_model(&path)
+                }
+
+                // Events consumed by a console or UI context don't pick or orbit
+                Event::WindowEvent { event, .. } if reaches_base => {
+                    let gesture = self.selection_input.as_mut().and_then(|input| input.handle_window_event(&event));
+                    if let Some(gesture) = gesture {
+                        self.apply_selection_gesture(gesture);
+                    }
+                    // A box selection drag must not also orbit the camera
+                    let box_selecting = self.selection_input.as_ref().is_some_and(SelectionInput::is_box_selecting);
+                    if let Some(controller) = &mut self.camera_controller
+                        && !box_selecting
+                    {
+                        controller.handle_window_event(&event);
+                    }
+                }
+
+                Event::RedrawRequested(_) => self.render_frame(),
+
+                Event::LoopDestroyed => self.shutdown_plugins(),
+
+                _ => {}
+            }
+
+            // Continuously redraw at vsync rate, waiting out the frame limiter first
+            match self.next_frame {
+                Some(next_frame) if *control_flow != ControlFlow::Exit && Instant::now() < next_frame => {
+                    *control_flow = ControlFlow::WaitUntil(next_frame)
+                }
+                _ => self.windowed_context.window().request_redraw(),
+            }
+        });
+    }
+
+    /// Passes the engine event for a window event, if there is one, to the input contexts and
+    /// every event handler. Returns whether it reached the base input context.
+    fn dispatch_event(&mut self, event: &WindowEvent) -> bool {
+        let Some(event) = self.event_translator.translate(event) else {
+            return true;
+        };
+        self.ui.handle_event(&event);
+        let reaches_base = self.input_contexts.handle_event(&event);
+        let plugins = self.plugins.names(Some(true));
+        self.each_plugin(plugins, |plugin, renderer| plugin.event(renderer, &event));
+        // Handlers may add or remove handlers, so look each up again by id
+        let ids: Vec<EventHandlerId> = self.event_handlers.iter().map(|(id, _)| *id).collect();
+        for id in ids {
+            let slot = self.event_handlers.iter_mut().find(|(other, _)| *other == id);
+            let Some(mut handler) = slot.and_then(|(_, handler)| handler.take()) else {
+                continue;
+            };
+            handler(self, &event);
+            if let Some((_, slot)) = self.event_handlers.iter_mut().find(|(other, _)| *other == id) {
+                *slot = Some(handler);
+            }
+        }
+        reaches_base
+    }
+
+    /// Keeps the UI's input context in line with what the UI uses: pushed while it wants the
+    /// mouse or keyboard, gone otherwise.
+    fn sync_ui_input_context(&mut self) {
+        let capture = Capture { keyboard: self.ui.wants_keyboard(), mouse: self.ui.wants_mouse(), gamepad: false };
+        let current = self.input_contexts.context(UI_INPUT_CONTEXT).map(|context| context.capture);
+        if capture == Capture::NONE {
+            self.input_contexts.remove(UI_INPUT_CONTEXT);
+        } else if current != Some(capture) {
+            self.input_contexts.push(InputContext::new(UI_INPUT_CONTEXT, capture));
+        }
+    }
+
+    /// Sets when the frame limiter lets the next frame start: one frame period after this
+    /// frame's slot, so the cap holds on average, but never in the past, so a slow frame
+    /// isn't followed by a burst of catch-up frames.
+    fn schedule_next_frame(&mut self) {
+        let Some(fps) = self.target_fps else {
+            self.next_frame = None;
+            return;
+        };
+        let period = Duration::from_secs_f64(1.0 / f64::from(fps));
+        let now = Instant::now();
+        let slot = self.next_frame.unwrap_or(now);
+        self.next_frame = Some((slot + period).max(now));
+    }
+
+    /// Resizes the context's framebuffer (needed on some platforms) and the viewport to a
+    /// new window size.
+    fn resize_framebuffer(&mut self, size: PhysicalSize<u32>) {
+        if size.width == 0 || size.height == 0 {
+            // Minimized; keep the old size until the window comes back
+            return;
+        }
+        self.windowed_context.resize(size);
+        unsafe {
+            gl::Viewport(0, 0, size.width as i32, size.height as i32);
+        }
+        if let Some(camera) = &mut self.camera {
+            camera.fit_aspect([size.width, size.height]);
+        }
+    }
+
+    /// Loads a model file dropped onto the window into the scene, according to the
+    /// [`ModelDrop`] mode, and frames the camera on it. Load errors are logged.
+    fn load_dropped_model(&mut self, path: &Path) {
+        if self.model_drop == ModelDrop::Ignore || !is_model_file(path) {
+            return;
+        }
+        let Some(scene) = &self.scene else {
+            return;
+        };
+        let model = match load_model(path) {
+            Ok(model) => model,
+            Err(err) => {
+                eprintln!("Failed to load dropped model {}: {err}", path.display());
+                return;
+            }
+        };
+
+        if self.model_drop == ModelDrop::Replace {
+            for previous in self.dropped_models.drain(..) {
+                Object3D::detach(&previous);
+            }
+        }
+        scene.add(model.clone());
+        self.dropped_models.push(model.clone());
+
+        if let Some(bounds) = Object3D::subtree_bounds(&model) {
+            self.frame_bounds(&bounds);
+        }
+    }
+
+    /// Picks the nodes a click or box gesture hit and updates the selection.
+    fn apply_selection_gesture(&mut self, gesture: SelectionGesture) {
+        match gesture {
+            SelectionGesture::Click { pixel, additive } => {
+                let hit = self.pick(pixel);
+                self.selection.click(hit, additive);
+            }
+            SelectionGesture::Box { corner_a, corner_b, additive } => {
+                let hits = self.pick_rect(corner_a, corner_b);
+                self.selection.select_many(hits, additive);
+            }
+        }
+    }
+
+    /// Points the camera at `bounds` from its current direction (see [`Camera::frame_bounds`]),
+    /// creating a camera first if there is none, and retargets the camera controller.
+    fn frame_bounds(&mut self, bounds: &Aabb) {
+        let window_size = self.window_size();
+        let camera = self.camera.get_or_insert_with(|| {
+            let mut camera = Camera::new(1.0);
+            camera.aspect_mode = AspectMode::Window;
+            camera.fit_aspect(window_size);
+            camera
+        });
+        let distance = camera.frame_bounds(bounds, 0.1);
+
+        // The controller keeps its angles and moves the camera into place on its next update
+        if let Some(controller) = &mut self.camera_controller {
+            controller.set_target(bounds.center());
+            controller.set_distance(distance);
+        }
+    }
+
+    /// Starts the plugins added since the last call, in order.
+    fn start_plugins(&mut self) {
+        let plugins = self.plugins.names(Some(false));
+        self.each_plugin(plugins, |plugin, renderer| plugin.startup(renderer));
+    }
+
+    /// Updates the started plugins at `stage`.
+    fn update_plugins(&mut self, stage: FrameStage) {
+        let plugins = self.plugins.names(Some(true));
+        if plugins.is_empty() {
+            return;
+        }
+        let clock = self.clock.clone();
+        let scope = profiler::scope(stage.scope_name());
+        self.each_plugin(plugins, |plugin, renderer| plugin.update(renderer, stage, &clock));
+        self.frame_watchdog.record(stage.scope_name(), scope.finish());
+    }
+
+    /// Shuts the started plugins down in reverse order and removes every plugin.
+    fn shutdown_plugins(&mut self) {
+        for name in self.plugins.names(None).into_iter().rev() {
+            self.remove_plugin(&name);
+        }
+    }
+
+    /// Runs `hook` on each of the plugins named `names` that is still registered, taken out
+    /// so it can borrow the renderer mutably. Plugins removed by their own hook are shut
+    /// down once it returns.
+    fn each_plugin(&mut self, names: Vec<String>, mut hook: impl FnMut(&mut dyn Plugin, &mut Renderer)) {
+        for name in names {
+            let Some(mut plugin) = self.plugins.take(&name) else {
+                continue;
+            };
+            let scope = profiler::scope(&name);
+            hook(plugin.as_mut(), self);
+            drop(scope);
+            if let Some(mut removed) = self.plugins.restore(&name, plugin) {
+                removed.shutdown(self);
+            }
+        }
+    }
+
+    /// Runs one frame: ticks the clock, calls the update callback and node updates, draws and presents.
+    fn render_frame(&mut self) {
+        self.frame_watchdog.begin_frame();
+        self.profiler.begin_frame();
+        self.clock.tick();
+        self.schedule_next_frame();
+        self.uploader.poll();
+        self.hot_reload.poll();
+        if let (Some(live_sync), Some(scene)) = (&mut self.live_sync, &self.scene) {
+            live_sync.poll(scene);
+        }
+        self.start_plugins();
+        self.update_plugins(FrameStage::PreUpdate);
+
+        // Take the callback out while it runs so it can borrow the renderer mutably
+        if let Some(mut update) = self.update_callback.take() {
+            let clock = self.clock.clone();
+            let scope = profiler::scope("update callback");
+            update(self, &clock);
+            self.frame_watchdog.record("update callback", scope.finish());
+            // Keep it unless the callback registered a replacement
+            if self.update_callback.is_none() {
+                self.update_callback = Some(update);
+            }
+        }
+
+        let mut ui = std::mem::take(&mut self.ui);
+        ui.begin_frame();
+        if let Some(mut callback) = self.ui_callback.take() {
+            let scope = profiler::scope("ui callback");
+            callback(self, &mut ui);
+            self.frame_watchdog.record("ui callback", scope.finish());
+            if self.ui_callback.is_none() {
+                self.ui_callback = Some(callback);
+            }
+        }
+        ui.end_frame();
+        self.ui = ui;
+        self.sync_ui_input_context();
+        self.update_plugins(FrameStage::Update);
+
+        let (tweens, delta) = (&mut self.tweens, self.clock.delta());
+        self.frame_watchdog.time("tweens", || tweens.update(delta));
+
+        let (world, watchdog) = (&mut self.world, &mut self.frame_watchdog);
+        self.schedule.run_timed(world, &self.clock, |name, elapsed| {
+            profiler::record(name, elapsed);
+            watchdog.record(name, elapsed);
+        });
+        self.frame_watchdog.time("entity sync", || sync_scene(&self.world));
+
+        if let Some(scene) = &self.scene {
+            let (physics, delta) = (&mut self.physics, self.clock.delta());
+            self.frame_watchdog.time("physics", || physics.step(scene, delta));
+        }
+
+        if let (Some(controller), Some(camera)) = (&mut self.camera_controller, &mut self.camera) {
+            let delta = self.clock.delta();
+            self.frame_watchdog.time("camera controller", || controller.update(camera, delta));
+        }
+
+        if let Some(scene) = &self.scene {
+            let (camera, clock) = (self.camera.as_ref(), &self.clock);
+            self.frame_watchdog.time("scene update", || scene.update(camera, clock));
+            if let Some(publisher) = &self.snapshot_publisher {
+                self.frame_watchdog.time("scene snapshot", || publisher.publish(scene.snapshot(camera, clock)));
+            }
+        }
+        self.update_plugins(FrameStage::PostUpdate);
+        self.input_contexts.end_frame();
+
+        // Without TAA nothing averages the sub-pixel jitter away, so the frame is drawn still
+        if !self.quality.taa && let Some(camera) = &mut self.camera {
+            camera.clear_jitter();
+        }
+
+        if let Some(settings) = &self.eye_adaptation {
+            self.exposure_meter.update(settings, self.clock.delta());
+        }
+        set_frame_exposure(self.exposure_meter.exposure());
+
+        let size = self.windowed_context.window().inner_size();
+        let size = [size.width, size.height];
+        self.frame_graph.begin_frame();
+
+        if self.compute_skinning && let Some(scene) = &self.scene {
+            self.frame_graph.pass("skinning", "skinned vertices", size, || skin_on_gpu(scene.query::<(Skin,)>()));
+        }
+        if let (Some(camera), Some(scene)) = (&self.camera, &self.scene)
+            && let Some(settings) = scene.shadows()
+        {
+            let atlas = [settings.atlas_size.next_power_of_two(); 2];
+            let resolution = self.quality.shadow_resolution;
+            self.frame_graph.pass("shadows", "shadow atlas", atlas, || scene.render_shadows(camera, resolution));
+        }
+
+        // MSAA and post effects need the scene offscreen, resolved and processed afterwards
+        let post_effects = self.quality.post_effects && self.post_chain.is_active();
+        let samples = self.quality.msaa_samples.max(1);
+        if samples > 1 || post_effects {
+            if !self.scene_targets.as_ref().is_some_and(|targets| targets.matches(size, samples)) {
+                self.scene_targets = Some(SceneTargets::new(size, samples));
+            }
+        } else {
+            self.scene_targets = None;
+        }
+        let target = match &self.scene_targets {
+            Some(targets) => {
+                unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, targets.scene_fbo()) };
+                "scene"
+            }
+            None => "backbuffer",
+        };
+
+        let taa_active = self.quality.taa && self.camera.as_ref().is_some_and(|camera| camera.jitter != [0.0, 0.0]);
+        set_frame_lod_bias(self.texture_lod.frame_bias(taa_active));
+
+        let scene_clear_color = self.scene.as_ref().and_then(Scene::clear_color);
+        let clear_color = scene_clear_color.unwrap_or(self.clear_color);
+        self.frame_graph.pass("clear", target, size, || clear_framebuffer(clear_color));
+
+        if let (Some(camera), Some(scene)) = (&self.camera, &self.scene) {
+            // With depth testing, the background only shades the pixels the opaque geometry
+            // left uncovered; without, it has to come first to stay behind
+            let polygon_mode = self.polygon_mode.to_gl();
+            if !self.depth_test {
+                self.frame_graph.pass("background", target, size, || scene.draw_background(camera));
+            }
+            unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, polygon_mode) };
+            self.frame_graph.pass("scene", target, size, || scene.draw_opaque(camera));
+            if self.depth_test {
+                unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL) };
+                self.frame_graph.pass("background", target, size, || scene.draw_background(camera));
+                unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, polygon_mode) };
+            }
+            if scene.has_queued_transparent() {
+                self.frame_graph.pass("transparent", target, size, || scene.draw_transparent(camera));
+            } else {
+                // Nothing to draw, but this releases the queued nodes
+                scene.draw_transparent(camera);
+            }
+            unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL) };
+
+            if let Some(motion) = &mut self.motion_vectors {
+                self.frame_graph.pass("motion vectors", "motion vectors", size, || motion.render(scene.root(), camera, size));
+            }
+
+            let selected = self.selection.nodes();
+            if !selected.is_empty() {
+                let style = &self.outline_style;
+                self.frame_graph.pass("outline", target, size, || draw_outlines(&selected, camera, size, style));
+            }
+        }
+
+        match &self.camera {
+            Some(camera) if !self.debug_draw.is_empty() => {
+                let debug_draw = &mut self.debug_draw;
+                self.frame_graph.pass("debug", target, size, || debug_draw.draw(camera, size));
+            }
+            _ => self.debug_draw.clear(),
+        }
+
+        if let Some(targets) = &self.scene_targets
+            && targets.is_multisampled()
+        {
+            self.frame_graph.pass("resolve", "scene", size, || targets.resolve());
+        }
+        if self.eye_adaptation.is_some() {
+            let fbo = self.scene_targets.as_ref().map_or(0, SceneTargets::resolved_fbo);
+            let meter = &mut self.exposure_meter;
+            self.frame_graph.pass("exposure meter", "exposure meter", size, || meter.measure(fbo, size));
+        }
+
+        let target = "backbuffer";
+        if let Some(targets) = &mut self.scene_targets {
+            let graph = &mut self.frame_graph;
+            let no_effects = PostChain::new();
+            let chain = if post_effects { &self.post_chain } else { &no_effects };
+            let motion = self.motion_vectors.as_ref().and_then(MotionVectors::texture);
+            targets.apply(chain, motion, |name, draw| graph.pass(name, target, size, draw));
+        }
+
+        // Rubber band of a box selection in progress
+        if let Some([x, y, width, height]) = self.selection_input.as_ref().and_then(SelectionInput::box_rect) {
+            let color = self.outline_style.color;
+            self.overlay_text.rect(x, y, width, height, color.with_alpha(0.15));
+            for [x, y, width, height] in [[x, y, width, 1.0], [x, y + height, width, 1.0], [x, y, 1.0, height], [x + width, y, 1.0, height]] {
+                self.overlay_text.rect(x, y, width, height, color);
+            }
+        }
+
+        if self.pass_overlay {
+            queue_pass_overlay(&mut self.overlay_text, &self.frame_graph);
+        }
+        if self.profiler_overlay {
+            queue_profiler_overlay(&mut self.overlay_text, &self.profiler, size);
+        }
+        if !self.overlay_text.is_empty() {
+            let (graph, text) = (&mut self.frame_graph, &mut self.overlay_text);
+            graph.pass("overlay", target, size, || text.draw(size));
+        }
+        if !self.ui.is_empty() {
+            let (graph, ui) = (&mut self.frame_graph, &mut self.ui);
+            graph.pass("ui", target, size, || ui.draw(size));
+        }
+
+        for path in self.screenshot_requests.drain(..) {
+            let readback = Readback::framebuffer(0, [0, 0, size[0], size[1]], ReadbackFormat::Rgba8);
+            self.pending_screenshots.push((path, readback));
+        }
+        self.pending_screenshots.retain_mut(|(path, readback)| {
+            let Some(pixels) = readback.try_take() else {
+                return true;
+            };
+            if let Err(error) = write_screenshot(path, readback.size(), pixels) {
+                eprintln!("Failed to save screenshot {}: {}", path.display(), error);
+            }
+            false
+        });
+
+        self.frame_graph.end_frame();
+        let scope = profiler::scope("swap buffers");
+        self.swap_buffers();
+        self.frame_watchdog.record("swap buffers", scope.finish());
+        self.update_plugins(FrameStage::PostRender);
+        self.frame_watchdog.end_frame(self.clock.frame_count(), self.frame_graph.recorded_passes());
+        self.profiler.end_frame(self.clock.frame_count(), self.frame_graph.passes());
+    }
+
+}
+
+/// Writes back buffer pixels (rows bottom to top) to a PNG file.
+fn write_screenshot(path: &Path, size: [u32; 2], mut pixels: Vec<u8>) -> io::Result<()> {
+    flip_rows(&mut pixels, size[0] as usize * 4);
+    // The back buffer's alpha is whatever blending left behind
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), size[0], size[1]);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&pixels).map_err(io::Error::other)
+}
+
+/// View rotation for rendering cube map face `face` (OpenGL order +X, -X, +Y, -Y, +Z, -Z),
+/// using the per-face up vectors the cube map sampling convention expects.
+fn cube_face_rotation(face: usize) -> [f32; 4] {
+    let (forward, up): ([f32; 3], [f32; 3]) = match face {
+        0 => ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+        1 => ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+        2 => ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+        3 => ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+        4 => ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+        _ => ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+    };
+    decompose_matrix(&look_at_matrix([0.0; 3], forward, up)).1
+}
+
+/// Sets `glClearColor`, encoded as the [color pipeline](crate::engine::color_space) expects
+/// so the color displays as specified.
+fn apply_clear_color(color: Color) {
+    let [r, g, b, a] = output_color(color);
+    unsafe {
+        gl::ClearColor(r, g, b, a);
+    }
+}
+
+/// Clears the color and depth buffers of the bound framebuffer.
+///
+/// Depth writes are re-enabled first: a material with `depth_write = false` leaves the depth
+/// mask off, and a masked depth buffer is not cleared by `glClear`.
+fn clear_framebuffer(color: Color) {
+    apply_clear_color(color);
+    unsafe {
+        gl::DepthMask(gl::TRUE);
+        gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+    }
 }
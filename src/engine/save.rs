@@ -0,0 +1,293 @@
+//! Save games: the dynamic state of a running game, separate from the authored scene.
+//!
+//! A level is loaded from its scene files as usual; a [`SaveGame`] then holds only what
+//! changed while playing, and is applied on top:
+//!
+//! - the transforms of nodes marked with [`Object3D::set_persistent_id`], keyed by that id;
+//! - their components of types registered in a [`SaveSchema`], serialized with serde;
+//! - named random number streams ([`Rng`]), so sequences continue where they were;
+//! - the time of day, and any other game-wide values under string keys.
+//!
+//! Saves are JSON. They record the engine's [`SAVE_FORMAT_VERSION`] and the game's own
+//! version from the schema. Unknown fields, nodes and components are skipped with a warning
+//! and missing ones keep their authored values, so saves stay loadable as the game changes;
+//! [`SaveSchema::migration`] upgrades older saves whose data must be converted.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{object3d::Object3D, save::{SaveGame, SaveSchema}, scene::Scene};
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize)]
+//! struct Health(f32);
+//!
+//! let schema = SaveSchema::new(1).component::<Health>("health");
+//! let scene = Scene::new();
+//! let gate = Object3D::new();
+//! gate.borrow_mut().set_persistent_id(Some("castle/gate"));
+//! gate.borrow_mut().insert_component(Health(40.0));
+//! scene.add(gate.clone());
+//!
+//! let mut save = SaveGame::capture(&scene, &schema);
+//! save.time_of_day = Some(18.5);
+//! let json = save.to_json().unwrap();
+//!
+//! // After reloading the level
+//! gate.borrow_mut().insert_component(Health(100.0));
+//! SaveGame::from_json(&json).unwrap().restore(&scene, &schema);
+//! assert_eq!(gate.borrow().component::<Health>().map(|health| health.0), Some(40.0));
+//! ```
+//!
+//! [`Object3D::set_persistent_id`]: crate::engine::object3d::Object3D::set_persistent_id
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::engine::math::random::Rng;
+use crate::engine::object3d::Object3D;
+use crate::engine::scene::Scene;
+
+/// Version of the save file layout written by this engine.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+
+type CaptureFn = Box<dyn Fn(&Object3D) -> Option<serde_json::Result<Value>>>;
+type RestoreFn = Box<dyn Fn(&mut Object3D, Value) -> serde_json::Result<()>>;
+type MigrationFn = Box<dyn Fn(&mut SaveGame)>;
+
+/// A serializable component type, by the name it is saved under.
+struct ComponentCodec {
+    name: String,
+    capture: CaptureFn,
+    restore: RestoreFn,
+}
+
+/// What a game saves: its version, the component types to save and the migrations that
+/// upgrade older saves.
+pub struct SaveSchema {
+    game_version: u32,
+    components: Vec<ComponentCodec>,
+    migrations: Vec<(u32, MigrationFn)>,
+}
+
+impl SaveSchema {
+    /// A schema for version `game_version` of the game, saving no components yet.
+    pub fn new(game_version: u32) -> Self {
+        Self { game_version, components: Vec::new(), migrations: Vec::new() }
+    }
+
+    /// Saves components of type `T` under `name`. Names must stay stable across versions
+    /// (unlike Rust type names, which change with refactoring).
+    pub fn component<T: Serialize + DeserializeOwned + 'static>(mut self, name: &str) -> Self {
+        self.components.push(ComponentCodec {
+            name: name.to_string(),
+            capture: Box::new(|node| node.component::<T>().map(serde_json::to_value)),
+            restore: Box::new(|node, value| {
+                node.insert_component(serde_json::from_value::<T>(value)?);
+                Ok(())
+            }),
+        });
+        self
+    }
+
+    /// Upgrades saves from game versions before `version` by running `migrate` on them
+    /// before they are restored, e.g. to rename a node id or convert a component's fields.
+    /// Migrations run in order of version.
+    pub fn migration<F>(mut self, version: u32, migrate: F) -> Self
+    where
+        F: Fn(&mut SaveGame) + 'static,
+    {
+        self.migrations.push((version, Box::new(migrate)));
+        self.migrations.sort_by_key(|&(version, _)| version);
+        self
+    }
+
+    /// The game version saves are written with.
+    pub fn game_version(&self) -> u32 {
+        self.game_version
+    }
+}
+
+/// The saved state of one persistent node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeState {
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub scale: [f32; 3],
+
+    /// Serialized components by their [`SaveSchema::component`] name.
+    pub components: BTreeMap<String, Value>,
+}
+
+impl Default for NodeState {
+    fn default() -> Self {
+        Self { position: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], scale: [1.0; 3], components: BTreeMap::new() }
+    }
+}
+
+/// A snapshot of a game's dynamic state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveGame {
+    /// [`SAVE_FORMAT_VERSION`] of the engine that wrote the save.
+    pub format_version: u32,
+
+    /// [`SaveSchema::game_version`] of the game that wrote the save.
+    pub game_version: u32,
+
+    /// Persistent nodes by id.
+    pub nodes: BTreeMap<String, NodeState>,
+
+    /// Random number streams by name.
+    pub rng_streams: BTreeMap<String, Rng>,
+
+    /// In-game time of day in hours, if the game has a day cycle.
+    pub time_of_day: Option<f32>,
+
+    /// Other game-wide values (quest flags, score, settings) by key.
+    pub data: BTreeMap<String, Value>,
+}
+
+impl Default for SaveGame {
+    fn default() -> Self {
+        Self {
+            format_version: SAVE_FORMAT_VERSION,
+            game_version: 0,
+            nodes: BTreeMap::new(),
+            rng_streams: BTreeMap::new(),
+            time_of_day: None,
+            data: BTreeMap::new(),
+        }
+    }
+}
+
+impl SaveGame {
+    /// Captures the persistent nodes of `scene` and their components registered in `schema`.
+    /// Components that fail to serialize are skipped with a warning.
+    pub fn capture(scene: &Scene, schema: &SaveSchema) -> Self {
+        let mut nodes = BTreeMap::new();
+        for (id, node) in persistent_nodes(scene.root()) {
+            let node = node.borrow();
+            let mut components = BTreeMap::new();
+            for codec in &schema.components {
+                match (codec.capture)(&node) {
+                    Some(Ok(value)) => {
+                        components.insert(codec.name.clone(), value);
+                    }
+                    Some(Err(err)) => eprintln!("Warning: Failed to save component {} of {id}: {err}", codec.name),
+                    None => {}
+                }
+            }
+            let state = NodeState { position: node.position(), rotation: node.rotation(), scale: node.scale(), components };
+            nodes.insert(id, state);
+        }
+        Self { game_version: schema.game_version, nodes, ..Self::default() }
+    }
+
+    /// Applies the save to the persistent nodes of `scene`, after running the schema's
+    /// migrations for older game versions. Nodes and components the save doesn't mention
+    /// keep their current state. Returns the number of nodes restored.
+    pub fn restore(&self, scene: &Scene, schema: &SaveSchema) -> usize {
+        if self.format_version > SAVE_FORMAT_VERSION {
+            eprintln!("Warning: Save format {} is newer than {SAVE_FORMAT_VERSION}; loading what is understood", self.format_version);
+        }
+        let mut migrated = None;
+        for (version, migrate) in &schema.migrations {
+            if self.game_version < *version {
+                migrate(migrated.get_or_insert_with(|| self.clone()));
+            }
+        }
+        let save = migrated.as_ref().unwrap_or(self);
+
+        let mut nodes = persistent_nodes(scene.root());
+        let mut restored = 0;
+        for (id, state) in &save.nodes {
+            let Some(node) = nodes.remove(id) else {
+                eprintln!("Warning: Saved node {id} is not in the scene");
+                continue;
+            };
+            let mut node = node.borrow_mut();
+            node.set_transform(state.position, state.rotation, state.scale);
+            for (name, value) in &state.components {
+                let Some(codec) = schema.components.iter().find(|codec| codec.name == *name) else {
+                    eprintln!("Warning: Saved component {name} of {id} is not registered");
+                    continue;
+                };
+                if let Err(err) = (codec.restore)(&mut node, value.clone()) {
+                    eprintln!("Warning: Failed to load component {name} of {id}: {err}");
+                }
+            }
+            restored += 1;
+        }
+        restored
+    }
+
+    /// Stores the state of the random number stream `name`.
+    pub fn set_rng(&mut self, name: &str, rng: &Rng) {
+        self.rng_streams.insert(name.to_string(), *rng);
+    }
+
+    /// The saved state of the random number stream `name`.
+    pub fn rng(&self, name: &str) -> Option<Rng> {
+        self.rng_streams.get(name).copied()
+    }
+
+    /// Stores a game-wide value under `key`.
+    pub fn set_data<T: Serialize>(&mut self, key: &str, value: &T) -> io::Result<()> {
+        self.data.insert(key.to_string(), serde_json::to_value(value).map_err(io::Error::other)?);
+        Ok(())
+    }
+
+    /// The game-wide value under `key`, if present and of type `T`.
+    pub fn data<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        serde_json::from_value(self.data.get(key)?.clone()).ok()
+    }
+
+    /// The save as pretty-printed JSON.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    /// Parses a save from JSON.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Writes the save to `path`, replacing the previous file only once the new one is
+    /// complete, so a crash while saving doesn't lose the last save.
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, self.to_json()?)?;
+        std::fs::rename(&temporary, path)
+    }
+
+    /// Reads a save written by [`write`](Self::write).
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Every node under `root` with a persistent id, by id. Later duplicates are skipped with
+/// a warning.
+fn persistent_nodes(root: &Rc<RefCell<Object3D>>) -> BTreeMap<String, Rc<RefCell<Object3D>>> {
+    let mut nodes = BTreeMap::new();
+    let mut stack = vec![root.clone()];
+    while let Some(node) = stack.pop() {
+        let borrowed = node.borrow();
+        stack.extend(borrowed.children().iter().rev().cloned());
+        if let Some(id) = borrowed.persistent_id() {
+            if nodes.contains_key(id) {
+                eprintln!("Warning: Persistent id {id} is used by more than one node");
+            } else {
+                nodes.insert(id.to_string(), node.clone());
+            }
+        }
+    }
+    nodes
+}
@@ -0,0 +1,648 @@
+//! The scene: a root node plus scene-wide settings such as the background.
+//!
+//! A [`Scene`] owns the root [`Object3D`] of the scene graph. Everything added under the
+//! root is drawn by the renderer each frame, lit by the lights found in the graph and
+//! (optionally) by ambient light derived from the scene's [`Background`].
+
+use std::cell::{Ref, RefCell};
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use crate::engine::activation::ActivationSettings;
+use crate::engine::animation::clip::{advance_players, AnimationPlayer};
+use crate::engine::animation::skeleton::{update_skins, Skin};
+use crate::engine::background::Background;
+use crate::engine::camera::Camera;
+use crate::engine::export::gltf::write_gltf;
+use crate::engine::light::{EnvironmentLight, LightLod, LightSet};
+use crate::engine::math::color::Color;
+use crate::engine::math::ray::Ray;
+use crate::engine::object3d::Object3D;
+use crate::engine::physics::cloth::update_cloth;
+use crate::engine::physics::spatial_hash::SpatialHash;
+use crate::engine::query::{is_under, tagged, with_components, ComponentQuery};
+use crate::engine::render_queue::RenderQueue;
+use crate::engine::shadow::{ShadowAtlas, ShadowSettings};
+use crate::engine::simulation::SimulationSettings;
+use crate::engine::snapshot::FrameSnapshot;
+use crate::engine::stats::SceneStatistics;
+use crate::engine::texture::Cubemap;
+use crate::engine::time::Clock;
+
+/// Default cell size of the grid behind [`Scene::neighbors_within`].
+const DEFAULT_NEIGHBOR_CELL_SIZE: f32 = 4.0;
+
+/// A renderable scene graph with its environment.
+///
+/// # Example
+/// ```no_run
+/// # use rustge::engine::{background::{Background, ProceduralSky}, object3d::Object3D, scene::Scene};
+/// let mut scene = Scene::new();
+/// scene.set_background(Some(Background::Sky(ProceduralSky::default())));
+/// scene.set_environment_lighting(Some(0.5));
+/// scene.add(Object3D::new());
+/// ```
+#[derive(Debug)]
+pub struct Scene {
+    /// Root of the scene graph.
+    root: Rc<RefCell<Object3D>>,
+
+    /// What is shown behind the geometry; `None` uses the renderer's clear color.
+    background: Option<Background>,
+
+    /// Intensity of the ambient light derived from the background, if enabled.
+    environment_lighting: Option<f32>,
+
+    /// Distances at which lights are rendered in less detail; `None` keeps every light in
+    /// full detail (up to [`MAX_LIGHTS`](crate::engine::light::MAX_LIGHTS)).
+    light_lod: Option<LightLod>,
+
+    /// Gravity and timestep for the scene's physics and particle systems.
+    simulation: SimulationSettings,
+
+    /// Distances at which update callbacks run less often or sleep; `None` updates every
+    /// node every frame.
+    activation: Option<ActivationSettings>,
+
+    /// Draw commands of the last frame, kept to reuse the allocation.
+    render_queue: RefCell<RenderQueue>,
+
+    /// How point and spot light shadows are rendered; `None` renders none.
+    shadows: Option<ShadowSettings>,
+
+    /// Shadow maps of the last frame.
+    shadow_atlas: RefCell<ShadowAtlas>,
+
+    /// Cell size of the node position grid behind [`neighbors_within`](Self::neighbors_within).
+    neighbor_cell_size: f32,
+
+    /// Node positions bucketed for [`neighbors_within`](Self::neighbors_within), built on
+    /// the first query after each update.
+    neighbors: RefCell<Option<SpatialHash<Rc<RefCell<Object3D>>>>>,
+}
+
+impl Scene {
+    /// Creates an empty scene with no background (the renderer's clear color shows through)
+    /// and no environment lighting.
+    pub fn new() -> Self {
+        Self {
+            root: Object3D::new(),
+            background: None,
+            environment_lighting: None,
+            light_lod: None,
+            simulation: SimulationSettings::default(),
+            activation: None,
+            render_queue: RefCell::new(RenderQueue::new()),
+            shadows: None,
+            shadow_atlas: RefCell::new(ShadowAtlas::new()),
+            neighbor_cell_size: DEFAULT_NEIGHBOR_CELL_SIZE,
+            neighbors: RefCell::new(None),
+        }
+    }
+
+    /// The root node. Transforming it transforms the whole scene.
+    pub fn root(&self) -> &Rc<RefCell<Object3D>> {
+        &self.root
+    }
+
+    /// Adds a node directly under the root.
+    pub fn add(&self, child: Rc<RefCell<Object3D>>) {
+        Object3D::add_child(&self.root, child);
+        self.invalidate_neighbors();
+    }
+
+    /// Every node in the scene with `tag` (see [`Object3D::add_tag`]), from the tag index
+    /// rather than a search of the graph. The order is unspecified.
+    pub fn with_tag(&self, tag: &str) -> impl Iterator<Item = Rc<RefCell<Object3D>>> + '_ {
+        tagged(tag).into_iter().filter(|node| is_under(&self.root, node))
+    }
+
+    /// The first node in the scene named `name` (see [`Object3D::set_name`]), searching the
+    /// graph depth first.
+    ///
+    /// # Panics
+    /// Panics if a node is mutably borrowed.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::{object3d::Object3D, scene::Scene};
+    /// let scene = Scene::new();
+    /// let (player, squad) = (Object3D::new(), Object3D::new());
+    /// player.borrow_mut().set_name("player");
+    /// for _ in 0..3 {
+    ///     let enemy = Object3D::new();
+    ///     enemy.borrow_mut().add_tag("enemy");
+    ///     Object3D::add_child(&squad, enemy);
+    /// }
+    /// scene.add(player.clone());
+    /// scene.add(squad);
+    ///
+    /// assert!(std::rc::Rc::ptr_eq(&scene.find_by_name("player").unwrap(), &player));
+    /// assert_eq!(scene.find_all_with_tag("enemy").len(), 3);
+    ///
+    /// let mut nodes = 0;
+    /// scene.traverse(|_| nodes += 1);
+    /// assert_eq!(nodes, 6); // the root, the player, the squad and its enemies
+    /// ```
+    pub fn find_by_name(&self, name: &str) -> Option<Rc<RefCell<Object3D>>> {
+        Object3D::find_by_name(&self.root, name)
+    }
+
+    /// Every node in the scene with `tag`, in depth-first graph order. [`with_tag`](Self::with_tag)
+    /// is faster when the order doesn't matter.
+    ///
+    /// # Panics
+    /// Panics if a node is mutably borrowed.
+    pub fn find_all_with_tag(&self, tag: &str) -> Vec<Rc<RefCell<Object3D>>> {
+        Object3D::find_all_with_tag(&self.root, tag)
+    }
+
+    /// Calls `f` with every node in the scene, the root first, depth first; see
+    /// [`Object3D::traverse`].
+    pub fn traverse(&self, f: impl FnMut(&Rc<RefCell<Object3D>>)) {
+        Object3D::traverse(&self.root, f);
+    }
+
+    /// Every node in the scene with a component of each type in the tuple `Q` (see
+    /// [`Object3D::insert_component`]), e.g. `scene.query::<(Health, Poisoned)>()`, from
+    /// the per-type indices rather than a search of the graph. The order is unspecified.
+    ///
+    /// See [`query`](crate::engine::query) for an example.
+    pub fn query<Q: ComponentQuery>(&self) -> impl Iterator<Item = Rc<RefCell<Object3D>>> + '_ {
+        with_components(&Q::type_ids()).into_iter().filter(|node| is_under(&self.root, node))
+    }
+
+    /// Calls `f` with each node in the scene that has a component of type `T`, and that
+    /// component. The component is taken out of the node during the call, so `f` sees the
+    /// node without it: removing it from within `f` has no effect, and a component of type
+    /// `T` inserted by `f` replaces it.
+    ///
+    /// # Panics
+    /// Panics if a node is already borrowed.
+    pub fn for_each<T: 'static>(&self, mut f: impl FnMut(&mut Object3D, &mut T)) {
+        for node in self.query::<(T,)>() {
+            let mut node = node.borrow_mut();
+            let Some(mut component) = node.take_component::<T>() else {
+                continue;
+            };
+            f(&mut node, &mut component);
+            node.restore_component(component);
+        }
+    }
+
+    /// The nearest mesh triangle in the scene hit by `ray`, for shooting, placing objects
+    /// and line of sight. Tests actual triangles, from either side, through each mesh's
+    /// [`MeshBvh`](crate::engine::mesh::bvh::MeshBvh); nodes whose geometry isn't made of
+    /// triangles are never hit, and skinned meshes are hit in their bind pose. Hidden and
+    /// disabled nodes are skipped along with their descendants. See [`Object3D::raycast`],
+    /// and [`raycast_layers`](Self::raycast_layers) to only hit some render layers.
+    ///
+    /// The distance is in units of the ray's direction, which [`Ray::new`] normalizes.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::{math::ray::Ray, object3d::{Geometry, Object3D, Topology}, scene::Scene};
+    /// let scene = Scene::new();
+    /// let wall = Object3D::new();
+    /// wall.borrow_mut().set_geometry(Geometry::from_positions(
+    ///     Topology::Triangles,
+    ///     &[[0.0, -1.0, -1.0], [0.0, 1.0, -1.0], [0.0, 0.0, 1.0]],
+    /// ));
+    /// wall.borrow_mut().set_position([10.0, 0.0, 0.0]);
+    /// scene.add(wall.clone());
+    ///
+    /// let hit = scene.raycast(&Ray::new([0.0; 3], [1.0, 0.0, 0.0])).unwrap();
+    /// assert!(std::rc::Rc::ptr_eq(&hit.node, &wall));
+    /// assert_eq!((hit.distance, hit.point, hit.normal), (10.0, [10.0, 0.0, 0.0], [-1.0, 0.0, 0.0]));
+    ///
+    /// // Line of sight: nothing in the way within 5 units
+    /// assert!(scene.raycast(&Ray::new([0.0; 3], [1.0, 0.0, 0.0])).is_none_or(|hit| hit.distance > 5.0));
+    /// ```
+    pub fn raycast(&self, ray: &Ray) -> Option<RayHit> {
+        self.raycast_layers(ray, u32::MAX)
+    }
+
+    /// Like [`raycast`](Self::raycast), but only hits nodes in one of the
+    /// [render layers](Object3D::set_layers) set in `layer_mask`, e.g. to ignore effects.
+    ///
+    /// ```
+    /// # use rustge::engine::{math::ray::Ray, object3d::{Geometry, Object3D, Topology}, scene::Scene};
+    /// const EFFECTS: u32 = 1 << 1;
+    /// let scene = Scene::new();
+    /// let sparks = Object3D::new();
+    /// sparks.borrow_mut().set_geometry(Geometry::from_positions(
+    ///     Topology::Triangles,
+    ///     &[[1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [1.0, 0.0, 1.0]],
+    /// ));
+    /// sparks.borrow_mut().set_layers(EFFECTS);
+    /// scene.add(sparks);
+    ///
+    /// let ray = Ray::new([0.0; 3], [1.0, 0.0, 0.0]);
+    /// assert!(scene.raycast(&ray).is_some());
+    /// assert!(scene.raycast_layers(&ray, !EFFECTS).is_none());
+    /// ```
+    pub fn raycast_layers(&self, ray: &Ray, layer_mask: u32) -> Option<RayHit> {
+        let mut nearest = None;
+        raycast_subtree(&self.root, ray, layer_mask, &mut nearest);
+        nearest
+    }
+
+    /// Every node in the scene whose world position is within `radius` of `position`, for
+    /// flocking, area damage and picking targets; a node at `position` is included. The
+    /// order is unspecified.
+    ///
+    /// Positions come from a [`SpatialHash`] of every node, built on the first query after
+    /// each [`update`](Self::update), so many queries per frame stay cheap. Nodes moved
+    /// since are found where they were; call [`invalidate_neighbors`](Self::invalidate_neighbors)
+    /// after moving them to query their new positions.
+    ///
+    /// # Panics
+    /// Panics if a node is already borrowed when the grid is built.
+    ///
+    /// # Example
+    /// ```
+    /// # use rustge::engine::{object3d::Object3D, scene::Scene};
+    /// let scene = Scene::new();
+    /// let boids: Vec<_> = (0..50).map(|i| {
+    ///     let boid = Object3D::new();
+    ///     boid.borrow_mut().set_position([i as f32 * 0.5, 0.0, 0.0]);
+    ///     scene.add(boid.clone());
+    ///     boid
+    /// }).collect();
+    ///
+    /// let position = boids[10].borrow().position();
+    /// let flock = scene.neighbors_within(position, 1.0);
+    /// assert_eq!(flock.len(), 5); // itself and two on either side
+    /// ```
+    pub fn neighbors_within(&self, position: [f32; 3], radius: f32) -> Vec<Rc<RefCell<Object3D>>> {
+        let mut neighbors = self.neighbors.borrow_mut();
+        let grid = neighbors.get_or_insert_with(|| {
+            let mut grid = SpatialHash::new(self.neighbor_cell_size);
+            let children = self.root.borrow().children().to_vec();
+            for child in &children {
+                insert_positions(child, &mut grid);
+            }
+            grid
+        });
+        grid.within(position, radius).into_iter().cloned().collect()
+    }
+
+    /// Drops the positions [`neighbors_within`](Self::neighbors_within) searches, so the
+    /// next query sees where nodes are now.
+    pub fn invalidate_neighbors(&self) {
+        self.neighbors.borrow_mut().take();
+    }
+
+    /// Sets the cell size of the grid behind [`neighbors_within`](Self::neighbors_within),
+    /// best about the usual query radius (default 4 units).
+    pub fn set_neighbor_cell_size(&mut self, size: f32) {
+        self.neighbor_cell_size = size;
+        self.invalidate_neighbors();
+    }
+
+    /// The cell size of the grid behind [`neighbors_within`](Self::neighbors_within).
+    pub fn neighbor_cell_size(&self) -> f32 {
+        self.neighbor_cell_size
+    }
+
+    /// Sets the background, or `None` to show the renderer's clear color.
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background = background;
+    }
+
+    /// Sets a skybox as the background, a shorthand for
+    /// [`set_background`](Self::set_background) with [`Background::Skybox`].
+    ///
+    /// ```no_run
+    /// # use rustge::engine::{scene::Scene, texture::Cubemap};
+    /// # let mut scene = Scene::new();
+    /// scene.set_skybox(Cubemap::load_equirectangular("sky.png", 1024).expect("failed to load sky"));
+    /// ```
+    pub fn set_skybox(&mut self, cubemap: Cubemap) {
+        self.background = Some(Background::Skybox(Rc::new(cubemap)));
+    }
+
+    /// The scene's background, if any.
+    pub fn background(&self) -> Option<&Background> {
+        self.background.as_ref()
+    }
+
+    /// Enables (`Some(intensity)`) or disables (`None`) ambient lighting derived from the
+    /// background's average color, added on top of any ambient lights in the graph.
+    /// [PBR materials](crate::engine::pbr) are lit by a skybox image itself.
+    pub fn set_environment_lighting(&mut self, intensity: Option<f32>) {
+        self.environment_lighting = intensity;
+    }
+
+    /// Intensity of the environment lighting, if enabled.
+    pub fn environment_lighting(&self) -> Option<f32> {
+        self.environment_lighting
+    }
+
+    /// The ambient color the background contributes, or `None` if environment lighting is
+    /// disabled or there is no background.
+    pub fn environment_ambient(&self) -> Option<Color> {
+        let intensity = self.environment_lighting?;
+        let color = self.background.as_ref()?.ambient_color();
+        Some(Color::linear_rgba(color.r * intensity, color.g * intensity, color.b * intensity, color.a))
+    }
+
+    /// Enables (`Some`) or disables (`None`) distance-based light level of detail: distant
+    /// point and spot lights lose shadows, then specular highlights, and are finally merged
+    /// into ambient. With or without it, lights beyond [`MAX_LIGHTS`](crate::engine::light::MAX_LIGHTS)
+    /// are merged into ambient, farthest first.
+    ///
+    /// ```no_run
+    /// # use rustge::engine::{light::LightLod, scene::Scene};
+    /// # let mut scene = Scene::new();
+    /// scene.set_light_lod(Some(LightLod { shadow_distance: 15.0, ..LightLod::default() }));
+    /// ```
+    pub fn set_light_lod(&mut self, lod: Option<LightLod>) {
+        self.light_lod = lod;
+    }
+
+    /// The light level-of-detail distances, if enabled.
+    pub fn light_lod(&self) -> Option<&LightLod> {
+        self.light_lod.as_ref()
+    }
+
+    /// Enables (`Some`) or disables (`None`) shadows of point and spot lights, packed into
+    /// a [shadow atlas](crate::engine::shadow). The renderer draws them every frame before
+    /// the scene.
+    pub fn set_shadows(&mut self, settings: Option<ShadowSettings>) {
+        self.shadows = settings;
+        if settings.is_none() {
+            self.shadow_atlas.borrow_mut().clear();
+        }
+    }
+
+    /// The shadow settings, if shadows are enabled.
+    pub fn shadows(&self) -> Option<&ShadowSettings> {
+        self.shadows.as_ref()
+    }
+
+    /// The shadow atlas, e.g. to inspect how the last frame's tiles were allocated.
+    pub fn shadow_atlas(&self) -> Ref<'_, ShadowAtlas> {
+        self.shadow_atlas.borrow()
+    }
+
+    /// Renders the shadow maps of the lights [`collect_lights_for(camera)`](Self::collect_lights_for)
+    /// returns, each up to `max_resolution` texels across, into the shadow atlas. Returns the
+    /// number of atlas tiles rendered, 0 if shadows are disabled. Drawing with `camera`
+    /// afterwards samples them.
+    ///
+    /// Called every frame by the renderer, with the quality settings'
+    /// [`shadow_resolution`](crate::engine::quality::QualitySettings::shadow_resolution).
+    pub fn render_shadows(&self, camera: &Camera, max_resolution: u32) -> usize {
+        let Some(settings) = &self.shadows else {
+            return 0;
+        };
+        let mut lights = self.collect_lights();
+        lights.apply_lod(camera.position, self.light_lod.as_ref());
+        self.shadow_atlas.borrow_mut().render(&self.root, &lights, camera.position, settings, max_resolution)
+    }
+
+    /// Sets the gravity, timestep and step limits the scene's simulations run with.
+    ///
+    /// ```
+    /// # use rustge::engine::{scene::Scene, simulation::SimulationSettings};
+    /// let mut scene = Scene::new();
+    /// scene.set_simulation_settings(SimulationSettings { gravity: [0.0, -1.62, 0.0], ..SimulationSettings::default() });
+    /// assert_eq!(scene.simulation_settings().gravity[1], -1.62);
+    /// ```
+    pub fn set_simulation_settings(&mut self, settings: SimulationSettings) {
+        self.simulation = settings;
+    }
+
+    /// The scene's simulation settings; defaults to Earth gravity at 60 steps per second.
+    pub fn simulation_settings(&self) -> &SimulationSettings {
+        &self.simulation
+    }
+
+    /// Mutable access to the simulation settings, e.g. to change only gravity.
+    pub fn simulation_settings_mut(&mut self) -> &mut SimulationSettings {
+        &mut self.simulation
+    }
+
+    /// Enables (`Some`) or disables (`None`) distance-bucketed activation: update callbacks
+    /// of distant nodes run every few frames, or not at all beyond the last band. See
+    /// [`activation`](crate::engine::activation).
+    pub fn set_activation(&mut self, activation: Option<ActivationSettings>) {
+        self.activation = activation;
+    }
+
+    /// The activation bands, if enabled.
+    pub fn activation(&self) -> Option<&ActivationSettings> {
+        self.activation.as_ref()
+    }
+
+    /// Mutable access to the activation settings, e.g. to move the
+    /// [`focus`](ActivationSettings::focus) with the player each frame.
+    pub fn activation_mut(&mut self) -> Option<&mut ActivationSettings> {
+        self.activation.as_mut()
+    }
+
+    /// Gathers every light in the scene, including the environment's ambient contribution.
+    /// With a skybox, its cube map also becomes the set's
+    /// [environment](LightSet::environment) for image-based lighting.
+    pub fn collect_lights(&self) -> LightSet {
+        let mut lights = LightSet::new();
+        self.root.borrow_mut().collect_lights(&mut lights);
+        if let Some(ambient) = self.environment_ambient() {
+            lights.ambient[0] += ambient.r;
+            lights.ambient[1] += ambient.g;
+            lights.ambient[2] += ambient.b;
+        }
+        if let (Some(intensity), Some(Background::Skybox(cubemap))) = (self.environment_lighting, &self.background) {
+            lights.environment = Some(EnvironmentLight { cubemap: cubemap.clone(), intensity });
+        }
+        lights
+    }
+
+    /// Gathers the lights as [`collect_lights`](Self::collect_lights) does, fitted to what
+    /// a shader takes for `camera` (see [`LightSet::apply_lod`]), with the shadow maps of
+    /// the last [`render_shadows`](Self::render_shadows). This is what the scene is drawn with.
+    pub fn collect_lights_for(&self, camera: &Camera) -> LightSet {
+        let mut lights = self.collect_lights();
+        lights.apply_lod(camera.position, self.light_lod.as_ref());
+        if self.shadows.is_some() {
+            self.shadow_atlas.borrow().attach(&mut lights);
+        }
+        lights
+    }
+
+    /// Counts the scene's nodes, geometry, materials and lights, with memory estimates.
+    ///
+    /// Use [`Object3D::statistics`] for a single subtree.
+    pub fn statistics(&self) -> SceneStatistics {
+        self.root.borrow().statistics()
+    }
+
+    /// Writes the scene graph (transforms, geometry, materials and lights) to a glTF file:
+    /// binary `.glb` if `path` ends in `.glb`, otherwise `.gltf` with a `.bin` buffer beside it.
+    ///
+    /// See [`export::gltf`](crate::engine::export::gltf) for what is and isn't exported.
+    pub fn export_gltf(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_gltf(&self.root, path)
+    }
+
+    /// Every node the camera can currently see, by the same frustum test the renderer culls
+    /// with (see [`Camera::is_visible`]), in depth-first order.
+    ///
+    /// Useful for gameplay logic such as AI perception or audio prioritization. Occlusion
+    /// by other objects is not considered.
+    pub fn visible_set(&self, camera: &Camera) -> Vec<Rc<RefCell<Object3D>>> {
+        Object3D::visible_set(&self.root, camera)
+    }
+
+    /// Copies the world transforms of every node and `camera` into a [`FrameSnapshot`] other
+    /// threads can read (see [`snapshot`](crate::engine::snapshot)).
+    pub fn snapshot(&self, camera: Option<&Camera>, clock: &Clock) -> FrameSnapshot {
+        FrameSnapshot::capture(&self.root, camera, clock)
+    }
+
+    /// Advances [`AnimationPlayer`]s, then runs the per-node update callbacks (see
+    /// [`Object3D::on_update`]), skipping nodes whose
+    /// [`UpdatePolicy`](crate::engine::object3d::UpdatePolicy) pauses them relative to `camera`
+    /// and throttling distant ones if [activation](Self::set_activation) is enabled, then
+    /// simulates [cloth](crate::engine::physics::cloth) on the moved nodes, and finally
+    /// recomputes the joint matrices of [`Skin`]s from the resulting pose.
+    ///
+    /// Called once per frame by the renderer, after the user's update callback.
+    pub fn update(&self, camera: Option<&Camera>, clock: &Clock) {
+        advance_players(self.query::<(AnimationPlayer,)>(), clock.delta());
+        self.root.borrow_mut().update_with_activation(camera, clock, self.activation.as_ref());
+        update_cloth(self, clock.delta());
+        update_skins(self.query::<(Skin,)>());
+        self.invalidate_neighbors();
+    }
+
+    /// Draws the scene graph and the background (if it isn't a plain color): opaque nodes,
+    /// then the background into the pixels they left uncovered, then transparent nodes.
+    ///
+    /// The framebuffer should already be cleared, to [`clear_color`](Self::clear_color)
+    /// if the scene has one, and depth testing enabled.
+    pub fn draw(&self, camera: &Camera) {
+        self.draw_opaque(camera);
+        self.draw_background(camera);
+        self.draw_transparent(camera);
+    }
+
+    /// Draws only the background, at the far plane and depth tested, so drawing it after
+    /// the opaque geometry only shades the pixels nothing covers. Solid color backgrounds
+    /// draw nothing; they are applied by the framebuffer clear.
+    pub fn draw_background(&self, camera: &Camera) {
+        if let Some(background) = &self.background {
+            background.draw(camera);
+        }
+    }
+
+    /// Draws only the scene graph, lit by the scene's lights: [`draw_opaque`](Self::draw_opaque)
+    /// followed by [`draw_transparent`](Self::draw_transparent).
+    pub fn draw_objects(&self, camera: &Camera) {
+        self.draw_opaque(camera);
+        self.draw_transparent(camera);
+    }
+
+    /// Draws the visible opaque nodes through a [`RenderQueue`], grouped by shader, render
+    /// state and mesh, and keeps the transparent ones queued for
+    /// [`draw_transparent`](Self::draw_transparent).
+    pub fn draw_opaque(&self, camera: &Camera) {
+        let lights = self.collect_lights_for(camera);
+        let mut queue = self.render_queue.borrow_mut();
+        queue.collect(&self.root, camera);
+        queue.sort();
+        queue.submit_opaque(camera, &lights);
+    }
+
+    /// Draws the transparent nodes queued by the last [`draw_opaque`](Self::draw_opaque)
+    /// back to front, then empties the queue.
+    pub fn draw_transparent(&self, camera: &Camera) {
+        let mut queue = self.render_queue.borrow_mut();
+        if queue.transparent_len() > 0 {
+            queue.submit_transparent(camera, &self.collect_lights_for(camera));
+        }
+        queue.clear();
+    }
+
+    /// Whether the last [`draw_opaque`](Self::draw_opaque) queued transparent nodes that
+    /// [`draw_transparent`](Self::draw_transparent) has yet to draw.
+    pub fn has_queued_transparent(&self) -> bool {
+        self.render_queue.borrow().transparent_len() > 0
+    }
+
+    /// The color the framebuffer should be cleared to for this scene, if its background
+    /// is a solid color.
+    pub fn clear_color(&self) -> Option<Color> {
+        match self.background {
+            Some(Background::Color(color)) => Some(color),
+            _ => None,
+        }
+    }
+}
+
+/// Where a [`Scene::raycast`] hit the scene.
+#[derive(Clone, Debug)]
+pub struct RayHit {
+    /// The node whose mesh was hit.
+    pub node: Rc<RefCell<Object3D>>,
+
+    /// Distance from the ray's origin, in units of its direction.
+    pub distance: f32,
+
+    /// The world-space point hit.
+    pub point: [f32; 3],
+
+    /// The world-space normal of the triangle hit, facing the ray's origin.
+    pub normal: [f32; 3],
+}
+
+/// Inserts the world positions of `node` and its descendants into `grid`.
+fn insert_positions(node: &Rc<RefCell<Object3D>>, grid: &mut SpatialHash<Rc<RefCell<Object3D>>>) {
+    let children = {
+        let mut object = node.borrow_mut();
+        let world = object.world_matrix();
+        grid.insert_point(node.clone(), [world[12], world[13], world[14]]);
+        object.children().to_vec()
+    };
+    for child in &children {
+        insert_positions(child, grid);
+    }
+}
+
+/// Tests `ray` against the nodes of `node`'s subtree in `layer_mask`, keeping the nearest
+/// hit in `nearest`. Hidden and disabled subtrees are skipped, and so are subtrees whose
+/// bounding sphere the ray misses or only reaches beyond the nearest hit so far.
+fn raycast_subtree(node: &Rc<RefCell<Object3D>>, ray: &Ray, layer_mask: u32, nearest: &mut Option<RayHit>) {
+    {
+        let object = node.borrow();
+        if !object.visible() || !object.enabled() {
+            return;
+        }
+    }
+    let limit = nearest.as_ref().map_or(f32::INFINITY, |hit| hit.distance);
+    let reaches = Object3D::subtree_bounding_sphere(node)
+        .is_some_and(|(center, radius)| ray.intersect_sphere(center, radius).is_some_and(|t| t <= limit));
+    if !reaches {
+        return;
+    }
+
+    let children = {
+        let mut object = node.borrow_mut();
+        if object.layers() & layer_mask != 0
+            && let Some((distance, normal)) = object.raycast(ray, limit)
+        {
+            *nearest = Some(RayHit { node: node.clone(), distance, point: ray.at(distance), normal });
+        }
+        object.children().to_vec()
+    };
+    for child in &children {
+        raycast_subtree(child, ray, layer_mask, nearest);
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,9 +1,25 @@
-use gl::types::{GLenum, GLuint};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use crate::engine::color_space::with_pipeline_define;
+use crate::engine::debug::gl_debug::label_object;
+use crate::engine::program_cache;
+use crate::engine::uniform_buffer::bind_standard_blocks;
 
 pub fn compile_shader(src: &str, kind: GLenum) -> GLuint {
+    try_compile_shader(src, kind).unwrap_or_else(|log| panic!("Shader compile error: {:?}", log))
+}
+
+/// Compiles a shader of `kind`, returning the driver's info log if compilation fails.
+pub fn try_compile_shader(src: &str, kind: GLenum) -> Result<GLuint, String> {
     unsafe {
         let shader = gl::CreateShader(kind);
-        gl::ShaderSource(shader, 1, [src.as_ptr() as *const _].as_ptr(), std::ptr::null());
+        let len = src.len() as GLint;
+        gl::ShaderSource(shader, 1, [src.as_ptr() as *const _].as_ptr(), &len);
         gl::CompileShader(shader);
 
         // Check compile status
@@ -12,46 +28,427 @@ pub fn compile_shader(src: &str, kind: GLenum) -> GLuint {
         if status == 0 {
             let mut len = 0;
             gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buf = Vec::with_capacity(len as usize);
+            let mut buf = vec![0u8; len.max(1) as usize];
             gl::GetShaderInfoLog(shader, len, std::ptr::null_mut(), buf.as_mut_ptr() as *mut _);
-            panic!("Shader compile error: {:?}", String::from_utf8_lossy(&buf));
+            gl::DeleteShader(shader);
+            return Err(String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string());
         }
 
-        shader
+        Ok(shader)
     }
 }
 
 pub fn create_shader_program(vs_src: &str, fs_src: &str) -> GLuint {
-    unsafe {
-        let vs = compile_shader(vs_src, gl::VERTEX_SHADER);
-        let fs = compile_shader(fs_src, gl::FRAGMENT_SHADER);
+    build_program(&[(gl::VERTEX_SHADER, vs_src), (gl::FRAGMENT_SHADER, fs_src)])
+}
+
+/// Compiles and links a compute program (OpenGL 4.3).
+pub fn create_compute_program(cs_src: &str) -> GLuint {
+    build_program(&[(gl::COMPUTE_SHADER, cs_src)])
+}
+
+/// Compiles and links a program from vertex and fragment shader sources, returning the
+/// driver's info log if compilation or linking fails.
+pub fn try_create_shader_program(vs_src: &str, fs_src: &str) -> Result<GLuint, String> {
+    try_build_program(&[(gl::VERTEX_SHADER, vs_src), (gl::FRAGMENT_SHADER, fs_src)])
+}
+
+/// Compiles and links a compute program, returning the driver's info log on failure.
+pub fn try_create_compute_program(cs_src: &str) -> Result<GLuint, String> {
+    try_build_program(&[(gl::COMPUTE_SHADER, cs_src)])
+}
+
+/// Builds a program from shader `stages` (kind and source), panicking on errors.
+fn build_program(stages: &[(GLenum, &str)]) -> GLuint {
+    try_build_program(stages).unwrap_or_else(|log| panic!("Shader build failed: {:?}", log))
+}
+
+/// Builds a program from shader `stages` (kind and source), from the
+/// [program cache](crate::engine::program_cache) if it has it, else by compiling and
+/// linking them, and links its [standard uniform blocks](crate::engine::uniform_buffer);
+/// returns the driver's info log on failure. Every stage gets the
+/// [color space define](crate::engine::color_space) after its `#version` line.
+fn try_build_program(stages: &[(GLenum, &str)]) -> Result<GLuint, String> {
+    let sources: Vec<String> = stages.iter().map(|&(_, source)| with_pipeline_define(source)).collect();
+    let stages: Vec<(GLenum, &str)> = stages.iter().zip(&sources).map(|(&(kind, _), source)| (kind, source.as_str())).collect();
+    let program = match program_cache::load(&stages) {
+        Some(program) => program,
+        None => try_compile_and_link(&stages)?,
+    };
+    bind_standard_blocks(program);
+    Ok(program)
+}
 
+/// Compiles and links shader `stages`, storing the program in the
+/// [program cache](crate::engine::program_cache).
+fn try_compile_and_link(stages: &[(GLenum, &str)]) -> Result<GLuint, String> {
+    let mut shaders = Vec::with_capacity(stages.len());
+    for &(kind, source) in stages {
+        match try_compile_shader(source, kind) {
+            Ok(shader) => shaders.push(shader),
+            Err(log) => {
+                for shader in shaders {
+                    unsafe { gl::DeleteShader(shader) };
+                }
+                return Err(log);
+            }
+        }
+    }
+    let program = try_link_program(&shaders)?;
+    program_cache::store(stages, program);
+    Ok(program)
+}
+
+/// Links the compiled `shaders` into a program, deleting them afterwards; returns the
+/// driver's info log if linking fails.
+fn try_link_program(shaders: &[GLuint]) -> Result<GLuint, String> {
+    unsafe {
         let program = gl::CreateProgram();
-        gl::AttachShader(program, vs);
-        gl::AttachShader(program, fs);
+        for &shader in shaders {
+            gl::AttachShader(program, shader);
+        }
+        if program_cache::is_enabled() {
+            gl::ProgramParameteri(program, gl::PROGRAM_BINARY_RETRIEVABLE_HINT, gl::TRUE as GLint);
+        }
         gl::LinkProgram(program);
+        for &shader in shaders {
+            gl::DeleteShader(shader);
+        }
 
         // Check link status
         let mut status = 0;
         gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
         if status == 0 {
-            panic!("Shader linking failed");
+            let mut len = 0;
+            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+            let mut buf = vec![0u8; len.max(1) as usize];
+            gl::GetProgramInfoLog(program, len, std::ptr::null_mut(), buf.as_mut_ptr() as *mut _);
+            gl::DeleteProgram(program);
+            return Err(String::from_utf8_lossy(&buf).trim_end_matches('\0').to_string());
         }
 
-        gl::DeleteShader(vs);
-        gl::DeleteShader(fs);
-
-        program
+        Ok(program)
     }
 }
 
-#[derive(Clone, Debug)]
+/// A typed value that can be uploaded to a shader uniform.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4([f32; 16]),
+}
+
+/// A linked OpenGL shader program.
+///
+/// Owns the GL program object (deleted on drop) and caches uniform locations by name,
+/// so repeated uniform uploads don't query the driver every frame.
+///
+/// Uniform setters act on the currently bound program: call [`use_program`](Self::use_program)
+/// before setting uniforms. Setting a uniform the program doesn't declare (or that the GLSL
+/// compiler optimized away) is silently ignored, as in OpenGL itself.
+///
+/// # Example
+/// ```no_run
+/// # use rustge::engine::shader::GLShaderProgram;
+/// let shader = GLShaderProgram::from_sources(
+///     "#version 330 core\nlayout(location = 0) in vec3 a_position;\nuniform mat4 u_model;\nvoid main() { gl_Position = u_model * vec4(a_position, 1.0); }",
+///     "#version 330 core\nuniform vec4 u_color;\nout vec4 frag_color;\nvoid main() { frag_color = u_color; }",
+/// );
+/// shader.use_program();
+/// shader.set_uniform_vec4("u_color", [1.0, 0.0, 0.0, 1.0]);
+/// ```
+#[derive(Debug)]
 pub struct GLShaderProgram {
+    /// The OpenGL program name; replaced when the program is [reloaded](Self::reload).
+    id: Cell<GLuint>,
 
+    /// Cache of uniform name → location. `-1` is cached too, for uniforms that don't exist.
+    uniform_locations: RefCell<HashMap<String, GLint>>,
+
+    /// Cache of uniform block name → whether the program declares it.
+    uniform_blocks: RefCell<HashMap<String, bool>>,
+
+    /// Debug label, attached again to the new program on reload.
+    label: RefCell<String>,
 }
 
 impl GLShaderProgram {
+    fn with_id(id: GLuint) -> Self {
+        Self {
+            id: Cell::new(id),
+            uniform_locations: RefCell::new(HashMap::new()),
+            uniform_blocks: RefCell::new(HashMap::new()),
+            label: RefCell::new(String::new()),
+        }
+    }
+
+    /// Compiles and links a program from vertex and fragment shader sources.
+    ///
+    /// # Panics
+    /// Panics with the driver's info log if compilation or linking fails.
+    pub fn from_sources(vs_src: &str, fs_src: &str) -> Self {
+        Self::with_id(create_shader_program(vs_src, fs_src))
+    }
+
+    /// Compiles and links a compute program. Requires OpenGL 4.3.
+    ///
+    /// # Panics
+    /// Panics with the driver's info log if compilation or linking fails.
+    pub fn from_compute_source(cs_src: &str) -> Self {
+        Self::with_id(create_compute_program(cs_src))
+    }
+
+    /// Compiles and links a program from the vertex and fragment shader source files at
+    /// `vertex_path` and `fragment_path`. Compile and link errors come back as
+    /// [`InvalidData`](io::ErrorKind::InvalidData) errors carrying the driver's info log.
+    /// The program is [labeled](Self::set_label) with the file names.
+    pub fn load(vertex_path: impl AsRef<Path>, fragment_path: impl AsRef<Path>) -> io::Result<Self> {
+        let (vertex_path, fragment_path) = (vertex_path.as_ref(), fragment_path.as_ref());
+        let (vs_src, fs_src) = (fs::read_to_string(vertex_path)?, fs::read_to_string(fragment_path)?);
+        let id = try_create_shader_program(&vs_src, &fs_src).map_err(|log| io::Error::new(io::ErrorKind::InvalidData, log))?;
+        let program = Self::with_id(id);
+        program.set_label(&format!("{} + {}", vertex_path.display(), fragment_path.display()));
+        Ok(program)
+    }
+
+    /// Compiles and links a compute program from the source file at `path`, like
+    /// [`load`](Self::load). Requires OpenGL 4.3.
+    pub fn load_compute(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let cs_src = fs::read_to_string(path)?;
+        let id = try_create_compute_program(&cs_src).map_err(|log| io::Error::new(io::ErrorKind::InvalidData, log))?;
+        let program = Self::with_id(id);
+        program.set_label(&path.display().to_string());
+        Ok(program)
+    }
+
+    /// Names the program in [GL debug messages](crate::engine::debug::gl_debug) and
+    /// graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        label_object(gl::PROGRAM, self.id(), label);
+        *self.label.borrow_mut() = label.to_string();
+    }
+
+    /// Replaces the program with one compiled from new vertex and fragment shader sources,
+    /// in place, so every material sharing it draws with the new version. If compiling or
+    /// linking fails, the program stays as it was and the driver's info log is returned.
+    pub fn reload(&self, vs_src: &str, fs_src: &str) -> Result<(), String> {
+        self.replace(try_create_shader_program(vs_src, fs_src)?);
+        Ok(())
+    }
+
+    /// Replaces a compute program in place, like [`reload`](Self::reload).
+    pub fn reload_compute(&self, cs_src: &str) -> Result<(), String> {
+        self.replace(try_create_compute_program(cs_src)?);
+        Ok(())
+    }
+
+    /// Swaps in program `id`, deleting the old one and forgetting its uniform locations and
+    /// blocks.
+    fn replace(&self, id: GLuint) {
+        unsafe {
+            gl::DeleteProgram(self.id.replace(id));
+        }
+        self.uniform_locations.borrow_mut().clear();
+        self.uniform_blocks.borrow_mut().clear();
+        label_object(gl::PROGRAM, id, &self.label.borrow());
+    }
+
+    /// The OpenGL program name. Changes when the program is [reloaded](Self::reload).
+    pub fn id(&self) -> GLuint {
+        self.id.get()
+    }
+
+    /// Makes this the current program (`glUseProgram`).
+    pub fn use_program(&self) {
+        unsafe {
+            gl::UseProgram(self.id.get());
+        }
+    }
+
+    /// Returns the location of a uniform, querying the driver only the first time a name is seen.
+    ///
+    /// Returns `-1` if the program has no active uniform with that name.
+    pub fn uniform_location(&self, name: &str) -> GLint {
+        if let Some(&location) = self.uniform_locations.borrow().get(name) {
+            return location;
+        }
+
+        let c_name = std::ffi::CString::new(name).expect("uniform name contains a NUL byte");
+        let location = unsafe { gl::GetUniformLocation(self.id.get(), c_name.as_ptr()) };
+        self.uniform_locations.borrow_mut().insert(name.to_string(), location);
+        location
+    }
+
+    /// Whether the program declares the uniform block `name`, querying the driver only the
+    /// first time a name is seen. See [`uniform_buffer`](crate::engine::uniform_buffer).
+    pub fn has_uniform_block(&self, name: &str) -> bool {
+        if let Some(&declared) = self.uniform_blocks.borrow().get(name) {
+            return declared;
+        }
+
+        let c_name = std::ffi::CString::new(name).expect("block name contains a NUL byte");
+        let declared = unsafe { gl::GetUniformBlockIndex(self.id.get(), c_name.as_ptr()) } != gl::INVALID_INDEX;
+        self.uniform_blocks.borrow_mut().insert(name.to_string(), declared);
+        declared
+    }
+
+    /// Uploads a 4x4 column-major matrix.
     pub fn set_uniform_matrix4(&self, name: &str, matrix: &[f32; 16]) {
-        
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_ptr());
+            }
+        }
+    }
+
+    /// Uploads an array of 4x4 column-major matrices, such as joint matrices, starting at
+    /// element 0 of the array uniform `name`.
+    pub fn set_uniform_matrix4_array(&self, name: &str, matrices: &[[f32; 16]]) {
+        let location = self.uniform_location(name);
+        if location >= 0 && !matrices.is_empty() {
+            unsafe {
+                gl::UniformMatrix4fv(location, matrices.len() as GLsizei, gl::FALSE, matrices.as_ptr() as *const f32);
+            }
+        }
+    }
+
+    /// Uploads a 3x3 column-major matrix, such as a normal matrix.
+    pub fn set_uniform_matrix3(&self, name: &str, matrix: &[f32; 9]) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::UniformMatrix3fv(location, 1, gl::FALSE, matrix.as_ptr());
+            }
+        }
+    }
+
+    /// Uploads a `vec2`.
+    pub fn set_uniform_vec2(&self, name: &str, value: [f32; 2]) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform2f(location, value[0], value[1]);
+            }
+        }
+    }
+
+    /// Uploads a `vec3`.
+    pub fn set_uniform_vec3(&self, name: &str, value: [f32; 3]) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform3f(location, value[0], value[1], value[2]);
+            }
+        }
+    }
+
+    /// Uploads a `vec4`.
+    pub fn set_uniform_vec4(&self, name: &str, value: [f32; 4]) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform4f(location, value[0], value[1], value[2], value[3]);
+            }
+        }
+    }
+
+    /// Uploads a `float`.
+    pub fn set_uniform_float(&self, name: &str, value: f32) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform1f(location, value);
+            }
+        }
+    }
+
+    /// Uploads an `int` (or `bool`).
+    pub fn set_uniform_int(&self, name: &str, value: i32) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform1i(location, value);
+            }
+        }
     }
+
+    /// Uploads a `uint`.
+    pub fn set_uniform_uint(&self, name: &str, value: u32) {
+        let location = self.uniform_location(name);
+        if location >= 0 {
+            unsafe {
+                gl::Uniform1ui(location, value);
+            }
+        }
+    }
+
+    /// Points a sampler uniform at a texture unit (`GL_TEXTURE0 + unit`).
+    pub fn set_sampler(&self, name: &str, unit: u32) {
+        self.set_uniform_int(name, unit as i32);
+    }
+
+    /// Uploads any [`UniformValue`], dispatching to the matching typed setter.
+    pub fn set_uniform(&self, name: &str, value: &UniformValue) {
+        match *value {
+            UniformValue::Float(v) => self.set_uniform_float(name, v),
+            UniformValue::Int(v) => self.set_uniform_int(name, v),
+            UniformValue::Vec2(v) => self.set_uniform_vec2(name, v),
+            UniformValue::Vec3(v) => self.set_uniform_vec3(name, v),
+            UniformValue::Vec4(v) => self.set_uniform_vec4(name, v),
+            UniformValue::Mat4(ref m) => self.set_uniform_matrix4(name, m),
+        }
+    }
+}
+
+impl Drop for GLShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.id.get());
+        }
+    }
+}
+
+thread_local! {
+    /// Built-in programs compiled so far, keyed by name. Programs belong to the GL context's thread.
+    static BUILTIN_PROGRAMS: RefCell<HashMap<&'static str, Rc<GLShaderProgram>>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the engine's shared instance of a built-in program, compiling it on first use.
+///
+/// Built-in shaders (debug lines, lighting, helpers) are compiled once per context and shared
+/// by every material that uses them.
+pub fn builtin_program(name: &'static str, vs_src: &str, fs_src: &str) -> Rc<GLShaderProgram> {
+    BUILTIN_PROGRAMS.with(|programs| {
+        programs
+            .borrow_mut()
+            .entry(name)
+            .or_insert_with(|| {
+                let program = GLShaderProgram::from_sources(vs_src, fs_src);
+                program.set_label(name);
+                Rc::new(program)
+            })
+            .clone()
+    })
+}
+
+/// Returns the engine's shared instance of a built-in compute program, compiling it on
+/// first use, like [`builtin_program`].
+pub fn builtin_compute_program(name: &'static str, cs_src: &str) -> Rc<GLShaderProgram> {
+    BUILTIN_PROGRAMS.with(|programs| {
+        programs
+            .borrow_mut()
+            .entry(name)
+            .or_insert_with(|| {
+                let program = GLShaderProgram::from_compute_source(cs_src);
+                program.set_label(name);
+                Rc::new(program)
+            })
+            .clone()
+    })
 }
@@ -0,0 +1,64 @@
+#version 330 core
+
+#define MODE_GRADIENT 0
+#define MODE_SKYBOX 1
+#define MODE_SKY 2
+
+in vec3 v_direction;
+
+uniform int u_mode;
+
+// Gradient (linear colors)
+uniform vec3 u_top_color;
+uniform vec3 u_bottom_color;
+
+// Skybox (sRGB-encoded texels, decoded when sampling in the sRGB pipeline)
+uniform samplerCube u_skybox;
+
+// Procedural sky (linear colors)
+uniform vec3 u_sun_direction;
+uniform vec3 u_sun_color;
+uniform float u_sun_cos_radius;
+uniform vec3 u_zenith_color;
+uniform vec3 u_horizon_color;
+uniform vec3 u_ground_color;
+
+out vec4 frag_color;
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+void main() {
+    vec3 dir = normalize(v_direction);
+
+    if (u_mode == MODE_SKYBOX) {
+        frag_color = vec4(texture(u_skybox, dir).rgb, 1.0);
+        return;
+    }
+
+    vec3 color;
+    if (u_mode == MODE_GRADIENT) {
+        color = mix(u_bottom_color, u_top_color, dir.y * 0.5 + 0.5);
+    } else {
+        float h = dir.y;
+        if (h >= 0.0) {
+            color = mix(u_horizon_color, u_zenith_color, sqrt(h));
+        } else {
+            color = mix(u_horizon_color, u_ground_color, clamp(-h * 8.0, 0.0, 1.0));
+        }
+
+        // Sun disk with a soft glow around it
+        float sun = dot(dir, u_sun_direction);
+        float disk = smoothstep(u_sun_cos_radius - 0.0005, u_sun_cos_radius, sun);
+        float glow = pow(max(sun, 0.0), 256.0) * 0.5 + pow(max(sun, 0.0), 8.0) * 0.1;
+        color += u_sun_color * (disk + glow) * step(0.0, h + 0.02);
+    }
+
+#ifdef SRGB_PIPELINE
+    frag_color = vec4(color, 1.0);
+#else
+    // The framebuffer is not sRGB, so encode the result ourselves
+    frag_color = vec4(linear_to_srgb(color), 1.0);
+#endif
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+// Fullscreen triangle generated from gl_VertexID; no vertex buffers needed.
+
+uniform mat4 u_projection;
+uniform mat4 u_view_rotation;   // camera view matrix without translation
+
+out vec3 v_direction;
+
+void main() {
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    vec4 far_point = inverse(u_projection * u_view_rotation) * vec4(ndc, 1.0, 1.0);
+    v_direction = far_point.xyz / far_point.w;
+    gl_Position = vec4(ndc, 1.0, 1.0);
+}
@@ -0,0 +1,52 @@
+#version 330 core
+
+in vec3 v_local_position;
+in vec3 v_world_position;
+
+uniform vec3 u_camera_position;
+uniform float u_cell_size;
+uniform float u_major_every;
+uniform float u_half_size;
+uniform float u_fade_start;
+uniform float u_fade_end;
+uniform vec4 u_minor_color;
+uniform vec4 u_major_color;
+uniform vec4 u_x_axis_color;
+uniform vec4 u_z_axis_color;
+
+out vec4 frag_color;
+
+// Coverage of grid lines spaced `spacing` apart, antialiased to about one pixel
+float grid_lines(vec2 p, float spacing) {
+    vec2 coord = p / spacing;
+    vec2 derivative = fwidth(coord);
+    vec2 line = abs(fract(coord - 0.5) - 0.5) / derivative;
+    return 1.0 - min(min(line.x, line.y), 1.0);
+}
+
+// Coverage of a single line along `value == 0`
+float axis_line(float value) {
+    return 1.0 - min(abs(value) / fwidth(value), 1.0);
+}
+
+void main() {
+    vec2 p = v_local_position.xz;
+    if (u_half_size > 0.0 && (abs(p.x) > u_half_size || abs(p.y) > u_half_size)) {
+        discard;
+    }
+
+    vec4 color = u_minor_color * grid_lines(p, u_cell_size);
+    float major = grid_lines(p, u_cell_size * u_major_every);
+    color = mix(color, u_major_color, major);
+
+    // The X axis runs along z == 0, the Z axis along x == 0
+    color = mix(color, u_x_axis_color, axis_line(p.y));
+    color = mix(color, u_z_axis_color, axis_line(p.x));
+
+    float camera_distance = length(v_world_position - u_camera_position);
+    color.a *= 1.0 - smoothstep(u_fade_start, u_fade_end, camera_distance);
+    if (color.a <= 0.001) {
+        discard;
+    }
+    frag_color = color;
+}
@@ -0,0 +1,26 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+
+uniform mat4 u_model;
+uniform mat4 u_proj_view;
+uniform vec3 u_camera_position;
+uniform float u_extent;
+uniform int u_follow_camera;
+
+out vec3 v_local_position;
+out vec3 v_world_position;
+
+void main() {
+    vec3 local = vec3(a_position.x * u_extent, 0.0, a_position.z * u_extent);
+    if (u_follow_camera != 0) {
+        // Keep the quad centered under the camera so the grid appears infinite
+        vec3 camera_local = (inverse(u_model) * vec4(u_camera_position, 1.0)).xyz;
+        local.xz += camera_local.xz;
+    }
+
+    vec4 world = u_model * vec4(local, 1.0);
+    v_local_position = local;
+    v_world_position = world.xyz;
+    gl_Position = u_proj_view * world;
+}
@@ -0,0 +1,144 @@
+// Scene lighting shared by the lit fragment shaders (phong.frag, pbr.frag), which are built
+// as "#version 330 core" + this + their own source; see material::lit_fragment_source.
+// Colors are linear; shaders decode sRGB texels themselves unless SRGB_PIPELINE is defined.
+
+#define MAX_LIGHTS 8
+#define MAX_COOKIES 4
+#define MAX_SHADOW_TILES 16
+
+struct Light {
+    int kind;           // 0 = directional, 1 = point, 2 = spot
+    vec3 position;
+    vec3 direction;
+    vec3 color;         // linear, multiplied by intensity
+    vec3 attenuation;   // constant, linear, quadratic
+    vec2 cone;          // cos(inner), cos(outer)
+    int cookie;         // index into u_cookies, -1 for none
+    mat4 cookie_matrix; // world space to cookie clip space
+    float specular;     // 0 for distant lights rendered without highlights
+    int shadow;         // first tile in the shadow atlas, -1 for none
+};
+
+in vec3 v_world_position;
+in vec3 v_normal;
+in vec2 v_uv;
+
+layout(std140) uniform Camera {   // see uniform_buffer
+    mat4 u_proj_view;
+    vec3 u_camera_position;
+    float u_exposure;   // 1.0 unless the camera uses physical exposure
+};
+
+layout(std140) uniform Lights {   // see LightSet::upload
+    Light u_lights[MAX_LIGHTS];
+    int u_light_count;
+    vec3 u_ambient;
+    mat4 u_shadow_matrices[MAX_SHADOW_TILES];   // world space to each tile's clip space
+    vec4 u_shadow_rects[MAX_SHADOW_TILES];      // tile x, y, width, height in atlas coordinates
+    float u_shadow_bias;
+};
+
+uniform sampler2D u_cookies[MAX_COOKIES];
+uniform sampler2DShadow u_shadow_atlas;
+
+vec3 srgb_to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+// Color the light's cookie lets through at this fragment
+vec3 cookie_color(Light light) {
+    if (light.cookie < 0) {
+        return vec3(1.0);
+    }
+    vec4 p = light.cookie_matrix * vec4(v_world_position, 1.0);
+    if (p.w <= 0.0) {
+        return vec3(0.0);
+    }
+    vec2 uv = p.xy / p.w * 0.5 + 0.5;
+    // Spot cookies cover the cone once; directional ones repeat
+    if (light.kind == 2 && (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))))) {
+        return vec3(0.0);
+    }
+
+    // Sampler arrays may only be indexed with constants in GLSL 3.30
+    vec3 c;
+    if (light.cookie == 0) {
+        c = texture(u_cookies[0], uv).rgb;
+    } else if (light.cookie == 1) {
+        c = texture(u_cookies[1], uv).rgb;
+    } else if (light.cookie == 2) {
+        c = texture(u_cookies[2], uv).rgb;
+    } else {
+        c = texture(u_cookies[3], uv).rgb;
+    }
+#ifdef SRGB_PIPELINE
+    return c;
+#else
+    return srgb_to_linear(c);
+#endif
+}
+
+// Fraction of the light reaching this fragment past the shadow casters
+float shadow_visibility(Light light) {
+    if (light.shadow < 0) {
+        return 1.0;
+    }
+    int tile = light.shadow;
+    if (light.kind == 1) {
+        // Point lights have a tile per cube face: +X, -X, +Y, -Y, +Z, -Z
+        vec3 d = v_world_position - light.position;
+        vec3 a = abs(d);
+        if (a.x >= a.y && a.x >= a.z) {
+            tile += d.x > 0.0 ? 0 : 1;
+        } else if (a.y >= a.z) {
+            tile += d.y > 0.0 ? 2 : 3;
+        } else {
+            tile += d.z > 0.0 ? 4 : 5;
+        }
+    }
+
+    vec4 clip = u_shadow_matrices[tile] * vec4(v_world_position, 1.0);
+    if (clip.w <= 0.0) {
+        return 1.0;
+    }
+    vec3 ndc = clip.xyz / clip.w;
+    if (any(greaterThan(abs(ndc), vec3(1.0)))) {
+        return 1.0;
+    }
+    vec4 rect = u_shadow_rects[tile];
+    vec2 uv = rect.xy + (ndc.xy * 0.5 + 0.5) * rect.zw;
+    float depth = ndc.z * 0.5 + 0.5 - u_shadow_bias;
+
+    // Four filtered taps, kept inside the tile so neighbouring tiles don't bleed in
+    vec2 texel = 1.0 / vec2(textureSize(u_shadow_atlas, 0));
+    vec2 low = rect.xy + texel;
+    vec2 high = rect.xy + rect.zw - texel;
+    float lit = 0.0;
+    for (int i = 0; i < 4; ++i) {
+        vec2 offset = (vec2(i & 1, i >> 1) - 0.5) * texel;
+        lit += texture(u_shadow_atlas, vec3(clamp(uv + offset, low, high), depth));
+    }
+    return lit * 0.25;
+}
+
+// Direction towards `light` and the fraction of its color reaching this fragment over
+// distance and the spot cone
+float light_falloff(Light light, out vec3 l) {
+    if (light.kind == 0) {
+        l = -light.direction;
+        return 1.0;
+    }
+    vec3 to_light = light.position - v_world_position;
+    float d = length(to_light);
+    l = to_light / max(d, 1e-5);
+    float falloff = 1.0 / max(light.attenuation.x + light.attenuation.y * d + light.attenuation.z * d * d, 1e-5);
+    if (light.kind == 2) {
+        float cos_angle = dot(-l, light.direction);
+        falloff *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return falloff;
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+in vec4 v_current;
+in vec4 v_previous;
+
+layout(location = 0) out vec2 frag_motion;
+
+void main() {
+    // Clip space to UV units: NDC spans 2 units per screen, UVs 1
+    frag_motion = (v_current.xy / v_current.w - v_previous.xy / v_previous.w) * 0.5;
+}
@@ -0,0 +1,21 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+
+uniform mat4 u_model;
+uniform mat4 u_previous_model;
+uniform mat4 u_proj_view;            // jittered, to rasterize like the scene
+uniform mat4 u_current_proj_view;    // unjittered, for the motion itself
+uniform mat4 u_previous_proj_view;
+uniform int u_instanced;
+
+out vec4 v_current;
+out vec4 v_previous;
+
+void main() {
+    vec4 local = u_instanced != 0 ? a_instance_matrix * vec4(a_position, 1.0) : vec4(a_position, 1.0);
+    gl_Position = u_proj_view * u_model * local;
+    v_current = u_current_proj_view * u_model * local;
+    v_previous = u_previous_proj_view * u_previous_model * local;
+}
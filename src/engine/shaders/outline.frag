@@ -0,0 +1,9 @@
+#version 330 core
+
+uniform vec4 u_color;   // as written to the framebuffer, see color_space::output_color
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = u_color;
+}
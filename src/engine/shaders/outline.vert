@@ -0,0 +1,27 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in vec3 a_normal;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+
+uniform mat4 u_model;
+uniform mat3 u_normal_matrix;
+uniform mat4 u_proj_view;
+uniform int u_instanced;
+uniform vec2 u_viewport_size;
+uniform float u_width;   // outline width in pixels, 0 for the stencil mask
+
+void main() {
+    mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+    mat3 normal_matrix = u_instanced != 0
+        ? u_normal_matrix * transpose(inverse(mat3(a_instance_matrix)))
+        : u_normal_matrix;
+    vec4 clip = u_proj_view * model * vec4(a_position, 1.0);
+
+    // Push the vertex outwards along its screen-space normal by a constant number of pixels
+    vec2 direction = (u_proj_view * vec4(normal_matrix * a_normal, 0.0)).xy * u_viewport_size;
+    if (u_width > 0.0 && length(direction) > 1e-6) {
+        clip.xy += normalize(direction) * u_width * 2.0 / u_viewport_size * clip.w;
+    }
+    gl_Position = clip;
+}
@@ -0,0 +1,186 @@
+// Metallic-roughness shading with a Cook-Torrance BRDF, appended to lighting.glsl; see pbr.
+
+#define PI 3.14159265
+
+// Bits of u_maps
+#define ALBEDO_MAP 1
+#define METALLIC_ROUGHNESS_MAP 2
+#define NORMAL_MAP 4
+#define OCCLUSION_MAP 8
+#define EMISSIVE_MAP 16
+
+uniform vec4 u_color;               // base color and alpha, sRGB-encoded
+uniform float u_metallic;
+uniform float u_roughness;          // perceptual, squared for the BRDF
+uniform vec3 u_emissive;            // linear
+uniform float u_normal_scale;
+uniform float u_occlusion_strength;
+uniform float u_alpha_cutoff;       // negative unless alpha is masked
+uniform int u_maps;                 // which of the maps below are bound
+
+uniform sampler2D u_albedo_map;               // sRGB color and alpha
+uniform sampler2D u_metallic_roughness_map;   // roughness in G, metallic in B, as in glTF
+uniform sampler2D u_normal_map;               // tangent space
+uniform sampler2D u_occlusion_map;            // in R
+uniform sampler2D u_emissive_map;             // sRGB color
+
+uniform samplerCube u_environment;            // see LightSet::upload
+uniform float u_environment_intensity;        // 0 without an environment
+uniform float u_environment_levels;
+uniform vec3 u_environment_ambient;
+
+out vec4 frag_color;
+
+vec3 color_texel(sampler2D map) {
+    vec3 c = texture(map, v_uv).rgb;
+#ifndef SRGB_PIPELINE
+    c = srgb_to_linear(c);
+#endif
+    return c;
+}
+
+vec3 environment_texel(vec3 direction, float lod) {
+    vec3 c = textureLod(u_environment, direction, lod).rgb;
+#ifndef SRGB_PIPELINE
+    c = srgb_to_linear(c);
+#endif
+    return c * u_environment_intensity;
+}
+
+// The normal map's normal in world space. Meshes carry no tangents, so the tangent frame
+// comes from the screen-space derivatives of position and UV
+vec3 mapped_normal(vec3 n) {
+    vec3 mapped = texture(u_normal_map, v_uv).xyz * 2.0 - 1.0;
+    mapped.xy *= u_normal_scale;
+
+    vec3 dp1 = dFdx(v_world_position);
+    vec3 dp2 = dFdy(v_world_position);
+    vec2 duv1 = dFdx(v_uv);
+    vec2 duv2 = dFdy(v_uv);
+    vec3 dp2_perp = cross(dp2, n);
+    vec3 dp1_perp = cross(n, dp1);
+    vec3 t = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    vec3 b = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    float frame_scale = inversesqrt(max(max(dot(t, t), dot(b, b)), 1e-20));
+    return normalize(mat3(t * frame_scale, b * frame_scale, n) * mapped);
+}
+
+// GGX / Trowbridge-Reitz normal distribution
+float distribution_ggx(float n_dot_h, float alpha) {
+    float a2 = alpha * alpha;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Height-correlated Smith masking for GGX, divided by 4 n.l n.v
+float visibility_smith(float n_dot_l, float n_dot_v, float alpha) {
+    float a2 = alpha * alpha;
+    float view = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2);
+    float light = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    return 0.5 / max(view + light, 1e-5);
+}
+
+vec3 fresnel_schlick(vec3 f0, float cos_theta) {
+    return f0 + (1.0 - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// Karis' analytic fit of the split-sum environment BRDF, instead of a lookup texture
+vec3 environment_brdf(vec3 f0, float roughness, float n_dot_v) {
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+void main() {
+    vec3 albedo = srgb_to_linear(u_color.rgb);
+    float alpha = u_color.a;
+    if ((u_maps & ALBEDO_MAP) != 0) {
+        albedo *= color_texel(u_albedo_map);
+        alpha *= texture(u_albedo_map, v_uv).a;
+    }
+    if (alpha < u_alpha_cutoff) {
+        discard;
+    }
+
+    float metallic = u_metallic;
+    float roughness = u_roughness;
+    if ((u_maps & METALLIC_ROUGHNESS_MAP) != 0) {
+        vec4 texel = texture(u_metallic_roughness_map, v_uv);
+        roughness *= texel.g;
+        metallic *= texel.b;
+    }
+    // Fully smooth surfaces would reflect point lights as invisible points
+    roughness = clamp(roughness, 0.045, 1.0);
+    metallic = clamp(metallic, 0.0, 1.0);
+    float a = roughness * roughness;
+
+    vec3 n = normalize(v_normal);
+    if (!gl_FrontFacing) {
+        n = -n;
+    }
+    if ((u_maps & NORMAL_MAP) != 0) {
+        n = mapped_normal(n);
+    }
+    vec3 v = normalize(u_camera_position - v_world_position);
+    float n_dot_v = max(dot(n, v), 1e-4);
+
+    vec3 diffuse_color = albedo * (1.0 - metallic);
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+
+    vec3 lit = vec3(0.0);
+    for (int i = 0; i < u_light_count && i < MAX_LIGHTS; ++i) {
+        Light light = u_lights[i];
+
+        vec3 l;
+        float falloff = light_falloff(light, l);
+
+        float n_dot_l = dot(n, l);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+
+        vec3 h = normalize(l + v);
+        float n_dot_h = max(dot(n, h), 0.0);
+        vec3 f = fresnel_schlick(f0, max(dot(v, h), 0.0));
+        vec3 specular = f * distribution_ggx(n_dot_h, a) * visibility_smith(n_dot_l, n_dot_v, a) * light.specular;
+        vec3 diffuse = (1.0 - f) * diffuse_color / PI;
+
+        // Light colors are in the Blinn-Phong shader's units, where a white light fully
+        // lights a white surface facing it: the BRDF is scaled by pi to match
+        lit += light.color * cookie_color(light) * shadow_visibility(light) * falloff * (diffuse + specular) * PI * n_dot_l;
+    }
+
+    // Ambient light reflects evenly, unless an environment map shows where it comes from:
+    // its blurry low mip levels stand in for irradiance and rough reflections
+    vec3 ambient_diffuse = u_ambient;
+    vec3 ambient_specular = u_ambient;
+    if (u_environment_intensity > 0.0) {
+        vec3 rest = max(u_ambient - u_environment_ambient, vec3(0.0));
+        float top = max(u_environment_levels - 1.0, 0.0);
+        ambient_diffuse = rest + environment_texel(n, max(top - 2.0, 0.0));
+        ambient_specular = rest + environment_texel(reflect(-v, n), roughness * top);
+    }
+    float occlusion = 1.0;
+    if ((u_maps & OCCLUSION_MAP) != 0) {
+        occlusion = mix(1.0, texture(u_occlusion_map, v_uv).r, u_occlusion_strength);
+    }
+    lit += (ambient_diffuse * diffuse_color + ambient_specular * environment_brdf(f0, roughness, n_dot_v)) * occlusion;
+
+    vec3 emissive = u_emissive;
+    if ((u_maps & EMISSIVE_MAP) != 0) {
+        emissive *= color_texel(u_emissive_map);
+    }
+    lit += emissive;
+
+    lit *= u_exposure;
+
+#ifdef SRGB_PIPELINE
+    frag_color = vec4(lit, alpha);
+#else
+    // The framebuffer is not sRGB, so encode the result ourselves
+    frag_color = vec4(linear_to_srgb(lit), alpha);
+#endif
+}
@@ -0,0 +1,54 @@
+// Blinn-Phong shading, appended to lighting.glsl; see Material::phong.
+
+uniform vec4 u_color;       // sRGB-encoded
+uniform vec3 u_specular;    // linear
+uniform float u_shininess;
+
+out vec4 frag_color;
+
+#ifdef SURFACE_ALBEDO
+// Variants defining SURFACE_ALBEDO append this, e.g. terrain splatting (see terrain::chunked):
+// the linear albedo at this fragment, given its world-space normal
+vec3 surface_albedo(vec3 n);
+#endif
+
+void main() {
+    vec3 n = normalize(v_normal);
+#ifdef SURFACE_ALBEDO
+    vec3 albedo = surface_albedo(n);
+#else
+    vec3 albedo = srgb_to_linear(u_color.rgb);
+#endif
+    vec3 v = normalize(u_camera_position - v_world_position);
+    if (!gl_FrontFacing) {
+        n = -n;
+    }
+
+    vec3 lit = u_ambient * albedo;
+    for (int i = 0; i < u_light_count && i < MAX_LIGHTS; ++i) {
+        Light light = u_lights[i];
+
+        vec3 l;
+        float falloff = light_falloff(light, l);
+
+        float diffuse = max(dot(n, l), 0.0);
+        if (diffuse <= 0.0) {
+            continue;
+        }
+
+        // Blinn-Phong specular
+        vec3 h = normalize(l + v);
+        float specular = pow(max(dot(n, h), 0.0), u_shininess) * light.specular;
+
+        lit += light.color * cookie_color(light) * shadow_visibility(light) * falloff * (albedo * diffuse + u_specular * specular);
+    }
+
+    lit *= u_exposure;
+
+#ifdef SRGB_PIPELINE
+    frag_color = vec4(lit, u_color.a);
+#else
+    // The framebuffer is not sRGB, so encode the result ourselves
+    frag_color = vec4(linear_to_srgb(lit), u_color.a);
+#endif
+}
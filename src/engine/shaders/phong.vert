@@ -0,0 +1,43 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in vec3 a_normal;
+layout(location = 2) in vec2 a_uv;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+layout(location = 7) in uvec4 a_joints;           // skinned meshes only, see SkinVertex
+layout(location = 8) in vec4 a_weights;
+
+layout(std140) uniform Camera {   // see uniform_buffer
+    mat4 u_proj_view;
+    vec3 u_camera_position;
+    float u_exposure;   // 1.0 unless the camera uses physical exposure
+};
+
+uniform mat4 u_model;
+uniform mat3 u_normal_matrix;   // inverse-transpose of u_model, see normal_matrix()
+uniform int u_instanced;
+uniform int u_skinned;
+uniform mat4 u_joint_matrices[64];   // see Skin, size is MAX_JOINTS
+
+out vec3 v_world_position;
+out vec3 v_normal;
+out vec2 v_uv;
+
+void main() {
+    // Blend the joints' movement from the bind pose into the vertex, in the mesh's space
+    mat4 skin = u_skinned != 0
+        ? a_weights.x * u_joint_matrices[a_joints.x] + a_weights.y * u_joint_matrices[a_joints.y]
+          + a_weights.z * u_joint_matrices[a_joints.z] + a_weights.w * u_joint_matrices[a_joints.w]
+        : mat4(1.0);
+    mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+    vec4 world = model * (skin * vec4(a_position, 1.0));
+    v_world_position = world.xyz;
+    // Inverse-transpose keeps normals perpendicular under non-uniform scale; the node's part is
+    // precomputed on the CPU, only instance matrices are inverted here
+    mat3 normal_matrix = u_instanced != 0
+        ? u_normal_matrix * transpose(inverse(mat3(a_instance_matrix)))
+        : u_normal_matrix;
+    v_normal = normal_matrix * (mat3(skin) * a_normal);
+    v_uv = a_uv;
+    gl_Position = u_proj_view * world;
+}
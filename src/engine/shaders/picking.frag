@@ -0,0 +1,9 @@
+#version 330 core
+
+uniform uint u_id;   // 1-based node index, 0 is background
+
+layout(location = 0) out uint frag_id;
+
+void main() {
+    frag_id = u_id;
+}
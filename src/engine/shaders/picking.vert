@@ -0,0 +1,13 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+
+uniform mat4 u_model;
+uniform mat4 u_proj_view;
+uniform int u_instanced;
+
+void main() {
+    mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+    gl_Position = u_proj_view * model * vec4(a_position, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 330 core
+
+// Fullscreen triangle generated from gl_VertexID; no vertex buffers needed.
+
+out vec2 v_uv;
+
+void main() {
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    v_uv = ndc * 0.5 + 0.5;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
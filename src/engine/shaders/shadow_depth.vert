@@ -0,0 +1,21 @@
+#version 330 core
+
+layout(location = 0) in vec3 a_position;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+layout(location = 7) in uvec4 a_joints;           // skinned meshes only, see SkinVertex
+layout(location = 8) in vec4 a_weights;
+
+uniform mat4 u_model;
+uniform mat4 u_proj_view;       // the light's view of its atlas tile
+uniform int u_instanced;
+uniform int u_skinned;
+uniform mat4 u_joint_matrices[64];   // see Skin, size is MAX_JOINTS
+
+void main() {
+    mat4 skin = u_skinned != 0
+        ? a_weights.x * u_joint_matrices[a_joints.x] + a_weights.y * u_joint_matrices[a_joints.y]
+          + a_weights.z * u_joint_matrices[a_joints.z] + a_weights.w * u_joint_matrices[a_joints.w]
+        : mat4(1.0);
+    mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+    gl_Position = u_proj_view * model * (skin * vec4(a_position, 1.0));
+}
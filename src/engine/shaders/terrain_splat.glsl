@@ -0,0 +1,57 @@
+// Terrain texture splatting, appended to the lit phong.frag with SURFACE_ALBEDO defined; see
+// terrain::chunked::splat_material. Each layer covers a band of world height and slope,
+// fading out beyond it; overlapping layers are averaged by weight.
+
+#define MAX_SPLAT_LAYERS 4
+
+uniform int u_splat_layer_count;
+uniform vec4 u_splat_colors[MAX_SPLAT_LAYERS];      // sRGB-encoded tint
+uniform vec4 u_splat_ranges[MAX_SPLAT_LAYERS];      // min height, max height, min slope, max slope (degrees)
+uniform vec4 u_splat_params[MAX_SPLAT_LAYERS];      // uv scale, textured (0 or 1), height fade, slope fade
+uniform sampler2D u_splat_textures[MAX_SPLAT_LAYERS];
+
+// Sampler arrays may only be indexed with constants in GLSL 3.30
+vec3 splat_texel(int layer, vec2 uv) {
+    if (layer == 0) {
+        return texture(u_splat_textures[0], uv).rgb;
+    } else if (layer == 1) {
+        return texture(u_splat_textures[1], uv).rgb;
+    } else if (layer == 2) {
+        return texture(u_splat_textures[2], uv).rgb;
+    }
+    return texture(u_splat_textures[3], uv).rgb;
+}
+
+// 1 inside [low, high], fading to 0 over `fade` outside it
+float band(float value, float low, float high, float fade) {
+    if (fade <= 0.0) {
+        return step(low, value) * step(value, high);
+    }
+    return smoothstep(low - fade, low, value) * (1.0 - smoothstep(high, high + fade, value));
+}
+
+vec3 surface_albedo(vec3 n) {
+    float slope = degrees(acos(clamp(n.y, -1.0, 1.0)));
+    vec3 sum = vec3(0.0);
+    float total = 0.0;
+    for (int i = 0; i < u_splat_layer_count && i < MAX_SPLAT_LAYERS; ++i) {
+        vec4 range = u_splat_ranges[i];
+        vec4 params = u_splat_params[i];
+        float weight = band(v_world_position.y, range.x, range.y, params.z) * band(slope, range.z, range.w, params.w);
+        if (weight <= 0.0) {
+            continue;
+        }
+        vec3 albedo = srgb_to_linear(u_splat_colors[i].rgb);
+        if (params.y > 0.5) {
+#ifdef SRGB_PIPELINE
+            albedo *= splat_texel(i, v_uv * params.x);
+#else
+            albedo *= srgb_to_linear(splat_texel(i, v_uv * params.x));
+#endif
+        }
+        sum += albedo * weight;
+        total += weight;
+    }
+    // Ground no layer covers keeps the material's color
+    return total > 1e-4 ? sum / total : srgb_to_linear(u_color.rgb);
+}
@@ -0,0 +1,64 @@
+#version 330 core
+
+// Phong vertex shader variant reading positions and normals from vertex animation textures,
+// see mesh::vertex_animation. Pairs with phong.frag.
+
+layout(location = 0) in vec3 a_position;
+layout(location = 1) in vec3 a_normal;
+layout(location = 2) in vec2 a_uv;
+layout(location = 3) in mat4 a_instance_matrix;   // per instance, see InstancedMesh
+
+layout(std140) uniform Camera {   // see uniform_buffer
+    mat4 u_proj_view;
+    vec3 u_camera_position;
+    float u_exposure;   // 1.0 unless the camera uses physical exposure
+};
+
+uniform mat4 u_model;
+uniform mat3 u_normal_matrix;   // inverse-transpose of u_model, see normal_matrix()
+uniform int u_instanced;
+
+uniform sampler2D u_vat_positions;
+uniform sampler2D u_vat_normals;
+uniform int u_vat_vertex_count;
+uniform int u_vat_width;
+uniform int u_vat_frame_count;
+uniform float u_vat_frame_rate;
+uniform float u_vat_time;
+uniform float u_vat_phase_spread;
+
+out vec3 v_world_position;
+out vec3 v_normal;
+out vec2 v_uv;
+
+vec3 fetch(sampler2D data, int frame) {
+    int texel = frame * u_vat_vertex_count + gl_VertexID;
+    return texelFetch(data, ivec2(texel % u_vat_width, texel / u_vat_width), 0).xyz;
+}
+
+void main() {
+    vec3 position = a_position;
+    vec3 normal = a_normal;
+    // Vertices past the baked count (a mismatched mesh) stay in their rest pose
+    if (u_vat_frame_count > 0 && gl_VertexID < u_vat_vertex_count) {
+        float duration = float(u_vat_frame_count) / u_vat_frame_rate;
+        // Golden ratio sequence: well spread phases for consecutive instance ids
+        float phase = u_instanced != 0 ? fract(float(gl_InstanceID) * 0.618034) * u_vat_phase_spread * duration : 0.0;
+        float frame = mod((u_vat_time + phase) * u_vat_frame_rate, float(u_vat_frame_count));
+        int f0 = int(frame) % u_vat_frame_count;
+        int f1 = (f0 + 1) % u_vat_frame_count;
+        float t = fract(frame);
+        position = mix(fetch(u_vat_positions, f0), fetch(u_vat_positions, f1), t);
+        normal = normalize(mix(fetch(u_vat_normals, f0), fetch(u_vat_normals, f1), t));
+    }
+
+    mat4 model = u_instanced != 0 ? u_model * a_instance_matrix : u_model;
+    vec4 world = model * vec4(position, 1.0);
+    v_world_position = world.xyz;
+    mat3 normal_matrix = u_instanced != 0
+        ? u_normal_matrix * transpose(inverse(mat3(a_instance_matrix)))
+        : u_normal_matrix;
+    v_normal = normal_matrix * normal;
+    v_uv = a_uv;
+    gl_Position = u_proj_view * world;
+}
@@ -0,0 +1,484 @@
+//! Shadow maps for point and spot lights, packed into one atlas.
+//!
+//! A texture and framebuffer per light wastes memory on lights that barely show and makes
+//! the shadow pass switch framebuffers for every light. Instead, every frame the
+//! [`ShadowAtlas`] hands each shadowed light a square tile of one large depth texture, sized
+//! by the light's importance: how much of the view its range covers from the camera. Near
+//! lights get up to the renderer's
+//! [`shadow_resolution`](crate::engine::quality::QualitySettings::shadow_resolution), distant
+//! ones a fraction of it, and when the atlas is full the least important lights go without.
+//! Spot lights take one tile covering their cone; point lights take six, one per cube face.
+//! All tiles are drawn into the one framebuffer, each through its own viewport.
+//!
+//! Tiles are placed by a [`ShadowAtlasAllocator`], a quadtree over the atlas: tiles are
+//! powers of two, and allocating them largest first leaves no gaps.
+//!
+//! Shadows are off until enabled on the scene with
+//! [`Scene::set_shadows`](crate::engine::scene::Scene::set_shadows). Lights cast shadows
+//! while their [detail](crate::engine::light::LightDetail) is full, so
+//! [light LOD](crate::engine::light::LightLod) drops them with distance. Opaque nodes with a
+//! material cast shadows; the built-in lit shader receives them (see
+//! [`LightSet::upload`](crate::engine::light::LightSet::upload)). Directional lights don't
+//! cast shadows, and vertex-animated meshes cast the shadow of their rest pose.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::{light::Light, math::color::Color, object3d::Object3D, scene::Scene};
+//! # use rustge::engine::shadow::ShadowSettings;
+//! let mut scene = Scene::new();
+//! scene.set_shadows(Some(ShadowSettings { atlas_size: 8192, ..ShadowSettings::default() }));
+//!
+//! let lamp = Object3D::new();
+//! lamp.borrow_mut().set_light(Some(Light::spot(Color::WHITE, 5.0, 20.0, 20.0, 30.0)));
+//! scene.add(lamp);
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use gl::types::{GLint, GLsizei, GLuint};
+use crate::engine::animation::skeleton::set_skin_uniforms;
+use crate::engine::camera::Camera;
+use crate::engine::light::{LightKind, LightSet};
+use crate::engine::math::vec::{add, distance};
+use crate::engine::object3d::Object3D;
+use crate::engine::shader::builtin_program;
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Maximum number of atlas tiles per frame, and the size of the tile arrays lit shaders
+/// declare. A point light takes six.
+pub const MAX_SHADOW_TILES: usize = 16;
+
+/// Range assumed for lights whose attenuation never fades out.
+const UNBOUNDED_RANGE: f32 = 100.0;
+
+/// Near plane of the light's views.
+const SHADOW_NEAR: f32 = 0.05;
+
+/// View directions and up vectors of a point light's tiles, in the order the lit shader
+/// picks them: +X, -X, +Y, -Y, +Z, -Z.
+const CUBE_FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+];
+
+/// How a scene's shadows are rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    /// Edge length of the atlas texture in texels, rounded up to a power of two.
+    pub atlas_size: u32,
+
+    /// Smallest tile a light gets, in texels; lights that don't fit at this size cast no
+    /// shadows that frame.
+    pub min_resolution: u32,
+
+    /// Depth subtracted before comparing with the shadow map, against shadow acne.
+    pub depth_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    /// A 4096² atlas with tiles down to 64², and a small depth bias.
+    fn default() -> Self {
+        Self { atlas_size: 4096, min_resolution: 64, depth_bias: 0.0005 }
+    }
+}
+
+/// A square region of the atlas, in texels from its bottom-left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub size: u32,
+}
+
+/// Packs power-of-two tiles into a square atlas: a quadtree whose free squares are split in
+/// four until they have the requested size.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::shadow::{AtlasRect, ShadowAtlasAllocator};
+/// let mut allocator = ShadowAtlasAllocator::new(1024, 128);
+/// assert_eq!(allocator.allocate(512), Some(AtlasRect { x: 0, y: 0, size: 512 }));
+/// for _ in 0..12 {
+///     assert!(allocator.allocate(256).is_some());
+/// }
+/// // Full: three quarters hold 256 tiles, one the 512 tile
+/// assert_eq!(allocator.allocate(128), None);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShadowAtlasAllocator {
+    size: u32,
+    min_tile: u32,
+    /// Free squares by level: level 0 is the whole atlas, each level halves the size.
+    free: Vec<Vec<[u32; 2]>>,
+    used: u64,
+}
+
+impl ShadowAtlasAllocator {
+    /// An empty atlas `size` texels across, handing out tiles of at least `min_tile`. Both
+    /// are rounded up to powers of two.
+    pub fn new(size: u32, min_tile: u32) -> Self {
+        let size = size.max(1).next_power_of_two();
+        let min_tile = min_tile.max(1).next_power_of_two().min(size);
+        let levels = (size / min_tile).trailing_zeros() as usize + 1;
+        let mut allocator = Self { size, min_tile, free: vec![Vec::new(); levels], used: 0 };
+        allocator.clear();
+        allocator
+    }
+
+    /// Frees every tile.
+    pub fn clear(&mut self) {
+        for level in &mut self.free {
+            level.clear();
+        }
+        self.free[0].push([0, 0]);
+        self.used = 0;
+    }
+
+    /// Edge length of the atlas in texels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The tile size `size` is allocated at: the next power of two, within the atlas's
+    /// limits.
+    pub fn tile_size(&self, size: u32) -> u32 {
+        size.max(1).next_power_of_two().clamp(self.min_tile, self.size)
+    }
+
+    /// Places a tile of [`tile_size(size)`](Self::tile_size) texels, or returns `None` if no
+    /// free square is large enough.
+    pub fn allocate(&mut self, size: u32) -> Option<AtlasRect> {
+        let size = self.tile_size(size);
+        let level = (self.size / size).trailing_zeros() as usize;
+        let mut from = (0..=level).rev().find(|&level| !self.free[level].is_empty())?;
+        let [x, y] = self.free[from].pop().expect("level has a free square");
+        // Split down to the requested size, keeping the bottom-left quarter each time
+        while from < level {
+            from += 1;
+            let half = self.size >> from;
+            self.free[from].extend([[x + half, y + half], [x, y + half], [x + half, y]]);
+        }
+        self.used += u64::from(size) * u64::from(size);
+        Some(AtlasRect { x, y, size })
+    }
+
+    /// Fraction of the atlas allocated.
+    pub fn used_fraction(&self) -> f32 {
+        self.used as f32 / (f64::from(self.size) * f64::from(self.size)) as f32
+    }
+}
+
+/// One light's view rendered into the atlas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowTile {
+    /// Index of the light in the frame's [`LightSet`].
+    pub light: usize,
+
+    /// Where in the atlas the view is.
+    pub rect: AtlasRect,
+
+    /// Maps world space to the view's clip space.
+    pub matrix: [f32; 16],
+}
+
+/// What lit shaders need to sample the frame's shadows, carried by the [`LightSet`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShadowMaps {
+    /// The atlas depth texture.
+    pub texture: GLuint,
+
+    /// Edge length of the atlas in texels.
+    pub atlas_size: u32,
+
+    /// The tiles, each light's consecutive (six for point lights, in +X, -X, +Y, -Y, +Z, -Z
+    /// order); see [`WorldLight::shadow`](crate::engine::light::WorldLight::shadow).
+    pub tiles: Vec<ShadowTile>,
+
+    /// See [`ShadowSettings::depth_bias`].
+    pub depth_bias: f32,
+}
+
+impl ShadowMaps {
+    /// Where `tile` lies in the atlas: x, y, width and height in texture coordinates.
+    pub fn tile_rect(&self, tile: &ShadowTile) -> [f32; 4] {
+        let atlas_size = self.atlas_size.max(1) as f32;
+        let rect = tile.rect;
+        [rect.x as f32, rect.y as f32, rect.size as f32, rect.size as f32].map(|value| value / atlas_size)
+    }
+}
+
+/// The shadow atlas of a scene: its depth texture and the tiles of the last frame; see the
+/// [module documentation](self).
+#[derive(Debug, Default)]
+pub struct ShadowAtlas {
+    target: Option<AtlasTarget>,
+    /// Tiles of the last render.
+    tiles: Vec<ShadowTile>,
+    /// Position of each shadowed light at the last render, so the tiles are only handed to
+    /// the lights they were rendered for.
+    light_positions: Vec<(usize, [f32; 3])>,
+    depth_bias: f32,
+    used_fraction: f32,
+}
+
+impl ShadowAtlas {
+    /// An empty atlas. The texture is created on the first render.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tiles of the last render.
+    pub fn tiles(&self) -> &[ShadowTile] {
+        &self.tiles
+    }
+
+    /// Fraction of the atlas the last render used.
+    pub fn used_fraction(&self) -> f32 {
+        self.used_fraction
+    }
+
+    /// Edge length of the atlas texture in texels, 0 before the first render.
+    pub fn size(&self) -> u32 {
+        self.target.as_ref().map_or(0, |target| target.size)
+    }
+
+    /// Forgets the last render's tiles, so lights are drawn unshadowed. The texture is kept.
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.light_positions.clear();
+        self.used_fraction = 0.0;
+    }
+
+    /// Allocates tiles for the shadowed point and spot lights of `lights`, as seen from a
+    /// camera at `eye`, up to `max_resolution` texels each, and renders the opaque nodes
+    /// under `root` into them. Returns the number of tiles rendered.
+    ///
+    /// Requires a current GL context; the framebuffer, viewport and depth state are restored
+    /// afterwards.
+    pub fn render(&mut self, root: &Rc<RefCell<Object3D>>, lights: &LightSet, eye: [f32; 3], settings: &ShadowSettings, max_resolution: u32) -> usize {
+        self.clear();
+        let atlas_size = settings.atlas_size.max(1).next_power_of_two();
+        if self.target.as_ref().is_none_or(|target| target.size != atlas_size) {
+            self.target = Some(AtlasTarget::new(atlas_size));
+        }
+        self.depth_bias = settings.depth_bias;
+
+        let views = allocate_views(lights, eye, settings, atlas_size, max_resolution);
+        if views.is_empty() {
+            return 0;
+        }
+        let Some(target) = &self.target else {
+            return 0;
+        };
+
+        let shader = builtin_program(
+            "shadow depth",
+            include_str!("shaders/shadow_depth.vert"),
+            include_str!("shaders/shadow_depth.frag"),
+        );
+        let mut previous_fbo: GLint = 0;
+        let mut previous_viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut previous_fbo);
+            gl::GetIntegerv(gl::VIEWPORT, previous_viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+            gl::Viewport(0, 0, atlas_size as GLsizei, atlas_size as GLsizei);
+            gl::DepthMask(gl::TRUE);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(gl::LESS);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            // Slope-scaled offset keeps surfaces at grazing angles from shadowing themselves
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(1.5, 4.0);
+        }
+        shader.use_program();
+
+        for (tile, camera) in &views {
+            let AtlasRect { x, y, size } = tile.rect;
+            unsafe {
+                gl::Viewport(x as GLint, y as GLint, size as GLsizei, size as GLsizei);
+            }
+            shader.set_uniform_matrix4("u_proj_view", &tile.matrix);
+            for node in Object3D::visible_set(root, camera) {
+                let mut object = node.borrow_mut();
+                if object.geometry().is_none() || object.material().is_none_or(|material| material.is_transparent()) {
+                    continue;
+                }
+                shader.set_uniform_matrix4("u_model", &object.world_matrix());
+                shader.set_uniform_int("u_instanced", object.instances().is_some() as i32);
+                set_skin_uniforms(&shader, &object);
+                object.draw_geometry();
+            }
+        }
+
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous_fbo as GLuint);
+            let [x, y, width, height] = previous_viewport;
+            gl::Viewport(x, y, width, height);
+        }
+
+        self.tiles = views.into_iter().map(|(tile, _)| tile).collect();
+        self.light_positions = self.tiles.iter().map(|tile| (tile.light, lights.lights[tile.light].position)).collect();
+        self.light_positions.dedup();
+        let used: u64 = self.tiles.iter().map(|tile| u64::from(tile.rect.size).pow(2)).sum();
+        self.used_fraction = (used as f64 / f64::from(atlas_size).powi(2)) as f32;
+        self.tiles.len()
+    }
+
+    /// Hands the last render's tiles to the lights of `lights` they were rendered for,
+    /// setting their [`shadow`](crate::engine::light::WorldLight::shadow) and the set's
+    /// [`shadows`](LightSet::shadows). Lights that moved since keep no shadow.
+    pub fn attach(&self, lights: &mut LightSet) {
+        let Some(target) = &self.target else {
+            return;
+        };
+        if self.tiles.is_empty() {
+            return;
+        }
+        let mut tiles = Vec::new();
+        for &(index, position) in &self.light_positions {
+            let Some(world) = lights.lights.get_mut(index).filter(|world| world.position == position) else {
+                continue;
+            };
+            world.shadow = Some(tiles.len());
+            tiles.extend(self.tiles.iter().filter(|tile| tile.light == index));
+        }
+        lights.shadows = Some(ShadowMaps { texture: target.texture, atlas_size: target.size, tiles, depth_bias: self.depth_bias });
+    }
+}
+
+/// The tiles of the frame with the camera each is rendered through, most important lights
+/// first, within the atlas and [`MAX_SHADOW_TILES`].
+fn allocate_views(lights: &LightSet, eye: [f32; 3], settings: &ShadowSettings, atlas_size: u32, max_resolution: u32) -> Vec<(ShadowTile, Camera)> {
+    let mut candidates: Vec<(usize, f32, f32)> = lights
+        .lights
+        .iter()
+        .enumerate()
+        .filter(|(_, world)| world.casts_shadows())
+        .filter_map(|(index, world)| {
+            let attenuation = match world.light.kind {
+                LightKind::Point { attenuation } | LightKind::Spot { attenuation, .. } => attenuation,
+                _ => return None,
+            };
+            let range = attenuation.range().unwrap_or(UNBOUNDED_RANGE);
+            // The share of the view the light's reach spans, 1 once the camera is inside it
+            let importance = range / distance(eye, world.position).max(range);
+            Some((index, range, importance))
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut allocator = ShadowAtlasAllocator::new(atlas_size, settings.min_resolution);
+    let max_resolution = allocator.tile_size(max_resolution);
+    let mut views = Vec::new();
+    for (index, range, importance) in candidates {
+        let world = &lights.lights[index];
+        let faces: Vec<([f32; 3], [f32; 3], f32)> = match world.light.kind {
+            LightKind::Spot { outer_angle, .. } => vec![(world.direction, up_for(world.direction), 2.0 * outer_angle)],
+            _ => CUBE_FACES.iter().map(|&(direction, up)| (direction, up, 90f32.to_radians())).collect(),
+        };
+        if views.len() + faces.len() > MAX_SHADOW_TILES {
+            continue;
+        }
+        // A point light's faces share its resolution
+        let wanted = (max_resolution as f32 * importance) as u32 / if faces.len() > 1 { 2 } else { 1 };
+        let Some(rects) = allocate_all(&mut allocator, wanted, faces.len()) else {
+            continue;
+        };
+        for ((direction, up, fov), rect) in faces.into_iter().zip(rects) {
+            let mut camera = Camera::new(1.0);
+            camera.fov_y = fov.min(179f32.to_radians());
+            camera.near = SHADOW_NEAR;
+            camera.far = range.max(SHADOW_NEAR * 2.0);
+            camera.look_at(world.position, add(world.position, direction), up);
+            views.push((ShadowTile { light: index, rect, matrix: camera.proj_view_matrix() }, camera));
+        }
+    }
+    views
+}
+
+/// `count` tiles of `size` texels, halving the size until they fit, or `None` if even the
+/// smallest don't.
+fn allocate_all(allocator: &mut ShadowAtlasAllocator, size: u32, count: usize) -> Option<Vec<AtlasRect>> {
+    let mut size = allocator.tile_size(size);
+    loop {
+        let mut attempt = allocator.clone();
+        let rects: Option<Vec<AtlasRect>> = (0..count).map(|_| attempt.allocate(size)).collect();
+        if let Some(rects) = rects {
+            *allocator = attempt;
+            return Some(rects);
+        }
+        if allocator.tile_size(size / 2) == size {
+            return None;
+        }
+        size /= 2;
+    }
+}
+
+/// An up vector not parallel to `direction`.
+fn up_for(direction: [f32; 3]) -> [f32; 3] {
+    if direction[1].abs() > 0.99 { [0.0, 0.0, 1.0] } else { [0.0, 1.0, 0.0] }
+}
+
+/// The atlas depth texture and its framebuffer.
+#[derive(Debug)]
+struct AtlasTarget {
+    fbo: GLuint,
+    texture: GLuint,
+    size: u32,
+}
+
+impl AtlasTarget {
+    fn new(size: u32) -> Self {
+        let (mut fbo, mut texture) = (0, 0);
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH_COMPONENT24 as GLint,
+                size as GLsizei,
+                size as GLsizei,
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+            // Linear filtering with comparison gives 2x2 filtered shadow edges for free
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::TEXTURE_2D, texture, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::RenderTarget, texture, (size as usize).pow(2) * 4, "shadow atlas");
+        Self { fbo, texture, size }
+    }
+}
+
+impl Drop for AtlasTarget {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::RenderTarget, self.texture);
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! Scene-wide simulation settings and fixed-timestep stepping.
+//!
+//! Simulations (rigid bodies, particles, cloth) stay stable and repeatable only when they
+//! advance in steps of a fixed length, independent of the frame rate. [`SimulationSettings`]
+//! holds what those systems share: gravity, the step length, how many substeps each step is
+//! divided into, and how much frame time is simulated at most. Every [`Scene`](crate::engine::scene::Scene)
+//! carries one; systems read it from the scene they simulate rather than hard-coding values,
+//! so a low-gravity level or a slow-motion effect is a matter of changing the scene's settings.
+//!
+//! A [`FixedStepper`] turns variable frame deltas into a number of fixed steps to run.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::simulation::{FixedStepper, SimulationSettings};
+//! let settings = SimulationSettings { gravity: [0.0, -1.62, 0.0], ..SimulationSettings::default() };
+//! let mut stepper = FixedStepper::new();
+//! let mut velocity = [0.0f32; 3];
+//!
+//! // Once per frame
+//! for _ in 0..stepper.advance(&settings, 1.0 / 30.0) {
+//!     for _ in 0..settings.substeps {
+//!         let dt = settings.substep_duration();
+//!         velocity = std::array::from_fn(|i| velocity[i] + settings.gravity[i] * dt);
+//!     }
+//! }
+//! assert!(velocity[1] < 0.0);
+//! ```
+
+/// Gravity, step length and step limits shared by a scene's simulations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulationSettings {
+    /// World-space acceleration applied to every simulated body and particle, in units per
+    /// second squared.
+    pub gravity: [f32; 3],
+
+    /// Length of one simulation step in seconds.
+    pub fixed_timestep: f32,
+
+    /// Number of substeps each step is divided into; more substeps make stiff constraints
+    /// and fast objects more stable at a proportional cost. At least 1.
+    pub substeps: u32,
+
+    /// Longest frame delta simulated, in seconds. After a hitch (loading, a breakpoint,
+    /// a dragged window) the simulation falls behind instead of running hundreds of steps
+    /// to catch up.
+    pub max_delta: f32,
+}
+
+impl SimulationSettings {
+    /// `delta` limited to [`max_delta`](Self::max_delta), and never negative.
+    ///
+    /// ```
+    /// # use rustge::engine::simulation::SimulationSettings;
+    /// let settings = SimulationSettings::default();
+    /// assert_eq!(settings.clamp_delta(0.01), 0.01);
+    /// assert_eq!(settings.clamp_delta(3.0), settings.max_delta);
+    /// ```
+    pub fn clamp_delta(&self, delta: f32) -> f32 {
+        delta.clamp(0.0, self.max_delta.max(0.0))
+    }
+
+    /// Length of one substep in seconds.
+    pub fn substep_duration(&self) -> f32 {
+        self.fixed_timestep / self.substeps.max(1) as f32
+    }
+}
+
+impl Default for SimulationSettings {
+    /// Earth gravity along -Y, 60 steps per second without substeps, at most a quarter
+    /// second simulated per frame.
+    fn default() -> Self {
+        Self { gravity: [0.0, -9.81, 0.0], fixed_timestep: 1.0 / 60.0, substeps: 1, max_delta: 0.25 }
+    }
+}
+
+/// Accumulates frame time and hands it out in fixed steps.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FixedStepper {
+    /// Simulated time owed but not yet stepped, in seconds.
+    accumulator: f32,
+}
+
+impl FixedStepper {
+    /// A stepper with no time accumulated.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame's `delta` (clamped by [`SimulationSettings::clamp_delta`]) and returns
+    /// how many steps of [`fixed_timestep`](SimulationSettings::fixed_timestep) to run now.
+    /// The remainder carries over to the next frame.
+    ///
+    /// ```
+    /// # use rustge::engine::simulation::{FixedStepper, SimulationSettings};
+    /// let settings = SimulationSettings { fixed_timestep: 0.1, ..SimulationSettings::default() };
+    /// let mut stepper = FixedStepper::new();
+    /// assert_eq!(stepper.advance(&settings, 0.25), 2);
+    /// assert_eq!(stepper.advance(&settings, 0.06), 1);
+    /// ```
+    pub fn advance(&mut self, settings: &SimulationSettings, delta: f32) -> u32 {
+        if settings.fixed_timestep <= 0.0 {
+            return 0;
+        }
+        self.accumulator += settings.clamp_delta(delta);
+        let steps = (self.accumulator / settings.fixed_timestep).floor();
+        self.accumulator -= steps * settings.fixed_timestep;
+        steps as u32
+    }
+
+    /// How far between the last and the next step the current frame lies, in `[0, 1)`: the
+    /// weight for interpolating rendered transforms between the two most recent steps.
+    pub fn alpha(&self, settings: &SimulationSettings) -> f32 {
+        if settings.fixed_timestep <= 0.0 {
+            return 0.0;
+        }
+        (self.accumulator / settings.fixed_timestep).clamp(0.0, 1.0)
+    }
+
+    /// Drops any accumulated time, e.g. after loading a level.
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
@@ -0,0 +1,168 @@
+//! Immutable per-frame snapshots of the scene for other threads.
+//!
+//! The scene graph is `Rc<RefCell<..>>` and lives on the render thread. Systems on other
+//! threads (audio spatialization, network replication, video recording) only need to know
+//! where things were: a [`FrameSnapshot`] copies the world transforms of every node and the
+//! camera once per frame into plain data behind an `Arc`. Readers take the latest one from
+//! a [`SnapshotReader`] and keep it as long as they like; the render thread publishes the
+//! next without waiting for them.
+//!
+//! Snapshots of consecutive frames also let readers running at a different rate (a 100 Hz
+//! network tick, a 48 kHz audio callback) interpolate between frames with
+//! [`FrameSnapshot::interpolated_position`].
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{object3d::Object3D, scene::Scene, snapshot::snapshot_channel, time::Clock};
+//! let scene = Scene::new();
+//! let emitter = Object3D::new();
+//! emitter.borrow_mut().set_position([3.0, 0.0, 0.0]);
+//! scene.add(emitter.clone());
+//! let emitter_id = emitter.borrow().id();
+//!
+//! let (publisher, reader) = snapshot_channel();
+//! // Once per frame, on the render thread
+//! publisher.publish(scene.snapshot(None, &Clock::new()));
+//!
+//! let audio = std::thread::spawn(move || {
+//!     let snapshot = reader.latest();
+//!     snapshot.node(emitter_id).map(|node| node.world_position())
+//! });
+//! assert_eq!(audio.join().unwrap(), Some([3.0, 0.0, 0.0]));
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use crate::engine::camera::Camera;
+use crate::engine::math::vec::lerp;
+use crate::engine::object3d::{NodeId, Object3D};
+use crate::engine::time::Clock;
+
+/// A node's state in a [`FrameSnapshot`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeSnapshot {
+    pub id: NodeId,
+
+    /// The parent node, `None` for the scene root.
+    pub parent: Option<NodeId>,
+
+    /// The node's [persistent id](Object3D::persistent_id), a name stable across runs.
+    pub persistent_id: Option<String>,
+
+    /// Column-major local-to-world transform.
+    pub world_matrix: [f32; 16],
+}
+
+impl NodeSnapshot {
+    /// The world-space position of the node's origin.
+    pub fn world_position(&self) -> [f32; 3] {
+        [self.world_matrix[12], self.world_matrix[13], self.world_matrix[14]]
+    }
+}
+
+/// World transforms and camera of one frame.
+#[derive(Clone, Debug, Default)]
+pub struct FrameSnapshot {
+    /// Frame number the snapshot was taken in (see [`Clock::frame_count`]).
+    pub frame: u64,
+
+    /// Seconds since the clock started, at that frame.
+    pub time: f64,
+
+    /// The camera the frame was rendered from, if any.
+    pub camera: Option<Camera>,
+
+    /// Every node, parents before children.
+    pub nodes: Vec<NodeSnapshot>,
+
+    /// Positions in `nodes` by id.
+    index: HashMap<NodeId, usize>,
+}
+
+impl FrameSnapshot {
+    /// Snapshots the graph under `root` in the frame `clock` is at, seen from `camera`.
+    pub fn capture(root: &Rc<RefCell<Object3D>>, camera: Option<&Camera>, clock: &Clock) -> Self {
+        let mut snapshot = Self { frame: clock.frame_count(), time: clock.elapsed(), camera: camera.cloned(), ..Self::default() };
+        let mut stack = vec![(root.clone(), None)];
+        while let Some((node, parent)) = stack.pop() {
+            // Parents are visited first, so their world matrices are already up to date
+            let mut borrowed = node.borrow_mut();
+            let id = borrowed.id();
+            snapshot.index.insert(id, snapshot.nodes.len());
+            snapshot.nodes.push(NodeSnapshot {
+                id,
+                parent,
+                persistent_id: borrowed.persistent_id().map(str::to_string),
+                world_matrix: borrowed.world_matrix(),
+            });
+            stack.extend(borrowed.children().iter().rev().map(|child| (child.clone(), Some(id))));
+        }
+        snapshot
+    }
+
+    /// The node with `id`, if it was in the scene.
+    pub fn node(&self, id: NodeId) -> Option<&NodeSnapshot> {
+        self.index.get(&id).map(|&i| &self.nodes[i])
+    }
+
+    /// The node with persistent id `persistent_id`, if any. Searches every node; look up
+    /// its [`NodeId`] once and use [`node`](Self::node) for repeated access.
+    pub fn find_persistent(&self, persistent_id: &str) -> Option<&NodeSnapshot> {
+        self.nodes.iter().find(|node| node.persistent_id.as_deref() == Some(persistent_id))
+    }
+
+    /// The world position of node `id` a fraction `alpha` of the way from `previous` to this
+    /// snapshot. Falls back to this snapshot's position if the node is new.
+    pub fn interpolated_position(&self, previous: &FrameSnapshot, id: NodeId, alpha: f32) -> Option<[f32; 3]> {
+        let current = self.node(id)?.world_position();
+        Some(match previous.node(id) {
+            Some(node) => lerp(node.world_position(), current, alpha.clamp(0.0, 1.0)),
+            None => current,
+        })
+    }
+}
+
+/// Publishes snapshots to the [`SnapshotReader`]s of the same channel.
+#[derive(Clone, Debug)]
+pub struct SnapshotPublisher {
+    latest: Arc<Mutex<Arc<FrameSnapshot>>>,
+}
+
+impl SnapshotPublisher {
+    /// Makes `snapshot` the latest. Readers holding an older one keep it until they ask
+    /// again.
+    pub fn publish(&self, snapshot: FrameSnapshot) {
+        let snapshot = Arc::new(snapshot);
+        // Only the pointer swap happens under the lock; the old snapshot is dropped after
+        let mut latest = self.latest.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let previous = std::mem::replace(&mut *latest, snapshot);
+        drop(latest);
+        drop(previous);
+    }
+
+    /// Another reader of this channel.
+    pub fn reader(&self) -> SnapshotReader {
+        SnapshotReader { latest: self.latest.clone() }
+    }
+}
+
+/// Reads the latest snapshot from any thread.
+#[derive(Clone, Debug)]
+pub struct SnapshotReader {
+    latest: Arc<Mutex<Arc<FrameSnapshot>>>,
+}
+
+impl SnapshotReader {
+    /// The most recently published snapshot (empty before the first).
+    pub fn latest(&self) -> Arc<FrameSnapshot> {
+        self.latest.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+/// A connected publisher and reader. Clone the reader for each thread that needs it.
+pub fn snapshot_channel() -> (SnapshotPublisher, SnapshotReader) {
+    let latest = Arc::new(Mutex::new(Arc::new(FrameSnapshot::default())));
+    (SnapshotPublisher { latest: latest.clone() }, SnapshotReader { latest })
+}
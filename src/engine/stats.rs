@@ -0,0 +1,437 @@
+//! Engine statistics and GPU resource accounting.
+//!
+//! Every GPU allocation made by the engine (vertex/index buffers, textures, render targets,
+//! readback buffers) is recorded in a central registry together with its size and a debug
+//! label. The registry can be queried for totals per category, and warns when a configurable memory budget is exceeded.
+//!
+//! OpenGL contexts are bound to a single thread, so the registry is thread-local to the
+//! thread that owns the context.
+//!
+//! Alongside the registry, per-frame [`FrameCounters`] count draw calls and GPU uploads, and
+//! the optional [`CountingAllocator`] counts heap allocations. The renderer's
+//! [`FrameWatchdog`](crate::engine::watchdog::FrameWatchdog) reports both for slow frames.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use gl::types::{GLenum, GLuint};
+use crate::engine::debug::gl_debug::label_object;
+
+/// The category of a tracked GPU allocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GpuResourceKind {
+    /// Vertex buffer objects (VBOs).
+    VertexBuffer,
+    /// Index buffer objects (IBOs / element array buffers).
+    IndexBuffer,
+    /// Texture storage (2D textures, cubemaps, ...).
+    Texture,
+    /// Framebuffer attachments used as render targets.
+    RenderTarget,
+    /// Pixel pack buffers receiving asynchronous readbacks.
+    PixelBuffer,
+    /// Uniform buffer objects holding per-frame shader data.
+    UniformBuffer,
+}
+
+impl GpuResourceKind {
+    /// All categories, in reporting order.
+    pub const ALL: [GpuResourceKind; 6] = [
+        GpuResourceKind::VertexBuffer,
+        GpuResourceKind::IndexBuffer,
+        GpuResourceKind::Texture,
+        GpuResourceKind::RenderTarget,
+        GpuResourceKind::PixelBuffer,
+        GpuResourceKind::UniformBuffer,
+    ];
+
+    /// The `glObjectLabel` namespace matching this kind of resource.
+    fn gl_identifier(self) -> GLenum {
+        match self {
+            GpuResourceKind::VertexBuffer
+            | GpuResourceKind::IndexBuffer
+            | GpuResourceKind::PixelBuffer
+            | GpuResourceKind::UniformBuffer => gl::BUFFER,
+            GpuResourceKind::Texture => gl::TEXTURE,
+            GpuResourceKind::RenderTarget => gl::FRAMEBUFFER,
+        }
+    }
+}
+
+/// A single live GPU allocation as recorded by the registry.
+#[derive(Clone, Debug)]
+pub struct GpuAllocation {
+    /// What kind of resource this is.
+    pub kind: GpuResourceKind,
+    /// The OpenGL object name (id).
+    pub id: GLuint,
+    /// Size of the allocation in bytes.
+    pub bytes: usize,
+    /// Human-readable debug label, also attached to the GL object via `glObjectLabel`.
+    pub label: String,
+}
+
+/// Snapshot of GPU memory usage, broken down by category.
+#[derive(Clone, Debug, Default)]
+pub struct GpuMemoryStats {
+    /// Bytes allocated per category.
+    pub bytes_by_kind: HashMap<GpuResourceKind, usize>,
+    /// Number of live allocations per category.
+    pub count_by_kind: HashMap<GpuResourceKind, usize>,
+    /// Total bytes across all categories.
+    pub total_bytes: usize,
+    /// The configured budget in bytes, if any.
+    pub budget_bytes: Option<usize>,
+}
+
+impl GpuMemoryStats {
+    /// Bytes currently allocated for the given category.
+    pub fn bytes(&self, kind: GpuResourceKind) -> usize {
+        self.bytes_by_kind.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Number of live allocations for the given category.
+    pub fn count(&self, kind: GpuResourceKind) -> usize {
+        self.count_by_kind.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if a budget is configured and the total exceeds it.
+    pub fn over_budget(&self) -> bool {
+        self.budget_bytes.is_some_and(|budget| self.total_bytes > budget)
+    }
+}
+
+/// Central registry of live GPU allocations.
+#[derive(Default)]
+struct GpuMemoryRegistry {
+    allocations: HashMap<(GpuResourceKind, GLuint), GpuAllocation>,
+    total_bytes: usize,
+    budget_bytes: Option<usize>,
+    /// Whether we already warned about the current budget overrun (avoids log spam).
+    warned: bool,
+}
+
+thread_local! {
+    static GPU_MEMORY: RefCell<GpuMemoryRegistry> = RefCell::new(GpuMemoryRegistry::default());
+    static FRAME_COUNTERS: Cell<FrameCounters> = Cell::new(FrameCounters::default());
+}
+
+/// Records a new GPU allocation and attaches `label` to the GL object when `glObjectLabel`
+/// is available.
+///
+/// If the allocation pushes total usage above the configured budget, a warning is printed once
+/// until usage drops back under the budget.
+///
+/// Re-tracking an id that is already registered replaces the previous entry (e.g. when a
+/// buffer is re-specified with `glBufferData`).
+pub fn track_gpu_allocation(kind: GpuResourceKind, id: GLuint, bytes: usize, label: &str) {
+    label_gl_object(kind, id, label);
+
+    GPU_MEMORY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        let allocation = GpuAllocation { kind, id, bytes, label: label.to_string() };
+
+        if let Some(previous) = registry.allocations.insert((kind, id), allocation) {
+            registry.total_bytes -= previous.bytes;
+        }
+        registry.total_bytes += bytes;
+        record_gpu_upload(bytes);
+
+        if let Some(budget) = registry.budget_bytes
+            && registry.total_bytes > budget
+            && !registry.warned
+        {
+            registry.warned = true;
+            eprintln!(
+                "GPU memory budget exceeded: {} bytes in use, budget is {} bytes (last allocation: {:?} '{}', {} bytes)",
+                registry.total_bytes, budget, kind, label, bytes
+            );
+        }
+    });
+}
+
+/// Removes a previously tracked allocation. Unknown ids are ignored.
+pub fn release_gpu_allocation(kind: GpuResourceKind, id: GLuint) {
+    GPU_MEMORY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        if let Some(previous) = registry.allocations.remove(&(kind, id)) {
+            registry.total_bytes -= previous.bytes;
+        }
+        if registry.budget_bytes.is_none_or(|budget| registry.total_bytes <= budget) {
+            registry.warned = false;
+        }
+    });
+}
+
+/// Sets the GPU memory budget in bytes. `None` disables budget warnings.
+pub fn set_gpu_memory_budget(budget_bytes: Option<usize>) {
+    GPU_MEMORY.with(|registry| {
+        let mut registry = registry.borrow_mut();
+        registry.budget_bytes = budget_bytes;
+        registry.warned = false;
+    });
+}
+
+/// Returns the current GPU memory totals by category.
+pub fn gpu_memory_stats() -> GpuMemoryStats {
+    GPU_MEMORY.with(|registry| {
+        let registry = registry.borrow();
+        let mut stats = GpuMemoryStats {
+            total_bytes: registry.total_bytes,
+            budget_bytes: registry.budget_bytes,
+            ..Default::default()
+        };
+        for allocation in registry.allocations.values() {
+            *stats.bytes_by_kind.entry(allocation.kind).or_insert(0) += allocation.bytes;
+            *stats.count_by_kind.entry(allocation.kind).or_insert(0) += 1;
+        }
+        stats
+    })
+}
+
+/// Returns every live allocation, largest first. Useful for finding what is eating the budget.
+pub fn gpu_allocations() -> Vec<GpuAllocation> {
+    GPU_MEMORY.with(|registry| {
+        let mut allocations: Vec<GpuAllocation> = registry.borrow().allocations.values().cloned().collect();
+        allocations.sort_by_key(|allocation| std::cmp::Reverse(allocation.bytes));
+        allocations
+    })
+}
+
+/// Attaches a debug label to a GL object so it shows up by name in tools like RenderDoc.
+///
+/// Silently does nothing when the driver doesn't expose `glObjectLabel` (GL < 4.3 without KHR_debug).
+fn label_gl_object(kind: GpuResourceKind, id: GLuint, label: &str) {
+    label_object(kind.gl_identifier(), id, label);
+}
+
+/// Size and content counts of a scene or subtree, as returned by
+/// [`Scene::statistics`](crate::engine::scene::Scene::statistics) and
+/// [`Object3D::statistics`](crate::engine::object3d::Object3D::statistics).
+///
+/// Byte counts are estimates derived from the data held by the nodes, not driver-reported
+/// figures; see [`gpu_memory_stats`] for what is actually allocated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SceneStatistics {
+    /// Number of nodes, including the subtree's root.
+    pub nodes: usize,
+    /// Nodes with geometry.
+    pub meshes: usize,
+    /// Instances drawn by instanced meshes.
+    pub instances: usize,
+    /// Total vertices over all geometry.
+    pub vertices: usize,
+    /// Triangles drawn by `Triangles` geometry, counting every instance.
+    pub triangles: usize,
+    /// Line segments drawn by `Lines` and `LineStrip` geometry.
+    pub line_segments: usize,
+    /// Points drawn by `Points` geometry.
+    pub points: usize,
+    /// Distinct materials; nodes whose materials have the same shader, parameters and state
+    /// share one.
+    pub materials: usize,
+    /// Distinct shader programs used by those materials.
+    pub shaders: usize,
+    /// Distinct textures referenced by those materials.
+    pub textures: usize,
+    /// Nodes carrying a light.
+    pub lights: usize,
+    /// Bytes of vertex and index data held on the CPU.
+    pub geometry_bytes: usize,
+    /// Bytes of vertex and index data uploaded to the GPU (geometry drawn at least once).
+    /// Meshes shared between nodes count once.
+    pub gpu_geometry_bytes: usize,
+    /// Estimated bytes of the distinct textures, including mipmaps.
+    pub texture_bytes: usize,
+}
+
+/// GL work issued since the counters were last reset (normally once per frame).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameCounters {
+    /// `glDraw*` calls issued.
+    pub draw_calls: usize,
+    /// Vertices (or indices) submitted by those draw calls.
+    pub vertices: usize,
+    /// Triangles drawn by those draw calls, across all instances. Lines and points count none.
+    pub triangles: usize,
+    /// Buffer and texture (re-)specifications recorded through [`track_gpu_allocation`], and
+    /// updates recorded through [`record_gpu_upload`].
+    pub gpu_uploads: usize,
+    /// Bytes of those uploads.
+    pub gpu_upload_bytes: usize,
+    /// Shader program switches made by the render queue.
+    pub program_binds: usize,
+    /// Material parameter uploads (color, textures, uniforms) made by the render queue.
+    pub material_binds: usize,
+    /// Fixed-function state changes (blending, culling, depth) made by the render queue.
+    pub state_changes: usize,
+}
+
+/// Counts a draw call submitting `vertices` vertices (or indices) of primitive `mode`
+/// (`gl::TRIANGLES`, `gl::LINES`, ...).
+pub fn record_draw_call(mode: GLenum, vertices: usize) {
+    record_instanced_draw_call(mode, vertices, 1);
+}
+
+/// Counts an instanced draw call submitting `vertices` vertices (or indices) of primitive
+/// `mode` for each of `instances` instances.
+pub fn record_instanced_draw_call(mode: GLenum, vertices: usize, instances: usize) {
+    FRAME_COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        current.draw_calls += 1;
+        current.vertices += vertices * instances;
+        current.triangles += triangle_count(mode, vertices) * instances;
+        counters.set(current);
+    });
+}
+
+/// Triangles drawn from `vertices` vertices of primitive `mode`.
+fn triangle_count(mode: GLenum, vertices: usize) -> usize {
+    match mode {
+        gl::TRIANGLES => vertices / 3,
+        gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => vertices.saturating_sub(2),
+        _ => 0,
+    }
+}
+
+/// Counts an upload of `bytes` into existing GPU storage, such as a `glBufferSubData` update.
+/// Allocations recorded with [`track_gpu_allocation`] are counted already.
+pub fn record_gpu_upload(bytes: usize) {
+    FRAME_COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        current.gpu_uploads += 1;
+        current.gpu_upload_bytes += bytes;
+        counters.set(current);
+    });
+}
+
+/// Counts a shader program switch.
+pub fn record_program_bind() {
+    FRAME_COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        current.program_binds += 1;
+        counters.set(current);
+    });
+}
+
+/// Counts an upload of a material's parameters.
+pub fn record_material_bind() {
+    FRAME_COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        current.material_binds += 1;
+        counters.set(current);
+    });
+}
+
+/// Counts a change of fixed-function render state.
+pub fn record_state_change() {
+    FRAME_COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        current.state_changes += 1;
+        counters.set(current);
+    });
+}
+
+/// Returns the counters accumulated since the last [`reset_frame_counters`].
+pub fn frame_counters() -> FrameCounters {
+    FRAME_COUNTERS.with(Cell::get)
+}
+
+/// Zeroes the frame counters. The renderer calls this at the start of every frame.
+pub fn reset_frame_counters() {
+    FRAME_COUNTERS.with(|counters| counters.set(FrameCounters::default()));
+}
+
+static ALLOCATOR_INSTALLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Heap allocation totals counted by [`CountingAllocator`] since the program started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocationCounts {
+    /// Number of allocations (including reallocations).
+    pub allocations: u64,
+    /// Number of deallocations.
+    pub deallocations: u64,
+    /// Total bytes requested by those allocations.
+    pub bytes: u64,
+}
+
+impl AllocationCounts {
+    /// The counts accumulated between `earlier` and `self`.
+    pub fn since(&self, earlier: &AllocationCounts) -> AllocationCounts {
+        AllocationCounts {
+            allocations: self.allocations.saturating_sub(earlier.allocations),
+            deallocations: self.deallocations.saturating_sub(earlier.deallocations),
+            bytes: self.bytes.saturating_sub(earlier.bytes),
+        }
+    }
+}
+
+/// A global allocator wrapper that counts heap allocations, so slow frames can be checked
+/// for allocation churn.
+///
+/// Counting is opt-in because a library can't choose the program's allocator; install it
+/// in the binary:
+///
+/// ```
+/// use rustge::engine::stats::{allocation_counts, CountingAllocator};
+///
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator::new(std::alloc::System);
+///
+/// let before = allocation_counts().unwrap();
+/// let data = vec![0u8; 1024];
+/// let after = allocation_counts().unwrap();
+/// assert!(after.since(&before).bytes >= data.len() as u64);
+/// ```
+#[derive(Debug, Default)]
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    /// Wraps `inner`, counting every allocation made through it.
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        unsafe { self.inner.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        unsafe { self.inner.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation(new_size);
+        unsafe { self.inner.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { self.inner.dealloc(ptr, layout) }
+    }
+}
+
+fn count_allocation(bytes: usize) {
+    ALLOCATOR_INSTALLED.store(true, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Heap allocation totals so far, or `None` if no [`CountingAllocator`] is installed.
+pub fn allocation_counts() -> Option<AllocationCounts> {
+    ALLOCATOR_INSTALLED.load(Ordering::Relaxed).then(|| AllocationCounts {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+    })
+}
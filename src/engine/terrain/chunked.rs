@@ -0,0 +1,450 @@
+//! Heightmap terrain split into chunks with level of detail.
+//!
+//! A [`Terrain`] turns a [`Heightmap`] into a grid of chunk nodes, each a square of
+//! [`TerrainOptions::chunk_size`] cells. Chunks near the camera use every sample; farther
+//! ones skip samples, each [LOD level](TerrainOptions::lod_levels) doubling the spacing, so
+//! a large map costs about as many triangles as a small one. Neighbouring chunks at
+//! different levels don't share their edge vertices; instead each chunk hangs a skirt
+//! from its edges, a vertical strip reaching below the chunk's lowest point, which hides
+//! the cracks between them. Chunks are culled like any other node.
+//!
+//! Normals come from the full-resolution heightmap at every level, so lighting doesn't pop
+//! when a chunk changes level. UVs are local positions scaled by
+//! [`TerrainOptions::uv_scale`], repeating ground textures seamlessly across chunks.
+//! [`splat_material`] blends up to four textured layers by height and slope, e.g. grass on
+//! flat lowland, rock on cliffs and snow on peaks.
+//!
+//! [`Terrain::height_at`] answers where the ground is, for placing objects and keeping
+//! characters on it, without touching the meshes.
+//!
+//! ```no_run
+//! # use std::rc::Rc;
+//! # use rustge::engine::{math::{color::Color, noise::FractalNoise}, renderer::Renderer, texture::Texture};
+//! # use rustge::engine::terrain::{chunked::{splat_material, SplatLayer, Terrain, TerrainOptions}, heightmap::Heightmap};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! # let rock_texture = Rc::new(Texture::from_rgba8(1, 1, &[128; 4], "rock"));
+//! let noise = FractalNoise { frequency: 1.0 / 200.0, ..FractalNoise::new(11) };
+//! let heights = Heightmap::from_fn(1025, 1025, |x, z| noise.sample(x as f32, z as f32) * 80.0);
+//! let material = splat_material(&[
+//!     SplatLayer { heights: [f32::NEG_INFINITY, 40.0], slopes: [0.0, 30.0], ..SplatLayer::new(Color::srgb(0.3, 0.5, 0.2)) },
+//!     SplatLayer { slopes: [30.0, 90.0], texture: Some(rock_texture), ..SplatLayer::new(Color::WHITE) },
+//!     SplatLayer { heights: [40.0, f32::INFINITY], slopes: [0.0, 30.0], ..SplatLayer::new(Color::WHITE) },
+//! ]);
+//! let mut terrain = Terrain::new(heights, TerrainOptions::default(), material);
+//! renderer.get_scene().unwrap().add(terrain.node());
+//!
+//! let tree_height = terrain.height_at(12.0, -40.0).unwrap_or(0.0);
+//! renderer.on_update(move |renderer, _| {
+//!     if let Some(camera) = renderer.get_camera() {
+//!         terrain.update(camera.position);
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::engine::jobs;
+use crate::engine::material::{lit_fragment_source, Material};
+use crate::engine::math::aabb::Aabb;
+use crate::engine::math::color::Color;
+use crate::engine::math::vec::{cross, dot, lerp, normalize_or, sub};
+use crate::engine::object3d::{Geometry, Index, Indices, Object3D, Topology, Vertex};
+use crate::engine::shader::{builtin_program, UniformValue};
+use crate::engine::terrain::heightmap::Heightmap;
+use crate::engine::texture::Texture;
+
+/// Most layers [`splat_material`] blends, the shader's `MAX_SPLAT_LAYERS`.
+pub const MAX_SPLAT_LAYERS: usize = 4;
+
+/// Layout of a [`Terrain`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainOptions {
+    /// Distance between neighbouring heightmap samples, in local units.
+    pub spacing: f32,
+
+    /// Cells along each side of a chunk. Rounded to a power of two in `4..=256`.
+    pub chunk_size: u32,
+
+    /// Number of detail levels, each skipping twice as many samples as the one before.
+    /// Limited so the coarsest level keeps one cell per chunk.
+    pub lod_levels: u32,
+
+    /// Distance from the camera within which chunks are drawn in full detail, in local
+    /// units; every doubling of it drops a level.
+    pub lod_distance: f32,
+
+    /// UVs per local unit, e.g. `0.25` to repeat ground textures every 4 units.
+    pub uv_scale: f32,
+}
+
+impl Default for TerrainOptions {
+    /// 1-unit spacing in chunks of 64 cells, with four levels switching at 64, 128 and
+    /// 256 units.
+    fn default() -> Self {
+        Self { spacing: 1.0, chunk_size: 64, lod_levels: 4, lod_distance: 64.0, uv_scale: 0.25 }
+    }
+}
+
+/// One chunk of the terrain.
+struct Chunk {
+    node: Rc<RefCell<Object3D>>,
+
+    /// First and last sample covered, along X and Z.
+    start: [u32; 2],
+    end: [u32; 2],
+
+    /// Local-space box around the chunk's heights, to measure the camera's distance.
+    bounds: Aabb,
+
+    /// Detail level of the current geometry; `None` until first built.
+    lod: Option<u32>,
+}
+
+/// Heightmap terrain drawn in chunks; see the [module documentation](self).
+pub struct Terrain {
+    heightmap: Heightmap,
+    options: TerrainOptions,
+    material: Material,
+    root: Rc<RefCell<Object3D>>,
+    chunks: Vec<Chunk>,
+}
+
+impl std::fmt::Debug for Terrain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Terrain")
+            .field("heightmap", &self.heightmap)
+            .field("options", &self.options)
+            .field("chunks", &self.chunks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Terrain {
+    /// Creates the terrain, centered on its node's origin. Every chunk is drawn with a copy
+    /// of `material`, e.g. one from [`splat_material`] or [`Material::phong`].
+    ///
+    /// Nothing is built until the first [`update`](Self::update).
+    pub fn new(heightmap: Heightmap, options: TerrainOptions, material: Material) -> Self {
+        let chunk_size = options.chunk_size.clamp(4, 256).next_power_of_two();
+        let options = TerrainOptions {
+            spacing: options.spacing.max(f32::EPSILON),
+            chunk_size,
+            lod_levels: options.lod_levels.clamp(1, chunk_size.ilog2() + 1),
+            ..options
+        };
+        let mut terrain = Self { heightmap, options, material, root: Object3D::new(), chunks: Vec::new() };
+        terrain.layout_chunks();
+        terrain
+    }
+
+    /// The node holding the chunks; add it to the scene. Its transform moves the whole
+    /// terrain, and heights are in its local space.
+    pub fn node(&self) -> Rc<RefCell<Object3D>> {
+        self.root.clone()
+    }
+
+    /// The layout in use, after rounding.
+    pub fn options(&self) -> TerrainOptions {
+        self.options
+    }
+
+    /// The heights the terrain is built from.
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    /// Replaces the heights, e.g. after sculpting, and rebuilds every chunk on the next
+    /// update.
+    pub fn set_heightmap(&mut self, heightmap: Heightmap) {
+        self.heightmap = heightmap;
+        self.layout_chunks();
+    }
+
+    /// Local-space size of the terrain along X and Z.
+    pub fn size(&self) -> [f32; 2] {
+        [self.heightmap.width() - 1, self.heightmap.depth() - 1].map(|cells| cells as f32 * self.options.spacing)
+    }
+
+    /// Height of the full-detail surface at local (`x`, `z`), following its triangles
+    /// exactly; `None` outside the terrain. Distant chunks drawn in less detail may be
+    /// slightly above or below it.
+    ///
+    /// To stand a character on the ground, convert its position with
+    /// [`world_to_local`](Object3D::world_to_local) on the terrain's node first.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let (cell, [fx, fz]) = self.cell_at(x, z)?;
+        let corner = |dx: u32, dz: u32| self.heightmap.get(cell[0] + dx, cell[1] + dz);
+        // Cells are split along the diagonal from (0, 0) to (1, 1), as the chunks draw them
+        Some(if fx >= fz {
+            corner(0, 0) + fx * (corner(1, 0) - corner(0, 0)) + fz * (corner(1, 1) - corner(1, 0))
+        } else {
+            corner(0, 0) + fz * (corner(0, 1) - corner(0, 0)) + fx * (corner(1, 1) - corner(0, 1))
+        })
+    }
+
+    /// The smooth surface normal at local (`x`, `z`), interpolated like the shading
+    /// normals; `None` outside the terrain. Useful to tilt objects placed on slopes.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<[f32; 3]> {
+        let (cell, [fx, fz]) = self.cell_at(x, z)?;
+        let builder = ChunkBuilder { heightmap: &self.heightmap, options: self.options };
+        let normal = |dx: u32, dz: u32| builder.normal(cell[0] + dx, cell[1] + dz);
+        let near = lerp(normal(0, 0), normal(1, 0), fx);
+        let far = lerp(normal(0, 1), normal(1, 1), fx);
+        Some(normalize_or(lerp(near, far, fz), [0.0, 1.0, 0.0]))
+    }
+
+    /// Rebuilds the chunks whose detail level should change for a camera at
+    /// `camera_position` (world space), in parallel on the [job pool](crate::engine::jobs).
+    /// Returns the number of chunks rebuilt.
+    pub fn update(&mut self, camera_position: [f32; 3]) -> usize {
+        let local = self.root.borrow_mut().world_to_local(camera_position);
+        let rebuilds: Vec<(usize, [u32; 2], [u32; 2], u32)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(index, chunk)| {
+                let lod = self.lod_for(&chunk.bounds, local);
+                (chunk.lod != Some(lod)).then_some((index, chunk.start, chunk.end, lod))
+            })
+            .collect();
+
+        let builder = ChunkBuilder { heightmap: &self.heightmap, options: self.options };
+        let geometries = jobs::parallel_map(&rebuilds, |&(_, start, end, lod)| builder.chunk_geometry(start, end, lod));
+        for (&(index, _, _, lod), geometry) in rebuilds.iter().zip(geometries) {
+            let chunk = &mut self.chunks[index];
+            chunk.node.borrow_mut().set_geometry(geometry);
+            chunk.lod = Some(lod);
+        }
+        rebuilds.len()
+    }
+
+    /// Forces every chunk to be rebuilt on the next update.
+    pub fn invalidate(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.lod = None;
+        }
+    }
+
+    /// Detail level for a chunk within `bounds`, seen from local position `eye`.
+    fn lod_for(&self, bounds: &Aabb, eye: [f32; 3]) -> u32 {
+        let closest: [f32; 3] = std::array::from_fn(|axis| eye[axis].clamp(bounds.min[axis], bounds.max[axis]));
+        let ratio = dot(sub(eye, closest), sub(eye, closest)).sqrt() / self.options.lod_distance.max(f32::EPSILON);
+        if ratio < 1.0 {
+            0
+        } else {
+            (ratio.log2().floor() as u32 + 1).min(self.options.lod_levels - 1)
+        }
+    }
+
+    /// Replaces the chunk nodes with a fresh, unbuilt set covering the heightmap.
+    fn layout_chunks(&mut self) {
+        for chunk in self.chunks.drain(..) {
+            Object3D::remove_child(&self.root, &chunk.node);
+        }
+        let size = self.options.chunk_size;
+        let last = [self.heightmap.width() - 1, self.heightmap.depth() - 1];
+        let builder = ChunkBuilder { heightmap: &self.heightmap, options: self.options };
+        for z in (0..last[1]).step_by(size as usize) {
+            for x in (0..last[0]).step_by(size as usize) {
+                let (start, end) = ([x, z], [(x + size).min(last[0]), (z + size).min(last[1])]);
+                let (low, high) = self.heightmap.range(start, end);
+                let [min, max] = [start, end].map(|[x, z]| builder.position(x, z));
+                let node = Object3D::new();
+                node.borrow_mut().set_material(self.material.clone());
+                Object3D::add_child(&self.root, node.clone());
+                let bounds = Aabb::new([min[0], low, min[2]], [max[0], high, max[2]]);
+                self.chunks.push(Chunk { node, start, end, bounds, lod: None });
+            }
+        }
+    }
+
+    /// The cell containing local (`x`, `z`) and the position within it, each in `[0, 1]`.
+    fn cell_at(&self, x: f32, z: f32) -> Option<([u32; 2], [f32; 2])> {
+        let origin = ChunkBuilder { heightmap: &self.heightmap, options: self.options }.position(0, 0);
+        let cells = [self.heightmap.width() - 1, self.heightmap.depth() - 1];
+        let mut cell = [0; 2];
+        let mut fraction = [0.0; 2];
+        for (axis, value) in [(0, x - origin[0]), (1, z - origin[2])] {
+            let samples = value / self.options.spacing;
+            if !(0.0..=cells[axis] as f32).contains(&samples) {
+                return None;
+            }
+            // The far edge belongs to the last cell
+            cell[axis] = (samples.floor() as u32).min(cells[axis] - 1);
+            fraction[axis] = samples - cell[axis] as f32;
+        }
+        Some((cell, fraction))
+    }
+}
+
+/// Builds chunk geometry from the heightmap; shared with the jobs building chunks.
+struct ChunkBuilder<'a> {
+    heightmap: &'a Heightmap,
+    options: TerrainOptions,
+}
+
+impl ChunkBuilder<'_> {
+    /// Local position of sample (`x`, `z`), the heightmap being centered on the origin.
+    fn position(&self, x: u32, z: u32) -> [f32; 3] {
+        let spacing = self.options.spacing;
+        let half = [self.heightmap.width() - 1, self.heightmap.depth() - 1].map(|cells| cells as f32 * spacing / 2.0);
+        [x as f32 * spacing - half[0], self.heightmap.get(x, z), z as f32 * spacing - half[1]]
+    }
+
+    /// Smooth normal at sample (`x`, `z`), from differences to its neighbours (one-sided at
+    /// the edges).
+    fn normal(&self, x: u32, z: u32) -> [f32; 3] {
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.heightmap.width() - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(self.heightmap.depth() - 1));
+        let dx = (self.heightmap.get(right, z) - self.heightmap.get(left, z)) / ((right - left) as f32 * self.options.spacing);
+        let dz = (self.heightmap.get(x, front) - self.heightmap.get(x, back)) / ((front - back) as f32 * self.options.spacing);
+        normalize_or([-dx, 1.0, -dz], [0.0, 1.0, 0.0])
+    }
+
+    /// The chunk from sample `start` to `end` at detail level `lod`, with skirts.
+    fn chunk_geometry(&self, start: [u32; 2], end: [u32; 2], lod: u32) -> Geometry {
+        let step = 1usize << lod;
+        // Every `step`-th sample, plus the far edge where the chunk isn't a multiple of it
+        let samples = |from: u32, to: u32| {
+            let mut samples: Vec<u32> = (from..to).step_by(step).collect();
+            samples.push(to);
+            samples
+        };
+        let (xs, zs) = (samples(start[0], end[0]), samples(start[1], end[1]));
+        let (columns, rows) = (xs.len(), zs.len());
+
+        let vertex_at = |x: u32, z: u32| {
+            let position = self.position(x, z);
+            let uv = [position[0] * self.options.uv_scale, position[2] * self.options.uv_scale];
+            Vertex { position, normal: self.normal(x, z), uv }
+        };
+        let mut vertices: Vec<Vertex> = zs.iter().flat_map(|&z| xs.iter().map(move |&x| vertex_at(x, z))).collect();
+
+        let grid = |i: usize, j: usize| (j * columns + i) as Index;
+        let mut indices = Vec::with_capacity((columns - 1) * (rows - 1) * 6);
+        for j in 0..rows - 1 {
+            for i in 0..columns - 1 {
+                let (a, b, c, d) = (grid(i, j), grid(i + 1, j), grid(i, j + 1), grid(i + 1, j + 1));
+                indices.extend_from_slice(&[a, d, b, a, c, d]);
+            }
+        }
+
+        // Skirts reach below the lowest point, past any neighbour's coarser edge
+        let (low, high) = self.heightmap.range(start, end);
+        let depth = high - low + self.options.spacing * step as f32;
+        let sides: [(Vec<Index>, [f32; 3]); 4] = [
+            ((0..columns).map(|i| grid(i, 0)).collect(), [0.0, 0.0, -1.0]),
+            ((0..columns).map(|i| grid(i, rows - 1)).collect(), [0.0, 0.0, 1.0]),
+            ((0..rows).map(|j| grid(0, j)).collect(), [-1.0, 0.0, 0.0]),
+            ((0..rows).map(|j| grid(columns - 1, j)).collect(), [1.0, 0.0, 0.0]),
+        ];
+        for (edge, outward) in sides {
+            let base = vertices.len() as Index;
+            for &top in &edge {
+                let mut bottom = vertices[top as usize];
+                bottom.position[1] -= depth;
+                vertices.push(bottom);
+            }
+            for (k, pair) in edge.windows(2).enumerate() {
+                let (p, q) = (pair[0], pair[1]);
+                let (p_low, q_low) = (base + k as Index, base + k as Index + 1);
+                let [pp, qp, qlp] = [p, q, q_low].map(|index| vertices[index as usize].position);
+                // Wind each quad to face outward, so back-face culling keeps it
+                if dot(cross(sub(qp, pp), sub(qlp, pp)), outward) >= 0.0 {
+                    indices.extend_from_slice(&[p, q, q_low, p, q_low, p_low]);
+                } else {
+                    indices.extend_from_slice(&[p, q_low, q, p, p_low, q_low]);
+                }
+            }
+        }
+
+        Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+    }
+}
+
+/// A layer of a [`splat_material`]: a tinted, optionally textured ground cover on terrain
+/// within a band of height and slope.
+#[derive(Clone, Debug)]
+pub struct SplatLayer {
+    /// Tint, multiplied with the texture if there is one.
+    pub color: Color,
+
+    /// Texture repeated over the layer, `None` for plain color.
+    pub texture: Option<Rc<Texture>>,
+
+    /// Texture repeats per terrain UV unit (see [`TerrainOptions::uv_scale`]).
+    pub uv_scale: f32,
+
+    /// Lowest and highest world-space height covered.
+    pub heights: [f32; 2],
+
+    /// Least and most steep slope covered, in degrees from flat.
+    pub slopes: [f32; 2],
+
+    /// Height over which the layer fades out beyond its height band, in world units.
+    /// Overlapping layers are blended by weight.
+    pub height_blend: f32,
+
+    /// Slope over which the layer fades out beyond its slope band, in degrees.
+    pub slope_blend: f32,
+}
+
+impl SplatLayer {
+    /// An untextured layer in `color` covering every height and slope.
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            texture: None,
+            uv_scale: 1.0,
+            heights: [f32::NEG_INFINITY, f32::INFINITY],
+            slopes: [0.0, 90.0],
+            height_blend: 2.0,
+            slope_blend: 5.0,
+        }
+    }
+}
+
+/// A Blinn-Phong material splatting up to [`MAX_SPLAT_LAYERS`] `layers` over terrain by
+/// world height and slope; ground outside every layer takes the material's color. Extra
+/// layers are ignored with a warning. Works on any mesh, but is made for [`Terrain`].
+///
+/// Must be called with a current GL context.
+pub fn splat_material(layers: &[SplatLayer]) -> Material {
+    let fragment = lit_fragment_source(include_str!("../shaders/phong.frag")).replacen(
+        "#version 330 core",
+        "#version 330 core\n#define SURFACE_ALBEDO",
+        1,
+    ) + include_str!("../shaders/terrain_splat.glsl");
+    let shader = builtin_program("terrain splat", include_str!("../shaders/phong.vert"), &fragment);
+    let mut material = Material::new(shader);
+    material.set_uniform("u_specular", UniformValue::Vec3([0.05, 0.05, 0.05]));
+    material.set_uniform("u_shininess", UniformValue::Float(16.0));
+
+    if layers.len() > MAX_SPLAT_LAYERS {
+        eprintln!("Warning: terrain splatting supports {} layers, ignoring {} more", MAX_SPLAT_LAYERS, layers.len() - MAX_SPLAT_LAYERS);
+    }
+    let layers = &layers[..layers.len().min(MAX_SPLAT_LAYERS)];
+    material.set_uniform("u_splat_layer_count", UniformValue::Int(layers.len() as i32));
+    for (i, layer) in layers.iter().enumerate() {
+        // Unbounded bands stay finite for the shader's arithmetic
+        let [min_height, max_height] = layer.heights.map(|height| height.clamp(-1e6, 1e6));
+        material.set_uniform(&format!("u_splat_colors[{}]", i), UniformValue::Vec4(layer.color.to_srgb()));
+        material.set_uniform(
+            &format!("u_splat_ranges[{}]", i),
+            UniformValue::Vec4([min_height, max_height, layer.slopes[0], layer.slopes[1]]),
+        );
+        material.set_uniform(
+            &format!("u_splat_params[{}]", i),
+            UniformValue::Vec4([
+                layer.uv_scale,
+                layer.texture.is_some() as i32 as f32,
+                layer.height_blend.max(0.0),
+                layer.slope_blend.max(0.0),
+            ]),
+        );
+        if let Some(texture) = &layer.texture {
+            material.set_texture(&format!("u_splat_textures[{}]", i), texture.clone());
+        }
+    }
+    material
+}
@@ -0,0 +1,279 @@
+//! Geometry clipmap ground that follows the camera.
+//!
+//! A [`ClipmapGround`] draws terrain as nested square rings centered on the camera: the
+//! innermost level has the finest vertex spacing and every level around it doubles the
+//! spacing while covering twice the distance. A handful of small meshes thus reach far into
+//! the distance with detail where the camera is, and no mesh of the whole world is ever
+//! stored.
+//!
+//! Heights are streamed from a [`HeightSource`] (a procedural function, or a lookup into
+//! height data paged in by the game). When the camera moves, each level re-centers once the
+//! camera has crossed one of its (coarse) grid cells, re-sampling only that level; levels
+//! re-sampled together are built in parallel on the [job pool](crate::engine::jobs), so the
+//! height source must be `Send + Sync`. UVs are
+//! world-space positions scaled by [`ClipmapOptions::uv_scale`], so tiled ground textures on
+//! the material line up seamlessly across levels.
+//!
+//! ```no_run
+//! # use rustge::engine::{material::Material, math::color::Color, renderer::Renderer};
+//! # use rustge::engine::terrain::clipmap::{ClipmapGround, ClipmapOptions};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! let hills = |x: f32, z: f32| (x * 0.05).sin() * (z * 0.04).cos() * 6.0;
+//! let mut ground = ClipmapGround::new(ClipmapOptions::default(), hills, Material::phong(Color::srgb(0.3, 0.5, 0.2)));
+//! renderer.get_scene().unwrap().add(ground.node());
+//!
+//! renderer.on_update(move |renderer, _| {
+//!     if let Some(camera) = renderer.get_camera() {
+//!         ground.update(camera.position);
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use crate::engine::jobs;
+use crate::engine::material::Material;
+use crate::engine::math::vec::{add, normalize};
+use crate::engine::object3d::{Geometry, Index, Indices, Object3D, Topology, Vertex};
+
+/// Terrain height at a local-space (x, z) position.
+pub type HeightSource = Arc<dyn Fn(f32, f32) -> f32 + Send + Sync>;
+
+/// Layout of a [`ClipmapGround`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipmapOptions {
+    /// Number of nested levels. The ground reaches `grid_size * base_spacing * 2^(levels - 1) / 2`
+    /// from the camera.
+    pub levels: u32,
+
+    /// Cells along each side of a level. Rounded to a multiple of 4 in `8..=1024`; up to 252
+    /// keeps each level within 16-bit indices.
+    pub grid_size: u32,
+
+    /// Vertex spacing of the innermost level, in local units.
+    pub base_spacing: f32,
+
+    /// UVs per local unit, e.g. `0.25` to repeat the ground texture every 4 units.
+    pub uv_scale: f32,
+}
+
+impl Default for ClipmapOptions {
+    /// Six levels of 64 cells with 1-unit spacing, reaching 1 km from the camera.
+    fn default() -> Self {
+        Self { levels: 6, grid_size: 64, base_spacing: 1.0, uv_scale: 0.25 }
+    }
+}
+
+/// One ring of the clipmap.
+struct ClipmapLevel {
+    node: Rc<RefCell<Object3D>>,
+
+    /// Center of the level in units of its vertex spacing; `None` until first streamed.
+    center: Option<[i64; 2]>,
+}
+
+/// Camera-centered terrain made of nested rings of increasing vertex spacing.
+pub struct ClipmapGround {
+    options: ClipmapOptions,
+    heights: HeightSource,
+    root: Rc<RefCell<Object3D>>,
+    levels: Vec<ClipmapLevel>,
+}
+
+impl std::fmt::Debug for ClipmapGround {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClipmapGround")
+            .field("options", &self.options)
+            .field("levels", &self.levels.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClipmapGround {
+    /// Creates the ground. Every level is drawn with a copy of `material`, whose shader
+    /// receives ordinary position/normal/uv vertices (e.g. [`Material::phong`]).
+    ///
+    /// Nothing is streamed until the first [`update`](Self::update).
+    pub fn new(
+        options: ClipmapOptions,
+        heights: impl Fn(f32, f32) -> f32 + Send + Sync + 'static,
+        material: Material,
+    ) -> Self {
+        let options = ClipmapOptions {
+            levels: options.levels.max(1),
+            grid_size: (options.grid_size.clamp(8, 1024) / 4) * 4,
+            base_spacing: options.base_spacing.max(f32::EPSILON),
+            ..options
+        };
+
+        let root = Object3D::new();
+        let levels = (0..options.levels)
+            .map(|_| {
+                let node = Object3D::new();
+                node.borrow_mut().set_material(material.clone());
+                Object3D::add_child(&root, node.clone());
+                ClipmapLevel { node, center: None }
+            })
+            .collect();
+
+        Self { options, heights: Arc::new(heights), root, levels }
+    }
+
+    /// The node holding the levels; add it to the scene. Its transform moves the whole
+    /// ground, and heights are sampled in its local space.
+    pub fn node(&self) -> Rc<RefCell<Object3D>> {
+        self.root.clone()
+    }
+
+    /// The layout in use, after rounding.
+    pub fn options(&self) -> ClipmapOptions {
+        self.options
+    }
+
+    /// Terrain height at local (x, z), as given by the height source.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        (self.heights)(x, z)
+    }
+
+    /// Replaces the height source and re-streams every level on the next update.
+    pub fn set_heights(&mut self, heights: impl Fn(f32, f32) -> f32 + Send + Sync + 'static) {
+        self.heights = Arc::new(heights);
+        self.invalidate();
+    }
+
+    /// Forces every level to be re-streamed on the next update, e.g. after the data behind
+    /// the height source changed.
+    pub fn invalidate(&mut self) {
+        for level in &mut self.levels {
+            level.center = None;
+        }
+    }
+
+    /// Re-centers the levels on `camera_position` (world space), streaming heights for the
+    /// levels whose center moved. Returns the number of levels re-streamed.
+    pub fn update(&mut self, camera_position: [f32; 3]) -> usize {
+        let local = self.root.borrow_mut().world_to_local(camera_position);
+
+        // Each level snaps to twice its spacing, so its edges line up with the next level's grid
+        let centers: Vec<[i64; 2]> = (0..self.levels.len())
+            .map(|index| {
+                let spacing = spacing(&self.options, index);
+                let snap = |v: f32| ((v / (2.0 * spacing)).round() as i64) * 2;
+                [snap(local[0]), snap(local[2])]
+            })
+            .collect();
+
+        // A level's hole depends on the level inside it, so re-stream when either moved
+        let moved: Vec<usize> = (0..self.levels.len())
+            .filter(|&index| {
+                let inner_moved = index > 0 && self.levels[index - 1].center != Some(centers[index - 1]);
+                self.levels[index].center != Some(centers[index]) || inner_moved
+            })
+            .collect();
+
+        let sampler = LevelSampler { options: self.options, heights: &*self.heights };
+        let geometries = jobs::parallel_map(&moved, |&index| {
+            let inner = (index > 0).then(|| centers[index - 1]);
+            sampler.level_geometry(index, centers[index], inner)
+        });
+        for (&index, geometry) in moved.iter().zip(geometries) {
+            self.levels[index].node.borrow_mut().set_geometry(geometry);
+        }
+        for (level, center) in self.levels.iter_mut().zip(centers) {
+            level.center = Some(center);
+        }
+        moved.len()
+    }
+}
+
+/// Vertex spacing of level `index` of a clipmap laid out by `options`.
+fn spacing(options: &ClipmapOptions, index: usize) -> f32 {
+    options.base_spacing * (1u64 << index.min(62)) as f32
+}
+
+/// Builds level geometry from the height source; shared with the jobs building levels.
+struct LevelSampler<'a> {
+    options: ClipmapOptions,
+    heights: &'a (dyn Fn(f32, f32) -> f32 + Send + Sync),
+}
+
+impl LevelSampler<'_> {
+    /// Builds level `index` centered on `center` (in units of its spacing), leaving a hole
+    /// for the finer level centered on `inner` (in units of the finer spacing).
+    fn level_geometry(&self, index: usize, center: [i64; 2], inner: Option<[i64; 2]>) -> Geometry {
+        let n = self.options.grid_size as i64;
+        let half = n / 2;
+        let spacing = spacing(&self.options, index);
+        let origin = [center[0] - half, center[1] - half];
+
+        // The finer level covers [c - n/4, c + n/4] cells of this level's spacing
+        let hole = inner.map(|inner| {
+            let (x, z) = (inner[0] / 2, inner[1] / 2);
+            [x - n / 4, x + n / 4, z - n / 4, z + n / 4]
+        });
+
+        let height = |gx: i64, gz: i64| (self.heights)(gx as f32 * spacing, gz as f32 * spacing);
+        let mut vertices = Vec::with_capacity(((n + 1) * (n + 1)) as usize);
+        for j in 0..=n {
+            for i in 0..=n {
+                let (gx, gz) = (origin[0] + i, origin[1] + j);
+                let on_x_edge = i == 0 || i == n;
+                let on_z_edge = j == 0 || j == n;
+
+                // Odd vertices on the outer edge sit mid-way along a cell edge of the coarser
+                // level around this one; interpolating them along the edge avoids cracks
+                let (y, normal) = if on_z_edge && gx.rem_euclid(2) == 1 {
+                    let (a, b) = ((gx - 1, gz), (gx + 1, gz));
+                    (
+                        (height(a.0, a.1) + height(b.0, b.1)) / 2.0,
+                        normalize(add(self.normal(a.0, a.1, spacing), self.normal(b.0, b.1, spacing))),
+                    )
+                } else if on_x_edge && gz.rem_euclid(2) == 1 {
+                    let (a, b) = ((gx, gz - 1), (gx, gz + 1));
+                    (
+                        (height(a.0, a.1) + height(b.0, b.1)) / 2.0,
+                        normalize(add(self.normal(a.0, a.1, spacing), self.normal(b.0, b.1, spacing))),
+                    )
+                } else {
+                    (height(gx, gz), self.normal(gx, gz, spacing))
+                };
+
+                let (x, z) = (gx as f32 * spacing, gz as f32 * spacing);
+                vertices.push(Vertex {
+                    position: [x, y, z],
+                    normal,
+                    uv: [x * self.options.uv_scale, z * self.options.uv_scale],
+                });
+            }
+        }
+
+        let vertex = |i: i64, j: i64| (j * (n + 1) + i) as Index;
+        let mut indices = Vec::with_capacity((n * n * 6) as usize);
+        for j in 0..n {
+            for i in 0..n {
+                let (gx, gz) = (origin[0] + i, origin[1] + j);
+                if let Some([x0, x1, z0, z1]) = hole
+                    && gx >= x0
+                    && gx < x1
+                    && gz >= z0
+                    && gz < z1
+                {
+                    continue;
+                }
+                let (a, b, c, d) = (vertex(i, j), vertex(i + 1, j), vertex(i, j + 1), vertex(i + 1, j + 1));
+                indices.extend_from_slice(&[a, d, b, a, c, d]);
+            }
+        }
+
+        Geometry { vertices, indices: Indices::from_u32(indices), topology: Topology::Triangles, skin: None }
+    }
+
+    /// Surface normal at grid position (`gx`, `gz`), from central differences at `spacing`.
+    fn normal(&self, gx: i64, gz: i64, spacing: f32) -> [f32; 3] {
+        let (x, z) = (gx as f32 * spacing, gz as f32 * spacing);
+        let dx = (self.heights)(x + spacing, z) - (self.heights)(x - spacing, z);
+        let dz = (self.heights)(x, z + spacing) - (self.heights)(x, z - spacing);
+        normalize([-dx, 2.0 * spacing, -dz])
+    }
+}
@@ -0,0 +1,124 @@
+//! Grids of terrain heights.
+//!
+//! A [`Heightmap`] stores one height per sample of a regular grid, in world units, row by
+//! row along Z. It comes from a grayscale image exported by a terrain tool
+//! ([`Heightmap::load_png`], 16-bit images keep their full precision) or from a function
+//! such as [`FractalNoise`](crate::engine::math::noise::FractalNoise)
+//! ([`Heightmap::from_fn`]), and is turned into geometry by a
+//! [`Terrain`](crate::engine::terrain::chunked::Terrain).
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::math::noise::FractalNoise;
+//! # use rustge::engine::terrain::heightmap::Heightmap;
+//! let noise = FractalNoise { frequency: 1.0 / 64.0, ..FractalNoise::new(3) };
+//! let mut heights = Heightmap::from_fn(257, 257, |x, z| noise.sample(x as f32, z as f32) * 30.0);
+//! heights.set(128, 128, 50.0); // a peak in the middle
+//! assert_eq!(heights.get(128, 128), 50.0);
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+
+/// Heights on a `width` × `depth` grid of samples; see the [module documentation](self).
+#[derive(Clone, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    /// Row-major along Z: the sample at (x, z) is at `z * width + x`.
+    heights: Vec<f32>,
+}
+
+impl std::fmt::Debug for Heightmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Heightmap").field("width", &self.width).field("depth", &self.depth).finish_non_exhaustive()
+    }
+}
+
+impl Heightmap {
+    /// A flat heightmap of `width` × `depth` samples at height 0, both at least 2.
+    pub fn new(width: u32, depth: u32) -> Self {
+        let (width, depth) = (width.max(2), depth.max(2));
+        Self { width, depth, heights: vec![0.0; width as usize * depth as usize] }
+    }
+
+    /// A heightmap of `width` × `depth` samples (both at least 2) with the height of each
+    /// sample given by `height(x, z)`.
+    pub fn from_fn(width: u32, depth: u32, height: impl Fn(u32, u32) -> f32) -> Self {
+        let mut heightmap = Self::new(width, depth);
+        for z in 0..heightmap.depth {
+            for x in 0..heightmap.width {
+                heightmap.set(x, z, height(x, z));
+            }
+        }
+        heightmap
+    }
+
+    /// Loads a grayscale PNG with a sample per pixel, the image's top row at -Z. Black is
+    /// height 0 and white `height_scale`; 16-bit images keep their precision, color images
+    /// use their red channel.
+    pub fn load_png(path: impl AsRef<Path>, height_scale: f32) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+        buffer.truncate(info.buffer_size());
+
+        let channels = info.color_type.samples();
+        let values: Vec<f32> = match info.bit_depth {
+            png::BitDepth::Sixteen => buffer
+                .chunks_exact(2 * channels)
+                .map(|pixel| u16::from_be_bytes([pixel[0], pixel[1]]) as f32 / u16::MAX as f32)
+                .collect(),
+            _ => buffer.chunks_exact(channels).map(|pixel| pixel[0] as f32 / u8::MAX as f32).collect(),
+        };
+        if info.width < 2 || info.height < 2 || values.len() != info.width as usize * info.height as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Heightmap images need at least 2x2 pixels"));
+        }
+        Ok(Self { width: info.width, depth: info.height, heights: values.into_iter().map(|v| v * height_scale).collect() })
+    }
+
+    /// Number of samples along X.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Number of samples along Z.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// The height of sample (`x`, `z`), clamped to the grid.
+    pub fn get(&self, x: u32, z: u32) -> f32 {
+        let (x, z) = (x.min(self.width - 1), z.min(self.depth - 1));
+        self.heights[(z * self.width + x) as usize]
+    }
+
+    /// Sets the height of sample (`x`, `z`); does nothing outside the grid.
+    pub fn set(&mut self, x: u32, z: u32, height: f32) {
+        if x < self.width && z < self.depth {
+            self.heights[(z * self.width + x) as usize] = height;
+        }
+    }
+
+    /// The lowest and highest height of the samples from `start` to `end` (inclusive, in
+    /// samples along X and Z).
+    pub fn range(&self, start: [u32; 2], end: [u32; 2]) -> (f32, f32) {
+        let (mut low, mut high) = (f32::INFINITY, f32::NEG_INFINITY);
+        for z in start[1]..=end[1].min(self.depth - 1) {
+            for x in start[0]..=end[0].min(self.width - 1) {
+                let height = self.get(x, z);
+                low = low.min(height);
+                high = high.max(height);
+            }
+        }
+        (low, high)
+    }
+
+    /// All heights, row by row along Z.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+}
@@ -0,0 +1,3 @@
+pub mod clipmap;
+pub mod heightmap;
+pub mod chunked;
@@ -0,0 +1,780 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use gl::types::{GLenum, GLint, GLsizei, GLuint};
+use crate::engine::color_space::color_texture_format;
+use crate::engine::jobs;
+use crate::engine::math::color::{srgb_to_linear, Color};
+use crate::engine::math::vec::normalize;
+use crate::engine::readback::flip_rows;
+use crate::engine::stats::{release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// How a texture is sampled when minified or magnified.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Nearest texel, no mipmaps. Keeps pixel art and bitmap fonts crisp.
+    Nearest,
+    /// Bilinear filtering of the base level, no mipmaps.
+    Linear,
+    /// Bilinear filtering blended between mip levels (the default).
+    Trilinear,
+}
+
+/// Sharpness of mipmapped textures, set with
+/// [`Renderer::set_texture_lod`](crate::engine::renderer::Renderer::set_texture_lod).
+///
+/// A negative LOD bias samples more detailed mip levels: crisper textures, but more shimmer
+/// on fine patterns in motion. Temporal anti-aliasing averages that shimmer away while
+/// blurring slightly, so TAA usually pairs with a negative bias to restore sharpness.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextureLod {
+    /// Bias added to every texture's mip level selection, in levels.
+    pub bias: f32,
+
+    /// Extra negative bias applied while the camera is jittered for TAA (see
+    /// [`Camera::jitter`](crate::engine::camera::Camera::jitter) and
+    /// [`QualitySettings::taa`](crate::engine::quality::QualitySettings::taa)), e.g. 0.5 for
+    /// half a level sharper. 0 disables it.
+    pub taa_sharpening: f32,
+}
+
+impl TextureLod {
+    /// Biases are clamped to this many levels either way, the least OpenGL guarantees.
+    pub const MAX_BIAS: f32 = 2.0;
+
+    /// The global bias for a frame drawn with `taa_active`.
+    ///
+    /// ```
+    /// # use rustge::engine::texture::TextureLod;
+    /// let lod = TextureLod { bias: 0.25, taa_sharpening: 0.75 };
+    /// assert_eq!(lod.frame_bias(false), 0.25);
+    /// assert_eq!(lod.frame_bias(true), -0.5);
+    /// ```
+    pub fn frame_bias(&self, taa_active: bool) -> f32 {
+        let sharpening = if taa_active { self.taa_sharpening } else { 0.0 };
+        (self.bias - sharpening).clamp(-Self::MAX_BIAS, Self::MAX_BIAS)
+    }
+}
+
+thread_local! {
+    /// LOD bias of the frame being drawn, added to each material's own.
+    static FRAME_LOD_BIAS: Cell<f32> = const { Cell::new(0.0) };
+}
+
+/// Sets the LOD bias every material adds to its own while drawing the current frame.
+pub(crate) fn set_frame_lod_bias(bias: f32) {
+    FRAME_LOD_BIAS.with(|frame_bias| frame_bias.set(bias));
+}
+
+/// The LOD bias of the frame being drawn.
+pub(crate) fn frame_lod_bias() -> f32 {
+    FRAME_LOD_BIAS.with(Cell::get)
+}
+
+/// A 2D OpenGL texture.
+///
+/// The texture owns its GL object and deletes it when dropped, so it is usually shared
+/// between materials through an `Rc<Texture>`.
+#[derive(Debug)]
+pub struct Texture {
+    /// The OpenGL texture name; replaced when the texture is [reloaded](Self::reload_rgba8).
+    id: Cell<GLuint>,
+
+    /// Width of the base mip level in pixels.
+    width: Cell<u32>,
+
+    /// Height of the base mip level in pixels.
+    height: Cell<u32>,
+
+    /// LOD bias last set on the GL object, to skip redundant parameter changes.
+    lod_bias: Cell<f32>,
+}
+
+impl Texture {
+    /// Uploads tightly packed 8-bit RGBA pixel data and generates a full mip chain.
+    ///
+    /// The pixels are sRGB-encoded color: in the [sRGB pipeline](crate::engine::color_space)
+    /// the texture is `SRGB8_ALPHA8` and shaders sample linear values.
+    ///
+    /// # Parameters
+    /// - `width`, `height`: dimensions of the image in pixels.
+    /// - `pixels`: `width * height * 4` bytes, rows ordered bottom to top as OpenGL expects.
+    /// - `label`: debug label used for GPU memory tracking and `glObjectLabel`.
+    ///
+    /// # Panics
+    /// Panics if `pixels` is smaller than `width * height * 4` bytes.
+    pub fn from_rgba8(width: u32, height: u32, pixels: &[u8], label: &str) -> Self {
+        assert!(
+            pixels.len() >= (width * height * 4) as usize,
+            "Texture data too small: expected {} bytes, got {}",
+            width * height * 4,
+            pixels.len()
+        );
+
+        Self::from_uploaded(upload_rgba8(width, height, pixels), width, height, label)
+    }
+
+    /// Uploads tightly packed 8-bit RGBA data that isn't color, such as normal, roughness or
+    /// occlusion maps, like [`from_rgba8`](Self::from_rgba8) but always as `RGBA8`: shaders
+    /// sample the values as stored in either [color pipeline](crate::engine::color_space).
+    ///
+    /// # Panics
+    /// Panics if `pixels` is smaller than `width * height * 4` bytes.
+    pub fn from_linear_rgba8(width: u32, height: u32, pixels: &[u8], label: &str) -> Self {
+        assert!(
+            pixels.len() >= (width * height * 4) as usize,
+            "Texture data too small: expected {} bytes, got {}",
+            width * height * 4,
+            pixels.len()
+        );
+
+        Self::from_uploaded(upload_pixels(width, height, pixels, gl::RGBA8), width, height, label)
+    }
+
+    /// Takes ownership of texture `id`, filled by [`upload_rgba8`] (possibly on the
+    /// [upload context](crate::engine::upload)), and registers its memory under `label`.
+    pub(crate) fn from_uploaded(id: GLuint, width: u32, height: u32, label: &str) -> Self {
+        // A full mip chain adds roughly one third on top of the base level
+        let base_bytes = (width * height * 4) as usize;
+        track_gpu_allocation(GpuResourceKind::Texture, id, base_bytes + base_bytes / 3, label);
+
+        Self { id: Cell::new(id), width: Cell::new(width), height: Cell::new(height), lod_bias: Cell::new(0.0) }
+    }
+
+    /// Loads a PNG file as an RGBA8 texture with a full mip chain, labeled with its path.
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let (width, height, pixels) = read_png_bottom_up(path)?;
+        Ok(Self::from_rgba8(width, height, &pixels, &path.display().to_string()))
+    }
+
+    /// Loads a PNG file holding data rather than color, see
+    /// [`from_linear_rgba8`](Self::from_linear_rgba8).
+    pub fn load_png_linear(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let (width, height, pixels) = read_png_bottom_up(path)?;
+        Ok(Self::from_linear_rgba8(width, height, &pixels, &path.display().to_string()))
+    }
+
+    /// Replaces the texture's contents with tightly packed RGBA8 `pixels` (rows bottom to
+    /// top), possibly of a different size, in place: every material sharing the texture
+    /// samples the new image. Filtering and wrapping stay as they were.
+    ///
+    /// # Panics
+    /// Panics if `pixels` is smaller than `width * height * 4` bytes.
+    pub fn reload_rgba8(&self, width: u32, height: u32, pixels: &[u8], label: &str) {
+        assert!(
+            pixels.len() >= (width * height * 4) as usize,
+            "Texture data too small: expected {} bytes, got {}",
+            width * height * 4,
+            pixels.len()
+        );
+        let id = upload_rgba8(width, height, pixels);
+        let old = self.id.replace(id);
+        unsafe {
+            // Carry over the sampling parameters set on the old texture
+            for parameter in [gl::TEXTURE_MIN_FILTER, gl::TEXTURE_MAG_FILTER, gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T] {
+                let mut value = 0;
+                gl::BindTexture(gl::TEXTURE_2D, old);
+                gl::GetTexParameteriv(gl::TEXTURE_2D, parameter, &mut value);
+                gl::BindTexture(gl::TEXTURE_2D, id);
+                gl::TexParameteri(gl::TEXTURE_2D, parameter, value);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::DeleteTextures(1, &old);
+        }
+        release_gpu_allocation(GpuResourceKind::Texture, old);
+        let base_bytes = (width * height * 4) as usize;
+        track_gpu_allocation(GpuResourceKind::Texture, id, base_bytes + base_bytes / 3, label);
+        self.width.set(width);
+        self.height.set(height);
+        self.lod_bias.set(0.0);
+    }
+
+    /// Reloads the texture from a PNG file, in place, like [`reload_rgba8`](Self::reload_rgba8).
+    /// On error the texture keeps its contents.
+    pub fn reload_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let (width, height, pixels) = read_png_bottom_up(path)?;
+        self.reload_rgba8(width, height, &pixels, &path.display().to_string());
+        Ok(())
+    }
+
+    /// Allocates an uninitialized `width` x `height` 8-bit color texture to be rendered into (see
+    /// [`RenderTarget`](crate::engine::render_target::RenderTarget)). Bilinear filtering
+    /// without mipmaps, clamped edges.
+    pub fn empty(width: u32, height: u32, label: &str) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                color_texture_format() as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::Texture, id, (width * height * 4) as usize, label);
+
+        Self { id: Cell::new(id), width: Cell::new(width), height: Cell::new(height), lod_bias: Cell::new(0.0) }
+    }
+
+    /// Allocates an uninitialized `width` x `height` 24-bit depth, 8-bit stencil texture to
+    /// be rendered into, so passes after the scene can sample its depth. Sampling returns
+    /// the depth in the red channel. Nearest filtering, clamped edges.
+    pub fn empty_depth(width: u32, height: u32, label: &str) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::DEPTH24_STENCIL8 as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::DEPTH_STENCIL,
+                gl::UNSIGNED_INT_24_8,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::Texture, id, (width * height * 4) as usize, label);
+
+        Self { id: Cell::new(id), width: Cell::new(width), height: Cell::new(height), lod_bias: Cell::new(0.0) }
+    }
+
+    /// Allocates an uninitialized `width` x `height` two-channel 16-bit float texture to be
+    /// rendered into, for signed data such as
+    /// [motion vectors](crate::engine::motion). Nearest filtering, clamped edges.
+    pub fn empty_rg16f(width: u32, height: u32, label: &str) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RG16F as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RG,
+                gl::HALF_FLOAT,
+                std::ptr::null(),
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::Texture, id, (width * height * 4) as usize, label);
+
+        Self { id: Cell::new(id), width: Cell::new(width), height: Cell::new(height), lod_bias: Cell::new(0.0) }
+    }
+
+    /// Uploads 32-bit float RGBA data, one `[r, g, b, a]` per texel, for data textures
+    /// read with `texelFetch` (such as [vertex animations](crate::engine::mesh::vertex_animation)).
+    /// No mipmaps, nearest filtering and clamped edges, so values come back exactly.
+    ///
+    /// # Panics
+    /// Panics if `texels` is shorter than `width * height`.
+    pub fn from_rgba32f(width: u32, height: u32, texels: &[[f32; 4]], label: &str) -> Self {
+        assert!(
+            texels.len() >= (width * height) as usize,
+            "Texture data too small: expected {} texels, got {}",
+            width * height,
+            texels.len()
+        );
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA32F as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                0,
+                gl::RGBA,
+                gl::FLOAT,
+                texels.as_ptr() as *const _,
+            );
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        track_gpu_allocation(GpuResourceKind::Texture, id, (width * height * 16) as usize, label);
+
+        Self { id: Cell::new(id), width: Cell::new(width), height: Cell::new(height), lod_bias: Cell::new(0.0) }
+    }
+
+    /// Binds the texture to the given texture unit (`GL_TEXTURE0 + unit`).
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id.get());
+        }
+    }
+
+    /// Sets the mip level selection bias of the texture, which must be bound to the active
+    /// texture unit (see [`bind`](Self::bind)). Called by materials with their own bias
+    /// plus the frame's; does nothing if the bias is unchanged.
+    pub fn apply_lod_bias(&self, bias: f32) {
+        let bias = bias.clamp(-TextureLod::MAX_BIAS, TextureLod::MAX_BIAS);
+        if self.lod_bias.get() != bias {
+            self.lod_bias.set(bias);
+            unsafe {
+                gl::TexParameterf(gl::TEXTURE_2D, gl::TEXTURE_LOD_BIAS, bias);
+            }
+        }
+    }
+
+    /// Changes how the texture is filtered when sampled.
+    pub fn set_filter(&self, filter: TextureFilter) {
+        let (min, mag) = match filter {
+            TextureFilter::Nearest => (gl::NEAREST, gl::NEAREST),
+            TextureFilter::Linear => (gl::LINEAR, gl::LINEAR),
+            TextureFilter::Trilinear => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+        };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.id.get());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    /// The OpenGL texture name.
+    pub fn id(&self) -> GLuint {
+        self.id.get()
+    }
+
+    /// Width of the base mip level in pixels.
+    pub fn width(&self) -> u32 {
+        self.width.get()
+    }
+
+    /// Height of the base mip level in pixels.
+    pub fn height(&self) -> u32 {
+        self.height.get()
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::Texture, self.id.get());
+        unsafe {
+            gl::DeleteTextures(1, &self.id.get());
+        }
+    }
+}
+
+/// A cube map texture: six square faces sampled by direction, used for skyboxes and
+/// environment reflections.
+///
+/// Owns its GL object like [`Texture`] and is usually shared through an `Rc<Cubemap>`.
+#[derive(Debug)]
+pub struct Cubemap {
+    /// The OpenGL texture name.
+    id: GLuint,
+
+    /// Width and height of each face in pixels.
+    size: u32,
+
+    /// Average linear color over all faces, used as an ambient lighting estimate.
+    average_color: Color,
+}
+
+impl Cubemap {
+    /// Uploads six tightly packed 8-bit sRGB RGBA faces of `size * size` pixels.
+    ///
+    /// Faces are in OpenGL order: +X, -X, +Y, -Y, +Z, -Z. Mipmaps are generated and
+    /// filtering is seamless across face edges.
+    ///
+    /// # Panics
+    /// Panics if any face is smaller than `size * size * 4` bytes.
+    pub fn from_faces_rgba8(size: u32, faces: [&[u8]; 6], label: &str) -> Self {
+        let face_bytes = (size * size * 4) as usize;
+        for face in &faces {
+            assert!(
+                face.len() >= face_bytes,
+                "Cubemap face too small: expected {} bytes, got {}",
+                face_bytes,
+                face.len()
+            );
+        }
+
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            for (i, face) in faces.iter().enumerate() {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+                    0,
+                    color_texture_format() as GLint,
+                    size as GLsizei,
+                    size as GLsizei,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    face.as_ptr() as *const _,
+                );
+            }
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+
+        track_gpu_allocation(GpuResourceKind::Texture, id, (face_bytes * 6) + (face_bytes * 6) / 3, label);
+
+        Self { id, size, average_color: average_color(&faces, face_bytes) }
+    }
+
+    /// Allocates a cube map with uninitialized `size * size` 8-bit color faces, to be rendered into
+    /// (see [`Renderer::capture_cubemap`](crate::engine::renderer::Renderer::capture_cubemap)).
+    pub fn empty(size: u32, label: &str) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            for i in 0..6 {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + i,
+                    0,
+                    color_texture_format() as GLint,
+                    size as GLsizei,
+                    size as GLsizei,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+            }
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+
+        let face_bytes = size as usize * size as usize * 4;
+        track_gpu_allocation(GpuResourceKind::Texture, id, (face_bytes * 6) + (face_bytes * 6) / 3, label);
+
+        Self { id, size, average_color: Color::BLACK }
+    }
+
+    /// Loads six square PNG images of equal size as the faces, in OpenGL order: +X, -X, +Y,
+    /// -Y, +Z, -Z. The faces are decoded in parallel on the [job pool](crate::engine::jobs).
+    pub fn load_faces<P: AsRef<Path>>(paths: [P; 6]) -> io::Result<Self> {
+        let paths = paths.map(|path| path.as_ref().to_path_buf());
+        let decoded = jobs::parallel_map(&paths, |path| read_png_rgba8(path));
+
+        let mut size = None;
+        let mut faces = Vec::with_capacity(6);
+        for (path, decoded) in paths.iter().zip(decoded) {
+            let (width, height, pixels) = decoded?;
+            if width != height || size.is_some_and(|size| size != width) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Cube map face {} is {}x{}, faces must be square and equally sized", path.display(), width, height),
+                ));
+            }
+            size = Some(width);
+            faces.push(pixels);
+        }
+        let label = paths[0].display().to_string();
+        Ok(Self::from_faces_rgba8(size.unwrap_or(0), std::array::from_fn(|i| faces[i].as_slice()), &label))
+    }
+
+    /// Loads the faces written by [`save_png`](Self::save_png): `<name>_px.png` through
+    /// `<name>_nz.png` in `directory`.
+    pub fn load_png(directory: impl AsRef<Path>, name: &str) -> io::Result<Self> {
+        let directory = directory.as_ref();
+        Self::load_faces(CUBEMAP_FACE_SUFFIXES.map(|suffix| directory.join(format!("{}_{}.png", name, suffix))))
+    }
+
+    /// Resamples an equirectangular (latitude-longitude) panorama of 8-bit sRGB RGBA pixels
+    /// into a cube map with `size * size` faces. See [`equirectangular_to_faces`].
+    pub fn from_equirectangular_rgba8(width: u32, height: u32, pixels: &[u8], size: u32, label: &str) -> Self {
+        let faces = equirectangular_to_faces(width, height, pixels, size);
+        Self::from_faces_rgba8(size, std::array::from_fn(|i| faces[i].as_slice()), label)
+    }
+
+    /// Loads an equirectangular PNG panorama (twice as wide as high) into a cube map with
+    /// `size * size` faces. A quarter of the panorama's width is a good face size.
+    pub fn load_equirectangular(path: impl AsRef<Path>, size: u32) -> io::Result<Self> {
+        let path = path.as_ref();
+        let (width, height, pixels) = read_png_rgba8(path)?;
+        Ok(Self::from_equirectangular_rgba8(width, height, &pixels, size, &path.display().to_string()))
+    }
+
+    /// Regenerates mipmaps and the average color after the faces were rendered into.
+    pub(crate) fn finish_rendering(&mut self) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+
+        let faces: Vec<Vec<u8>> = (0..6).map(|face| self.read_face_rgba8(face)).collect();
+        let faces: [&[u8]; 6] = std::array::from_fn(|i| faces[i].as_slice());
+        self.average_color = average_color(&faces, (self.size * self.size * 4) as usize);
+    }
+
+    /// Reads back one face (0..6, in OpenGL order +X, -X, +Y, -Y, +Z, -Z) as tightly packed
+    /// RGBA8, in the same row order [`from_faces_rgba8`](Self::from_faces_rgba8) accepts.
+    ///
+    /// # Panics
+    /// Panics if `face` is not below 6.
+    pub fn read_face_rgba8(&self, face: usize) -> Vec<u8> {
+        assert!(face < 6, "Cubemap face index out of range: {}", face);
+        let mut pixels = vec![0u8; (self.size * self.size * 4) as usize];
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::GetTexImage(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as u32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        pixels
+    }
+
+    /// Saves the six faces as PNG files named `<name>_px.png`, `<name>_nx.png`, `<name>_py.png`,
+    /// `<name>_ny.png`, `<name>_pz.png` and `<name>_nz.png` in `directory`.
+    pub fn save_png(&self, directory: impl AsRef<Path>, name: &str) -> io::Result<()> {
+        for (face, suffix) in CUBEMAP_FACE_SUFFIXES.iter().enumerate() {
+            let path = directory.as_ref().join(format!("{}_{}.png", name, suffix));
+            let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.size, self.size);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+            let mut writer = encoder.write_header().map_err(io::Error::other)?;
+            writer.write_image_data(&self.read_face_rgba8(face)).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    /// Binds the cube map to the given texture unit (`GL_TEXTURE0 + unit`).
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+
+    /// The OpenGL texture name.
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Width and height of each face in pixels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Number of mip levels: loaded cube maps get a full chain, as do rendered ones once
+    /// finished.
+    ///
+    /// ```no_run
+    /// # use rustge::engine::texture::Cubemap;
+    /// let sky = Cubemap::load_png("assets/sky", "sky").unwrap();
+    /// assert_eq!(sky.levels(), 1 + sky.size().ilog2());
+    /// ```
+    pub fn levels(&self) -> u32 {
+        1 + self.size.max(1).ilog2()
+    }
+
+    /// Average color over all faces, computed in linear space at upload time.
+    pub fn average_color(&self) -> Color {
+        self.average_color
+    }
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::Texture, self.id);
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// Resamples an equirectangular panorama of tightly packed 8-bit RGBA pixels (rows top to
+/// bottom) into six `size * size` cube map faces in OpenGL order, ready for
+/// [`Cubemap::from_faces_rgba8`]. Bilinear filtering; the panorama wraps horizontally.
+///
+/// The panorama's center looks along -Z (the default camera forward), its top row straight up.
+///
+/// ```
+/// # use rustge::engine::texture::equirectangular_to_faces;
+/// // Top half white (sky), bottom half black (ground)
+/// let (width, height) = (16, 8);
+/// let pixels: Vec<u8> = (0..width * height).flat_map(|i| if i < width * height / 2 { [255; 4] } else { [0, 0, 0, 255] }).collect();
+///
+/// let faces = equirectangular_to_faces(width, height, &pixels, 4);
+/// assert!(faces[2].iter().all(|&c| c == 255)); // +Y looks up at the sky
+/// assert_eq!(&faces[3][..3], &[0, 0, 0]); // -Y looks down at the ground
+/// ```
+pub fn equirectangular_to_faces(width: u32, height: u32, pixels: &[u8], size: u32) -> [Vec<u8>; 6] {
+    let (w, h) = (width.max(1) as usize, height.max(1) as usize);
+    let texel = |x: usize, y: usize, channel: usize| {
+        pixels.get(((y.min(h - 1) * w) + x % w) * 4 + channel).copied().unwrap_or(0) as f32
+    };
+
+    std::array::from_fn(|face| {
+        let mut out = vec![0u8; (size * size * 4) as usize];
+        for (i, pixel) in out.chunks_exact_mut(4).enumerate() {
+            let (column, row) = (i % size as usize, i / size as usize);
+            // Face coordinates in -1..1 through the texel center
+            let sc = (column as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let tc = (row as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let [x, y, z] = normalize(cube_face_direction(face, sc, tc));
+
+            let u = 0.5 + x.atan2(-z) / std::f32::consts::TAU;
+            let v = y.clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+            let fx = (u * w as f32 - 0.5).rem_euclid(w as f32);
+            let fy = (v * h as f32 - 0.5).max(0.0);
+            let (x0, y0) = (fx as usize, fy as usize);
+            let (tx, ty) = (fx.fract(), fy.fract());
+            for (channel, value) in pixel.iter_mut().enumerate() {
+                let top = texel(x0, y0, channel) * (1.0 - tx) + texel(x0 + 1, y0, channel) * tx;
+                let bottom = texel(x0, y0 + 1, channel) * (1.0 - tx) + texel(x0 + 1, y0 + 1, channel) * tx;
+                *value = (top * (1.0 - ty) + bottom * ty).round() as u8;
+            }
+        }
+        out
+    })
+}
+
+/// The direction a cube map samples at face coordinates `sc`, `tc` (in -1..1, `tc` growing
+/// downwards through the face's rows) of `face`, per the OpenGL cube map selection rules.
+fn cube_face_direction(face: usize, sc: f32, tc: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -tc, -sc],
+        1 => [-1.0, -tc, sc],
+        2 => [sc, 1.0, tc],
+        3 => [sc, -1.0, -tc],
+        4 => [sc, -tc, 1.0],
+        _ => [-sc, -tc, -1.0],
+    }
+}
+
+/// Creates a texture holding tightly packed RGBA8 `pixels` with a full mip chain, in the
+/// context current on this thread, and returns its name. The texture is sRGB-encoded in the
+/// [sRGB pipeline](crate::engine::color_space).
+pub(crate) fn upload_rgba8(width: u32, height: u32, pixels: &[u8]) -> GLuint {
+    upload_pixels(width, height, pixels, color_texture_format())
+}
+
+/// Creates a texture of internal `format` from tightly packed RGBA8 `pixels` with a full mip
+/// chain, and returns its name.
+fn upload_pixels(width: u32, height: u32, pixels: &[u8], format: GLenum) -> GLuint {
+    let mut id = 0;
+    unsafe {
+        gl::GenTextures(1, &mut id);
+        gl::BindTexture(gl::TEXTURE_2D, id);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            format as GLint,
+            width as GLsizei,
+            height as GLsizei,
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_ptr() as *const _,
+        );
+        gl::GenerateMipmap(gl::TEXTURE_2D);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as GLint);
+        gl::BindTexture(gl::TEXTURE_2D, 0);
+    }
+    id
+}
+
+/// Decodes a PNG file into tightly packed 8-bit RGBA with rows bottom to top, as 2D
+/// textures take them.
+fn read_png_bottom_up(path: &Path) -> io::Result<(u32, u32, Vec<u8>)> {
+    let (width, height, mut pixels) = read_png_rgba8(path)?;
+    flip_rows(&mut pixels, width as usize * 4);
+    Ok((width, height, pixels))
+}
+
+/// Decodes a PNG file into tightly packed 8-bit RGBA with rows top to bottom.
+fn read_png_rgba8(path: &Path) -> io::Result<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(io::Error::other)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+    buffer.truncate(info.buffer_size());
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => buffer.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        png::ColorType::GrayscaleAlpha => buffer.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpanded indexed PNG"));
+        }
+    };
+    Ok((info.width, info.height, pixels))
+}
+
+/// File name suffixes of the cube map faces, in OpenGL order.
+pub const CUBEMAP_FACE_SUFFIXES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Averages sRGB RGBA8 faces in linear space.
+fn average_color(faces: &[&[u8]; 6], face_bytes: usize) -> Color {
+    let mut sum = [0.0f64; 3];
+    let mut count = 0usize;
+    for face in faces {
+        for pixel in face[..face_bytes].chunks_exact(4) {
+            for (channel, &value) in sum.iter_mut().zip(pixel) {
+                *channel += srgb_to_linear(value as f32 / 255.0) as f64;
+            }
+            count += 1;
+        }
+    }
+    let count = count.max(1) as f64;
+    Color::linear_rgb((sum[0] / count) as f32, (sum[1] / count) as f32, (sum[2] / count) as f32)
+}
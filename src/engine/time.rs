@@ -0,0 +1,128 @@
+//! Frame timing.
+//!
+//! The [`Clock`] is ticked once per frame by the renderer and handed to the user's update
+//! callback, so animation and physics can scale by the real time between frames instead of
+//! assuming a fixed frame rate.
+
+use std::time::{Duration, Instant};
+
+/// Tracks per-frame delta time, total elapsed time, frame count and a smoothed FPS value.
+///
+/// # Example
+/// ```
+/// # use rustge::engine::time::Clock;
+/// let mut clock = Clock::new();
+/// clock.tick();
+/// let speed = 2.0; // units per second
+/// let distance_this_frame = speed * clock.delta();
+/// # let _ = distance_this_frame;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Clock {
+    /// When the clock was created (or last reset).
+    start: Instant,
+
+    /// When the previous frame was ticked.
+    last_tick: Instant,
+
+    /// Seconds between the two most recent ticks.
+    delta: f32,
+
+    /// Seconds since the clock started, as of the last tick.
+    elapsed: f64,
+
+    /// Number of ticks since the clock started.
+    frame_count: u64,
+
+    /// Exponentially smoothed frames per second.
+    fps: f32,
+
+    /// Weight given to the newest frame when smoothing FPS, in `(0, 1]`.
+    fps_smoothing: f32,
+}
+
+impl Clock {
+    /// Creates a clock starting now, with zero delta and no frames counted.
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last_tick: now,
+            delta: 0.0,
+            elapsed: 0.0,
+            frame_count: 0,
+            fps: 0.0,
+            fps_smoothing: 0.1,
+        }
+    }
+
+    /// Restarts the clock from now, clearing all accumulated timing.
+    pub fn reset(&mut self) {
+        *self = Self { fps_smoothing: self.fps_smoothing, ..Self::new() };
+    }
+
+    /// Advances the clock by one frame and returns the new delta time in seconds.
+    ///
+    /// Called once per frame by the renderer before the update callback runs.
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        self.delta = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        self.elapsed = now.duration_since(self.start).as_secs_f64();
+        self.frame_count += 1;
+
+        if self.delta > 0.0 {
+            let instant_fps = 1.0 / self.delta;
+            self.fps = if self.frame_count <= 1 || self.fps == 0.0 {
+                instant_fps
+            } else {
+                self.fps + (instant_fps - self.fps) * self.fps_smoothing
+            };
+        }
+
+        self.delta
+    }
+
+    /// Seconds between the two most recent frames.
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Delta time as a `Duration`.
+    pub fn delta_duration(&self) -> Duration {
+        Duration::from_secs_f32(self.delta)
+    }
+
+    /// Seconds elapsed since the clock started, as of the last frame.
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Number of frames ticked since the clock started.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Smoothed frames per second.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// A copy of the clock reporting `delta` as the frame delta, for callbacks that run less
+    /// often than every frame.
+    pub(crate) fn with_delta(&self, delta: f32) -> Self {
+        Self { delta, ..self.clone() }
+    }
+
+    /// Sets how quickly the FPS value reacts to changes: `1.0` reports the last frame only,
+    /// small values average over many frames.
+    pub fn set_fps_smoothing(&mut self, smoothing: f32) {
+        self.fps_smoothing = smoothing.clamp(f32::EPSILON, 1.0);
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,692 @@
+//! Timelines: authored sequences of timed tracks, played by a [`Sequencer`] for cutscenes.
+//!
+//! A [`Timeline`] is data, loaded from a JSON file. Its [`Track`]s run side by side:
+//!
+//! - **camera** cuts, each placing the camera at a fixed spot or following a node (e.g. a
+//!   camera rig animated in a modeling tool) until the next cut;
+//! - **animation** clips from a node's [`AnimationLibrary`], started at a time;
+//! - **transform** keyframes moving, turning or scaling a node;
+//! - **audio** cues and **events**: markers handed to the game when playback passes them.
+//!   The engine has no audio of its own, so cues name the sound and the game plays it.
+//!
+//! Nodes are referred to by [name](crate::engine::object3d::Object3D::find_by_name) and
+//! looked up in the scene when the sequencer is [bound](Sequencer::bind).
+//!
+//! A [`Sequencer`] plays a timeline: it can play, pause, [seek](Sequencer::seek) (scrub)
+//! and run at any speed, also backwards. Posing is a function of the time alone, so seeking
+//! shows the same frame as playing up to it. Markers fire only while playing, when the time
+//! passes them. Added as a [`Plugin`], it advances by the clock's delta every frame after the
+//! update callbacks, moves the renderer's camera and passes markers to
+//! [`on_event`](Sequencer::on_event).
+//!
+//! ```json
+//! {
+//!   "name": "intro",
+//!   "tracks": [
+//!     { "kind": "camera", "cuts": [
+//!       { "time": 0.0, "position": [0.0, 2.0, 8.0], "fov": 40.0 },
+//!       { "time": 4.0, "node": "crane_cam" }
+//!     ] },
+//!     { "kind": "animation", "node": "hero", "clip": "wave", "start": 1.0 },
+//!     { "kind": "transform", "node": "gate", "keys": [
+//!       { "time": 2.0, "position": [0.0, 0.0, 0.0] },
+//!       { "time": 5.0, "position": [0.0, 4.0, 0.0] }
+//!     ] },
+//!     { "kind": "audio", "cues": [{ "time": 2.0, "sound": "sfx/gate.ogg" }] },
+//!     { "kind": "events", "markers": [{ "time": 6.0, "name": "spawn", "data": { "count": 3 } }] }
+//!   ]
+//! }
+//! ```
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::{object3d::Object3D, scene::Scene};
+//! # use rustge::engine::timeline::{Sequencer, Timeline, TimelineEvent};
+//! let timeline = Timeline::from_json(r#"{
+//!     "name": "gate",
+//!     "tracks": [
+//!         { "kind": "transform", "node": "gate", "keys": [
+//!             { "time": 0.0, "position": [0.0, 0.0, 0.0] },
+//!             { "time": 2.0, "position": [0.0, 4.0, 0.0] }
+//!         ] },
+//!         { "kind": "events", "markers": [{ "time": 1.5, "name": "rumble" }] }
+//!     ]
+//! }"#).unwrap();
+//!
+//! let scene = Scene::new();
+//! let gate = Object3D::new();
+//! gate.borrow_mut().set_name("gate");
+//! scene.add(gate.clone());
+//!
+//! let mut sequencer = Sequencer::new(timeline);
+//! sequencer.bind(&scene);
+//!
+//! // Scrubbing poses without firing markers
+//! sequencer.seek(1.0);
+//! sequencer.pose(None);
+//! assert_eq!(gate.borrow().position(), [0.0, 2.0, 0.0]);
+//!
+//! sequencer.play();
+//! let events = sequencer.advance(1.0);
+//! assert!(matches!(&events[..], [TimelineEvent::Marker { name, .. }, TimelineEvent::Finished] if name == "rumble"));
+//! ```
+//!
+//! Playing a cutscene file:
+//! ```no_run
+//! # use rustge::engine::{renderer::Renderer, timeline::{Sequencer, Timeline, TimelineEvent}};
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! let mut intro = Sequencer::new(Timeline::load("cutscenes/intro.json").unwrap());
+//! intro.on_event(|_renderer, event| match event {
+//!     TimelineEvent::Audio { sound, volume, .. } => println!("play {sound} at {volume}"),
+//!     TimelineEvent::Finished => println!("back to the game"),
+//!     _ => {}
+//! });
+//! intro.play();
+//! renderer.add_plugin(intro);
+//! ```
+//!
+//! [`AnimationLibrary`]: crate::engine::animation::clip::AnimationLibrary
+
+use std::cell::RefCell;
+use std::io;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::engine::animation::clip::{AnimatedProperty, AnimationClip, AnimationLibrary, Channel, Interpolation};
+use crate::engine::camera::Camera;
+use crate::engine::math::matrixfuncs::decompose_matrix;
+use crate::engine::object3d::Object3D;
+use crate::engine::plugin::{FrameStage, Plugin};
+use crate::engine::renderer::Renderer;
+use crate::engine::scene::Scene;
+use crate::engine::time::Clock;
+
+/// A cutscene or other authored sequence; see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+    /// Name of the timeline, also naming its sequencer's plugin.
+    pub name: String,
+
+    /// Length in seconds, or `None` to end at the last key, cut, cue or marker. Set it when
+    /// an animation clip runs past them.
+    pub duration: Option<f32>,
+
+    /// The tracks, played side by side.
+    pub tracks: Vec<Track>,
+}
+
+/// One track of a [`Timeline`], tagged by `"kind"` in files.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Track {
+    /// Camera placements; each holds until the next cut. Before the first cut the first
+    /// one applies.
+    Camera { cuts: Vec<CameraCut> },
+
+    /// A clip from the [`AnimationLibrary`] component of `node`, such as an imported model's
+    /// root. Holds the first frame before `start` and the last after the clip ends, unless
+    /// looping.
+    Animation {
+        node: String,
+        clip: String,
+        /// Timeline time at which the clip starts.
+        #[serde(default)]
+        start: f32,
+        /// Playback rate of the clip.
+        #[serde(default = "unit_speed")]
+        speed: f32,
+        #[serde(default)]
+        looping: bool,
+    },
+
+    /// Keyframes of the transform of `node`. Keys may set any of position, rotation and
+    /// scale; each is interpolated between the keys setting it. Cubic spline keys ease in
+    /// and out of every key.
+    Transform {
+        node: String,
+        #[serde(default)]
+        interpolation: Interpolation,
+        keys: Vec<TransformKey>,
+    },
+
+    /// Sounds for the game to play, as [`TimelineEvent::Audio`].
+    Audio { cues: Vec<AudioCue> },
+
+    /// Game-defined markers, as [`TimelineEvent::Marker`].
+    Events { markers: Vec<Marker> },
+}
+
+fn unit_speed() -> f32 {
+    1.0
+}
+
+/// A camera placement in a [`Track::Camera`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraCut {
+    /// Seconds into the timeline.
+    pub time: f32,
+
+    /// Node whose world position and rotation the camera takes every frame, instead of
+    /// `position` and `rotation`.
+    pub node: Option<String>,
+
+    /// Camera position in world space.
+    pub position: [f32; 3],
+
+    /// Camera rotation as a quaternion `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+
+    /// Vertical field of view in degrees, or `None` to keep the camera's.
+    pub fov: Option<f32>,
+}
+
+impl Default for CameraCut {
+    fn default() -> Self {
+        Self { time: 0.0, node: None, position: [0.0; 3], rotation: [0.0, 0.0, 0.0, 1.0], fov: None }
+    }
+}
+
+/// A keyframe of a [`Track::Transform`]; unset parts are left to the other keys.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformKey {
+    /// Seconds into the timeline.
+    pub time: f32,
+    pub position: Option<[f32; 3]>,
+    /// Quaternion `[x, y, z, w]`.
+    pub rotation: Option<[f32; 4]>,
+    pub scale: Option<[f32; 3]>,
+}
+
+/// A sound cue of a [`Track::Audio`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioCue {
+    /// Seconds into the timeline.
+    pub time: f32,
+
+    /// The sound, e.g. an asset path, as the game's audio understands it.
+    pub sound: String,
+
+    pub volume: f32,
+}
+
+impl Default for AudioCue {
+    fn default() -> Self {
+        Self { time: 0.0, sound: String::new(), volume: 1.0 }
+    }
+}
+
+/// A marker of a [`Track::Events`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Marker {
+    /// Seconds into the timeline.
+    pub time: f32,
+    pub name: String,
+
+    /// Anything the game needs, `null` if not given.
+    pub data: Value,
+}
+
+impl Timeline {
+    /// Length in seconds: [`duration`](Self::duration) if set, else the time of the last key,
+    /// cut, cue or marker, or the start of the last animation.
+    pub fn length(&self) -> f32 {
+        if let Some(duration) = self.duration {
+            return duration.max(0.0);
+        }
+        let track_end = |track: &Track| match track {
+            Track::Camera { cuts } => cuts.iter().map(|cut| cut.time).fold(0.0, f32::max),
+            Track::Animation { start, .. } => *start,
+            Track::Transform { keys, .. } => keys.iter().map(|key| key.time).fold(0.0, f32::max),
+            Track::Audio { cues } => cues.iter().map(|cue| cue.time).fold(0.0, f32::max),
+            Track::Events { markers } => markers.iter().map(|marker| marker.time).fold(0.0, f32::max),
+        };
+        self.tracks.iter().map(track_end).fold(0.0, f32::max)
+    }
+
+    /// The timeline as pretty-printed JSON.
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    /// Parses a timeline from JSON, sorting every track's keys, cuts, cues and markers by
+    /// time.
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let mut timeline: Self = serde_json::from_str(json).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        timeline.sort();
+        Ok(timeline)
+    }
+
+    /// Reads a timeline file.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Sorts every track's keys, cuts, cues and markers by time.
+    fn sort(&mut self) {
+        for track in &mut self.tracks {
+            match track {
+                Track::Camera { cuts } => cuts.sort_by(|a, b| a.time.total_cmp(&b.time)),
+                Track::Animation { .. } => {}
+                Track::Transform { keys, .. } => keys.sort_by(|a, b| a.time.total_cmp(&b.time)),
+                Track::Audio { cues } => cues.sort_by(|a, b| a.time.total_cmp(&b.time)),
+                Track::Events { markers } => markers.sort_by(|a, b| a.time.total_cmp(&b.time)),
+            }
+        }
+    }
+}
+
+/// Something a playing [`Sequencer`] passed, returned by [`advance`](Sequencer::advance) and
+/// handed to [`on_event`](Sequencer::on_event).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TimelineEvent {
+    /// An [`AudioCue`] to play.
+    Audio { time: f32, sound: String, volume: f32 },
+
+    /// A [`Marker`].
+    Marker { time: f32, name: String, data: Value },
+
+    /// Playback reached the end (or the start, playing backwards) without looping, and
+    /// paused.
+    Finished,
+}
+
+impl TimelineEvent {
+    /// The timeline time of the cue or marker; `None` for [`Finished`](Self::Finished).
+    pub fn time(&self) -> Option<f32> {
+        match self {
+            TimelineEvent::Audio { time, .. } | TimelineEvent::Marker { time, .. } => Some(*time),
+            TimelineEvent::Finished => None,
+        }
+    }
+}
+
+/// A track's nodes and clips, resolved by [`Sequencer::bind`].
+enum BoundTrack {
+    /// The cuts' followed nodes, `None` for fixed cuts.
+    Camera(Vec<Option<Weak<RefCell<Object3D>>>>),
+    Animation(Option<Rc<AnimationClip>>),
+    Transform(Vec<Channel>),
+    /// Audio and event tracks need nothing from the scene.
+    Unbound,
+}
+
+type EventFn = Box<dyn FnMut(&mut Renderer, &TimelineEvent)>;
+
+/// Plays a [`Timeline`]; see the [module documentation](self).
+pub struct Sequencer {
+    timeline: Timeline,
+    length: f32,
+    time: f32,
+    playing: bool,
+    /// Whether the pose at `time` was applied.
+    posed: bool,
+    bound: Option<Vec<BoundTrack>>,
+    on_event: Option<EventFn>,
+    plugin_name: String,
+
+    /// Playback rate; 1 is normal speed, negative plays backwards.
+    pub speed: f32,
+
+    /// Whether playback wraps around at the ends, or stops there.
+    pub looping: bool,
+}
+
+impl Sequencer {
+    /// A sequencer paused at the start of `timeline`, at normal speed without looping.
+    pub fn new(mut timeline: Timeline) -> Self {
+        timeline.sort();
+        let plugin_name = format!("sequencer '{}'", timeline.name);
+        Self {
+            length: timeline.length(),
+            timeline,
+            time: 0.0,
+            playing: false,
+            posed: false,
+            bound: None,
+            on_event: None,
+            plugin_name,
+            speed: 1.0,
+            looping: false,
+        }
+    }
+
+    /// The timeline played, with its keys sorted by time.
+    pub fn timeline(&self) -> &Timeline {
+        &self.timeline
+    }
+
+    /// Length of the timeline in seconds.
+    pub fn duration(&self) -> f32 {
+        self.length
+    }
+
+    /// Seconds into the timeline.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Whether the sequencer advances.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Starts or resumes playback. At the end it plays in (the start, playing backwards) it
+    /// starts over.
+    pub fn play(&mut self) {
+        if self.speed >= 0.0 && self.time >= self.length {
+            self.seek(0.0);
+        } else if self.speed < 0.0 && self.time <= 0.0 {
+            self.seek(self.length);
+        }
+        self.playing = true;
+    }
+
+    /// Pauses playback; the pose stays applied.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Pauses and rewinds to the start, posing the nodes as they are there.
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.seek(0.0);
+    }
+
+    /// Jumps to `time` seconds, clamped to the timeline, without firing the cues and markers
+    /// in between. The new pose is applied on the next update, or with [`pose`](Self::pose).
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.clamp(0.0, self.length);
+        self.posed = false;
+    }
+
+    /// Calls `handler` with the cues and markers passed and when playback finishes, while the
+    /// sequencer runs as a plugin. Replaces the previous handler.
+    pub fn on_event<F>(&mut self, handler: F)
+    where
+        F: FnMut(&mut Renderer, &TimelineEvent) + 'static,
+    {
+        self.on_event = Some(Box::new(handler));
+    }
+
+    /// Resolves the tracks' nodes and clips in `scene`, warning about missing ones. Done
+    /// when the sequencer first updates as a plugin; call it again after replacing the scene.
+    ///
+    /// # Panics
+    /// Panics if a node is mutably borrowed.
+    pub fn bind(&mut self, scene: &Scene) {
+        let name = &self.timeline.name;
+        let find = |node: &str| {
+            let found = scene.find_by_name(node);
+            if found.is_none() {
+                eprintln!("Warning: Timeline '{name}' refers to node '{node}', which is not in the scene");
+            }
+            found
+        };
+
+        let bound = self
+            .timeline
+            .tracks
+            .iter()
+            .map(|track| match track {
+                Track::Camera { cuts } => BoundTrack::Camera(
+                    cuts.iter().map(|cut| cut.node.as_deref().and_then(find).map(|node| Rc::downgrade(&node))).collect(),
+                ),
+                Track::Animation { node, clip, .. } => {
+                    let library_clip = find(node).and_then(|node| node.borrow().component::<AnimationLibrary>().and_then(|library| library.get(clip)));
+                    if library_clip.is_none() {
+                        eprintln!("Warning: Timeline '{name}' plays clip '{clip}', which node '{node}' has no animation of");
+                    }
+                    BoundTrack::Animation(library_clip)
+                }
+                Track::Transform { node, interpolation, keys } => {
+                    let channels = match find(node) {
+                        Some(node) => transform_channels(&node, *interpolation, keys),
+                        None => Vec::new(),
+                    };
+                    BoundTrack::Transform(channels)
+                }
+                Track::Audio { .. } | Track::Events { .. } => BoundTrack::Unbound,
+            })
+            .collect();
+        self.bound = Some(bound);
+        self.posed = false;
+    }
+
+    /// Moves the time by `delta` seconds at the sequencer's speed if playing, wrapping around
+    /// or stopping at the ends. Returns the cues and markers passed, in the order passed,
+    /// followed by [`Finished`](TimelineEvent::Finished) if playback stopped at an end.
+    ///
+    /// Markers fire when the time reaches them, so one at the start fires as playback
+    /// begins; a looping timeline fires markers at its end before wrapping around, and the
+    /// markers of laps skipped by a long `delta` once.
+    ///
+    /// ```
+    /// # use rustge::engine::timeline::{Sequencer, Timeline, TimelineEvent};
+    /// let timeline = Timeline::from_json(r#"{ "name": "lap", "tracks": [
+    ///     { "kind": "events", "markers": [{ "time": 2.0, "name": "end" }, { "time": 0.0, "name": "start" }] }
+    /// ] }"#).unwrap();
+    /// let mut sequencer = Sequencer::new(timeline);
+    /// sequencer.looping = true;
+    /// sequencer.play();
+    ///
+    /// let names = |events: Vec<TimelineEvent>| -> Vec<String> {
+    ///     events.into_iter().filter_map(|event| match event {
+    ///         TimelineEvent::Marker { name, .. } => Some(name),
+    ///         _ => None,
+    ///     }).collect()
+    /// };
+    /// assert_eq!(names(sequencer.advance(1.5)), ["start"]);
+    /// assert_eq!(names(sequencer.advance(1.0)), ["end", "start"]);
+    /// ```
+    pub fn advance(&mut self, delta: f32) -> Vec<TimelineEvent> {
+        let mut events = Vec::new();
+        if !self.playing {
+            return events;
+        }
+        self.posed = false;
+
+        let length = self.length;
+        let looping = self.looping && length > 0.0;
+        let mut step = delta * self.speed;
+        if looping {
+            step %= length;
+        }
+        loop {
+            let target = self.time + step;
+            if step >= 0.0 {
+                if target < length {
+                    self.passed(self.time, target, false, &mut events);
+                    self.time = target;
+                } else if looping {
+                    // The end and the start are both passed, so markers at either fire
+                    self.passed(self.time, length, true, &mut events);
+                    self.time = 0.0;
+                    step = target - length;
+                    continue;
+                } else {
+                    self.passed(self.time, length, true, &mut events);
+                    self.time = length;
+                    self.playing = false;
+                    events.push(TimelineEvent::Finished);
+                }
+            } else if target > 0.0 {
+                self.passed(self.time, target, false, &mut events);
+                self.time = target;
+            } else if looping {
+                self.passed(self.time, 0.0, true, &mut events);
+                self.time = length;
+                step = target;
+                continue;
+            } else {
+                self.passed(self.time, 0.0, true, &mut events);
+                self.time = 0.0;
+                self.playing = false;
+                events.push(TimelineEvent::Finished);
+            }
+            return events;
+        }
+    }
+
+    /// Appends the cues and markers from `from` towards `to`, in that order: including
+    /// `from`, and `to` only if `to_inclusive`.
+    fn passed(&self, from: f32, to: f32, to_inclusive: bool, events: &mut Vec<TimelineEvent>) {
+        let forward = from <= to;
+        let within = |time: f32| {
+            let (low, high) = if forward { (from, to) } else { (to, from) };
+            let past_low = if forward || to_inclusive { time >= low } else { time > low };
+            let before_high = if !forward || to_inclusive { time <= high } else { time < high };
+            past_low && before_high
+        };
+
+        let start = events.len();
+        for track in &self.timeline.tracks {
+            match track {
+                Track::Audio { cues } => events.extend(cues.iter().filter(|cue| within(cue.time)).map(|cue| {
+                    TimelineEvent::Audio { time: cue.time, sound: cue.sound.clone(), volume: cue.volume }
+                })),
+                Track::Events { markers } => events.extend(markers.iter().filter(|marker| within(marker.time)).map(|marker| {
+                    TimelineEvent::Marker { time: marker.time, name: marker.name.clone(), data: marker.data.clone() }
+                })),
+                _ => {}
+            }
+        }
+        // Stable, so simultaneous events keep the order of their tracks
+        let passed = &mut events[start..];
+        passed.sort_by(|a, b| a.time().unwrap_or(0.0).total_cmp(&b.time().unwrap_or(0.0)));
+        if !forward {
+            passed.reverse();
+        }
+    }
+
+    /// Poses the bound nodes as they are at the current time, and places `camera` if the
+    /// timeline has a camera track. Does nothing before [`bind`](Self::bind).
+    ///
+    /// # Panics
+    /// Panics if an animated node is already borrowed.
+    pub fn pose(&mut self, camera: Option<&mut Camera>) {
+        let Some(bound) = &self.bound else {
+            return;
+        };
+        let time = self.time;
+        let mut camera = camera;
+        for (track, bound) in self.timeline.tracks.iter().zip(bound) {
+            match (track, bound) {
+                (Track::Animation { start, speed, looping, .. }, BoundTrack::Animation(Some(clip))) => {
+                    let local = (time - start) * speed;
+                    let duration = clip.duration();
+                    let local = if *looping && duration > 0.0 { local.rem_euclid(duration) } else { local.clamp(0.0, duration) };
+                    clip.apply(local);
+                }
+                (Track::Transform { .. }, BoundTrack::Transform(channels)) => {
+                    for channel in channels {
+                        channel.apply(time);
+                    }
+                }
+                (Track::Camera { cuts }, BoundTrack::Camera(nodes)) => {
+                    if let Some(camera) = camera.as_deref_mut() {
+                        place_camera(camera, cuts, nodes, time);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.posed = true;
+    }
+
+    /// Whether the timeline has a camera track.
+    fn has_camera_track(&self) -> bool {
+        self.timeline.tracks.iter().any(|track| matches!(track, Track::Camera { cuts } if !cuts.is_empty()))
+    }
+}
+
+/// Channels animating `node` with `keys`, one per transform part the keys set.
+fn transform_channels(node: &Rc<RefCell<Object3D>>, interpolation: Interpolation, keys: &[TransformKey]) -> Vec<Channel> {
+    type Part = fn(&TransformKey) -> Option<[f32; 4]>;
+    let parts: [(AnimatedProperty, Part); 3] = [
+        (AnimatedProperty::Translation, |key| key.position.map(|[x, y, z]| [x, y, z, 0.0])),
+        (AnimatedProperty::Rotation, |key| key.rotation),
+        (AnimatedProperty::Scale, |key| key.scale.map(|[x, y, z]| [x, y, z, 0.0])),
+    ];
+    parts
+        .into_iter()
+        .filter_map(|(property, part)| {
+            let (times, values): (Vec<f32>, Vec<[f32; 4]>) = keys.iter().filter_map(|key| Some((key.time, part(key)?))).unzip();
+            if times.is_empty() {
+                return None;
+            }
+            let values = match interpolation {
+                // Flat tangents, so the spline eases in and out of each key
+                Interpolation::CubicSpline => values.into_iter().flat_map(|value| [[0.0; 4], value, [0.0; 4]]).collect(),
+                _ => values,
+            };
+            Some(Channel { target: Rc::downgrade(node), property, interpolation, times, values })
+        })
+        .collect()
+}
+
+/// Places `camera` by the last of `cuts` at or before `time`, or the first one before them.
+fn place_camera(camera: &mut Camera, cuts: &[CameraCut], nodes: &[Option<Weak<RefCell<Object3D>>>], time: f32) {
+    let index = cuts.partition_point(|cut| cut.time <= time).saturating_sub(1);
+    let Some(cut) = cuts.get(index) else {
+        return;
+    };
+    let followed = nodes.get(index).and_then(|node| node.as_ref()?.upgrade());
+    match followed {
+        Some(node) => {
+            let (position, rotation, _) = decompose_matrix(&node.borrow_mut().world_matrix());
+            camera.set_position(position);
+            camera.set_rotation(rotation);
+        }
+        // A cut following a missing node keeps the camera where it was
+        None if cut.node.is_some() => {}
+        None => {
+            camera.set_position(cut.position);
+            camera.set_rotation(cut.rotation);
+        }
+    }
+    if let Some(fov) = cut.fov {
+        camera.set_fov(fov);
+    }
+}
+
+impl Plugin for Sequencer {
+    fn name(&self) -> &str {
+        &self.plugin_name
+    }
+
+    /// Binds to the renderer's scene on the first update, then advances by the clock's delta
+    /// and applies the pose at [`FrameStage::Update`], before systems, physics and the scene
+    /// update. A camera track sets a camera if the renderer has none.
+    fn update(&mut self, renderer: &mut Renderer, stage: FrameStage, clock: &Clock) {
+        if stage != FrameStage::Update {
+            return;
+        }
+        if self.bound.is_none() {
+            let Some(scene) = renderer.get_scene() else {
+                return;
+            };
+            self.bind(scene);
+        }
+
+        let events = self.advance(clock.delta());
+        if !self.posed {
+            if renderer.get_camera().is_none() && self.has_camera_track() {
+                renderer.set_camera(Camera::new(1.0));
+            }
+            self.pose(renderer.get_camera_mut());
+        }
+
+        if let Some(mut handler) = self.on_event.take() {
+            for event in &events {
+                handler(renderer, event);
+            }
+            self.on_event = Some(handler);
+        }
+    }
+}
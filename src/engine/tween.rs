@@ -0,0 +1,431 @@
+//! Tweens: values eased from a start to an end over time.
+//!
+//! A [`Tween`] interpolates anything [`Tweenable`] (floats, vectors, [`Color`]s,
+//! [`Rotation`]s) with an [`Easing`] curve, writing each new value through a callback.
+//! Shorthands animate a node's [position](Tween::position), [rotation](Tween::rotation),
+//! [scale](Tween::scale) or [material color](Tween::color), starting from wherever the node
+//! is when the tween begins. Tweens can wait before starting, call back when they complete,
+//! and be chained [one after another](Tween::then).
+//!
+//! A [`TweenManager`] plays tweens; the renderer owns one (see
+//! [`Renderer::tweens_mut`](crate::engine::renderer::Renderer::tweens_mut)) and advances it
+//! every frame by the clock's delta, after the update callback.
+//!
+//! # Example
+//! ```
+//! # use std::{cell::Cell, rc::Rc};
+//! # use rustge::engine::tween::{Easing, Tween, TweenManager};
+//! let opacity = Rc::new(Cell::new(0.0));
+//! let done = Rc::new(Cell::new(false));
+//!
+//! let (target, finished) = (opacity.clone(), done.clone());
+//! let fade = Tween::new(0.0, 1.0, 0.5)
+//!     .ease(Easing::EaseOutCubic)
+//!     .on_update(move |value| target.set(value))
+//!     .then(Tween::new(1.0, 0.0, 0.5).on_complete(move || finished.set(true)));
+//!
+//! let mut tweens = TweenManager::new();
+//! let id = tweens.add(fade);
+//! tweens.update(0.5);
+//! assert_eq!(opacity.get(), 1.0);
+//! tweens.update(0.5);
+//! assert!(done.get() && !tweens.is_active(id));
+//! ```
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use crate::engine::math::color::Color;
+use crate::engine::math::quat;
+use crate::engine::object3d::Object3D;
+
+/// The rate of change of a tween over its duration. `In` curves start slow, `Out` curves
+/// end slow, `InOut` curves do both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    EaseInQuad,
+    EaseOutQuad,
+    EaseInOutQuad,
+    EaseInCubic,
+    EaseOutCubic,
+    EaseInOutCubic,
+    EaseInSine,
+    EaseOutSine,
+    EaseInOutSine,
+    /// Overshoots the end slightly before settling.
+    EaseOutBack,
+    /// Springs past the end a few times, losing energy.
+    EaseOutElastic,
+    /// Bounces off the end like a dropped ball.
+    EaseOutBounce,
+}
+
+impl Easing {
+    /// Maps linear progress `t` in `[0, 1]` to eased progress, 0 at 0 and 1 at 1 (with
+    /// overshoot in between for the back and elastic curves).
+    pub fn apply(self, t: f32) -> f32 {
+        use std::f32::consts::{PI, TAU};
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInQuad => t * t,
+            Easing::EaseOutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOutQuad => {
+                if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+            }
+            Easing::EaseInCubic => t * t * t,
+            Easing::EaseOutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOutCubic => {
+                if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+            }
+            Easing::EaseInSine => 1.0 - (t * PI / 2.0).cos(),
+            Easing::EaseOutSine => (t * PI / 2.0).sin(),
+            Easing::EaseInOutSine => -((t * PI).cos() - 1.0) / 2.0,
+            Easing::EaseOutBack => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+            Easing::EaseOutElastic => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else {
+                    2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * TAU / 3.0).sin() + 1.0
+                }
+            }
+            Easing::EaseOutBounce => {
+                const N: f32 = 7.5625;
+                const D: f32 = 2.75;
+                if t < 1.0 / D {
+                    N * t * t
+                } else if t < 2.0 / D {
+                    let t = t - 1.5 / D;
+                    N * t * t + 0.75
+                } else if t < 2.5 / D {
+                    let t = t - 2.25 / D;
+                    N * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D;
+                    N * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+/// A value a [`Tween`] can interpolate.
+pub trait Tweenable: Copy + 'static {
+    /// The value a fraction `t` of the way from `a` to `b`. `t` may leave `[0, 1]` slightly
+    /// with overshooting easings.
+    fn interpolate(a: Self, b: Self, t: f32) -> Self;
+}
+
+impl Tweenable for f32 {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+}
+
+impl<const N: usize> Tweenable for [f32; N] {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+    }
+}
+
+impl Tweenable for Color {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+}
+
+/// A rotation quaternion `[x, y, z, w]`, tweened along the shortest arc rather than
+/// component-wise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rotation(pub [f32; 4]);
+
+impl Tweenable for Rotation {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        Rotation(quat::slerp(a.0, b.0, t))
+    }
+}
+
+/// A tween as played by the [`TweenManager`], whatever its value type.
+trait Playing {
+    /// Advances by `delta` seconds. Returns the time left over if the tween finished.
+    fn advance(&mut self, delta: f32) -> Option<f32>;
+
+    /// The tween chained after this one.
+    fn take_next(&mut self) -> Option<Box<dyn Playing>>;
+
+    /// Chains `next` at the end of this tween's chain.
+    fn append(&mut self, next: Box<dyn Playing>);
+}
+
+/// An animation of a value from a start to an end over a duration.
+pub struct Tween<T: Tweenable> {
+    /// Start value; `None` until read from `current` when the tween begins.
+    from: Option<T>,
+    to: T,
+    duration: f32,
+    delay: f32,
+    easing: Easing,
+
+    /// Seconds since the tween was added, including the delay.
+    elapsed: f32,
+
+    /// Reads the start value when the tween begins, for tweens of a node's current state.
+    current: Box<dyn Fn() -> Option<T>>,
+    update: Option<Box<dyn FnMut(T)>>,
+    complete: Option<Box<dyn FnOnce()>>,
+    next: Option<Box<dyn Playing>>,
+}
+
+impl<T: Tweenable> std::fmt::Debug for Tween<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tween")
+            .field("duration", &self.duration)
+            .field("delay", &self.delay)
+            .field("easing", &self.easing)
+            .field("elapsed", &self.elapsed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Tweenable> Tween<T> {
+    /// A linear tween from `start` to `end` over `duration` seconds. Give it an
+    /// [`on_update`](Self::on_update) callback to do something with the values.
+    pub fn new(start: T, end: T, duration: f32) -> Self {
+        let mut tween = Self::to(end, duration, || None);
+        tween.from = Some(start);
+        tween
+    }
+
+    /// A tween to `end` from the value `current` returns when the tween begins (after its
+    /// delay, or when the tween before it in a chain completes). If `current` returns
+    /// `None`, the tween jumps to `end`.
+    pub fn to(end: T, duration: f32, current: impl Fn() -> Option<T> + 'static) -> Self {
+        Self {
+            from: None,
+            to: end,
+            duration: duration.max(0.0),
+            delay: 0.0,
+            easing: Easing::Linear,
+            elapsed: 0.0,
+            current: Box::new(current),
+            update: None,
+            complete: None,
+            next: None,
+        }
+    }
+
+    /// Sets the easing curve.
+    pub fn ease(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Waits `seconds` before starting.
+    pub fn delay(mut self, seconds: f32) -> Self {
+        self.delay = seconds.max(0.0);
+        self
+    }
+
+    /// Calls `callback` with the value every time the tween advances, ending with the end
+    /// value. Callbacks added earlier (such as the node shorthands') still run first.
+    pub fn on_update(mut self, mut callback: impl FnMut(T) + 'static) -> Self {
+        self.update = Some(match self.update.take() {
+            Some(mut earlier) => Box::new(move |value| {
+                earlier(value);
+                callback(value);
+            }),
+            None => Box::new(callback),
+        });
+        self
+    }
+
+    /// Calls `callback` once when the tween reaches its end, before the next tween in the
+    /// chain starts. Not called for cancelled tweens.
+    pub fn on_complete(mut self, callback: impl FnOnce() + 'static) -> Self {
+        self.complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Plays `next` after this tween completes, and after everything already chained to it.
+    pub fn then<U: Tweenable>(mut self, next: Tween<U>) -> Self {
+        self.append(Box::new(next));
+        self
+    }
+
+    /// The duration in seconds, without the delay.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    /// A tween of a property of `node`, read with `get` when the tween begins and written
+    /// with `set`. Holds the node weakly; the tween keeps running if it is dropped.
+    fn of_node(
+        node: &Rc<RefCell<Object3D>>,
+        end: T,
+        duration: f32,
+        get: fn(&Object3D) -> Option<T>,
+        set: fn(&mut Object3D, T),
+    ) -> Self {
+        let (read, write): (Weak<_>, Weak<_>) = (Rc::downgrade(node), Rc::downgrade(node));
+        Self::to(end, duration, move || get(&read.upgrade()?.borrow())).on_update(move |value| {
+            if let Some(node) = write.upgrade() {
+                set(&mut node.borrow_mut(), value);
+            }
+        })
+    }
+}
+
+impl Tween<[f32; 3]> {
+    /// Moves `node` to the local `position`.
+    pub fn position(node: &Rc<RefCell<Object3D>>, position: [f32; 3], duration: f32) -> Self {
+        Self::of_node(node, position, duration, |node| Some(node.position()), Object3D::set_position)
+    }
+
+    /// Scales `node` to `scale`.
+    pub fn scale(node: &Rc<RefCell<Object3D>>, scale: [f32; 3], duration: f32) -> Self {
+        Self::of_node(node, scale, duration, |node| Some(node.scale()), Object3D::set_scale)
+    }
+}
+
+impl Tween<Rotation> {
+    /// Turns `node` to the local `rotation` quaternion along the shortest arc.
+    pub fn rotation(node: &Rc<RefCell<Object3D>>, rotation: [f32; 4], duration: f32) -> Self {
+        Self::of_node(node, Rotation(rotation), duration, |node| Some(Rotation(node.rotation())), |node, rotation| {
+            node.set_rotation(rotation.0)
+        })
+    }
+}
+
+impl Tween<Color> {
+    /// Fades the color of `node`'s material to `color`. Does nothing to nodes without a
+    /// material.
+    pub fn color(node: &Rc<RefCell<Object3D>>, color: Color, duration: f32) -> Self {
+        Self::of_node(node, color, duration, |node| node.material().map(|material| material.color), |node, color| {
+            if let Some(material) = node.material_mut() {
+                material.set_color(color);
+            }
+        })
+    }
+}
+
+impl<T: Tweenable> Playing for Tween<T> {
+    fn advance(&mut self, delta: f32) -> Option<f32> {
+        self.elapsed += delta;
+        let active = self.elapsed - self.delay;
+        if active < 0.0 {
+            return None;
+        }
+        let to = self.to;
+        let from = *self.from.get_or_insert_with(|| (self.current)().unwrap_or(to));
+        let t = if self.duration > 0.0 { active / self.duration } else { 1.0 };
+        let value = if t >= 1.0 { to } else { T::interpolate(from, to, self.easing.apply(t)) };
+        if let Some(update) = &mut self.update {
+            update(value);
+        }
+        if t < 1.0 {
+            return None;
+        }
+        if let Some(complete) = self.complete.take() {
+            complete();
+        }
+        Some(active - self.duration)
+    }
+
+    fn take_next(&mut self) -> Option<Box<dyn Playing>> {
+        self.next.take()
+    }
+
+    fn append(&mut self, next: Box<dyn Playing>) {
+        match &mut self.next {
+            Some(chained) => chained.append(next),
+            None => self.next = Some(next),
+        }
+    }
+}
+
+/// Identifies a tween added to a [`TweenManager`], for cancelling it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TweenId(u64);
+
+/// Plays tweens, advancing them all by the same time step.
+#[derive(Default)]
+pub struct TweenManager {
+    tweens: Vec<(TweenId, Box<dyn Playing>)>,
+    next_id: u64,
+}
+
+impl std::fmt::Debug for TweenManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TweenManager").field("playing", &self.tweens.len()).finish()
+    }
+}
+
+impl TweenManager {
+    /// A manager playing nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts playing `tween` (and the tweens chained to it) on the next
+    /// [`update`](Self::update).
+    pub fn add<T: Tweenable>(&mut self, tween: Tween<T>) -> TweenId {
+        let id = TweenId(self.next_id);
+        self.next_id += 1;
+        self.tweens.push((id, Box::new(tween)));
+        id
+    }
+
+    /// Stops a tween and its chain where they are, without completion callbacks. Returns
+    /// `false` if it had already finished.
+    pub fn cancel(&mut self, id: TweenId) -> bool {
+        let count = self.tweens.len();
+        self.tweens.retain(|(tween, _)| *tween != id);
+        self.tweens.len() != count
+    }
+
+    /// Whether a tween or a tween chained to it is still playing.
+    pub fn is_active(&self, id: TweenId) -> bool {
+        self.tweens.iter().any(|(tween, _)| *tween == id)
+    }
+
+    /// Number of chains playing.
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+
+    /// Whether nothing is playing.
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+
+    /// Cancels every tween.
+    pub fn clear(&mut self) {
+        self.tweens.clear();
+    }
+
+    /// Advances every tween by `delta` seconds. Time left over when a tween completes goes
+    /// to the next one in its chain, so chains keep their timing at any frame rate.
+    pub fn update(&mut self, delta: f32) {
+        self.tweens.retain_mut(|(_, tween)| {
+            let mut delta = delta;
+            loop {
+                let Some(left) = tween.advance(delta) else {
+                    return true;
+                };
+                match tween.take_next() {
+                    Some(next) => {
+                        *tween = next;
+                        delta = left;
+                    }
+                    None => return false,
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,567 @@
+//! Immediate-mode UI for debug panels and simple editors.
+//!
+//! Widgets are function calls made every frame, with the state they show passed in and
+//! written back: `ui.slider("speed", &mut speed, 0.0..=10.0)` draws a slider and changes
+//! `speed` while it is dragged; `ui.button("Reset")` returns `true` the frame it is clicked.
+//! There is no widget tree to build or keep in sync with game state, which suits debug
+//! panels that come and go with the code they inspect.
+//!
+//! A [`UiContext`] lays widgets out top to bottom in [panels](UiContext::panel), which can
+//! be dragged by their title bar and collapsed with the box at its left, and draws them
+//! with the built-in [debug font](crate::engine::debug::text). Widgets outside any panel
+//! are laid out from the top-left corner of the screen.
+//!
+//! The renderer owns one and runs the callback registered with
+//! [`Renderer::on_ui`](crate::engine::renderer::Renderer::on_ui) every frame after the update
+//! callback, drawing the result last, over post effects and overlays. It forwards every
+//! input event to the UI, and keeps mouse input from the game (camera controller,
+//! selection, the [input context](crate::engine::input_context) below) while the cursor is
+//! over the UI, and keyboard input while a text field has focus.
+//!
+//! # Example
+//! ```
+//! # use rustge::engine::event::{ButtonState, EngineEvent, MouseButton};
+//! # use rustge::engine::ui::UiContext;
+//! let (mut speed, mut paused, mut resets) = (2.0, false, 0);
+//! let mut ui = UiContext::new();
+//! let mut frame = |ui: &mut UiContext| {
+//!     ui.begin_frame();
+//!     ui.panel("Tweaks", [20.0, 20.0], |ui| {
+//!         ui.slider("speed", &mut speed, 0.0..=10.0);
+//!         ui.checkbox("paused", &mut paused);
+//!         if ui.button("Reset") {
+//!             resets += 1;
+//!         }
+//!     });
+//!     ui.end_frame();
+//! };
+//! frame(&mut ui);
+//!
+//! // Click the button, found by the layout of the last frame
+//! let [x, y, width, height] = ui.last_rect("Reset").unwrap();
+//! ui.handle_event(&EngineEvent::CursorMoved { position: [(x + width / 2.0) as f64, (y + height / 2.0) as f64] });
+//! ui.handle_event(&EngineEvent::MouseButton { button: MouseButton::Left, state: ButtonState::Pressed });
+//! ui.handle_event(&EngineEvent::MouseButton { button: MouseButton::Left, state: ButtonState::Released });
+//! assert!(ui.wants_mouse());
+//! frame(&mut ui);
+//! assert_eq!(resets, 1);
+//! ```
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+use crate::engine::debug::text::{text_width, TextBatch, GLYPH_ADVANCE, GLYPH_HEIGHT};
+use crate::engine::event::{ButtonState, EngineEvent, Key, MouseButton};
+use crate::engine::math::color::Color;
+
+/// Sizes and colors of the UI, in screen pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiStyle {
+    /// Screen pixels per font pixel.
+    pub text_scale: f32,
+
+    /// Space between a widget's edge and its text.
+    pub padding: f32,
+
+    /// Space between widgets.
+    pub spacing: f32,
+
+    /// Width of sliders and text fields.
+    pub field_width: f32,
+
+    pub text_color: Color,
+    pub panel_color: Color,
+    pub title_color: Color,
+    pub widget_color: Color,
+    pub hover_color: Color,
+
+    /// Pressed buttons, slider fills and check marks.
+    pub accent_color: Color,
+}
+
+impl UiStyle {
+    /// Light text on dark, translucent panels.
+    pub fn new() -> Self {
+        Self {
+            text_scale: 2.0,
+            padding: 4.0,
+            spacing: 4.0,
+            field_width: 160.0,
+            text_color: Color::WHITE,
+            panel_color: Color::linear_rgba(0.02, 0.02, 0.025, 0.85),
+            title_color: Color::linear_rgba(0.06, 0.08, 0.14, 0.95),
+            widget_color: Color::linear_rgba(0.08, 0.08, 0.1, 1.0),
+            hover_color: Color::linear_rgba(0.15, 0.15, 0.2, 1.0),
+            accent_color: Color::linear_rgb(0.12, 0.3, 0.7),
+        }
+    }
+
+    /// Height of a widget: one line of text and its padding.
+    fn widget_height(&self) -> f32 {
+        GLYPH_HEIGHT as f32 * self.text_scale + 2.0 * self.padding
+    }
+}
+
+impl Default for UiStyle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mouse and keyboard state gathered from events since the last frame.
+#[derive(Debug, Default)]
+struct UiInput {
+    mouse: [f32; 2],
+    mouse_down: bool,
+    pressed: bool,
+    released: bool,
+    /// The current press started outside the UI, so it belongs to the game.
+    pressed_outside: bool,
+    text: String,
+    backspaces: u32,
+    enter: bool,
+}
+
+/// Where a panel is, kept across frames.
+#[derive(Clone, Copy, Debug)]
+struct PanelState {
+    position: [f32; 2],
+    /// Size when last drawn.
+    size: [f32; 2],
+    collapsed: bool,
+}
+
+/// What a widget draws.
+#[derive(Clone, Debug)]
+enum Shape {
+    Rect([f32; 4], Color),
+    Text([f32; 2], Color, String),
+}
+
+/// Places widgets top to bottom.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    left: f32,
+    /// Top of the next row.
+    next_y: f32,
+    /// Right and bottom edges of everything placed.
+    extent: [f32; 2],
+    /// The last widget placed, which `same_line` continues after.
+    last: [f32; 4],
+    same_line: bool,
+}
+
+impl Layout {
+    fn new(origin: [f32; 2]) -> Self {
+        Self { left: origin[0], next_y: origin[1], extent: origin, last: [origin[0], origin[1], 0.0, 0.0], same_line: false }
+    }
+
+    fn place(&mut self, size: [f32; 2], spacing: f32) -> [f32; 4] {
+        let [x, y] = if self.same_line {
+            [self.last[0] + self.last[2] + spacing, self.last[1]]
+        } else {
+            [self.left, self.next_y]
+        };
+        self.same_line = false;
+        self.last = [x, y, size[0], size[1]];
+        self.next_y = self.next_y.max(y + size[1] + spacing);
+        self.extent = [self.extent[0].max(x + size[0]), self.extent[1].max(y + size[1])];
+        self.last
+    }
+}
+
+/// The panel whose contents are being laid out.
+#[derive(Debug)]
+struct OpenPanel {
+    id: u64,
+    /// Drawn over the panel's background once its size is known.
+    shapes: Vec<Shape>,
+    /// The layout outside the panel, restored when it closes.
+    outer: Layout,
+}
+
+/// State and draw list of an immediate-mode UI; see the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct UiContext {
+    /// How widgets look; changes apply from the next widget drawn.
+    pub style: UiStyle,
+    input: UiInput,
+    panels: HashMap<u64, PanelState>,
+    layout: Layout,
+    open_panel: Option<OpenPanel>,
+    /// Areas covered this frame, in drawing order, with the panel owning them (`None` for
+    /// widgets outside panels).
+    areas: Vec<([f32; 4], Option<u64>)>,
+    /// The areas of the last frame, which decide what the cursor is over.
+    last_areas: Vec<([f32; 4], Option<u64>)>,
+    /// The owner of the topmost area under the cursor at the start of the frame.
+    hovered: Option<Option<u64>>,
+    /// Rectangles of the widgets of the last frame by label, for tests and tools.
+    rects: HashMap<String, [f32; 4]>,
+    last_rects: HashMap<String, [f32; 4]>,
+    /// The widget being pressed or dragged.
+    active: Option<u64>,
+    /// The text field receiving typed text.
+    focused: Option<u64>,
+    /// Whether the focused text field was shown this frame; it loses focus when hidden.
+    focused_shown: bool,
+    /// Cursor offset from the position of the panel being dragged.
+    drag_offset: [f32; 2],
+    batch: TextBatch,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new([0.0, 0.0])
+    }
+}
+
+impl UiContext {
+    /// An empty UI with the default style.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Updates the input state from `event`. The renderer forwards every event; call it
+    /// yourself when driving a UI manually.
+    pub fn handle_event(&mut self, event: &EngineEvent) {
+        let input = &mut self.input;
+        match *event {
+            EngineEvent::CursorMoved { position } => input.mouse = [position[0] as f32, position[1] as f32],
+            EngineEvent::MouseButton { button: MouseButton::Left, state } => {
+                input.mouse_down = state == ButtonState::Pressed;
+                if input.mouse_down {
+                    input.pressed = true;
+                    input.pressed_outside = area_at(&self.last_areas, input.mouse).is_none();
+                } else {
+                    input.released = true;
+                    input.pressed_outside = false;
+                }
+            }
+            EngineEvent::Text(character) if self.focused.is_some() => input.text.push(character),
+            EngineEvent::Key { key, state: ButtonState::Pressed, .. } if self.focused.is_some() => match key {
+                Key::Backspace => input.backspaces += 1,
+                Key::Enter | Key::NumpadEnter | Key::Escape => input.enter = true,
+                _ => {}
+            },
+            EngineEvent::Focused(false) | EngineEvent::CursorLeft => {
+                input.mouse_down = false;
+                input.pressed_outside = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts a frame's widgets, discarding any left undrawn from the last frame.
+    pub fn begin_frame(&mut self) {
+        self.layout = Layout::new([self.style.spacing * 2.0; 2]);
+        self.open_panel = None;
+        self.hovered = area_at(&self.last_areas, self.input.mouse);
+        self.areas.clear();
+        self.rects.clear();
+        self.focused_shown = false;
+    }
+
+    /// Ends a frame's widgets: clears the input edges and keeps the layout to hit-test the
+    /// next frame's input against. Draw the UI afterwards.
+    pub fn end_frame(&mut self) {
+        self.last_areas = std::mem::take(&mut self.areas);
+        self.last_rects = std::mem::take(&mut self.rects);
+        if !self.input.mouse_down {
+            self.active = None;
+        }
+        if !self.focused_shown {
+            self.focused = None;
+        }
+        let input = &mut self.input;
+        (input.pressed, input.released, input.enter, input.backspaces) = (false, false, false, 0);
+        input.text.clear();
+    }
+
+    /// Draws the frame's widgets over the current framebuffer, of `screen_size` pixels.
+    pub fn draw(&mut self, screen_size: [u32; 2]) {
+        self.batch.draw(screen_size);
+    }
+
+    /// Returns `true` if the frame has nothing to draw.
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    /// Whether the UI uses the mouse: the cursor is over it, or one of its widgets is being
+    /// dragged. The game should ignore mouse input meanwhile.
+    pub fn wants_mouse(&self) -> bool {
+        self.active.is_some() || (area_at(&self.last_areas, self.input.mouse).is_some() && !self.input.pressed_outside)
+    }
+
+    /// Whether a text field has focus. The game should ignore keyboard input meanwhile.
+    pub fn wants_keyboard(&self) -> bool {
+        self.focused.is_some()
+    }
+
+    /// The rectangle (x, y, width, height) of the widget labelled `label` in the last frame.
+    pub fn last_rect(&self, label: &str) -> Option<[f32; 4]> {
+        self.last_rects.get(label).copied()
+    }
+
+    /// A panel titled `title` with `contents` laid out inside, first shown at `position`
+    /// (top-left, in pixels); it remembers where it was dragged to. Panels don't nest: a
+    /// panel opened inside another lays its contents out inline.
+    pub fn panel(&mut self, title: &str, position: [f32; 2], contents: impl FnOnce(&mut Self)) {
+        if self.open_panel.is_some() {
+            self.label(title);
+            contents(self);
+            return;
+        }
+        let id = self.id(title);
+        let style = self.style;
+        let height = style.widget_height();
+        let state = *self.panels.entry(id).or_insert(PanelState { position, size: [0.0, 0.0], collapsed: false });
+        let mut position = state.position;
+        let mut collapsed = state.collapsed;
+
+        // The title bar drags the panel, its box toggles the contents
+        let title_width = state.size[0].max(height + text_width(title, style.text_scale) + 2.0 * style.padding);
+        let bar = [position[0], position[1], title_width, height];
+        let toggle = [position[0], position[1], height, height];
+        let over_panel = self.hovered == Some(Some(id));
+        if over_panel && self.input.pressed && contains(bar, self.input.mouse) {
+            if contains(toggle, self.input.mouse) {
+                collapsed = !collapsed;
+            } else {
+                self.active = Some(id);
+                self.drag_offset = [self.input.mouse[0] - position[0], self.input.mouse[1] - position[1]];
+            }
+        }
+        if self.active == Some(id) && self.input.mouse_down {
+            position = [self.input.mouse[0] - self.drag_offset[0], self.input.mouse[1] - self.drag_offset[1]];
+        }
+
+        let origin = [position[0] + style.padding, position[1] + height + style.padding];
+        let outer = std::mem::replace(&mut self.layout, Layout::new(origin));
+        self.open_panel = Some(OpenPanel { id, shapes: Vec::new(), outer });
+        if !collapsed {
+            contents(self);
+        }
+        let Some(panel) = self.open_panel.take() else {
+            return;
+        };
+        let layout = std::mem::replace(&mut self.layout, panel.outer);
+
+        let width = (layout.extent[0] + style.padding - position[0]).max(title_width);
+        let size = if collapsed { [width, height] } else { [width, layout.extent[1] + style.padding - position[1]] };
+        let batch = &mut self.batch;
+        batch.rect(position[0], position[1], size[0], size[1], style.panel_color);
+        batch.rect(position[0], position[1], size[0], height, style.title_color);
+        let mark = if collapsed { "+" } else { "-" };
+        let text_y = position[1] + style.padding;
+        batch.text(position[0] + (height - text_width(mark, style.text_scale)) / 2.0, text_y, style.text_scale, style.text_color, mark);
+        batch.text(position[0] + height, text_y, style.text_scale, style.text_color, title);
+        for shape in panel.shapes {
+            match shape {
+                Shape::Rect([x, y, width, height], color) => batch.rect(x, y, width, height, color),
+                Shape::Text([x, y], color, text) => batch.text(x, y, style.text_scale, color, &text),
+            }
+        }
+        self.areas.push(([position[0], position[1], size[0], size[1]], Some(id)));
+        self.panels.insert(id, PanelState { position, size, collapsed });
+    }
+
+    /// Places the next widget to the right of the last one instead of below it.
+    pub fn same_line(&mut self) {
+        self.layout.same_line = true;
+    }
+
+    /// A line of text. Text after `##` in a label is not shown; it only tells widgets with
+    /// the same visible label apart.
+    pub fn label(&mut self, text: &str) {
+        let style = self.style;
+        let shown = visible(text);
+        let rect = self.place(text, [text_width(shown, style.text_scale), style.widget_height()]);
+        self.shape(Shape::Text([rect[0], rect[1] + style.padding], style.text_color, shown.to_string()));
+    }
+
+    /// A horizontal line across the panel.
+    pub fn separator(&mut self) {
+        let width = match &self.open_panel {
+            Some(panel) => self.panels.get(&panel.id).map_or(0.0, |state| state.size[0] - 2.0 * self.style.padding),
+            None => self.style.field_width,
+        };
+        let rect = self.place("##separator", [width.max(self.style.field_width), self.style.spacing]);
+        self.shape(Shape::Rect([rect[0], rect[1] + rect[3] / 2.0, rect[2], 1.0], self.style.hover_color));
+    }
+
+    /// A button; returns `true` the frame it is clicked.
+    pub fn button(&mut self, label: &str) -> bool {
+        let style = self.style;
+        let shown = visible(label);
+        let size = [text_width(shown, style.text_scale) + 2.0 * style.padding, style.widget_height()];
+        let rect = self.place(label, size);
+        let (hovered, clicked) = self.interact(label, rect);
+        let id = self.id(label);
+        let color = match () {
+            _ if self.active == Some(id) => style.accent_color,
+            _ if hovered => style.hover_color,
+            _ => style.widget_color,
+        };
+        self.shape(Shape::Rect(rect, color));
+        self.shape(Shape::Text([rect[0] + style.padding, rect[1] + style.padding], style.text_color, shown.to_string()));
+        clicked
+    }
+
+    /// A check box toggling `value`; returns `true` the frame it changes.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let style = self.style;
+        let shown = visible(label);
+        let height = style.widget_height();
+        let rect = self.place(label, [height + style.spacing + text_width(shown, style.text_scale), height]);
+        let (hovered, clicked) = self.interact(label, rect);
+        if clicked {
+            *value = !*value;
+        }
+        self.shape(Shape::Rect([rect[0], rect[1], height, height], if hovered { style.hover_color } else { style.widget_color }));
+        if *value {
+            let inset = style.padding + 1.0;
+            self.shape(Shape::Rect([rect[0] + inset, rect[1] + inset, height - 2.0 * inset, height - 2.0 * inset], style.accent_color));
+        }
+        self.shape(Shape::Text([rect[0] + height + style.spacing, rect[1] + style.padding], style.text_color, shown.to_string()));
+        clicked
+    }
+
+    /// A slider setting `value` within `range` while dragged; returns `true` the frames it
+    /// changes.
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let style = self.style;
+        let shown = visible(label);
+        let size = [style.field_width + style.spacing + text_width(shown, style.text_scale), style.widget_height()];
+        let rect = self.place(label, size);
+        let track = [rect[0], rect[1], style.field_width, rect[3]];
+        let (hovered, _) = self.interact(label, track);
+
+        let (low, high) = (*range.start(), *range.end());
+        let old = *value;
+        if self.active == Some(self.id(label)) && self.input.mouse_down && high > low {
+            let t = ((self.input.mouse[0] - track[0]) / track[2]).clamp(0.0, 1.0);
+            *value = low + (high - low) * t;
+        }
+        let t = if high > low { ((*value - low) / (high - low)).clamp(0.0, 1.0) } else { 0.0 };
+
+        self.shape(Shape::Rect(track, if hovered { style.hover_color } else { style.widget_color }));
+        self.shape(Shape::Rect([track[0], track[1], track[2] * t, track[3]], style.accent_color));
+        let text_y = rect[1] + style.padding;
+        self.shape(Shape::Text([track[0] + style.padding, text_y], style.text_color, format_value(*value)));
+        self.shape(Shape::Text([track[0] + track[2] + style.spacing, text_y], style.text_color, shown.to_string()));
+        *value != old
+    }
+
+    /// A single-line text field editing `text`; returns `true` the frames it changes. It
+    /// takes typed text after being clicked, until Enter, Escape or a click elsewhere.
+    pub fn text_edit(&mut self, label: &str, text: &mut String) -> bool {
+        let style = self.style;
+        let shown = visible(label);
+        let size = [style.field_width + style.spacing + text_width(shown, style.text_scale), style.widget_height()];
+        let rect = self.place(label, size);
+        let field = [rect[0], rect[1], style.field_width, rect[3]];
+        let (hovered, clicked) = self.interact(label, field);
+        let id = self.id(label);
+
+        if clicked {
+            self.focused = Some(id);
+        } else if self.focused == Some(id) && self.input.pressed && !hovered {
+            self.focused = None;
+        }
+        let old = text.clone();
+        let focused = self.focused == Some(id);
+        if focused {
+            self.focused_shown = true;
+            for _ in 0..self.input.backspaces {
+                text.pop();
+            }
+            text.push_str(&self.input.text);
+            if self.input.enter {
+                self.focused = None;
+            }
+        }
+
+        let color = if focused || hovered { style.hover_color } else { style.widget_color };
+        self.shape(Shape::Rect(field, color));
+        // Show the end of text too long for the field
+        let fits = ((field[2] - 2.0 * style.padding) / (GLYPH_ADVANCE as f32 * style.text_scale)) as usize;
+        let mut line: String = text.chars().rev().take(fits.saturating_sub(1)).collect::<Vec<_>>().into_iter().rev().collect();
+        if focused {
+            line.push('_');
+        }
+        let text_y = rect[1] + style.padding;
+        self.shape(Shape::Text([field[0] + style.padding, text_y], style.text_color, line));
+        self.shape(Shape::Text([field[0] + field[2] + style.spacing, text_y], style.text_color, shown.to_string()));
+        *text != old
+    }
+
+    /// Id of the widget labelled `label` in the open panel.
+    fn id(&self, label: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.open_panel.as_ref().map(|panel| panel.id).hash(&mut hasher);
+        label.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Places a widget of `size`, recording its rectangle under `label`.
+    fn place(&mut self, label: &str, size: [f32; 2]) -> [f32; 4] {
+        let rect = self.layout.place(size, self.style.spacing);
+        self.rects.insert(label.to_string(), rect);
+        if self.open_panel.is_none() {
+            self.areas.push((rect, None));
+        }
+        rect
+    }
+
+    /// Mouse interaction with the widget labelled `label` covering `rect`: whether the cursor
+    /// is over it, and whether it was clicked (pressed and released over it) this frame.
+    fn interact(&mut self, label: &str, rect: [f32; 4]) -> (bool, bool) {
+        let id = self.id(label);
+        let owner = self.open_panel.as_ref().map(|panel| panel.id);
+        let hovered = (self.active.is_none() || self.active == Some(id))
+            && self.hovered == Some(owner)
+            && contains(rect, self.input.mouse);
+        if hovered && self.input.pressed {
+            self.active = Some(id);
+        }
+        (hovered, hovered && self.active == Some(id) && self.input.released)
+    }
+
+    /// Draws `shape` now, or with the open panel once its background is drawn.
+    fn shape(&mut self, shape: Shape) {
+        if let Some(panel) = &mut self.open_panel {
+            panel.shapes.push(shape);
+            return;
+        }
+        match shape {
+            Shape::Rect([x, y, width, height], color) => self.batch.rect(x, y, width, height, color),
+            Shape::Text([x, y], color, text) => self.batch.text(x, y, self.style.text_scale, color, &text),
+        }
+    }
+}
+
+/// The owner of the topmost of `areas` containing `point`.
+fn area_at(areas: &[([f32; 4], Option<u64>)], point: [f32; 2]) -> Option<Option<u64>> {
+    areas.iter().rev().find(|(rect, _)| contains(*rect, point)).map(|(_, owner)| *owner)
+}
+
+fn contains(rect: [f32; 4], point: [f32; 2]) -> bool {
+    point[0] >= rect[0] && point[0] < rect[0] + rect[2] && point[1] >= rect[1] && point[1] < rect[1] + rect[3]
+}
+
+/// The part of `label` shown, before any `##`.
+fn visible(label: &str) -> &str {
+    label.split("##").next().unwrap_or(label)
+}
+
+/// `value` with as many decimals as its size needs.
+fn format_value(value: f32) -> String {
+    match value.abs() {
+        v if v >= 1000.0 => format!("{value:.0}"),
+        v if v >= 10.0 => format!("{value:.1}"),
+        _ => format!("{value:.3}"),
+    }
+}
@@ -0,0 +1,315 @@
+//! Uniform buffer objects and the engine's standard uniform blocks.
+//!
+//! Every material's program used to get the camera and every light as separate uniforms,
+//! set again each time the render queue switched programs. Shaders that declare the
+//! standard `std140` blocks instead read them from two buffers uploaded once per pass, and
+//! only when their contents changed:
+//!
+//! ```glsl
+//! layout(std140) uniform Camera {     // binding CAMERA_BLOCK_BINDING
+//!     mat4 u_proj_view;
+//!     vec3 u_camera_position;
+//!     float u_exposure;               // 1.0 unless the camera uses physical exposure
+//! };
+//!
+//! layout(std140) uniform Lights {     // binding LIGHTS_BLOCK_BINDING, see LightSet::upload
+//!     Light u_lights[8];
+//!     int u_light_count;
+//!     vec3 u_ambient;
+//!     mat4 u_shadow_matrices[16];
+//!     vec4 u_shadow_rects[16];
+//!     float u_shadow_bias;
+//! };
+//! ```
+//!
+//! Every program is linked to the blocks' binding points when it is built, so declaring
+//! them is all a shader needs; the built-in Blinn-Phong shaders do. Shaders declaring plain
+//! uniforms of the same names keep working: they are set per program as before. Samplers
+//! can't live in blocks, so cookie textures and the shadow atlas stay plain uniforms.
+//!
+//! [`UniformBuffer`] and [`Std140Writer`] are public for games with blocks of their own.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::uniform_buffer::{Std140Writer, UniformBuffer};
+//! let mut wind = UniformBuffer::new("wind");
+//! let mut block = Std140Writer::new();
+//! block.vec3([1.0, 0.0, 0.5]);
+//! block.float(2.5);
+//! wind.update(&block.finish());
+//! wind.bind(4);   // matching `layout(std140, binding = 4)` or glUniformBlockBinding
+//! ```
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::thread::LocalKey;
+use gl::types::{GLsizeiptr, GLuint};
+use crate::engine::camera::Camera;
+use crate::engine::eye_adaptation::frame_exposure;
+use crate::engine::light::LightSet;
+use crate::engine::shader::GLShaderProgram;
+use crate::engine::stats::{record_gpu_upload, release_gpu_allocation, track_gpu_allocation, GpuResourceKind};
+
+/// Name of the standard camera block.
+pub const CAMERA_BLOCK: &str = "Camera";
+
+/// Binding point of the standard camera block.
+pub const CAMERA_BLOCK_BINDING: u32 = 0;
+
+/// Name of the standard lights block.
+pub const LIGHTS_BLOCK: &str = "Lights";
+
+/// Binding point of the standard lights block.
+pub const LIGHTS_BLOCK_BINDING: u32 = 1;
+
+/// Blocks linked to their binding point in every program.
+const STANDARD_BLOCKS: [(&str, u32); 2] = [(CAMERA_BLOCK, CAMERA_BLOCK_BINDING), (LIGHTS_BLOCK, LIGHTS_BLOCK_BINDING)];
+
+/// Packs values with the `std140` layout rules, for uploading to a [`UniformBuffer`].
+///
+/// Values are written in the order the block declares them; each is aligned as `std140`
+/// requires (4 bytes for scalars, 8 for `vec2`, 16 for `vec3`, `vec4` and matrices). Structs
+/// start and end on 16 bytes, as do the elements of arrays of scalars and vectors smaller
+/// than a `vec4`: [`align`](Self::align) to 16 around them.
+///
+/// ```
+/// # use rustge::engine::uniform_buffer::Std140Writer;
+/// # use rustge::engine::math::matrixfuncs::IDENTITY_MATRIX;
+/// let mut block = Std140Writer::new();
+/// block.mat4(&IDENTITY_MATRIX);
+/// block.vec3([0.0, 2.0, 5.0]);
+/// block.float(1.0);   // fills the vec3's last 4 bytes
+/// assert_eq!(block.len(), 80);
+/// block.int(3);
+/// block.vec3([1.0; 3]);   // aligned to 16
+/// assert_eq!(block.len(), 108);
+/// assert_eq!(block.finish().len(), 112);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Std140Writer {
+    bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+    /// An empty block.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pads with zeros up to the next multiple of `alignment` bytes.
+    pub fn align(&mut self, alignment: usize) {
+        let padded = self.bytes.len().next_multiple_of(alignment);
+        self.bytes.resize(padded, 0);
+    }
+
+    /// Writes a `float`.
+    pub fn float(&mut self, value: f32) {
+        self.floats(4, &[value]);
+    }
+
+    /// Writes an `int`.
+    pub fn int(&mut self, value: i32) {
+        self.align(4);
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    /// Writes a `uint`.
+    pub fn uint(&mut self, value: u32) {
+        self.align(4);
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    /// Writes a `vec2`.
+    pub fn vec2(&mut self, value: [f32; 2]) {
+        self.floats(8, &value);
+    }
+
+    /// Writes a `vec3`.
+    pub fn vec3(&mut self, value: [f32; 3]) {
+        self.floats(16, &value);
+    }
+
+    /// Writes a `vec4`.
+    pub fn vec4(&mut self, value: [f32; 4]) {
+        self.floats(16, &value);
+    }
+
+    /// Writes a column-major `mat4`.
+    pub fn mat4(&mut self, value: &[f32; 16]) {
+        self.floats(16, value);
+    }
+
+    /// Bytes written so far.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Whether nothing was written yet.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The block's bytes, padded to a multiple of 16 like the block's size in GL.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.align(16);
+        self.bytes
+    }
+
+    fn floats(&mut self, alignment: usize, values: &[f32]) {
+        self.align(alignment);
+        for value in values {
+            self.bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+}
+
+/// A GL uniform buffer object, holding the data of one uniform block.
+///
+/// [`update`](Self::update) skips uploads of unchanged data, so updating every pass costs
+/// a comparison when nothing moved.
+#[derive(Debug)]
+pub struct UniformBuffer {
+    id: GLuint,
+    /// Bytes of storage allocated for the buffer.
+    capacity: usize,
+    /// What the buffer holds, to skip redundant uploads.
+    contents: Vec<u8>,
+    label: String,
+}
+
+impl UniformBuffer {
+    /// An empty buffer, labeled `label` in GPU memory statistics and debuggers.
+    pub fn new(label: &str) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+        }
+        Self { id, capacity: 0, contents: Vec::new(), label: label.to_string() }
+    }
+
+    /// Replaces the buffer's contents with `data`, usually from [`Std140Writer::finish`].
+    /// Returns whether anything was uploaded: nothing is when the data is unchanged.
+    pub fn update(&mut self, data: &[u8]) -> bool {
+        if data == self.contents.as_slice() {
+            return false;
+        }
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.id);
+            if data.len() > self.capacity {
+                gl::BufferData(gl::UNIFORM_BUFFER, data.len() as GLsizeiptr, data.as_ptr() as *const _, gl::DYNAMIC_DRAW);
+            } else {
+                gl::BufferSubData(gl::UNIFORM_BUFFER, 0, data.len() as GLsizeiptr, data.as_ptr() as *const _);
+            }
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+        }
+        if data.len() > self.capacity {
+            self.capacity = data.len();
+            track_gpu_allocation(GpuResourceKind::UniformBuffer, self.id, self.capacity, &self.label);
+        } else {
+            record_gpu_upload(data.len());
+        }
+        self.contents.clear();
+        self.contents.extend_from_slice(data);
+        true
+    }
+
+    /// Binds the buffer to uniform block binding point `binding` (`glBindBufferBase`).
+    pub fn bind(&self, binding: u32) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.id);
+        }
+    }
+
+    /// The OpenGL buffer name.
+    pub fn id(&self) -> GLuint {
+        self.id
+    }
+
+    /// Bytes of data last uploaded.
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    /// Whether no data was uploaded yet.
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+}
+
+impl Drop for UniformBuffer {
+    fn drop(&mut self) {
+        release_gpu_allocation(GpuResourceKind::UniformBuffer, self.id);
+        unsafe {
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
+
+thread_local! {
+    /// Buffers of the standard blocks, created on first use. Buffers belong to the GL context's thread.
+    static CAMERA_BUFFER: RefCell<Option<UniformBuffer>> = const { RefCell::new(None) };
+    static LIGHTS_BUFFER: RefCell<Option<UniformBuffer>> = const { RefCell::new(None) };
+}
+
+/// Links the standard blocks `program` declares to their binding points. Called for every
+/// program the engine builds.
+pub(crate) fn bind_standard_blocks(program: GLuint) {
+    for (name, binding) in STANDARD_BLOCKS {
+        let c_name = CString::new(name).expect("block name contains a NUL byte");
+        unsafe {
+            let index = gl::GetUniformBlockIndex(program, c_name.as_ptr());
+            if index != gl::INVALID_INDEX {
+                gl::UniformBlockBinding(program, index, binding);
+            }
+        }
+    }
+}
+
+/// Uploads `camera` to the standard camera block, if it changed.
+pub fn upload_camera_block(camera: &Camera) {
+    let mut block = Std140Writer::new();
+    block.mat4(&camera.proj_view_matrix());
+    block.vec3(camera.position);
+    block.float(camera.exposure() * frame_exposure());
+    update_standard_block(&CAMERA_BUFFER, "camera block", CAMERA_BLOCK_BINDING, &block.finish());
+}
+
+/// Uploads `lights` to the standard lights block, if they changed. Their textures are bound
+/// per program, by [`LightSet::upload`].
+pub fn upload_lights_block(lights: &LightSet) {
+    let mut block = Std140Writer::new();
+    lights.write_block(&mut block);
+    update_standard_block(&LIGHTS_BUFFER, "lights block", LIGHTS_BLOCK_BINDING, &block.finish());
+}
+
+/// Uploads both standard blocks. The render queue does this once per pass.
+pub fn upload_frame_blocks(camera: &Camera, lights: &LightSet) {
+    upload_camera_block(camera);
+    upload_lights_block(lights);
+}
+
+fn update_standard_block(buffer: &'static LocalKey<RefCell<Option<UniformBuffer>>>, label: &str, binding: u32, data: &[u8]) {
+    buffer.with(|buffer| {
+        let mut buffer = buffer.borrow_mut();
+        let buffer = buffer.get_or_insert_with(|| UniformBuffer::new(label));
+        buffer.update(data);
+        // Rebound every time in case something else used the binding point meanwhile
+        buffer.bind(binding);
+    });
+}
+
+/// Gives `shader`, the current program, the camera and lights of the pass: shaders declaring
+/// the standard blocks read them from the buffers [`upload_frame_blocks`] filled and only get
+/// the lights' textures bound, others get everything as plain uniforms.
+pub(crate) fn apply_frame_uniforms(shader: &GLShaderProgram, camera: &Camera, lights: &LightSet) {
+    if !shader.has_uniform_block(CAMERA_BLOCK) {
+        shader.set_uniform_matrix4("u_proj_view", &camera.proj_view_matrix());
+        shader.set_uniform_vec3("u_camera_position", camera.position);
+        shader.set_uniform_float("u_exposure", camera.exposure() * frame_exposure());
+    }
+    if shader.has_uniform_block(LIGHTS_BLOCK) {
+        lights.bind_textures(shader);
+    } else {
+        lights.upload(shader);
+    }
+}
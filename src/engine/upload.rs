@@ -0,0 +1,313 @@
+//! Texture and mesh uploads on a second GL context, off the render thread.
+//!
+//! Uploading a large texture or mesh copies its data through the driver while the render
+//! thread waits, and streaming in a few of them shows as a hitch. With
+//! [async uploads](crate::engine::renderer::RendererBuilder::async_uploads) on, the renderer
+//! creates a second context sharing its objects with the window's and hands it to an upload
+//! thread. [`Renderer::upload_texture`](crate::engine::renderer::Renderer::upload_texture)
+//! and [`Renderer::upload_geometry`](crate::engine::renderer::Renderer::upload_geometry)
+//! send the data there and return at once; the upload thread creates and fills the GL
+//! objects and puts a fence behind them. The renderer checks the fences at the start of
+//! every frame, and a [`PendingUpload`] hands its object over once the GPU has it, without
+//! ever waiting.
+//!
+//! Vertex arrays aren't shared between contexts, so the upload thread only fills a mesh's
+//! buffers; the render thread sets up its vertex array, which is cheap, when the upload
+//! completes. A completed mesh joins the [mesh cache](crate::engine::mesh::gpu::GpuMesh::shared):
+//! nodes drawing the same geometry use it instead of uploading again, for as long as the
+//! pending upload or a node holds it.
+//!
+//! Without a second context (async uploads off, or the driver wouldn't share) the same calls
+//! upload right away on the render thread, so code using them works either way.
+//!
+//! # Example
+//! ```no_run
+//! # use rustge::engine::material::Material;
+//! # use rustge::engine::math::color::Color;
+//! # use rustge::engine::object3d::Object3D;
+//! # use rustge::engine::renderer::Renderer;
+//! # fn decode_terrain() -> (u32, u32, Vec<u8>) { (1, 1, vec![0; 4]) }
+//! let mut renderer = Renderer::builder().async_uploads(true).build();
+//! let ground = Object3D::new();
+//! renderer.get_scene().unwrap().add(ground.clone());
+//!
+//! let (width, height, pixels) = decode_terrain();
+//! let mut albedo = renderer.upload_texture(width, height, pixels, "terrain albedo");
+//! renderer.on_update(move |_, _| {
+//!     // Frames keep coming while the texture uploads
+//!     if let Some(texture) = albedo.try_take() {
+//!         let mut material = Material::phong(Color::WHITE);
+//!         material.set_texture("u_albedo", texture);
+//!         ground.borrow_mut().set_material(material);
+//!     }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use gl::types::{GLsync, GLuint};
+use glutin::{Context, NotCurrent};
+use crate::engine::mesh::gpu::{geometry_bytes, upload_buffers, GpuMesh};
+use crate::engine::object3d::Geometry;
+use crate::engine::texture::{upload_rgba8, Texture};
+
+/// A texture or mesh being uploaded; see the [module documentation](self).
+///
+/// Dropping it before the upload completes cancels it: the objects are deleted once the
+/// upload thread is done with them.
+#[derive(Debug)]
+pub struct PendingUpload<T> {
+    slot: Rc<RefCell<Option<T>>>,
+}
+
+impl<T> PendingUpload<T> {
+    /// An upload that already completed, with `value`.
+    fn ready(value: T) -> Self {
+        Self { slot: Rc::new(RefCell::new(Some(value))) }
+    }
+
+    /// Whether the upload has completed and the object wasn't taken yet.
+    pub fn is_ready(&self) -> bool {
+        self.slot.borrow().is_some()
+    }
+
+    /// The uploaded object, once the GPU has it. Returns `None` while the upload is in
+    /// flight, and after the object was taken once. Never blocks.
+    pub fn try_take(&mut self) -> Option<T> {
+        self.slot.borrow_mut().take()
+    }
+}
+
+/// Data for the upload thread.
+enum Job {
+    Texture { id: u64, width: u32, height: u32, pixels: Vec<u8> },
+    Mesh { id: u64, vertices: Vec<u8>, indices: Vec<u8>, skin: Vec<u8> },
+}
+
+impl Job {
+    fn id(&self) -> u64 {
+        match *self {
+            Job::Texture { id, .. } | Job::Mesh { id, .. } => id,
+        }
+    }
+}
+
+/// Objects the upload thread filled, with the fence behind them (a `GLsync`, which isn't
+/// `Send`, as an address).
+struct Uploaded {
+    id: u64,
+    names: [GLuint; 3],
+    fence: usize,
+}
+
+/// What to make of an upload when it completes.
+enum Waiting {
+    Texture { width: u32, height: u32, label: String, slot: Weak<RefCell<Option<Rc<Texture>>>> },
+    Mesh { geometry: Rc<Geometry>, label: String, slot: Weak<RefCell<Option<Rc<GpuMesh>>>> },
+}
+
+/// The upload thread and the uploads in flight; owned by the renderer.
+pub(crate) struct Uploader {
+    /// Job queue of the upload thread; `None` uploads on the render thread.
+    jobs: Option<Sender<Job>>,
+    uploaded: Option<Receiver<Uploaded>>,
+    thread: Option<JoinHandle<()>>,
+
+    waiting: HashMap<u64, Waiting>,
+    /// Uploads the upload thread finished, whose fences haven't signaled yet.
+    fenced: Vec<(Uploaded, GLsync)>,
+    next_id: u64,
+}
+
+impl Uploader {
+    /// Uploads on the render thread.
+    pub(crate) fn synchronous() -> Self {
+        Self { jobs: None, uploaded: None, thread: None, waiting: HashMap::new(), fenced: Vec::new(), next_id: 0 }
+    }
+
+    /// Starts an upload thread on `context`, which shares objects with the render thread's
+    /// context. Falls back to uploading on the render thread, with a warning, if the
+    /// context can't be made current there.
+    pub(crate) fn threaded(context: Context<NotCurrent>) -> Self {
+        let (jobs, job_queue) = mpsc::channel();
+        let (uploaded_sender, uploaded) = mpsc::channel();
+        let (started_sender, started) = mpsc::channel();
+        let thread = thread::Builder::new().name("gl upload".to_string()).spawn(move || {
+            // The GL functions were loaded for the render thread's context; contexts sharing
+            // objects use the same driver, so the pointers work here too
+            let context = match unsafe { context.make_current() } {
+                Ok(context) => context,
+                Err((_, error)) => {
+                    let _ = started_sender.send(Err(error.to_string()));
+                    return;
+                }
+            };
+            let _ = started_sender.send(Ok(()));
+            for job in job_queue {
+                let (id, names) = match job {
+                    Job::Texture { id, width, height, pixels } => (id, [upload_rgba8(width, height, &pixels), 0, 0]),
+                    Job::Mesh { id, vertices, indices, skin } => (id, upload_buffers(&vertices, &indices, &skin, gl::STATIC_DRAW)),
+                };
+                let fence = unsafe {
+                    let fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+                    // Submits the upload and the fence, which otherwise might never signal
+                    gl::Flush();
+                    fence
+                };
+                if uploaded_sender.send(Uploaded { id, names, fence: fence as usize }).is_err() {
+                    break;
+                }
+            }
+            drop(context);
+        });
+
+        let started = match thread {
+            Ok(thread) => match started.recv() {
+                Ok(Ok(())) => Ok(thread),
+                Ok(Err(error)) => Err(error),
+                Err(error) => Err(error.to_string()),
+            },
+            Err(error) => Err(error.to_string()),
+        };
+        match started {
+            Ok(thread) => Self {
+                jobs: Some(jobs),
+                uploaded: Some(uploaded),
+                thread: Some(thread),
+                waiting: HashMap::new(),
+                fenced: Vec::new(),
+                next_id: 0,
+            },
+            Err(error) => {
+                eprintln!("Warning: Could not start the upload thread ({error}); uploading on the render thread");
+                Self::synchronous()
+            }
+        }
+    }
+
+    /// Whether uploads happen on the upload thread.
+    pub(crate) fn is_threaded(&self) -> bool {
+        self.jobs.is_some()
+    }
+
+    /// Number of uploads sent to the upload thread and not yet completed.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Starts uploading tightly packed RGBA8 `pixels`, rows bottom to top, as a texture with
+    /// a full mip chain.
+    ///
+    /// # Panics
+    /// Panics if `pixels` is smaller than `width * height * 4` bytes.
+    pub(crate) fn upload_texture(&mut self, width: u32, height: u32, pixels: Vec<u8>, label: &str) -> PendingUpload<Rc<Texture>> {
+        let expected = width as usize * height as usize * 4;
+        assert!(pixels.len() >= expected, "Texture data too small: expected {} bytes, got {}", expected, pixels.len());
+        let mut pixels = pixels;
+        if self.is_threaded() {
+            let (pending, id) = self.wait_for(|slot| Waiting::Texture { width, height, label: label.to_string(), slot });
+            match self.send(Job::Texture { id, width, height, pixels }) {
+                Ok(()) => return pending,
+                Err(Job::Texture { pixels: returned, .. }) => pixels = returned,
+                Err(Job::Mesh { .. }) => unreachable!(),
+            }
+        }
+        PendingUpload::ready(Rc::new(Texture::from_rgba8(width, height, &pixels, label)))
+    }
+
+    /// Starts uploading the buffers of `geometry`.
+    pub(crate) fn upload_geometry(&mut self, geometry: &Rc<Geometry>, label: &str) -> PendingUpload<Rc<GpuMesh>> {
+        if self.is_threaded() {
+            let label = label.to_string();
+            let (pending, id) = self.wait_for(|slot| Waiting::Mesh { geometry: geometry.clone(), label, slot });
+            let [vertices, indices, skin] = geometry_bytes(geometry).map(<[u8]>::to_vec);
+            if self.send(Job::Mesh { id, vertices, indices, skin }).is_ok() {
+                return pending;
+            }
+        }
+        PendingUpload::ready(GpuMesh::shared(geometry, label))
+    }
+
+    /// A pending upload, and its id, completed as `waiting` says.
+    fn wait_for<T>(&mut self, waiting: impl FnOnce(Weak<RefCell<Option<T>>>) -> Waiting) -> (PendingUpload<T>, u64) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let pending = PendingUpload { slot: Rc::new(RefCell::new(None)) };
+        self.waiting.insert(id, waiting(Rc::downgrade(&pending.slot)));
+        (pending, id)
+    }
+
+    /// Queues `job` on the upload thread. If the thread has stopped, returns the job to do on
+    /// the render thread instead, and does all later uploads there.
+    fn send(&mut self, job: Job) -> Result<(), Job> {
+        let (Some(jobs), id) = (&self.jobs, job.id()) else {
+            return Err(job);
+        };
+        let Err(mpsc::SendError(job)) = jobs.send(job) else {
+            return Ok(());
+        };
+        eprintln!("Warning: The upload thread stopped; uploading on the render thread");
+        self.waiting.remove(&id);
+        (self.jobs, self.uploaded) = (None, None);
+        Err(job)
+    }
+
+    /// Completes the uploads whose fences have signaled. Requires the render thread's
+    /// context to be current; never blocks.
+    pub(crate) fn poll(&mut self) {
+        if let Some(uploaded) = &self.uploaded {
+            for upload in uploaded.try_iter() {
+                let fence = upload.fence as GLsync;
+                self.fenced.push((upload, fence));
+            }
+        }
+        for (upload, fence) in std::mem::take(&mut self.fenced) {
+            let status = unsafe { gl::ClientWaitSync(fence, 0, 0) };
+            if status == gl::TIMEOUT_EXPIRED {
+                self.fenced.push((upload, fence));
+                continue;
+            }
+            if status == gl::WAIT_FAILED {
+                eprintln!("Warning: Waiting for upload {} failed; using it anyway", upload.id);
+            }
+            unsafe { gl::DeleteSync(fence) };
+            self.complete(upload);
+        }
+    }
+
+    /// Hands the objects of a finished upload to its [`PendingUpload`], or deletes them if
+    /// that was dropped.
+    fn complete(&mut self, upload: Uploaded) {
+        let names = upload.names;
+        match self.waiting.remove(&upload.id) {
+            Some(Waiting::Texture { width, height, label, slot }) => match slot.upgrade() {
+                Some(slot) => *slot.borrow_mut() = Some(Rc::new(Texture::from_uploaded(names[0], width, height, &label))),
+                None => unsafe { gl::DeleteTextures(1, &names[0]) },
+            },
+            Some(Waiting::Mesh { geometry, label, slot }) => match slot.upgrade() {
+                Some(slot) => *slot.borrow_mut() = Some(GpuMesh::from_buffers(&geometry, names, &label).into_shared(&geometry)),
+                None => delete_buffers(names),
+            },
+            None => delete_buffers(names),
+        }
+    }
+}
+
+impl Drop for Uploader {
+    fn drop(&mut self) {
+        // Closing the job queue ends the upload thread after its current upload
+        self.jobs = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn delete_buffers(names: [GLuint; 3]) {
+    for name in names.into_iter().filter(|&name| name != 0) {
+        unsafe { gl::DeleteBuffers(1, &name) };
+    }
+}
@@ -0,0 +1,229 @@
+//! Frame-time spike detection.
+//!
+//! Hitches that happen once every few minutes are hard to catch in a profiler. The
+//! [`FrameWatchdog`] times every frame and, whenever one takes longer than a configurable
+//! threshold, writes a [`SpikeReport`] to the log: how long each system and render pass took,
+//! how many draw calls and GPU uploads were issued and, if a
+//! [`CountingAllocator`](crate::engine::stats::CountingAllocator) is installed, how many heap
+//! allocations were made.
+//!
+//! The renderer owns a watchdog (see
+//! [`Renderer::frame_watchdog_mut`](crate::engine::renderer::Renderer::frame_watchdog_mut)),
+//! disabled until a threshold is set:
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use rustge::engine::renderer::Renderer;
+//! # let mut renderer = Renderer::new("Example", 800, 600);
+//! // Anything slower than two frames at 60 Hz is logged
+//! renderer.frame_watchdog_mut().set_threshold(Some(Duration::from_micros(33_333)));
+//! ```
+
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::engine::frame_graph::PassTiming;
+use crate::engine::profiler;
+use crate::engine::stats::{
+    allocation_counts, frame_counters, gpu_memory_stats, reset_frame_counters, AllocationCounts, FrameCounters,
+};
+
+/// Breakdown of a frame that exceeded the watchdog threshold.
+#[derive(Clone, Debug)]
+pub struct SpikeReport {
+    /// Frame number, as counted by the renderer's clock.
+    pub frame: u64,
+
+    /// Total time the frame took, from its start until the buffers were swapped.
+    pub frame_time: Duration,
+
+    /// The threshold that was exceeded.
+    pub threshold: Duration,
+
+    /// CPU time of each timed system (update callback, scene update, buffer swap, ...), in
+    /// execution order.
+    pub systems: Vec<(String, Duration)>,
+
+    /// Render passes of the frame with their CPU timings.
+    pub passes: Vec<PassTiming>,
+
+    /// Draw calls and GPU uploads issued during the frame.
+    pub counters: FrameCounters,
+
+    /// Heap allocations made during the frame, if a counting allocator is installed.
+    pub allocations: Option<AllocationCounts>,
+
+    /// Tracked GPU memory in use at the end of the frame.
+    pub gpu_memory_bytes: usize,
+}
+
+impl SpikeReport {
+    /// Frame time not covered by any timed system or pass.
+    pub fn untracked_time(&self) -> Duration {
+        let systems: Duration = self.systems.iter().map(|(_, time)| *time).sum();
+        let passes: Duration = self.passes.iter().map(|pass| pass.cpu_time).sum();
+        self.frame_time.saturating_sub(systems + passes)
+    }
+}
+
+impl fmt::Display for SpikeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Frame spike: frame {} took {:.2} ms (threshold {:.2} ms)",
+            self.frame,
+            millis(self.frame_time),
+            millis(self.threshold)
+        )?;
+        for (name, time) in &self.systems {
+            writeln!(f, "  system {:<16} {:>8.2} ms", name, millis(*time))?;
+        }
+        for pass in &self.passes {
+            writeln!(f, "  pass   {:<16} {:>8.2} ms", pass.name, millis(pass.cpu_time))?;
+        }
+        writeln!(f, "  untracked               {:>8.2} ms", millis(self.untracked_time()))?;
+        writeln!(
+            f,
+            "  {} draw calls ({} vertices, {} triangles, {} program binds, {} material binds, {} state changes), {} GPU uploads ({} bytes), {} bytes GPU memory in use",
+            self.counters.draw_calls,
+            self.counters.vertices,
+            self.counters.triangles,
+            self.counters.program_binds,
+            self.counters.material_binds,
+            self.counters.state_changes,
+            self.counters.gpu_uploads,
+            self.counters.gpu_upload_bytes,
+            self.gpu_memory_bytes
+        )?;
+        match &self.allocations {
+            Some(allocations) => write!(
+                f,
+                "  {} heap allocations ({} bytes), {} frees",
+                allocations.allocations, allocations.bytes, allocations.deallocations
+            ),
+            None => write!(f, "  heap allocations not counted (no CountingAllocator installed)"),
+        }
+    }
+}
+
+/// Times frames and logs a [`SpikeReport`] for every frame slower than the threshold.
+#[derive(Debug, Default)]
+pub struct FrameWatchdog {
+    /// Frames slower than this are reported; `None` disables the watchdog.
+    threshold: Option<Duration>,
+
+    /// Start of the frame being timed.
+    frame_start: Option<Instant>,
+
+    /// Systems timed so far this frame, followed by entries left over from earlier frames
+    /// whose names are overwritten in place, so timing a frame allocates nothing.
+    systems: Vec<(String, Duration)>,
+
+    /// Number of `systems` entries recorded this frame.
+    recorded: usize,
+
+    /// Allocation totals at the start of the frame.
+    allocations_at_start: Option<AllocationCounts>,
+
+    /// Number of frames reported so far.
+    spike_count: u64,
+
+    /// The most recent report.
+    last_report: Option<SpikeReport>,
+}
+
+impl FrameWatchdog {
+    /// A disabled watchdog; see [`set_threshold`](Self::set_threshold).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the frame time above which frames are reported, or disables reporting with `None`.
+    pub fn set_threshold(&mut self, threshold: Option<Duration>) {
+        self.threshold = threshold;
+    }
+
+    /// The configured threshold, if reporting is enabled.
+    pub fn threshold(&self) -> Option<Duration> {
+        self.threshold
+    }
+
+    /// Number of spikes reported since the watchdog was created.
+    pub fn spike_count(&self) -> u64 {
+        self.spike_count
+    }
+
+    /// The most recent spike report, if any frame has exceeded the threshold.
+    pub fn last_report(&self) -> Option<&SpikeReport> {
+        self.last_report.as_ref()
+    }
+
+    /// Starts timing a frame and resets the per-frame counters.
+    pub fn begin_frame(&mut self) {
+        reset_frame_counters();
+        self.recorded = 0;
+        self.allocations_at_start = allocation_counts();
+        self.frame_start = Some(Instant::now());
+    }
+
+    /// Records that the system `name` took `time` this frame.
+    pub fn record(&mut self, name: &str, time: Duration) {
+        // Systems run in the same order every frame, so the entry usually has the name already
+        match self.systems.get_mut(self.recorded) {
+            Some((entry_name, entry_time)) => {
+                if entry_name != name {
+                    entry_name.clear();
+                    entry_name.push_str(name);
+                }
+                *entry_time = time;
+            }
+            None => self.systems.push((name.to_string(), time)),
+        }
+        self.recorded += 1;
+    }
+
+    /// Runs `f` and records its duration as the system `name`, also timed as a
+    /// [profiler scope](crate::engine::profiler::scope).
+    pub fn time<R>(&mut self, name: &str, f: impl FnOnce() -> R) -> R {
+        let scope = profiler::scope(name);
+        let result = f();
+        self.record(name, scope.finish());
+        result
+    }
+
+    /// Finishes the frame started by [`begin_frame`](Self::begin_frame). If it was slower
+    /// than the threshold, the breakdown is logged and returned.
+    ///
+    /// `passes` are the frame's render passes, usually
+    /// [`FrameGraph::recorded_passes`](crate::engine::frame_graph::FrameGraph::recorded_passes).
+    pub fn end_frame(&mut self, frame: u64, passes: &[PassTiming]) -> Option<&SpikeReport> {
+        let frame_time = self.frame_start.take()?.elapsed();
+        let threshold = self.threshold?;
+        if frame_time <= threshold {
+            return None;
+        }
+
+        let allocations = allocation_counts()
+            .zip(self.allocations_at_start)
+            .map(|(now, start)| now.since(&start));
+        let report = SpikeReport {
+            frame,
+            frame_time,
+            threshold,
+            systems: self.systems[..self.recorded].to_vec(),
+            passes: passes.to_vec(),
+            counters: frame_counters(),
+            allocations,
+            gpu_memory_bytes: gpu_memory_stats().total_bytes,
+        };
+        eprintln!("{report}");
+
+        self.spike_count += 1;
+        self.last_report = Some(report);
+        self.last_report.as_ref()
+    }
+}
+
+/// `duration` in (fractional) milliseconds.
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -1,14 +1,14 @@
-use crate::engine::renderer::Renderer;
-use crate::engine::camera::Camera;
-use crate::engine::object3d::Object3D;
-
-mod engine;
+use rustge::engine::renderer::Renderer;
+use rustge::engine::camera::{AspectMode, Camera};
+use rustge::engine::math::color::Color;
 
 fn main() {
     let mut renderer = Renderer::new("My Game", 800, 600);
-    renderer.set_clear_color(0.0, 0.0, 0.0, 1.0);
+    renderer.set_clear_color(Color::BLACK);
 
-    let mut camera = Camera::new((800 / 600) as f32);
+    // The renderer keeps the aspect ratio matching the window
+    let mut camera = Camera::new(1.0);
+    camera.aspect_mode = AspectMode::Window;
     camera.set_fov(90f32);
     camera.set_near_far(0.01, 1000.00);
     